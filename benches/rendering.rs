@@ -0,0 +1,338 @@
+//! Microbenchmarks of the core rendering primitives.
+//!
+//! Run with `cargo bench --bench rendering`. Besides criterion's own reports, a summary of the
+//! mean time of every benchmark is written to `target/criterion/summary.json`, see
+//! [`write_summary`]. Baseline numbers are in `docs/benchmarks.md`.
+
+use criterion::{BenchmarkId, Criterion, Throughput, black_box, criterion_group};
+use std::io;
+use std::path::Path;
+use teng::rendering::color::Color;
+use teng::rendering::pixel::Pixel;
+use teng::rendering::render::{HalfBlockDisplayRender, Render, SixelRender};
+use teng::rendering::renderer::{DisplayRenderer, Renderer};
+use teng::util::planarvec::{Bounds, PlanarVec};
+use teng::util::spatial::{Aabb, SpatialHashGrid};
+
+
+/// The screen size of the full-screen benchmarks, a large terminal.
+const WIDTH: usize = 300;
+const HEIGHT: usize = 80;
+
+/// A colorful, deterministic color for a pixel.
+fn color_at(x: usize, y: usize, frame: usize) -> [u8; 3] {
+    [(x * 3 + frame) as u8, (y * 7) as u8, (x ^ y) as u8]
+}
+
+/// Renders a full-screen half-block display into a renderer, without flushing.
+fn bench_hbd_render(c: &mut Criterion) {
+    let mut hbd = HalfBlockDisplayRender::new(WIDTH, 2 * HEIGHT);
+    for y in 0..2 * HEIGHT {
+        for x in 0..WIDTH {
+            hbd.set_color(x, y, Color::Rgb(color_at(x, y, 0)));
+        }
+    }
+    let mut renderer = DisplayRenderer::new_with_sink(WIDTH, HEIGHT, io::sink());
+    let mut group = c.benchmark_group("hbd_render");
+    group.throughput(Throughput::Elements((WIDTH * HEIGHT) as u64));
+    group.bench_function(format!("{WIDTH}x{HEIGHT}"), |b| {
+        b.iter(|| {
+            hbd.render(&mut renderer, 0, 0, 0);
+            renderer.reset_screen();
+        })
+    });
+    group.finish();
+}
+
+/// Fills every pixel of a full-screen half-block display, once with `set_color` per pixel and
+/// once with `par_fill_with`, which fills the rows on all cores.
+fn bench_hbd_fill(c: &mut Criterion) {
+    let mut hbd = HalfBlockDisplayRender::new(WIDTH, 2 * HEIGHT);
+    let mut group = c.benchmark_group("hbd_fill");
+    group.throughput(Throughput::Elements((WIDTH * 2 * HEIGHT) as u64));
+    let mut frame = 0;
+    group.bench_function("set_color", |b| {
+        b.iter(|| {
+            frame += 1;
+            for y in 0..2 * HEIGHT {
+                for x in 0..WIDTH {
+                    hbd.set_color(x, y, Color::Rgb(color_at(x, y, frame)));
+                }
+            }
+            black_box(&hbd);
+        })
+    });
+    #[cfg(feature = "parallel")]
+    group.bench_function("par_fill_with", |b| {
+        b.iter(|| {
+            frame += 1;
+            hbd.par_fill_with(|x, y| Color::Rgb(color_at(x, y, frame)));
+            black_box(&hbd);
+        })
+    });
+    group.finish();
+}
+
+/// Flushes a full frame where a percentage of the cells changed since the last flush.
+///
+/// 0% is the idle case that the diff renderer optimizes, 100% e.g. a scrolling background.
+fn bench_flush(c: &mut Criterion) {
+    let mut group = c.benchmark_group("flush");
+    for changed_percent in [0, 5, 100] {
+        let mut renderer = DisplayRenderer::new_with_sink(WIDTH, HEIGHT, io::sink());
+        let mut frame = 0;
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format!("{changed_percent}%")),
+            &changed_percent,
+            |b, &changed_percent| {
+                b.iter(|| {
+                    frame += 1;
+                    for y in 0..HEIGHT {
+                        for x in 0..WIDTH {
+                            let changed = (y * WIDTH + x) % 100 < changed_percent;
+                            let frame = if changed { frame } else { 0 };
+                            let pixel = Pixel::new('█').with_color(color_at(x, y, frame));
+                            renderer.render_pixel(x, y, pixel, 0);
+                        }
+                    }
+                    renderer.flush().unwrap();
+                    renderer.reset_screen();
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Flushes a mostly static frame: a status line and a small sprite moving over an otherwise
+/// empty screen, as in many games with a plain background.
+fn bench_flush_sparse(c: &mut Criterion) {
+    let mut renderer = DisplayRenderer::new_with_sink(WIDTH, HEIGHT, io::sink());
+    let mut frame = 0;
+    let mut group = c.benchmark_group("flush_sparse");
+    group.bench_function("hud_and_sprite", |b| {
+        b.iter(|| {
+            frame += 1;
+            "score: 1234  lives: 3  level: 7".render(&mut renderer, 0, 0, 0);
+            let (sprite_x, sprite_y) = (frame % (WIDTH - 6), HEIGHT / 2);
+            for y in 0..3 {
+                for x in 0..6 {
+                    let pixel = Pixel::new('#').with_color(color_at(x, y, 0));
+                    renderer.render_pixel(sprite_x + x, sprite_y + y, pixel, 0);
+                }
+            }
+            renderer.flush().unwrap();
+            renderer.reset_screen();
+        })
+    });
+    group.finish();
+}
+
+/// Renders single pixels through `&mut dyn Renderer`, as components do.
+fn bench_render_pixel(c: &mut Criterion) {
+    let mut renderer = DisplayRenderer::new_with_sink(WIDTH, HEIGHT, io::sink());
+    let mut group = c.benchmark_group("render_pixel");
+    group.throughput(Throughput::Elements((WIDTH * HEIGHT) as u64));
+    for layers in [1, 4] {
+        group.bench_with_input(
+            BenchmarkId::new("dyn", format!("{layers} layers")),
+            &layers,
+            |b, &layers| {
+                b.iter(|| {
+                    let renderer: &mut dyn Renderer = &mut renderer;
+                    for depth in 0..layers {
+                        for y in 0..HEIGHT {
+                            for x in 0..WIDTH {
+                                let pixel = Pixel::new('x').with_color(color_at(x, y, 0));
+                                renderer.render_pixel(x, y, black_box(pixel), depth);
+                            }
+                        }
+                    }
+                });
+                renderer.reset_screen();
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Grows a `PlanarVec` like a camera panning in one direction, and like a world growing in
+/// all directions.
+fn bench_planarvec_expand(c: &mut Criterion) {
+    let mut group = c.benchmark_group("planarvec_expand");
+    group.bench_function("pan_right_100x50", |b| {
+        b.iter(|| {
+            let mut vec = PlanarVec::new(bounds(0, 0, 99, 49), 0u8);
+            for step in 1..=100 {
+                vec.expand(bounds(step, 0, step + 99, 49), 0);
+            }
+            black_box(vec.bounds())
+        })
+    });
+    group.bench_function("grow_all_directions", |b| {
+        b.iter(|| {
+            let mut vec = PlanarVec::new(bounds(0, 0, 9, 9), 0u8);
+            for step in 1..=50 {
+                vec.expand(bounds(-step, -step, 9 + step, 9 + step), 0);
+            }
+            black_box(vec.bounds())
+        })
+    });
+    group.finish();
+}
+
+fn bounds(min_x: i64, min_y: i64, max_x: i64, max_y: i64) -> Bounds {
+    Bounds {
+        min_x,
+        min_y,
+        max_x,
+        max_y,
+    }
+}
+
+/// Inserts items into a `SpatialHashGrid`, then queries every item's neighborhood.
+fn bench_spatial_hash_grid(c: &mut Criterion) {
+    let mut group = c.benchmark_group("spatial_hash_grid");
+    for items in [10_000, 50_000] {
+        // a deterministic scatter over a 1000x1000 world
+        let positions = (0..items as i64)
+            .map(|i| ((i * 7919) % 1000, (i * 104_729) % 1000))
+            .collect::<Vec<_>>();
+        group.throughput(Throughput::Elements(items as u64));
+        group.bench_with_input(
+            BenchmarkId::new("insert_query", items),
+            &positions,
+            |b, positions| {
+                b.iter(|| {
+                    let mut grid = SpatialHashGrid::new(10);
+                    for (idx, &(x, y)) in positions.iter().enumerate() {
+                        grid.insert_with_aabb(idx, aabb(x, y, 1));
+                    }
+                    let mut neighbors = 0;
+                    for &(x, y) in positions {
+                        neighbors += grid.query_aabb(aabb(x, y, 5)).count();
+                    }
+                    black_box((neighbors, grid.query_point(0, 0).count()))
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn aabb(x: i64, y: i64, radius: i64) -> Aabb {
+    Aabb::new(x - radius, y - radius, x + radius, y + radius)
+}
+
+/// Renders a screen full of text, plain and through the color adapters.
+fn bench_string_render(c: &mut Criterion) {
+    let line = "The quick brown fox jumps over the lazy dog. ".repeat(WIDTH / 45 + 1);
+    let line = &line[..WIDTH];
+    let mut renderer = DisplayRenderer::new_with_sink(WIDTH, HEIGHT, io::sink());
+    let mut group = c.benchmark_group("string_render");
+    group.throughput(Throughput::Elements((WIDTH * HEIGHT) as u64));
+    group.bench_function("plain", |b| {
+        b.iter(|| {
+            for y in 0..HEIGHT {
+                line.render(&mut renderer, 0, y, 0);
+            }
+            renderer.reset_screen();
+        })
+    });
+    group.bench_function("with_color_and_bg", |b| {
+        b.iter(|| {
+            for y in 0..HEIGHT {
+                line.with_color([255, 200, 0])
+                    .with_bg_color([0, 0, 80])
+                    .render(&mut renderer, 0, y, 0);
+            }
+            renderer.reset_screen();
+        })
+    });
+    group.finish();
+}
+
+/// Encodes a colorful 400x200 image as sixels, which quantizes every pixel to the sixel palette.
+fn bench_sixel_encode(c: &mut Criterion) {
+    let (width, height) = (400, 200);
+    let mut image = SixelRender::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            image.set_pixel(x, y, color_at(x, y, 0));
+        }
+    }
+    let mut group = c.benchmark_group("sixel_encode");
+    group.throughput(Throughput::Elements((width * height) as u64));
+    group.bench_function("400x200", |b| {
+        b.iter(|| black_box(image.to_sixel(width, height)))
+    });
+    group.finish();
+}
+
+/// Writes the mean time of every benchmark in `criterion_dir` to `summary.json` in it, as an
+/// array of `{"name": ..., "mean_ns": ..., "std_dev_ns": ...}` sorted by name.
+///
+/// The names are the benchmark ids, e.g. `flush/5%`, so the summary can be joined with other
+/// benchmark results.
+fn write_summary(criterion_dir: &Path) -> io::Result<()> {
+    let mut entries = vec![];
+    collect_estimates(criterion_dir, criterion_dir, &mut entries)?;
+    entries.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+    let summary = serde_json::to_string_pretty(&entries).map_err(io::Error::other)?;
+    std::fs::write(criterion_dir.join("summary.json"), summary)
+}
+
+fn collect_estimates(
+    root: &Path,
+    dir: &Path,
+    entries: &mut Vec<serde_json::Value>,
+) -> io::Result<()> {
+    let estimates = dir.join("new").join("estimates.json");
+    if estimates.is_file() {
+        let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&estimates)?)
+            .map_err(io::Error::other)?;
+        let name = dir
+            .strip_prefix(root)
+            .unwrap()
+            .to_string_lossy()
+            .replace('\\', "/");
+        entries.push(serde_json::json!({
+            "name": name,
+            "mean_ns": json["mean"]["point_estimate"],
+            "std_dev_ns": json["std_dev"]["point_estimate"],
+        }));
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() && path.file_name().is_some_and(|name| name != "report") {
+            collect_estimates(root, &path, entries)?;
+        }
+    }
+    Ok(())
+}
+
+criterion_group!(
+    benches,
+    bench_hbd_render,
+    bench_hbd_fill,
+    bench_flush,
+    bench_flush_sparse,
+    bench_render_pixel,
+    bench_planarvec_expand,
+    bench_spatial_hash_grid,
+    bench_string_render,
+    bench_sixel_encode
+);
+
+fn main() {
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+    let target_dir = std::env::var("CARGO_TARGET_DIR").unwrap_or_else(|_| "target".to_string());
+    let criterion_dir = Path::new(&target_dir).join("criterion");
+    if criterion_dir.is_dir()
+        && let Err(e) = write_summary(&criterion_dir)
+    {
+        eprintln!("Failed to write the benchmark summary: {e}");
+    }
+}
@@ -0,0 +1,84 @@
+//! A small keyboard piano: the keys `a` to `k` play the notes of a C major scale.
+//!
+//! Run with `cargo run --example audio --features audio`. Without the feature or an audio device,
+//! the game runs silently and shows a warning. `+` and `-` change the master volume.
+
+use std::io;
+use teng::components::Component;
+use teng::components::audio::{AudioComponent, AudioHandle};
+use teng::rendering::render::Render;
+use teng::rendering::renderer::Renderer;
+use teng::{
+    Game, SharedState, UpdateInfo, install_panic_handler, terminal_cleanup, terminal_setup,
+};
+
+fn main() -> io::Result<()> {
+    terminal_setup()?;
+    install_panic_handler();
+
+    let mut game = Game::new_with_custom_buf_writer();
+    game.install_recommended_components();
+    // the audio component inserts the handle the piano uses, so it is set up first
+    game.add_component(Box::new(AudioComponent::new()));
+    game.add_component(Box::new(PianoComponent { last_note: None }));
+    game.run()?;
+
+    terminal_cleanup()?;
+
+    Ok(())
+}
+
+/// The keys and frequencies of the C major scale from C4 to C5.
+const NOTES: [(char, &str, f32); 8] = [
+    ('a', "C4", 261.63),
+    ('s', "D4", 293.66),
+    ('d', "E4", 329.63),
+    ('f', "F4", 349.23),
+    ('g', "G4", 392.00),
+    ('h', "A4", 440.00),
+    ('j', "B4", 493.88),
+    ('k', "C5", 523.25),
+];
+
+struct PianoComponent {
+    last_note: Option<&'static str>,
+}
+
+impl Component for PianoComponent {
+    fn update(&mut self, _update_info: UpdateInfo, shared_state: &mut SharedState) {
+        for (key, name, freq_hz) in NOTES {
+            if shared_state.pressed_keys.did_press_char(key) {
+                shared_state
+                    .ext_expect::<AudioHandle>("PianoComponent")
+                    .play_tone(freq_hz, 300);
+                self.last_note = Some(name);
+            }
+        }
+
+        let volume_change = if shared_state.pressed_keys.did_press_char('+') {
+            0.1
+        } else if shared_state.pressed_keys.did_press_char('-') {
+            -0.1
+        } else {
+            return;
+        };
+        let audio = shared_state.ext_mut::<AudioHandle>().unwrap();
+        audio.set_master_volume(audio.master_volume() + volume_change);
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, shared_state: &SharedState, depth_base: i32) {
+        let audio = shared_state.ext_expect::<AudioHandle>("PianoComponent");
+        let status = match audio.disabled_reason() {
+            None => format!("Volume: {:.0}%", audio.master_volume() * 100.0),
+            Some(reason) => format!("No sound: {reason}"),
+        };
+        status.render(renderer, 1, 1, depth_base);
+        let keys = NOTES
+            .map(|(key, name, _)| format!("{key}={name}"))
+            .join("  ");
+        keys.render(renderer, 1, 3, depth_base);
+        if let Some(note) = self.last_note {
+            format!("Last note: {note}").render(renderer, 1, 5, depth_base);
+        }
+    }
+}
@@ -388,14 +388,12 @@ impl Component for CircleRasterizerComponent {
         }
 
         if let Some(current_ball) = &mut self.current_ball {
-            shared_state.debug_info.custom.insert(
-                "Circle Radius".to_string(),
-                format!("{:.2}", current_ball.radius),
-            );
-            shared_state.debug_info.custom.insert(
-                "Circle Center".to_string(),
-                format!("({}, {})", current_ball.x, current_ball.y),
-            );
+            shared_state
+                .debug_info
+                .set_f64("circle.radius", current_ball.radius);
+            shared_state
+                .debug_info
+                .set("circle.center", (current_ball.x, current_ball.y));
         }
 
         // simple physics
@@ -456,33 +456,29 @@ impl Component for CircleRasterizerComponent {
         }
 
         if let Some(current_ball) = &mut self.current_ball {
-            shared_state.debug_info.custom.insert(
-                "Circle Radius".to_string(),
-                format!("{:.2}", current_ball.radius),
+            let debug_info = &mut shared_state.debug_info;
+            debug_info.set_f64("circle.radius", current_ball.radius);
+            debug_info.set(
+                "circle.center.local",
+                (current_ball.local_x(), current_ball.local_y()),
             );
-            shared_state.debug_info.custom.insert(
-                "Circle Center (local)".to_string(),
-                format!("({}, {})", current_ball.local_x(), current_ball.local_y()),
-            );
-            shared_state.debug_info.custom.insert(
-                "Circle Center (world)".to_string(),
-                format!("({}, {})", current_ball.world_x(), current_ball.world_y()),
+            debug_info.set(
+                "circle.center.world",
+                (current_ball.world_x(), current_ball.world_y()),
             );
         }
 
         if let Some(first_ball) = self.free_balls.first() {
-            shared_state.debug_info.custom.insert(
-                "First Ball Center (local)".to_string(),
-                format!("({:.2}, {:.2})", first_ball.local_x(), first_ball.local_y()),
-            );
-            shared_state.debug_info.custom.insert(
-                "First Ball Center (world)".to_string(),
-                format!("({:.2}, {:.2})", first_ball.world_x(), first_ball.world_y()),
+            let debug_info = &mut shared_state.debug_info;
+            debug_info.set(
+                "first_ball.center.local",
+                (first_ball.local_x(), first_ball.local_y()),
             );
-            shared_state.debug_info.custom.insert(
-                "First Ball velocity".to_string(),
-                format!("({:.2}, {:.2})", first_ball.x_vel, first_ball.y_vel),
+            debug_info.set(
+                "first_ball.center.world",
+                (first_ball.world_x(), first_ball.world_y()),
             );
+            debug_info.set("first_ball.velocity", (first_ball.x_vel, first_ball.y_vel));
         }
 
         update_balls(
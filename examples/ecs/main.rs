@@ -3,8 +3,9 @@
 //! Arbitrary structs can be registered and used as components, and systems are just
 //! types that implement `teng::Component`.
 
-use anymap::AnyMap;
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind};
+use rand::Rng;
+use rand::rngs::StdRng;
 use std::any::{Any, TypeId};
 use std::collections::HashMap;
 use std::io;
@@ -12,6 +13,8 @@ use std::io::stdout;
 use teng::components::Component as TengComponent;
 use teng::rendering::pixel::Pixel;
 use teng::rendering::renderer::Renderer;
+use teng::seeds::{seeded_rng_for, set_seed};
+use teng::util::anymap::AnyMap;
 use teng::{BreakingAction, Game, SetupInfo, SharedState};
 
 /// An ECS-component that holds the position of an entity.
@@ -71,10 +74,11 @@ impl TengComponent<Ecs> for PhysicsSystem {
 fn main() -> io::Result<()> {
     teng::terminal_setup()?;
     teng::install_panic_handler();
+    set_seed(42);
 
     let mut game = Game::new(stdout());
     game.install_recommended_components();
-    game.add_component(Box::new(EcsComponent::default()));
+    game.add_component(Box::new(EcsComponent::new()));
     game.add_component(Box::new(DrawSystem));
     game.add_component(Box::new(PhysicsSystem));
     game.run()?;
@@ -166,10 +170,20 @@ impl Ecs {
 }
 
 /// A wrapper component that sets up the ECS and creates new entities.
-#[derive(Default)]
 struct EcsComponent {
     width: usize,
     height: usize,
+    rng: StdRng,
+}
+
+impl EcsComponent {
+    fn new() -> Self {
+        Self {
+            width: 0,
+            height: 0,
+            rng: seeded_rng_for("ecs positions"),
+        }
+    }
 }
 
 impl TengComponent<Ecs> for EcsComponent {
@@ -196,8 +210,8 @@ impl TengComponent<Ecs> for EcsComponent {
         {
             // Create a new entity with a random position and the pressed key as display character.
             let entity = ecs.create_entity();
-            let x = rand::random::<usize>() % self.width;
-            let y = rand::random::<usize>() % self.height;
+            let x = self.rng.gen_range(0..self.width);
+            let y = self.rng.gen_range(0..self.height);
             ecs.add_component(entity, Position { x, y });
             ecs.add_component(entity, Draw { ch });
         }
@@ -1,18 +1,24 @@
 use std::io;
 use crokey::key;
-use crossterm::event::{Event, MouseEventKind};
+use crossterm::event::{Event, MouseButton};
 use teng::components::Component;
 use teng::rendering::pixel::Pixel;
-use teng::rendering::render::{HalfBlockDisplayRender, Render};
+use teng::rendering::render::{HalfBlockDisplayRender, Render, SixelRender};
 use teng::rendering::renderer::Renderer;
 use teng::{Game, SharedState, install_panic_handler, terminal_cleanup, terminal_setup, UpdateInfo, SetupInfo, BreakingAction};
 use teng::components::ui::{UiComponent, UiElement};
+use teng::components::ui::layout::HStack;
+use teng::rendering::ansi::AnsiArt;
 use teng::rendering::color::Color;
+use teng::rendering::sixel;
+use teng::components::debuginfo::DebugMessage;
+use teng::util::camera::{Camera2D, CellHalf};
 use teng::util::planarvec::{Bounds, PlanarVec};
 
-// Renders in a half block display.
+// Renders in a half block display, or as a sixel image if the sixel preview is on.
 struct PreviewWindow {
     hbd: HalfBlockDisplayRender,
+    sixel: SixelRender,
     size: (usize, usize),
 }
 
@@ -20,6 +26,7 @@ impl PreviewWindow {
     fn new() -> Self {
         Self {
             hbd: HalfBlockDisplayRender::new(1,1),
+            sixel: SixelRender::new(1, 1),
             size: (1, 1),
         }
     }
@@ -53,6 +60,10 @@ impl UiElement<State> for PreviewWindow {
                 self.hbd.set_color(x, y, color);
             }
         }
+
+        if shared_state.custom.sixel_preview {
+            self.sixel.copy_from_hbd(&self.hbd, [0, 0, 0]);
+        }
     }
 
     fn get_size(&self) -> (usize, usize) {
@@ -60,7 +71,11 @@ impl UiElement<State> for PreviewWindow {
     }
 
     fn render(&self, renderer: &mut dyn Renderer, shared_state: &SharedState<State>, depth_base: i32) {
-        self.hbd.render(renderer, 0, 0, depth_base);
+        if shared_state.custom.sixel_preview {
+            self.sixel.render(renderer, 0, 0, depth_base);
+        } else {
+            self.hbd.render(renderer, 0, 0, depth_base);
+        }
     }
 }
 
@@ -93,6 +108,7 @@ impl UiElement<State> for DrawWindow {
         let depth_checkerboard = depth_base;
         let depth_mouse = depth_base + 1;
         let depth_drawing = depth_base + 2;
+        let depth_selection = depth_base + 3;
         let (width, height) = shared_state.custom.screen_size;
 
         let mouse_image_pos = shared_state.custom.last_mouse_pos;
@@ -117,6 +133,11 @@ impl UiElement<State> for DrawWindow {
                 let mut pixel = Pixel::new('█');
                 pixel.color = color;
                 renderer.render_pixel(x, y, pixel, depth_drawing);
+
+                if shared_state.custom.is_on_selection_border(image_pos) {
+                    let pixel = Pixel::new('░').with_color([255, 200, 0]);
+                    renderer.render_pixel(x, y, pixel, depth_selection);
+                }
             }
         }
     }
@@ -165,6 +186,10 @@ struct State {
     history: EditHistory,
     // used to draw a grey hover
     last_mouse_pos: (i64, i64),
+    // The corners of the selected rectangle, in image coordinates. Dragging with the middle mouse button selects.
+    selection: Option<((i64, i64), (i64, i64))>,
+    // Whether the preview is drawn as a sixel image, toggled with 'p'.
+    sixel_preview: bool,
 }
 
 impl Default for State {
@@ -178,6 +203,8 @@ impl Default for State {
             screen_size: (1, 1),
             history: EditHistory::default(),
             last_mouse_pos: (0, 0),
+            selection: None,
+            sixel_preview: false,
         }
     }
 }
@@ -194,26 +221,30 @@ impl State {
 
     const CHECKERBOARD_SCALE: i64 = 3;
 
-    fn screen_to_image(&self, screen_x: usize, screen_y: usize) -> (i64, i64) {
-        let (camera_x, camera_y) = self.camera_center;
+    /// The camera of the draw window, in which one image pixel is `editor_scale` half pixels.
+    fn camera(&self) -> Camera2D {
         let (screen_width, screen_height) = self.screen_size;
-        let scale = self.editor_scale;
-        let screen_x_offset = screen_x as i64 - screen_width as i64 / 2;
-        let screen_y_offset = screen_y as i64 - screen_height as i64 / 2;
-        let image_x = camera_x + div_floor(screen_x_offset, scale);
-        let image_y = camera_y - div_floor(screen_y_offset * 2, scale); // - because the y axis is flipped, *2 because we want square displays
-        (image_x, image_y)
+        let mut camera = Camera2D::for_half_block(screen_width as usize, screen_height as usize);
+        camera.scale = 1.0 / self.editor_scale as f64;
+        camera.center_on(self.camera_center.0 as f64, self.camera_center.1 as f64);
+        camera
+    }
+
+    /// The camera of the preview window, in which one image pixel is one half pixel.
+    fn raw_camera(&self) -> Camera2D {
+        let (screen_width, screen_height) = self.screen_size;
+        let mut camera = Camera2D::for_half_block(screen_width as usize, screen_height as usize);
+        camera.center_on(self.camera_center.0 as f64, self.camera_center.1 as f64);
+        camera
+    }
+
+    fn screen_to_image(&self, screen_x: usize, screen_y: usize) -> (i64, i64) {
+        self.camera().screen_to_tile(screen_x, screen_y, CellHalf::Top)
     }
 
     /// Expects square pixel coordinates and ignores scale.
     fn screen_to_image_raw(&self, screen_x: usize, screen_y: usize) -> (i64, i64) {
-        let (camera_x, camera_y) = self.camera_center;
-        let (screen_width, screen_height) = self.screen_size;
-        let screen_x_offset = screen_x as i64 - screen_width as i64 / 2;
-        let screen_y_offset = screen_y as i64 - screen_height as i64 / 2 * 2; // * 2 because we're in pixel coordinates
-        let image_x = camera_x + screen_x_offset;
-        let image_y = camera_y - screen_y_offset;
-        (image_x, image_y)
+        self.raw_camera().pixel_to_tile(screen_x as i64, screen_y as i64)
     }
 
     fn screen_to_checkerboard(&self, screen_x: usize, screen_y: usize) -> Color {
@@ -243,15 +274,7 @@ impl State {
     }
 
     fn camera_bounds(&self) -> Bounds {
-        // From the bottom left corner to the top right corner.
-        let (min_x, min_y) = self.screen_to_image_raw(0, 2 * self.screen_size.1 as usize);
-        let (max_x, max_y) = self.screen_to_image_raw(self.screen_size.0 as usize, 0);
-        Bounds {
-            min_x,
-            min_y,
-            max_x,
-            max_y,
-        }
+        self.raw_camera().visible_tile_bounds()
     }
 
     fn move_camera(&mut self, dx: i64, dy: i64) {
@@ -291,41 +314,102 @@ impl State {
             self.image[(x, y)] = old_color;
         }
     }
+
+    fn selection_bounds(&self) -> Option<Bounds> {
+        let ((x0, y0), (x1, y1)) = self.selection?;
+        Some(Bounds {
+            min_x: x0.min(x1),
+            max_x: x0.max(x1),
+            min_y: y0.min(y1),
+            max_y: y0.max(y1),
+        })
+    }
+
+    fn is_on_selection_border(&self, (x, y): (i64, i64)) -> bool {
+        let Some(b) = self.selection_bounds() else {
+            return false;
+        };
+        b.contains(x, y) && (x == b.min_x || x == b.max_x || y == b.min_y || y == b.max_y)
+    }
+
+    /// Returns the selected region as ANSI art text. The top row of the text is the top row of the selection.
+    fn copy_selection(&self) -> Option<String> {
+        let b = self.selection_bounds()?;
+        Some(region_to_art(&self.image, b).to_ansi_string())
+    }
+
+    /// Pastes ANSI art text with its top left corner at `(x, y)`. Transparent pixels keep the image.
+    fn paste(&mut self, x: i64, y: i64, text: &str) {
+        let art = AnsiArt::parse(text);
+        if art.width() == 0 {
+            return;
+        }
+        let bounds = Bounds {
+            min_x: x,
+            max_x: x + art.width() as i64 - 1,
+            min_y: y - art.height() as i64 + 1,
+            max_y: y,
+        };
+        self.image.expand(bounds, self.default_color);
+        for art_y in 0..art.height() {
+            for art_x in 0..art.width() {
+                let color = art.get(art_x, art_y);
+                if color.is_solid() {
+                    self.draw_pixel(x + art_x as i64, y - art_y as i64, color);
+                }
+            }
+        }
+    }
+}
+
+/// Converts the region of the image to ANSI art. The image's y goes up, the art's goes down.
+fn region_to_art(image: &PlanarVec<Color>, b: Bounds) -> AnsiArt {
+    let mut art = AnsiArt::new((b.max_x - b.min_x + 1) as usize, (b.max_y - b.min_y + 1) as usize);
+    for art_y in 0..art.height() {
+        for art_x in 0..art.width() {
+            let pos = (b.min_x + art_x as i64, b.max_y - art_y as i64);
+            if let Some(&color) = image.get(pos.0, pos.1) {
+                art.set(art_x, art_y, color);
+            }
+        }
+    }
+    art
 }
 
 struct DrawComponent {
     combiner: crokey::Combiner,
+    // whether the terminal reported its cell size, which sixel images need
+    sixel_supported: bool,
 }
 
 impl DrawComponent {
-    fn new() -> Self {
+    fn new(sixel_supported: bool) -> Self {
         Self {
             combiner: crokey::Combiner::default(),
+            sixel_supported,
         }
     }
 }
 
 impl Component<State> for DrawComponent {
     fn setup(&mut self, setup_info: &SetupInfo, shared_state: &mut SharedState<State>) {
-        shared_state.ui.add_window("draw_window", 0, 0, Box::new(DrawWindow::new()));
-        shared_state.ui.add_window("preview_window", 0, 0, Box::new(PreviewWindow::new()));
+        // both windows are as large as the screen size of the state, i.e. half the terminal
+        let windows = HStack::new()
+            .with(Box::new(DrawWindow::new()))
+            .with(Box::new(PreviewWindow::new()));
+        shared_state.ui.add_window("windows", 0, 0, Box::new(windows));
 
         self.on_resize(setup_info.display_info.width(), setup_info.display_info.height(), shared_state);
     }
 
     fn on_resize(&mut self, width: usize, height: usize, shared_state: &mut SharedState<State>) {
         shared_state.custom.resize(width / 2, height);
-        shared_state.ui.set_anchor("preview_window", width / 2, 0);
     }
 
     fn on_event(&mut self, event: Event, shared_state: &mut SharedState<State>) -> Option<BreakingAction> {
-        if let Event::Mouse(me) = event {
-            if let MouseEventKind::ScrollUp = me.kind {
-                shared_state.custom.adjust_scale(2);
-            }
-            if let MouseEventKind::ScrollDown = me.kind {
-                shared_state.custom.adjust_scale(-2);
-            }
+        if let Event::Paste(text) = &event {
+            let (x, y) = shared_state.custom.last_mouse_pos;
+            shared_state.custom.paste(x, y, text);
         }
 
         if let Event::Key(ke) = event {
@@ -347,6 +431,23 @@ impl Component<State> for DrawComponent {
         if shared_state.pressed_keys.did_press_char_ignore_case('c') {
             shared_state.custom.image.clear(shared_state.custom.default_color);
         }
+        if shared_state.pressed_keys.did_press_char_ignore_case('y')
+            && let Some(text) = shared_state.custom.copy_selection()
+        {
+            shared_state.clipboard.copy(&text);
+        }
+        if shared_state.pressed_keys.did_press_char_ignore_case('p') {
+            let state = &mut shared_state.custom;
+            state.sixel_preview = !state.sixel_preview;
+            let message = if !state.sixel_preview {
+                DebugMessage::new_3s("Sixel preview off")
+            } else if self.sixel_supported {
+                DebugMessage::new_3s("Sixel preview on")
+            } else {
+                DebugMessage::warn_5s("Sixel preview on, but the terminal does not report its cell size")
+            };
+            shared_state.debug_messages.push(message);
+        }
         if shared_state.pressed_keys.did_press_char_ignore_case('w') {
             shared_state.custom.move_camera(0, 1);
         }
@@ -361,17 +462,37 @@ impl Component<State> for DrawComponent {
         }
 
         shared_state.custom.set_mouse_pos(shared_state.mouse_info.last_mouse_pos);
-
-        if shared_state.mouse_info.left_mouse_down {
-            let (x, y) = shared_state.mouse_info.last_mouse_pos;
-            let (image_x, image_y) = shared_state.custom.screen_to_image(x, y);
-            shared_state.custom.draw_pixel(image_x, image_y, Color::Rgb([255, 255, 255]));
+        let scroll = shared_state.mouse_info.scroll_delta_y;
+        if scroll != 0 {
+            shared_state.custom.adjust_scale(2 * scroll as i64);
         }
-        if shared_state.mouse_info.right_mouse_down {
-            let (x, y) = shared_state.mouse_info.last_mouse_pos;
-            let (image_x, image_y) = shared_state.custom.screen_to_image(x, y);
-            shared_state.custom.draw_pixel(image_x, image_y, shared_state.custom.default_color);
+
+        // a middle click clears the selection, a middle drag selects a rectangle
+        let gestures = shared_state.mouse_gestures;
+        if let Some(drag) = gestures.drag_in_progress.or(gestures.drag_finished)
+            && drag.button == MouseButton::Middle
+        {
+            let start = shared_state.custom.screen_to_image(drag.start.0, drag.start.1);
+            let current = shared_state.custom.screen_to_image(drag.current.0, drag.current.1);
+            shared_state.custom.selection = Some((start, current));
+        } else if shared_state.mouse_released.middle {
+            shared_state.custom.selection = None;
         }
+
+        // draw along the mouse's path in image coordinates, which have twice the resolution of the
+        // screen vertically
+        let camera = shared_state.custom.camera();
+        let state = &mut shared_state.custom;
+        shared_state.mouse_events.for_each_linerp_mapped(
+            |(x, y)| camera.screen_to_tile(x, y, CellHalf::Top),
+            |(image_x, image_y), mi| {
+                if mi.left_mouse_down {
+                    state.draw_pixel(image_x, image_y, Color::Rgb([255, 255, 255]));
+                } else if mi.right_mouse_down {
+                    state.draw_pixel(image_x, image_y, state.default_color);
+                }
+            },
+        );
     }
 
     fn render(&self, renderer: &mut dyn Renderer, shared_state: &SharedState<State>, depth_base: i32) {
@@ -397,10 +518,13 @@ fn main() -> io::Result<()> {
     install_panic_handler();
 
     let mut game = Game::new_with_custom_buf_writer();
+    // the preview can be drawn as a sixel image, which needs the size of a cell in pixels
+    let sixel_cell_size = sixel::cell_size().unwrap_or(None);
+    game.set_sixel_cell_size(sixel_cell_size);
     // If you don't install the recommended components, you will need to have your own
     // component that exits the process, since Ctrl-C does not work in raw mode.
     game.install_recommended_components();
-    game.add_component(Box::new(DrawComponent::new()));
+    game.add_component(Box::new(DrawComponent::new(sixel_cell_size.is_some())));
     game.add_component(Box::new(UiComponent::new()));
     game.run()?;
 
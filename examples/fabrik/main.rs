@@ -1,17 +1,24 @@
 //! FABRIK (Forward And Backward Reaching Inverse Kinematics) example.
+//!
+//! Press 'v' to switch to rope mode, which builds the chain out of Verlet particles instead.
+//! In rope mode, drag with the middle mouse button to build a rope hanging from its start and
+//! hold the left mouse button to drag the rope's end around.
 
 use crossterm::event::KeyCode;
 use std::io;
 use std::io::stdout;
 use std::time::Instant;
 use teng::components::Component;
+use teng::components::coordinates::CoordinateDebugComponent;
 use teng::rendering::color::Color;
 use teng::rendering::pixel::Pixel;
 use teng::rendering::render::{HalfBlockDisplayRender, Render};
 use teng::rendering::renderer::Renderer;
+use teng::util::fixedupdate::FixedUpdateRunner;
 use teng::util::for_coord_in_line;
 use teng::util::planarvec::Bounds;
 use teng::util::planarvec2_experimental::ExponentialGrowingBounds;
+use teng::util::verlet::ParticleSystem;
 use teng::{
     Game, SetupInfo, SharedState, UpdateInfo, install_panic_handler, terminal_cleanup,
     terminal_setup,
@@ -24,6 +31,7 @@ fn main() -> io::Result<()> {
     let mut game = Game::new_with_custom_buf_writer();
     game.install_recommended_components();
     game.add_component(Box::new(FabrikComponent::new()));
+    game.add_component(Box::new(CoordinateDebugComponent::new()));
     game.run()?;
 
     terminal_cleanup()?;
@@ -48,7 +56,16 @@ struct Segment {
     length: f64,
 }
 
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Mode {
+    Fabrik,
+    Rope,
+}
+
 pub struct FabrikComponent {
+    mode: Mode,
+    rope: ParticleSystem,
+    rope_fixed_update: FixedUpdateRunner,
     base_anchor: Option<Point<f64>>,
     target: Option<Point<f64>>,
     segments: Vec<Segment>,
@@ -60,6 +77,9 @@ pub struct FabrikComponent {
 impl FabrikComponent {
     pub fn new() -> Self {
         Self {
+            mode: Mode::Fabrik,
+            rope: ParticleSystem::new(),
+            rope_fixed_update: FixedUpdateRunner::new_from_rate_per_second(120.0),
             base_anchor: None,
             target: None,
             segments: vec![],
@@ -133,6 +153,79 @@ impl FabrikComponent {
         self.last_point = Some(last_point);
     }
 
+    fn update_rope(&mut self, update_info: UpdateInfo, shared_state: &mut SharedState<()>) {
+        let (x, y) = shared_state.mouse_info.last_mouse_pos;
+        let mouse_point = (x as f64, y as f64 * 2.0);
+
+        shared_state.mouse_events.for_each_linerp_only_fresh(|mi| {
+            if mi.middle_mouse_down {
+                let point = (mi.last_mouse_pos.0 as f64, mi.last_mouse_pos.1 as f64 * 2.0);
+                // the first interpolated point of a frame is the last one of the previous frame
+                if let Some(last) = self.rope.particles().last() {
+                    let (dx, dy) = (last.pos.0 - point.0, last.pos.1 - point.1);
+                    if dx * dx + dy * dy < 1.0 {
+                        return;
+                    }
+                }
+                let idx = self.rope.push_to_chain(point, 1.0);
+                if idx == 0 {
+                    self.rope.pin(0);
+                }
+            }
+        });
+
+        // drag the end of the rope
+        let last = self.rope.particles().len().saturating_sub(1);
+        if last > 0 {
+            if shared_state.mouse_info.left_mouse_down {
+                self.rope.pin_at(last, mouse_point);
+            } else if shared_state.mouse_released.left {
+                self.rope.unpin(last);
+            }
+        }
+
+        let (width, height) = (
+            self.half_block_display_render.width() as i64,
+            self.half_block_display_render.height() as i64,
+        );
+        self.rope_fixed_update.fuel(update_info.dt);
+        while self.rope_fixed_update.has_gas() {
+            self.rope_fixed_update.consume();
+            // keep the rope on screen
+            self.rope
+                .step_with_collision(self.rope_fixed_update.fixed_dt(), |x, y| {
+                    x < 0 || y < 0 || x >= width || y >= height
+                });
+        }
+
+        self.half_block_display_render.clear();
+        for (start, end) in self.rope.segments() {
+            let start = (start.0.floor() as i64, start.1.floor() as i64);
+            let end = (end.0.floor() as i64, end.1.floor() as i64);
+            for_coord_in_line(false, start, end, |x, y| {
+                if x < 0 || y < 0 {
+                    return;
+                }
+                self.half_block_display_render.set_color(
+                    x as usize,
+                    y as usize,
+                    Color::Rgb([200, 150, 100]),
+                );
+            });
+        }
+        for particle in self.rope.particles().iter().filter(|p| p.pinned) {
+            let (x, y) = (particle.pos.0.floor(), particle.pos.1.floor());
+            if x < 0.0 || y < 0.0 {
+                continue;
+            }
+            self.half_block_display_render.set_color(
+                x as usize,
+                y as usize,
+                Color::Rgb([0, 0, 255]),
+            );
+        }
+    }
+
     fn render_to_half_block_display(&mut self, mouse_point: Point<f64>) {
         self.half_block_display_render.clear();
 
@@ -231,6 +324,20 @@ impl Component for FabrikComponent {
     }
 
     fn update(&mut self, update_info: UpdateInfo, shared_state: &mut SharedState<()>) {
+        if shared_state.pressed_keys.did_press_char_ignore_case('v') {
+            self.mode = match self.mode {
+                Mode::Fabrik => Mode::Rope,
+                Mode::Rope => Mode::Fabrik,
+            };
+        }
+        if self.mode == Mode::Rope {
+            if shared_state.pressed_keys.did_press_char_ignore_case('c') {
+                self.rope.clear();
+            }
+            self.update_rope(update_info, shared_state);
+            return;
+        }
+
         let (x, y) = shared_state.mouse_info.last_mouse_pos;
         let mouse_point = Point {
             x: x as f64,
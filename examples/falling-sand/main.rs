@@ -1,12 +1,16 @@
 use std::io::stdout;
 use teng::components::Component;
+use teng::components::coordinates::CoordinateDebugComponent;
+use teng::components::eventrecorder::{EventReplayerComponent, Recording};
 use teng::rendering::color::Color;
+use teng::rendering::hud::{FixedWidthNumber, HudRow};
 use teng::rendering::render::{HalfBlockDisplayRender, Render};
 use teng::rendering::renderer::Renderer;
+use teng::util::camera::{Camera2D, CellHalf};
 use teng::util::fixedupdate::FixedUpdateRunner;
 use teng::util::planarvec::{Bounds, PlanarVec};
 use teng::{
-    DisplayInfo, Game, SetupInfo, SharedState, UpdateInfo, install_panic_handler, terminal_cleanup,
+    Game, SetupInfo, SharedState, UpdateInfo, install_panic_handler, terminal_cleanup,
     terminal_setup,
 };
 
@@ -17,6 +21,21 @@ fn main() -> std::io::Result<()> {
     let mut game = Game::new(stdout());
     game.install_recommended_components();
     game.add_component(Box::new(FallingSimulationComponent::new()));
+    game.add_component(Box::new(CoordinateDebugComponent::new()));
+    if std::env::args().any(|arg| arg == "--demo") {
+        let recording = Recording::from_bytes(include_bytes!("demo.bin"));
+        game.add_component(Box::new(
+            EventReplayerComponent::demo(recording).with_loop_reset(
+                |shared_state: &mut SharedState<FallingSimulationData>| {
+                    let width = shared_state.display_info.width();
+                    let height = shared_state.display_info.height();
+                    let data = &mut shared_state.custom;
+                    data.resize_discard(width, height * 2);
+                    data.secs_passed = 0.0;
+                },
+            ),
+        ));
+    }
     game.run()?;
 
     terminal_cleanup()?;
@@ -174,6 +193,17 @@ impl FallingSimulationData {
         }
     }
 
+    /// Sets a piece, growing the world if the position is outside of it, e.g. when drawing right
+    /// after a resize or at the very edge of the screen.
+    fn paint(&mut self, (x, y): (i64, i64), kind: PieceKind) {
+        self.world
+            .get_or_insert_with(x, y, || Piece {
+                kind: PieceKind::Air,
+            })
+            .kind = kind;
+        self.has_moved.expand(self.world.bounds(), false);
+    }
+
     fn resize_discard(&mut self, width: usize, height: usize) {
         let bounds = Bounds {
             min_x: 0,
@@ -210,24 +240,16 @@ impl FallingSimulationComponent {
         }
     }
 
-    fn update_render(&mut self, data: &FallingSimulationData, display_info: &DisplayInfo) {
-        // TODO: add display here
-
-        for x in data.world.x_range() {
-            for y in data.world.y_range() {
-                let piece = data.world[(x, y)];
-                let color = match piece.kind {
-                    PieceKind::Air => Color::Transparent,
-                    PieceKind::Sand => Color::Rgb([255, 255, 0]),
-                    PieceKind::Water => Color::Rgb([0, 0, 255]),
-                };
-                let d_x = x;
-                let d_y = y;
-                let d_y = 2 * display_info.height() as i64 - d_y;
-                let d_y = d_y - 1;
-                self.hb_display.set_color(d_x as usize, d_y as usize, color);
+    fn update_render(&mut self, data: &FallingSimulationData, camera: &Camera2D) {
+        // every pixel shows the piece of its tile, so the rows can be filled in parallel
+        self.hb_display.par_fill_with(|x, y| {
+            let (tile_x, tile_y) = camera.pixel_to_tile(x as i64, y as i64);
+            match data.world.get(tile_x, tile_y).map(|piece| piece.kind) {
+                Some(PieceKind::Sand) => Color::Rgb([255, 255, 0]),
+                Some(PieceKind::Water) => Color::Rgb([0, 0, 255]),
+                Some(PieceKind::Air) | None => Color::Transparent,
             }
-        }
+        });
     }
 
     fn update_simulation(&mut self, shared_state: &mut SharedState<FallingSimulationData>) {
@@ -276,7 +298,9 @@ impl FallingSimulationComponent {
             }
         }
 
-        self.update_render(data, &shared_state.display_info);
+        shared_state.with_ext(|camera: &mut Camera2D, shared_state| {
+            self.update_render(&shared_state.custom, camera);
+        });
     }
 }
 
@@ -302,6 +326,9 @@ impl Component<FallingSimulationData> for FallingSimulationComponent {
         self.hb_display.resize_discard(width, height * 2);
         let data = &mut shared_state.custom;
         data.resize_discard(width, height * 2);
+        let mut camera = Camera2D::new(width, height);
+        camera.world_bounds = Some(((0.0, 0.0), (width as f64, 2.0 * height as f64)));
+        shared_state.extensions.insert(camera);
     }
 
     fn update(
@@ -311,34 +338,25 @@ impl Component<FallingSimulationData> for FallingSimulationComponent {
     ) {
         self.fixed_update_runner.fuel(update_info.dt);
 
-        // add sand from mouse events
+        // add sand along the mouse's path since the last frame
         let data = &mut shared_state.custom;
-
-        if shared_state.mouse_info.left_mouse_down
-            || shared_state.mouse_info.right_mouse_down
-            || shared_state.mouse_info.middle_mouse_down
-        {
-            let (s_x, s_y) = shared_state.mouse_info.last_mouse_pos;
-
-            let x = s_x as i64;
-            // scale to two halfblocks per pixel and recenter to 0,0
-            let y = shared_state.display_info.height() as i64 - s_y as i64;
-            let y = 2 * y;
-            let y = y - 1;
-
-            if let Some(piece) = data.world.get_mut(x, y) {
-                let kind = if shared_state.mouse_info.left_mouse_down {
+        let camera = shared_state.extensions.get::<Camera2D>().unwrap();
+        // interpolated in world coordinates, so fast strokes do not skip the bottom halves of cells
+        shared_state.mouse_events.for_each_linerp_mapped(
+            |(s_x, s_y)| camera.screen_to_tile(s_x, s_y, CellHalf::Top),
+            |(x, y), mouse_info| {
+                let kind = if mouse_info.left_mouse_down {
                     PieceKind::Sand
-                } else if shared_state.mouse_info.right_mouse_down {
+                } else if mouse_info.right_mouse_down {
                     PieceKind::Water
-                } else {
+                } else if mouse_info.middle_mouse_down {
                     PieceKind::Air
+                } else {
+                    return;
                 };
-                piece.kind = kind;
-            } else {
-                panic!("Mouse out of bounds: ({}, {})", x, y);
-            }
-        }
+                data.paint((x, y), kind);
+            },
+        );
 
         while self.fixed_update_runner.has_gas() {
             self.fixed_update_runner.consume();
@@ -356,7 +374,12 @@ impl Component<FallingSimulationData> for FallingSimulationComponent {
         let data = &shared_state.custom;
         format!("FallingSimulationComponent: {}s", data.secs_passed)
             .render(renderer, 0, 0, depth_base);
-        format!("sands: [{}]", data.total_pieces).render(renderer, 0, 1, depth_base);
+        HudRow::new()
+            .field(
+                "sands:",
+                FixedWidthNumber::for_max(data.total_pieces as f64, 99_999_999.0).with_separator(','),
+            )
+            .render(renderer, 0, 1, depth_base);
 
         self.hb_display.render(renderer, 0, 0, depth_base);
     }
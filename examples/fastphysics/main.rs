@@ -1,16 +1,20 @@
 mod math;
-mod spatial_hash_grid;
 
 use crate::math::Vec2;
-use crate::spatial_hash_grid::{Aabb, SpatialHashGrid};
+use rand::Rng;
 use rayon::prelude::*;
+use std::time::Duration;
 use std::{io, thread};
-use teng::components::Component;
+use teng::components::mouse::MouseTrackerComponent;
+use teng::components::{Component, Dependency};
 use teng::rendering::color::Color;
 use teng::rendering::pixel::Pixel;
 use teng::rendering::render::{HalfBlockDisplayRender, Render};
 use teng::rendering::renderer::Renderer;
+use teng::seeds::{rng_for_frame, set_seed};
 use teng::util::fixedupdate::FixedUpdateRunner;
+use teng::util::interp::Interpolated;
+use teng::util::spatial::{Aabb, SpatialHashGrid};
 use teng::{
     Game, SetupInfo, SharedState, UpdateInfo, install_panic_handler, terminal_cleanup,
     terminal_setup,
@@ -20,6 +24,8 @@ use teng::{
 #[derive(Debug, Clone)]
 struct Entity {
     pos: Vec2,
+    /// The position of the last two physics ticks, rendered in between.
+    render_pos: Interpolated<Vec2>,
     vel: Vec2,
     accel: Vec2,
     radius: f64,
@@ -32,6 +38,7 @@ impl Entity {
     fn new_at(x: f64, y: f64) -> Self {
         Self {
             pos: Vec2::new(x, y),
+            render_pos: Interpolated::new(Vec2::new(x, y)),
             vel: Vec2::new(0.0, 0.0),
             accel: Self::DEFAULT_ACCEL,
             radius: 0.5,
@@ -53,6 +60,12 @@ impl Entity {
         Self { vel, ..self }
     }
 
+    /// Moves the entity without rendering it moving there.
+    fn teleport(&mut self, pos: Vec2) {
+        self.pos = pos;
+        self.render_pos.reset(pos);
+    }
+
     #[inline]
     fn new_accel(&self) -> Vec2 {
         // Derive new acceleration from current position. Avoid using anything except the current
@@ -142,6 +155,8 @@ struct GameState {
     entities: Vec<Entity>,
     world_height: f64,
     world_width: f64,
+    /// How far the frame is between the last two physics ticks.
+    alpha: f64,
 }
 
 struct PhysicsComponent {
@@ -151,10 +166,14 @@ struct PhysicsComponent {
 impl PhysicsComponent {
     const COEFFICIENT_OF_RESTITUTION: f64 = 1.0;
     const PHYSICS_TICK_RATE: f64 = 60.0;
+    const MAX_TICKS_PER_FRAME: u64 = 4;
 
     fn new() -> Self {
         Self {
-            fur: FixedUpdateRunner::new_from_rate_per_second(Self::PHYSICS_TICK_RATE),
+            // without a cap, a frame that is slower than a tick leads to more ticks in the next
+            // frame, and the physics never catch up
+            fur: FixedUpdateRunner::new_from_rate_per_second(Self::PHYSICS_TICK_RATE)
+                .with_max_ticks_per_frame(Self::MAX_TICKS_PER_FRAME),
         }
     }
 
@@ -183,7 +202,7 @@ impl PhysicsComponent {
             shg.insert_with_aabb(idx, entity.get_aabb());
         }
         for idx1 in 0..state.entities.len() {
-            for &idx2 in shg.get_for_aabb(state.entities[idx1].get_aabb()) {
+            for &idx2 in shg.query_aabb(state.entities[idx1].get_aabb()) {
                 if idx1 == idx2 {
                     continue;
                 }
@@ -293,7 +312,7 @@ impl PhysicsComponent {
                 let shg = &shgs[idx];
                 s.spawn(|| {
                     for idx1 in 0..partition.len() {
-                        for &idx2 in shg.get_for_aabb(partition[idx1].get_aabb()) {
+                        for &idx2 in shg.query_aabb(partition[idx1].get_aabb()) {
                             if idx1 == idx2 {
                                 continue;
                             }
@@ -313,7 +332,7 @@ impl PhysicsComponent {
         // for partition in 0..num_pairs {
         //     let shg = &shgs[partition];
         //     for idx1 in 0..partitions[partition].len() {
-        //         for &idx2 in shg.get_for_aabb(partitions[partition][idx1].get_aabb()) {
+        //         for &idx2 in shg.query_aabb(partitions[partition][idx1].get_aabb()) {
         //             if idx1 == idx2 {
         //                 continue;
         //             }
@@ -375,7 +394,7 @@ impl PhysicsComponent {
                         // let first_partition: &mut Vec<Entity> = unsafe { &mut *(first_partition as *mut _) };
                         // let second_partition: &mut Vec<Entity> = unsafe { &mut *(second_partition as *mut _) };
                         for idx1 in 0..first_partition.len() {
-                            for &idx2 in shg2.get_for_aabb(first_partition[idx1].get_aabb()) {
+                            for &idx2 in shg2.query_aabb(first_partition[idx1].get_aabb()) {
                                 let entity1 = &mut first_partition[idx1];
                                 let entity2 = &mut second_partition[idx2];
                                 // check collision
@@ -405,7 +424,7 @@ impl PhysicsComponent {
         //         // let shg1 = &shgs[first];
         //         let shg2 = &shgs[second];
         //         for idx1 in 0..first_partition.len() {
-        //             for &idx2 in shg2.get_for_aabb(first_partition[idx1].get_aabb()) {
+        //             for &idx2 in shg2.query_aabb(first_partition[idx1].get_aabb()) {
         //                 let entity1 = &mut first_partition[idx1];
         //                 let entity2 = &mut second_partition[idx2];
         //                 // check collision
@@ -454,28 +473,33 @@ impl Component<GameState> for PhysicsComponent {
 
         let dt = update_info.dt;
         self.fur.fuel(dt);
+        shared_state
+            .debug_info
+            .set("physics.dropped_ticks", self.fur.dropped_ticks() as usize);
         while self.fur.has_gas() {
             self.fur.consume();
             let physics_dt = self.fur.fixed_dt();
             total_iterations += 1;
             let start = std::time::Instant::now();
             self.update_physics(physics_dt, &mut shared_state.custom);
+            for entity in &mut shared_state.custom.entities {
+                entity.render_pos.push(entity.pos);
+            }
             let duration = start.elapsed();
             total_duration_secs += duration.as_secs_f64();
         }
+        shared_state.custom.alpha = self.fur.alpha();
         if total_iterations > 0 {
             let avg = total_duration_secs / (total_iterations as f64);
-            shared_state.debug_info.custom.insert(
-                "average_physics_tick_ms_cost".to_string(),
-                format!("{:.5}", avg * 1000.0),
-            );
+            shared_state
+                .debug_info
+                .set_duration("physics.average_tick_cost", Duration::from_secs_f64(avg));
             if avg > self.fur.fixed_dt() {
-                let key = "entity_len_at_first_slow_physics_tick";
-                if !shared_state.debug_info.custom.contains_key(key) {
-                    shared_state.debug_info.custom.insert(
-                        key.to_string(),
-                        shared_state.custom.entities.len().to_string(),
-                    );
+                let key = "physics.entity_len_at_first_slow_tick";
+                if !shared_state.debug_info.contains_key(key) {
+                    shared_state
+                        .debug_info
+                        .set_persistent(key, shared_state.custom.entities.len());
                 }
                 // Log:
                 // first impl, no shg: at 5400 entities.
@@ -497,6 +521,11 @@ impl GameComponent {
 }
 
 impl Component<GameState> for GameComponent {
+    fn required_components(&self) -> Vec<Dependency> {
+        // reads the mouse position to spawn particles
+        vec![Dependency::of::<MouseTrackerComponent>()]
+    }
+
     fn setup(&mut self, setup_info: &SetupInfo, shared_state: &mut SharedState<GameState>) {
         self.on_resize(
             setup_info.display_info.width(),
@@ -528,30 +557,40 @@ impl Component<GameState> for GameComponent {
         // add entity on mouse click
         if shared_state.mouse_info.left_mouse_down {
             // spawn 100 in a radius of 3 around the mouse
+            let mut rng = rng_for_frame(update_info.frame_number, "spawn jitter");
             for _ in 0..100 {
-                let x = mouse_x + (rand::random::<i64>() % 3);
-                let y = mouse_y + (rand::random::<i64>() % 3);
+                let x = mouse_x + rng.gen_range(-2..=2);
+                let y = mouse_y + rng.gen_range(-2..=2);
                 shared_state
                     .custom
                     .entities
                     // .push(Entity::new_at(x as f64, y as f64).with_velocity((60.0, 0.0).into()).with_radius(2.0));
                     .push(Entity::new_at(x as f64, y as f64).with_velocity((60.0, 0.0).into()));
             }
-            shared_state.debug_info.custom.insert(
-                "total entities".to_string(),
-                format!("{}", shared_state.custom.entities.len()),
-            );
+            shared_state
+                .debug_info
+                .set("total_entities", shared_state.custom.entities.len());
         }
 
         // handle keyboard
         if shared_state.pressed_keys.did_press_char_ignore_case('c') {
             shared_state.custom.entities.clear();
         }
+        // respawn all entities at the top
+        if shared_state.pressed_keys.did_press_char_ignore_case('r') {
+            let state = &mut shared_state.custom;
+            let mut rng = rng_for_frame(update_info.frame_number, "respawn");
+            for entity in &mut state.entities {
+                let x = rng.r#gen::<f64>() * state.world_width;
+                entity.teleport(Vec2::new(x, state.world_height - entity.radius));
+            }
+        }
 
         // render entities
         self.hbd.clear();
+        let alpha = shared_state.custom.alpha;
         for entity in &shared_state.custom.entities {
-            let (x, y) = entity.pos.floor_to_i64();
+            let (x, y) = entity.render_pos.sample(alpha).floor_to_i64();
             // swap y axis, entity y grows upwards
             let y = height - y;
             // ignore oob
@@ -576,6 +615,7 @@ impl Component<GameState> for GameComponent {
 fn main() -> io::Result<()> {
     terminal_setup()?;
     install_panic_handler();
+    set_seed(42);
     // we need to exit on panic, see TODO in teng::install_panic_handler
     let old_hook = std::panic::take_hook();
     std::panic::set_hook(Box::new(move |panic_info| {
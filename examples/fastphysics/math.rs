@@ -1,4 +1,5 @@
 use std::ops::{Add, AddAssign, Div, Mul, MulAssign, Sub, SubAssign};
+use teng::util::interp::Lerp;
 
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct Vec2 {
@@ -45,6 +46,12 @@ impl Vec2 {
     }
 }
 
+impl Lerp for Vec2 {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        *self + (*other - *self) * t
+    }
+}
+
 impl Into<(f64, f64)> for Vec2 {
     fn into(self) -> (f64, f64) {
         (self.x, self.y)
@@ -0,0 +1,93 @@
+//! A hex map with `teng::util::hex`: click a hex to select it, which shows its movement range and
+//! the path to the hex under the mouse. Right click toggles walls.
+
+use std::io;
+use teng::components::Component;
+use teng::rendering::renderer::Renderer;
+use teng::util::hex::{Hex, HexLayout, HexMap, OffsetCoord, find_path, reachable};
+use teng::{
+    Game, SharedState, UpdateInfo, install_panic_handler, terminal_cleanup, terminal_setup,
+};
+
+const LAYOUT: HexLayout = HexLayout::DoubleWidth;
+/// The screen cell of hex `(0, 0)`.
+const ORIGIN: (i64, i64) = (2, 1);
+const MOVEMENT_RANGE: u32 = 4;
+
+struct HexMapComponent {
+    walls: HexMap<bool>,
+    selected: Option<Hex>,
+    hovered: Hex,
+    was_right_down: bool,
+}
+
+impl HexMapComponent {
+    fn new() -> Self {
+        let mut walls = HexMap::new(24, 14, false);
+        // a few walls in a fixed pattern
+        for row in 2..12 {
+            walls[OffsetCoord::new(8, row).to_hex()] = true;
+            walls[OffsetCoord::new(16, 13 - row).to_hex()] = true;
+        }
+        Self {
+            walls,
+            selected: None,
+            hovered: Hex::default(),
+            was_right_down: false,
+        }
+    }
+}
+
+impl Component for HexMapComponent {
+    fn update(&mut self, _update_info: UpdateInfo, shared_state: &mut SharedState) {
+        let mouse = &shared_state.mouse_info;
+        let (x, y) = mouse.last_mouse_pos;
+        self.hovered = LAYOUT.cell_to_hex(x as i64 - ORIGIN.0, y as i64 - ORIGIN.1);
+        if mouse.left_mouse_down && self.walls.get(self.hovered) == Some(&false) {
+            self.selected = Some(self.hovered);
+        }
+        if mouse.right_mouse_down
+            && !self.was_right_down
+            && let Some(wall) = self.walls.get_mut(self.hovered)
+        {
+            *wall = !*wall;
+        }
+        self.was_right_down = mouse.right_mouse_down;
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, _shared_state: &SharedState, depth_base: i32) {
+        for (hex, &wall) in self.walls.iter() {
+            if wall {
+                LAYOUT.render_fill(renderer, ORIGIN, hex, [90, 90, 90], depth_base);
+            } else {
+                LAYOUT.render_outline(renderer, ORIGIN, hex, [60, 80, 60], depth_base);
+            }
+        }
+        let Some(selected) = self.selected else {
+            return;
+        };
+        for (hex, _) in reachable(&self.walls, selected, MOVEMENT_RANGE, |wall| !wall) {
+            LAYOUT.render_outline(renderer, ORIGIN, hex, [80, 200, 80], depth_base + 1);
+        }
+        if let Some(path) = find_path(&self.walls, selected, self.hovered, |wall| !wall) {
+            for hex in path {
+                LAYOUT.render_fill(renderer, ORIGIN, hex, [200, 160, 40], depth_base + 2);
+            }
+        }
+        LAYOUT.render_fill(renderer, ORIGIN, selected, [60, 120, 255], depth_base + 3);
+    }
+}
+
+fn main() -> io::Result<()> {
+    terminal_setup()?;
+    install_panic_handler();
+
+    let mut game = Game::new_with_custom_buf_writer();
+    game.install_recommended_components();
+    game.add_component(Box::new(HexMapComponent::new()));
+    game.run()?;
+
+    terminal_cleanup()?;
+
+    Ok(())
+}
@@ -0,0 +1,195 @@
+//! A tiny system monitor that renders inline below the shell prompt, instead of taking over the
+//! whole terminal. Reads Linux's `/proc`, and shows "n/a" elsewhere. Press 'q' to quit.
+//!
+//! Runs in [`FpsMode::PowerSaver`], so between its updates it sleeps instead of rendering frames.
+
+use std::io;
+use std::time::Duration;
+use teng::components::Component;
+use teng::components::fpslocker::FpsMode;
+use teng::rendering::render::Render;
+use teng::rendering::renderer::Renderer;
+use teng::{
+    CustomBufWriter, Game, SetupInfo, SharedState, TerminalOptions, UpdateInfo,
+    install_panic_handler, terminal_cleanup, terminal_setup_with,
+};
+
+fn main() -> io::Result<()> {
+    terminal_setup_with(TerminalOptions {
+        alternate_screen: false,
+        mouse_capture: false,
+        ..TerminalOptions::default()
+    })?;
+    install_panic_handler();
+
+    let mut game = Game::new_inline(MonitorComponent::ROWS, CustomBufWriter::new())?;
+    game.install_recommended_components();
+    game.add_component(Box::new(MonitorComponent::new()));
+    game.run()?;
+
+    terminal_cleanup()?;
+
+    Ok(())
+}
+
+/// The busy and total jiffies of all CPUs, from the first line of `/proc/stat`.
+fn read_cpu_times() -> Option<(u64, u64)> {
+    let stat = std::fs::read_to_string("/proc/stat").ok()?;
+    let times = stat
+        .lines()
+        .next()?
+        .split_whitespace()
+        .skip(1)
+        .map(|t| t.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    let total = times.iter().sum();
+    // idle and iowait
+    let idle = times.get(3)? + times.get(4).unwrap_or(&0);
+    Some((total - idle, total))
+}
+
+/// The used and total memory in KiB, from `/proc/meminfo`.
+fn read_memory() -> Option<(u64, u64)> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let field = |name: &str| {
+        meminfo
+            .lines()
+            .find(|line| line.starts_with(name))?
+            .split_whitespace()
+            .nth(1)?
+            .parse::<u64>()
+            .ok()
+    };
+    let total = field("MemTotal:")?;
+    Some((total - field("MemAvailable:")?, total))
+}
+
+fn read_load_average() -> Option<String> {
+    let loadavg = std::fs::read_to_string("/proc/loadavg").ok()?;
+    Some(
+        loadavg
+            .split_whitespace()
+            .take(3)
+            .collect::<Vec<_>>()
+            .join(" "),
+    )
+}
+
+struct MonitorComponent {
+    last_cpu_times: Option<(u64, u64)>,
+    cpu_usage: Option<f64>,
+    memory: Option<(u64, u64)>,
+    load_average: Option<String>,
+}
+
+impl MonitorComponent {
+    const ROWS: u16 = 4;
+    const UPDATE_INTERVAL: Duration = Duration::from_millis(500);
+
+    fn new() -> Self {
+        Self {
+            last_cpu_times: read_cpu_times(),
+            cpu_usage: None,
+            memory: None,
+            load_average: None,
+        }
+    }
+
+    /// Renders a labeled bar that is `fraction` full.
+    fn render_bar(
+        renderer: &mut dyn Renderer,
+        y: usize,
+        width: usize,
+        label: &str,
+        fraction: Option<f64>,
+        detail: &str,
+        depth: i32,
+    ) {
+        format!("{label:<4}").render(renderer, 0, y, depth);
+        let bar_width = width.saturating_sub(6 + detail.len() + 1);
+        let Some(fraction) = fraction else {
+            "n/a".render(renderer, 4, y, depth);
+            return;
+        };
+        let filled = (fraction.clamp(0.0, 1.0) * bar_width as f64).round() as usize;
+        // green to red
+        let color = [
+            (255.0 * fraction) as u8,
+            (255.0 * (1.0 - fraction)) as u8,
+            60,
+        ];
+        "[".render(renderer, 4, y, depth);
+        "|".repeat(filled)
+            .with_color(color)
+            .render(renderer, 5, y, depth);
+        "]".render(renderer, 5 + bar_width, y, depth);
+        detail.render(renderer, 7 + bar_width, y, depth);
+    }
+}
+
+impl Component for MonitorComponent {
+    fn setup(&mut self, _setup_info: &SetupInfo, shared_state: &mut SharedState) {
+        // wake up for input or the next update only
+        shared_state.fps.mode = FpsMode::PowerSaver;
+        shared_state.fps.power_saver_timeout = Self::UPDATE_INTERVAL;
+    }
+
+    fn update_interval(&self) -> Option<Duration> {
+        Some(Self::UPDATE_INTERVAL)
+    }
+
+    fn update(&mut self, _update_info: UpdateInfo, _shared_state: &mut SharedState) {
+        let cpu_times = read_cpu_times();
+        if let (Some((busy, total)), Some((last_busy, last_total))) =
+            (cpu_times, self.last_cpu_times)
+            && total > last_total
+        {
+            self.cpu_usage = Some((busy - last_busy) as f64 / (total - last_total) as f64);
+        }
+        self.last_cpu_times = cpu_times;
+        self.memory = read_memory();
+        self.load_average = read_load_average();
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, shared_state: &SharedState, depth_base: i32) {
+        let width = shared_state.display_info.width();
+        "htop-lite - press q to quit"
+            .with_color([0, 0, 0])
+            .with_bg_color([80, 200, 120])
+            .render(renderer, 0, 0, depth_base);
+
+        let cpu_detail = match self.cpu_usage {
+            Some(usage) => format!("{:5.1}%", usage * 100.0),
+            None => String::new(),
+        };
+        Self::render_bar(
+            renderer,
+            1,
+            width,
+            "CPU",
+            self.cpu_usage,
+            &cpu_detail,
+            depth_base,
+        );
+
+        let (memory_fraction, memory_detail) = match self.memory {
+            Some((used, total)) => (
+                Some(used as f64 / total as f64),
+                format!("{}/{} MiB", used / 1024, total / 1024),
+            ),
+            None => (None, String::new()),
+        };
+        Self::render_bar(
+            renderer,
+            2,
+            width,
+            "Mem",
+            memory_fraction,
+            &memory_detail,
+            depth_base,
+        );
+
+        let load_average = self.load_average.as_deref().unwrap_or("n/a");
+        format!("Load average: {load_average}").render(renderer, 0, 3, depth_base);
+    }
+}
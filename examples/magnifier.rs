@@ -0,0 +1,129 @@
+//! Shows a magnifier that follows the mouse, using the renderer's previous frame.
+
+use std::io;
+use teng::components::Component;
+use teng::rendering::pixel::Pixel;
+use teng::rendering::render::Render;
+use teng::rendering::renderer::Renderer;
+use teng::{
+    Game, SharedState, UpdateInfo, install_panic_handler, terminal_cleanup, terminal_setup,
+};
+
+/// Draws a colorful, moving background to magnify.
+struct BackgroundComponent {
+    time: f64,
+}
+
+impl Component for BackgroundComponent {
+    fn update(&mut self, update_info: UpdateInfo, _shared_state: &mut SharedState) {
+        self.time += update_info.dt;
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, shared_state: &SharedState, depth_base: i32) {
+        let text = "teng magnifier demo - move the mouse around! ";
+        let width = shared_state.display_info.width();
+        let height = shared_state.display_info.height();
+        let offset = (self.time * 8.0) as usize;
+        for y in 0..height {
+            for x in 0..width {
+                let c = text.chars().cycle().nth(x + y * 3 + offset).unwrap();
+                let hue = (x + 2 * y) as f64 * 0.05 + self.time;
+                let color = [
+                    (127.0 + 127.0 * hue.sin()) as u8,
+                    (127.0 + 127.0 * (hue + 2.0).sin()) as u8,
+                    (127.0 + 127.0 * (hue + 4.0).sin()) as u8,
+                ];
+                Pixel::new(c)
+                    .with_color(color)
+                    .render(renderer, x, y, depth_base);
+            }
+        }
+    }
+}
+
+/// Renders a zoomed copy of the cells around the mouse from the previous frame.
+struct MagnifierComponent;
+
+impl MagnifierComponent {
+    const RADIUS_X: usize = 4;
+    const RADIUS_Y: usize = 2;
+    const ZOOM: usize = 2;
+}
+
+impl Component for MagnifierComponent {
+    fn render(&self, renderer: &mut dyn Renderer, shared_state: &SharedState, _depth_base: i32) {
+        let (mouse_x, mouse_y) = shared_state.mouse_info.last_mouse_pos;
+        let (region_w, region_h) = (2 * Self::RADIUS_X + 1, 2 * Self::RADIUS_Y + 1);
+        // copy the region out of the view, since rendering needs the renderer mutably
+        let cells: Vec<Option<Pixel>> = {
+            let Some(frame) = renderer.previous_frame() else {
+                return;
+            };
+            (0..region_h)
+                .flat_map(|dy| (0..region_w).map(move |dx| (dx, dy)))
+                .map(|(dx, dy)| {
+                    let x = (mouse_x + dx).checked_sub(Self::RADIUS_X)?;
+                    let y = (mouse_y + dy).checked_sub(Self::RADIUS_Y)?;
+                    (x < frame.width() && y < frame.height()).then(|| frame.pixel_at(x, y))
+                })
+                .collect()
+        };
+
+        // place the magnified box next to the region, so it does not magnify itself
+        let (box_w, box_h) = (region_w * Self::ZOOM + 2, region_h * Self::ZOOM + 2);
+        let width = shared_state.display_info.width();
+        let height = shared_state.display_info.height();
+        let box_x = if mouse_x + Self::RADIUS_X + 1 + box_w <= width {
+            mouse_x + Self::RADIUS_X + 1
+        } else {
+            mouse_x.saturating_sub(Self::RADIUS_X + box_w)
+        };
+        let box_y = if mouse_y + Self::RADIUS_Y + 1 + box_h <= height {
+            mouse_y + Self::RADIUS_Y + 1
+        } else {
+            mouse_y.saturating_sub(Self::RADIUS_Y + box_h)
+        };
+
+        let depth = i32::MAX - 60;
+        let border = Pixel::new('#')
+            .with_color([255, 255, 255])
+            .with_bg_color([0, 0, 0]);
+        for y in 0..box_h {
+            for x in 0..box_w {
+                let on_border = x == 0 || y == 0 || x == box_w - 1 || y == box_h - 1;
+                let pixel = if on_border {
+                    border
+                } else {
+                    let dx = (x - 1) / Self::ZOOM;
+                    let dy = (y - 1) / Self::ZOOM;
+                    cells[dy * region_w + dx].unwrap_or(Pixel::new(' ').with_bg_color([0, 0, 0]))
+                };
+                // mark the cell under the mouse
+                let is_center = (x - 1) / Self::ZOOM == Self::RADIUS_X
+                    && (y - 1) / Self::ZOOM == Self::RADIUS_Y
+                    && !on_border;
+                let pixel = if is_center {
+                    pixel.with_bg_color([80, 80, 80])
+                } else {
+                    pixel
+                };
+                renderer.render_pixel(box_x + x, box_y + y, pixel, depth);
+            }
+        }
+    }
+}
+
+fn main() -> io::Result<()> {
+    terminal_setup()?;
+    install_panic_handler();
+
+    let mut game = Game::new_with_custom_buf_writer();
+    game.install_recommended_components();
+    game.add_component(Box::new(BackgroundComponent { time: 0.0 }));
+    game.add_component(Box::new(MagnifierComponent));
+    game.run()?;
+
+    terminal_cleanup()?;
+
+    Ok(())
+}
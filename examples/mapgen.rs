@@ -0,0 +1,160 @@
+//! Shows the map generators in `teng::util::mapgen`.
+//!
+//! Press space to cycle through the generators, and 'n' to generate a new map with the next seed.
+//! '<' and '>' mark the stairs, 'g' the spawn points.
+
+use std::io;
+use teng::components::Component;
+use teng::rendering::pixel::Pixel;
+use teng::rendering::render::Render;
+use teng::rendering::renderer::Renderer;
+use teng::seeds::{get_u64_seed_for, set_seed};
+use teng::util::grid::Grid;
+use teng::util::mapgen::{
+    BspParams, CaveParams, CorridorStyle, Tile, bsp_dungeon, cellular_caves, drunkards_walk,
+    place_doors, place_stairs, spawn_points,
+};
+use teng::util::planarvec::PlanarVec;
+use teng::{
+    Game, SetupInfo, SharedState, UpdateInfo, install_panic_handler, terminal_cleanup,
+    terminal_setup,
+};
+
+const GENERATORS: [&str; 4] = [
+    "BSP rooms (L-shaped corridors)",
+    "BSP rooms (straight corridors)",
+    "Cellular caves",
+    "Drunkard's walk",
+];
+
+struct MapGenComponent {
+    generator: usize,
+    generation: u64,
+    tiles: PlanarVec<Tile>,
+    stairs: Option<((i64, i64), (i64, i64))>,
+    spawns: Vec<(i64, i64)>,
+}
+
+impl MapGenComponent {
+    fn new() -> Self {
+        Self {
+            generator: 0,
+            generation: 0,
+            tiles: PlanarVec::default(),
+            stairs: None,
+            spawns: vec![],
+        }
+    }
+
+    fn generate(&mut self, width: usize, height: usize) {
+        // leave the first row for the status line
+        let (width, height) = (width as i64, height as i64 - 1);
+        let seed = get_u64_seed_for(&format!("map{}", self.generation));
+        self.tiles = match self.generator {
+            0 | 1 => {
+                let corridor_style = if self.generator == 0 {
+                    CorridorStyle::LShaped
+                } else {
+                    CorridorStyle::Straight
+                };
+                let params = BspParams {
+                    width,
+                    height,
+                    corridor_style,
+                    ..BspParams::default()
+                };
+                let mut dungeon = bsp_dungeon(&params, seed);
+                place_doors(&mut dungeon);
+                dungeon.tiles
+            }
+            2 => {
+                let params = CaveParams {
+                    width,
+                    height,
+                    ..CaveParams::default()
+                };
+                cellular_caves(&params, seed)
+            }
+            _ => drunkards_walk(width, height, 0.35, seed),
+        };
+        self.stairs = place_stairs(&self.tiles, get_u64_seed_for("stairs") ^ seed);
+        self.spawns = spawn_points(&self.tiles, 10, 8.0, get_u64_seed_for("spawns") ^ seed);
+    }
+}
+
+impl Component for MapGenComponent {
+    fn setup(&mut self, setup_info: &SetupInfo, _shared_state: &mut SharedState) {
+        self.generate(
+            setup_info.display_info.width(),
+            setup_info.display_info.height(),
+        );
+    }
+
+    fn on_resize(&mut self, width: usize, height: usize, _shared_state: &mut SharedState) {
+        self.generate(width, height);
+    }
+
+    fn update(&mut self, _update_info: UpdateInfo, shared_state: &mut SharedState) {
+        let keys = &shared_state.pressed_keys;
+        let regenerate = if keys.did_press_char(' ') {
+            self.generator = (self.generator + 1) % GENERATORS.len();
+            true
+        } else if keys.did_press_char_ignore_case('n') {
+            self.generation += 1;
+            true
+        } else {
+            false
+        };
+        if regenerate {
+            self.generate(
+                shared_state.display_info.width(),
+                shared_state.display_info.height(),
+            );
+        }
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, _shared_state: &SharedState, depth_base: i32) {
+        format!(
+            "{} (map {}) - space: next generator, n: new map",
+            GENERATORS[self.generator], self.generation
+        )
+        .render(renderer, 0, 0, depth_base);
+        for (x, y, tile) in self.tiles.iter_cells() {
+            let pixel = match tile {
+                Tile::Wall => Pixel::new('#').with_color([90, 90, 90]),
+                Tile::Floor => Pixel::new('.').with_color([160, 140, 100]),
+                Tile::Door => Pixel::new('+').with_color([200, 120, 40]),
+            };
+            pixel.render(renderer, x as usize, y as usize + 1, depth_base);
+        }
+        let mut markers = vec![];
+        if let Some((up, down)) = self.stairs {
+            markers.push((up, '<', [255, 255, 255]));
+            markers.push((down, '>', [255, 255, 255]));
+        }
+        markers.extend(self.spawns.iter().map(|&pos| (pos, 'g', [0, 255, 0])));
+        for ((x, y), c, color) in markers {
+            Pixel::new(c).with_color(color).render(
+                renderer,
+                x as usize,
+                y as usize + 1,
+                depth_base + 1,
+            );
+        }
+    }
+}
+
+fn main() -> io::Result<()> {
+    terminal_setup()?;
+    install_panic_handler();
+
+    set_seed(42);
+    let mut game = Game::new_with_custom_buf_writer();
+    game.install_recommended_components();
+    game.add_component(Box::new(MapGenComponent::new()));
+    game.run()?;
+
+    terminal_cleanup()?;
+
+    Ok(())
+}
@@ -0,0 +1,103 @@
+//! A scrolling heightfield of fractal noise, colored like terrain from deep water to snow.
+//!
+//! Pass a seed as the first argument to see another world. The time to fill the display is shown
+//! in the debug info as `noise.fill`, press i to toggle it.
+
+use std::io;
+use std::time::Instant;
+use teng::components::Component;
+use teng::components::debuginfo::DebugInfoComponent;
+use teng::rendering::color::{Color, Gradient};
+use teng::rendering::render::{HalfBlockDisplayRender, Render};
+use teng::rendering::renderer::Renderer;
+use teng::seeds::set_seed;
+use teng::util::noise::Perlin2D;
+use teng::{
+    Game, SetupInfo, SharedState, UpdateInfo, install_panic_handler, terminal_cleanup,
+    terminal_setup,
+};
+
+fn main() -> io::Result<()> {
+    let seed = std::env::args()
+        .nth(1)
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or(42);
+    set_seed(seed);
+
+    terminal_setup()?;
+    install_panic_handler();
+
+    let mut game = Game::new_with_custom_buf_writer();
+    game.install_recommended_components();
+    game.add_component(Box::new(DebugInfoComponent::new()));
+    game.add_component(Box::new(NoiseComponent::new()));
+    game.run()?;
+
+    terminal_cleanup()?;
+
+    Ok(())
+}
+
+/// World units per pixel.
+const ZOOM: f64 = 0.03;
+/// Pixels per second.
+const SCROLL_SPEED: f64 = 20.0;
+
+struct NoiseComponent {
+    noise: Perlin2D,
+    terrain: Gradient,
+    hbd: HalfBlockDisplayRender,
+    offset: f64,
+}
+
+impl NoiseComponent {
+    fn new() -> Self {
+        Self {
+            noise: Perlin2D::from_global_seed(),
+            terrain: Gradient::new(&[
+                (-1.0, [0, 0, 80]),
+                (-0.1, [30, 90, 200]),
+                (0.0, [230, 210, 140]),
+                (0.1, [60, 160, 60]),
+                (0.4, [30, 90, 30]),
+                (0.55, [120, 110, 100]),
+                (0.7, [255, 255, 255]),
+            ]),
+            hbd: HalfBlockDisplayRender::new(0, 0),
+            offset: 0.0,
+        }
+    }
+}
+
+impl Component for NoiseComponent {
+    fn setup(&mut self, setup_info: &SetupInfo, shared_state: &mut SharedState) {
+        self.on_resize(
+            setup_info.display_info.width(),
+            setup_info.display_info.height(),
+            shared_state,
+        );
+    }
+
+    fn on_resize(&mut self, width: usize, height: usize, _shared_state: &mut SharedState) {
+        self.hbd.resize_discard(width, height * 2);
+    }
+
+    fn update(&mut self, update_info: UpdateInfo, shared_state: &mut SharedState) {
+        self.offset += SCROLL_SPEED * update_info.dt;
+
+        let start = Instant::now();
+        let offset = self.offset;
+        let (noise, terrain) = (&self.noise, &self.terrain);
+        self.hbd.par_fill_with(|x, y| {
+            let world_x = (x as f64 + offset) * ZOOM;
+            let world_y = y as f64 * ZOOM;
+            let height = noise.fbm(world_x, world_y, 5, 2.0, 0.5);
+            Color::Rgb(terrain.sample(height as f32))
+        });
+        shared_state.debug_info.set("noise.fill", start.elapsed());
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, _shared_state: &SharedState, depth_base: i32) {
+        self.hbd.render(renderer, 0, 0, depth_base);
+    }
+}
@@ -1,16 +1,17 @@
 //! A visual tool to check the functionality of `Bounds::union` and `Bounds::subtract`
 
-use std::collections::VecDeque;
 use std::io;
 use std::io::stdout;
 use std::time::Instant;
 use teng::components::Component;
+use teng::rendering::color::Gradient;
 use teng::rendering::display::Display;
 use teng::rendering::pixel::Pixel;
 use teng::rendering::render::Render;
 use teng::rendering::renderer::Renderer;
+use teng::util::grid::{Grid, distance_field};
 use teng::util::planarvec::Bounds;
-use teng::util::{get_lerp_t_u16, lerp_color};
+use teng::util::get_lerp_t_u16;
 use teng::{
     Game, SetupInfo, SharedState, UpdateInfo, install_panic_handler, terminal_cleanup,
     terminal_setup,
@@ -35,6 +36,7 @@ pub struct PathFindingComponent {
     dist_field: Display<u16>,
     direction_field: Display<(i8, i8)>,
     target: (usize, usize),
+    dist_gradient: Gradient,
 }
 
 impl PathFindingComponent {
@@ -44,6 +46,14 @@ impl PathFindingComponent {
             direction_field: Display::new(0, 0, (0, 0)),
             obstacle_field: Display::new(0, 0, false),
             target: (0, 0),
+            // low distances are yellow, high distances red, and far or unreachable cells dark red
+            dist_gradient: Gradient::new(&[
+                (0.0, [255, 255, 0]),
+                (0.6, [255, 128, 0]),
+                (1.0, [255, 0, 0]),
+                (1.1, [96, 0, 0]),
+            ])
+            .with_gamma_correction(true),
         }
     }
 
@@ -102,30 +112,11 @@ impl Component for PathFindingComponent {
         });
 
         if compute_fields {
-            self.dist_field.clear();
-
             // run bfs starting from target and fill dist_field
-            let mut queue = VecDeque::new();
-            queue.push_back((self.target, 0));
-            while let Some((pos, dist)) = queue.pop_front() {
-                if self.dist_field[pos] != 9999 {
-                    continue;
-                }
-                if self.obstacle_field[pos] {
-                    continue;
-                }
-                self.dist_field[pos] = dist;
-                for (dx, dy) in &[(0, 1), (0, -1), (1, 0), (-1, 0)] {
-                    let new_pos = (pos.0 as isize + dx, pos.1 as isize + dy);
-                    if new_pos.0 < 0
-                        || new_pos.0 >= self.dist_field.width() as isize
-                        || new_pos.1 < 0
-                        || new_pos.1 >= self.dist_field.height() as isize
-                    {
-                        continue;
-                    }
-                    queue.push_back(((new_pos.0 as usize, new_pos.1 as usize), dist + 1));
-                }
+            let target = (self.target.0 as i64, self.target.1 as i64);
+            let distances = distance_field(&self.obstacle_field, target, |wall| !wall);
+            for (x, y, dist) in distances.iter_cells() {
+                self.dist_field[(x as usize, y as usize)] = dist.map_or(9999, |d| d as u16);
             }
 
             // compute direction to go to reach target
@@ -171,12 +162,8 @@ impl Component for PathFindingComponent {
         for y in 0..self.direction_field.height() {
             for x in 0..self.direction_field.width() {
                 let dist = self.dist_field[(x, y)];
-                // map dist to color
-                // map low dist to yellow, high dist to red
-                let yellow = [255, 255, 0];
-                let red = [255, 0, 0];
                 let t = get_lerp_t_u16(0, 300, dist);
-                let color = lerp_color(yellow, red, t);
+                let color = self.dist_gradient.sample(t);
 
                 let dir = self.direction_field[(x, y)];
                 let c = Self::direction_to_char(dir);
@@ -0,0 +1,142 @@
+//! Switching between a title screen, the game and a pause menu with scenes.
+//!
+//! Press Enter on the title screen to start. In the game, Esc pushes the pause menu on top of
+//! the frozen game, where Esc resumes and 't' goes back to the title screen.
+
+use crossterm::event::KeyCode;
+use std::io;
+use teng::components::Component;
+use teng::components::scene::Scene;
+use teng::rendering::pixel::Pixel;
+use teng::rendering::render::Render;
+use teng::rendering::renderer::Renderer;
+use teng::{
+    Game, SharedState, UpdateInfo, install_panic_handler, terminal_cleanup, terminal_setup,
+};
+
+fn main() -> io::Result<()> {
+    terminal_setup()?;
+    install_panic_handler();
+
+    let mut game = Game::new_with_custom_buf_writer();
+    game.install_recommended_components();
+    let scenes = &mut game.shared_state_mut().scenes;
+    scenes.register(Scene::new("title").with(Box::new(TitleScreenComponent)));
+    scenes.register(Scene::new("game").with(Box::new(BallComponent::new())));
+    scenes.register(Scene::new("pause").with(Box::new(PauseMenuComponent)));
+    scenes.switch_to("title");
+    game.run()?;
+
+    terminal_cleanup()?;
+
+    Ok(())
+}
+
+fn render_centered(
+    text: &str,
+    dy: i64,
+    renderer: &mut dyn Renderer,
+    shared_state: &SharedState,
+    depth: i32,
+) {
+    let x = shared_state.display_info.width().saturating_sub(text.len()) / 2;
+    let y = (shared_state.display_info.height() / 2) as i64 + dy;
+    text.render(renderer, x, y.max(0) as usize, depth);
+}
+
+struct TitleScreenComponent;
+
+impl Component for TitleScreenComponent {
+    fn update(&mut self, _update_info: UpdateInfo, shared_state: &mut SharedState) {
+        if shared_state.pressed_keys.did_press(KeyCode::Enter) {
+            shared_state.scenes.switch_to("game");
+        }
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, shared_state: &SharedState, depth_base: i32) {
+        render_centered("BOUNCE", -1, renderer, shared_state, depth_base);
+        render_centered(
+            "press Enter to start",
+            1,
+            renderer,
+            shared_state,
+            depth_base,
+        );
+    }
+}
+
+/// The game: a ball bouncing around the screen.
+struct BallComponent {
+    pos: (f64, f64),
+    velocity: (f64, f64),
+    bounces: u32,
+}
+
+impl BallComponent {
+    fn new() -> Self {
+        Self {
+            pos: (1.0, 1.0),
+            velocity: (20.0, 10.0),
+            bounces: 0,
+        }
+    }
+}
+
+impl Component for BallComponent {
+    fn on_scene_enter(&mut self, _shared_state: &mut SharedState) {
+        // a fresh game every time it is started from the title screen
+        *self = Self::new();
+    }
+
+    fn update(&mut self, update_info: UpdateInfo, shared_state: &mut SharedState) {
+        if shared_state.pressed_keys.did_press(KeyCode::Esc) {
+            shared_state.scenes.push("pause");
+        }
+        let width = shared_state.display_info.width() as f64;
+        let height = shared_state.display_info.height() as f64;
+        for (pos, velocity, max) in [
+            (&mut self.pos.0, &mut self.velocity.0, width - 1.0),
+            (&mut self.pos.1, &mut self.velocity.1, height - 1.0),
+        ] {
+            *pos += *velocity * update_info.dt;
+            if *pos < 0.0 || *pos > max {
+                *pos = pos.clamp(0.0, max.max(0.0));
+                *velocity = -*velocity;
+                self.bounces += 1;
+            }
+        }
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, _shared_state: &SharedState, depth_base: i32) {
+        let pixel = Pixel::new('●').with_color([255, 200, 0]);
+        renderer.render_pixel(self.pos.0 as usize, self.pos.1 as usize, pixel, depth_base);
+        format!("bounces: {}  (Esc to pause)", self.bounces).render(renderer, 0, 0, depth_base);
+    }
+}
+
+/// Pushed on top of the game, which is still shown but frozen.
+struct PauseMenuComponent;
+
+impl Component for PauseMenuComponent {
+    fn update(&mut self, _update_info: UpdateInfo, shared_state: &mut SharedState) {
+        if shared_state.pressed_keys.did_press(KeyCode::Esc) {
+            shared_state.scenes.pop();
+        } else if shared_state.pressed_keys.did_press_char_ignore_case('t') {
+            shared_state.scenes.switch_to("title");
+        }
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, shared_state: &SharedState, depth_base: i32) {
+        for (dy, text) in [
+            (-1, "                                 "),
+            (0, "  PAUSED                         "),
+            (1, "  Esc: resume   t: title screen  "),
+            (2, "                                 "),
+        ] {
+            let text = text.with_bg_color([40, 40, 90]);
+            let x = shared_state.display_info.width().saturating_sub(33) / 2;
+            let y = (shared_state.display_info.height() / 2) as i64 + dy;
+            text.render(renderer, x, y.max(0) as usize, depth_base);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+//! Draws lines, rectangles, circles and polygons with the drawing primitives of
+//! `teng::rendering::draw`. Shapes are partially off-screen on purpose to show the clipping.
+
+use std::io;
+use teng::components::Component;
+use teng::rendering::color::Color;
+use teng::rendering::draw::Canvas;
+use teng::rendering::render::{HalfBlockDisplayRender, Render};
+use teng::rendering::renderer::Renderer;
+use teng::{
+    Game, SharedState, UpdateInfo, install_panic_handler, terminal_cleanup, terminal_setup,
+};
+
+/// Draws a few rotating shapes into a half-block buffer.
+struct ShapesComponent {
+    hbd: HalfBlockDisplayRender,
+    time: f64,
+}
+
+impl ShapesComponent {
+    fn new() -> Self {
+        Self {
+            hbd: HalfBlockDisplayRender::new(0, 0),
+            time: 0.0,
+        }
+    }
+
+    fn redraw(&mut self) {
+        self.hbd.clear();
+        let width = self.hbd.width() as i64;
+        let height = self.hbd.height() as i64;
+        let (cx, cy) = (width / 2, height / 2);
+
+        let (sin, cos) = self.time.sin_cos();
+        let radius = (width.min(height) / 3) as f64;
+        let star: Vec<(i64, i64)> = (0..5)
+            .map(|i| {
+                // every second point of a pentagon makes a pentagram
+                let angle = self.time + i as f64 * 4.0 * std::f64::consts::PI / 5.0;
+                (
+                    cx + (radius * angle.cos()) as i64,
+                    cy + (radius * angle.sin()) as i64,
+                )
+            })
+            .collect();
+        self.hbd.fill_polygon(&star, Color::Rgb([255, 200, 0]));
+
+        self.hbd
+            .fill_rect(-10, -10, width / 4, height / 4, Color::Rgb([40, 40, 120]));
+        self.hbd
+            .draw_rect(2, 2, width - 3, height - 3, Color::Rgb([255, 255, 255]));
+        self.hbd
+            .fill_circle(width - 1, height - 1, height / 3, Color::Rgb([0, 160, 80]));
+        self.hbd
+            .draw_circle(cx, cy, radius as i64 + 3, Color::Rgb([255, 80, 80]));
+
+        let length = (width + height) as f64;
+        self.hbd.draw_line(
+            cx - (length * cos) as i64,
+            cy - (length * sin) as i64,
+            cx + (length * cos) as i64,
+            cy + (length * sin) as i64,
+            Color::Rgb([0, 200, 255]),
+        );
+    }
+}
+
+impl Component for ShapesComponent {
+    fn update(&mut self, update_info: UpdateInfo, shared_state: &mut SharedState) {
+        self.time += update_info.dt * 0.5;
+        let width = shared_state.display_info.width();
+        let height = 2 * shared_state.display_info.height();
+        if self.hbd.width() != width || self.hbd.height() != height {
+            self.hbd.resize_discard(width, height);
+        }
+        self.redraw();
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, _shared_state: &SharedState, depth_base: i32) {
+        self.hbd.render(renderer, 0, 0, depth_base);
+    }
+}
+
+fn main() -> io::Result<()> {
+    terminal_setup()?;
+    install_panic_handler();
+
+    let mut game = Game::new_with_custom_buf_writer();
+    game.install_recommended_components();
+    game.add_component(Box::new(ShapesComponent::new()));
+    game.run()?;
+
+    terminal_cleanup()?;
+
+    Ok(())
+}
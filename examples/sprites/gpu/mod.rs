@@ -205,7 +205,7 @@ impl Component<GameState> for GpuComponent {
         let hbd = &mut game_state.hbd;
 
 
-        shared_state.debug_info.custom.insert("adapter_info".to_string(), format!("{:?}", self.state.get_adapter_info()));
+        shared_state.debug_info.set_str("adapter_info", format!("{:?}", self.state.get_adapter_info()));
 
         // render to hbd
         self.state.render(hbd).unwrap()
@@ -704,9 +704,9 @@ impl State {
         let y = y as f32;
         let (x, y) = self.camera.screen_to_world_coords(x, y);
         self.instances[0].position = [x, y, self.instances[0].position[2]];
-        shared_state.debug_info.custom.insert("instance0.pos".to_string(), format!("{:?}", self.instances[0].position));
-        shared_state.debug_info.custom.insert("world_to_screen".to_string(), format!("{:?}", self.camera.world_to_screen_coords(x, y)));
-        shared_state.debug_info.custom.insert("camera.pos".to_string(), format!("{:?}", self.camera.position));
+        shared_state.debug_info.set_str("instance0.pos", format!("{:?}", self.instances[0].position));
+        shared_state.debug_info.set_str("camera.world_to_screen", format!("{:?}", self.camera.world_to_screen_coords(x, y)));
+        shared_state.debug_info.set_str("camera.pos", format!("{:?}", self.camera.position));
 
         // let mut instance_writer = InstanceWriter::new(&mut self.instances);
         // // TODO: how to make .clear not necessary? or do we need it? basically, I think it would be nice to have
@@ -2,6 +2,7 @@ mod animationcontroller;
 mod goblin;
 mod impulse;
 mod player;
+mod screenshot;
 mod setandforgetanimations;
 mod sprite;
 mod wgpurender;
@@ -12,6 +13,7 @@ use crate::animationcontroller::{AnimationController, KeyedAnimationResult};
 use crate::goblin::Goblin;
 use crate::impulse::Trigger;
 use crate::player::{Player, PlayerComponent};
+use crate::screenshot::ScreenshotComponent;
 use crate::setandforgetanimations::SetAndForgetAnimations;
 use crate::sprite::{
     Animation, AnimationKind, AnimationRepository, AnimationRepositoryKey, CombinedAnimations,
@@ -20,12 +22,14 @@ use crate::sprite::{
 use rayon::prelude::*;
 use std::collections::HashMap;
 use std::{io, thread};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use teng::components::Component;
+use teng::components::daynight::{DayNightComponent, TimeOfDay};
 use teng::components::debuginfo::DebugMessage;
 use teng::rendering::color::Color;
 use teng::rendering::pixel::Pixel;
-use teng::rendering::render::{HalfBlockDisplayRender, Render};
+use teng::rendering::kitty::detect_kitty_graphics;
+use teng::rendering::render::{HalfBlockDisplayRender, KittyImageRender, Render};
 use teng::rendering::renderer::Renderer;
 use teng::util::fixedupdate::FixedUpdateRunner;
 use teng::{
@@ -125,10 +129,17 @@ impl Component<GameState> for GameComponent {
 }
 
 // Just renders the hbd from GameState as the last component
-struct RendererComponent;
+struct RendererComponent {
+    // if set, the hbd is shown as an image with the kitty graphics protocol
+    image: Option<KittyImageRender>,
+}
 
 impl Component<GameState> for RendererComponent {
     fn update(&mut self, update_info: UpdateInfo, shared_state: &mut SharedState<GameState>) {
+        if let Some(image) = &mut self.image {
+            image.copy_from_hbd(&shared_state.custom.hbd);
+        }
+
         // screen tearing test
         // let random_grey = rand::random::<u8>();
         // shared_state.custom.hbd.set_color(0, 0, Color::Rgb([random_grey; 3]));
@@ -162,11 +173,40 @@ impl Component<GameState> for RendererComponent {
         //     }
         // }
 
-        shared_state.custom.hbd.render(renderer, 0, 0, depth_base);
+        match &self.image {
+            Some(image) => image.render(renderer, 0, 0, depth_base),
+            None => shared_state.custom.hbd.render(renderer, 0, 0, depth_base),
+        }
+    }
+}
+
+/// Cycles a full day in 60 seconds and announces every new phase.
+struct DayNightDemoComponent;
+
+impl Component<GameState> for DayNightDemoComponent {
+    fn setup(&mut self, _setup_info: &SetupInfo, shared_state: &mut SharedState<GameState>) {
+        shared_state
+            .ext_or_default::<TimeOfDay>()
+            .set_day_length(Duration::from_secs(60));
+    }
+
+    fn update(&mut self, _update_info: UpdateInfo, shared_state: &mut SharedState<GameState>) {
+        let phase_changed = shared_state
+            .ext::<TimeOfDay>()
+            .and_then(|time_of_day| time_of_day.phase_changed());
+        if let Some(phase) = phase_changed {
+            shared_state
+                .debug_messages
+                .push(DebugMessage::new_3s(format!("{phase:?} has begun")));
+        }
     }
 }
 
+/// Pass `--kitty` to show the world as an image if the terminal supports the kitty graphics
+/// protocol, or `--force-kitty` to skip the detection.
 fn main() -> io::Result<()> {
+    let kitty_arg = std::env::args().skip(1).find(|arg| arg == "--kitty" || arg == "--force-kitty");
+
     terminal_setup()?;
     install_panic_handler();
     // we need to exit on panic, see TODO in teng::install_panic_handler
@@ -178,16 +218,28 @@ fn main() -> io::Result<()> {
 
     init_animation_repository();
 
+    let kitty_graphics = match kitty_arg.as_deref() {
+        Some("--force-kitty") => true,
+        Some(_) => detect_kitty_graphics()?,
+        None => false,
+    };
+
     let mut game = Game::new_with_custom_buf_writer();
+    game.set_kitty_graphics(kitty_graphics);
     game.install_recommended_components();
     game.add_component(Box::new(KeypressDebouncerComponent::new(70)));
+    game.add_component(Box::new(DayNightComponent::new()));
+    game.add_component(Box::new(DayNightDemoComponent));
     game.add_component(Box::new(GameComponent::new()));
     game.add_component(Box::new(PlayerComponent));
     game.add_component(Box::new(GpuComponent::new()));
     // game.add_component(Box::new(WgpuSpriteRenderComponent::new()));
     // game.add_component(Box::new(WgpuRenderComponent::new()));
     // game.add_component(Box::new(WgpuShadertoyRenderComponent::new()));
-    game.add_component(Box::new(RendererComponent));
+    game.add_component(Box::new(RendererComponent {
+        image: kitty_graphics.then(|| KittyImageRender::new(0, 0)),
+    }));
+    game.add_component(Box::new(ScreenshotComponent));
     game.run()?;
 
     terminal_cleanup()?;
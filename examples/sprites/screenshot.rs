@@ -0,0 +1,29 @@
+use crossterm::event::KeyCode;
+use teng::components::Component;
+use teng::components::debuginfo::DebugMessage;
+use teng::{SharedState, UpdateInfo};
+
+/// Saves a screenshot to `screenshot.png` when F12 is pressed.
+pub struct ScreenshotComponent;
+
+impl<S> Component<S> for ScreenshotComponent {
+    fn update(&mut self, _update_info: UpdateInfo, shared_state: &mut SharedState<S>) {
+        if shared_state.pressed_keys.did_press(KeyCode::F(12)) {
+            shared_state.frame_capture.request();
+        }
+        let Some(snapshot) = shared_state.frame_capture.take() else {
+            return;
+        };
+        let buffer = snapshot.to_rgb_image();
+        let image =
+            image::RgbImage::from_raw(buffer.width as u32, buffer.height as u32, buffer.data)
+                .unwrap();
+        let message = match image.save("screenshot.png") {
+            Ok(()) => "Saved screenshot.png".to_string(),
+            Err(e) => format!("Failed to save screenshot: {e}"),
+        };
+        shared_state
+            .debug_messages
+            .push(DebugMessage::new_3s(message));
+    }
+}
@@ -1,4 +1,3 @@
-use image::GenericImageView;
 use std::cell::OnceCell;
 use std::collections::HashMap;
 use std::path::Path;
@@ -6,6 +5,7 @@ use std::sync::OnceLock;
 use std::time::Instant;
 use teng::rendering::color::Color;
 use teng::rendering::render::HalfBlockDisplayRender;
+use teng::rendering::sprite::{ImageSprite, SpriteSheet};
 
 //TODO: (sprite render order)
 // A sprite renderer that is essentially a HalfBlockDisplayRender, but it collects all sprites before rendering.
@@ -127,40 +127,38 @@ impl Animation {
             assert!(filename.ends_with(".png"));
             let strip_num = filename.split("strip").nth(1).unwrap();
             let strip_num = strip_num.split(".").nth(0).unwrap();
-            strip_num.parse::<u32>().unwrap()
+            strip_num.parse::<usize>().unwrap()
         };
 
-        let image = image::open(filename).unwrap();
-        let (width, height) = image.dimensions();
-
-        // single sprite dimension
-        let sprite_width = width / strip_num;
-        let sprite_height = height;
-
-        let mut frames = Vec::new();
-
-        for i in 0..strip_num {
-            let mut pixels = Vec::new();
-            for y in 0..sprite_height {
-                for x in 0..sprite_width {
-                    let pixel = image.get_pixel(x + i * sprite_width, y);
-                    let is_transparent = pixel[3] < 255;
-                    let color = if is_transparent {
-                        Color::Transparent
-                    } else {
-                        Color::Rgb([pixel[0], pixel[1], pixel[2]])
-                    };
-                    pixels.push(color);
+        let image = ImageSprite::from_file(filename).unwrap();
+        let (width, height) = (image.width(), image.height());
+        let sheet = SpriteSheet::new(image, width / strip_num, height);
+
+        let frames = (0..sheet.len())
+            .map(|i| {
+                let frame = sheet.get(i).unwrap();
+                let (sprite_width, sprite_height) = (frame.width(), frame.height());
+                let mut pixels = Vec::new();
+                for y in 0..sprite_height {
+                    for x in 0..sprite_width {
+                        let [r, g, b, a] = frame.get(x, y).unwrap();
+                        let color = if a < 255 {
+                            Color::Transparent
+                        } else {
+                            Color::Rgb([r, g, b])
+                        };
+                        pixels.push(color);
+                    }
                 }
-            }
-            frames.push(Sprite {
-                height: sprite_height as u16,
-                width: sprite_width as u16,
-                pixels,
-                attach_offset: (sprite_width as i16 / 2, sprite_height as i16 / 2),
-                flipped_x: false,
-            });
-        }
+                Sprite {
+                    height: sprite_height as u16,
+                    width: sprite_width as u16,
+                    pixels,
+                    attach_offset: (sprite_width as i16 / 2, sprite_height as i16 / 2),
+                    flipped_x: false,
+                }
+            })
+            .collect();
 
         Animation {
             frames,
@@ -60,7 +60,7 @@ impl Component<GameState> for WgpuSpriteRenderComponent {
 
         let hbd = &mut game_state.hbd;
 
-        self.state.input(&shared_state.debounced_down_keys);
+        self.state.input(shared_state.debounced_keys.down_keys());
 
         if shared_state.pressed_keys.did_press_char_ignore_case('r') {
             self.state.update_texture_to_hbd(hbd);
@@ -68,7 +68,7 @@ impl Component<GameState> for WgpuSpriteRenderComponent {
 
 
 
-        shared_state.debug_info.custom.insert("adapter_info".to_string(), format!("{:?}", self.state.get_adapter_info()));
+        shared_state.debug_info.set_str("adapter_info", format!("{:?}", self.state.get_adapter_info()));
 
         // render to hbd
         self.state.render(hbd).unwrap()
@@ -153,7 +153,7 @@ impl Component<GameState> for WgpuRenderComponent {
             assert_eq!(delta_mouse_y, 0.0);
         }
 
-        self.state.input(&shared_state.debounced_down_keys, delta_mouse_x, delta_mouse_y);
+        self.state.input(shared_state.debounced_keys.down_keys(), delta_mouse_x, delta_mouse_y);
 
         if shared_state.pressed_keys.did_press_char_ignore_case('r') {
             self.state.update_texture_to_hbd(hbd);
@@ -161,7 +161,7 @@ impl Component<GameState> for WgpuRenderComponent {
 
         self.state.update();
 
-        shared_state.debug_info.custom.insert("adapter_info".to_string(), format!("{:?}", self.state.get_adapter_info()));
+        shared_state.debug_info.set_str("adapter_info", format!("{:?}", self.state.get_adapter_info()));
 
         // render to hbd
         self.state.render(hbd).unwrap()
@@ -234,7 +234,7 @@ impl Component<GameState> for WgpuShadertoyRenderComponent {
         self.state.update(self.start_time.elapsed().as_secs_f32(), self.frame_count);
         self.frame_count += 1;
 
-        shared_state.debug_info.custom.insert("adapter_info".to_string(), format!("{:?}", self.state.get_adapter_info()));
+        shared_state.debug_info.set_str("adapter_info", format!("{:?}", self.state.get_adapter_info()));
 
         let do_alpha = shared_state.mouse_info.right_mouse_down;
 
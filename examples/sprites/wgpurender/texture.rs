@@ -34,6 +34,10 @@ fn buf_from_hbd(hbd: &HalfBlockDisplayRender) -> (Vec<u8>, u32, u32) {
             Color::Rgb(rgb) => {
                 [rgb[0], rgb[1], rgb[2], 255]
             }
+            Color::Ansi(index) => {
+                let rgb = teng::rendering::color::ansi_to_rgb(index);
+                [rgb[0], rgb[1], rgb[2], 255]
+            }
         };
         buf[idx..idx + 4].copy_from_slice(&rgba);
         idx += 4;
@@ -190,6 +194,10 @@ impl Texture {
                 Color::Rgb(rgb) => {
                     [rgb[0], rgb[1], rgb[2], 255]
                 }
+                Color::Ansi(index) => {
+                    let rgb = teng::rendering::color::ansi_to_rgb(index);
+                    [rgb[0], rgb[1], rgb[2], 255]
+                }
             };
             buf[idx..idx + 4].copy_from_slice(&rgba);
             idx += 4;
@@ -0,0 +1,69 @@
+//! Toasts in the top right corner, next to the debug info in the same corner.
+//!
+//! Press 1 to 4 for a toast of each severity, and hold Space to fire a toast every frame to see
+//! the stack overflow: the oldest toasts fade out early to make room for the waiting ones.
+
+use crossterm::event::KeyCode;
+use std::io;
+use teng::components::Component;
+use teng::components::debuginfo::DebugInfoComponent;
+use teng::components::overlay_layout::{Corner, OverlayAnchor};
+use teng::components::toast::{Severity, Toast, ToastComponent};
+use teng::rendering::render::Render;
+use teng::rendering::renderer::Renderer;
+use teng::{
+    Game, SharedState, UpdateInfo, install_panic_handler, terminal_cleanup, terminal_setup,
+};
+
+fn main() -> io::Result<()> {
+    terminal_setup()?;
+    install_panic_handler();
+
+    let mut game = Game::new_with_custom_buf_writer();
+    game.install_recommended_components();
+    // both prefer the top right corner, the toasts move to the top left
+    game.add_component(Box::new(
+        DebugInfoComponent::new().with_anchor(OverlayAnchor::new(Corner::TopRight)),
+    ));
+    game.add_component(Box::new(ToastComponent::new()));
+    game.add_component(Box::new(ToastFiringComponent { fired: 0 }));
+    game.run()?;
+
+    terminal_cleanup()?;
+
+    Ok(())
+}
+
+struct ToastFiringComponent {
+    fired: usize,
+}
+
+impl Component for ToastFiringComponent {
+    fn update(&mut self, _update_info: UpdateInfo, shared_state: &mut SharedState) {
+        let severities = [
+            ('1', Severity::Info, "Game saved"),
+            ('2', Severity::Success, "Level complete!"),
+            ('3', Severity::Warning, "Low health"),
+            ('4', Severity::Error, "Connection lost"),
+        ];
+        for (key, severity, text) in severities {
+            if shared_state.pressed_keys.did_press_char(key) {
+                shared_state.toast(Toast::new(text).with_severity(severity));
+                self.fired += 1;
+            }
+        }
+        if shared_state.pressed_keys.did_press(KeyCode::Char(' '))
+            || shared_state.held_keys.is_held(KeyCode::Char(' '))
+        {
+            self.fired += 1;
+            shared_state.toast(Toast::new(format!("Toast #{}", self.fired)).with_duration(1.5));
+        }
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, shared_state: &SharedState, depth_base: i32) {
+        let y = shared_state.display_info.height().saturating_sub(2);
+        "1-4: toast of each severity, hold Space: a toast every frame"
+            .render(renderer, 1, y, depth_base);
+        format!("fired: {}", self.fired).render(renderer, 1, y + 1, depth_base);
+    }
+}
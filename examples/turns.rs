@@ -0,0 +1,283 @@
+//! A tiny turn-based roguelike showing the turn system.
+//!
+//! Move the player ('@') with the arrow keys or WASD. Every move ends the player's turn,
+//! after which the goblin ('g') chases the player and the slower snail ('s') wanders around.
+//! Moves are animated in real time, and the player can only act once all animations finished.
+
+use crossterm::event::KeyCode;
+use std::io;
+use teng::components::Component;
+use teng::components::turns::{TurnState, TurnSystemComponent};
+use teng::rendering::pixel::Pixel;
+use teng::rendering::render::Render;
+use teng::rendering::renderer::Renderer;
+use teng::util::turns::{ActionQueue, ActionResult, ActorId, TurnScheduler};
+use teng::{
+    Game, SetupInfo, SharedState, UpdateInfo, install_panic_handler, terminal_cleanup,
+    terminal_setup,
+};
+
+const MAP_WIDTH: i64 = 30;
+const MAP_HEIGHT: i64 = 12;
+const MOVE_ANIMATION_SECS: f64 = 0.08;
+
+fn main() -> io::Result<()> {
+    terminal_setup()?;
+    install_panic_handler();
+
+    let mut game = Game::<_, World>::new_with_custom_buf_writer();
+    game.install_recommended_components();
+    // must come before any component reading the `TurnState`
+    game.add_component(Box::new(TurnSystemComponent));
+    game.add_component(Box::new(PlayerInputComponent));
+    // simulation: only runs during world turns
+    game.add_component(Box::new(AiComponent));
+    // presentation: runs every frame
+    game.add_component(Box::new(ActionResolverComponent));
+    game.add_component(Box::new(WorldRenderComponent));
+    game.run()?;
+
+    terminal_cleanup()?;
+
+    Ok(())
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Behavior {
+    Player,
+    Chase,
+    Wander,
+}
+
+struct Actor {
+    id: ActorId,
+    glyph: char,
+    color: [u8; 3],
+    behavior: Behavior,
+    pos: (i64, i64),
+    // for the movement animation
+    anim_from: (i64, i64),
+    anim_start: f64,
+}
+
+struct Move(i64, i64);
+
+#[derive(Default)]
+struct World {
+    scheduler: TurnScheduler,
+    actions: ActionQueue<Move>,
+    actors: Vec<Actor>,
+}
+
+impl World {
+    fn spawn(
+        &mut self,
+        glyph: char,
+        color: [u8; 3],
+        behavior: Behavior,
+        speed: u32,
+        pos: (i64, i64),
+    ) {
+        let id = self.scheduler.add_actor(speed);
+        self.actors.push(Actor {
+            id,
+            glyph,
+            color,
+            behavior,
+            pos,
+            anim_from: pos,
+            anim_start: 0.0,
+        });
+    }
+
+    fn actor(&self, id: ActorId) -> &Actor {
+        self.actors.iter().find(|a| a.id == id).unwrap()
+    }
+
+    fn player(&self) -> &Actor {
+        self.actors
+            .iter()
+            .find(|a| a.behavior == Behavior::Player)
+            .unwrap()
+    }
+}
+
+struct PlayerInputComponent;
+
+impl Component<World> for PlayerInputComponent {
+    fn setup(&mut self, _setup_info: &SetupInfo, shared_state: &mut SharedState<World>) {
+        let world = &mut shared_state.custom;
+        world.spawn('@', [255, 255, 0], Behavior::Player, 10, (3, 3));
+        world.spawn('g', [0, 255, 0], Behavior::Chase, 10, (20, 8));
+        world.spawn('s', [200, 100, 255], Behavior::Wander, 5, (10, 9));
+    }
+
+    fn update(&mut self, _update_info: UpdateInfo, shared_state: &mut SharedState<World>) {
+        if !shared_state
+            .ext_expect::<TurnState>("PlayerInputComponent")
+            .is_accepting_input()
+        {
+            return;
+        }
+        let keys = &shared_state.pressed_keys;
+        let direction = if keys.did_press(KeyCode::Up) || keys.did_press_char_ignore_case('w') {
+            (0, -1)
+        } else if keys.did_press(KeyCode::Down) || keys.did_press_char_ignore_case('s') {
+            (0, 1)
+        } else if keys.did_press(KeyCode::Left) || keys.did_press_char_ignore_case('a') {
+            (-1, 0)
+        } else if keys.did_press(KeyCode::Right) || keys.did_press_char_ignore_case('d') {
+            (1, 0)
+        } else {
+            return;
+        };
+
+        let world = &mut shared_state.custom;
+        let player = world.player().id;
+        // the player may only act when the scheduler says so
+        if world.scheduler.next_actor() != Some(player) {
+            return;
+        }
+        world
+            .actions
+            .queue_action(player, Move(direction.0, direction.1));
+        world
+            .scheduler
+            .end_turn(player, TurnScheduler::DEFAULT_ACTION_COST);
+        shared_state
+            .ext_mut::<TurnState>()
+            .unwrap()
+            .end_player_turn();
+    }
+}
+
+struct AiComponent;
+
+impl Component<World> for AiComponent {
+    fn update(&mut self, _update_info: UpdateInfo, shared_state: &mut SharedState<World>) {
+        let turns = shared_state.ext_expect::<TurnState>("AiComponent");
+        if !turns.is_world_turn() {
+            return;
+        }
+        let turn = turns.turn();
+        let world = &mut shared_state.custom;
+        let player = world.player().id;
+        // let all actors act until it's the player's turn again
+        while let Some(actor_id) = world.scheduler.next_actor() {
+            if actor_id == player {
+                break;
+            }
+            let actor = world.actor(actor_id);
+            let target = world.player().pos;
+            let direction = match actor.behavior {
+                Behavior::Chase => (
+                    (target.0 - actor.pos.0).signum(),
+                    if target.0 == actor.pos.0 {
+                        (target.1 - actor.pos.1).signum()
+                    } else {
+                        0
+                    },
+                ),
+                // deterministic pseudo-random wandering
+                _ => [(1, 0), (0, 1), (-1, 0), (0, -1)][(turn as usize * 7 + 3) % 4],
+            };
+            world
+                .actions
+                .queue_action(actor_id, Move(direction.0, direction.1));
+            world
+                .scheduler
+                .end_turn(actor_id, TurnScheduler::DEFAULT_ACTION_COST);
+        }
+    }
+}
+
+/// Resolves queued moves one after another, each waiting for the previous animation to finish.
+struct ActionResolverComponent;
+
+impl Component<World> for ActionResolverComponent {
+    fn update(&mut self, _update_info: UpdateInfo, shared_state: &mut SharedState<World>) {
+        let now = shared_state
+            .ext_expect::<TurnState>("ActionResolverComponent")
+            .time();
+        let world = &mut shared_state.custom;
+        let mut busy_until = None;
+        let World {
+            actions, actors, ..
+        } = world;
+        actions.resolve(now, |actor_id, Move(dx, dy)| {
+            let idx = actors.iter().position(|a| a.id == actor_id).unwrap();
+            let new_pos = (actors[idx].pos.0 + dx, actors[idx].pos.1 + dy);
+            let blocked = new_pos.0 <= 0
+                || new_pos.1 <= 0
+                || new_pos.0 >= MAP_WIDTH - 1
+                || new_pos.1 >= MAP_HEIGHT - 1
+                || actors.iter().any(|a| a.pos == new_pos);
+            if blocked {
+                return ActionResult::Done;
+            }
+            let actor = &mut actors[idx];
+            actor.anim_from = actor.pos;
+            actor.anim_start = now;
+            actor.pos = new_pos;
+            busy_until = Some(now + MOVE_ANIMATION_SECS);
+            ActionResult::BusyUntil(now + MOVE_ANIMATION_SECS)
+        });
+        let idle = world.actions.is_idle(now);
+        let turns = shared_state.ext_mut::<TurnState>().unwrap();
+        if !idle {
+            // keep the player waiting until every queued action has played
+            turns.block_for(MOVE_ANIMATION_SECS);
+        }
+        if let Some(busy_until) = busy_until {
+            turns.block_until(busy_until);
+        }
+    }
+}
+
+struct WorldRenderComponent;
+
+impl Component<World> for WorldRenderComponent {
+    fn render(
+        &self,
+        renderer: &mut dyn Renderer,
+        shared_state: &SharedState<World>,
+        depth_base: i32,
+    ) {
+        let turns = shared_state.ext_expect::<TurnState>("WorldRenderComponent");
+        let now = turns.time();
+        for y in 0..MAP_HEIGHT {
+            for x in 0..MAP_WIDTH {
+                let c = if x == 0 || y == 0 || x == MAP_WIDTH - 1 || y == MAP_HEIGHT - 1 {
+                    '#'
+                } else {
+                    '.'
+                };
+                Pixel::new(c).with_color([100, 100, 100]).render(
+                    renderer,
+                    x as usize,
+                    y as usize + 1,
+                    depth_base,
+                );
+            }
+        }
+        for actor in &shared_state.custom.actors {
+            // interpolate between the previous and current position while animating
+            let t = ((now - actor.anim_start) / MOVE_ANIMATION_SECS).clamp(0.0, 1.0);
+            let x = actor.anim_from.0 as f64 + (actor.pos.0 - actor.anim_from.0) as f64 * t;
+            let y = actor.anim_from.1 as f64 + (actor.pos.1 - actor.anim_from.1) as f64 * t;
+            Pixel::new(actor.glyph).with_color(actor.color).render(
+                renderer,
+                x.round() as usize,
+                y.round() as usize + 1,
+                depth_base + 1,
+            );
+        }
+        let status = if turns.is_accepting_input() {
+            "Your turn"
+        } else {
+            "..."
+        };
+        format!("Turn {}: {}", turns.turn(), status)
+            .render(renderer, 0, 0, depth_base);
+    }
+}
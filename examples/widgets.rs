@@ -0,0 +1,110 @@
+//! A settings panel built from the standard UI widgets.
+//!
+//! Click a widget to focus it. The slider also follows the arrow keys, the checkbox and the
+//! buttons Space and Enter, and the name field takes text while focused.
+
+use std::io;
+use teng::components::Component;
+use teng::components::ui::UiComponent;
+use teng::components::ui::widgets::{Button, Checkbox, Slider, TextInput};
+use teng::rendering::render::Render;
+use teng::rendering::renderer::Renderer;
+use teng::{
+    Game, Priority, SetupInfo, SharedState, UpdateInfo, install_panic_handler, terminal_cleanup,
+    terminal_setup,
+};
+
+fn main() -> io::Result<()> {
+    terminal_setup()?;
+    install_panic_handler();
+
+    let mut game = Game::new_with_custom_buf_writer();
+    game.install_recommended_components();
+    // before the quitter, so that typing a 'q' into the name field does not quit
+    game.add_component_with_priority(Box::new(UiComponent::new()), Priority(500));
+    game.add_component(Box::new(SettingsPanelComponent::new()));
+    game.run()?;
+
+    terminal_cleanup()?;
+
+    Ok(())
+}
+
+const LEFT: usize = 2;
+const FIELDS: usize = 14;
+
+struct SettingsPanelComponent {
+    name: TextInput,
+    volume: Slider,
+    fullscreen: Checkbox,
+    apply: Button,
+    reset: Button,
+    status: String,
+}
+
+impl SettingsPanelComponent {
+    fn new() -> Self {
+        Self {
+            name: TextInput::new(20).with_max_len(32).with_text("Player"),
+            volume: Slider::new(0.0, 100.0, 5.0).with_value(80.0),
+            fullscreen: Checkbox::new("Fullscreen"),
+            apply: Button::new("Apply"),
+            reset: Button::new("Reset"),
+            status: String::new(),
+        }
+    }
+
+    fn apply(&mut self) {
+        self.status = format!(
+            "Saved: {} at {}% volume{}",
+            self.name.text(),
+            self.volume.value(),
+            if self.fullscreen.is_checked() {
+                ", fullscreen"
+            } else {
+                ""
+            }
+        );
+    }
+}
+
+impl Component for SettingsPanelComponent {
+    fn setup(&mut self, _setup_info: &SetupInfo, shared_state: &mut SharedState) {
+        let ui = &mut shared_state.ui;
+        ui.add_window("name", LEFT + FIELDS, 3, Box::new(self.name.clone()));
+        ui.add_window("volume", LEFT + FIELDS, 5, Box::new(self.volume.clone()));
+        ui.add_window(
+            "fullscreen",
+            LEFT + FIELDS,
+            7,
+            Box::new(self.fullscreen.clone()),
+        );
+        ui.add_window("apply", LEFT + FIELDS, 9, Box::new(self.apply.clone()));
+        ui.add_window("reset", LEFT + FIELDS + 10, 9, Box::new(self.reset.clone()));
+    }
+
+    fn update(&mut self, _update_info: UpdateInfo, _shared_state: &mut SharedState) {
+        // without short-circuiting, so that every flag is reset
+        if self.volume.was_changed() | self.fullscreen.was_toggled() | self.name.was_changed() {
+            self.status = "Unsaved changes".to_string();
+        }
+        if self.apply.was_clicked() | self.name.was_submitted() {
+            self.apply();
+        }
+        if self.reset.was_clicked() {
+            // the windows hold clones of the widgets, so their state is reset in place
+            self.name.set_text("Player");
+            self.volume.set_value(80.0);
+            self.fullscreen.set_checked(false);
+            self.status = "Reset to defaults".to_string();
+        }
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, _shared_state: &SharedState, depth_base: i32) {
+        "Settings".render(renderer, LEFT, 1, depth_base);
+        "Name".render(renderer, LEFT, 3, depth_base);
+        "Volume".render(renderer, LEFT, 5, depth_base);
+        "Display".render(renderer, LEFT, 7, depth_base);
+        self.status.render(renderer, LEFT, 11, depth_base);
+    }
+}
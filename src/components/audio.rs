@@ -0,0 +1,855 @@
+//! Sound playback with [`AudioComponent`], and declarative sound reactions to game events with
+//! [`AudioReactionComponent`].
+//!
+//! With the `audio` feature, the [`AudioComponent`] plays tones and WAV files on the default
+//! output device through its [`AudioHandle`]:
+//! ```rust
+//! use teng::components::Component;
+//! use teng::components::audio::AudioHandle;
+//! use teng::{SharedState, UpdateInfo};
+//!
+//! struct Jump;
+//! impl Component for Jump {
+//!     fn update(&mut self, _update_info: UpdateInfo, shared_state: &mut SharedState) {
+//!         if shared_state.pressed_keys.did_press_char(' ') {
+//!             // a no-op if there is no audio device
+//!             shared_state.ext_expect::<AudioHandle>("Jump").play_tone(440.0, 100);
+//!         }
+//!     }
+//! }
+//! ```
+//! Add the [`AudioComponent`] before the components that use its handle.
+//!
+//! For anything else, games plug in their audio library by implementing [`AudioBackend`], and
+//! map game events to sounds once instead of playing sounds all over their systems:
+//! ```rust
+//! use std::time::Duration;
+//! use teng::components::audio::{
+//!     AudioBackend, AudioReaction, AudioReactionComponent, SfxPlay, SoundAction, Sfx,
+//! };
+//!
+//! struct HitLanded;
+//!
+//! struct MyAudio;
+//! impl AudioBackend for MyAudio {
+//!     fn play_sfx(&mut self, sfx: &SfxPlay) -> bool {
+//!         // play `sfx.name` with `sfx.volume` and `sfx.pitch`
+//!         true
+//!     }
+//!     fn play_music(&mut self, name: &str, crossfade: Duration) {}
+//!     fn stop_music(&mut self, fade: Duration) {}
+//!     fn duck_music(&mut self, volume: f32, duration: Duration) {}
+//! }
+//!
+//! let audio = AudioReactionComponent::new(MyAudio)
+//!     .with_reaction_to::<HitLanded>(
+//!         AudioReaction::new(Sfx::new("hit.wav").with_pitch(0.9, 1.1).with_intensity(0.1, 2.0))
+//!             .with_rate_limit(20.0),
+//!     )
+//!     .with_reaction(
+//!         "boss_appeared",
+//!         AudioReaction::new(SoundAction::PlayMusic {
+//!             name: "boss.ogg".to_string(),
+//!             crossfade: Duration::from_secs(2),
+//!         }),
+//!     );
+//! // keep a sender to trigger events from other components
+//! let events = audio.sender();
+//! events.send_event::<HitLanded>();
+//! events.send("boss_appeared");
+//! ```
+//! Events are collected from the [`AudioEvents`] senders at the start of every update. All
+//! events of the same key in one update are coalesced into a single reaction, which can be
+//! louder for larger bursts, see [`Sfx::with_intensity`].
+
+use crate::components::debuginfo::DebugMessage;
+use crate::{Component, SetupInfo, SharedState, UpdateInfo};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// A sound effect to play, as requested from an [`AudioBackend`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SfxPlay {
+    pub name: String,
+    /// The volume, where 1 is the sound's normal volume.
+    pub volume: f32,
+    /// The playback speed, where 1 is the sound's normal pitch.
+    pub pitch: f32,
+    /// Higher priorities may replace lower ones if all voices are in use.
+    pub priority: u8,
+}
+
+/// The audio library of a game, driven by an [`AudioReactionComponent`].
+pub trait AudioBackend {
+    /// Plays a sound effect, and returns false if it was not played because all voices are in
+    /// use by sounds of the same or a higher priority.
+    fn play_sfx(&mut self, sfx: &SfxPlay) -> bool;
+
+    /// Starts the music `name`, crossfading from the current music.
+    fn play_music(&mut self, name: &str, crossfade: Duration);
+
+    /// Fades out the current music.
+    fn stop_music(&mut self, fade: Duration);
+
+    /// Lowers the music to `volume` for `duration`, e.g. to make an announcement audible.
+    fn duck_music(&mut self, volume: f32, duration: Duration);
+}
+
+/// A sound effect with randomized volume and pitch, see [`SoundAction::PlaySfx`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sfx {
+    pub name: String,
+    pub volume: (f32, f32),
+    pub pitch: (f32, f32),
+    pub priority: u8,
+    /// The volume factor per coalesced event beyond the first, and the maximum factor.
+    pub intensity: Option<(f32, f32)>,
+}
+
+impl Sfx {
+    /// Creates a sound effect at normal volume and pitch, with priority 0.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            volume: (1.0, 1.0),
+            pitch: (1.0, 1.0),
+            priority: 0,
+            intensity: None,
+        }
+    }
+
+    /// Randomizes the volume uniformly between `min` and `max`.
+    pub fn with_volume(mut self, min: f32, max: f32) -> Self {
+        self.volume = (min, max);
+        self
+    }
+
+    /// Randomizes the pitch uniformly between `min` and `max`.
+    pub fn with_pitch(mut self, min: f32, max: f32) -> Self {
+        self.pitch = (min, max);
+        self
+    }
+
+    pub fn with_priority(mut self, priority: u8) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Scales the volume of a burst of coalesced events by `1 + per_event * (count - 1)`, up
+    /// to `max`.
+    pub fn with_intensity(mut self, per_event: f32, max: f32) -> Self {
+        self.intensity = Some((per_event, max));
+        self
+    }
+}
+
+/// What an [`AudioReaction`] does.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SoundAction {
+    PlaySfx(Sfx),
+    /// Starts music, crossfading from the current music.
+    PlayMusic {
+        name: String,
+        crossfade: Duration,
+    },
+    StopMusic {
+        fade: Duration,
+    },
+    /// Lowers the music volume for a while.
+    DuckMusic {
+        volume: f32,
+        duration: Duration,
+    },
+}
+
+impl From<Sfx> for SoundAction {
+    fn from(sfx: Sfx) -> Self {
+        SoundAction::PlaySfx(sfx)
+    }
+}
+
+/// A sound action with an optional rate limit, see [`AudioReactionComponent::with_reaction`].
+#[derive(Clone, Debug)]
+pub struct AudioReaction {
+    pub action: SoundAction,
+    /// The minimum time between two triggers. Events in between are dropped.
+    pub min_interval: Option<Duration>,
+    last_triggered: Option<Instant>,
+}
+
+impl AudioReaction {
+    pub fn new(action: impl Into<SoundAction>) -> Self {
+        Self {
+            action: action.into(),
+            min_interval: None,
+            last_triggered: None,
+        }
+    }
+
+    /// Triggers at most `max_per_second` times per second.
+    pub fn with_rate_limit(mut self, max_per_second: f64) -> Self {
+        self.min_interval = Some(Duration::from_secs_f64(1.0 / max_per_second));
+        self
+    }
+
+    fn is_ready(&self, now: Instant) -> bool {
+        match (self.last_triggered, self.min_interval) {
+            (Some(last), Some(min_interval)) => now.duration_since(last) >= min_interval,
+            _ => true,
+        }
+    }
+}
+
+/// A sender of game events to an [`AudioReactionComponent`], see
+/// [`AudioReactionComponent::sender`].
+#[derive(Clone, Debug)]
+pub struct AudioEvents(mpsc::Sender<String>);
+
+impl AudioEvents {
+    /// Sends the string-keyed event `key`.
+    pub fn send(&self, key: impl Into<String>) {
+        // the component was dropped, so nobody listens
+        let _ = self.0.send(key.into());
+    }
+
+    /// Sends the event of type `E`.
+    pub fn send_event<E: 'static>(&self) {
+        self.send(std::any::type_name::<E>());
+    }
+}
+
+/// Statistics of an [`AudioReactionComponent`], e.g. for debug info.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AudioReactionStats {
+    /// The number of events received.
+    pub events: usize,
+    /// The number of sound effects played.
+    pub played: usize,
+    /// The number of events that were coalesced into another event's reaction.
+    pub coalesced: usize,
+    /// The number of reactions that were skipped because of their rate limit.
+    pub rate_limited: usize,
+    /// The number of sound effects the backend refused because all voices were in use.
+    pub voice_limited: usize,
+}
+
+/// A component that plays sounds in reaction to game events.
+pub struct AudioReactionComponent<B> {
+    backend: B,
+    reactions: BTreeMap<String, Vec<AudioReaction>>,
+    sender: mpsc::Sender<String>,
+    receiver: mpsc::Receiver<String>,
+    rng: u64,
+    stats: AudioReactionStats,
+}
+
+impl<B: AudioBackend> AudioReactionComponent<B> {
+    /// Creates a component without reactions. Randomization is seeded from the
+    /// [global seed](crate::seeds), if it is set.
+    pub fn new(backend: B) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let seed = crate::seeds::get_seed_opt()
+            .map(|_| crate::seeds::get_u64_seed_for("audio_reactions"))
+            .unwrap_or(0);
+        Self {
+            backend,
+            reactions: BTreeMap::new(),
+            sender,
+            receiver,
+            rng: seed,
+            stats: AudioReactionStats::default(),
+        }
+    }
+
+    /// Seeds the randomization of volumes and pitches.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = seed;
+        self
+    }
+
+    /// Adds a reaction to the string-keyed event `key`.
+    pub fn with_reaction(mut self, key: impl Into<String>, reaction: AudioReaction) -> Self {
+        self.add_reaction(key, reaction);
+        self
+    }
+
+    /// Adds a reaction to the event type `E`, see [`AudioEvents::send_event`].
+    pub fn with_reaction_to<E: 'static>(self, reaction: AudioReaction) -> Self {
+        self.with_reaction(std::any::type_name::<E>(), reaction)
+    }
+
+    /// Adds a reaction to the string-keyed event `key` at runtime.
+    pub fn add_reaction(&mut self, key: impl Into<String>, reaction: AudioReaction) {
+        self.reactions.entry(key.into()).or_default().push(reaction);
+    }
+
+    /// Removes all reactions to `key`.
+    pub fn remove_reactions(&mut self, key: &str) {
+        self.reactions.remove(key);
+    }
+
+    /// Returns a sender of events to this component.
+    pub fn sender(&self) -> AudioEvents {
+        AudioEvents(self.sender.clone())
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
+    pub fn stats(&self) -> AudioReactionStats {
+        self.stats
+    }
+
+    /// Returns a uniformly distributed value in `min..=max` (SplitMix64).
+    fn random_in(&mut self, (min, max): (f32, f32)) -> f32 {
+        self.rng = self.rng.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        let unit = (z >> 40) as f32 / (1u64 << 24) as f32;
+        min + (max - min) * unit
+    }
+
+    /// Reacts to the events received since the last call.
+    fn react(&mut self, now: Instant) {
+        let mut counts = BTreeMap::<String, usize>::new();
+        for key in self.receiver.try_iter() {
+            *counts.entry(key).or_default() += 1;
+            self.stats.events += 1;
+        }
+
+        // sound effects are played by priority, so that the important ones get the voices
+        let mut plays = vec![];
+        for (key, count) in counts {
+            self.stats.coalesced += count - 1;
+            let Some(mut reactions) = self.reactions.remove(&key) else {
+                continue;
+            };
+            for (idx, reaction) in reactions.iter_mut().enumerate() {
+                if !reaction.is_ready(now) {
+                    self.stats.rate_limited += 1;
+                    continue;
+                }
+                match &reaction.action {
+                    SoundAction::PlaySfx(sfx) => {
+                        let intensity = sfx.intensity.map_or(1.0, |(per_event, max)| {
+                            (1.0 + per_event * (count - 1) as f32).min(max)
+                        });
+                        let play = SfxPlay {
+                            name: sfx.name.clone(),
+                            volume: self.random_in(sfx.volume) * intensity,
+                            pitch: self.random_in(sfx.pitch),
+                            priority: sfx.priority,
+                        };
+                        plays.push((key.clone(), idx, play));
+                        continue;
+                    }
+                    SoundAction::PlayMusic { name, crossfade } => {
+                        self.backend.play_music(name, *crossfade);
+                    }
+                    SoundAction::StopMusic { fade } => self.backend.stop_music(*fade),
+                    SoundAction::DuckMusic { volume, duration } => {
+                        self.backend.duck_music(*volume, *duration);
+                    }
+                }
+                reaction.last_triggered = Some(now);
+            }
+            self.reactions.insert(key, reactions);
+        }
+
+        plays.sort_by_key(|(_, _, play)| std::cmp::Reverse(play.priority));
+        for (key, idx, play) in plays {
+            if !self.backend.play_sfx(&play) {
+                // a refused sound does not count towards the rate limit
+                self.stats.voice_limited += 1;
+                continue;
+            }
+            self.stats.played += 1;
+            if let Some(reaction) = self.reactions.get_mut(&key).and_then(|r| r.get_mut(idx)) {
+                reaction.last_triggered = Some(now);
+            }
+        }
+    }
+}
+
+impl<B: AudioBackend + 'static, S> Component<S> for AudioReactionComponent<B> {
+    fn runs_while_paused(&self) -> bool {
+        // menus make sounds too
+        true
+    }
+
+    fn update(&mut self, update_info: UpdateInfo, _shared_state: &mut SharedState<S>) {
+        self.react(update_info.current_time);
+    }
+}
+
+#[cfg(feature = "audio")]
+mod device {
+    use rodio::source::SineWave;
+    use rodio::{Decoder, OutputStream, OutputStreamHandle, Source};
+    use std::fs::File;
+    use std::io;
+    use std::io::{BufReader, Cursor, Read, Seek};
+    use std::path::Path;
+    use std::time::Duration;
+
+    /// The volume of tones at a master volume of 1, since a full-scale sine wave is very loud.
+    const TONE_VOLUME: f32 = 0.25;
+
+    /// The output stream. Dropping it stops the audio thread.
+    pub(super) struct Device(#[allow(dead_code)] OutputStream);
+
+    /// Mixes sounds into the output stream.
+    pub(super) struct Output(OutputStreamHandle);
+
+    pub(super) fn open() -> Result<(Device, Output), String> {
+        OutputStream::try_default()
+            .map(|(stream, handle)| (Device(stream), Output(handle)))
+            .map_err(|err| err.to_string())
+    }
+
+    impl Output {
+        pub(super) fn play_tone(&self, freq_hz: f32, duration: Duration, volume: f32) {
+            let mut tone = SineWave::new(freq_hz).take_duration(duration);
+            // avoids a click at the end
+            tone.set_filter_fadeout();
+            // fails only if the device is gone, which the component already reported
+            let _ = self.0.play_raw(tone.amplify(TONE_VOLUME * volume));
+        }
+
+        pub(super) fn play_wav(&self, data: &'static [u8], volume: f32) -> io::Result<()> {
+            self.play_decoded(Cursor::new(data), volume)
+        }
+
+        pub(super) fn play_file(&self, path: &Path, volume: f32) -> io::Result<()> {
+            self.play_decoded(BufReader::new(File::open(path)?), volume)
+        }
+
+        fn play_decoded(
+            &self,
+            data: impl Read + Seek + Send + Sync + 'static,
+            volume: f32,
+        ) -> io::Result<()> {
+            let decoder = Decoder::new(data)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            self.0
+                .play_raw(decoder.convert_samples().amplify(volume))
+                .map_err(io::Error::other)
+        }
+    }
+}
+
+#[cfg(not(feature = "audio"))]
+mod device {
+    use std::io;
+    use std::path::Path;
+    use std::time::Duration;
+
+    /// Never constructed, since there is no audio without the `audio` feature.
+    pub(super) enum Device {}
+
+    pub(super) enum Output {}
+
+    pub(super) fn open() -> Result<(Device, Output), String> {
+        Err("teng was built without the `audio` feature".to_string())
+    }
+
+    impl Output {
+        pub(super) fn play_tone(&self, _freq_hz: f32, _duration: Duration, _volume: f32) {
+            match *self {}
+        }
+
+        pub(super) fn play_wav(&self, _data: &'static [u8], _volume: f32) -> io::Result<()> {
+            match *self {}
+        }
+
+        pub(super) fn play_file(&self, _path: &Path, _volume: f32) -> io::Result<()> {
+            match *self {}
+        }
+    }
+}
+
+/// Plays sounds on the output device of an [`AudioComponent`]. Inserted as an extension in the
+/// component's setup, see [`SharedState::ext_expect`].
+///
+/// Sounds are fire-and-forget, and overlapping sounds are mixed. If there is no output device,
+/// the handle is disabled and playing sounds does nothing.
+pub struct AudioHandle {
+    output: Option<device::Output>,
+    master_volume: f32,
+    disabled_reason: Option<String>,
+}
+
+impl AudioHandle {
+    /// Creates a handle that plays nothing, e.g. for tests.
+    pub fn disabled(reason: impl Into<String>) -> Self {
+        Self {
+            output: None,
+            master_volume: 1.0,
+            disabled_reason: Some(reason.into()),
+        }
+    }
+
+    fn new(output: device::Output) -> Self {
+        Self {
+            output: Some(output),
+            master_volume: 1.0,
+            disabled_reason: None,
+        }
+    }
+
+    /// Returns false if sounds are not played.
+    pub fn is_enabled(&self) -> bool {
+        self.output.is_some()
+    }
+
+    /// Returns why sounds are not played, if they are not.
+    pub fn disabled_reason(&self) -> Option<&str> {
+        self.disabled_reason.as_deref()
+    }
+
+    pub fn master_volume(&self) -> f32 {
+        self.master_volume
+    }
+
+    /// Sets the volume of all sounds, where 1 is their normal volume. Negative volumes are
+    /// clamped to 0.
+    ///
+    /// Sounds that are already playing keep their volume.
+    pub fn set_master_volume(&mut self, volume: f32) {
+        self.master_volume = volume.max(0.0);
+    }
+
+    /// Plays a sine tone of `freq_hz` for `duration_ms` milliseconds.
+    pub fn play_tone(&self, freq_hz: f32, duration_ms: u64) {
+        if let Some(output) = &self.output {
+            output.play_tone(
+                freq_hz,
+                Duration::from_millis(duration_ms),
+                self.master_volume,
+            );
+        }
+    }
+
+    /// Plays a WAV file embedded in the binary, e.g. with `include_bytes!`.
+    ///
+    /// Returns an error if the data is not a supported WAV file. A disabled handle does not
+    /// check the data.
+    pub fn play_wav(&self, data: &'static [u8]) -> io::Result<()> {
+        match &self.output {
+            Some(output) => output.play_wav(data, self.master_volume),
+            None => Ok(()),
+        }
+    }
+
+    /// Plays the WAV file at `path`.
+    ///
+    /// Returns an error if the file cannot be read or is not a supported WAV file. A disabled
+    /// handle does not read the file.
+    pub fn play_file(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        match &self.output {
+            Some(output) => output.play_file(path.as_ref(), self.master_volume),
+            None => Ok(()),
+        }
+    }
+
+    fn disable(&mut self, reason: impl Into<String>) {
+        self.output = None;
+        self.disabled_reason = Some(reason.into());
+    }
+}
+
+/// A component that opens the default audio output device and inserts an [`AudioHandle`] to
+/// play sounds on it.
+///
+/// If the device cannot be opened, e.g. over SSH or without the `audio` feature, the game runs
+/// without sound and a warning is shown once.
+pub struct AudioComponent {
+    device: Option<device::Device>,
+    enabled: bool,
+    master_volume: f32,
+}
+
+impl AudioComponent {
+    pub fn new() -> Self {
+        Self {
+            device: None,
+            enabled: true,
+            master_volume: 1.0,
+        }
+    }
+
+    /// Creates a component that does not open a device, and whose handle plays nothing, e.g. for
+    /// a mute option.
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            ..Self::new()
+        }
+    }
+
+    /// Sets the initial master volume, see [`AudioHandle::set_master_volume`].
+    pub fn with_master_volume(mut self, volume: f32) -> Self {
+        self.master_volume = volume;
+        self
+    }
+}
+
+impl Default for AudioComponent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Component<S> for AudioComponent {
+    fn setup(&mut self, _setup_info: &SetupInfo, shared_state: &mut SharedState<S>) {
+        let mut handle = if self.enabled {
+            match device::open() {
+                Ok((device, output)) => {
+                    self.device = Some(device);
+                    AudioHandle::new(output)
+                }
+                Err(reason) => {
+                    shared_state
+                        .debug_messages
+                        .push(DebugMessage::warn_5s(format!("Audio disabled: {reason}")));
+                    AudioHandle::disabled(reason)
+                }
+            }
+        } else {
+            AudioHandle::disabled("the audio component is disabled")
+        };
+        handle.set_master_volume(self.master_volume);
+        shared_state.extensions.insert(handle);
+    }
+
+    fn on_quit(&mut self, shared_state: &mut SharedState<S>) {
+        if let Some(handle) = shared_state.ext_mut::<AudioHandle>() {
+            handle.disable("the game quit");
+        }
+        // stops the audio thread
+        self.device = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    enum Call {
+        Sfx(SfxPlay),
+        Music(String),
+        Duck(f32),
+    }
+
+    /// Records the calls, and refuses sound effects beyond `voices` per update.
+    #[derive(Default)]
+    struct MockBackend {
+        calls: Vec<Call>,
+        voices: Option<usize>,
+    }
+
+    impl AudioBackend for MockBackend {
+        fn play_sfx(&mut self, sfx: &SfxPlay) -> bool {
+            if let Some(voices) = &mut self.voices {
+                if *voices == 0 {
+                    return false;
+                }
+                *voices -= 1;
+            }
+            self.calls.push(Call::Sfx(sfx.clone()));
+            true
+        }
+
+        fn play_music(&mut self, name: &str, _crossfade: Duration) {
+            self.calls.push(Call::Music(name.to_string()));
+        }
+
+        fn stop_music(&mut self, _fade: Duration) {}
+
+        fn duck_music(&mut self, volume: f32, _duration: Duration) {
+            self.calls.push(Call::Duck(volume));
+        }
+    }
+
+    struct HitLanded;
+
+    fn sfx_calls(component: &AudioReactionComponent<MockBackend>) -> Vec<&SfxPlay> {
+        let calls = component.backend().calls.iter();
+        calls
+            .filter_map(|c| match c {
+                Call::Sfx(play) => Some(play),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let mut component = AudioReactionComponent::new(MockBackend::default())
+            .with_reaction_to::<HitLanded>(
+                AudioReaction::new(Sfx::new("hit")).with_rate_limit(10.0),
+            )
+            .with_reaction(
+                "alarm",
+                AudioReaction::new(SoundAction::DuckMusic {
+                    volume: 0.3,
+                    duration: Duration::from_secs(1),
+                }),
+            );
+        let events = component.sender();
+        let start = Instant::now();
+        // one event per 30 ms for 300 ms
+        for frame in 0..10 {
+            events.send_event::<HitLanded>();
+            component.react(start + Duration::from_millis(30 * frame));
+        }
+        // at 0, 120 and 240 ms
+        assert_eq!(sfx_calls(&component).len(), 3);
+        assert_eq!(component.stats().rate_limited, 7);
+
+        events.send("alarm");
+        events.send("unmapped");
+        component.react(start);
+        assert_eq!(component.backend().calls.last(), Some(&Call::Duck(0.3)));
+        assert_eq!(component.stats().events, 12);
+    }
+
+    #[test]
+    fn test_randomization_bounds() {
+        let sfx = Sfx::new("step").with_volume(0.5, 0.7).with_pitch(0.9, 1.1);
+        let run = |seed| {
+            let mut component = AudioReactionComponent::new(MockBackend::default())
+                .with_seed(seed)
+                .with_reaction("step", AudioReaction::new(sfx.clone()));
+            let events = component.sender();
+            for _ in 0..200 {
+                events.send("step");
+                component.react(Instant::now());
+            }
+            sfx_calls(&component)
+                .iter()
+                .map(|play| (play.volume, play.pitch))
+                .collect::<Vec<_>>()
+        };
+        let plays = run(7);
+        assert_eq!(plays.len(), 200);
+        for (volume, pitch) in &plays {
+            assert!((0.5..=0.7).contains(volume), "{volume}");
+            assert!((0.9..=1.1).contains(pitch), "{pitch}");
+        }
+        // the values are spread over the range
+        assert!(plays.iter().any(|(v, _)| *v < 0.55) && plays.iter().any(|(v, _)| *v > 0.65));
+        // and seeded
+        assert_eq!(plays, run(7));
+        assert_ne!(plays, run(8));
+    }
+
+    #[test]
+    fn test_bursts_are_coalesced() {
+        let mut component = AudioReactionComponent::new(MockBackend::default())
+            .with_reaction_to::<HitLanded>(AudioReaction::new(
+                Sfx::new("hit").with_intensity(0.01, 1.5),
+            ))
+            .with_reaction(
+                "music",
+                AudioReaction::new(SoundAction::PlayMusic {
+                    name: "theme".to_string(),
+                    crossfade: Duration::ZERO,
+                }),
+            );
+        let events = component.sender();
+        for _ in 0..200 {
+            events.send_event::<HitLanded>();
+        }
+        events.send("music");
+        events.send("music");
+        component.react(Instant::now());
+        // one louder hit, capped at 1.5, and the music started once
+        assert_eq!(sfx_calls(&component).len(), 1);
+        assert_eq!(sfx_calls(&component)[0].volume, 1.5);
+        let music = component.backend().calls.iter();
+        assert_eq!(music.filter(|c| matches!(c, Call::Music(_))).count(), 1);
+        assert_eq!(component.stats().coalesced, 200);
+
+        events.send_event::<HitLanded>();
+        events.send_event::<HitLanded>();
+        component.react(Instant::now());
+        assert!((sfx_calls(&component)[1].volume - 1.01).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_priorities_get_the_voices() {
+        let mut component = AudioReactionComponent::new(MockBackend {
+            voices: Some(2),
+            ..Default::default()
+        })
+        .with_reaction("a_ambient", AudioReaction::new(Sfx::new("wind")))
+        .with_reaction(
+            "b_step",
+            AudioReaction::new(Sfx::new("step").with_priority(1)),
+        )
+        .with_reaction(
+            "c_alarm",
+            AudioReaction::new(Sfx::new("alarm").with_priority(9)),
+        );
+        let events = component.sender();
+        for key in ["a_ambient", "b_step", "c_alarm"] {
+            events.send(key);
+        }
+        component.react(Instant::now());
+        let names: Vec<_> = sfx_calls(&component)
+            .iter()
+            .map(|p| p.name.clone())
+            .collect();
+        assert_eq!(names, vec!["alarm", "step"]);
+        assert_eq!(component.stats().voice_limited, 1);
+    }
+
+    #[test]
+    fn test_disabled_handle_plays_nothing() {
+        let mut handle = AudioHandle::disabled("muted");
+        assert!(!handle.is_enabled());
+        assert_eq!(handle.disabled_reason(), Some("muted"));
+        handle.play_tone(440.0, 100);
+        assert!(handle.play_wav(b"not a wav file").is_ok());
+        assert!(handle.play_file("does/not/exist.wav").is_ok());
+        handle.set_master_volume(-1.0);
+        assert_eq!(handle.master_volume(), 0.0);
+    }
+
+    #[test]
+    fn test_disabled_component_inserts_a_silent_handle() {
+        let mut game = crate::Game::<Vec<u8>, ()>::new_headless(10, 5);
+        game.add_component(Box::new(AudioComponent::disabled().with_master_volume(0.5)));
+        game.run_frames(2).unwrap();
+        let handle = game.shared_state().ext::<AudioHandle>().unwrap();
+        assert!(!handle.is_enabled());
+        assert_eq!(handle.master_volume(), 0.5);
+        // disabling on purpose is not worth a warning
+        assert_eq!(game.shared_state().debug_messages.len(), 0);
+    }
+
+    /// Without the `audio` feature, opening the device always fails like a missing device.
+    #[cfg(not(feature = "audio"))]
+    #[test]
+    fn test_missing_device_warns_once() {
+        let mut game = crate::Game::<Vec<u8>, ()>::new_headless(10, 5);
+        game.add_component(Box::new(AudioComponent::new()));
+        game.run_frames(3).unwrap();
+        let handle = game.shared_state().ext::<AudioHandle>().unwrap();
+        assert!(!handle.is_enabled());
+        assert!(handle.disabled_reason().unwrap().contains("audio"));
+        assert_eq!(game.shared_state().debug_messages.len(), 1);
+    }
+}
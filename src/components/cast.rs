@@ -0,0 +1,340 @@
+//! Records the terminal output as an [asciinema](https://asciinema.org) v2 `.cast` file.
+//!
+//! Unlike the [`EventRecorderComponent`](crate::components::eventrecorder::EventRecorderComponent),
+//! which records the input, this records what was shown, so that a session can be replayed with
+//! `asciinema play` or embedded on a website. The game writes through a [`CastSink`], which passes
+//! the output on to its inner sink and, while recording, also appends every flush to the file:
+//! ```no_run
+//! use teng::components::cast::{CastRecorderComponent, CastSink};
+//! use teng::{CustomBufWriter, Game};
+//!
+//! let sink = CastSink::new(CustomBufWriter::new());
+//! let recorder = CastRecorderComponent::new(sink.handle());
+//! let mut game: Game<_, ()> = Game::new(sink);
+//! game.add_component(Box::new(recorder));
+//! ```
+//! The [`CastRecorderComponent`] starts and stops recording with F9, and finishes the file when
+//! the game quits. Recordings start with a full redraw of the screen, and resizes are recorded as
+//! resize events.
+
+use crate::components::Dependency;
+use crate::components::debuginfo::DebugMessage;
+use crate::components::keyboard::KeyPressRecorderComponent;
+use crate::{Component, SetupInfo, SharedState, UpdateInfo};
+use crossterm::event::KeyCode;
+use std::cell::RefCell;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Instant, SystemTime};
+
+/// An active recording.
+struct CastFile {
+    path: PathBuf,
+    file: BufWriter<File>,
+    start: Instant,
+}
+
+impl CastFile {
+    fn create(path: &Path, width: usize, height: usize) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = BufWriter::new(File::create(path)?);
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        writeln!(
+            file,
+            r#"{{"version": 2, "width": {width}, "height": {height}, "timestamp": {timestamp}}}"#
+        )?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            start: Instant::now(),
+        })
+    }
+
+    /// Appends an event of `kind`, e.g. `"o"` for output, with the current time.
+    fn event(&mut self, kind: &str, data: &str) -> io::Result<()> {
+        let time = self.start.elapsed().as_secs_f64();
+        writeln!(
+            self.file,
+            "[{time:.6}, \"{kind}\", \"{}\"]",
+            json_escape(data)
+        )
+    }
+}
+
+/// Shared access to the recording of a [`CastSink`], e.g. for the [`CastRecorderComponent`].
+#[derive(Clone, Default)]
+pub struct CastHandle(Rc<RefCell<Option<CastFile>>>);
+
+impl CastHandle {
+    /// Starts recording to `path` on a `width` x `height` terminal, finishing the previous
+    /// recording, if any.
+    pub fn start(&self, path: impl AsRef<Path>, width: usize, height: usize) -> io::Result<()> {
+        self.stop()?;
+        *self.0.borrow_mut() = Some(CastFile::create(path.as_ref(), width, height)?);
+        Ok(())
+    }
+
+    /// Finishes the recording and returns its path, or `None` if there was no recording.
+    pub fn stop(&self) -> io::Result<Option<PathBuf>> {
+        let Some(mut cast) = self.0.borrow_mut().take() else {
+            return Ok(None);
+        };
+        cast.file.flush()?;
+        Ok(Some(cast.path))
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.0.borrow().is_some()
+    }
+
+    /// Records that the terminal was resized.
+    pub fn resize(&self, width: usize, height: usize) -> io::Result<()> {
+        match self.0.borrow_mut().as_mut() {
+            Some(cast) => cast.event("r", &format!("{width}x{height}")),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A sink that passes everything to its inner sink, and records the output while a recording
+/// is active.
+pub struct CastSink<W: Write> {
+    inner: W,
+    /// The output since the last flush.
+    buf: Vec<u8>,
+    handle: CastHandle,
+}
+
+impl<W: Write> CastSink<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            buf: vec![],
+            handle: CastHandle::default(),
+        }
+    }
+
+    /// Returns a handle to start and stop recording.
+    pub fn handle(&self) -> CastHandle {
+        self.handle.clone()
+    }
+}
+
+impl<W: Write> Write for CastSink<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        if self.handle.is_recording() {
+            self.buf.extend_from_slice(&buf[..written]);
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()?;
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        if let Some(cast) = self.handle.0.borrow_mut().as_mut() {
+            cast.event("o", &String::from_utf8_lossy(&self.buf))?;
+        }
+        self.buf.clear();
+        Ok(())
+    }
+}
+
+/// Returns `text` escaped for a JSON string.
+fn json_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// A component that starts and stops recording a [`CastSink`].
+pub struct CastRecorderComponent {
+    handle: CastHandle,
+    key: KeyCode,
+    directory: PathBuf,
+    display_size: (usize, usize),
+}
+
+impl CastRecorderComponent {
+    /// Creates a component that records to `recordings/` when F9 is pressed.
+    pub fn new(handle: CastHandle) -> Self {
+        Self {
+            handle,
+            key: KeyCode::F(9),
+            directory: PathBuf::from("recordings"),
+            display_size: (0, 0),
+        }
+    }
+
+    /// Sets the key that starts and stops recording.
+    pub fn with_key(mut self, key: KeyCode) -> Self {
+        self.key = key;
+        self
+    }
+
+    /// Sets the directory the recordings are saved to. It is created if it does not exist.
+    pub fn with_directory(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.directory = directory.into();
+        self
+    }
+
+    /// Starts a new recording, or stops the current one.
+    pub fn toggle<S>(&mut self, shared_state: &mut SharedState<S>) {
+        let message = if self.handle.is_recording() {
+            match self.handle.stop() {
+                Ok(path) => DebugMessage::new_3s(format!(
+                    "Saved terminal recording to {}",
+                    path.unwrap_or_default().display()
+                )),
+                Err(e) => DebugMessage::error_5s(format!("Failed to save terminal recording: {e}")),
+            }
+        } else {
+            let timestamp = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            let path = self.directory.join(format!("session-{timestamp}.cast"));
+            let (width, height) = self.display_size;
+            match self.handle.start(&path, width, height) {
+                Ok(()) => {
+                    // the recording needs the whole screen, not just the cells that change
+                    shared_state.redraw_requested = true;
+                    DebugMessage::new_3s("Terminal recording started")
+                }
+                Err(e) => {
+                    DebugMessage::error_5s(format!("Failed to start terminal recording: {e}"))
+                }
+            }
+        };
+        shared_state.debug_messages.push(message);
+    }
+}
+
+impl<S> Component<S> for CastRecorderComponent {
+    fn setup(&mut self, setup_info: &SetupInfo, _shared_state: &mut SharedState<S>) {
+        self.display_size = (
+            setup_info.display_info.width(),
+            setup_info.display_info.height(),
+        );
+    }
+
+    fn on_resize(&mut self, width: usize, height: usize, shared_state: &mut SharedState<S>) {
+        self.display_size = (width, height);
+        if let Err(e) = self.handle.resize(width, height) {
+            shared_state
+                .debug_messages
+                .push(DebugMessage::error_5s(format!(
+                    "Failed to record resize: {e}"
+                )));
+        }
+    }
+
+    fn on_quit(&mut self, _shared_state: &mut SharedState<S>) {
+        // the debug message would not be shown anymore
+        let _ = self.handle.stop();
+    }
+
+    fn runs_while_paused(&self) -> bool {
+        true
+    }
+
+    fn required_components(&self) -> Vec<Dependency> {
+        vec![Dependency::of::<KeyPressRecorderComponent>()]
+    }
+
+    fn update(&mut self, _update_info: UpdateInfo, shared_state: &mut SharedState<S>) {
+        if shared_state.pressed_keys.did_press(self.key) {
+            self.toggle(shared_state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Game;
+    use crate::components::fncomponent::FnComponent;
+    use crate::rendering::pixel::Pixel;
+    use crossterm::event::{Event, KeyEvent};
+
+    #[test]
+    fn test_json_escape() {
+        assert_eq!(
+            json_escape("a\"b\\c\n\x1b[0m ✓"),
+            "a\\\"b\\\\c\\n\\u001b[0m ✓"
+        );
+    }
+
+    #[test]
+    fn test_cast_lines_parse() {
+        let dir = std::env::temp_dir().join(format!("teng-cast-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let sink = CastSink::new(vec![]);
+        let handle = sink.handle();
+        let mut game = Game::<_, ()>::new_headless_with_sink(4, 2, sink);
+        game.add_component(Box::new(KeyPressRecorderComponent::new()));
+        game.add_component(Box::new(
+            CastRecorderComponent::new(handle.clone()).with_directory(&dir),
+        ));
+        game.add_component(Box::new(FnComponent::new().with_render(
+            |renderer, _, _| {
+                renderer.render_pixel(0, 0, Pixel::new('"'), 0);
+            },
+        )));
+        game.run_frames(1).unwrap();
+        assert!(!handle.is_recording());
+
+        game.push_event(Event::Key(KeyEvent::from(KeyCode::F(9))));
+        game.run_frames(2).unwrap();
+        assert!(handle.is_recording());
+        game.push_event(Event::Resize(5, 3));
+        game.run_frames(1).unwrap();
+        let path = handle.stop().unwrap().unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let lines = text
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(lines[0]["version"], 2);
+        assert_eq!(
+            (lines[0]["width"].as_u64(), lines[0]["height"].as_u64()),
+            (Some(4), Some(2))
+        );
+        let events = &lines[1..];
+        assert!(
+            events
+                .iter()
+                .all(|event| event.as_array().unwrap().len() == 3)
+        );
+        // the first frame of the recording redraws the whole screen
+        assert_eq!(events[0][1], "o");
+        assert!(events[0][2].as_str().unwrap().contains('"'));
+        assert!(
+            events
+                .iter()
+                .any(|event| event[1] == "r" && event[2] == "5x3")
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,546 @@
+//! A cheat menu for debugging, instead of cheat keys scattered through `update`.
+//!
+//! Games add their cheats to the [`CheatMenuComponent`]. Cheats act on the
+//! [`SharedState`], usually on the game's state in [`SharedState::custom`]:
+//! ```rust
+//! use teng::components::cheats::CheatMenuComponent;
+//!
+//! #[derive(Default)]
+//! struct GameState {
+//!     blocks: i64,
+//!     god_mode: bool,
+//! }
+//!
+//! let cheats = CheatMenuComponent::<GameState>::new()
+//!     .with_amount("Grant blocks", 1_000_000, |shared_state, amount| {
+//!         shared_state.custom.blocks += amount;
+//!     })
+//!     .with_toggle(
+//!         "God mode",
+//!         |shared_state| shared_state.custom.god_mode,
+//!         |shared_state, on| shared_state.custom.god_mode = on,
+//!     );
+//! ```
+//! Press ctrl+shift+d to open the menu. While it is open, the game is paused.
+//! * Up/Down: select a cheat
+//! * Enter: run the selected cheat, toggle it, or enter an amount and confirm it
+//! * Esc: cancel the amount, or close the menu
+//!
+//! The menu only opens if the `debug.cheats` setting is on, which is the default in debug builds.
+//!
+//! Every use of a cheat is shown as a debug message and recorded in the [`CheatLog`] extension,
+//! which marks the session as cheated. Games persist the flag in their saves, e.g. with
+//! [`SlotMetadata::with_cheated`](crate::util::saveslots::SlotMetadata::with_cheated), and
+//! restore it with [`CheatLog::set_cheated`] when loading, so that a cheated run stays marked.
+
+use crate::components::debuginfo::DebugMessage;
+use crate::components::dim::DimBehindComponent;
+use crate::components::keyboard::KeyPressRecorderComponent;
+use crate::components::settings::{Setting, Settings};
+use crate::rendering::render::Render;
+use crate::rendering::renderer::Renderer;
+use crate::{BreakingAction, Component, ComponentFilter, SetupInfo, SharedState};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use std::any::TypeId;
+use std::collections::HashSet;
+
+/// The cheats used in a session, kept as an extension of the [`SharedState`] that the
+/// [`CheatMenuComponent`] inserts in its setup. Read it with `shared_state.ext::<CheatLog>()`.
+#[derive(Debug, Default)]
+pub struct CheatLog {
+    cheated: bool,
+    used: Vec<String>,
+}
+
+impl CheatLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns true if a cheat was used in this session, or in the session of a loaded save.
+    pub fn is_cheated(&self) -> bool {
+        self.cheated
+    }
+
+    /// Marks the session as cheated or not, e.g. from a loaded save.
+    pub fn set_cheated(&mut self, cheated: bool) {
+        self.cheated = cheated;
+    }
+
+    /// Records the use of a cheat and marks the session as cheated.
+    pub fn record(&mut self, description: impl Into<String>) {
+        self.cheated = true;
+        self.used.push(description.into());
+    }
+
+    /// Returns the descriptions of the cheats used in this session, oldest first.
+    pub fn used(&self) -> &[String] {
+        &self.used
+    }
+}
+
+enum CheatKind<S> {
+    Action(Action<S>),
+    Amount {
+        default: i64,
+        apply: AmountAction<S>,
+    },
+    Toggle {
+        get: Getter<S>,
+        set: Setter<S>,
+    },
+}
+
+type Action<S> = Box<dyn FnMut(&mut SharedState<S>)>;
+type AmountAction<S> = Box<dyn FnMut(&mut SharedState<S>, i64)>;
+type Getter<S> = Box<dyn Fn(&SharedState<S>) -> bool>;
+type Setter<S> = Box<dyn FnMut(&mut SharedState<S>, bool)>;
+
+/// A cheat in the [`CheatMenuComponent`].
+struct Cheat<S> {
+    name: String,
+    kind: CheatKind<S>,
+}
+
+impl<S> Cheat<S> {
+    /// Runs the cheat, with the amount for amount cheats, and returns a description of the use.
+    fn run(&mut self, shared_state: &mut SharedState<S>, amount: Option<i64>) -> String {
+        match &mut self.kind {
+            CheatKind::Action(action) => {
+                action(shared_state);
+                self.name.clone()
+            }
+            CheatKind::Amount { default, apply } => {
+                let amount = amount.unwrap_or(*default);
+                apply(shared_state, amount);
+                format!("{} ({amount})", self.name)
+            }
+            CheatKind::Toggle { get, set } => {
+                let on = !get(shared_state);
+                set(shared_state, on);
+                format!("{} ({})", self.name, if on { "on" } else { "off" })
+            }
+        }
+    }
+}
+
+struct Menu {
+    selected: usize,
+    /// The amount being entered for the selected cheat.
+    entry: Option<String>,
+}
+
+/// A menu of cheats for debugging.
+pub struct CheatMenuComponent<S = ()> {
+    cheats: Vec<Cheat<S>>,
+    chord: (KeyCode, KeyModifiers),
+    menu: Option<Menu>,
+}
+
+impl<S> CheatMenuComponent<S> {
+    /// The setting that allows opening the menu. On by default in debug builds.
+    pub const ENABLED_SETTING: &'static str = "debug.cheats";
+
+    pub fn new() -> Self {
+        Self {
+            cheats: vec![],
+            chord: (
+                KeyCode::Char('d'),
+                KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+            ),
+            menu: None,
+        }
+    }
+
+    /// Adds a cheat that runs `action` when selected, e.g. to unlock all upgrades.
+    pub fn with_action(
+        mut self,
+        name: impl Into<String>,
+        action: impl FnMut(&mut SharedState<S>) + 'static,
+    ) -> Self {
+        self.cheats.push(Cheat {
+            name: name.into(),
+            kind: CheatKind::Action(Box::new(action)),
+        });
+        self
+    }
+
+    /// Adds a cheat that asks for an amount, prefilled with `default`, and calls `apply` with it.
+    pub fn with_amount(
+        mut self,
+        name: impl Into<String>,
+        default: i64,
+        apply: impl FnMut(&mut SharedState<S>, i64) + 'static,
+    ) -> Self {
+        self.cheats.push(Cheat {
+            name: name.into(),
+            kind: CheatKind::Amount {
+                default,
+                apply: Box::new(apply),
+            },
+        });
+        self
+    }
+
+    /// Adds a cheat that is on or off, e.g. god mode. The menu shows the value from `get`, and
+    /// selecting the cheat calls `set` with the opposite.
+    pub fn with_toggle(
+        mut self,
+        name: impl Into<String>,
+        get: impl Fn(&SharedState<S>) -> bool + 'static,
+        set: impl FnMut(&mut SharedState<S>, bool) + 'static,
+    ) -> Self {
+        self.cheats.push(Cheat {
+            name: name.into(),
+            kind: CheatKind::Toggle {
+                get: Box::new(get),
+                set: Box::new(set),
+            },
+        });
+        self
+    }
+
+    /// Sets the key and modifiers that open and close the menu. The default is ctrl+shift+d.
+    pub fn with_chord(mut self, code: KeyCode, modifiers: KeyModifiers) -> Self {
+        self.chord = (code, modifiers);
+        self
+    }
+
+    pub fn is_menu_open(&self) -> bool {
+        self.menu.is_some()
+    }
+
+    fn is_chord(&self, key: &KeyEvent) -> bool {
+        let (code, modifiers) = self.chord;
+        // with shift, terminals report the uppercase letter
+        let same_code = match (key.code, code) {
+            (KeyCode::Char(a), KeyCode::Char(b)) => a.eq_ignore_ascii_case(&b),
+            (a, b) => a == b,
+        };
+        same_code && key.modifiers.contains(modifiers)
+    }
+
+    fn close_menu(&mut self, shared_state: &mut SharedState<S>) {
+        if self.menu.take().is_some() {
+            shared_state.pop_component_filter();
+        }
+    }
+
+    fn run_selected(&mut self, shared_state: &mut SharedState<S>, amount: Option<i64>) {
+        let Some(menu) = &self.menu else {
+            return;
+        };
+        let Some(cheat) = self.cheats.get_mut(menu.selected) else {
+            return;
+        };
+        let description = cheat.run(shared_state, amount);
+        shared_state
+            .debug_messages
+            .push(DebugMessage::new_3s(format!("Cheat used: {description}")));
+        shared_state
+            .ext_or_default::<CheatLog>()
+            .record(description);
+    }
+
+    fn handle_menu_key(&mut self, key: KeyEvent, shared_state: &mut SharedState<S>) {
+        let Some(menu) = &mut self.menu else {
+            return;
+        };
+        if let Some(entry) = &mut menu.entry {
+            match key.code {
+                KeyCode::Char(c @ '0'..='9') => entry.push(c),
+                KeyCode::Char('-') if entry.is_empty() => entry.push('-'),
+                KeyCode::Backspace => {
+                    entry.pop();
+                }
+                KeyCode::Esc => menu.entry = None,
+                KeyCode::Enter => {
+                    // an empty or invalid entry keeps the menu open for another try
+                    if let Ok(amount) = entry.parse::<i64>() {
+                        menu.entry = None;
+                        self.run_selected(shared_state, Some(amount));
+                    }
+                }
+                _ => {}
+            }
+            return;
+        }
+        let len = self.cheats.len();
+        match key.code {
+            KeyCode::Esc => self.close_menu(shared_state),
+            KeyCode::Up if len > 0 => menu.selected = (menu.selected + len - 1) % len,
+            KeyCode::Down if len > 0 => menu.selected = (menu.selected + 1) % len,
+            KeyCode::Enter => match self.cheats.get(menu.selected).map(|c| &c.kind) {
+                Some(CheatKind::Amount { default, .. }) => menu.entry = Some(default.to_string()),
+                Some(_) => self.run_selected(shared_state, None),
+                None => {}
+            },
+            _ => {}
+        }
+    }
+}
+
+impl<S> Default for CheatMenuComponent<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: 'static> CheatMenuComponent<S> {
+    /// Opens the menu and pauses all other components except the input recorder.
+    pub fn open_menu(&mut self, shared_state: &mut SharedState<S>) {
+        if self.menu.is_some() {
+            return;
+        }
+        let whitelist = HashSet::from([
+            TypeId::of::<Self>(),
+            TypeId::of::<KeyPressRecorderComponent>(),
+            TypeId::of::<DimBehindComponent>(),
+        ]);
+        shared_state.push_component_filter(ComponentFilter::Whitelist(whitelist));
+        self.menu = Some(Menu {
+            selected: 0,
+            entry: None,
+        });
+    }
+}
+
+impl<S: 'static> Component<S> for CheatMenuComponent<S> {
+    fn runs_while_paused(&self) -> bool {
+        true
+    }
+
+    fn setup(&mut self, _setup_info: &SetupInfo, shared_state: &mut SharedState<S>) {
+        // a log restored from a save before the setup is kept
+        shared_state.ext_or_default::<CheatLog>();
+        shared_state.ext_or_default::<Settings<S>>().register(
+            Setting::bool(Self::ENABLED_SETTING, cfg!(debug_assertions))
+                .with_label("Cheat menu")
+                .with_category("Debug"),
+        );
+    }
+
+    fn on_event(
+        &mut self,
+        event: Event,
+        shared_state: &mut SharedState<S>,
+    ) -> Option<BreakingAction> {
+        let Event::Key(key) = event else {
+            return None;
+        };
+        if key.kind != KeyEventKind::Press {
+            return None;
+        }
+        let in_entry = self.menu.as_ref().is_some_and(|menu| menu.entry.is_some());
+        if self.is_chord(&key) && !in_entry {
+            if self.menu.is_some() {
+                self.close_menu(shared_state);
+            } else if shared_state
+                .ext_or_default::<Settings<S>>()
+                .get_bool(Self::ENABLED_SETTING)
+                .unwrap_or(false)
+            {
+                self.open_menu(shared_state);
+            }
+            return None;
+        }
+        self.handle_menu_key(key, shared_state);
+        None
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, shared_state: &SharedState<S>, _depth_base: i32) {
+        let Some(menu) = &self.menu else {
+            return;
+        };
+        let mut lines = vec![("Cheats".to_string(), false), (String::new(), false)];
+        if self.cheats.is_empty() {
+            lines.push(("No cheats".to_string(), false));
+        }
+        for (idx, cheat) in self.cheats.iter().enumerate() {
+            let selected = idx == menu.selected;
+            let value = match &cheat.kind {
+                CheatKind::Action(_) => String::new(),
+                CheatKind::Amount { default, .. } => match &menu.entry {
+                    Some(entry) if selected => format!("{entry}_"),
+                    _ => default.to_string(),
+                },
+                CheatKind::Toggle { get, .. } => {
+                    if get(shared_state) { "on" } else { "off" }.to_string()
+                }
+            };
+            let cursor = if selected { '>' } else { ' ' };
+            lines.push((format!("{cursor} {:<24} {value}", cheat.name), selected));
+        }
+        lines.push((String::new(), false));
+        let help = if menu.entry.is_some() {
+            "Type an amount, Enter: confirm, Esc: cancel"
+        } else {
+            "Up/Down: select, Enter: use, Esc: close"
+        };
+        lines.push((help.to_string(), false));
+
+        let width = lines
+            .iter()
+            .map(|(l, _)| l.chars().count())
+            .max()
+            .unwrap_or(0)
+            + 2;
+        for (y, (line, selected)) in lines.iter().enumerate() {
+            let bg_color = if *selected {
+                [90, 40, 40]
+            } else {
+                [30, 30, 30]
+            };
+            format!(" {line:<width$}", width = width - 1)
+                .with_bg_color(bg_color)
+                .render(renderer, 1, y + 1, i32::MAX - 90);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Game;
+    use crate::components::settings::SettingValue;
+
+    #[derive(Default)]
+    struct GameState {
+        blocks: i64,
+        upgrades_unlocked: bool,
+        phase: i64,
+        player: (i64, i64),
+        god_mode: bool,
+        ghosts: i64,
+    }
+
+    fn cheats() -> CheatMenuComponent<GameState> {
+        CheatMenuComponent::<GameState>::new()
+            .with_amount("Grant blocks", 1_000_000, |s, n| s.custom.blocks += n)
+            .with_action("Unlock all upgrades", |s| s.custom.upgrades_unlocked = true)
+            .with_amount("Set phase", 1, |s, n| s.custom.phase = n)
+            .with_action("Teleport to spawn", |s| s.custom.player = (0, 0))
+            .with_toggle(
+                "God mode",
+                |s| s.custom.god_mode,
+                |s, on| s.custom.god_mode = on,
+            )
+            .with_amount("Set ghost count", 0, |s, n| s.custom.ghosts = n)
+    }
+
+    fn press(game: &mut Game<Vec<u8>, GameState>, keys: &[KeyCode]) {
+        for &code in keys {
+            game.push_event(Event::Key(KeyEvent::new(code, KeyModifiers::NONE)));
+        }
+        game.run_frames(1).unwrap();
+    }
+
+    fn open(game: &mut Game<Vec<u8>, GameState>) {
+        let chord = KeyEvent::new(
+            KeyCode::Char('D'),
+            KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+        );
+        game.push_event(Event::Key(chord));
+        game.run_frames(1).unwrap();
+    }
+
+    fn is_menu_open(game: &Game<Vec<u8>, GameState>) -> bool {
+        game.shared_state().component_filter().is_some()
+    }
+
+    fn game() -> Game<Vec<u8>, GameState> {
+        let mut game = Game::<Vec<u8>, GameState>::new_headless(80, 24);
+        game.shared_state_mut().custom.player = (5, 7);
+        game.add_component(Box::new(cheats()));
+        // settings are registered during setup
+        game.run_frames(1).unwrap();
+        game.shared_state_mut()
+            .ext_or_default::<Settings<GameState>>()
+            .set(
+                CheatMenuComponent::<GameState>::ENABLED_SETTING,
+                SettingValue::Bool(true),
+            );
+        game
+    }
+
+    #[test]
+    fn test_cheats() {
+        use KeyCode::*;
+        let mut game = game();
+        // plain d does not open the menu
+        press(&mut game, &[Char('d')]);
+        assert!(!is_menu_open(&game));
+        open(&mut game);
+        assert!(is_menu_open(&game));
+
+        // the prefilled amount, then a typed one
+        press(&mut game, &[Enter, Enter]);
+        assert_eq!(game.shared_state().custom.blocks, 1_000_000);
+        press(
+            &mut game,
+            &[
+                Enter, Backspace, Backspace, Backspace, Backspace, Backspace, Backspace, Backspace,
+            ],
+        );
+        press(&mut game, &[Char('4'), Char('2'), Enter]);
+        assert_eq!(game.shared_state().custom.blocks, 1_000_042);
+        // an abandoned entry changes nothing
+        press(&mut game, &[Enter, Char('9'), Esc]);
+        assert_eq!(game.shared_state().custom.blocks, 1_000_042);
+        assert!(is_menu_open(&game));
+
+        press(&mut game, &[Down, Enter]);
+        assert!(game.shared_state().custom.upgrades_unlocked);
+        press(&mut game, &[Down, Enter, Backspace, Char('3'), Enter]);
+        assert_eq!(game.shared_state().custom.phase, 3);
+        press(&mut game, &[Down, Enter]);
+        assert_eq!(game.shared_state().custom.player, (0, 0));
+        press(&mut game, &[Down, Enter]);
+        assert!(game.shared_state().custom.god_mode);
+        press(&mut game, &[Enter]);
+        assert!(!game.shared_state().custom.god_mode);
+        press(
+            &mut game,
+            &[Down, Enter, Backspace, Char('-'), Char('2'), Enter],
+        );
+        assert_eq!(game.shared_state().custom.ghosts, -2);
+
+        let log = game.shared_state().ext::<CheatLog>().unwrap();
+        assert!(log.is_cheated());
+        assert_eq!(
+            log.used(),
+            [
+                "Grant blocks (1000000)",
+                "Grant blocks (42)",
+                "Unlock all upgrades",
+                "Set phase (3)",
+                "Teleport to spawn",
+                "God mode (on)",
+                "God mode (off)",
+                "Set ghost count (-2)",
+            ]
+        );
+        assert_eq!(game.shared_state().debug_messages.len(), 8);
+
+        // wraps around to the first cheat
+        press(&mut game, &[Down, Enter, Enter]);
+        assert_eq!(game.shared_state().custom.blocks, 2_000_042);
+
+        press(&mut game, &[Esc]);
+        assert!(!is_menu_open(&game));
+    }
+
+    #[test]
+    fn test_disabled() {
+        let mut game = game();
+        game.shared_state_mut()
+            .ext_or_default::<Settings<GameState>>()
+            .set(
+                CheatMenuComponent::<GameState>::ENABLED_SETTING,
+                SettingValue::Bool(false),
+            );
+        open(&mut game);
+        assert!(!is_menu_open(&game));
+        assert!(!game.shared_state().ext::<CheatLog>().unwrap().is_cheated());
+    }
+}
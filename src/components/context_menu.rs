@@ -0,0 +1,624 @@
+//! Right-click context menus.
+//!
+//! Any component can open a menu through the [`ContextMenu`] extension, e.g. when it sees a
+//! right click in its `update`:
+//! ```rust
+//! use teng::SharedState;
+//! use teng::components::context_menu::{ContextMenu, MenuItem};
+//!
+//! fn update(shared_state: &mut SharedState) {
+//!     if shared_state.mouse_pressed.right {
+//!         let (x, y) = shared_state.mouse_info.last_mouse_pos;
+//!         shared_state.ext_or_default::<ContextMenu>().open_at(
+//!             x,
+//!             y,
+//!             vec![
+//!                 MenuItem::action("Copy", "copy"),
+//!                 MenuItem::separator(),
+//!                 MenuItem::submenu(
+//!                     "Export",
+//!                     vec![MenuItem::action("PNG", "export-png")],
+//!                 ),
+//!             ],
+//!         );
+//!     }
+//!     if let Some(tag) = shared_state.ext_or_default::<ContextMenu>().take_result() {
+//!         // handle the chosen item...
+//!     }
+//! }
+//! ```
+//! The [`ContextMenuComponent`] renders the open menu with its top left corner at the requested
+//! position, or flipped to the left and above if it would not fit on the screen otherwise.
+//! Submenus open next to their item when it is hovered.
+//!
+//! While a menu is open, it captures all input: mouse buttons, scrolling and key presses do not
+//! reach the other components. Clicking an action item closes the menu and stores the item's tag
+//! as the result, clicking outside of the menu or pressing Esc closes it without a result.
+//! The keyboard can be used as well:
+//! * Up/Down: select an item
+//! * Right or Enter: open the selected submenu
+//! * Left: close the innermost submenu
+//! * Enter: choose the selected action
+//!
+//! Only one menu is open at a time, opening a new one replaces the previous one.
+//!
+//! For the input capturing to work, the [`ContextMenuComponent`] must be added before all
+//! components that handle input, including the ones from
+//! [`Game::install_recommended_components`](crate::Game::install_recommended_components).
+
+use crate::rendering::render::Render;
+use crate::rendering::renderer::Renderer;
+use crate::{BreakingAction, Component, SetupInfo, SharedState};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEventKind};
+use std::collections::HashSet;
+
+/// An entry of a context menu.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MenuItem {
+    /// An item that closes the menu and returns `tag` as the result when chosen.
+    Action { label: String, tag: String },
+    /// A horizontal line between items.
+    Separator,
+    /// An item that opens a nested menu.
+    Submenu { label: String, items: Vec<MenuItem> },
+}
+
+impl MenuItem {
+    pub fn action(label: impl Into<String>, tag: impl Into<String>) -> Self {
+        Self::Action {
+            label: label.into(),
+            tag: tag.into(),
+        }
+    }
+
+    pub fn separator() -> Self {
+        Self::Separator
+    }
+
+    pub fn submenu(label: impl Into<String>, items: Vec<MenuItem>) -> Self {
+        Self::Submenu {
+            label: label.into(),
+            items,
+        }
+    }
+
+    fn label(&self) -> &str {
+        match self {
+            MenuItem::Action { label, .. } | MenuItem::Submenu { label, .. } => label,
+            MenuItem::Separator => "",
+        }
+    }
+
+    fn is_selectable(&self) -> bool {
+        !matches!(self, MenuItem::Separator)
+    }
+}
+
+/// The screen area of one menu level, including its border.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct MenuRect {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+impl MenuRect {
+    fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+
+    /// Returns the index of the item at `(x, y)`, if any.
+    fn item_at(&self, x: usize, y: usize) -> Option<usize> {
+        if !self.contains(x, y) || y == self.y || y == self.y + self.height - 1 {
+            return None;
+        }
+        Some(y - self.y - 1)
+    }
+}
+
+/// Returns the size of a menu with `items`, including its border.
+fn menu_size(items: &[MenuItem]) -> (usize, usize) {
+    let label_width = items
+        .iter()
+        .map(|item| item.label().chars().count())
+        .max()
+        .unwrap_or(0);
+    // border, space, label, space, submenu arrow, space, border
+    (label_width + 6, items.len() + 2)
+}
+
+/// Returns the start of a span of `size` cells that should touch `anchor` and fit into `screen`.
+///
+/// The span starts at `anchor` if it fits, otherwise it ends at `flipped_end` (exclusive).
+/// If neither fits, the span is clamped to the screen.
+fn place_span(anchor: usize, flipped_end: usize, size: usize, screen: usize) -> usize {
+    let start = if anchor + size <= screen {
+        anchor
+    } else {
+        flipped_end.saturating_sub(size)
+    };
+    start.min(screen.saturating_sub(size))
+}
+
+struct Level {
+    items: Vec<MenuItem>,
+    hovered: Option<usize>,
+}
+
+/// The open context menu, if any, and the result of the last one.
+///
+/// Kept as an extension of the [`SharedState`] that the [`ContextMenuComponent`] inserts in its
+/// setup.
+#[derive(Default)]
+pub struct ContextMenu {
+    anchor: (usize, usize),
+    // the root menu followed by the open submenus
+    levels: Vec<Level>,
+    result: Option<String>,
+}
+
+impl ContextMenu {
+    /// Opens a menu at the screen position `(x, y)`, closing any open menu.
+    ///
+    /// Clears a result that has not been taken yet.
+    pub fn open_at(&mut self, x: usize, y: usize, items: Vec<MenuItem>) {
+        self.anchor = (x, y);
+        self.levels = vec![Level {
+            items,
+            hovered: None,
+        }];
+        self.result = None;
+    }
+
+    /// Closes the menu without a result.
+    pub fn close(&mut self) {
+        self.levels.clear();
+    }
+
+    pub fn is_open(&self) -> bool {
+        !self.levels.is_empty()
+    }
+
+    /// Returns the tag of the chosen item, if the last menu was closed by choosing one.
+    pub fn result(&self) -> Option<&str> {
+        self.result.as_deref()
+    }
+
+    /// Returns and clears the tag of the chosen item.
+    pub fn take_result(&mut self) -> Option<String> {
+        self.result.take()
+    }
+
+    /// Returns the screen areas of all open levels for a screen of size `screen`.
+    fn level_rects(&self, screen: (usize, usize)) -> Vec<MenuRect> {
+        let mut rects: Vec<MenuRect> = vec![];
+        for level in &self.levels {
+            let (width, height) = menu_size(&level.items);
+            let (x, y) = match rects.last() {
+                None => {
+                    let (x, y) = self.anchor;
+                    (
+                        place_span(x, x + 1, width, screen.0),
+                        place_span(y, y + 1, height, screen.1),
+                    )
+                }
+                Some(parent) => {
+                    // a submenu is open only while its item is hovered
+                    let parent_level = &self.levels[rects.len() - 1];
+                    let row = parent.y + 1 + parent_level.hovered.unwrap_or(0);
+                    (
+                        place_span(parent.x + parent.width, parent.x, width, screen.0),
+                        // align the first item with the parent item, or the last one if flipped
+                        place_span(row.saturating_sub(1), row + 2, height, screen.1),
+                    )
+                }
+            };
+            rects.push(MenuRect {
+                x,
+                y,
+                width,
+                height,
+            });
+        }
+        rects
+    }
+
+    /// Returns the innermost level and item at `(x, y)`. The item is `None` on borders.
+    fn hit(&self, x: usize, y: usize, screen: (usize, usize)) -> Option<(usize, Option<usize>)> {
+        let rects = self.level_rects(screen);
+        // later levels are rendered on top
+        rects
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, rect)| rect.contains(x, y))
+            .map(|(level, rect)| (level, rect.item_at(x, y)))
+    }
+
+    /// Hovers `item` of `level`, closing deeper levels and opening its submenu, if any.
+    fn hover(&mut self, level: usize, item: Option<usize>) {
+        let unchanged = self.levels[level].hovered == item;
+        if unchanged && (self.levels.len() > level + 1 || item.is_none()) {
+            return;
+        }
+        self.levels.truncate(level + 1);
+        self.levels[level].hovered = item.filter(|&i| self.levels[level].items[i].is_selectable());
+        self.open_hovered_submenu(false);
+    }
+
+    /// Opens the submenu of the hovered item of the innermost level, if it is one.
+    fn open_hovered_submenu(&mut self, select_first: bool) {
+        let level = self.levels.last().unwrap();
+        let Some(MenuItem::Submenu { items, .. }) = level.hovered.map(|i| &level.items[i]) else {
+            return;
+        };
+        let hovered = if select_first {
+            items.iter().position(MenuItem::is_selectable)
+        } else {
+            None
+        };
+        self.levels.push(Level {
+            items: items.clone(),
+            hovered,
+        });
+    }
+
+    /// Chooses the hovered item of the innermost level.
+    fn activate(&mut self) {
+        let level = self.levels.last().unwrap();
+        match level.hovered.map(|i| &level.items[i]) {
+            Some(MenuItem::Action { tag, .. }) => {
+                self.result = Some(tag.clone());
+                self.close();
+            }
+            Some(MenuItem::Submenu { .. }) => self.open_hovered_submenu(true),
+            _ => {}
+        }
+    }
+
+    /// Moves the hover of the innermost level to the next selectable item in `direction`.
+    fn move_hover(&mut self, direction: isize) {
+        let level = self.levels.last_mut().unwrap();
+        let len = level.items.len() as isize;
+        let mut idx = match level.hovered {
+            Some(idx) => idx as isize,
+            None if direction > 0 => -1,
+            None => len,
+        };
+        for _ in 0..len {
+            idx = (idx + direction).rem_euclid(len);
+            if level.items[idx as usize].is_selectable() {
+                level.hovered = Some(idx as usize);
+                return;
+            }
+        }
+    }
+
+    fn on_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Esc => self.close(),
+            KeyCode::Up => self.move_hover(-1),
+            KeyCode::Down => self.move_hover(1),
+            KeyCode::Left if self.levels.len() > 1 => {
+                self.levels.pop();
+            }
+            KeyCode::Right => self.open_hovered_submenu(true),
+            KeyCode::Enter => self.activate(),
+            _ => {}
+        }
+    }
+}
+
+/// A component that handles input for and renders the [`ContextMenu`].
+#[derive(Default)]
+pub struct ContextMenuComponent {
+    // buttons whose press was captured by the menu, so that their release is captured as well
+    captured_buttons: HashSet<MouseButton>,
+}
+
+impl ContextMenuComponent {
+    pub fn new() -> Self {
+        Self {
+            captured_buttons: HashSet::new(),
+        }
+    }
+}
+
+impl<S> Component<S> for ContextMenuComponent {
+    fn setup(&mut self, _setup_info: &SetupInfo, shared_state: &mut SharedState<S>) {
+        shared_state.ext_or_default::<ContextMenu>();
+    }
+
+    fn on_event(
+        &mut self,
+        event: Event,
+        shared_state: &mut SharedState<S>,
+    ) -> Option<BreakingAction> {
+        let screen = (
+            shared_state.display_info.width(),
+            shared_state.display_info.height(),
+        );
+        let menu = shared_state.ext_or_default::<ContextMenu>();
+        match event {
+            Event::Mouse(event) => {
+                let (x, y) = (event.column as usize, event.row as usize);
+                match event.kind {
+                    MouseEventKind::Up(button) | MouseEventKind::Drag(button)
+                        if self.captured_buttons.contains(&button) =>
+                    {
+                        if matches!(event.kind, MouseEventKind::Up(_)) {
+                            self.captured_buttons.remove(&button);
+                        }
+                        return Some(BreakingAction::ConsumeEvent);
+                    }
+                    _ if !menu.is_open() => return None,
+                    MouseEventKind::Moved => {
+                        if let Some((level, item)) = menu.hit(x, y, screen) {
+                            menu.hover(level, item);
+                        }
+                        // let the mouse position through
+                        return None;
+                    }
+                    MouseEventKind::Down(button) => {
+                        self.captured_buttons.insert(button);
+                        match menu.hit(x, y, screen) {
+                            Some((level, item)) => {
+                                menu.hover(level, item);
+                                if item.is_some() {
+                                    menu.activate();
+                                }
+                            }
+                            None => menu.close(),
+                        }
+                    }
+                    _ => {}
+                }
+                Some(BreakingAction::ConsumeEvent)
+            }
+            Event::Key(KeyEvent { code, kind, .. })
+                if menu.is_open() && kind != KeyEventKind::Release =>
+            {
+                menu.on_key(code);
+                Some(BreakingAction::ConsumeEvent)
+            }
+            _ => None,
+        }
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, shared_state: &SharedState<S>, _depth_base: i32) {
+        let Some(menu) = shared_state.ext::<ContextMenu>() else {
+            return;
+        };
+        let screen = (
+            shared_state.display_info.width(),
+            shared_state.display_info.height(),
+        );
+        let border_color = [150, 150, 150];
+        for (depth, (level, rect)) in menu.levels.iter().zip(menu.level_rects(screen)).enumerate() {
+            let depth = i32::MAX - 80 + depth as i32;
+            let inner = rect.width - 2;
+            let top = format!("┌{}┐", "─".repeat(inner));
+            let bottom = format!("└{}┘", "─".repeat(inner));
+            top.with_color(border_color)
+                .with_bg_color([30, 30, 30])
+                .render(renderer, rect.x, rect.y, depth);
+            bottom
+                .with_color(border_color)
+                .with_bg_color([30, 30, 30])
+                .render(renderer, rect.x, rect.y + rect.height - 1, depth);
+            for (idx, item) in level.items.iter().enumerate() {
+                let y = rect.y + 1 + idx;
+                if *item == MenuItem::Separator {
+                    format!("├{}┤", "─".repeat(inner))
+                        .with_color(border_color)
+                        .with_bg_color([30, 30, 30])
+                        .render(renderer, rect.x, y, depth);
+                    continue;
+                }
+                let bg_color = if level.hovered == Some(idx) {
+                    [60, 60, 90]
+                } else {
+                    [30, 30, 30]
+                };
+                let arrow = if matches!(item, MenuItem::Submenu { .. }) {
+                    '▸'
+                } else {
+                    ' '
+                };
+                let label = format!(" {:<width$} {arrow} ", item.label(), width = inner - 4);
+                '│'
+                    .with_color(border_color)
+                    .with_bg_color([30, 30, 30])
+                    .render(renderer, rect.x, y, depth);
+                label
+                    .with_bg_color(bg_color)
+                    .render(renderer, rect.x + 1, y, depth);
+                '│'
+                    .with_color(border_color)
+                    .with_bg_color([30, 30, 30])
+                    .render(renderer, rect.x + rect.width - 1, y, depth);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Game;
+    use crate::components::fncomponent::FnComponent;
+    use crossterm::event::{KeyModifiers, MouseEvent};
+
+    fn items() -> Vec<MenuItem> {
+        vec![
+            MenuItem::action("Copy", "copy"),
+            MenuItem::separator(),
+            MenuItem::submenu(
+                "Export",
+                vec![
+                    MenuItem::action("PNG", "export-png"),
+                    MenuItem::action("Text", "export-text"),
+                ],
+            ),
+        ]
+    }
+
+    /// Counts the events that reach the components after the menu.
+    #[derive(Default)]
+    struct Passed(usize);
+
+    fn game() -> Game<Vec<u8>, ()> {
+        let mut game = Game::<Vec<u8>, ()>::new_headless(40, 20);
+        game.add_component(Box::new(ContextMenuComponent::new()));
+        game.add_component(Box::new(FnComponent::new().with_on_event(
+            |_, shared_state| {
+                shared_state.ext_or_default::<Passed>().0 += 1;
+                None
+            },
+        )));
+        game
+    }
+
+    /// Runs a frame with the mouse event, and returns whether the menu consumed it.
+    fn mouse(game: &mut Game<Vec<u8>, ()>, kind: MouseEventKind, x: usize, y: usize) -> bool {
+        let passed =
+            |game: &Game<Vec<u8>, ()>| game.shared_state().ext::<Passed>().map_or(0, |p| p.0);
+        let before = passed(game);
+        game.push_event(Event::Mouse(MouseEvent {
+            kind,
+            column: x as u16,
+            row: y as u16,
+            modifiers: KeyModifiers::NONE,
+        }));
+        game.run_frames(1).unwrap();
+        passed(game) == before
+    }
+
+    fn context_menu(game: &mut Game<Vec<u8>, ()>) -> &mut ContextMenu {
+        game.shared_state_mut().ext_or_default()
+    }
+
+    fn key(game: &mut Game<Vec<u8>, ()>, code: KeyCode) {
+        game.push_event(Event::Key(KeyEvent::new(code, KeyModifiers::NONE)));
+        game.run_frames(1).unwrap();
+    }
+
+    #[test]
+    fn test_edge_flipping() {
+        let mut menu = ContextMenu::default();
+        // 12x5 with the border
+        menu.open_at(10, 5, items());
+        let rect = |menu: &ContextMenu| menu.level_rects((40, 20))[0];
+        assert_eq!(
+            rect(&menu),
+            MenuRect {
+                x: 10,
+                y: 5,
+                width: 12,
+                height: 5,
+            }
+        );
+        // the bottom right corner touches the anchor
+        menu.open_at(35, 18, items());
+        assert_eq!((rect(&menu).x, rect(&menu).y), (24, 14));
+        // exactly fits
+        menu.open_at(28, 15, items());
+        assert_eq!((rect(&menu).x, rect(&menu).y), (28, 15));
+        // too large to flip, clamped to the screen
+        menu.open_at(5, 2, items());
+        assert_eq!(menu.level_rects((8, 4))[0].x, 0);
+        assert_eq!(menu.level_rects((8, 4))[0].y, 0);
+
+        // the submenu opens to the right of the hovered item, or to the left near the edge
+        menu.open_at(10, 5, items());
+        menu.hover(0, Some(2));
+        assert_eq!(
+            menu.level_rects((40, 20))[1],
+            MenuRect {
+                x: 22,
+                y: 7,
+                width: 10,
+                height: 4,
+            }
+        );
+        menu.open_at(25, 15, items());
+        menu.hover(0, Some(2));
+        let rects = menu.level_rects((40, 20));
+        assert_eq!((rects[0].x, rects[0].y), (25, 15));
+        // flipped up, so that the last item is next to the hovered one
+        assert_eq!((rects[1].x, rects[1].y), (15, 16));
+        assert!(rects[1].y + rects[1].height <= 20);
+    }
+
+    #[test]
+    fn test_submenu_navigation() {
+        let mut game = game();
+        context_menu(&mut game).open_at(10, 5, items());
+
+        // hovering "Export" opens the submenu next to it
+        assert!(!mouse(&mut game, MouseEventKind::Moved, 12, 8));
+        assert_eq!(context_menu(&mut game).levels.len(), 2);
+        // moving onto the submenu keeps it open, clicking "Text" chooses it
+        mouse(&mut game, MouseEventKind::Moved, 24, 8);
+        assert_eq!(context_menu(&mut game).levels.len(), 2);
+        let down = MouseEventKind::Down(MouseButton::Left);
+        assert!(mouse(&mut game, down, 24, 9));
+        assert!(!context_menu(&mut game).is_open());
+        assert_eq!(
+            context_menu(&mut game).take_result().as_deref(),
+            Some("export-text")
+        );
+        // the release of the click is captured as well
+        let up = MouseEventKind::Up(MouseButton::Left);
+        assert!(mouse(&mut game, up, 24, 9));
+        assert!(!mouse(&mut game, up, 24, 9));
+
+        // the same with the keyboard, skipping the separator
+        context_menu(&mut game).open_at(10, 5, items());
+        key(&mut game, KeyCode::Down);
+        key(&mut game, KeyCode::Down);
+        assert_eq!(context_menu(&mut game).levels[0].hovered, Some(2));
+        key(&mut game, KeyCode::Right);
+        assert_eq!(context_menu(&mut game).levels[1].hovered, Some(0));
+        key(&mut game, KeyCode::Left);
+        assert_eq!(context_menu(&mut game).levels.len(), 1);
+        key(&mut game, KeyCode::Enter);
+        key(&mut game, KeyCode::Enter);
+        assert_eq!(context_menu(&mut game).result(), Some("export-png"));
+    }
+
+    #[test]
+    fn test_dismissal() {
+        let mut game = game();
+        let down = MouseEventKind::Down(MouseButton::Right);
+
+        // without an open menu, input passes through
+        assert!(!mouse(&mut game, down, 0, 0));
+
+        // clicking the border or a separator does nothing
+        context_menu(&mut game).open_at(10, 5, items());
+        assert!(mouse(&mut game, down, 10, 5));
+        assert!(mouse(&mut game, down, 12, 7));
+        assert!(context_menu(&mut game).is_open());
+
+        // an outside click closes the menu without a result and does not reach the world
+        assert!(mouse(&mut game, down, 0, 0));
+        assert!(!context_menu(&mut game).is_open());
+        assert_eq!(context_menu(&mut game).result(), None);
+
+        context_menu(&mut game).open_at(10, 5, items());
+        key(&mut game, KeyCode::Esc);
+        assert!(!context_menu(&mut game).is_open());
+        assert_eq!(context_menu(&mut game).result(), None);
+
+        // opening a new menu replaces the previous one and its result
+        context_menu(&mut game).open_at(10, 5, items());
+        context_menu(&mut game).open_at(0, 0, vec![MenuItem::action("Paste", "paste")]);
+        assert_eq!(context_menu(&mut game).levels.len(), 1);
+        mouse(&mut game, down, 2, 1);
+        assert_eq!(context_menu(&mut game).result(), Some("paste"));
+        context_menu(&mut game).open_at(0, 0, items());
+        assert_eq!(context_menu(&mut game).result(), None);
+    }
+}
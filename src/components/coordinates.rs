@@ -0,0 +1,197 @@
+//! An overlay that shows the coordinates under the mouse.
+//!
+//! Press F6 to toggle the [`CoordinateDebugComponent`]. While it is enabled, it shows next to the
+//! mouse:
+//! * the terminal cell,
+//! * the two half-block pixels of the cell, as used by
+//!   [`HalfBlockDisplayRender`](crate::rendering::render::HalfBlockDisplayRender),
+//! * the world coordinates of both halves, if a [`Camera2D`] is registered in
+//!   [`SharedState::extensions`].
+//!
+//! It also draws rulers along the top and left screen edges every 10 cells, and with a camera,
+//! the world origin, the viewport if it does not fill the screen, and the world bounds if the
+//! world does not fill the viewport.
+
+use crate::components::Component;
+use crate::rendering::pixel::Pixel;
+use crate::rendering::render::Render;
+use crate::rendering::renderer::Renderer;
+use crate::util::camera::{Camera2D, CellHalf};
+use crate::{SharedState, UpdateInfo};
+use crossterm::event::KeyCode;
+
+/// A component that shows the coordinates under the mouse.
+#[derive(Default)]
+pub struct CoordinateDebugComponent {
+    enabled: bool,
+}
+
+impl CoordinateDebugComponent {
+    /// The key that toggles the overlay.
+    pub const TOGGLE_KEY: KeyCode = KeyCode::F(6);
+    /// The distance between ruler marks in cells.
+    pub const RULER_SPACING: usize = 10;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+}
+
+/// Returns the lines of the overlay for the mouse in the cell `(x, y)`.
+fn info_lines((x, y): (usize, usize), camera: Option<&Camera2D>) -> Vec<String> {
+    let mut lines = vec![format!("cell  ({x}, {y})")];
+    let (ox, oy) = camera.map_or((0, 0), |c| c.viewport_offset);
+    let (px, py) = (x as i64 - ox as i64, 2 * (y as i64 - oy as i64));
+    lines.push(format!("hbd   top ({px}, {py}) bottom ({px}, {})", py + 1));
+    if let Some(camera) = camera {
+        if camera.contains_cell(x, y) {
+            let (tx, ty) = camera.screen_to_world(x, y, CellHalf::Top);
+            let (bx, by) = camera.screen_to_world(x, y, CellHalf::Bottom);
+            lines.push(format!(
+                "world top ({tx:.2}, {ty:.2}) bottom ({bx:.2}, {by:.2})"
+            ));
+        } else {
+            lines.push("world outside of the viewport".to_string());
+        }
+    }
+    lines
+}
+
+/// Draws the outline of the cell rectangle from `min` to `max`, inclusive.
+fn render_rect(
+    renderer: &mut dyn Renderer,
+    (min_x, min_y): (usize, usize),
+    (max_x, max_y): (usize, usize),
+    color: [u8; 3],
+    depth: i32,
+) {
+    for x in min_x..=max_x {
+        for y in [min_y, max_y] {
+            renderer.render_pixel(x, y, Pixel::new('·').with_color(color), depth);
+        }
+    }
+    for y in min_y..=max_y {
+        for x in [min_x, max_x] {
+            renderer.render_pixel(x, y, Pixel::new('·').with_color(color), depth);
+        }
+    }
+}
+
+impl<S: 'static> Component<S> for CoordinateDebugComponent {
+    fn runs_while_paused(&self) -> bool {
+        true
+    }
+
+    fn update(&mut self, _update_info: UpdateInfo, shared_state: &mut SharedState<S>) {
+        if shared_state.pressed_keys.did_press(Self::TOGGLE_KEY) {
+            self.enabled = !self.enabled;
+        }
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, shared_state: &SharedState<S>, _depth_base: i32) {
+        if !self.enabled {
+            return;
+        }
+        let width = shared_state.display_info.width();
+        let height = shared_state.display_info.height();
+        let camera = shared_state.ext::<Camera2D>();
+        let depth = i32::MAX - 50;
+        let gray = [150, 150, 150];
+
+        for x in (0..width).step_by(Self::RULER_SPACING) {
+            format!("|{x}")
+                .with_color(gray)
+                .render(renderer, x, 0, depth);
+        }
+        for y in (Self::RULER_SPACING..height).step_by(Self::RULER_SPACING) {
+            format!("-{y}")
+                .with_color(gray)
+                .render(renderer, 0, y, depth);
+        }
+
+        if let Some(camera) = camera {
+            let (ox, oy) = camera.viewport_offset;
+            let (w, h) = camera.viewport_size;
+            if (ox, oy, w, h) != (0, 0, width, height) && w > 0 && h > 0 {
+                render_rect(renderer, (ox, oy), (ox + w - 1, oy + h - 1), gray, depth);
+            }
+            if let Some((min, max)) = camera.world_bounds {
+                let (visible_min, visible_max) = camera.visible_bounds();
+                let fills = min.0 <= visible_min.0
+                    && min.1 <= visible_min.1
+                    && max.0 >= visible_max.0
+                    && max.1 >= visible_max.1;
+                if !fills {
+                    // the pixels at the inner edges of the bounds
+                    let inset = camera.scale / 2.0;
+                    let (x0, y1, _) = camera.world_to_screen(min.0 + inset, min.1 + inset);
+                    let (x1, y0, _) = camera.world_to_screen(max.0 - inset, max.1 - inset);
+                    let clamp = |x: usize, y: usize| (x.min(width - 1), y.min(height - 1));
+                    render_rect(renderer, clamp(x0, y0), clamp(x1, y1), [255, 200, 0], depth);
+                }
+            }
+            let (x, y, _) = camera.world_to_screen(0.0, 0.0);
+            if camera.contains_cell(x, y) {
+                Pixel::new('+')
+                    .with_color([255, 80, 80])
+                    .render(renderer, x, y, depth + 1);
+            }
+        }
+
+        let mouse = shared_state.mouse_info.last_mouse_pos;
+        let lines = info_lines(mouse, camera);
+        let box_width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+        let x = if mouse.0 + 2 + box_width <= width {
+            mouse.0 + 2
+        } else {
+            mouse.0.saturating_sub(box_width + 1)
+        };
+        let y = if mouse.1 + 1 + lines.len() <= height {
+            mouse.1 + 1
+        } else {
+            mouse.1.saturating_sub(lines.len())
+        };
+        for (i, line) in lines.iter().enumerate() {
+            format!("{line:box_width$}")
+                .with_color([255, 255, 255])
+                .with_bg_color([30, 30, 30])
+                .render(renderer, x, y + i, depth + 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_info_without_camera() {
+        assert_eq!(
+            info_lines((3, 5), None),
+            vec!["cell  (3, 5)", "hbd   top (3, 10) bottom (3, 11)"]
+        );
+    }
+
+    #[test]
+    fn test_info_matches_camera() {
+        let mut camera = Camera2D::new(40, 20);
+        camera.position = (100.0, -4.0);
+        camera.viewport_offset = (0, 2);
+        let lines = info_lines((3, 12), Some(&camera));
+        assert_eq!(lines[1], "hbd   top (3, 20) bottom (3, 21)");
+        // 40 pixels high: pixel row 20 is 19 rows above the bottom row
+        assert_eq!(camera.screen_to_world(3, 12, CellHalf::Top), (103.0, 15.0));
+        assert_eq!(lines[2], "world top (103.00, 15.00) bottom (103.00, 14.00)");
+
+        let lines = info_lines((3, 0), Some(&camera));
+        assert_eq!(lines[2], "world outside of the viewport");
+    }
+}
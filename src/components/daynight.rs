@@ -0,0 +1,376 @@
+//! A day/night cycle that tints the whole screen with an ambient light.
+//!
+//! The [`DayNightComponent`] advances the time of day in the [`TimeOfDay`] extension, a value
+//! from 0 to 1 where 0 is midnight and 0.5 is noon. From a keyframed curve it computes an
+//! ambient color and intensity, by default a warm dawn, a neutral noon, an orange dusk and a
+//! dark blue night, see [`AmbientKeyframe::DEFAULT`].
+//!
+//! Gameplay logic reads the current [`DayPhase`], its changes and a normalized darkness from
+//! [`TimeOfDay`], e.g. to spawn monsters only at night.
+//!
+//! # Stacking order
+//! The ambient light is a [`PostProcess::Multiply`] that the component keeps at the front of
+//! [`SharedState::post_processes`]. The frame is thus composed as follows:
+//! 1. The [`Overlay`](crate::rendering::renderer::Overlay), e.g. a dim behind a menu.
+//! 2. The ambient light.
+//! 3. All other post-processes in order, e.g. a manual [`PostProcess::Multiply`] for a damage
+//!    flash, or a simulated color vision deficiency, which must see the final colors.
+//!
+//! Since post-processes apply to the whole screen, the ambient light also tints UI elements.
+
+use crate::rendering::renderer::PostProcess;
+use crate::{Component, SetupInfo, SharedState, UpdateInfo};
+use std::time::Duration;
+
+/// A phase of the day, see [`DayPhase::at`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DayPhase {
+    Dawn,
+    Day,
+    Dusk,
+    Night,
+}
+
+impl DayPhase {
+    /// The times of day at which the phases start, in order.
+    pub const STARTS: [(f64, DayPhase); 4] = [
+        (0.2, DayPhase::Dawn),
+        (0.3, DayPhase::Day),
+        (0.7, DayPhase::Dusk),
+        (0.8, DayPhase::Night),
+    ];
+
+    /// Returns the phase at the time of day `time` from 0 to 1.
+    pub fn at(time: f64) -> Self {
+        let time = time.rem_euclid(1.0);
+        Self::STARTS
+            .iter()
+            .rev()
+            .find(|(start, _)| time >= *start)
+            .map_or(DayPhase::Night, |(_, phase)| *phase)
+    }
+}
+
+/// The state of the day/night cycle, kept as an extension of the [`SharedState`], e.g.
+/// `shared_state.ext::<TimeOfDay>()`.
+///
+/// Managed by the [`DayNightComponent`], which inserts it in its setup. Without it, the time does
+/// not advance.
+#[derive(Debug, Clone)]
+pub struct TimeOfDay {
+    time: f64,
+    day_length: Duration,
+    running: bool,
+    phase: DayPhase,
+    phase_changed: Option<DayPhase>,
+    darkness: f64,
+}
+
+impl Default for TimeOfDay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimeOfDay {
+    /// Creates a running day of ten minutes, starting at noon.
+    pub fn new() -> Self {
+        Self {
+            time: 0.5,
+            day_length: Duration::from_secs(600),
+            running: true,
+            phase: DayPhase::Day,
+            phase_changed: None,
+            darkness: 0.0,
+        }
+    }
+
+    /// Returns the time of day from 0 (inclusive) to 1 (exclusive), where 0 is midnight.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// Sets the time of day, wrapping it into 0 to 1. The phase follows in the next update.
+    pub fn set_time(&mut self, time: f64) {
+        self.time = time.rem_euclid(1.0);
+    }
+
+    /// Returns the real time a full day takes.
+    pub fn day_length(&self) -> Duration {
+        self.day_length
+    }
+
+    /// Sets the real time a full day takes.
+    pub fn set_day_length(&mut self, day_length: Duration) {
+        self.day_length = day_length;
+    }
+
+    /// Returns true if the time advances.
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Stops advancing the time. The ambient light stays as it is.
+    pub fn pause(&mut self) {
+        self.running = false;
+    }
+
+    /// Continues advancing the time.
+    pub fn resume(&mut self) {
+        self.running = true;
+    }
+
+    /// Returns the current phase of the day.
+    pub fn phase(&self) -> DayPhase {
+        self.phase
+    }
+
+    /// Returns the new phase during the frame in which the phase changed.
+    ///
+    /// If a single frame skips over a phase, only the latest phase is reported.
+    pub fn phase_changed(&self) -> Option<DayPhase> {
+        self.phase_changed
+    }
+
+    /// Returns how dark it is, from 0 at the brightest keyframe to 1 at the darkest.
+    pub fn darkness(&self) -> f64 {
+        self.darkness
+    }
+}
+
+/// A point on the ambient light curve of a [`DayNightComponent`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AmbientKeyframe {
+    /// The time of day from 0 to 1.
+    pub time: f64,
+    /// The color of the light, multiplied with the intensity.
+    pub color: [u8; 3],
+    /// The brightness from 0 (black) to 1 (unchanged).
+    pub intensity: f64,
+}
+
+impl AmbientKeyframe {
+    /// Midnight, dawn, noon and dusk.
+    pub const DEFAULT: [AmbientKeyframe; 4] = [
+        AmbientKeyframe::new(0.0, [90, 110, 200], 0.35),
+        AmbientKeyframe::new(0.25, [255, 200, 160], 0.8),
+        AmbientKeyframe::new(0.5, [255, 255, 255], 1.0),
+        AmbientKeyframe::new(0.75, [255, 150, 80], 0.75),
+    ];
+
+    pub const fn new(time: f64, color: [u8; 3], intensity: f64) -> Self {
+        Self {
+            time,
+            color,
+            intensity,
+        }
+    }
+
+    /// Returns the per-channel factor of the [`PostProcess::Multiply`] for this light.
+    pub fn factor(&self) -> [u8; 3] {
+        self.color
+            .map(|c| (c as f64 * self.intensity.clamp(0.0, 1.0)).round() as u8)
+    }
+}
+
+/// A component that advances the [`TimeOfDay`] and applies the ambient light.
+pub struct DayNightComponent {
+    keyframes: Vec<AmbientKeyframe>,
+    applied: Option<PostProcess>,
+}
+
+impl Default for DayNightComponent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DayNightComponent {
+    /// Creates a day/night cycle with the [default keyframes](AmbientKeyframe::DEFAULT).
+    pub fn new() -> Self {
+        Self::with_keyframes(AmbientKeyframe::DEFAULT.to_vec())
+    }
+
+    /// Creates a day/night cycle with custom keyframes. The curve wraps around from the last
+    /// keyframe to the first.
+    ///
+    /// # Panics
+    /// Panics if `keyframes` is empty.
+    pub fn with_keyframes(mut keyframes: Vec<AmbientKeyframe>) -> Self {
+        assert!(!keyframes.is_empty(), "at least one keyframe is required");
+        for keyframe in &mut keyframes {
+            keyframe.time = keyframe.time.rem_euclid(1.0);
+        }
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Self {
+            keyframes,
+            applied: None,
+        }
+    }
+
+    /// Returns the ambient light at the time of day `time`, interpolated linearly between the
+    /// surrounding keyframes.
+    pub fn ambient_at(&self, time: f64) -> AmbientKeyframe {
+        let time = time.rem_euclid(1.0);
+        let next_idx = self.keyframes.partition_point(|k| k.time <= time);
+        let prev = self.keyframes[(next_idx + self.keyframes.len() - 1) % self.keyframes.len()];
+        let next = self.keyframes[next_idx % self.keyframes.len()];
+        let span = (next.time - prev.time).rem_euclid(1.0);
+        let t = if span == 0.0 {
+            0.0
+        } else {
+            (time - prev.time).rem_euclid(1.0) / span
+        };
+        AmbientKeyframe {
+            time,
+            color: crate::util::lerp_color(prev.color, next.color, t as f32),
+            intensity: prev.intensity + (next.intensity - prev.intensity) * t,
+        }
+    }
+
+    /// Returns the normalized darkness of an intensity, see [`TimeOfDay::darkness`].
+    fn darkness(&self, intensity: f64) -> f64 {
+        let intensities = self.keyframes.iter().map(|k| k.intensity);
+        let max = intensities.clone().fold(f64::MIN, f64::max);
+        let min = intensities.fold(f64::MAX, f64::min);
+        if max > min {
+            ((max - intensity) / (max - min)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// Updates the phase, darkness and ambient light for the current time of day.
+    fn apply<S>(&mut self, shared_state: &mut SharedState<S>) {
+        let time_of_day = shared_state.ext_or_default::<TimeOfDay>();
+        let phase = DayPhase::at(time_of_day.time);
+        time_of_day.phase_changed = (phase != time_of_day.phase).then_some(phase);
+        time_of_day.phase = phase;
+        let ambient = self.ambient_at(time_of_day.time);
+        time_of_day.darkness = self.darkness(ambient.intensity);
+
+        let light = PostProcess::Multiply(ambient.factor());
+        let post_processes = &mut shared_state.post_processes;
+        match self
+            .applied
+            .and_then(|applied| post_processes.iter().position(|p| *p == applied))
+        {
+            Some(idx) => post_processes[idx] = light,
+            None => post_processes.insert(0, light),
+        }
+        self.applied = Some(light);
+    }
+}
+
+impl<S> Component<S> for DayNightComponent {
+    fn setup(&mut self, _setup_info: &SetupInfo, shared_state: &mut SharedState<S>) {
+        // a time of day configured before the setup is kept
+        self.apply(shared_state);
+        shared_state.ext_or_default::<TimeOfDay>().phase_changed = None;
+    }
+
+    fn update(&mut self, update_info: UpdateInfo, shared_state: &mut SharedState<S>) {
+        let time_of_day = shared_state.ext_or_default::<TimeOfDay>();
+        let day_length = time_of_day.day_length.as_secs_f64();
+        if time_of_day.running && day_length > 0.0 {
+            time_of_day.set_time(time_of_day.time + update_info.dt / day_length);
+        }
+        self.apply(shared_state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DisplayInfo;
+    use std::time::Instant;
+
+    fn update(component: &mut DayNightComponent, state: &mut SharedState<()>, dt: f64) {
+        let update_info = UpdateInfo {
+            dt,
+            actual_dt: dt,
+            ..UpdateInfo::at(Instant::now())
+        };
+        component.update(update_info, state);
+    }
+
+    #[test]
+    fn test_tint_at_keyframes() {
+        let component = DayNightComponent::new();
+        let factor = |time| component.ambient_at(time).factor();
+        assert_eq!(factor(0.0), [31, 39, 70]);
+        assert_eq!(factor(0.25), [204, 160, 128]);
+        assert_eq!(factor(0.5), [255, 255, 255]);
+        assert_eq!(factor(0.75), [191, 113, 60]);
+        // wraps around from dusk to midnight
+        assert_eq!(factor(1.0), factor(0.0));
+        assert_eq!(component.ambient_at(0.875).intensity, (0.75 + 0.35) / 2.0);
+
+        let custom = DayNightComponent::with_keyframes(vec![
+            AmbientKeyframe::new(0.5, [255, 255, 255], 1.0),
+            AmbientKeyframe::new(0.0, [0, 0, 0], 1.0),
+        ]);
+        assert_eq!(custom.ambient_at(0.25).factor(), [127, 127, 127]);
+        assert_eq!(custom.ambient_at(0.75).factor(), [127, 127, 127]);
+    }
+
+    #[test]
+    fn test_phase_events_and_post_process() {
+        let mut state = SharedState::<()>::new(10, 10);
+        let manual = PostProcess::Multiply([255, 0, 0]);
+        state.post_processes.push(manual);
+        let time_of_day = state.ext_or_default::<TimeOfDay>();
+        time_of_day.set_day_length(Duration::from_secs(100));
+        time_of_day.set_time(0.0);
+        let mut component = DayNightComponent::new();
+        Component::<()>::setup(
+            &mut component,
+            &SetupInfo {
+                display_info: DisplayInfo::new(10, 10),
+            },
+            &mut state,
+        );
+        assert_eq!(state.ext::<TimeOfDay>().unwrap().phase(), DayPhase::Night);
+        assert_eq!(state.ext::<TimeOfDay>().unwrap().phase_changed(), None);
+        assert_eq!(state.ext::<TimeOfDay>().unwrap().darkness(), 1.0);
+        assert_eq!(
+            state.post_processes,
+            vec![PostProcess::Multiply([31, 39, 70]), manual]
+        );
+
+        // one second per frame, i.e. 0.01 of a day
+        let mut events = vec![];
+        for second in 1..=100 {
+            update(&mut component, &mut state, 1.0);
+            if let Some(phase) = state.ext::<TimeOfDay>().unwrap().phase_changed() {
+                events.push((second, phase));
+            }
+        }
+        assert_eq!(
+            events,
+            vec![
+                (20, DayPhase::Dawn),
+                (30, DayPhase::Day),
+                (70, DayPhase::Dusk),
+                (80, DayPhase::Night),
+            ]
+        );
+        assert_eq!(state.post_processes.len(), 2);
+        assert_eq!(state.post_processes[1], manual);
+
+        let time_of_day = state.ext_mut::<TimeOfDay>().unwrap();
+        time_of_day.set_time(0.5);
+        time_of_day.pause();
+        update(&mut component, &mut state, 10.0);
+        assert_eq!(state.ext::<TimeOfDay>().unwrap().time(), 0.5);
+        assert_eq!(
+            state.ext::<TimeOfDay>().unwrap().phase_changed(),
+            Some(DayPhase::Day)
+        );
+        assert_eq!(state.ext::<TimeOfDay>().unwrap().darkness(), 0.0);
+        assert_eq!(
+            state.post_processes,
+            vec![PostProcess::Multiply([255, 255, 255]), manual]
+        );
+    }
+}
@@ -1,53 +1,523 @@
-use crate::rendering::render::Render;
-use crate::rendering::renderer::Renderer;
+use crate::components::Dependency;
+use crate::components::fpslocker::FpsMode;
+use crate::components::keyboard::KeyPressRecorderComponent;
+use crate::components::overlay_layout::{Corner, OverlayAnchor, Placement};
+use crate::components::problems::Severity;
+use crate::components::settings::format_key;
+use crate::rendering::color::ColorVisionDeficiency;
+use crate::rendering::hud::{FixedWidthNumber, HudRow};
+use crate::rendering::pixel::Pixel;
+use crate::rendering::render::{Paragraph, Render};
+use crate::rendering::renderer::{PostProcess, Renderer};
 use crate::seeds::get_seed_opt;
-use crate::{BreakingAction, Component, SharedState, UpdateInfo};
-use crossterm::event::Event;
-use std::collections::BTreeMap;
+use crate::{BreakingAction, Component, SetupInfo, SharedState, UpdateInfo};
+use crossterm::event::{Event, KeyCode};
+use smallvec::SmallVec;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
 use std::time::{Duration, Instant};
 
 /// A debug message that will be displayed on the screen for a limited time.
+#[derive(Debug, Clone)]
 pub struct DebugMessage {
     message: String,
     expiry_time: Instant,
+    severity: Severity,
+    dedup_key: Option<String>,
+    count: usize,
 }
 
 impl DebugMessage {
-    /// Create a new debug message with the given message and expiry time.
+    /// Create a new info message with the given message and expiry time.
     pub fn new(message: impl Into<String>, expiry_time: Instant) -> Self {
         Self {
             message: message.into(),
             expiry_time,
+            severity: Severity::Info,
+            dedup_key: None,
+            count: 1,
         }
     }
 
-    /// Create a new debug message with the given message that will expire in 3 seconds.
+    /// Create a new info message with the given message that will expire in 3 seconds.
     pub fn new_3s(message: impl Into<String>) -> Self {
         Self::new(message.into(), Instant::now() + Duration::from_secs(3))
     }
+
+    /// Create a new warning with the given message that will expire in 5 seconds.
+    pub fn warn_5s(message: impl Into<String>) -> Self {
+        Self::new(message.into(), Instant::now() + Duration::from_secs(5))
+            .with_severity(Severity::Warning)
+    }
+
+    /// Create a new error with the given message that will expire in 5 seconds.
+    pub fn error_5s(message: impl Into<String>) -> Self {
+        Self::new(message.into(), Instant::now() + Duration::from_secs(5))
+            .with_severity(Severity::Error)
+    }
+
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    /// Sets the key that identifies repetitions of this message, see [`DebugMessages::push`].
+    pub fn with_dedup_key(mut self, key: impl Into<String>) -> Self {
+        self.dedup_key = Some(key.into());
+        self
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn dedup_key(&self) -> Option<&str> {
+        self.dedup_key.as_deref()
+    }
+
+    /// Returns how often the message was pushed, counting repetitions with the same dedup key.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Returns the message as displayed, with the number of repetitions if there were any.
+    pub fn text(&self) -> String {
+        if self.count > 1 {
+            format!("{} (x{})", self.message, self.count)
+        } else {
+            self.message.clone()
+        }
+    }
+
+    /// Returns the color the message is displayed with, or `None` for the default color.
+    fn color(&self) -> Option<[u8; 3]> {
+        (self.severity != Severity::Info).then(|| self.severity.color())
+    }
+
+    /// Replaces `self` with its repetition `newer`, keeping the count.
+    fn repeat(&mut self, newer: DebugMessage) {
+        let count = self.count + newer.count;
+        *self = newer;
+        self.count = count;
+    }
 }
 
-// TODO: make this extensible
-/// Static debug content that will be displayed on the screen and updated every frame.
+/// The debug messages that are displayed on the screen, accessible via
+/// [`SharedState::debug_messages`].
+///
+/// Pushing a message with the same dedup key as a displayed one refreshes that message and
+/// increments its counter instead of adding another line. If a history limit is set, e.g. by the
+/// [`DebugLogComponent`], the most recent messages are also kept after they expire.
+#[derive(Debug, Clone, Default)]
+pub struct DebugMessages {
+    messages: SmallVec<[DebugMessage; 16]>,
+    history: VecDeque<DebugMessage>,
+    history_limit: usize,
+}
+
+impl DebugMessages {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, message: DebugMessage) {
+        if self.history_limit > 0 {
+            match self.history.back_mut() {
+                Some(last)
+                    if message.dedup_key.is_some() && last.dedup_key == message.dedup_key =>
+                {
+                    last.repeat(message.clone())
+                }
+                _ => {
+                    if self.history.len() == self.history_limit {
+                        self.history.pop_front();
+                    }
+                    self.history.push_back(message.clone());
+                }
+            }
+        }
+        let repeated = message.dedup_key.as_ref().and_then(|key| {
+            self.messages
+                .iter_mut()
+                .find(|m| m.dedup_key.as_ref() == Some(key))
+        });
+        match repeated {
+            Some(repeated) => repeated.repeat(message),
+            None => self.messages.push(message),
+        }
+    }
+
+    /// Returns the displayed messages, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &DebugMessage> {
+        self.messages.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Removes the displayed messages. The history is kept.
+    pub fn clear(&mut self) {
+        self.messages.clear();
+    }
+
+    /// Returns the most recent messages, including expired ones, oldest first.
+    pub fn history(&self) -> &VecDeque<DebugMessage> {
+        &self.history
+    }
+
+    /// Sets the number of messages kept in the history. 0, the default, disables the history.
+    pub fn set_history_limit(&mut self, limit: usize) {
+        self.history_limit = limit;
+        while self.history.len() > limit {
+            self.history.pop_front();
+        }
+    }
+
+    /// Removes the messages that expired by `now`, and all but the `max` most recent ones.
+    fn expire(&mut self, now: Instant, max: usize) {
+        self.messages.retain(|msg| msg.expiry_time > now);
+        if self.messages.len() > max {
+            self.messages.drain(0..self.messages.len() - max);
+        }
+    }
+}
+
+/// A typed debug value. Formatting is handled centrally by its [`Display`](fmt::Display) impl.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DebugValue {
+    F64(f64),
+    I64(i64),
+    Bool(bool),
+    Vec2(f64, f64),
+    Str(String),
+    Duration(Duration),
+}
+
+impl DebugValue {
+    const F64_PRECISION: usize = 2;
+
+    fn fmt_f64(f: &mut fmt::Formatter<'_>, v: f64) -> fmt::Result {
+        // avoid showing small non-zero values as 0.00
+        if v != 0.0 && v.abs() < 0.1f64.powi(Self::F64_PRECISION as i32) {
+            write!(f, "{:.*e}", Self::F64_PRECISION, v)
+        } else {
+            write!(f, "{:.*}", Self::F64_PRECISION, v)
+        }
+    }
+}
+
+impl fmt::Display for DebugValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DebugValue::F64(v) => Self::fmt_f64(f, *v),
+            DebugValue::I64(v) => write!(f, "{v}"),
+            DebugValue::Bool(v) => write!(f, "{v}"),
+            DebugValue::Vec2(x, y) => {
+                write!(f, "(")?;
+                Self::fmt_f64(f, *x)?;
+                write!(f, ", ")?;
+                Self::fmt_f64(f, *y)?;
+                write!(f, ")")
+            }
+            DebugValue::Str(v) => write!(f, "{v}"),
+            DebugValue::Duration(d) => {
+                let secs = d.as_secs_f64();
+                if secs >= 1.0 {
+                    write!(f, "{secs:.2} s")
+                } else if secs >= 1e-3 {
+                    write!(f, "{:.2} ms", secs * 1e3)
+                } else if secs >= 1e-6 {
+                    write!(f, "{:.2} µs", secs * 1e6)
+                } else {
+                    write!(f, "{} ns", d.as_nanos())
+                }
+            }
+        }
+    }
+}
+
+impl From<f64> for DebugValue {
+    fn from(v: f64) -> Self {
+        DebugValue::F64(v)
+    }
+}
+
+impl From<i64> for DebugValue {
+    fn from(v: i64) -> Self {
+        DebugValue::I64(v)
+    }
+}
+
+impl From<usize> for DebugValue {
+    fn from(v: usize) -> Self {
+        DebugValue::I64(v as i64)
+    }
+}
+
+impl From<bool> for DebugValue {
+    fn from(v: bool) -> Self {
+        DebugValue::Bool(v)
+    }
+}
+
+impl From<(f64, f64)> for DebugValue {
+    fn from((x, y): (f64, f64)) -> Self {
+        DebugValue::Vec2(x, y)
+    }
+}
+
+impl From<&str> for DebugValue {
+    fn from(v: &str) -> Self {
+        DebugValue::Str(v.to_string())
+    }
+}
+
+impl From<String> for DebugValue {
+    fn from(v: String) -> Self {
+        DebugValue::Str(v)
+    }
+}
+
+impl From<Duration> for DebugValue {
+    fn from(v: Duration) -> Self {
+        DebugValue::Duration(v)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct DebugEntry {
+    value: DebugValue,
+    last_updated_frame: u64,
+    persistent: bool,
+}
+
+/// A line of the grouped debug values overlay, see [`DebugInfo::lines`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugLine {
+    /// The nesting depth of the line.
+    pub indent: usize,
+    pub text: String,
+    /// Whether the value was not updated recently. Always false for group headers.
+    pub stale: bool,
+}
+
+/// Debug values that are displayed on the screen and updated every frame.
 ///
 /// Use this instead of [`DebugMessage`] if you want to display content that is updated every frame,
 /// such as a player's position.
-#[derive(Debug, Default, Clone)]
+///
+/// Keys are hierarchical: `"player.x"` and `"player.y"` are displayed together under a
+/// `player` group. Groups and values are sorted by key, so the overlay's order is stable.
+///
+/// Values must be set every frame they should stay current. Values that were not updated for
+/// a while are dimmed, and eventually dropped, so that removed code does not leave zombie
+/// readouts behind. Use [`DebugInfo::set_persistent`] for values that are only set once.
+#[derive(Debug, Clone)]
 pub struct DebugInfo {
-    player_y: f64,
-    player_x: f64,
-    left_wall: f64,
-    bottom_wall: f64,
-    y_vel: f64,
-    target_queue: Vec<u16>,
-    /// Custom debug information that can be set by other components.
-    pub custom: BTreeMap<String, String>,
+    entries: BTreeMap<String, DebugEntry>,
+    frame: u64,
+    stale_after_frames: u64,
+    drop_after_frames: u64,
+}
+
+impl Default for DebugInfo {
+    fn default() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            frame: 0,
+            stale_after_frames: Self::DEFAULT_STALE_AFTER_FRAMES,
+            drop_after_frames: Self::DEFAULT_DROP_AFTER_FRAMES,
+        }
+    }
 }
 
 impl DebugInfo {
+    /// The default number of frames without an update after which a value is dimmed.
+    pub const DEFAULT_STALE_AFTER_FRAMES: u64 = 60;
+    /// The default number of frames without an update after which a value is dropped.
+    pub const DEFAULT_DROP_AFTER_FRAMES: u64 = 600;
+
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Sets the number of frames without an update after which values are dimmed and dropped.
+    pub fn set_staleness(&mut self, stale_after_frames: u64, drop_after_frames: u64) {
+        self.stale_after_frames = stale_after_frames;
+        self.drop_after_frames = drop_after_frames;
+    }
+
+    /// Sets the value for `key`.
+    pub fn set(&mut self, key: impl Into<String>, value: impl Into<DebugValue>) {
+        self.insert(key.into(), value.into(), false);
+    }
+
+    /// Sets the value for `key`. It is never considered stale.
+    pub fn set_persistent(&mut self, key: impl Into<String>, value: impl Into<DebugValue>) {
+        self.insert(key.into(), value.into(), true);
+    }
+
+    pub fn set_f64(&mut self, key: impl Into<String>, value: f64) {
+        self.set(key, value);
+    }
+
+    pub fn set_i64(&mut self, key: impl Into<String>, value: i64) {
+        self.set(key, value);
+    }
+
+    pub fn set_str(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.set(key, value.into());
+    }
+
+    pub fn set_duration(&mut self, key: impl Into<String>, value: Duration) {
+        self.set(key, value);
+    }
+
+    fn insert(&mut self, key: String, value: DebugValue, persistent: bool) {
+        self.entries.insert(
+            key,
+            DebugEntry {
+                value,
+                last_updated_frame: self.frame,
+                persistent,
+            },
+        );
+    }
+
+    /// Returns the value for `key`, if any.
+    pub fn get(&self, key: &str) -> Option<&DebugValue> {
+        self.entries.get(key).map(|e| &e.value)
+    }
+
+    /// Returns true if there is a value for `key`.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Removes the value for `key`.
+    pub fn remove(&mut self, key: &str) -> Option<DebugValue> {
+        self.entries.remove(key).map(|e| e.value)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns true if the value for `key` was not updated recently.
+    pub fn is_stale(&self, key: &str) -> bool {
+        self.entries
+            .get(key)
+            .is_some_and(|e| self.entry_is_stale(e))
+    }
+
+    fn frames_since_update(&self, entry: &DebugEntry) -> u64 {
+        self.frame - entry.last_updated_frame
+    }
+
+    fn entry_is_stale(&self, entry: &DebugEntry) -> bool {
+        !entry.persistent && self.frames_since_update(entry) > self.stale_after_frames
+    }
+
+    /// Advances the frame counter and drops values that were not updated for too long.
+    fn next_frame(&mut self) {
+        self.frame += 1;
+        let frame = self.frame;
+        let drop_after_frames = self.drop_after_frames;
+        self.entries
+            .retain(|_, e| e.persistent || frame - e.last_updated_frame <= drop_after_frames);
+    }
+
+    /// Returns the overlay's lines: values grouped by their key's `.`-separated prefixes.
+    pub fn lines(&self) -> Vec<DebugLine> {
+        let mut entries = self
+            .entries
+            .iter()
+            .map(|(key, entry)| (key.split('.').collect::<Vec<_>>(), entry))
+            .collect::<Vec<_>>();
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let mut lines = vec![];
+        let mut prev_group: &[&str] = &[];
+        for (segments, entry) in &entries {
+            let (leaf, group) = segments.split_last().unwrap();
+            let common = prev_group
+                .iter()
+                .zip(group.iter())
+                .take_while(|(a, b)| a == b)
+                .count();
+            for (indent, name) in group.iter().enumerate().skip(common) {
+                lines.push(DebugLine {
+                    indent,
+                    text: name.to_string(),
+                    stale: false,
+                });
+            }
+            lines.push(DebugLine {
+                indent: group.len(),
+                text: format!("{leaf}: {}", entry.value),
+                stale: self.entry_is_stale(entry),
+            });
+            prev_group = group;
+        }
+        lines
+    }
+}
+
+/// A part of the [`DebugInfoComponent`]'s overlay, see [`DebugInfoComponent::with_sections`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    /// The key bindings.
+    Help,
+    /// The current and maximum frame time.
+    FrameTime,
+    /// The FPS and the FPS mode.
+    Fps,
+    /// The display size, simulated color vision, game seed and debounced keys.
+    State,
+    /// The values of [`SharedState::debug_info`].
+    Custom,
+    /// The [`DebugMessage`]s.
+    Messages,
+}
+
+impl Section {
+    pub const ALL: [Section; 6] = [
+        Section::Help,
+        Section::FrameTime,
+        Section::Fps,
+        Section::State,
+        Section::Custom,
+        Section::Messages,
+    ];
+}
+
+/// How much the [`DebugInfoComponent`] shows. Cycled at runtime with its verbosity key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Verbosity {
+    /// Only the FPS.
+    Minimal,
+    /// All configured sections.
+    #[default]
+    Full,
+}
+
+impl Verbosity {
+    /// Returns the next verbosity level, wrapping around.
+    pub fn next(self) -> Self {
+        match self {
+            Verbosity::Minimal => Verbosity::Full,
+            Verbosity::Full => Verbosity::Minimal,
+        }
+    }
 }
 
 /// A component that displays debug information on the screen.
@@ -58,12 +528,18 @@ pub struct DebugInfoComponent {
     min_frametime_ns: u128,
     last_fps_time: Instant,
     fps: f64,
-    target_fps: Option<f64>,
+    fps_mode: FpsMode,
     frames_since_last_fps: u32,
     num_events: u64,
     num_update_calls: u64,
     sum_actual_dts: f64,
     last_actual_fps_computed: f64,
+    anchor: OverlayAnchor,
+    placement: Option<Placement>,
+    sections: Vec<Section>,
+    verbosity: Verbosity,
+    verbosity_key: KeyCode,
+    cvd_key: KeyCode,
 }
 
 impl DebugInfoComponent {
@@ -75,12 +551,48 @@ impl DebugInfoComponent {
             min_frametime_ns: u128::MAX,
             last_fps_time: Instant::now(),
             fps: 0.0,
-            target_fps: None,
+            fps_mode: FpsMode::Unlimited,
             frames_since_last_fps: 0,
             num_events: 0,
             num_update_calls: 0,
             sum_actual_dts: 0.0,
             last_actual_fps_computed: 0.0,
+            anchor: OverlayAnchor::new(Corner::TopLeft),
+            placement: None,
+            sections: Section::ALL.to_vec(),
+            verbosity: Verbosity::Full,
+            verbosity_key: KeyCode::F(3),
+            cvd_key: KeyCode::F(1),
+        }
+    }
+}
+
+impl DebugInfoComponent {
+    /// Returns the simulated color vision deficiency, if any.
+    fn simulated_cvd<S>(shared_state: &SharedState<S>) -> Option<ColorVisionDeficiency> {
+        shared_state.post_processes.iter().find_map(|p| match p {
+            PostProcess::SimulateCvd(kind) => Some(*kind),
+            _ => None,
+        })
+    }
+
+    /// Cycles the simulated color vision deficiency, starting and ending with none.
+    fn cycle_simulated_cvd<S>(shared_state: &mut SharedState<S>) {
+        let next = match Self::simulated_cvd(shared_state) {
+            None => Some(ColorVisionDeficiency::ALL[0]),
+            Some(kind) => {
+                let idx = ColorVisionDeficiency::ALL.iter().position(|k| *k == kind);
+                idx.and_then(|idx| ColorVisionDeficiency::ALL.get(idx + 1).copied())
+            }
+        };
+        shared_state
+            .post_processes
+            .retain(|p| !matches!(p, PostProcess::SimulateCvd(_)));
+        if let Some(kind) = next {
+            // last, to simulate how the final image is seen
+            shared_state
+                .post_processes
+                .push(PostProcess::simulate_cvd(kind));
         }
     }
 }
@@ -91,6 +603,14 @@ impl DebugInfoComponent {
 }
 
 impl<S> Component<S> for DebugInfoComponent {
+    fn runs_while_paused(&self) -> bool {
+        true
+    }
+
+    fn ignores_filters(&self) -> bool {
+        true
+    }
+
     fn on_event(
         &mut self,
         _event: Event,
@@ -136,86 +656,735 @@ impl<S> Component<S> for DebugInfoComponent {
             self.sum_actual_dts = 0.0;
             self.last_fps_time = current_time;
         }
-        self.target_fps = shared_state.target_fps;
+        self.fps_mode = shared_state.fps.mode;
 
-        // expire debug messages
-        shared_state
-            .debug_messages
-            .retain(|msg| msg.expiry_time > current_time);
-        // only keep the 10 most recent messages
-        if shared_state.debug_messages.len() > 10 {
-            shared_state
-                .debug_messages
-                .drain(0..shared_state.debug_messages.len() - 10);
+        if shared_state.pressed_keys.did_press(self.cvd_key) {
+            Self::cycle_simulated_cvd(shared_state);
+        }
+        if shared_state.pressed_keys.did_press(self.verbosity_key) {
+            self.verbosity = self.verbosity.next();
         }
+
+        shared_state.debug_info.next_frame();
+
+        // expire debug messages, and only keep the 10 most recent ones
+        shared_state.debug_messages.expire(current_time, 10);
+
+        let lines = self.lines(shared_state);
+        let width = lines.iter().map(OverlayLine::width).max().unwrap_or(0);
+        self.placement =
+            Some(shared_state.place_overlay(Self::OVERLAY, self.anchor, width, lines.len()));
     }
 
-    fn render(&self, renderer: &mut dyn Renderer, shared_state: &SharedState<S>, depth_base: i32) {
+    fn render(&self, renderer: &mut dyn Renderer, shared_state: &SharedState<S>, _depth_base: i32) {
         let depth_base = i32::MAX - 100;
-        let mut y = 0;
-        "Help: q to quit, l to lock/unlock FPS, scroll to change FPS, b to cheat blocks, p to toggle parallax, m to toggle minimap, i to toggle debug info, r to start/stop recording".render(
-            renderer,
-            0,
-            y,
-            depth_base,
-        );
-        y += 1;
-
-        format!("Frame time: {} ns", self.frametime_ns).render(renderer, 0, y, depth_base);
-        y += 1;
-        format!("Max frame time: {} ns", self.max_frametime_ns).render(renderer, 0, y, depth_base);
-        y += 1;
-        // format!("Min frame time: {} ns", self.min_frametime_ns).render(&mut renderer, 0, y, depth_base);
-        // y += 1;
-        let target_str = if let Some(target_fps) = self.target_fps {
-            format!("{:.0}", target_fps)
+        let Some(placement) = self.placement else {
+            return;
+        };
+        let rect = placement.rect;
+        for (y, line) in self
+            .lines(shared_state)
+            .iter()
+            .take(rect.height)
+            .enumerate()
+        {
+            let (x, y) = (rect.x + line.indent, rect.y + y);
+            match line.color {
+                Some(color) => line
+                    .text
+                    .with_color(color)
+                    .render(renderer, x, y, depth_base),
+                None => line.text.render(renderer, x, y, depth_base),
+            }
+        }
+    }
+}
+
+impl DebugInfoComponent {
+    /// The name of the overlay in the [`OverlayLayoutManager`](crate::components::overlay_layout::OverlayLayoutManager).
+    pub const OVERLAY: &'static str = "debug info";
+    /// The width the help text is wrapped to.
+    const HELP_WIDTH: usize = 60;
+
+    /// Sets the preferred corner of the overlay. The default is the top left corner.
+    pub fn with_anchor(mut self, anchor: OverlayAnchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    /// Sets which sections are shown at [`Verbosity::Full`], in their fixed order. By default,
+    /// all sections are shown.
+    pub fn with_sections(mut self, sections: &[Section]) -> Self {
+        self.sections = sections.to_vec();
+        self
+    }
+
+    pub fn with_verbosity(mut self, verbosity: Verbosity) -> Self {
+        self.verbosity = verbosity;
+        self
+    }
+
+    /// Sets the key that cycles the verbosity. The default is F3.
+    pub fn with_verbosity_key(mut self, key: KeyCode) -> Self {
+        self.verbosity_key = key;
+        self
+    }
+
+    /// Sets the key that cycles the simulated color vision deficiency. The default is F1.
+    pub fn with_cvd_key(mut self, key: KeyCode) -> Self {
+        self.cvd_key = key;
+        self
+    }
+
+    pub fn verbosity(&self) -> Verbosity {
+        self.verbosity
+    }
+
+    fn shows(&self, section: Section) -> bool {
+        match self.verbosity {
+            Verbosity::Minimal => section == Section::Fps,
+            Verbosity::Full => self.sections.contains(&section),
+        }
+    }
+
+    /// Returns the lines of the overlay.
+    fn lines<S>(&self, shared_state: &SharedState<S>) -> Vec<OverlayLine> {
+        let mut lines = vec![];
+        if self.shows(Section::Help) {
+            let help = Paragraph::new(
+                format!(
+                    "Help: q to quit, l to lock/unlock FPS, scroll to change FPS, ctrl+shift+d to open cheats, p to toggle parallax, m to toggle minimap, i to toggle debug info, {} to change debug info verbosity, r to start/stop recording, {} to simulate color blindness",
+                    format_key(self.verbosity_key),
+                    format_key(self.cvd_key),
+                ),
+                shared_state.display_info.width().min(Self::HELP_WIDTH),
+            );
+            lines.extend(help.lines().into_iter().map(OverlayLine::new));
+        }
+
+        if self.shows(Section::FrameTime) {
+            // fixed widths, so that the rows do not jitter as the numbers change
+            let nanos = |ns: u128| {
+                FixedWidthNumber::for_max(ns as f64, 9_999_999_999.0).with_separator(',')
+            };
+            let row = HudRow::new()
+                .with_gap(1)
+                .field("Frame time:", nanos(self.frametime_ns))
+                .text("ns", 2);
+            lines.push(OverlayLine::new(row.format()));
+            let row = HudRow::new()
+                .with_gap(1)
+                .field("Max frame time:", nanos(self.max_frametime_ns))
+                .text("ns", 2);
+            lines.push(OverlayLine::new(row.format()));
+            // lines.push(OverlayLine::new(format!("Min frame time: {} ns", self.min_frametime_ns)));
+        }
+        if self.shows(Section::Fps) {
+            let target_str = match self.fps_mode {
+                FpsMode::Target(target_fps) => format!("({:.0})", target_fps),
+                FpsMode::Unlimited => "(Unlocked)".to_string(),
+                FpsMode::PowerSaver => "(Power saver)".to_string(),
+            };
+            let row = HudRow::new()
+                .with_gap(1)
+                .field(
+                    "FPS:",
+                    FixedWidthNumber::for_max(self.fps, 99_999.0).with_decimals(2),
+                )
+                .text(target_str, 13);
+            lines.push(OverlayLine::new(row.format()));
+            // lines.push(OverlayLine::new(format!("Achievable FPS: {:.2}", self.last_actual_fps_computed)));
+        }
+        if self.shows(Section::State) {
+            lines.push(OverlayLine::new(format!(
+                "Display size: {}x{}",
+                shared_state.display_info.width(),
+                shared_state.display_info.height()
+            )));
+            let vision = match Self::simulated_cvd(shared_state) {
+                Some(kind) => format!("{kind:?}"),
+                None => "normal".to_string(),
+            };
+            lines.push(OverlayLine::new(format!("Color vision: {vision}")));
+            lines.push(OverlayLine::new(format!("Game seed: {:?}", get_seed_opt())));
+            lines.push(OverlayLine::new(format!(
+                "Debounced keys: {:?}",
+                shared_state.debounced_keys.down_keys()
+            )));
+            // lines.push(OverlayLine::new(format!("Events: {}", self.num_events)));
+            // lines.push(OverlayLine::new(format!("Update calls: {}", self.num_update_calls)));
+        }
+
+        if self.shows(Section::Custom) && !shared_state.debug_info.is_empty() {
+            lines.push(OverlayLine::new("Debug values:"));
+            for line in shared_state.debug_info.lines() {
+                lines.push(OverlayLine {
+                    indent: 2 * (line.indent + 1),
+                    text: line.text,
+                    color: line.stale.then_some([120, 120, 120]),
+                });
+            }
+        }
+
+        if self.shows(Section::Messages) {
+            for dbg_msg in shared_state.debug_messages.iter() {
+                lines.extend(dbg_msg.text().lines().map(|line| OverlayLine {
+                    color: dbg_msg.color(),
+                    ..OverlayLine::new(line)
+                }));
+            }
+        }
+        lines
+    }
+}
+
+/// A line of the [`DebugInfoComponent`]'s overlay.
+struct OverlayLine {
+    indent: usize,
+    text: String,
+    color: Option<[u8; 3]>,
+}
+
+impl OverlayLine {
+    fn new(text: impl Into<String>) -> Self {
+        Self {
+            indent: 0,
+            text: text.into(),
+            color: None,
+        }
+    }
+
+    fn width(&self) -> usize {
+        self.indent + self.text.chars().count()
+    }
+}
+
+/// A component that draws a bar chart of the most recent frame times in a corner of the screen.
+///
+/// Each column is one frame, drawn with block characters of varying heights. Frames within the
+/// budget of [`FpsMode::Target`] are green and slower frames are red, and a horizontal line marks
+/// the budget. The chart is toggled with the same key as the [`DebugInfoComponent`] by default.
+pub struct FrameTimeGraphComponent {
+    /// The `actual_dt` of the most recent frames, oldest first.
+    samples: VecDeque<f64>,
+    capacity: usize,
+    height: usize,
+    anchor: OverlayAnchor,
+    toggle_key: Option<char>,
+    visible: bool,
+    /// The frame time budget in seconds, if the FPS are locked.
+    budget: Option<f64>,
+    /// The frame time that corresponds to the full height of the chart.
+    scale: f64,
+    placement: Option<Placement>,
+}
+
+impl FrameTimeGraphComponent {
+    /// The name of the overlay in the [`OverlayLayoutManager`](crate::components::overlay_layout::OverlayLayoutManager).
+    pub const OVERLAY: &'static str = "frame time graph";
+    pub const DEFAULT_CAPACITY: usize = 240;
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    const BUDGET_LINE: char = '─';
+    const FAST_COLOR: [u8; 3] = [0, 200, 0];
+    const SLOW_COLOR: [u8; 3] = [220, 0, 0];
+    const BUDGET_COLOR: [u8; 3] = [120, 120, 120];
+
+    /// Creates a chart of the last [`DEFAULT_CAPACITY`](Self::DEFAULT_CAPACITY) frames that is
+    /// 4 rows high, in the bottom right corner.
+    pub fn new() -> Self {
+        Self {
+            samples: VecDeque::with_capacity(Self::DEFAULT_CAPACITY),
+            capacity: Self::DEFAULT_CAPACITY,
+            height: 4,
+            anchor: OverlayAnchor::new(Corner::BottomRight),
+            toggle_key: Some('i'),
+            visible: true,
+            budget: None,
+            scale: 0.0,
+            placement: None,
+        }
+    }
+
+    /// Sets the number of frames that are kept. The chart is at most this many columns wide.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self.samples = VecDeque::with_capacity(self.capacity);
+        self
+    }
+
+    /// Sets the number of rows of the chart.
+    pub fn with_height(mut self, height: usize) -> Self {
+        self.height = height.max(1);
+        self
+    }
+
+    /// Sets the preferred corner of the chart. The default is the bottom right corner.
+    pub fn with_anchor(mut self, anchor: OverlayAnchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    /// Sets the key that toggles the chart, or disables toggling with `None`.
+    pub fn with_toggle_key(mut self, key: Option<char>) -> Self {
+        self.toggle_key = key;
+        self
+    }
+
+    /// Returns the number of columns the chart has on a screen `display_width` columns wide.
+    fn width(&self, display_width: usize) -> usize {
+        self.capacity.min(display_width)
+    }
+
+    /// Returns the glyph and color of the cell at `column` and `row` of a chart `width` columns
+    /// wide, where the rightmost column is the most recent frame and row 0 is the top.
+    fn cell(&self, column: usize, row: usize, width: usize) -> (char, [u8; 3]) {
+        let empty = (' ', Self::BUDGET_COLOR);
+        if self.scale <= 0.0 {
+            return empty;
+        }
+        let level = self.height - 1 - row;
+        let budget_level = self.budget.map(|budget| {
+            ((budget / self.scale * self.height as f64) as usize).min(self.height - 1)
+        });
+        let budget_line = if budget_level == Some(level) {
+            (Self::BUDGET_LINE, Self::BUDGET_COLOR)
         } else {
-            "Unlocked".to_string()
+            empty
+        };
+        // the columns on the left are empty until enough frames were recorded
+        let Some(index) = (self.samples.len() + column).checked_sub(width) else {
+            return budget_line;
+        };
+        let dt = self.samples[index];
+        let eighths = (dt / self.scale * (self.height * 8) as f64).round() as usize;
+        let fill = eighths.saturating_sub(level * 8).min(8);
+        if fill == 0 {
+            return budget_line;
+        }
+        let color = match self.budget {
+            Some(budget) if dt > budget => Self::SLOW_COLOR,
+            _ => Self::FAST_COLOR,
         };
-        format!("FPS: {:.2} ({})", self.fps, target_str).render(renderer, 0, y, depth_base);
-        y += 1;
-        // format!("Achievable FPS: {:.2}", self.last_actual_fps_computed).render(
-        //     &mut renderer,
-        //     0,
-        //     y,
-        //     depth_base,
-        // );
-        // y += 1;
-        // let debug_string = format!("DebugInfo: {:#?}", shared_state.debug_info);
-        // for line in debug_string.lines() {
-        //     line.render(&mut renderer, 0, y, depth_base);
-        //     y += 1;
-        // }
-        format!(
-            "Display size: {}x{}",
-            shared_state.display_info.width(),
-            shared_state.display_info.height()
-        )
-        .render(renderer, 0, y, depth_base);
-        y += 1;
-        format!("Game seed: {:?}", get_seed_opt()).render(renderer, 0, y, depth_base);
-        y += 1;
-        format!("Debounced keys: {:?}", shared_state.debounced_down_keys)
-            .render(renderer, 0, y, depth_base);
-        y += 1;
-        // format!("Events: {}", self.num_events).render(&mut renderer, 0, y, depth_base);
-        // y += 1;
-        // format!("Frames since last FPS: {}", self.frames_since_last_fps).render(&mut renderer, 0, y, depth_base);
-        // y += 1;
-        // format!("Update calls: {}", self.num_update_calls).render(&mut renderer, 0, y, depth_base);
-
-        if !shared_state.debug_info.custom.is_empty() {
-            let custom_debug_s =
-                format!("Custom debug info: {:#?}", shared_state.debug_info.custom);
-            custom_debug_s.render(renderer, 0, y, depth_base);
-            y += custom_debug_s.lines().count();
-        }
-
-        for dbg_msg in shared_state.debug_messages.iter() {
-            for line in dbg_msg.message.as_str().lines() {
-                line.render(renderer, 0, y, depth_base);
-                y += 1;
+        (Self::BLOCKS[fill - 1], color)
+    }
+}
+
+impl Default for FrameTimeGraphComponent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Component<S> for FrameTimeGraphComponent {
+    fn runs_while_paused(&self) -> bool {
+        true
+    }
+
+    fn update(&mut self, update_info: UpdateInfo, shared_state: &mut SharedState<S>) {
+        if let Some(key) = self.toggle_key
+            && shared_state.pressed_keys.did_press_char_ignore_case(key)
+        {
+            self.visible = !self.visible;
+        }
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(update_info.actual_dt);
+
+        if !self.visible {
+            if self.placement.take().is_some() {
+                shared_state.overlay_layout.release(Self::OVERLAY);
             }
+            return;
         }
+        self.budget = match shared_state.fps.mode {
+            FpsMode::Target(fps) if fps > 0.0 => Some(1.0 / fps),
+            _ => None,
+        };
+        let width = self.width(shared_state.display_info.width());
+        let max_dt = self
+            .samples
+            .iter()
+            .rev()
+            .take(width)
+            .copied()
+            .fold(0.0, f64::max);
+        // the budget line stays at half the height until a frame is slower than twice the budget
+        self.scale = max_dt.max(self.budget.map_or(0.0, |budget| 2.0 * budget));
+        // placed every frame, so that the chart moves with its corner when the terminal is resized
+        self.placement =
+            Some(shared_state.place_overlay(Self::OVERLAY, self.anchor, width, self.height));
+    }
+
+    fn render(
+        &self,
+        renderer: &mut dyn Renderer,
+        _shared_state: &SharedState<S>,
+        _depth_base: i32,
+    ) {
+        let depth_base = i32::MAX - 100;
+        let Some(placement) = self.placement else {
+            return;
+        };
+        let rect = placement.rect;
+        for row in 0..rect.height.min(self.height) {
+            for column in 0..rect.width {
+                let (c, color) = self.cell(column, row, rect.width);
+                let pixel = Pixel::new(c).with_color(color);
+                renderer.render_pixel(rect.x + column, rect.y + row, pixel, depth_base);
+            }
+        }
+    }
+}
+
+/// A component that shows the most recent [`DebugMessage`]s in a pane, including expired ones, so
+/// that messages that flashed by can be reviewed. PageUp and PageDown scroll the pane.
+pub struct DebugLogComponent {
+    capacity: usize,
+    height: usize,
+    anchor: OverlayAnchor,
+    /// The number of messages scrolled up from the most recent one.
+    scroll: usize,
+    placement: Option<Placement>,
+}
+
+impl DebugLogComponent {
+    /// The name of the overlay in the [`OverlayLayoutManager`](crate::components::overlay_layout::OverlayLayoutManager).
+    pub const OVERLAY: &'static str = "debug log";
+    pub const DEFAULT_CAPACITY: usize = 500;
+    const MAX_WIDTH: usize = 80;
+
+    /// Creates a pane of 10 rows in the bottom left corner that keeps the last
+    /// [`DEFAULT_CAPACITY`](Self::DEFAULT_CAPACITY) messages.
+    pub fn new() -> Self {
+        Self {
+            capacity: Self::DEFAULT_CAPACITY,
+            height: 10,
+            anchor: OverlayAnchor::new(Corner::BottomLeft),
+            scroll: 0,
+            placement: None,
+        }
+    }
+
+    /// Sets the number of messages that are kept.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Sets the number of rows of the pane.
+    pub fn with_height(mut self, height: usize) -> Self {
+        self.height = height.max(1);
+        self
+    }
+
+    /// Sets the preferred corner of the pane. The default is the bottom left corner.
+    pub fn with_anchor(mut self, anchor: OverlayAnchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    /// Returns the messages in the pane, oldest first.
+    fn visible<'a, S>(
+        &self,
+        shared_state: &'a SharedState<S>,
+    ) -> impl Iterator<Item = &'a DebugMessage> {
+        let history = shared_state.debug_messages.history();
+        let end = history.len() - self.scroll.min(history.len());
+        history.range(end.saturating_sub(self.height)..end)
+    }
+}
+
+impl Default for DebugLogComponent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Component<S> for DebugLogComponent {
+    fn setup(&mut self, _setup_info: &SetupInfo, shared_state: &mut SharedState<S>) {
+        shared_state.debug_messages.set_history_limit(self.capacity);
+    }
+
+    fn runs_while_paused(&self) -> bool {
+        true
+    }
+
+    fn required_components(&self) -> Vec<Dependency> {
+        vec![Dependency::of::<KeyPressRecorderComponent>()]
+    }
+
+    fn update(&mut self, _update_info: UpdateInfo, shared_state: &mut SharedState<S>) {
+        let keys = &shared_state.pressed_keys;
+        if keys.did_press(KeyCode::PageUp) {
+            self.scroll += self.height;
+        }
+        if keys.did_press(KeyCode::PageDown) {
+            self.scroll = self.scroll.saturating_sub(self.height);
+        }
+        let len = shared_state.debug_messages.history().len();
+        self.scroll = self.scroll.min(len.saturating_sub(self.height));
+
+        if len == 0 {
+            if self.placement.take().is_some() {
+                shared_state.overlay_layout.release(Self::OVERLAY);
+            }
+            return;
+        }
+        let width = shared_state.display_info.width().min(Self::MAX_WIDTH);
+        self.placement = Some(shared_state.place_overlay(
+            Self::OVERLAY,
+            self.anchor,
+            width,
+            self.height.min(len),
+        ));
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, shared_state: &SharedState<S>, _depth_base: i32) {
+        let depth_base = i32::MAX - 100;
+        let Some(placement) = self.placement else {
+            return;
+        };
+        let rect = placement.rect;
+        for (y, msg) in self.visible(shared_state).take(rect.height).enumerate() {
+            let text = format!("{}: {}", msg.severity(), msg.text());
+            let line = text.lines().next().unwrap_or_default();
+            let line = line.chars().take(rect.width).collect::<String>();
+            let (x, y) = (rect.x, rect.y + y);
+            match msg.color() {
+                Some(color) => line.with_color(color).render(renderer, x, y, depth_base),
+                None => line.render(renderer, x, y, depth_base),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::keyboard::PressedKeys;
+
+    #[test]
+    fn test_staleness_expiry() {
+        let mut info = DebugInfo::new();
+        info.set_staleness(2, 4);
+        info.set_f64("updated", 1.0);
+        info.set_f64("forgotten", 1.0);
+        info.set_persistent("once", "hello");
+        for _ in 0..3 {
+            info.next_frame();
+            info.set_f64("updated", 2.0);
+        }
+        assert!(!info.is_stale("updated"));
+        assert!(info.is_stale("forgotten"));
+        assert!(!info.is_stale("once"));
+        assert!(info.lines().iter().any(|l| l.stale));
+
+        for _ in 0..2 {
+            info.next_frame();
+            info.set_f64("updated", 3.0);
+        }
+        assert_eq!(info.get("forgotten"), None);
+        assert_eq!(info.get("updated"), Some(&DebugValue::F64(3.0)));
+        assert_eq!(
+            info.get("once"),
+            Some(&DebugValue::Str("hello".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_grouped_lines_order() {
+        let keys = [
+            "player.y",
+            "fps",
+            "world.chunks",
+            "player.vel.x",
+            "player.x",
+        ];
+        let mut info = DebugInfo::new();
+        for key in keys {
+            info.set_i64(key, 1);
+        }
+        let lines = info
+            .lines()
+            .into_iter()
+            .map(|l| format!("{}{}", "  ".repeat(l.indent), l.text))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                "fps: 1",
+                "player",
+                "  vel",
+                "    x: 1",
+                "  x: 1",
+                "  y: 1",
+                "world",
+                "  chunks: 1",
+            ]
+        );
+
+        // insertion order and repeated updates do not change the output
+        let mut other = DebugInfo::new();
+        for key in keys.iter().rev().chain(keys.iter()) {
+            other.set_i64(*key, 1);
+        }
+        assert_eq!(info.lines(), other.lines());
+    }
+
+    #[test]
+    fn test_value_formatting() {
+        assert_eq!(DebugValue::from(1.23456).to_string(), "1.23");
+        assert_eq!(DebugValue::from(0.0001).to_string(), "1.00e-4");
+        assert_eq!(DebugValue::from((1.0, -2.5)).to_string(), "(1.00, -2.50)");
+        assert_eq!(
+            DebugValue::from(Duration::from_micros(1500)).to_string(),
+            "1.50 ms"
+        );
+        assert_eq!(
+            DebugValue::from(Duration::from_nanos(42)).to_string(),
+            "42 ns"
+        );
+    }
+
+    fn texts<S>(component: &DebugInfoComponent, shared_state: &SharedState<S>) -> Vec<String> {
+        component
+            .lines(shared_state)
+            .into_iter()
+            .map(|l| l.text)
+            .collect()
+    }
+
+    #[test]
+    fn test_sections_and_verbosity() {
+        let mut shared_state = SharedState::<()>::new(80, 24);
+        shared_state.debug_info.set_i64("b", 2);
+        shared_state.debug_info.set_i64("a", 1);
+        shared_state
+            .debug_messages
+            .push(DebugMessage::new_3s("hello"));
+
+        let full = texts(&DebugInfoComponent::new(), &shared_state);
+        assert!(full[0].starts_with("Help:"));
+        assert!(full.iter().any(|t| t.starts_with("Frame time:")));
+        assert_eq!(full.last().unwrap(), "hello");
+
+        let component = DebugInfoComponent::new().with_sections(&[Section::Fps, Section::Custom]);
+        let lines = texts(&component, &shared_state);
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("FPS:"));
+        // sorted by key, independent of insertion order
+        assert_eq!(lines[1..], ["Debug values:", "a: 1", "b: 2"]);
+
+        let mut component = component.with_verbosity(Verbosity::Minimal);
+        let lines = texts(&component, &shared_state);
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].starts_with("FPS:"));
+
+        shared_state.pressed_keys.insert(KeyCode::F(3));
+        component.update(UpdateInfo::at(Instant::now()), &mut shared_state);
+        assert_eq!(component.verbosity(), Verbosity::Full);
+        assert_eq!(texts(&component, &shared_state).len(), 4);
+    }
+
+    #[test]
+    fn test_frame_time_graph_glyphs() {
+        let mut shared_state = SharedState::<()>::new(80, 24);
+        shared_state.fps.mode = FpsMode::Target(100.0);
+        let mut graph = FrameTimeGraphComponent::new()
+            .with_capacity(6)
+            .with_height(2);
+        // the budget is 10ms, so the chart is 20ms high and the budget line is on the top row
+        for ms in [5.0, 10.0, 15.0, 20.0, 2.5] {
+            let mut info = UpdateInfo::at(Instant::now());
+            info.actual_dt = ms / 1000.0;
+            graph.update(info, &mut shared_state);
+        }
+        let rows = (0..2)
+            .map(|row| (0..6).map(|c| graph.cell(c, row, 6).0).collect::<String>())
+            .collect::<Vec<_>>();
+        // the first column is empty, since only 5 frames were recorded
+        assert_eq!(rows, ["───▄█─", " ▄███▂"]);
+        // frames slower than the budget are red
+        assert_eq!(graph.cell(2, 1, 6).1, FrameTimeGraphComponent::FAST_COLOR);
+        assert_eq!(graph.cell(3, 1, 6).1, FrameTimeGraphComponent::SLOW_COLOR);
+
+        // the oldest frames are dropped, and without a budget the slowest frame is full height
+        shared_state.fps.mode = FpsMode::Unlimited;
+        for _ in 0..6 {
+            let mut info = UpdateInfo::at(Instant::now());
+            info.actual_dt = 0.01;
+            graph.update(info, &mut shared_state);
+        }
+        assert_eq!(graph.samples.len(), 6);
+        assert_eq!(
+            graph.cell(0, 0, 6),
+            ('█', FrameTimeGraphComponent::FAST_COLOR)
+        );
+    }
+
+    #[test]
+    fn test_dedup_messages() {
+        let mut messages = DebugMessages::new();
+        messages.push(DebugMessage::new_3s("a"));
+        for i in 0..3 {
+            messages.push(DebugMessage::warn_5s(format!("tick {i}")).with_dedup_key("tick"));
+        }
+        messages.push(DebugMessage::new_3s("a"));
+        let texts = messages.iter().map(DebugMessage::text).collect::<Vec<_>>();
+        // messages without a key are never deduplicated
+        assert_eq!(texts, ["a", "tick 2 (x3)", "a"]);
+        assert_eq!(
+            messages.iter().nth(1).unwrap().severity(),
+            Severity::Warning
+        );
+        // the history is disabled by default
+        assert!(messages.history().is_empty());
+
+        // expiring the message restarts its count
+        messages.expire(Instant::now() + Duration::from_secs(10), 10);
+        assert!(messages.is_empty());
+        messages.push(DebugMessage::error_5s("tick").with_dedup_key("tick"));
+        assert_eq!(messages.iter().next().unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_debug_log_scrolling() {
+        let mut shared_state = SharedState::<()>::new(80, 24);
+        let mut log = DebugLogComponent::new().with_capacity(5).with_height(2);
+        shared_state.debug_messages.set_history_limit(log.capacity);
+        for i in 0..7 {
+            shared_state
+                .debug_messages
+                .push(DebugMessage::new_3s(i.to_string()));
+        }
+        // repeated messages are collapsed in the history too
+        for _ in 0..2 {
+            shared_state
+                .debug_messages
+                .push(DebugMessage::new_3s("spam").with_dedup_key("spam"));
+        }
+        // expired messages stay in the log
+        shared_state
+            .debug_messages
+            .expire(Instant::now() + Duration::from_secs(10), 10);
+        let visible = |log: &DebugLogComponent, shared_state: &SharedState<()>| {
+            log.visible(shared_state)
+                .map(DebugMessage::text)
+                .collect::<Vec<_>>()
+        };
+
+        log.update(UpdateInfo::at(Instant::now()), &mut shared_state);
+        assert_eq!(visible(&log, &shared_state), ["6", "spam (x2)"]);
+        shared_state.pressed_keys.insert(KeyCode::PageUp);
+        log.update(UpdateInfo::at(Instant::now()), &mut shared_state);
+        assert_eq!(visible(&log, &shared_state), ["4", "5"]);
+        // only 5 messages are kept, so scrolling stops at the oldest
+        log.update(UpdateInfo::at(Instant::now()), &mut shared_state);
+        assert_eq!(visible(&log, &shared_state), ["3", "4"]);
+        shared_state.pressed_keys = PressedKeys::new();
+        shared_state.pressed_keys.insert(KeyCode::PageDown);
+        log.update(UpdateInfo::at(Instant::now()), &mut shared_state);
+        assert_eq!(visible(&log, &shared_state), ["5", "6"]);
     }
 }
@@ -0,0 +1,113 @@
+//! Dims the game while it is paused.
+//!
+//! The [`DimBehindComponent`] sets [`SharedState::overlay`] while the game is paused, either with
+//! [`SharedState::paused`] or by a pause menu, e.g. the settings menu, that whitelists components. The overlay is applied by the renderer,
+//! so it costs nothing while the game runs and writes nothing to the terminal while the paused
+//! frame does not change.
+//!
+//! Components that pause the game whitelist the [`DimBehindComponent`], so that it keeps
+//! running. Custom dialogs can do the same, or set [`SharedState::overlay`] themselves.
+//!
+//! The component is not part of [`Game::install_recommended_components`](crate::Game::install_recommended_components),
+//! games that want the dimming add it themselves:
+//! ```rust
+//! # use teng::Game;
+//! # use teng::components::dim::DimBehindComponent;
+//! # fn install<W: std::io::Write>(game: &mut Game<W>) {
+//! game.add_component(Box::new(DimBehindComponent::new()));
+//! # }
+//! ```
+
+use crate::components::Component;
+use crate::rendering::renderer::Overlay;
+use crate::{ComponentFilter, SharedState, UpdateInfo};
+
+/// A component that dims the game behind pause menus.
+pub struct DimBehindComponent {
+    overlay: Overlay,
+    dimming: bool,
+}
+
+impl DimBehindComponent {
+    /// The depth of the built-in pause menus and overlays. Cells rendered at this depth or above
+    /// are not dimmed.
+    pub const MENU_DEPTH: i32 = i32::MAX - 100;
+
+    /// Creates a component that dims the game by half behind the built-in menus.
+    pub fn new() -> Self {
+        Self::with_overlay(Overlay::dim(0.5).below(Self::MENU_DEPTH))
+    }
+
+    /// Creates a component that applies `overlay` while the game is paused.
+    pub fn with_overlay(overlay: Overlay) -> Self {
+        Self {
+            overlay,
+            dimming: false,
+        }
+    }
+}
+
+impl Default for DimBehindComponent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Component<S> for DimBehindComponent {
+    fn runs_while_paused(&self) -> bool {
+        true
+    }
+
+    fn update(&mut self, _update_info: UpdateInfo, shared_state: &mut SharedState<S>) {
+        let paused = shared_state.paused
+            || !matches!(shared_state.component_filter(), None | Some(ComponentFilter::All));
+        if paused == self.dimming {
+            return;
+        }
+        self.dimming = paused;
+        if paused {
+            shared_state.overlay = Some(self.overlay);
+        } else if shared_state.overlay == Some(self.overlay) {
+            // only remove our own overlay
+            shared_state.overlay = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::time::Instant;
+
+    #[test]
+    fn test_dims_while_paused() {
+        let mut shared_state = SharedState::<()>::new(10, 5);
+        let mut component = DimBehindComponent::new();
+        let now = Instant::now();
+        let update_info = UpdateInfo::at(now);
+        component.update(update_info, &mut shared_state);
+        assert_eq!(shared_state.overlay, None);
+
+        shared_state.push_component_filter(ComponentFilter::Whitelist(HashSet::new()));
+        component.update(update_info, &mut shared_state);
+        assert_eq!(shared_state.overlay, Some(component.overlay));
+
+        shared_state.pop_component_filter();
+        component.update(update_info, &mut shared_state);
+        assert_eq!(shared_state.overlay, None);
+
+        shared_state.paused = true;
+        component.update(update_info, &mut shared_state);
+        assert_eq!(shared_state.overlay, Some(component.overlay));
+        shared_state.paused = false;
+        component.update(update_info, &mut shared_state);
+        assert_eq!(shared_state.overlay, None);
+
+        // an overlay set by someone else is kept
+        let tint = Overlay::tint([255, 0, 0], 0.2);
+        shared_state.overlay = Some(tint);
+        component.update(update_info, &mut shared_state);
+        assert_eq!(shared_state.overlay, Some(tint));
+    }
+}
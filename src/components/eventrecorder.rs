@@ -1,12 +1,20 @@
 //! This module contains components for recording and replaying events, as well as benchmarking frame counts.
 //!
 //! - `EventRecorderComponent`: Records events and saves them to a file.
-//! - `EventReplayerComponent`: Replays recorded events.
-//! - `BenchFrameCounter`: Counts the number of frames and reports it on quit.
+//! - `EventReplayerComponent`: Replays recorded events, optionally as a looping demo.
+//! - `BenchFrameCounter`: Counts the number of frames and reports frame time statistics on quit.
 
+use crate::components::problems::{Problem, Severity};
+use crate::rendering::render::Render;
+use crate::rendering::renderer::Renderer;
+use crate::seeds::get_seed_opt;
 use crate::{BreakingAction, Component, DebugMessage, SetupInfo, SharedState, UpdateInfo};
-use crossterm::event::Event;
+use crossterm::event::{
+    Event, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::io;
 use std::path::Path;
 use std::time::SystemTime;
 
@@ -19,21 +27,84 @@ pub struct RecordedEvent {
 }
 
 /// A recording of events.
+///
+/// Serialized recordings start with a header of [`Recording::MAGIC`] and the little-endian
+/// [`Recording::FORMAT_VERSION`], so that reading a recording of another version fails with an
+/// error instead of producing garbage.
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct Recording {
     pub events: Vec<RecordedEvent>,
+    /// The terminal size when the recording started.
     pub initial_display_size: (usize, usize),
     /// The time at which the recording stopped relative to its start.
     /// This is the same as the duration of the recording.
     /// Set when the recording is finished.
     pub duration_ns_offset: u128,
+    /// The global seed of the recorded game, if it was set, see [`crate::seeds`]. Set it to the
+    /// same value before replaying for a deterministic replay.
+    pub seed: Option<u64>,
 }
 
 impl Recording {
-    // TODO: handle errors
+    /// The first bytes of a serialized recording.
+    pub const MAGIC: [u8; 8] = *b"TENGREC\0";
+    /// The version of the serialized format, increased on every incompatible change.
+    pub const FORMAT_VERSION: u32 = 1;
+
+    /// Reads a recording from a file.
+    ///
+    /// Panics if the file cannot be read, see [`Recording::try_read_from_file`].
     pub fn read_from_file(path: impl AsRef<Path>) -> Self {
-        let file = std::fs::File::open(path).unwrap();
-        bincode::deserialize_from(file).unwrap()
+        Self::try_read_from_file(path).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Reads a recording from a file, or returns an error if the file cannot be read or is not a
+    /// valid recording of the current format version.
+    pub fn try_read_from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::try_from_bytes(&std::fs::read(path)?)
+    }
+
+    /// Reads a recording from bytes, e.g. a recording embedded with `include_bytes!`.
+    ///
+    /// Panics if the bytes are not a valid recording, see [`Recording::try_from_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        Self::try_from_bytes(bytes).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Reads a recording from bytes, or returns an error if they are not a valid recording of the
+    /// current format version.
+    pub fn try_from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let Some(payload) = bytes.strip_prefix(&Self::MAGIC) else {
+            return Err(invalid(
+                "not a recording, or a recording from before format versions".to_string(),
+            ));
+        };
+        let Some((version, payload)) = payload.split_first_chunk::<4>() else {
+            return Err(invalid("the recording header is truncated".to_string()));
+        };
+        let version = u32::from_le_bytes(*version);
+        if version != Self::FORMAT_VERSION {
+            return Err(invalid(format!(
+                "the recording has format version {version}, but only version {} is supported",
+                Self::FORMAT_VERSION
+            )));
+        }
+        bincode::deserialize(payload).map_err(|e| invalid(format!("invalid recording: {e}")))
+    }
+
+    /// Serializes the recording to the format read by [`Recording::from_bytes`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Self::MAGIC.to_vec();
+        bytes.extend_from_slice(&Self::FORMAT_VERSION.to_le_bytes());
+        bincode::serialize_into(&mut bytes, self).unwrap();
+        bytes
+    }
+
+    /// The length of the recording, which is at least the offset of its last event.
+    fn loop_duration_ns(&self) -> u128 {
+        let last_event = self.events.last().map_or(0, |e| e.ns_offset);
+        self.duration_ns_offset.max(last_event).max(1)
     }
 }
 
@@ -72,6 +143,7 @@ impl EventRecorderComponent {
             events: vec![],
             initial_display_size: self.current_display_size,
             duration_ns_offset: 0,
+            seed: get_seed_opt(),
         };
         self.current_start_time = std::time::Instant::now();
     }
@@ -79,17 +151,17 @@ impl EventRecorderComponent {
     /// Stops recording events.
     pub fn stop_recording(&mut self) {
         self.recording = false;
+        self.active_recording.duration_ns_offset = self.current_start_time.elapsed().as_nanos();
     }
 
     /// Saves the last recording to a file.
     /// You may want to use [`EventRecorderComponent::stop_and_save_recording`] instead.
-    pub fn save_recording(&mut self, path: impl AsRef<Path>) {
+    pub fn save_recording(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
         assert!(!self.is_recording());
         if let Some(parent) = path.as_ref().parent() {
-            std::fs::create_dir_all(parent).unwrap();
+            std::fs::create_dir_all(parent)?;
         }
-        let file = std::fs::File::create(path).unwrap();
-        bincode::serialize_into(file, &self.active_recording).unwrap();
+        std::fs::write(path, self.active_recording.to_bytes())
     }
 
     /// Returns whether the component is currently recording events.
@@ -108,15 +180,48 @@ impl EventRecorderComponent {
     }
 
     /// Stops recording and saves the recording to a file with an auto-generated name.
-    pub fn stop_and_save_recording(&mut self) {
+    pub fn stop_and_save_recording(&mut self) -> io::Result<()> {
         if self.is_recording() {
             self.stop_recording();
-            self.save_recording(self.get_new_file_path());
+            self.save_recording(self.get_new_file_path())?;
+        }
+        Ok(())
+    }
+
+    /// Saves the recording and reports a failure to [`SharedState::problems`].
+    fn stop_and_save_reporting<S>(&mut self, shared_state: &mut SharedState<S>) -> bool {
+        let source = std::any::type_name::<Self>();
+        match self.stop_and_save_recording() {
+            Ok(()) => {
+                shared_state.problems.clear(source, "save");
+                true
+            }
+            Err(e) => {
+                shared_state.problems.report(
+                    Problem::new(
+                        Severity::Error,
+                        source,
+                        format!("Failed to save recording: {e}"),
+                    )
+                    .with_hint("check that the recordings directory is writable")
+                    .with_dedup_key("save"),
+                );
+                false
+            }
         }
     }
 }
 
 impl<S> Component<S> for EventRecorderComponent {
+    fn runs_while_paused(&self) -> bool {
+        true
+    }
+
+    fn wants_raw_events(&self) -> bool {
+        // replayed events are normalized like live ones
+        true
+    }
+
     fn setup(&mut self, setup_info: &SetupInfo, shared_state: &mut SharedState<S>) {
         self.current_display_size = (
             setup_info.display_info.width(),
@@ -134,7 +239,7 @@ impl<S> Component<S> for EventRecorderComponent {
             crossterm::event::KeyCode::Char('q'),
             crossterm::event::KeyModifiers::empty(),
         )));
-        self.stop_and_save_recording();
+        self.stop_and_save_reporting(shared_state);
     }
 
     fn on_event(
@@ -151,10 +256,11 @@ impl<S> Component<S> for EventRecorderComponent {
         // at a frame boundary.
         if shared_state.pressed_keys.did_press_char_ignore_case('r') {
             if self.is_recording() {
-                self.stop_and_save_recording();
-                shared_state
-                    .debug_messages
-                    .push(DebugMessage::new_3s("Recording stopped and saved"));
+                if self.stop_and_save_reporting(shared_state) {
+                    shared_state
+                        .debug_messages
+                        .push(DebugMessage::new_3s("Recording stopped and saved"));
+                }
             } else {
                 self.start_recording();
                 shared_state
@@ -165,31 +271,149 @@ impl<S> Component<S> for EventRecorderComponent {
     }
 }
 
-// TODO: Does not currently handle resize events properly.
-// Unsure how to even handle them, do we just cap the mouse events at the current display size?
+type ResetFn<S> = Box<dyn FnMut(&mut SharedState<S>)>;
+
+/// When an [`EventReplayerComponent`] replays the recorded events.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReplayMode {
+    /// Events are replayed once their recorded time has elapsed since the replay started.
+    #[default]
+    RealTime,
+    /// The given number of events are replayed every frame, regardless of their timing.
+    PerFrame(usize),
+    /// All events are replayed in the first frame.
+    AsFastAsPossible,
+}
+
 /// A component that replays a recording of events.
-/// Events are replayed in the next frame.
-pub struct EventReplayerComponent {
+/// Events are replayed in the next frame, at the times given by the [`ReplayMode`].
+///
+/// If the terminal size differs from the recorded size, a warning is shown. With
+/// [`EventReplayerComponent::with_rescaled_mouse`], mouse coordinates are instead scaled
+/// proportionally to the current size, and recorded resize events are not replayed, so that a
+/// recording can be replayed deterministically at any size, e.g. in CI.
+/// A mismatch of the [`Recording::seed`] and the global seed is also warned about.
+///
+/// Created with [`EventReplayerComponent::demo`], the recording is instead played in a loop as a
+/// demo, e.g. for an example's attract screen:
+/// * Before every restart, the mouse buttons held by the recording are released and the closure
+///   registered with [`EventReplayerComponent::with_loop_reset`] runs, so that the game can reset
+///   its state and the demo does not drift.
+/// * The overlay "DEMO — press any key" is shown.
+/// * When the player presses a real key, the demo stops and hands off to normal input. Held mouse
+///   buttons are released and the reset closure runs once more. The key itself is handled as usual.
+/// * The recording's display size does not need to match, mouse events outside the screen are
+///   skipped.
+pub struct EventReplayerComponent<S = ()> {
     recording: Recording,
+    mode: ReplayMode,
+    rescale_mouse: bool,
+    /// The terminal size of the recording at the current event.
+    recorded_size: (usize, usize),
     replaying: bool,
     replay_start_time: std::time::Instant,
     /// The amount of events in `recording` that have been replayed and can be skipped.
     finished_events: usize,
+    demo: bool,
+    loop_reset: Option<ResetFn<S>>,
+    /// Events passed to the game that have not come back through `on_event` yet, to tell them
+    /// apart from real input.
+    injected: VecDeque<Event>,
+    /// Mouse buttons that are held down by the injected events.
+    held_buttons: HashSet<MouseButton>,
+    handing_off: bool,
 }
 
-impl EventReplayerComponent {
+impl<S> EventReplayerComponent<S> {
     /// Creates a new `EventReplayerComponent` that will start replaying immediately if
     /// `immediately_start_playing` is `true`.
     pub fn new(immediately_start_playing: bool, recording: Recording) -> Self {
         Self {
+            recorded_size: recording.initial_display_size,
             recording,
+            mode: ReplayMode::RealTime,
+            rescale_mouse: false,
             replaying: immediately_start_playing,
             replay_start_time: std::time::Instant::now(),
             finished_events: 0,
+            demo: false,
+            loop_reset: None,
+            injected: VecDeque::new(),
+            held_buttons: HashSet::new(),
+            handing_off: false,
+        }
+    }
+
+    /// Creates a new `EventReplayerComponent` that immediately starts playing `recording` in a loop
+    /// until the player presses a key.
+    ///
+    /// See the [type documentation](Self) for more information.
+    pub fn demo(recording: Recording) -> Self {
+        Self {
+            demo: true,
+            ..Self::new(true, recording)
+        }
+    }
+
+    /// Sets a closure that resets the game state before the demo restarts and when the player
+    /// takes over.
+    pub fn with_loop_reset(mut self, reset: impl FnMut(&mut SharedState<S>) + 'static) -> Self {
+        self.loop_reset = Some(Box::new(reset));
+        self
+    }
+
+    /// Sets when the events are replayed. The default is [`ReplayMode::RealTime`].
+    pub fn with_mode(mut self, mode: ReplayMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Scales mouse coordinates from the recorded terminal size to the current one, instead of
+    /// warning about a size mismatch.
+    pub fn with_rescaled_mouse(mut self, rescale_mouse: bool) -> Self {
+        self.rescale_mouse = rescale_mouse;
+        self
+    }
+
+    /// Returns true while the demo loop is playing.
+    pub fn is_demo_running(&self) -> bool {
+        self.demo && self.replaying
+    }
+
+    fn inject(&mut self, event: Event, shared_state: &mut SharedState<S>) {
+        if let Event::Mouse(MouseEvent { kind, .. }) = event {
+            match kind {
+                MouseEventKind::Down(button) => {
+                    self.held_buttons.insert(button);
+                }
+                MouseEventKind::Up(button) => {
+                    self.held_buttons.remove(&button);
+                }
+                _ => {}
+            }
         }
+        self.injected.push_back(event.clone());
+        shared_state.fake_events_for_next_frame.push(event);
     }
 
-    fn play_events_until<S>(
+    /// Releases all held mouse buttons and runs the reset closure.
+    fn reset_demo_state(&mut self, shared_state: &mut SharedState<S>) {
+        let (column, row) = shared_state.mouse_info.last_mouse_pos;
+        for button in std::mem::take(&mut self.held_buttons) {
+            let release = Event::Mouse(MouseEvent {
+                kind: MouseEventKind::Up(button),
+                column: column as u16,
+                row: row as u16,
+                modifiers: KeyModifiers::NONE,
+            });
+            self.inject(release, shared_state);
+        }
+        if let Some(reset) = &mut self.loop_reset {
+            reset(shared_state);
+        }
+    }
+
+    fn play_events_until(
         &mut self,
         current_time: std::time::Instant,
         shared_state: &mut SharedState<S>,
@@ -199,21 +423,61 @@ impl EventReplayerComponent {
         }
         let duration = current_time.duration_since(self.replay_start_time);
         let ns_offset = duration.as_nanos();
+        let (width, height) = (
+            shared_state.display_info.width(),
+            shared_state.display_info.height(),
+        );
+        let budget = match self.mode {
+            ReplayMode::PerFrame(events) => events,
+            ReplayMode::RealTime | ReplayMode::AsFastAsPossible => usize::MAX,
+        };
         let mut events_played = 0;
-        for event in &self.recording.events[self.finished_events..] {
-            if event.ns_offset <= ns_offset {
-                shared_state
-                    .fake_events_for_next_frame
-                    .push(event.event.clone());
-                events_played += 1;
-            } else {
+        for idx in self.finished_events..self.recording.events.len() {
+            let event = &self.recording.events[idx];
+            if events_played == budget
+                || (self.mode == ReplayMode::RealTime && event.ns_offset > ns_offset)
+            {
                 break;
             }
+            events_played += 1;
+            let mut event = event.event.clone();
+            if let Event::Resize(width, height) = event {
+                self.recorded_size = (width as usize, height as usize);
+                if self.rescale_mouse {
+                    continue;
+                }
+            }
+            if self.rescale_mouse
+                && let Event::Mouse(mouse_event) = &mut event
+            {
+                let (recorded_width, recorded_height) = self.recorded_size;
+                mouse_event.column = rescale(mouse_event.column, recorded_width, width);
+                mouse_event.row = rescale(mouse_event.row, recorded_height, height);
+            }
+            if let Event::Mouse(MouseEvent { column, row, .. }) = event
+                && self.demo
+                && (column as usize >= width || row as usize >= height)
+            {
+                continue;
+            }
+            self.inject(event, shared_state);
         }
         self.finished_events += events_played;
-        if self.finished_events == self.recording.events.len() {
+        if self.demo {
+            let loop_duration = self.recording.loop_duration_ns();
+            let loop_done = self.mode != ReplayMode::RealTime || ns_offset >= loop_duration;
+            if self.finished_events == self.recording.events.len() && loop_done {
+                self.reset_demo_state(shared_state);
+                self.finished_events = 0;
+                self.recorded_size = self.recording.initial_display_size;
+                // keep the original timing to not drift
+                self.replay_start_time +=
+                    std::time::Duration::from_nanos(loop_duration.min(u64::MAX as u128) as u64);
+            }
+        } else if self.finished_events == self.recording.events.len() {
             self.replaying = false;
             self.finished_events = 0;
+            self.recorded_size = self.recording.initial_display_size;
             shared_state
                 .debug_messages
                 .push(DebugMessage::new_3s("Replay finished"));
@@ -221,53 +485,451 @@ impl EventReplayerComponent {
     }
 }
 
-impl<S> Component<S> for EventReplayerComponent {
+impl<S: 'static> Component<S> for EventReplayerComponent<S> {
+    fn runs_while_paused(&self) -> bool {
+        true
+    }
+
+    fn wants_raw_events(&self) -> bool {
+        // to recognize the injected events
+        true
+    }
+
     fn setup(&mut self, setup_info: &SetupInfo, shared_state: &mut SharedState<S>) {
-        assert_eq!(
+        if self.demo {
+            return;
+        }
+        let size = (
             setup_info.display_info.width(),
-            self.recording.initial_display_size.0,
-            "Width mismatch for replay"
-        );
-        assert_eq!(
             setup_info.display_info.height(),
-            self.recording.initial_display_size.1,
-            "Height mismatch for replay"
         );
+        let (recorded_width, recorded_height) = self.recording.initial_display_size;
+        if size != (recorded_width, recorded_height) && !self.rescale_mouse {
+            shared_state.debug_messages.push(DebugMessage::warn_5s(format!(
+                "Replaying a {recorded_width}x{recorded_height} recording at {}x{}, mouse events may miss",
+                size.0, size.1
+            )));
+        }
+        if let (Some(recorded), Some(current)) = (self.recording.seed, get_seed_opt())
+            && recorded != current
+        {
+            shared_state
+                .debug_messages
+                .push(DebugMessage::warn_5s(format!(
+                    "Replaying a recording with seed {recorded} with seed {current}"
+                )));
+        }
+    }
+
+    fn on_event(
+        &mut self,
+        event: Event,
+        _shared_state: &mut SharedState<S>,
+    ) -> Option<BreakingAction> {
+        if self.injected.front() == Some(&event) {
+            self.injected.pop_front();
+            return None;
+        }
+        if let Event::Key(KeyEvent {
+            kind: KeyEventKind::Press,
+            ..
+        }) = event
+            && self.is_demo_running()
+        {
+            self.replaying = false;
+            self.handing_off = true;
+        }
+        None
     }
 
     fn update(&mut self, update_info: UpdateInfo, shared_state: &mut SharedState<S>) {
+        // the events injected last frame have been delivered already
+        self.injected.clear();
+        if std::mem::take(&mut self.handing_off) {
+            self.reset_demo_state(shared_state);
+            shared_state
+                .debug_messages
+                .push(DebugMessage::new_3s("Demo stopped"));
+            return;
+        }
         let current_time = update_info.current_time;
         self.play_events_until(current_time, shared_state);
     }
+
+    fn render(&self, renderer: &mut dyn Renderer, shared_state: &SharedState<S>, _depth_base: i32) {
+        if !self.is_demo_running() {
+            return;
+        }
+        let text = " DEMO — press any key ";
+        let x = shared_state
+            .display_info
+            .width()
+            .saturating_sub(text.chars().count())
+            / 2;
+        text.with_color([0, 0, 0])
+            .with_bg_color([255, 200, 0])
+            .render(renderer, x, 1, i32::MAX - 70);
+    }
+}
+
+/// Scales a cell coordinate from a terminal dimension of `from` cells to one of `to` cells,
+/// mapping cell centers.
+fn rescale(coord: u16, from: usize, to: usize) -> u16 {
+    if from == 0 || to == 0 {
+        return coord;
+    }
+    let scaled = (2 * coord as usize + 1) * to / (2 * from);
+    scaled.min(to - 1) as u16
 }
 
-/// A component that counts the number of frames that have passed and reports it when the game is
+/// Frame time statistics of a benchmark run, reported by [`BenchFrameCounter`].
+///
+/// All times are in seconds. Percentiles use the nearest-rank method, i.e., they are the smallest
+/// sample that is at least as large as the given percentage of samples.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BenchStats {
+    pub frame_count: usize,
+    pub mean_frame_time: f64,
+    pub median_frame_time: f64,
+    pub p95_frame_time: f64,
+    pub p99_frame_time: f64,
+    pub max_frame_time: f64,
+}
+
+impl BenchStats {
+    /// The names of the columns of [`BenchStats::csv_row`]. The frame count comes first, so that
+    /// the other statistics can be appended to CSV files that only had a frame count.
+    pub const CSV_HEADER: &'static str =
+        "frames,mean_frame_time,median_frame_time,p95_frame_time,p99_frame_time,max_frame_time";
+
+    /// Computes the statistics of the frame time samples, sorting them.
+    pub fn from_samples(samples: &mut [f64]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        samples.sort_by(f64::total_cmp);
+        let len = samples.len();
+        let percentile = |percent: usize| samples[(percent * len).div_ceil(100).max(1) - 1];
+        let median = if len.is_multiple_of(2) {
+            (samples[len / 2 - 1] + samples[len / 2]) / 2.0
+        } else {
+            samples[len / 2]
+        };
+        Self {
+            frame_count: len,
+            mean_frame_time: samples.iter().sum::<f64>() / len as f64,
+            median_frame_time: median,
+            p95_frame_time: percentile(95),
+            p99_frame_time: percentile(99),
+            max_frame_time: samples[len - 1],
+        }
+    }
+
+    /// Returns the statistics as comma-separated values, see [`BenchStats::CSV_HEADER`].
+    pub fn csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{},{}",
+            self.frame_count,
+            self.mean_frame_time,
+            self.median_frame_time,
+            self.p95_frame_time,
+            self.p99_frame_time,
+            self.max_frame_time
+        )
+    }
+}
+
+/// A component that records the time of every frame and reports [`BenchStats`] when the game is
 /// quit.
-/// Useful for basic benchmarking by counting the number of frames that have passed when e.g. a
-/// recording is played.
+/// Useful for basic benchmarking of the frames that pass when e.g. a recording is played.
 pub struct BenchFrameCounter {
-    frame_count: usize,
-    report_fn: Box<dyn Fn(usize)>,
+    frame_times: Vec<f64>,
+    report_fn: Box<dyn Fn(BenchStats)>,
 }
 
 impl BenchFrameCounter {
-    /// Creates a new `BenchFrameCounter` that will report the frame count to `report_fn` when the
+    /// Creates a new `BenchFrameCounter` that will report the statistics to `report_fn` when the
     /// game is quit.
-    pub fn new(report_fn: impl Fn(usize) + 'static) -> Self {
+    pub fn new(report_fn: impl Fn(BenchStats) + 'static) -> Self {
         Self {
-            frame_count: 0,
+            frame_times: vec![],
             report_fn: Box::new(report_fn),
         }
     }
+
+    /// Reserves space for `frames` frame times, so that recording them does not allocate, e.g.
+    /// the number of events of a recording replayed with [`ReplayMode::PerFrame`].
+    pub fn with_expected_frames(mut self, frames: usize) -> Self {
+        self.frame_times.reserve(frames);
+        self
+    }
 }
 
 impl<S> Component<S> for BenchFrameCounter {
+    fn runs_while_paused(&self) -> bool {
+        true
+    }
+
     fn on_quit(&mut self, shared_state: &mut SharedState<S>) {
-        // report the count
-        (self.report_fn)(self.frame_count);
+        // report the statistics
+        (self.report_fn)(BenchStats::from_samples(&mut self.frame_times));
     }
 
     fn update(&mut self, update_info: UpdateInfo, shared_state: &mut SharedState<S>) {
-        self.frame_count += 1;
+        self.frame_times.push(update_info.actual_dt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Game;
+    use crate::components::fpslocker::FpsMode;
+    use crate::components::mouse::MouseTrackerComponent;
+    use crossterm::event::KeyCode;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::time::Instant;
+
+    fn mouse(kind: MouseEventKind, column: u16, row: u16) -> Event {
+        Event::Mouse(MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        })
+    }
+
+    fn key(c: char) -> Event {
+        Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+    }
+
+    fn recording() -> Recording {
+        let ms = |ms: u128| ms * 1_000_000;
+        let events = vec![
+            (ms(0), mouse(MouseEventKind::Down(MouseButton::Left), 2, 2)),
+            (
+                ms(100),
+                mouse(MouseEventKind::Drag(MouseButton::Left), 3, 2),
+            ),
+            // outside of the 20x10 screen
+            (
+                ms(200),
+                mouse(MouseEventKind::Drag(MouseButton::Left), 30, 2),
+            ),
+            (ms(300), key('x')),
+        ];
+        Recording {
+            events: events
+                .into_iter()
+                .map(|(ns_offset, event)| RecordedEvent { event, ns_offset })
+                .collect(),
+            initial_display_size: (80, 24),
+            duration_ns_offset: ms(500),
+            seed: Some(7),
+        }
+    }
+
+    /// Runs a game at 100 FPS with the replayer, so that frame `n` is `n * 10ms` into the replay.
+    fn replay_game(
+        replayer: EventReplayerComponent,
+        width: usize,
+        height: usize,
+    ) -> Game<Vec<u8>, ()> {
+        let mut game = Game::<Vec<u8>, ()>::new_headless(width, height);
+        game.shared_state_mut().fps.mode = FpsMode::Target(100.0);
+        // releases go to the last mouse position
+        game.add_component(Box::new(MouseTrackerComponent::new()));
+        game.add_component(Box::new(replayer));
+        game
+    }
+
+    /// Runs `frames` frames, and returns the events the replayer injected in them.
+    fn run(game: &mut Game<Vec<u8>, ()>, frames: usize) -> Vec<Event> {
+        let mut injected = vec![];
+        for _ in 0..frames {
+            game.run_frames(1).unwrap();
+            injected.extend_from_slice(&game.shared_state().fake_events_for_next_frame);
+        }
+        injected
+    }
+
+    fn is_demo_running(game: &Game<Vec<u8>, ()>) -> bool {
+        let frame = game.frame();
+        let row: String = (0..frame.width()).map(|x| frame.pixel_at(x, 1).c).collect();
+        row.contains("DEMO")
+    }
+
+    fn demo() -> (EventReplayerComponent, Rc<Cell<usize>>) {
+        let resets = Rc::new(Cell::new(0));
+        let resets_clone = resets.clone();
+        let bytes = recording().to_bytes();
+        let replayer = EventReplayerComponent::demo(Recording::from_bytes(&bytes))
+            .with_loop_reset(move |_| resets_clone.set(resets_clone.get() + 1));
+        (replayer, resets)
+    }
+
+    #[test]
+    fn test_demo_loop_reset() {
+        let (replayer, resets) = demo();
+        let mut game = replay_game(replayer, 20, 10);
+
+        // up to 150ms
+        assert_eq!(run(&mut game, 15).len(), 2);
+        // the off-screen drag is skipped, the injected key does not stop the demo
+        assert_eq!(run(&mut game, 20), vec![key('x')]);
+        assert!(run(&mut game, 5).is_empty());
+        assert!(is_demo_running(&game));
+        assert_eq!(resets.get(), 0);
+
+        // at the end of the recording, the held button is released and the game reset
+        assert_eq!(
+            run(&mut game, 10),
+            vec![mouse(MouseEventKind::Up(MouseButton::Left), 3, 2)]
+        );
+        assert_eq!(resets.get(), 1);
+        // the next loop keeps the original timing, up to 610ms
+        assert_eq!(run(&mut game, 11).len(), 2);
+        assert!(is_demo_running(&game));
+    }
+
+    #[test]
+    fn test_demo_real_input_takeover() {
+        let (replayer, resets) = demo();
+        let mut game = replay_game(replayer, 20, 10);
+
+        run(&mut game, 15);
+        // real mouse input does not stop the demo
+        game.push_event(mouse(MouseEventKind::Moved, 5, 5));
+        run(&mut game, 1);
+        assert!(is_demo_running(&game));
+
+        // a real key press hands off to the player
+        game.push_event(key('x'));
+        let events = run(&mut game, 1);
+        assert!(!is_demo_running(&game));
+        assert_eq!(
+            events,
+            vec![mouse(MouseEventKind::Up(MouseButton::Left), 5, 5)]
+        );
+        assert_eq!(resets.get(), 1);
+
+        // nothing is replayed anymore
+        assert!(run(&mut game, 100).is_empty());
+        assert_eq!(resets.get(), 1);
+    }
+
+    #[test]
+    fn test_recording_format_version() {
+        let bytes = recording().to_bytes();
+        let read = Recording::try_from_bytes(&bytes).unwrap();
+        assert_eq!(read.seed, Some(7));
+        assert_eq!(read.initial_display_size, (80, 24));
+        assert_eq!(read.events.len(), 4);
+
+        let mut other_version = bytes.clone();
+        other_version[8..12].copy_from_slice(&2u32.to_le_bytes());
+        let error = Recording::try_from_bytes(&other_version).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("version 2"), "{error}");
+
+        let error = Recording::try_from_bytes(&bytes[Recording::MAGIC.len()..]).unwrap_err();
+        assert!(error.to_string().contains("not a recording"), "{error}");
+        assert!(Recording::try_from_bytes(&bytes[..10]).is_err());
+        assert!(Recording::try_from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_replay_modes() {
+        let replayer =
+            EventReplayerComponent::new(true, recording()).with_mode(ReplayMode::PerFrame(3));
+        let mut game = replay_game(replayer, 80, 24);
+        // the timing is ignored
+        let frames: Vec<_> = (0..3).map(|_| run(&mut game, 1).len()).collect();
+        assert_eq!(frames, [3, 1, 0]);
+
+        let replayer =
+            EventReplayerComponent::new(true, recording()).with_mode(ReplayMode::AsFastAsPossible);
+        let mut game = replay_game(replayer, 80, 24);
+        assert_eq!(run(&mut game, 1).len(), 4);
+        assert!(run(&mut game, 1).is_empty());
+    }
+
+    #[test]
+    fn test_replay_at_other_size() {
+        let warnings = |game: &Game<Vec<u8>, ()>| {
+            game.shared_state()
+                .debug_messages
+                .iter()
+                .filter(|message| message.message().starts_with("Replaying"))
+                .count()
+        };
+        let replayer =
+            EventReplayerComponent::new(true, recording()).with_mode(ReplayMode::AsFastAsPossible);
+        let mut game = replay_game(replayer, 20, 10);
+        run(&mut game, 1);
+        // the global seed is not set in tests, so only the size is warned about
+        assert_eq!(warnings(&game), 1);
+
+        let replayer = EventReplayerComponent::new(true, recording())
+            .with_mode(ReplayMode::AsFastAsPossible)
+            .with_rescaled_mouse(true);
+        let mut game = replay_game(replayer, 20, 10);
+        let events = run(&mut game, 1);
+        assert_eq!(warnings(&game), 0);
+        // from 80x24 to 20x10
+        assert_eq!(
+            events,
+            vec![
+                mouse(MouseEventKind::Down(MouseButton::Left), 0, 1),
+                mouse(MouseEventKind::Drag(MouseButton::Left), 0, 1),
+                mouse(MouseEventKind::Drag(MouseButton::Left), 7, 1),
+                key('x'),
+            ]
+        );
+        assert_eq!(rescale(79, 80, 20), 19);
+        assert_eq!(rescale(23, 24, 10), 9);
+        assert_eq!(rescale(5, 10, 20), 11);
+    }
+
+    #[test]
+    fn test_bench_stats() {
+        let mut shared_state = SharedState::<()>::new(80, 24);
+        let stats = Rc::new(Cell::new(BenchStats::default()));
+        let stats_clone = stats.clone();
+        let mut counter =
+            BenchFrameCounter::new(move |s| stats_clone.set(s)).with_expected_frames(200);
+        // 1ms to 200ms in a shuffled order
+        for i in 0..200 {
+            let ms = (i * 67) % 200 + 1;
+            let mut info = UpdateInfo::at(Instant::now());
+            info.actual_dt = ms as f64 / 1000.0;
+            counter.update(info, &mut shared_state);
+        }
+        assert_eq!(counter.frame_times.capacity(), 200);
+        counter.on_quit(&mut shared_state);
+        let stats = stats.get();
+        assert_eq!(stats.frame_count, 200);
+        assert!((stats.mean_frame_time - 0.1005).abs() < 1e-9);
+        assert!((stats.median_frame_time - 0.1005).abs() < 1e-9);
+        assert_eq!(stats.p95_frame_time, 0.19);
+        assert_eq!(stats.p99_frame_time, 0.198);
+        assert_eq!(stats.max_frame_time, 0.2);
+        assert_eq!(
+            stats.csv_row().split(',').count(),
+            BenchStats::CSV_HEADER.split(',').count()
+        );
+
+        let mut samples = [3.0, 1.0, 2.0];
+        let stats = BenchStats::from_samples(&mut samples);
+        assert_eq!(
+            (
+                stats.median_frame_time,
+                stats.p95_frame_time,
+                stats.p99_frame_time
+            ),
+            (2.0, 3.0, 3.0)
+        );
+        assert_eq!(BenchStats::from_samples(&mut []), BenchStats::default());
     }
 }
@@ -0,0 +1,490 @@
+//! Detection of single-frame flicker, a debugging tool.
+//!
+//! Flicker is a cell that briefly shows something else, e.g. because a component skipped drawing
+//! it for a frame, or drew something on top of it for a frame. It is hard to catch by eye, so the
+//! [`FlickerDetectorComponent`] watches the flushed frames for cells that change and then change
+//! back within a few frames:
+//! ```rust
+//! use teng::Game;
+//! use teng::components::flicker::FlickerDetectorComponent;
+//!
+//! let mut game = Game::<_, ()>::new_headless(80, 24);
+//! game.add_component(Box::new(FlickerDetectorComponent::new().with_window(3)));
+//! ```
+//! Every flicker is shown as a debug message with the cell, the stable and the flickering
+//! contents, and the component that drew each of them, see [`Flicker`]. By default, the game is
+//! paused and the flickering cells are highlighted, so that they can be inspected.
+//!
+//! The detector only looks at the cells that changed in a flush, so it is cheap enough to leave
+//! on while debugging. It is disabled unless the component is added.
+
+use crate::components::Component;
+use crate::components::debuginfo::DebugMessage;
+use crate::rendering::display::Display;
+use crate::rendering::pixel::Pixel;
+use crate::rendering::renderer::{FrameView, Renderer};
+use crate::{SetupInfo, SharedState, UpdateInfo};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+/// Who drew the contents of a cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Attribution {
+    /// The depth of the topmost pixel of the cell.
+    pub depth: i32,
+    /// The name of the component that owns the depth, see [`Component::name`]. `None` for draws
+    /// outside of the components' depth ranges, e.g. overlays and queued draws.
+    pub component: Option<&'static str>,
+}
+
+impl fmt::Display for Attribution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.component {
+            Some(component) => write!(f, "{component} at depth {}", self.depth),
+            None => write!(f, "depth {}", self.depth),
+        }
+    }
+}
+
+/// A cell that changed and then changed back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Flicker {
+    pub x: usize,
+    pub y: usize,
+    /// What the cell showed before and after the flicker.
+    pub stable: Pixel,
+    /// What the cell showed during the flicker.
+    pub flicker: Pixel,
+    /// For how many frames the flicker was shown.
+    pub frames: u64,
+    /// Who drew the stable contents, or `None` if nothing was drawn.
+    pub stable_drawn_by: Option<Attribution>,
+    /// Who drew the flicker, or `None` if nothing was drawn, i.e., the stable contents were
+    /// missing.
+    pub flicker_drawn_by: Option<Attribution>,
+}
+
+impl fmt::Display for Flicker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let describe = |pixel: &Pixel, drawn_by: &Option<Attribution>| match drawn_by {
+            Some(drawn_by) => format!(
+                "{:?} {:?}/{:?} by {drawn_by}",
+                pixel.c, pixel.color, pixel.bg_color
+            ),
+            None => "nothing".to_string(),
+        };
+        write!(
+            f,
+            "({}, {}) showed {} instead of {} for {} frame(s)",
+            self.x,
+            self.y,
+            describe(&self.flicker, &self.flicker_drawn_by),
+            describe(&self.stable, &self.stable_drawn_by),
+            self.frames
+        )
+    }
+}
+
+/// What a cell showed last.
+#[derive(Debug, Clone, Copy, Default)]
+struct Shown {
+    pixel: Pixel,
+    drawn_by: Option<Attribution>,
+}
+
+/// The last change of a cell.
+#[derive(Debug, Clone, Copy)]
+struct Change {
+    frame: u64,
+    before: Shown,
+}
+
+/// Finds flicker in the flushed frames.
+///
+/// Lives in [`SharedState::flicker_detector`] and is fed by the game after every flush while it is
+/// enabled.
+#[derive(Debug)]
+pub struct FlickerDetector {
+    enabled: bool,
+    window: u64,
+    frame: u64,
+    /// A copy of the flushed frame, updated with the changed cells only.
+    shown: Display<Shown>,
+    /// The last change of the cells that changed within the window.
+    recent: HashMap<(usize, usize), Change>,
+    /// The cells that changed in each frame of the window, oldest first.
+    history: VecDeque<(u64, Vec<(usize, usize)>)>,
+    reports: Vec<Flicker>,
+    detected: usize,
+}
+
+impl FlickerDetector {
+    /// The default number of frames within which a change must revert to count as flicker.
+    pub const DEFAULT_WINDOW: u64 = 2;
+
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            window: Self::DEFAULT_WINDOW,
+            frame: 0,
+            shown: Display::new(0, 0, Shown::default()),
+            recent: HashMap::new(),
+            history: VecDeque::new(),
+            reports: vec![],
+            detected: 0,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Starts watching the frames. Changes that revert within `window` frames are flicker.
+    ///
+    /// # Panics
+    /// If `window` is zero.
+    pub fn enable(&mut self, window: u64) {
+        assert!(window > 0, "the window must be at least one frame");
+        self.enabled = true;
+        self.window = window;
+    }
+
+    /// Stops watching the frames and forgets them.
+    pub fn disable(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Returns the flicker found since the last call.
+    pub fn take_reports(&mut self) -> Vec<Flicker> {
+        std::mem::take(&mut self.reports)
+    }
+
+    /// Returns the number of flickers found since the detector was enabled.
+    pub fn detected(&self) -> usize {
+        self.detected
+    }
+
+    /// Compares the cells that changed in the flush of `frame` with the previous frames.
+    ///
+    /// `depth_at` returns the depth of a cell in `frame`, and `names` the names of the components
+    /// in render order, so that each one owns a range of 100 depths.
+    pub(crate) fn observe(
+        &mut self,
+        frame: &FrameView,
+        depth_at: impl Fn(usize, usize) -> Option<i32>,
+        names: &[&'static str],
+    ) {
+        if !self.enabled {
+            return;
+        }
+        self.frame += 1;
+        if self.shown.width() != frame.width() || self.shown.height() != frame.height() {
+            // a resize redraws everything, which is not flicker
+            self.shown.resize_discard(frame.width(), frame.height());
+            self.recent.clear();
+            self.history.clear();
+        }
+
+        let mut changed = vec![];
+        for (x, y) in frame.changed_cells_last_flush() {
+            let pixel = frame.pixel_at(x, y);
+            if self.shown[(x, y)].pixel == pixel {
+                continue;
+            }
+            let drawn_by = depth_at(x, y).map(|depth| Attribution {
+                depth,
+                component: usize::try_from(depth.div_euclid(100))
+                    .ok()
+                    .and_then(|order| names.get(order).copied()),
+            });
+            let now = Shown { pixel, drawn_by };
+            let before = std::mem::replace(&mut self.shown[(x, y)], now);
+            match self.recent.get(&(x, y)) {
+                Some(change)
+                    if change.before.pixel == pixel && self.frame - change.frame <= self.window =>
+                {
+                    self.reports.push(Flicker {
+                        x,
+                        y,
+                        stable: pixel,
+                        flicker: before.pixel,
+                        frames: self.frame - change.frame,
+                        stable_drawn_by: drawn_by,
+                        flicker_drawn_by: before.drawn_by,
+                    });
+                    self.detected += 1;
+                    // the revert does not start another flicker
+                    self.recent.remove(&(x, y));
+                }
+                _ => {
+                    self.recent.insert(
+                        (x, y),
+                        Change {
+                            frame: self.frame,
+                            before,
+                        },
+                    );
+                    changed.push((x, y));
+                }
+            }
+        }
+        self.history.push_back((self.frame, changed));
+
+        // forget the changes that can not revert within the window anymore
+        while let Some((frame, _)) = self.history.front()
+            && frame + self.window <= self.frame
+        {
+            let (frame, cells) = self.history.pop_front().unwrap();
+            for cell in cells {
+                if self
+                    .recent
+                    .get(&cell)
+                    .is_some_and(|change| change.frame == frame)
+                {
+                    self.recent.remove(&cell);
+                }
+            }
+        }
+    }
+}
+
+impl Default for FlickerDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Reports flicker and freezes the game on it.
+pub struct FlickerDetectorComponent {
+    window: u64,
+    pause_on_detect: bool,
+    highlight: bool,
+    highlighted: Vec<(usize, usize)>,
+    /// Seconds until the highlights disappear, unless the game was paused by us.
+    highlight_left: f64,
+    /// Whether we paused the game and wait for it to be resumed.
+    paused_game: bool,
+}
+
+impl FlickerDetectorComponent {
+    /// How many flickers are shown as debug messages per frame.
+    const MAX_MESSAGES: usize = 3;
+    const HIGHLIGHT_SECS: f64 = 2.0;
+    const HIGHLIGHT_COLOR: [u8; 3] = [255, 0, 255];
+
+    pub fn new() -> Self {
+        Self {
+            window: FlickerDetector::DEFAULT_WINDOW,
+            pause_on_detect: true,
+            highlight: true,
+            highlighted: vec![],
+            highlight_left: 0.0,
+            paused_game: false,
+        }
+    }
+
+    /// Sets the number of frames within which a change must revert to count as flicker.
+    pub fn with_window(mut self, frames: u64) -> Self {
+        self.window = frames;
+        self
+    }
+
+    /// Sets whether the game is paused when flicker is found. Defaults to true.
+    pub fn with_pause_on_detect(mut self, pause_on_detect: bool) -> Self {
+        self.pause_on_detect = pause_on_detect;
+        self
+    }
+
+    /// Sets whether the flickering cells are highlighted. Defaults to true.
+    ///
+    /// The highlights stay until the game is resumed, or for two seconds if it was not paused.
+    pub fn with_highlight(mut self, highlight: bool) -> Self {
+        self.highlight = highlight;
+        self
+    }
+}
+
+impl Default for FlickerDetectorComponent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: 'static> Component<S> for FlickerDetectorComponent {
+    fn setup(&mut self, _setup_info: &SetupInfo, shared_state: &mut SharedState<S>) {
+        shared_state.flicker_detector.enable(self.window);
+    }
+
+    fn runs_while_paused(&self) -> bool {
+        true
+    }
+
+    fn update(&mut self, update_info: UpdateInfo, shared_state: &mut SharedState<S>) {
+        let reports = shared_state.flicker_detector.take_reports();
+        if reports.is_empty() {
+            if self.paused_game {
+                if !shared_state.paused {
+                    self.paused_game = false;
+                    self.highlighted.clear();
+                }
+            } else {
+                self.highlight_left -= update_info.dt;
+                if self.highlight_left <= 0.0 {
+                    self.highlighted.clear();
+                }
+            }
+            return;
+        }
+
+        for flicker in reports.iter().take(Self::MAX_MESSAGES) {
+            shared_state
+                .debug_messages
+                .push(DebugMessage::new_3s(format!("Flicker at {flicker}")));
+        }
+        if reports.len() > Self::MAX_MESSAGES {
+            shared_state
+                .debug_messages
+                .push(DebugMessage::new_3s(format!(
+                    "Flicker in {} more cells",
+                    reports.len() - Self::MAX_MESSAGES
+                )));
+        }
+        if self.highlight {
+            self.highlighted = reports
+                .iter()
+                .map(|flicker| (flicker.x, flicker.y))
+                .collect();
+            self.highlight_left = Self::HIGHLIGHT_SECS;
+        }
+        if self.pause_on_detect && !shared_state.paused {
+            shared_state.paused = true;
+            self.paused_game = true;
+        }
+    }
+
+    fn render(
+        &self,
+        renderer: &mut dyn Renderer,
+        _shared_state: &SharedState<S>,
+        _depth_base: i32,
+    ) {
+        // only the background, so that the flickering contents stay visible
+        let pixel = Pixel::transparent().with_bg_color(Self::HIGHLIGHT_COLOR);
+        for &(x, y) in &self.highlighted {
+            renderer.render_pixel(x, y, pixel, i32::MAX - 100);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Game;
+    use crate::rendering::color::Color;
+
+    /// Draws an 'X', but skips it in frame 8 and flashes a '!' in frame 5.
+    #[derive(Default)]
+    struct MisbehavingComponent {
+        frame: usize,
+    }
+
+    impl Component for MisbehavingComponent {
+        fn runs_while_paused(&self) -> bool {
+            true
+        }
+
+        fn update(&mut self, _update_info: UpdateInfo, _shared_state: &mut SharedState) {
+            self.frame += 1;
+        }
+
+        fn render(
+            &self,
+            renderer: &mut dyn Renderer,
+            _shared_state: &SharedState,
+            depth_base: i32,
+        ) {
+            if self.frame != 8 {
+                let pixel = Pixel::new('X').with_color([255, 0, 0]);
+                renderer.render_pixel(2, 1, pixel, depth_base + 1);
+            }
+            if self.frame == 5 {
+                renderer.render_pixel(4, 0, Pixel::new('!'), depth_base + 2);
+            }
+            // a counter changes every frame, but never back within the window
+            let digit = char::from_digit(self.frame as u32 % 10, 10).unwrap();
+            renderer.render_pixel(0, 0, Pixel::new(digit), depth_base);
+        }
+    }
+
+    /// Renders nothing, to take the first depth range.
+    struct EmptyComponent;
+
+    impl Component for EmptyComponent {}
+
+    #[test]
+    fn test_detects_flicker_with_attribution() {
+        let mut game = Game::<Vec<u8>, ()>::new_headless(6, 3);
+        game.add_component(Box::new(EmptyComponent));
+        game.add_component(Box::new(MisbehavingComponent::default()));
+        game.shared_state_mut().flicker_detector.enable(2);
+        game.run_frames(4).unwrap();
+        assert!(
+            game.shared_state_mut()
+                .flicker_detector
+                .take_reports()
+                .is_empty()
+        );
+
+        game.run_frames(6).unwrap();
+        let drawn_by = |depth| {
+            Some(Attribution {
+                depth,
+                component: Some(std::any::type_name::<MisbehavingComponent>()),
+            })
+        };
+        let blank = game.frame().pixel_at(5, 2);
+        let x = Pixel::new('X').with_color([255, 0, 0]);
+        assert_eq!(
+            game.shared_state_mut().flicker_detector.take_reports(),
+            vec![
+                Flicker {
+                    x: 4,
+                    y: 0,
+                    stable: blank,
+                    flicker: Pixel::new('!'),
+                    frames: 1,
+                    stable_drawn_by: None,
+                    flicker_drawn_by: drawn_by(102),
+                },
+                Flicker {
+                    x: 2,
+                    y: 1,
+                    stable: x,
+                    flicker: blank,
+                    frames: 1,
+                    stable_drawn_by: drawn_by(101),
+                    flicker_drawn_by: None,
+                },
+            ]
+        );
+        assert_eq!(game.shared_state().flicker_detector.detected(), 2);
+    }
+
+    #[test]
+    fn test_component_pauses_and_highlights() {
+        let mut game = Game::<Vec<u8>, ()>::new_headless(6, 3);
+        game.add_component(Box::new(MisbehavingComponent::default()));
+        game.add_component(Box::new(FlickerDetectorComponent::new()));
+        game.run_frames(6).unwrap();
+        assert!(!game.shared_state().paused);
+        // the flash in frame 5 reverts in frame 6 and is reported in the update of frame 7
+        game.run_frames(1).unwrap();
+        assert!(game.shared_state().paused);
+        let highlight = Color::Rgb(FlickerDetectorComponent::HIGHLIGHT_COLOR);
+        assert_eq!(game.frame().pixel_at(4, 0).bg_color, highlight);
+
+        game.shared_state_mut().paused = false;
+        game.run_frames(1).unwrap();
+        assert_ne!(game.frame().pixel_at(4, 0).bg_color, highlight);
+    }
+}
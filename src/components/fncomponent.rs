@@ -0,0 +1,390 @@
+//! Less boilerplate for small components.
+//!
+//! [`FnComponent`] builds a component from closures, for prototypes and one-off glue:
+//! ```
+//! use teng::components::fncomponent::FnComponent;
+//! use teng::rendering::render::Render;
+//! use teng::Game;
+//!
+//! let mut game = Game::<_, ()>::new_headless(20, 3);
+//! game.add_component(Box::new(FnComponent::from_fns(
+//!     |_update_info, _shared_state| {},
+//!     |renderer, shared_state, depth_base| {
+//!         let fps = format!("{:.0} fps", shared_state.fps.target_fps().unwrap_or(0.0));
+//!         fps.render(renderer, 0, 0, depth_base);
+//!     },
+//! )));
+//! game.run_frames(1).unwrap();
+//! ```
+//!
+//! The [`component!`](crate::component!) macro declares a component struct together with the
+//! usual glue: a `new` function, resizing half-block displays in `on_resize`, and calling
+//! `on_resize` from `setup`.
+//!
+//! # Type ids
+//!
+//! Every `FnComponent<S>` has the same [`TypeId`](std::any::TypeId), no matter which closures it
+//! holds. Functions that find components by type, e.g.
+//! [`Game::set_component_interval`](crate::Game::set_component_interval) or
+//! [`SharedState::remove_components`](crate::SharedState::remove_components) with a type id,
+//! therefore affect all of them at once. Remove a single one by the
+//! [`ComponentId`](crate::ComponentId) returned when adding it, or give it its own type once it
+//! needs to be configured on its own.
+
+use crate::components::Component;
+use crate::rendering::renderer::Renderer;
+use crate::{BreakingAction, SetupInfo, SharedState, UpdateInfo};
+use crossterm::event::Event;
+
+type SetupFn<S> = Box<dyn FnMut(&SetupInfo, &mut SharedState<S>)>;
+type ResizeFn<S> = Box<dyn FnMut(usize, usize, &mut SharedState<S>)>;
+type EventFn<S> = Box<dyn FnMut(Event, &mut SharedState<S>) -> Option<BreakingAction>>;
+type UpdateFn<S> = Box<dyn FnMut(UpdateInfo, &mut SharedState<S>)>;
+type RenderFn<S> = Box<dyn Fn(&mut dyn Renderer, &SharedState<S>, i32)>;
+
+/// A component made of closures.
+///
+/// Every hook is optional. State that the closures share, e.g. between `update` and `render`,
+/// lives in the [`SharedState`] or behind an `Rc<RefCell<_>>`.
+pub struct FnComponent<S = ()> {
+    name: &'static str,
+    setup: Option<SetupFn<S>>,
+    on_resize: Option<ResizeFn<S>>,
+    on_event: Option<EventFn<S>>,
+    update: Option<UpdateFn<S>>,
+    render: Option<RenderFn<S>>,
+    runs_while_paused: bool,
+}
+
+impl<S> FnComponent<S> {
+    /// Creates a component without any hooks.
+    pub fn new() -> Self {
+        Self {
+            name: std::any::type_name::<Self>(),
+            setup: None,
+            on_resize: None,
+            on_event: None,
+            update: None,
+            render: None,
+            runs_while_paused: false,
+        }
+    }
+
+    /// Creates a component that only updates and renders.
+    pub fn from_fns(
+        update: impl FnMut(UpdateInfo, &mut SharedState<S>) + 'static,
+        render: impl Fn(&mut dyn Renderer, &SharedState<S>, i32) + 'static,
+    ) -> Self {
+        Self::new().with_update(update).with_render(render)
+    }
+
+    /// Sets the name shown in debugging tools, see [`Component::name`].
+    pub fn with_name(mut self, name: &'static str) -> Self {
+        self.name = name;
+        self
+    }
+
+    /// Runs `setup` once before the first frame. Afterwards, the `on_resize` hook is called with
+    /// the initial screen size.
+    pub fn with_setup(
+        mut self,
+        setup: impl FnMut(&SetupInfo, &mut SharedState<S>) + 'static,
+    ) -> Self {
+        self.setup = Some(Box::new(setup));
+        self
+    }
+
+    /// Runs `on_resize` when the terminal is resized, and once after setup.
+    pub fn with_on_resize(
+        mut self,
+        on_resize: impl FnMut(usize, usize, &mut SharedState<S>) + 'static,
+    ) -> Self {
+        self.on_resize = Some(Box::new(on_resize));
+        self
+    }
+
+    pub fn with_on_event(
+        mut self,
+        on_event: impl FnMut(Event, &mut SharedState<S>) -> Option<BreakingAction> + 'static,
+    ) -> Self {
+        self.on_event = Some(Box::new(on_event));
+        self
+    }
+
+    pub fn with_update(
+        mut self,
+        update: impl FnMut(UpdateInfo, &mut SharedState<S>) + 'static,
+    ) -> Self {
+        self.update = Some(Box::new(update));
+        self
+    }
+
+    pub fn with_render(
+        mut self,
+        render: impl Fn(&mut dyn Renderer, &SharedState<S>, i32) + 'static,
+    ) -> Self {
+        self.render = Some(Box::new(render));
+        self
+    }
+
+    /// See [`Component::runs_while_paused`].
+    pub fn with_runs_while_paused(mut self, runs_while_paused: bool) -> Self {
+        self.runs_while_paused = runs_while_paused;
+        self
+    }
+}
+
+impl<S> Default for FnComponent<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: 'static> Component<S> for FnComponent<S> {
+    fn setup(&mut self, setup_info: &SetupInfo, shared_state: &mut SharedState<S>) {
+        if let Some(setup) = &mut self.setup {
+            setup(setup_info, shared_state);
+        }
+        let display_info = &setup_info.display_info;
+        self.on_resize(display_info.width(), display_info.height(), shared_state);
+    }
+
+    fn on_resize(&mut self, width: usize, height: usize, shared_state: &mut SharedState<S>) {
+        if let Some(on_resize) = &mut self.on_resize {
+            on_resize(width, height, shared_state);
+        }
+    }
+
+    fn runs_while_paused(&self) -> bool {
+        self.runs_while_paused
+    }
+
+    fn on_event(
+        &mut self,
+        event: Event,
+        shared_state: &mut SharedState<S>,
+    ) -> Option<BreakingAction> {
+        let on_event = self.on_event.as_mut()?;
+        on_event(event, shared_state)
+    }
+
+    fn update(&mut self, update_info: UpdateInfo, shared_state: &mut SharedState<S>) {
+        if let Some(update) = &mut self.update {
+            update(update_info, shared_state);
+        }
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, shared_state: &SharedState<S>, depth_base: i32) {
+        if let Some(render) = &self.render {
+            render(renderer, shared_state, depth_base);
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+/// Declares a component struct together with its common glue, see the
+/// [`fncomponent` module](crate::components::fncomponent).
+///
+/// The macro generates
+/// * the struct. Fields are initialized with `= expr`, or with [`Default::default`],
+/// * `new()` and a [`Default`] implementation,
+/// * a [`Component`] implementation whose `on_resize` resizes the listed displays to the screen
+///   width and `x1` or `x2` the screen height, and whose `setup` calls `on_resize` if
+///   `setup_forwards_resize` is `true`. The remaining hooks are written in the `impl` block and
+///   must not include `setup` or `on_resize`.
+///
+/// The game state type goes in angle brackets after the name and defaults to `()`.
+///
+/// # Example
+/// ```
+/// use teng::component;
+/// use teng::components::Component;
+/// use teng::rendering::render::{HalfBlockDisplayRender, Render};
+/// use teng::rendering::renderer::Renderer;
+/// use teng::{Game, SharedState};
+///
+/// #[derive(Default)]
+/// struct GameState {
+///     frames: u32,
+/// }
+///
+/// component! {
+///     /// Draws the world.
+///     struct WorldView<GameState> {
+///         hbd: HalfBlockDisplayRender = HalfBlockDisplayRender::new(1, 1),
+///         zoom: u32,
+///     }
+///     resize_hbd: hbd (x2);
+///     setup_forwards_resize: true;
+///     impl {
+///         fn render(&self, renderer: &mut dyn Renderer, _: &SharedState<GameState>, depth: i32) {
+///             self.hbd.render(renderer, 0, 0, depth);
+///         }
+///     }
+/// }
+///
+/// let mut game = Game::<_, GameState>::new_headless(80, 24);
+/// // the display is resized to 80x48 during setup
+/// game.add_component(Box::new(WorldView::new()));
+/// game.run_frames(1).unwrap();
+/// ```
+#[macro_export]
+macro_rules! component {
+    (@state) => { () };
+    (@state $state:ty) => { $state };
+    (@init) => { ::std::default::Default::default() };
+    (@init $init:expr) => { $init };
+    (@factor x1) => { 1 };
+    (@factor x2) => { 2 };
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident $(<$state:ty>)? {
+            $(
+                $(#[$field_meta:meta])*
+                $field_vis:vis $field:ident : $field_ty:ty $(= $init:expr)?
+            ),* $(,)?
+        }
+        $(resize_hbd: $($hbd:ident ($factor:ident)),+ ;)?
+        $(setup_forwards_resize: $forward:literal ;)?
+        impl { $($body:tt)* }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($(#[$field_meta])* $field_vis $field: $field_ty,)*
+        }
+
+        impl $name {
+            $vis fn new() -> Self {
+                Self {
+                    $($field: $crate::component!(@init $($init)?),)*
+                }
+            }
+        }
+
+        impl ::std::default::Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl $crate::components::Component<$crate::component!(@state $($state)?)> for $name {
+            fn setup(
+                &mut self,
+                _setup_info: &$crate::SetupInfo,
+                _shared_state: &mut $crate::SharedState<$crate::component!(@state $($state)?)>,
+            ) {
+                $(
+                    if $forward {
+                        let display_info = &_setup_info.display_info;
+                        self.on_resize(display_info.width(), display_info.height(), _shared_state);
+                    }
+                )?
+            }
+
+            fn on_resize(
+                &mut self,
+                width: usize,
+                height: usize,
+                _shared_state: &mut $crate::SharedState<$crate::component!(@state $($state)?)>,
+            ) {
+                let _ = (width, height);
+                $($(
+                    self.$hbd.resize_discard(width, height * $crate::component!(@factor $factor));
+                )+)?
+            }
+
+            $($body)*
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Game;
+    use crate::rendering::render::{HalfBlockDisplayRender, Render};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_fn_component_hooks() {
+        let calls = Rc::new(RefCell::new(vec![]));
+        let log = |name: &'static str| {
+            let calls = calls.clone();
+            move || calls.borrow_mut().push(name)
+        };
+        let (setup, event, update) = (log("setup"), log("event"), log("update"));
+        let resize_calls = calls.clone();
+        let component = FnComponent::<()>::new()
+            .with_name("test")
+            .with_setup(move |_, _| setup())
+            .with_on_resize(move |width, height, _| {
+                resize_calls
+                    .borrow_mut()
+                    .push(if (width, height) == (4, 2) {
+                        "resize"
+                    } else {
+                        "resize to 5x3"
+                    })
+            })
+            .with_on_event(move |_, _| {
+                event();
+                None
+            })
+            .with_update(move |_, _| update())
+            .with_render(|renderer, _, depth_base| 'x'.render(renderer, 1, 1, depth_base));
+        assert_eq!(component.name(), "test");
+
+        let mut game = Game::<Vec<u8>, ()>::new_headless(4, 2);
+        game.add_component(Box::new(component));
+        game.run_frames(1).unwrap();
+        game.push_event(Event::Resize(5, 3));
+        game.run_frames(1).unwrap();
+        assert_eq!(game.frame().pixel_at(1, 1).c, 'x');
+        assert_eq!(
+            *calls.borrow(),
+            [
+                "setup",
+                "resize",
+                "update",
+                "event",
+                "resize to 5x3",
+                "update"
+            ]
+        );
+    }
+
+    component! {
+        struct Doubled {
+            hbd: HalfBlockDisplayRender = HalfBlockDisplayRender::new(1, 1),
+            cells: HalfBlockDisplayRender = HalfBlockDisplayRender::new(1, 1),
+            other: u32,
+        }
+        resize_hbd: hbd (x2), cells (x1);
+        setup_forwards_resize: true;
+        impl {
+            fn name(&self) -> &'static str {
+                "doubled"
+            }
+        }
+    }
+
+    #[test]
+    fn test_component_macro_resizes() {
+        let mut component = Doubled::new();
+        assert_eq!(component.other, 0);
+        assert_eq!(component.name(), "doubled");
+        let mut shared_state = SharedState::new(7, 3);
+        let setup_info = SetupInfo {
+            display_info: shared_state.display_info.clone(),
+        };
+        component.setup(&setup_info, &mut shared_state);
+        assert_eq!((component.hbd.width(), component.hbd.height()), (7, 6));
+        assert_eq!((component.cells.width(), component.cells.height()), (7, 3));
+        component.on_resize(5, 5, &mut shared_state);
+        assert_eq!((component.hbd.width(), component.hbd.height()), (5, 10));
+    }
+}
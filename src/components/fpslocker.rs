@@ -1,55 +1,238 @@
-use crate::{BreakingAction, Component, SetupInfo, SharedState, UpdateInfo};
-use crossterm::event::{Event, MouseEventKind};
+use crate::components::settings::{Setting, SettingValue, Settings};
+use crate::{Component, SetupInfo, SharedState, UpdateInfo};
+use std::time::Duration;
+
+/// How the game loop paces its frames, see [`FpsSettings`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FpsMode {
+    /// Runs the next frame right away, using a full CPU core.
+    Unlimited,
+    /// Sleeps between frames to run at the given frames per second.
+    Target(f64),
+    /// Waits for the next event before running a frame, but at most
+    /// [`FpsSettings::power_saver_timeout`]. An idle app uses next to no CPU, and still reacts
+    /// to input immediately.
+    ///
+    /// Animations only advance once per timeout, so this suits apps that change on input, or
+    /// components with an [`update_interval`](Component::update_interval) of at least the
+    /// timeout.
+    PowerSaver,
+}
+
+/// The frame pacing of the game loop, accessible via [`SharedState::fps`].
+///
+/// The [`FpsLockerComponent`] sets the mode when its settings change, but leaves it alone
+/// otherwise, so other components may set it as well.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FpsSettings {
+    pub mode: FpsMode,
+    /// The longest a [`FpsMode::PowerSaver`] frame waits for an event.
+    pub power_saver_timeout: Duration,
+}
+
+impl Default for FpsSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FpsSettings {
+    /// Creates unlimited settings with a power saver timeout of one second.
+    pub fn new() -> Self {
+        Self {
+            mode: FpsMode::Unlimited,
+            power_saver_timeout: Duration::from_secs(1),
+        }
+    }
+
+    /// Returns the target frames per second of [`FpsMode::Target`], if that is the mode.
+    pub fn target_fps(&self) -> Option<f64> {
+        match self.mode {
+            FpsMode::Target(fps) => Some(fps),
+            _ => None,
+        }
+    }
+}
 
 /// A component that locks the FPS to a certain value.
 ///
 /// Press 'l' to toggle the lock, and scroll up/down to change the target FPS.
+/// Both are stored in the `fps.locked` and `fps.target` settings. The lock sets
+/// [`SharedState::fps`] to [`FpsMode::Target`], and unlocking to [`FpsMode::Unlimited`], but
+/// only when the settings change, so a mode set by another component is kept until then.
 pub struct FpsLockerComponent {
     locked: bool,
     default_fps: f64,
+    toggle_key: Option<char>,
+    scroll_adjust: bool,
+    /// Whether [`FpsLockerComponent::set_target`] was called since the last update.
+    target_changed: bool,
+    /// The mode set last, to only set it again on changes.
+    applied: Option<FpsMode>,
 }
 
 impl FpsLockerComponent {
+    /// The key of the setting for whether the FPS are locked.
+    pub const LOCKED_SETTING: &'static str = "fps.locked";
+    /// The key of the setting for the target FPS.
+    pub const TARGET_SETTING: &'static str = "fps.target";
+
     pub fn new(default_fps: f64) -> Self {
         Self {
             locked: true,
             default_fps,
+            toggle_key: Some('l'),
+            scroll_adjust: true,
+            target_changed: false,
+            applied: None,
+        }
+    }
+
+    /// Sets the key that toggles the lock, or disables toggling with `None`.
+    pub fn with_toggle_key(mut self, key: Option<char>) -> Self {
+        self.toggle_key = key;
+        self
+    }
+
+    /// Enables or disables changing the target FPS by scrolling.
+    pub fn with_scroll_adjust(mut self, enabled: bool) -> Self {
+        self.scroll_adjust = enabled;
+        self
+    }
+
+    /// Locks the FPS to `fps`, from the next update on.
+    pub fn set_target(&mut self, fps: f64) {
+        self.default_fps = fps.max(1.0);
+        self.locked = true;
+        self.target_changed = true;
+        // also when the mode was changed by someone else since
+        self.applied = None;
+    }
+
+    fn mode(&self) -> FpsMode {
+        if self.locked {
+            FpsMode::Target(self.default_fps)
+        } else {
+            FpsMode::Unlimited
+        }
+    }
+
+    /// Sets the mode in the shared state if it changed since it was last set.
+    fn apply<S>(&mut self, shared_state: &mut SharedState<S>) {
+        let mode = self.mode();
+        if self.applied != Some(mode) {
+            shared_state.fps.mode = mode;
+            self.applied = Some(mode);
         }
     }
 }
 
-impl<S> Component<S> for FpsLockerComponent {
+impl<S: 'static> Component<S> for FpsLockerComponent {
+    fn runs_while_paused(&self) -> bool {
+        true
+    }
+
     fn setup(&mut self, setup_info: &SetupInfo, shared_state: &mut SharedState<S>) {
-        shared_state.target_fps = Some(self.default_fps);
-    }
-
-    fn on_event(
-        &mut self,
-        event: Event,
-        shared_state: &mut SharedState<S>,
-    ) -> Option<BreakingAction> {
-        match event {
-            Event::Mouse(me) => match me.kind {
-                MouseEventKind::ScrollDown => {
-                    self.default_fps -= 1.0;
-                    if self.default_fps < 1.0 {
-                        self.default_fps = 1.0;
-                    }
-                }
-                MouseEventKind::ScrollUp => {
-                    self.default_fps += 1.0;
-                }
-                _ => {}
-            },
-            _ => {}
-        }
-        None
+        let settings = shared_state.ext_or_default::<Settings<S>>();
+        settings.register(
+            Setting::bool(Self::LOCKED_SETTING, self.locked)
+                .with_label("Lock FPS")
+                .with_category("Performance"),
+        );
+        settings.register(
+            Setting::range(Self::TARGET_SETTING, self.default_fps, 1.0, 1000.0, 10.0)
+                .with_label("Target FPS")
+                .with_category("Performance"),
+        );
+        // the settings may have been loaded from a file
+        self.locked = settings
+            .get_bool(Self::LOCKED_SETTING)
+            .unwrap_or(self.locked);
+        self.default_fps = settings
+            .get_f64(Self::TARGET_SETTING)
+            .unwrap_or(self.default_fps);
+        self.apply(shared_state);
     }
 
     fn update(&mut self, update_info: UpdateInfo, shared_state: &mut SharedState<S>) {
-        if shared_state.pressed_keys.did_press_char_ignore_case('l') {
-            self.locked = !self.locked;
+        if std::mem::take(&mut self.target_changed) {
+            let settings = shared_state.ext_or_default::<Settings<S>>();
+            settings.set(Self::TARGET_SETTING, SettingValue::F64(self.default_fps));
+            settings.set(Self::LOCKED_SETTING, SettingValue::Bool(true));
+        }
+        let scroll = shared_state.mouse_info.scroll_delta_y;
+        if self.scroll_adjust && scroll != 0 {
+            self.default_fps = (self.default_fps + scroll as f64).max(1.0);
+            shared_state
+                .ext_or_default::<Settings<S>>()
+                .set(Self::TARGET_SETTING, SettingValue::F64(self.default_fps));
+        }
+        if let Some(key) = self.toggle_key
+            && shared_state.pressed_keys.did_press_char_ignore_case(key)
+        {
+            shared_state
+                .ext_or_default::<Settings<S>>()
+                .set(Self::LOCKED_SETTING, SettingValue::Bool(!self.locked));
         }
-        shared_state.target_fps = self.locked.then_some(self.default_fps);
+        // the settings may also have been changed by the settings menu or file
+        let settings = shared_state.ext_or_default::<Settings<S>>();
+        self.locked = settings
+            .get_bool(Self::LOCKED_SETTING)
+            .unwrap_or(self.locked);
+        self.default_fps = settings
+            .get_f64(Self::TARGET_SETTING)
+            .unwrap_or(self.default_fps);
+        self.apply(shared_state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DisplayInfo;
+    use crossterm::event::KeyCode;
+    use std::time::Instant;
+
+    fn update(locker: &mut FpsLockerComponent, shared_state: &mut SharedState<()>) {
+        let now = Instant::now();
+        let update_info = UpdateInfo::at(now);
+        locker.update(update_info, shared_state);
+    }
+
+    #[test]
+    fn test_mode_is_only_set_on_changes() {
+        let mut shared_state = SharedState::<()>::new(10, 5);
+        let mut locker = FpsLockerComponent::new(60.0).with_toggle_key(None);
+        let setup_info = SetupInfo {
+            display_info: DisplayInfo::new(10, 5),
+        };
+        locker.setup(&setup_info, &mut shared_state);
+        assert_eq!(shared_state.fps.mode, FpsMode::Target(60.0));
+
+        // a mode set by another component is kept
+        shared_state.fps.mode = FpsMode::PowerSaver;
+        update(&mut locker, &mut shared_state);
+        assert_eq!(shared_state.fps.mode, FpsMode::PowerSaver);
+
+        // the disabled key does nothing
+        shared_state.pressed_keys.insert(KeyCode::Char('l'));
+        update(&mut locker, &mut shared_state);
+        assert_eq!(shared_state.fps.mode, FpsMode::PowerSaver);
+
+        locker.set_target(30.0);
+        update(&mut locker, &mut shared_state);
+        assert_eq!(shared_state.fps.mode, FpsMode::Target(30.0));
+        assert_eq!(
+            shared_state
+                .ext_or_default::<Settings<()>>()
+                .get_f64(FpsLockerComponent::TARGET_SETTING),
+            Some(30.0)
+        );
+
+        shared_state
+            .ext_or_default::<Settings<()>>()
+            .set(FpsLockerComponent::LOCKED_SETTING, SettingValue::Bool(false));
+        update(&mut locker, &mut shared_state);
+        assert_eq!(shared_state.fps.mode, FpsMode::Unlimited);
     }
 }
@@ -1,33 +1,69 @@
-use crate::{BreakingAction, Component, SharedState, UpdateInfo};
-use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind};
-use std::collections::HashMap;
-use std::time::Instant;
+//! Keyboard input: key press recording, debouncing and normalization.
+//!
+//! # Held keys
+//!
+//! Most terminals only report key presses, and repeat them while a key is held. Terminals that
+//! support the kitty keyboard protocol also report releases once
+//! [`enable_key_release_events`](crate::enable_key_release_events) was called, and the Windows
+//! console always does. Then [`SharedState::held_keys`](crate::SharedState::held_keys) knows which
+//! keys are down and for how long, see [`HeldKeys`]. Elsewhere it stays empty, and
+//! [`KeypressDebouncerComponent`] approximates the down keys from repeated presses.
+//!
+//! # Normalization
+//!
+//! Terminals report some keys inconsistently, so the [`Game`](crate::Game) normalizes all key
+//! events with [`normalize_key_event`] before components see them. Components that want the
+//! events as delivered by crossterm can opt out with [`Component::wants_raw_events`].
+//!
+//! The canonical form is:
+//! * Backspace, Enter, Tab and Esc are always `KeyCode::Backspace`, `KeyCode::Enter`,
+//!   `KeyCode::Tab` and `KeyCode::Esc`, never their control characters. Shift-Tab is
+//!   `KeyCode::BackTab` with `SHIFT`.
+//! * Ctrl-H, Ctrl-M/Ctrl-J, Ctrl-I and Ctrl-[ are the keys above. Legacy terminals send the same
+//!   bytes for them, so they cannot be told apart.
+//! * Other control characters are the letter with `CONTROL`, e.g. `'\u{1}'` is Ctrl-A.
+//! * Letters carry `SHIFT` exactly if they are uppercase, e.g. `'A'` always has `SHIFT`, and
+//!   `'a'` with `SHIFT` becomes `'A'`. Other characters never carry `SHIFT`, since the character
+//!   already says whether it was shifted.
+//!
+//! Normalizing a normalized event does not change it.
+//!
+//! The translation table is derived from the bytes that xterm-compatible terminals send for
+//! these keys in their default (legacy) mode, as decoded by crossterm 0.28 on Unix, and from
+//! crossterm's Windows console backend, which reports control characters and `SHIFT` directly.
+
+use crate::util::smallmap::SmallMap;
+use crate::{BreakingAction, Component, Priority, SharedState, UpdateInfo};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
 
 // TODO: swap to `crokey` crate architecture?
 // Needing to check for "M" when we actually mean "shift-m" is a bit confusing.
 
 /// Contains the keys that have been pressed since the last update.
 pub struct PressedKeys {
-    inner: micromap::Map<KeyCode, u8, 16>,
+    inner: SmallMap<KeyCode, u8, 16>,
 }
 
 impl PressedKeys {
     /// Creates a new `PressedKeys` instance.
     pub fn new() -> Self {
         Self {
-            inner: micromap::Map::new(),
+            inner: SmallMap::new(),
         }
     }
 
     /// Returns the raw map of pressed keys.
-    pub fn inner(&self) -> &micromap::Map<KeyCode, u8, 16> {
+    pub fn inner(&self) -> &SmallMap<KeyCode, u8, 16> {
         &self.inner
     }
 
     /// Not recommended to use. However, it is useful to hack key actions in other components
     /// if the update order is known.
     pub fn insert(&mut self, key: KeyCode) {
-        self.inner.insert(key, 1);
+        // keys beyond the capacity are dropped
+        let _ = self.inner.insert(key, 1);
     }
 
     /// Returns true if the given key was pressed since the last update.
@@ -46,44 +82,125 @@ impl PressedKeys {
     }
 }
 
-/// A component that records key presses.
+/// The keys that are held down, if the terminal reports key releases.
+///
+/// Until the first release is reported, no key is held, and [`HeldKeys::is_tracking`] returns
+/// false.
+#[derive(Clone, Debug, Default)]
+pub struct HeldKeys {
+    down: HashMap<KeyCode, Instant>,
+    released: HashSet<KeyCode>,
+    tracking: bool,
+}
+
+impl HeldKeys {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns true if key releases are reported, i.e., if the held keys are known.
+    pub fn is_tracking(&self) -> bool {
+        self.tracking
+    }
+
+    /// Returns true if the key is held down.
+    pub fn is_held(&self, key: KeyCode) -> bool {
+        self.down.contains_key(&key)
+    }
+
+    /// Returns the time when the key was pressed, if it is held down.
+    pub fn pressed_at(&self, key: KeyCode) -> Option<Instant> {
+        self.down.get(&key).copied()
+    }
+
+    /// Returns how long the key has been held down, if it is.
+    pub fn held_duration(&self, key: KeyCode) -> Option<Duration> {
+        self.pressed_at(key).map(|pressed_at| pressed_at.elapsed())
+    }
+
+    /// Returns true if the key was released since the last update.
+    pub fn was_released_this_frame(&self, key: KeyCode) -> bool {
+        self.released.contains(&key)
+    }
+
+    /// Returns the held keys and when they were pressed.
+    pub fn iter(&self) -> impl Iterator<Item = (KeyCode, Instant)> + '_ {
+        self.down
+            .iter()
+            .map(|(key, pressed_at)| (*key, *pressed_at))
+    }
+}
+
+/// A component that records key presses and releases.
 ///
-/// Manages the `SharedState::pressed_keys` field, and must be in the update order before any component
-/// that uses `SharedState::pressed_keys`.
+/// Manages the `SharedState::pressed_keys` and `SharedState::held_keys` fields. It has the
+/// [`Priority::INPUT`] update priority, so that it runs before any component that uses them.
 pub struct KeyPressRecorderComponent {
-    pressed_keys: micromap::Map<KeyCode, u8, 16>,
+    pressed_keys: SmallMap<KeyCode, u8, 16>,
+    held_keys: HeldKeys,
 }
 
 impl KeyPressRecorderComponent {
     pub fn new() -> Self {
         Self {
-            pressed_keys: micromap::Map::new(),
+            pressed_keys: SmallMap::new(),
+            held_keys: HeldKeys::new(),
+        }
+    }
+
+    fn record_press(&mut self, code: KeyCode) {
+        if let Some(count) = self.pressed_keys.get_mut(&code) {
+            *count += 1;
+        } else {
+            // keys beyond the capacity are dropped
+            let _ = self.pressed_keys.insert(code, 1);
         }
     }
 }
 
 impl<S> Component<S> for KeyPressRecorderComponent {
+    fn runs_while_paused(&self) -> bool {
+        true
+    }
+
+    fn update_priority(&self) -> i32 {
+        Priority::INPUT.0
+    }
+
     fn on_event(
         &mut self,
         event: Event,
         shared_state: &mut SharedState<S>,
     ) -> Option<BreakingAction> {
+        let held = &mut self.held_keys;
+        held.tracking |= crate::key_release_events_enabled();
         match event {
-            // only capture presses to work on windows as well (where we get Release too)
+            // repeats are only reported separately with release events, count them as presses
+            Event::Key(KeyEvent {
+                kind: kind @ (KeyEventKind::Press | KeyEventKind::Repeat),
+                code,
+                ..
+            }) => {
+                if held.tracking {
+                    held.down.entry(code).or_insert_with(Instant::now);
+                } else if kind == KeyEventKind::Repeat {
+                    // a terminal that reports repeats separately also reports releases
+                    held.tracking = true;
+                }
+                self.record_press(code);
+            }
             Event::Key(KeyEvent {
-                kind: KeyEventKind::Press,
+                kind: KeyEventKind::Release,
                 code,
                 ..
             }) => {
-                if let Some(count) = self.pressed_keys.get_mut(&code) {
-                    *count += 1;
-                } else {
-                    // UB if we insert more than 16 keys
-                    if self.pressed_keys.len() < 16 {
-                        self.pressed_keys.insert(code, 1);
-                    }
+                held.tracking = true;
+                if held.down.remove(&code).is_some() {
+                    held.released.insert(code);
                 }
             }
+            // releases that happen while unfocused are not reported
+            Event::FocusLost => held.released.extend(held.down.drain().map(|(key, _)| key)),
             _ => {}
         }
         None
@@ -92,9 +209,51 @@ impl<S> Component<S> for KeyPressRecorderComponent {
     fn update(&mut self, update_info: UpdateInfo, shared_state: &mut SharedState<S>) {
         std::mem::swap(&mut shared_state.pressed_keys.inner, &mut self.pressed_keys);
         self.pressed_keys.clear();
+        shared_state.held_keys.clone_from(&self.held_keys);
+        self.held_keys.released.clear();
+    }
+}
+
+/// The debounced state of the keys, see [`KeypressDebouncerComponent`].
+#[derive(Clone, Debug, Default)]
+pub struct DebouncedKeys {
+    down: HashSet<KeyCode>,
+    repeats: HashMap<KeyCode, u32>,
+}
+
+impl DebouncedKeys {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns true if the key is considered down.
+    pub fn is_down(&self, key: KeyCode) -> bool {
+        self.down.contains(&key)
+    }
+
+    /// Returns the keys that are considered down.
+    pub fn down_keys(&self) -> &HashSet<KeyCode> {
+        &self.down
+    }
+
+    /// Returns how often the key repeated since the last update, which includes its first press.
+    ///
+    /// A held key repeats once per its debounce delay, so at low frame rates, a key with a short
+    /// delay can repeat multiple times in one frame.
+    pub fn repeats_this_frame(&self, key: KeyCode) -> u32 {
+        self.repeats.get(&key).copied().unwrap_or(0)
     }
 }
 
+/// The debounce state of a single key.
+struct KeyTimer {
+    last_press: Instant,
+    released_at: Option<Instant>,
+    next_repeat: Instant,
+    // repeats since the last update
+    pending: u32,
+}
+
 /// A component that tries to figure out the current "up"/"down" state of a key only from
 /// `KeyEventKind::Press` events.
 ///
@@ -103,37 +262,136 @@ impl<S> Component<S> for KeyPressRecorderComponent {
 /// Because typically those terminals will repeat a `KeyEventKind::Press` event after some delay if the key is held
 /// down, we can assume that a key is "down" if we have seen a `KeyEventKind::Press` event for it within that delay,
 /// and "up" if it's been longer than that delay since the last `KeyEventKind::Press` event.
+/// If the terminal reports releases, a key is up as soon as it is released.
+///
+/// While a key is down, it also repeats once per delay, see [`DebouncedKeys::repeats_this_frame`].
+/// The delay can be set per key, e.g. short for movement keys and long for action keys:
+/// ```
+/// use crossterm::event::KeyCode;
+/// use teng::components::keyboard::KeypressDebouncerComponent;
+///
+/// let debouncer = KeypressDebouncerComponent::new(100)
+///     .with_key_override(KeyCode::Char('w'), 40)
+///     .with_key_override(KeyCode::Char(' '), 500);
+/// ```
 ///
-/// It manages the `SharedState::debounced_down_keys` field, and must appear in the update order
-/// before any component that uses `SharedState::debounced_down_keys`.
+/// It manages the `SharedState::debounced_keys` field. It has the [`Priority::INPUT`] update
+/// priority, so that it runs before any component that uses `SharedState::debounced_keys`.
 pub struct KeypressDebouncerComponent {
     max_delay_ms: u128,
-    last_keypress: HashMap<KeyCode, Instant>,
+    key_overrides: HashMap<KeyCode, u128>,
+    timers: HashMap<KeyCode, KeyTimer>,
 }
 
 impl KeypressDebouncerComponent {
     pub fn new(max_delay_ms: u128) -> Self {
         Self {
             max_delay_ms,
-            last_keypress: HashMap::new(),
+            key_overrides: HashMap::new(),
+            timers: HashMap::new(),
+        }
+    }
+
+    /// Uses a delay of `millis` instead of the default for `key`.
+    pub fn with_key_override(mut self, key: KeyCode, millis: u128) -> Self {
+        self.key_overrides.insert(key, millis);
+        self
+    }
+
+    fn press(&mut self, key: KeyCode, now: Instant) {
+        let delay = self.delay(key);
+        match self.timers.get_mut(&key) {
+            Some(timer) if timer.released_at.is_none() => timer.last_press = now,
+            // a new press, or a press after a release, starts a new timer
+            timer => {
+                let pending = timer.map_or(0, |timer| timer.pending);
+                self.timers.insert(
+                    key,
+                    KeyTimer {
+                        last_press: now,
+                        released_at: None,
+                        next_repeat: now + delay,
+                        pending: pending + 1,
+                    },
+                );
+            }
+        }
+    }
+
+    fn release(&mut self, key: KeyCode, now: Instant) {
+        if let Some(timer) = self.timers.get_mut(&key) {
+            timer.released_at.get_or_insert(now);
+        }
+    }
+
+    fn delay(&self, key: KeyCode) -> Duration {
+        let millis = self
+            .key_overrides
+            .get(&key)
+            .copied()
+            .unwrap_or(self.max_delay_ms);
+        // a delay of 0 would repeat endlessly
+        Duration::from_millis(millis.max(1) as u64)
+    }
+
+    /// Counts the repeats up to `now` into `keys`, and forgets the keys that are up.
+    fn advance(&mut self, now: Instant, keys: &mut DebouncedKeys) {
+        keys.down.clear();
+        keys.repeats.clear();
+        let delays: Vec<_> = self
+            .timers
+            .keys()
+            .map(|&key| (key, self.delay(key)))
+            .collect();
+        for (key, delay) in delays {
+            let timer = self.timers.get_mut(&key).unwrap();
+            let up_at = timer.released_at.unwrap_or(timer.last_press + delay);
+            while timer.next_repeat <= now && timer.next_repeat < up_at {
+                timer.pending += 1;
+                timer.next_repeat += delay;
+            }
+            if timer.pending > 0 {
+                keys.repeats.insert(key, timer.pending);
+                timer.pending = 0;
+            }
+            if now < up_at {
+                keys.down.insert(key);
+            } else {
+                self.timers.remove(&key);
+            }
         }
     }
 }
 
 impl<S> Component<S> for KeypressDebouncerComponent {
+    fn runs_while_paused(&self) -> bool {
+        true
+    }
+
+    fn update_priority(&self) -> i32 {
+        Priority::INPUT.0
+    }
+
     fn on_event(
         &mut self,
         event: Event,
         shared_state: &mut SharedState<S>,
     ) -> Option<BreakingAction> {
         match event {
-            // only capture presses to work on windows as well (where we get Release too)
             Event::Key(KeyEvent {
-                kind: KeyEventKind::Press,
+                kind: KeyEventKind::Press | KeyEventKind::Repeat,
+                code,
+                ..
+            }) => {
+                self.press(code, Instant::now());
+            }
+            // only some terminals report releases
+            Event::Key(KeyEvent {
+                kind: KeyEventKind::Release,
                 code,
                 ..
             }) => {
-                self.last_keypress.insert(code, Instant::now());
+                self.release(code, Instant::now());
             }
             _ => {}
         }
@@ -141,19 +399,325 @@ impl<S> Component<S> for KeypressDebouncerComponent {
     }
 
     fn update(&mut self, update_info: UpdateInfo, shared_state: &mut SharedState<S>) {
-        // go through all keys, consume the ones that have expired
-        shared_state.debounced_down_keys.clear();
-        self.last_keypress = self
-            .last_keypress
-            .drain()
-            .filter(|(key, time)| {
-                if time.elapsed().as_millis() < self.max_delay_ms {
-                    shared_state.debounced_down_keys.insert(*key);
-                    true
-                } else {
-                    false
+        self.advance(Instant::now(), &mut shared_state.debounced_keys);
+    }
+}
+
+/// A known quirk: `raw` with `modifier` is reported for `canonical`.
+struct KeyQuirk {
+    raw: KeyCode,
+    modifier: KeyModifiers,
+    canonical: KeyCode,
+}
+
+const fn quirk(raw: KeyCode, modifier: KeyModifiers, canonical: KeyCode) -> KeyQuirk {
+    KeyQuirk {
+        raw,
+        modifier,
+        canonical,
+    }
+}
+
+/// Known quirks, keyed by what crossterm delivers.
+const KEY_QUIRKS: [KeyQuirk; 12] = [
+    // DEL, sent by most terminals for Backspace
+    quirk(
+        KeyCode::Char('\u{7f}'),
+        KeyModifiers::NONE,
+        KeyCode::Backspace,
+    ),
+    // BS, sent by some terminals for Backspace
+    quirk(
+        KeyCode::Char('\u{8}'),
+        KeyModifiers::NONE,
+        KeyCode::Backspace,
+    ),
+    // BS as decoded by crossterm on Unix
+    quirk(
+        KeyCode::Char('h'),
+        KeyModifiers::CONTROL,
+        KeyCode::Backspace,
+    ),
+    quirk(KeyCode::Char('\r'), KeyModifiers::NONE, KeyCode::Enter),
+    quirk(KeyCode::Char('\n'), KeyModifiers::NONE, KeyCode::Enter),
+    quirk(KeyCode::Char('m'), KeyModifiers::CONTROL, KeyCode::Enter),
+    // LF in raw mode as decoded by crossterm on Unix
+    quirk(KeyCode::Char('j'), KeyModifiers::CONTROL, KeyCode::Enter),
+    quirk(KeyCode::Char('\t'), KeyModifiers::NONE, KeyCode::Tab),
+    quirk(KeyCode::Char('i'), KeyModifiers::CONTROL, KeyCode::Tab),
+    quirk(KeyCode::Tab, KeyModifiers::SHIFT, KeyCode::BackTab),
+    quirk(KeyCode::Char('\u{1b}'), KeyModifiers::NONE, KeyCode::Esc),
+    quirk(KeyCode::Char('['), KeyModifiers::CONTROL, KeyCode::Esc),
+];
+
+/// Returns the canonical form of a key event.
+pub fn normalize_key_event(mut key: KeyEvent) -> KeyEvent {
+    if let Some(quirk) = KEY_QUIRKS
+        .iter()
+        .find(|q| q.raw == key.code && key.modifiers.contains(q.modifier))
+    {
+        key.code = quirk.canonical;
+        key.modifiers.remove(quirk.modifier);
+    }
+    if let KeyCode::Char(c) = key.code
+        && ('\u{1}'..='\u{1a}').contains(&c)
+    {
+        key.code = KeyCode::Char((b'a' + c as u8 - 1) as char);
+        key.modifiers.insert(KeyModifiers::CONTROL);
+    }
+    match key.code {
+        KeyCode::BackTab => key.modifiers.insert(KeyModifiers::SHIFT),
+        KeyCode::Char(c) if c.is_alphabetic() => {
+            if key.modifiers.contains(KeyModifiers::SHIFT) && c.is_lowercase() {
+                let mut upper = c.to_uppercase();
+                if let (Some(upper), None) = (upper.next(), upper.next()) {
+                    key.code = KeyCode::Char(upper);
                 }
-            })
-            .collect();
+            }
+            let is_upper = matches!(key.code, KeyCode::Char(c) if c.is_uppercase());
+            key.modifiers.set(KeyModifiers::SHIFT, is_upper);
+        }
+        KeyCode::Char(_) => key.modifiers.remove(KeyModifiers::SHIFT),
+        _ => {}
+    }
+    key
+}
+
+/// Returns the event with key events normalized by [`normalize_key_event`].
+pub fn normalize_event(event: Event) -> Event {
+    match event {
+        Event::Key(key) => Event::Key(normalize_key_event(key)),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    /// Asserts that all raw variants normalize to `canonical`, also when normalized twice.
+    fn assert_normalizes(raw_variants: &[KeyEvent], canonical: KeyEvent) {
+        for raw in raw_variants {
+            let normalized = normalize_key_event(*raw);
+            assert_eq!(normalized, canonical, "{raw:?}");
+            assert_eq!(normalize_key_event(normalized), canonical, "{raw:?}");
+        }
+    }
+
+    #[test]
+    fn test_control_key_quirks() {
+        use KeyCode::*;
+        let none = KeyModifiers::NONE;
+        let ctrl = KeyModifiers::CONTROL;
+        let shift = KeyModifiers::SHIFT;
+        assert_normalizes(
+            &[
+                key(Backspace, none),
+                key(Char('\u{7f}'), none),
+                key(Char('\u{8}'), none),
+                key(Char('h'), ctrl),
+            ],
+            key(Backspace, none),
+        );
+        assert_normalizes(
+            &[
+                key(Enter, none),
+                key(Char('\r'), none),
+                key(Char('\n'), none),
+                key(Char('m'), ctrl),
+                key(Char('j'), ctrl),
+            ],
+            key(Enter, none),
+        );
+        assert_normalizes(
+            &[key(Tab, none), key(Char('\t'), none), key(Char('i'), ctrl)],
+            key(Tab, none),
+        );
+        assert_normalizes(
+            &[key(BackTab, none), key(BackTab, shift), key(Tab, shift)],
+            key(BackTab, shift),
+        );
+        assert_normalizes(
+            &[
+                key(Esc, none),
+                key(Char('\u{1b}'), none),
+                key(Char('['), ctrl),
+            ],
+            key(Esc, none),
+        );
+        // control characters alias the letters
+        assert_normalizes(
+            &[key(Char('\u{1}'), none), key(Char('a'), ctrl)],
+            key(Char('a'), ctrl),
+        );
+        // modifiers other than the aliased one are kept
+        assert_normalizes(
+            &[
+                key(Char('\u{7f}'), KeyModifiers::ALT),
+                key(Char('h'), ctrl | KeyModifiers::ALT),
+            ],
+            key(Backspace, KeyModifiers::ALT),
+        );
+    }
+
+    #[test]
+    fn test_shift_quirks() {
+        use KeyCode::*;
+        let none = KeyModifiers::NONE;
+        let shift = KeyModifiers::SHIFT;
+        let ctrl = KeyModifiers::CONTROL;
+        assert_normalizes(
+            &[
+                key(Char('A'), none),
+                key(Char('A'), shift),
+                key(Char('a'), shift),
+            ],
+            key(Char('A'), shift),
+        );
+        assert_normalizes(&[key(Char('a'), none)], key(Char('a'), none));
+        assert_normalizes(
+            &[key(Char('!'), none), key(Char('!'), shift)],
+            key(Char('!'), none),
+        );
+        assert_normalizes(
+            &[key(Char('Ä'), none), key(Char('ä'), shift)],
+            key(Char('Ä'), shift),
+        );
+        assert_normalizes(
+            &[key(Char('B'), ctrl), key(Char('b'), ctrl | shift)],
+            key(Char('B'), ctrl | shift),
+        );
+        // other keys are unaffected
+        assert_normalizes(&[key(Up, shift)], key(Up, shift));
+        assert_normalizes(&[key(F(2), none)], key(F(2), none));
+    }
+
+    #[test]
+    fn test_held_keys() {
+        use std::time::Instant;
+        let mut shared_state = SharedState::<()>::new(10, 5);
+        let mut recorder = KeyPressRecorderComponent::new();
+        let now = Instant::now();
+        let update_info = UpdateInfo::at(now);
+        fn send(
+            recorder: &mut KeyPressRecorderComponent,
+            code: char,
+            kind: KeyEventKind,
+            shared_state: &mut SharedState,
+        ) {
+            let event = KeyEvent::new_with_kind(KeyCode::Char(code), KeyModifiers::NONE, kind);
+            recorder.on_event(Event::Key(event), shared_state);
+        }
+
+        // without releases, presses are recorded as before
+        send(&mut recorder, 'a', KeyEventKind::Press, &mut shared_state);
+        recorder.update(update_info, &mut shared_state);
+        assert!(shared_state.pressed_keys.did_press_char('a'));
+        assert!(!shared_state.held_keys.is_tracking());
+        assert_eq!(
+            shared_state.held_keys.held_duration(KeyCode::Char('a')),
+            None
+        );
+
+        // the first release starts tracking
+        send(&mut recorder, 'a', KeyEventKind::Release, &mut shared_state);
+        send(&mut recorder, 'b', KeyEventKind::Press, &mut shared_state);
+        recorder.update(update_info, &mut shared_state);
+        assert!(shared_state.held_keys.is_tracking());
+        assert!(
+            !shared_state
+                .held_keys
+                .was_released_this_frame(KeyCode::Char('a'))
+        );
+        assert!(shared_state.held_keys.is_held(KeyCode::Char('b')));
+        let pressed_at = shared_state.held_keys.pressed_at(KeyCode::Char('b'));
+
+        // repeats count as presses, but keep the time of the first press
+        send(&mut recorder, 'b', KeyEventKind::Repeat, &mut shared_state);
+        recorder.update(update_info, &mut shared_state);
+        assert!(shared_state.pressed_keys.did_press_char('b'));
+        assert_eq!(
+            shared_state.held_keys.pressed_at(KeyCode::Char('b')),
+            pressed_at
+        );
+        assert!(
+            shared_state
+                .held_keys
+                .held_duration(KeyCode::Char('b'))
+                .is_some()
+        );
+
+        send(&mut recorder, 'b', KeyEventKind::Release, &mut shared_state);
+        recorder.update(update_info, &mut shared_state);
+        assert!(
+            shared_state
+                .held_keys
+                .was_released_this_frame(KeyCode::Char('b'))
+        );
+        assert!(!shared_state.held_keys.is_held(KeyCode::Char('b')));
+        assert!(!shared_state.pressed_keys.did_press_char('b'));
+
+        recorder.update(update_info, &mut shared_state);
+        assert!(
+            !shared_state
+                .held_keys
+                .was_released_this_frame(KeyCode::Char('b'))
+        );
+    }
+
+    #[test]
+    fn test_debouncer_per_key_repeats() {
+        let w = KeyCode::Char('w');
+        let space = KeyCode::Char(' ');
+        let mut debouncer = KeypressDebouncerComponent::new(100)
+            .with_key_override(w, 30)
+            .with_key_override(space, 500);
+        let mut keys = DebouncedKeys::new();
+        let t0 = Instant::now();
+        let at = |millis| t0 + Duration::from_millis(millis);
+
+        debouncer.press(w, at(0));
+        debouncer.press(space, at(0));
+        debouncer.advance(at(0), &mut keys);
+        assert_eq!(keys.repeats_this_frame(w), 1);
+        assert_eq!(keys.repeats_this_frame(space), 1);
+        assert!(keys.is_down(w) && keys.is_down(space));
+
+        // the terminal repeats w every 20ms, and a slow frame sees multiple repeats of w
+        for millis in [20, 40, 60, 80, 100] {
+            debouncer.press(w, at(millis));
+        }
+        debouncer.advance(at(100), &mut keys);
+        assert_eq!(keys.repeats_this_frame(w), 3);
+        assert_eq!(keys.repeats_this_frame(space), 0);
+        assert!(keys.is_down(w) && keys.is_down(space));
+
+        // releasing and pressing again restarts the timer
+        debouncer.release(space, at(120));
+        debouncer.press(space, at(130));
+        debouncer.advance(at(150), &mut keys);
+        assert_eq!(keys.repeats_this_frame(space), 1);
+        // w is up 30ms after its last press, after one more repeat
+        assert_eq!(keys.repeats_this_frame(w), 1);
+        assert!(!keys.is_down(w));
+        assert!(keys.is_down(space));
+
+        debouncer.advance(at(600), &mut keys);
+        assert_eq!(keys.repeats_this_frame(space), 0);
+        assert!(keys.is_down(space));
+        debouncer.advance(at(630), &mut keys);
+        assert!(!keys.is_down(space));
+        assert_eq!(keys.down_keys().len(), 0);
+
+        // a tap that is released before the next update still counts
+        debouncer.press(w, at(700));
+        debouncer.release(w, at(710));
+        debouncer.advance(at(720), &mut keys);
+        assert_eq!(keys.repeats_this_frame(w), 1);
+        assert!(!keys.is_down(w));
     }
 }
@@ -0,0 +1,613 @@
+//! A scrollable view of log lines, e.g. for the output of a console or a game log.
+//!
+//! [`LogView`] keeps the last lines in a ring buffer and word wraps them to its width. Wrapping
+//! is lazy: only the lines that are shown are wrapped, and the result is cached until the width
+//! changes. Only the visible rows are rendered, so long logs cost nothing per frame.
+//!
+//! ```rust
+//! use teng::components::logview::{LogLine, LogView};
+//! use teng::components::problems::Severity;
+//!
+//! let mut view = LogView::new(1000);
+//! view.resize(40, 10);
+//! view.push_line("plain text");
+//! view.push_ansi_line("\x1b[32mok\x1b[0m: 3 tests passed");
+//! view.push(LogLine::with_severity(Severity::Warning, "low on memory"));
+//! assert_eq!(view.len(), 3);
+//! ```
+//! The view follows the tail of the log until it is scrolled up. While scrolled up, new lines do
+//! not move the visible lines, and an indicator shows how many lines arrived since.
+//!
+//! The [`LogViewComponent`] renders a view and scrolls it with the mouse wheel, PageUp/PageDown
+//! and End (back to the tail).
+
+use crate::components::Component;
+use crate::components::problems::Severity;
+use crate::rendering::ansi::{StyledSpan, parse_styled_text};
+use crate::rendering::color::Color;
+use crate::rendering::pixel::{Attributes, Pixel};
+use crate::rendering::render::Render;
+use crate::rendering::renderer::Renderer;
+use crate::{SetupInfo, SharedState, UpdateInfo};
+use crossterm::event::KeyCode;
+use std::collections::VecDeque;
+use std::sync::mpsc;
+
+/// A line of a [`LogView`].
+#[derive(Clone, Debug)]
+pub struct LogLine {
+    spans: Vec<StyledSpan>,
+    len: usize,
+    /// The severity of the line, if it has one.
+    pub severity: Option<Severity>,
+    /// The width the line was wrapped to, and the char index of the start of every row.
+    wrap: Option<(usize, Vec<usize>)>,
+}
+
+impl LogLine {
+    fn from_spans(spans: Vec<StyledSpan>) -> Self {
+        let len = spans.iter().map(|s| s.text.chars().count()).sum();
+        Self {
+            spans,
+            len,
+            severity: None,
+            wrap: None,
+        }
+    }
+
+    /// Creates a line in the default color.
+    pub fn plain(text: impl Into<String>) -> Self {
+        Self::from_spans(vec![StyledSpan {
+            text: text.into(),
+            color: Color::Default,
+            bg_color: Color::Transparent,
+        }])
+    }
+
+    /// Creates a line in a single color.
+    pub fn colored(text: impl Into<String>, color: [u8; 3]) -> Self {
+        Self::from_spans(vec![StyledSpan {
+            text: text.into(),
+            color: Color::Rgb(color),
+            bg_color: Color::Transparent,
+        }])
+    }
+
+    /// Creates a line in the color of `severity`.
+    pub fn with_severity(severity: Severity, text: impl Into<String>) -> Self {
+        Self {
+            severity: Some(severity),
+            ..Self::colored(text, severity.color())
+        }
+    }
+
+    /// Creates a line from text with SGR color sequences, see
+    /// [`parse_styled_text`].
+    pub fn from_ansi(text: &str) -> Self {
+        Self::from_spans(parse_styled_text(text))
+    }
+
+    /// Returns the text of the line without colors.
+    pub fn text(&self) -> String {
+        self.spans.iter().map(|s| s.text.as_str()).collect()
+    }
+
+    /// Returns the char index of the start of every row when wrapped to `width`.
+    ///
+    /// Rows break after the last space that fits, or inside a word that is longer than a row.
+    fn wrap(&self, width: usize) -> Vec<usize> {
+        let chars: Vec<char> = self.spans.iter().flat_map(|s| s.text.chars()).collect();
+        let width = width.max(1);
+        let mut starts = vec![0];
+        let mut start = 0;
+        while chars.len() - start > width {
+            let end = start + width;
+            // a space right after the row can be dropped as well
+            let space = chars[start + 1..=end].iter().rposition(|&c| c == ' ');
+            start = match space {
+                Some(idx) => start + 1 + idx + 1,
+                None => end,
+            };
+            starts.push(start);
+        }
+        starts
+    }
+
+    /// Returns the row starts for `width`, from the cache if possible.
+    fn rows(&self, width: usize) -> std::borrow::Cow<'_, [usize]> {
+        match &self.wrap {
+            Some((w, starts)) if *w == width => starts.into(),
+            _ => self.wrap(width).into(),
+        }
+    }
+
+    /// Returns the number of rows when wrapped to `width`, and caches the wrap.
+    fn row_count(&mut self, width: usize) -> usize {
+        if !matches!(&self.wrap, Some((w, _)) if *w == width) {
+            self.wrap = Some((width, self.wrap(width)));
+        }
+        self.wrap.as_ref().map_or(1, |(_, starts)| starts.len())
+    }
+
+    fn is_wrapped(&self, width: usize) -> bool {
+        matches!(&self.wrap, Some((w, _)) if *w == width)
+    }
+
+    /// Renders the chars `start..end` of the line.
+    fn render_row(
+        &self,
+        renderer: &mut dyn Renderer,
+        x: usize,
+        y: usize,
+        start: usize,
+        end: usize,
+        depth: i32,
+    ) {
+        let chars = self
+            .spans
+            .iter()
+            .flat_map(|s| s.text.chars().map(move |c| (c, s.color, s.bg_color)));
+        for (i, (c, color, bg_color)) in chars.skip(start).take(end - start).enumerate() {
+            let color = match color {
+                // the default of styled text, not a see-through foreground
+                Color::Transparent => Color::Default,
+                color => color,
+            };
+            let pixel = Pixel {
+                c,
+                color,
+                bg_color,
+                attributes: Attributes::NONE,
+            };
+            renderer.render_pixel(x + i, y, pixel, depth);
+        }
+    }
+}
+
+/// The last lines of a log, word wrapped and scrollable.
+#[derive(Debug)]
+pub struct LogView {
+    lines: VecDeque<LogLine>,
+    capacity: usize,
+    width: usize,
+    height: usize,
+    /// How many rows the view is scrolled up from the tail. Zero follows the tail.
+    scroll: usize,
+    /// How many lines were pushed while scrolled up.
+    new_lines: usize,
+}
+
+impl LogView {
+    /// Creates an empty view that keeps the last `capacity` lines.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            lines: VecDeque::with_capacity(capacity.min(1024)),
+            capacity: capacity.max(1),
+            width: 80,
+            height: 24,
+            scroll: 0,
+            new_lines: 0,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Sets the size of the view. Changing the width invalidates the wrapped lines.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        let width = width.max(1);
+        if width != self.width {
+            for line in &mut self.lines {
+                line.wrap = None;
+            }
+        }
+        self.width = width;
+        self.height = height;
+        if self.scroll > 0 {
+            self.clamp_scroll();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = &LogLine> {
+        self.lines.iter()
+    }
+
+    /// Removes all lines and follows the tail again.
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.scroll = 0;
+        self.new_lines = 0;
+    }
+
+    /// Appends a line, dropping the oldest line if the view is full.
+    ///
+    /// While scrolled up, the view keeps showing the same rows.
+    pub fn push(&mut self, mut line: LogLine) {
+        if self.scroll > 0 {
+            self.scroll += line.row_count(self.width);
+            self.new_lines += 1;
+        }
+        if self.lines.len() == self.capacity {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+        if self.scroll > 0 {
+            self.clamp_scroll();
+        }
+    }
+
+    /// Appends every line of `text` in the default color.
+    pub fn push_line(&mut self, text: &str) {
+        for line in text.lines() {
+            self.push(LogLine::plain(line));
+        }
+    }
+
+    /// Appends every line of `text`, colored by its SGR color sequences.
+    ///
+    /// Every line starts in the default colors.
+    pub fn push_ansi_line(&mut self, text: &str) {
+        for line in text.lines() {
+            self.push(LogLine::from_ansi(line));
+        }
+    }
+
+    /// Returns whether the view follows the tail of the log.
+    pub fn is_following(&self) -> bool {
+        self.scroll == 0
+    }
+
+    /// Returns how many rows the view is scrolled up from the tail.
+    pub fn scroll_offset(&self) -> usize {
+        self.scroll
+    }
+
+    /// Returns how many lines were pushed since the view was scrolled up.
+    pub fn new_lines(&self) -> usize {
+        self.new_lines
+    }
+
+    pub fn scroll_up(&mut self, rows: usize) {
+        self.scroll += rows;
+        self.clamp_scroll();
+    }
+
+    pub fn scroll_down(&mut self, rows: usize) {
+        self.scroll = self.scroll.saturating_sub(rows);
+        if self.scroll == 0 {
+            self.new_lines = 0;
+        }
+    }
+
+    /// Scrolls up by a page, keeping one row of the current page visible.
+    pub fn page_up(&mut self) {
+        self.scroll_up(self.height.saturating_sub(1).max(1));
+    }
+
+    /// Scrolls down by a page, keeping one row of the current page visible.
+    pub fn page_down(&mut self) {
+        self.scroll_down(self.height.saturating_sub(1).max(1));
+    }
+
+    /// Follows the tail of the log again.
+    pub fn scroll_to_tail(&mut self) {
+        self.scroll_down(self.scroll);
+    }
+
+    /// Limits the scroll offset so that the first row is at most at the top of the view.
+    fn clamp_scroll(&mut self) {
+        let needed = self.scroll + self.height;
+        let mut rows = 0;
+        for line in self.lines.iter_mut().rev() {
+            if rows >= needed {
+                return;
+            }
+            rows += line.row_count(self.width);
+        }
+        self.scroll = self.scroll.min(rows.saturating_sub(self.height));
+        if self.scroll == 0 {
+            self.new_lines = 0;
+        }
+    }
+
+    /// Wraps the visible lines that are not wrapped for the current width yet.
+    ///
+    /// Rendering wraps uncached lines on the fly, so this only saves work in later frames.
+    pub fn layout(&mut self) {
+        let needed = self.scroll + self.height;
+        let mut rows = 0;
+        for line in self.lines.iter_mut().rev() {
+            if rows >= needed {
+                break;
+            }
+            rows += line.row_count(self.width);
+        }
+    }
+
+    /// Returns how many of the lines are wrapped for the current width.
+    pub fn wrapped_lines(&self) -> usize {
+        self.lines
+            .iter()
+            .filter(|l| l.is_wrapped(self.width))
+            .count()
+    }
+
+    /// Returns the visible rows from top to bottom, as the index of the line and the char range
+    /// of the row.
+    fn visible_rows(&self) -> Vec<(usize, usize, usize)> {
+        let mut rows = Vec::with_capacity(self.height);
+        // rows counted from the tail
+        let mut skipped = 0;
+        for (idx, line) in self.lines.iter().enumerate().rev() {
+            if rows.len() == self.height {
+                break;
+            }
+            let starts = line.rows(self.width);
+            for (row, &start) in starts.iter().enumerate().rev() {
+                if skipped < self.scroll {
+                    skipped += 1;
+                    continue;
+                }
+                if rows.len() == self.height {
+                    break;
+                }
+                let end = starts.get(row + 1).copied().unwrap_or(line.len);
+                rows.push((idx, start, end));
+            }
+        }
+        rows.reverse();
+        rows
+    }
+}
+
+impl Render for LogView {
+    /// Renders the visible rows with the top left corner at `x`, `y`. A short log starts at the
+    /// top.
+    fn render(&self, renderer: &mut dyn Renderer, x: usize, y: usize, depth: i32) {
+        for (i, (idx, start, end)) in self.visible_rows().into_iter().enumerate() {
+            let line = &self.lines[idx];
+            // the space a row was broken at is not shown
+            let end = (start + self.width).min(end);
+            line.render_row(renderer, x, y + i, start, end, depth);
+        }
+        if self.new_lines > 0 && self.height > 0 {
+            let s = if self.new_lines == 1 { "" } else { "s" };
+            let text = format!(" {} new line{s} ↓ ", self.new_lines);
+            let text_x = x + self.width.saturating_sub(text.chars().count());
+            text.with_color([0, 0, 0])
+                .with_bg_color([200, 200, 200])
+                .render(renderer, text_x, y + self.height - 1, depth + 1);
+        }
+    }
+}
+
+/// A component that renders a [`LogView`] and scrolls it with the mouse wheel and keyboard.
+///
+/// Lines can be pushed from other components with [`LogViewComponent::view_mut`], or from
+/// anywhere, including other threads, through a [`LogViewComponent::sender`].
+pub struct LogViewComponent {
+    view: LogView,
+    /// The area of the view as x, y, width and height, or `None` for the whole screen.
+    rect: Option<(usize, usize, usize, usize)>,
+    sender: mpsc::Sender<LogLine>,
+    receiver: mpsc::Receiver<LogLine>,
+}
+
+impl LogViewComponent {
+    /// How many rows one step of the mouse wheel scrolls.
+    pub const WHEEL_ROWS: usize = 3;
+
+    pub fn new(view: LogView) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            view,
+            rect: None,
+            sender,
+            receiver,
+        }
+    }
+
+    /// Shows the view in the given area instead of the whole screen.
+    pub fn with_rect(mut self, x: usize, y: usize, width: usize, height: usize) -> Self {
+        self.rect = Some((x, y, width, height));
+        self.view.resize(width, height);
+        self
+    }
+
+    /// Returns a sender whose lines are appended to the view at the start of every update.
+    pub fn sender(&self) -> mpsc::Sender<LogLine> {
+        self.sender.clone()
+    }
+
+    pub fn view(&self) -> &LogView {
+        &self.view
+    }
+
+    pub fn view_mut(&mut self) -> &mut LogView {
+        &mut self.view
+    }
+
+    fn is_hovered(&self, mouse_pos: (usize, usize)) -> bool {
+        let Some((x, y, width, height)) = self.rect else {
+            return true;
+        };
+        let (mx, my) = mouse_pos;
+        (x..x + width).contains(&mx) && (y..y + height).contains(&my)
+    }
+}
+
+impl<S> Component<S> for LogViewComponent {
+    fn setup(&mut self, setup_info: &SetupInfo, _shared_state: &mut SharedState<S>) {
+        if self.rect.is_none() {
+            let display_info = &setup_info.display_info;
+            self.view
+                .resize(display_info.width(), display_info.height());
+        }
+    }
+
+    fn on_resize(&mut self, width: usize, height: usize, _shared_state: &mut SharedState<S>) {
+        if self.rect.is_none() {
+            self.view.resize(width, height);
+        }
+    }
+
+    fn update(&mut self, _update_info: UpdateInfo, shared_state: &mut SharedState<S>) {
+        for line in self.receiver.try_iter() {
+            self.view.push(line);
+        }
+
+        let mouse_info = &shared_state.mouse_info;
+        if mouse_info.scroll_delta_y != 0 && self.is_hovered(mouse_info.last_mouse_pos) {
+            let rows = mouse_info.scroll_delta_y.unsigned_abs() as usize * Self::WHEEL_ROWS;
+            if mouse_info.scroll_delta_y > 0 {
+                self.view.scroll_up(rows);
+            } else {
+                self.view.scroll_down(rows);
+            }
+        }
+        let keys = &shared_state.pressed_keys;
+        if keys.did_press(KeyCode::PageUp) {
+            self.view.page_up();
+        }
+        if keys.did_press(KeyCode::PageDown) {
+            self.view.page_down();
+        }
+        if keys.did_press(KeyCode::End) {
+            self.view.scroll_to_tail();
+        }
+
+        self.view.layout();
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, _shared_state: &SharedState<S>, depth_base: i32) {
+        let (x, y) = self.rect.map_or((0, 0), |(x, y, _, _)| (x, y));
+        self.view.render(renderer, x, y, depth_base);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn visible_text(view: &LogView) -> Vec<String> {
+        view.visible_rows()
+            .into_iter()
+            .map(|(idx, start, end)| {
+                let text: String = view.lines[idx].text().chars().skip(start).collect();
+                text.chars()
+                    .take(end - start)
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_word_wrap() {
+        let line = LogLine::plain("the quick brown fox");
+        assert_eq!(line.wrap(10), vec![0, 10]);
+        assert_eq!(line.wrap(5), vec![0, 4, 10, 16]);
+        // words longer than a row are broken
+        assert_eq!(LogLine::plain("abcdefgh").wrap(3), vec![0, 3, 6]);
+        assert_eq!(LogLine::plain("").wrap(3), vec![0]);
+    }
+
+    #[test]
+    fn test_wrap_cache_invalidation() {
+        let mut view = LogView::new(100);
+        view.resize(10, 2);
+        for i in 0..5 {
+            view.push_line(&format!("line {i} is rather long"));
+        }
+        // pushing does not wrap while following the tail
+        assert_eq!(view.wrapped_lines(), 0);
+        view.layout();
+        // only the visible lines are wrapped
+        assert_eq!(view.wrapped_lines(), 1);
+        assert_eq!(visible_text(&view), vec!["rather", "long"]);
+
+        view.resize(10, 5);
+        view.layout();
+        assert_eq!(view.wrapped_lines(), 2);
+        // a new width invalidates every cached wrap
+        view.resize(20, 5);
+        assert_eq!(view.wrapped_lines(), 0);
+        view.layout();
+        assert_eq!(view.wrapped_lines(), 3);
+        assert_eq!(
+            visible_text(&view),
+            vec![
+                "long",
+                "line 3 is rather",
+                "long",
+                "line 4 is rather",
+                "long"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_follow_tail_and_scrolled_append() {
+        let mut view = LogView::new(100);
+        view.resize(20, 2);
+        for i in 0..5 {
+            view.push_line(&format!("{i}"));
+        }
+        assert!(view.is_following());
+        assert_eq!(visible_text(&view), vec!["3", "4"]);
+        view.push_line("5");
+        assert_eq!(visible_text(&view), vec!["4", "5"]);
+
+        view.scroll_up(2);
+        assert_eq!(visible_text(&view), vec!["2", "3"]);
+        view.push_line("6\n7");
+        // the visible rows stay the same
+        assert_eq!(visible_text(&view), vec!["2", "3"]);
+        assert_eq!(view.scroll_offset(), 4);
+
+        // scrolling is limited to the first line
+        view.scroll_up(100);
+        assert_eq!(visible_text(&view), vec!["0", "1"]);
+        view.page_down();
+        assert_eq!(visible_text(&view), vec!["1", "2"]);
+        view.scroll_to_tail();
+        assert!(view.is_following());
+        assert_eq!(visible_text(&view), vec!["6", "7"]);
+    }
+
+    #[test]
+    fn test_new_lines_indicator_count() {
+        let mut view = LogView::new(3);
+        view.resize(20, 1);
+        view.push_line("a\nb");
+        // no indicator while following the tail
+        assert_eq!(view.new_lines(), 0);
+
+        view.scroll_up(1);
+        view.push_line("c");
+        view.push_ansi_line("\x1b[31md\x1b[0m");
+        assert_eq!(view.new_lines(), 2);
+        // "a" was dropped, the view shows the first line that is left
+        assert_eq!(visible_text(&view), vec!["b"]);
+
+        view.scroll_down(1);
+        assert_eq!(view.new_lines(), 2);
+        view.scroll_down(2);
+        assert_eq!(view.new_lines(), 0);
+        assert_eq!(visible_text(&view), vec!["d"]);
+    }
+}
@@ -5,17 +5,58 @@
 //! added to a game to modify its behavior.
 
 use crate::rendering::renderer::Renderer;
-use crate::{BreakingAction, SetupInfo, SharedState, UpdateInfo};
+use crate::{BreakingAction, Priority, QuitResponse, SetupInfo, SharedState, UpdateInfo};
 use crossterm::event::Event;
-use std::any::Any;
+use std::any::{Any, TypeId};
+use std::time::Duration;
 
+pub mod audio;
+pub mod cast;
+pub mod cheats;
+pub mod context_menu;
+pub mod coordinates;
+pub mod daynight;
 pub mod debuginfo;
+pub mod dim;
+#[cfg(feature = "recording")]
 pub mod eventrecorder;
+pub mod flicker;
+pub mod fncomponent;
 pub mod fpslocker;
 pub mod keyboard;
+pub mod logview;
 pub mod mouse;
+pub mod notify;
+pub mod overlay_layout;
+pub mod problems;
 pub mod quitter;
+#[cfg(feature = "persistence")]
+pub mod saveslots;
+pub mod scene;
+pub mod screenshot;
+pub mod settings;
+pub mod toast;
+pub mod turns;
 pub mod ui;
+pub mod watch;
+
+/// A component or extension type that a component depends on, see
+/// [`Component::required_components`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Dependency {
+    pub type_id: TypeId,
+    /// The type's name, for error messages.
+    pub name: &'static str,
+}
+
+impl Dependency {
+    pub fn of<T: 'static>() -> Self {
+        Self {
+            type_id: TypeId::of::<T>(),
+            name: std::any::type_name::<T>(),
+        }
+    }
+}
 
 /// A game component that can listen to events, perform logic, and render itself.
 /// Components are the main way to extend the game's functionality.
@@ -30,8 +71,83 @@ pub trait Component<S = ()>: Any {
     /// Called when the terminal is resized.
     /// Note that Resize events are also passed to on_event, so this is not strictly necessary.
     fn on_resize(&mut self, width: usize, height: usize, shared_state: &mut SharedState<S>) {}
+    /// Called when a component requested to quit, before the game exits. Return
+    /// [`QuitResponse::Defer`] to keep the request pending, e.g. to show a "save before quitting?"
+    /// dialog, and [`QuitResponse::Veto`] to cancel it.
+    ///
+    /// A pending request is polled again every frame. Repeated quit requests force the game to
+    /// quit, see [`Game::set_force_quit`](crate::Game::set_force_quit).
+    fn on_quit_requested(&mut self, shared_state: &mut SharedState<S>) -> QuitResponse {
+        QuitResponse::Allow
+    }
     /// Called when the game exits. Useful for cleanup.
+    /// Components are called in reverse order, so that a component can rely on the components
+    /// added before it during its cleanup.
     fn on_quit(&mut self, shared_state: &mut SharedState<S>) {}
+    /// Called when the component is removed while the game runs, e.g. with
+    /// [`SharedState::remove_components`]. Defaults to [`Self::on_quit`], so that cleanup also
+    /// happens for removed components.
+    fn on_removed(&mut self, shared_state: &mut SharedState<S>) {
+        self.on_quit(shared_state);
+    }
+    /// Called when the [scene] of this component is entered, after its first `setup`.
+    fn on_scene_enter(&mut self, _shared_state: &mut SharedState<S>) {}
+    /// Called when the [scene] of this component leaves the scene stack.
+    fn on_scene_exit(&mut self, _shared_state: &mut SharedState<S>) {}
+    /// Called repeatedly after `on_quit` until it returns true, e.g. to wait for a background
+    /// save to finish. The game waits until all components are finished, for at most the timeout
+    /// set with [`Game::set_quit_finish_timeout`](crate::Game::set_quit_finish_timeout).
+    fn poll_quit_finished(&mut self, shared_state: &mut SharedState<S>) -> bool {
+        true
+    }
+    /// Called to determine if this component receives events as delivered by the terminal.
+    /// By default, key events are normalized first, see
+    /// [`normalize_key_event`](keyboard::normalize_key_event).
+    fn wants_raw_events(&self) -> bool {
+        false
+    }
+    /// Called to determine if this component stays active while a
+    /// [`ComponentFilter`](crate::ComponentFilter) excludes it, e.g. to quit or toggle debug
+    /// overlays from within a menu. Defaults to false.
+    fn ignores_filters(&self) -> bool {
+        false
+    }
+    /// Called to determine the order of `on_event` and `update`. Components with a higher priority
+    /// run first, components with the same priority run in the order they were added.
+    /// Can be overridden with
+    /// [`Game::add_component_with_priority`](crate::Game::add_component_with_priority).
+    fn update_priority(&self) -> i32 {
+        Priority::DEFAULT.0
+    }
+    /// Called to determine the render order. Components with a higher render priority are
+    /// rendered on top, components with the same priority in the order they were added.
+    /// The render order is independent of the update order, e.g. a HUD can update first but
+    /// render last.
+    fn render_priority(&self) -> i32 {
+        0
+    }
+    /// Called to determine how often `update` runs. `None`, the default, updates every frame.
+    ///
+    /// With an interval, `update` is skipped until the interval has elapsed since the last
+    /// update, and the `dt` of the next [`UpdateInfo`] covers the whole span, so integrations
+    /// and a [`FixedUpdateRunner`](crate::util::fixedupdate::FixedUpdateRunner) fed with it stay
+    /// correct. Time while the component is inactive, e.g. paused, is not counted. `on_event`
+    /// and `render` still run every frame.
+    ///
+    /// Can be overridden with
+    /// [`Game::set_component_interval`](crate::Game::set_component_interval).
+    fn update_interval(&self) -> Option<Duration> {
+        None
+    }
+    /// Called to determine if `update` runs while [`SharedState::paused`] is set. Defaults to
+    /// false, so that game logic stops. Pause menus, input trackers and debug overlays return
+    /// true.
+    ///
+    /// `on_event` and `render` run while paused either way. The first update after the pause
+    /// has the `dt` of a single frame, not the whole pause.
+    fn runs_while_paused(&self) -> bool {
+        false
+    }
     /// Called when an event is received. This could happen multiple times per frame. Runs before update.
     fn on_event(
         &mut self,
@@ -45,4 +161,21 @@ pub trait Component<S = ()>: Any {
     /// Called once per frame to render the component. Each component has 100 depth available
     /// starting from the base.
     fn render(&self, renderer: &mut dyn Renderer, shared_state: &SharedState<S>, depth_base: i32) {}
+    /// Called once after setup to determine the components this component relies on, e.g. the
+    /// [`MouseTrackerComponent`](mouse::MouseTrackerComponent) for anything that reads
+    /// [`SharedState::mouse_info`]. They must be updated before this component, see
+    /// [`Game::set_dependency_check`](crate::Game::set_dependency_check).
+    fn required_components(&self) -> Vec<Dependency> {
+        vec![]
+    }
+    /// Called once after setup to determine the [`SharedState::extensions`] this component
+    /// relies on. They must have been inserted during setup.
+    fn required_extensions(&self) -> Vec<Dependency> {
+        vec![]
+    }
+    /// Called to name the component in debugging tools, e.g. the
+    /// [`FlickerDetectorComponent`](flicker::FlickerDetectorComponent). Defaults to the type name.
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
 }
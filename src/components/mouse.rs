@@ -1,6 +1,7 @@
 use crate::util::for_coord_in_line;
-use crate::{BreakingAction, Component, SharedState, UpdateInfo};
-use crossterm::event::{Event, MouseEvent, MouseEventKind};
+use crate::{BreakingAction, Component, Priority, SharedState, UpdateInfo};
+use crossterm::event::{Event, MouseButton, MouseEvent, MouseEventKind};
+use std::time::{Duration, Instant};
 
 /// Information about the current *state* of the mouse.
 /// If you are interested in mouse button presses, see `MousePressedInfo`.
@@ -15,6 +16,14 @@ pub struct MouseInfo {
     pub right_mouse_down: bool,
     /// Is the middle mouse button currently down?
     pub middle_mouse_down: bool,
+    /// The vertical scroll since the last frame, in wheel steps. Positive values scroll up.
+    ///
+    /// All scroll events of a frame add up, and the delta is zero on frames without scrolling.
+    pub scroll_delta_y: i32,
+    /// The horizontal scroll since the last frame, in wheel steps. Positive values scroll right.
+    ///
+    /// Only some terminals send horizontal scroll events.
+    pub scroll_delta_x: i32,
 }
 
 /// Information about mouse button presses since last frame.
@@ -46,6 +55,163 @@ pub struct MouseReleasedInfo {
     pub middle: bool,
 }
 
+/// A drag with a mouse button, in screen coordinates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Drag {
+    /// Where the button was pressed.
+    pub start: (usize, usize),
+    /// Where the mouse is now, or where the button was released for a finished drag.
+    pub current: (usize, usize),
+    pub button: MouseButton,
+}
+
+/// Mouse gestures recognized by the [`MouseTrackerComponent`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MouseGestures {
+    /// Where the left mouse button was double clicked since the last frame.
+    pub double_clicked_left: Option<(usize, usize)>,
+    /// The drag that is currently in progress.
+    ///
+    /// A press only becomes a drag once the mouse moves further than the drag threshold from
+    /// where it was pressed, see [`MouseTrackerComponent::set_drag_threshold`]. Plain clicks are
+    /// never drags.
+    pub drag_in_progress: Option<Drag>,
+    /// The drag that ended since the last frame.
+    pub drag_finished: Option<Drag>,
+}
+
+/// Recognizes [`MouseGestures`] from mouse events.
+#[derive(Debug)]
+struct GestureTracker {
+    double_click_window: Duration,
+    double_click_radius: usize,
+    drag_threshold: usize,
+    /// The time and position of the last left click that may start a double click.
+    last_click: Option<(Instant, (usize, usize))>,
+    /// The position and button of the current press.
+    press: Option<((usize, usize), MouseButton)>,
+    gestures: MouseGestures,
+}
+
+impl GestureTracker {
+    fn new() -> Self {
+        Self {
+            double_click_window: Duration::from_millis(400),
+            double_click_radius: 1,
+            drag_threshold: 1,
+            last_click: None,
+            press: None,
+            gestures: MouseGestures::default(),
+        }
+    }
+
+    fn on_mouse_event(&mut self, event: MouseEvent, now: Instant) {
+        let pos = (event.column as usize, event.row as usize);
+        match event.kind {
+            MouseEventKind::Down(button) => {
+                if button == MouseButton::Left {
+                    match self.last_click.take() {
+                        Some((time, click_pos))
+                            if now.duration_since(time) <= self.double_click_window
+                                && distance(click_pos, pos) <= self.double_click_radius =>
+                        {
+                            self.gestures.double_clicked_left = Some(pos);
+                        }
+                        _ => self.last_click = Some((now, pos)),
+                    }
+                }
+                self.press = Some((pos, button));
+                self.gestures.drag_in_progress = None;
+            }
+            MouseEventKind::Drag(_) | MouseEventKind::Moved => {
+                if let Some(drag) = &mut self.gestures.drag_in_progress {
+                    drag.current = pos;
+                } else if let Some((start, button)) = self.press
+                    && distance(start, pos) > self.drag_threshold
+                {
+                    // a drag is not the first click of a double click
+                    self.last_click = None;
+                    self.gestures.drag_in_progress = Some(Drag {
+                        start,
+                        current: pos,
+                        button,
+                    });
+                }
+            }
+            MouseEventKind::Up(button) if self.press.is_some_and(|(_, b)| b == button) => {
+                self.press = None;
+                if let Some(mut drag) = self.gestures.drag_in_progress.take() {
+                    drag.current = pos;
+                    self.gestures.drag_finished = Some(drag);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Forgets the current press, e.g. because its release will not be reported.
+    fn cancel_press(&mut self) {
+        self.press = None;
+        self.gestures.drag_in_progress = None;
+    }
+
+    /// Returns the gestures since the last call.
+    fn take_frame(&mut self) -> MouseGestures {
+        let gestures = self.gestures;
+        self.gestures.double_clicked_left = None;
+        self.gestures.drag_finished = None;
+        gestures
+    }
+}
+
+/// The Chebyshev distance between two cells.
+fn distance(a: (usize, usize), b: (usize, usize)) -> usize {
+    a.0.abs_diff(b.0).max(a.1.abs_diff(b.1))
+}
+
+/// Whether the terminal sends mouse events, see
+/// [`TerminalOptions::mouse_capture`](crate::TerminalOptions::mouse_capture).
+///
+/// Changes are applied with the next frame. While capture is disabled, the terminal's own text
+/// selection works, and no mouse buttons are down.
+#[derive(Debug)]
+pub struct MouseCapture {
+    enabled: bool,
+    requested: Option<bool>,
+}
+
+impl MouseCapture {
+    /// Creates the state for a terminal that was set up with or without mouse capture.
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            requested: None,
+        }
+    }
+
+    /// Returns true if mouse capture is enabled, including changes requested this frame.
+    pub fn is_enabled(&self) -> bool {
+        self.requested.unwrap_or(self.enabled)
+    }
+
+    /// Enables or disables mouse capture with the next frame.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.requested = (enabled != self.enabled).then_some(enabled);
+    }
+
+    /// Toggles mouse capture with the next frame.
+    pub fn toggle(&mut self) {
+        self.set_enabled(!self.is_enabled());
+    }
+
+    /// Returns the requested change, if any, and applies it.
+    pub(crate) fn take_request(&mut self) -> Option<bool> {
+        let enabled = self.requested.take()?;
+        self.enabled = enabled;
+        Some(enabled)
+    }
+}
+
 /// Aggregates mouse events since last frame.
 ///
 /// Use this to get various interpolation mechanics for mouse events, for example, a component
@@ -91,28 +257,86 @@ impl MouseEvents {
     /// since last frame. The closure is also called with the last mouse info if no event
     /// has been received this frame.
     /// To only get fresh events, use `for_each_linerp_only_fresh`.
-    pub fn for_each_linerp_sticky(&self, mut f: impl FnMut(MouseInfo)) {
-        if self.events.len() < 2 {
-            self.events.first().map(|mi| f(*mi));
-            return;
-        }
-        // do the start
-        f(*self.events.first().unwrap());
-        for i in 0..self.events.len() - 1 {
-            // and then every pair excluding the starts.
-            MouseTrackerComponent::smooth_two_updates(
-                true,
-                self.events[i],
-                self.events[i + 1],
-                &mut f,
+    ///
+    /// Consecutive events with the same position and state are only passed once.
+    pub fn for_each_linerp_sticky(&self, f: impl FnMut(MouseInfo)) {
+        self.for_each_linerp_scaled(1, 1, f);
+    }
+
+    /// Like [`MouseEvents::for_each_linerp_sticky`], but interpolates between the mouse positions
+    /// multiplied by `scale_x` and `scale_y`, which are also the positions passed to `f`.
+    ///
+    /// For example, a component drawing into a [`HalfBlockDisplayRender`] can use a scale of
+    /// `(1, 2)` to get a continuous stroke of half-block pixels instead of every other row.
+    /// The pixel of a cell is its top half.
+    ///
+    /// [`HalfBlockDisplayRender`]: crate::rendering::render::HalfBlockDisplayRender
+    pub fn for_each_linerp_scaled(
+        &self,
+        scale_x: usize,
+        scale_y: usize,
+        mut f: impl FnMut(MouseInfo),
+    ) {
+        self.for_each_linerp_mapped(
+            |(x, y)| ((x * scale_x) as i64, (y * scale_y) as i64),
+            |(x, y), mi| {
+                f(MouseInfo {
+                    last_mouse_pos: (x as usize, y as usize),
+                    ..mi
+                })
+            },
+        );
+    }
+
+    /// Like [`MouseEvents::for_each_linerp_sticky`], but maps every mouse position with `map`,
+    /// e.g. from the screen to world coordinates, and interpolates between the mapped positions.
+    ///
+    /// `f` is called with every mapped position of the interpolated line and the mouse info of
+    /// the event the position belongs to, as in [`MouseTrackerComponent::smooth_two_updates`].
+    /// The `last_mouse_pos` of that mouse info is the unmapped position of the event.
+    ///
+    /// Consecutive positions with the same button and scroll state are only passed once, so `f`
+    /// may apply non-idempotent changes, e.g. record an undo step per position.
+    pub fn for_each_linerp_mapped(
+        &self,
+        map: impl Fn((usize, usize)) -> (i64, i64),
+        mut f: impl FnMut((i64, i64), MouseInfo),
+    ) {
+        let mut last_passed = None;
+        let mut pass = |pos: (i64, i64), mi: MouseInfo| {
+            // the unmapped position does not matter, as long as the mapped one is the same
+            let key = (
+                pos,
+                MouseInfo {
+                    last_mouse_pos: (0, 0),
+                    ..mi
+                },
             );
+            if last_passed != Some(key) {
+                last_passed = Some(key);
+                f(pos, mi);
+            }
+        };
+
+        let Some(&first) = self.events.first() else {
+            return;
+        };
+        pass(map(first.last_mouse_pos), first);
+        for pair in self.events.windows(2) {
+            let (start, end) = (pair[0], pair[1]);
+            let to = map(end.last_mouse_pos);
+            for_coord_in_line(true, map(start.last_mouse_pos), to, |x, y| {
+                // same as smooth_two_updates, only the end point uses the state of the end
+                let mi = if (x, y) == to { end } else { start };
+                pass((x, y), mi);
+            });
         }
     }
 }
 
 /// Component that tracks mouse state and events.
 ///
-/// Should be early in the update order to ensure that other components can use the mouse state.
+/// Has the [`Priority::INPUT`] update priority, so that other components can use the mouse state.
 pub struct MouseTrackerComponent {
     last_mouse_info: MouseInfo,
     did_press_left: bool,
@@ -122,6 +346,7 @@ pub struct MouseTrackerComponent {
     did_release_right: bool,
     did_release_middle: bool,
     mouse_events: MouseEvents,
+    gestures: GestureTracker,
 }
 
 impl MouseTrackerComponent {
@@ -136,12 +361,38 @@ impl MouseTrackerComponent {
             did_release_right: false,
             did_release_middle: false,
             mouse_events: MouseEvents::new(),
+            gestures: GestureTracker::new(),
         }
     }
 
+    /// Sets the maximum time between the two clicks of a double click. The default is 400ms.
+    pub fn set_double_click_window(&mut self, window: Duration) {
+        self.gestures.double_click_window = window;
+    }
+
+    /// Sets how many cells apart the two clicks of a double click may be, to tolerate jitter.
+    /// The default is 1.
+    pub fn set_double_click_radius(&mut self, radius: usize) {
+        self.gestures.double_click_radius = radius;
+    }
+
+    /// Sets how many cells the mouse may move from where a button was pressed before the press
+    /// becomes a drag. The default is 1.
+    pub fn set_drag_threshold(&mut self, threshold: usize) {
+        self.gestures.drag_threshold = threshold;
+    }
+
     /// Updates a [`MouseInfo`] struct with the information from a `MouseEvent`.
+    /// Scroll events add to the scroll deltas.
     pub fn update_mouse_info(event: MouseEvent, mouse_info: &mut MouseInfo) {
         mouse_info.last_mouse_pos = (event.column as usize, event.row as usize);
+        match event.kind {
+            MouseEventKind::ScrollUp => mouse_info.scroll_delta_y += 1,
+            MouseEventKind::ScrollDown => mouse_info.scroll_delta_y -= 1,
+            MouseEventKind::ScrollRight => mouse_info.scroll_delta_x += 1,
+            MouseEventKind::ScrollLeft => mouse_info.scroll_delta_x -= 1,
+            _ => {}
+        }
         let (button, down) = match event {
             MouseEvent {
                 kind: MouseEventKind::Down(button),
@@ -155,13 +406,13 @@ impl MouseTrackerComponent {
         };
 
         match button {
-            crossterm::event::MouseButton::Left => {
+            MouseButton::Left => {
                 mouse_info.left_mouse_down = down;
             }
-            crossterm::event::MouseButton::Right => {
+            MouseButton::Right => {
                 mouse_info.right_mouse_down = down;
             }
-            crossterm::event::MouseButton::Middle => {
+            MouseButton::Middle => {
                 mouse_info.middle_mouse_down = down;
             }
         }
@@ -204,12 +455,22 @@ impl MouseTrackerComponent {
                 left_mouse_down: mi.left_mouse_down,
                 right_mouse_down: mi.right_mouse_down,
                 middle_mouse_down: mi.middle_mouse_down,
+                scroll_delta_y: mi.scroll_delta_y,
+                scroll_delta_x: mi.scroll_delta_x,
             });
         });
     }
 }
 
 impl<S> Component<S> for MouseTrackerComponent {
+    fn runs_while_paused(&self) -> bool {
+        true
+    }
+
+    fn update_priority(&self) -> i32 {
+        Priority::INPUT.0
+    }
+
     fn on_event(
         &mut self,
         event: Event,
@@ -217,6 +478,7 @@ impl<S> Component<S> for MouseTrackerComponent {
     ) -> Option<BreakingAction> {
         if let Event::Mouse(event) = event {
             Self::update_mouse_info(event, &mut self.last_mouse_info);
+            self.gestures.on_mouse_event(event, Instant::now());
             self.mouse_events.push(self.last_mouse_info);
             self.mouse_events.has_new_this_frame = true;
             match event {
@@ -227,13 +489,13 @@ impl<S> Component<S> for MouseTrackerComponent {
                     // Note: we are sticky-setting did_press_*: even if a Up event appear in the same
                     // frame, we're keeping the 'true'. Only next frame will we reset.
                     // The mouse_events queue should be used to handle inter-frame events.
-                    crossterm::event::MouseButton::Left => {
+                    MouseButton::Left => {
                         self.did_press_left = true;
                     }
-                    crossterm::event::MouseButton::Right => {
+                    MouseButton::Right => {
                         self.did_press_right = true;
                     }
-                    crossterm::event::MouseButton::Middle => {
+                    MouseButton::Middle => {
                         self.did_press_middle = true;
                     }
                 },
@@ -242,13 +504,13 @@ impl<S> Component<S> for MouseTrackerComponent {
                     ..
                 } => match button {
                     // Note: we are sticky-setting did_release_*, same as bove
-                    crossterm::event::MouseButton::Left => {
+                    MouseButton::Left => {
                         self.did_release_left = true;
                     }
-                    crossterm::event::MouseButton::Right => {
+                    MouseButton::Right => {
                         self.did_release_right = true;
                     }
-                    crossterm::event::MouseButton::Middle => {
+                    MouseButton::Middle => {
                         self.did_release_middle = true;
                     }
                 },
@@ -259,6 +521,13 @@ impl<S> Component<S> for MouseTrackerComponent {
     }
 
     fn update(&mut self, update_info: UpdateInfo, shared_state: &mut SharedState<S>) {
+        if !shared_state.mouse_capture.is_enabled() {
+            // releases are not reported without capture
+            self.last_mouse_info.left_mouse_down = false;
+            self.last_mouse_info.right_mouse_down = false;
+            self.last_mouse_info.middle_mouse_down = false;
+            self.gestures.cancel_press();
+        }
         shared_state.mouse_info = self.last_mouse_info;
         shared_state.mouse_pressed.right = self.did_press_right;
         shared_state.mouse_pressed.left = self.did_press_left;
@@ -266,11 +535,14 @@ impl<S> Component<S> for MouseTrackerComponent {
         shared_state.mouse_released.right = self.did_release_right;
         shared_state.mouse_released.left = self.did_release_left;
         shared_state.mouse_released.middle = self.did_release_middle;
+        shared_state.mouse_gestures = self.gestures.take_frame();
         std::mem::swap(&mut self.mouse_events, &mut shared_state.mouse_events);
         self.mouse_events.events.clear();
         // always have the last mouse info in the queue
         self.mouse_events.push(self.last_mouse_info);
         self.mouse_events.has_new_this_frame = false;
+        self.last_mouse_info.scroll_delta_y = 0;
+        self.last_mouse_info.scroll_delta_x = 0;
 
         self.did_press_left = false;
         self.did_press_right = false;
@@ -280,3 +552,159 @@ impl<S> Component<S> for MouseTrackerComponent {
         self.did_release_middle = false;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+
+    fn event(kind: MouseEventKind, (column, row): (u16, u16)) -> MouseEvent {
+        MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        }
+    }
+
+    fn click(tracker: &mut GestureTracker, pos: (u16, u16), now: Instant) {
+        tracker.on_mouse_event(event(MouseEventKind::Down(MouseButton::Left), pos), now);
+        tracker.on_mouse_event(event(MouseEventKind::Up(MouseButton::Left), pos), now);
+    }
+
+    #[test]
+    fn test_double_click() {
+        let mut tracker = GestureTracker::new();
+        let start = Instant::now();
+        click(&mut tracker, (5, 5), start);
+        assert_eq!(tracker.take_frame(), MouseGestures::default());
+        // jitter within the radius
+        click(&mut tracker, (6, 4), start + Duration::from_millis(300));
+        assert_eq!(tracker.take_frame().double_clicked_left, Some((6, 4)));
+        assert_eq!(tracker.take_frame().double_clicked_left, None);
+
+        // too slow, but the second click may start a new double click
+        click(&mut tracker, (5, 5), start + Duration::from_secs(1));
+        click(&mut tracker, (5, 5), start + Duration::from_millis(1500));
+        click(&mut tracker, (5, 5), start + Duration::from_millis(1600));
+        assert_eq!(tracker.take_frame().double_clicked_left, Some((5, 5)));
+
+        // too far
+        click(&mut tracker, (5, 5), start + Duration::from_secs(3));
+        click(&mut tracker, (8, 5), start + Duration::from_secs(3));
+        assert_eq!(tracker.take_frame().double_clicked_left, None);
+    }
+
+    #[test]
+    fn test_scroll_deltas_add_up_per_frame() {
+        let mut shared_state = SharedState::<()>::new(10, 10);
+        let mut tracker = MouseTrackerComponent::new();
+        let now = Instant::now();
+        let update_info = UpdateInfo::at(now);
+        let kinds = [
+            MouseEventKind::ScrollUp,
+            MouseEventKind::ScrollUp,
+            MouseEventKind::ScrollDown,
+            MouseEventKind::ScrollUp,
+            MouseEventKind::ScrollLeft,
+            MouseEventKind::Moved,
+        ];
+        for kind in kinds {
+            tracker.on_event(Event::Mouse(event(kind, (3, 4))), &mut shared_state);
+        }
+        tracker.update(update_info, &mut shared_state);
+        assert_eq!(shared_state.mouse_info.scroll_delta_y, 2);
+        assert_eq!(shared_state.mouse_info.scroll_delta_x, -1);
+        assert_eq!(shared_state.mouse_info.last_mouse_pos, (3, 4));
+
+        tracker.update(update_info, &mut shared_state);
+        assert_eq!(shared_state.mouse_info.scroll_delta_y, 0);
+        assert_eq!(shared_state.mouse_info.scroll_delta_x, 0);
+    }
+
+    #[test]
+    fn test_drag_threshold() {
+        let mut tracker = GestureTracker::new();
+        let now = Instant::now();
+        let right = MouseButton::Right;
+        tracker.on_mouse_event(event(MouseEventKind::Down(right), (2, 2)), now);
+        tracker.on_mouse_event(event(MouseEventKind::Drag(right), (3, 3)), now);
+        assert_eq!(tracker.take_frame(), MouseGestures::default());
+
+        tracker.on_mouse_event(event(MouseEventKind::Drag(right), (5, 3)), now);
+        let drag = Drag {
+            start: (2, 2),
+            current: (5, 3),
+            button: right,
+        };
+        assert_eq!(tracker.take_frame().drag_in_progress, Some(drag));
+
+        tracker.on_mouse_event(event(MouseEventKind::Up(right), (6, 1)), now);
+        let gestures = tracker.take_frame();
+        assert_eq!(gestures.drag_in_progress, None);
+        assert_eq!(
+            gestures.drag_finished,
+            Some(Drag {
+                current: (6, 1),
+                ..drag
+            })
+        );
+
+        // a click is not a drag
+        click(&mut tracker, (0, 0), now);
+        assert_eq!(tracker.take_frame(), MouseGestures::default());
+    }
+
+    fn mouse_info(pos: (usize, usize), left_mouse_down: bool) -> MouseInfo {
+        MouseInfo {
+            last_mouse_pos: pos,
+            left_mouse_down,
+            ..MouseInfo::default()
+        }
+    }
+
+    #[test]
+    fn test_linerp_scaled_is_continuous() {
+        let mut events = MouseEvents::new();
+        events.push(mouse_info((2, 0), true));
+        events.push(mouse_info((2, 3), true));
+        let mut positions = vec![];
+        events.for_each_linerp_scaled(1, 2, |mi| positions.push(mi.last_mouse_pos));
+        let expected: Vec<_> = (0..=6).map(|y| (2, y)).collect();
+        assert_eq!(positions, expected);
+
+        let mut positions = vec![];
+        events.for_each_linerp_sticky(|mi| positions.push(mi.last_mouse_pos));
+        assert_eq!(positions, [(2, 0), (2, 1), (2, 2), (2, 3)]);
+    }
+
+    #[test]
+    fn test_linerp_skips_duplicates() {
+        let mut events = MouseEvents::new();
+        // the last event of the previous frame, and a new one at the same position
+        events.push(mouse_info((1, 1), false));
+        events.push(mouse_info((1, 1), false));
+        events.push(mouse_info((1, 1), true));
+        events.push(mouse_info((1, 1), true));
+        events.push(mouse_info((3, 1), true));
+        let mut calls = vec![];
+        events.for_each_linerp_sticky(|mi| calls.push((mi.last_mouse_pos, mi.left_mouse_down)));
+        assert_eq!(
+            calls,
+            [
+                ((1, 1), false),
+                ((1, 1), true),
+                ((2, 1), true),
+                ((3, 1), true)
+            ]
+        );
+
+        // positions that map to the same cell are passed once
+        let mut calls = vec![];
+        events.for_each_linerp_mapped(
+            |(x, y)| (x as i64 / 2, y as i64 / 2),
+            |pos, mi| calls.push((pos, mi.left_mouse_down)),
+        );
+        assert_eq!(calls, [((0, 0), false), ((0, 0), true), ((1, 0), true)]);
+    }
+}
@@ -0,0 +1,634 @@
+//! Notifications that reach the player while the terminal is in the background.
+//!
+//! Games push notifications with [`SharedState::notify`], e.g. when a long-running round
+//! completes:
+//! ```rust
+//! use teng::SharedState;
+//!
+//! fn round_complete(shared_state: &mut SharedState) {
+//!     shared_state.notify("Round complete", "Earned 1.2M blocks");
+//! }
+//! ```
+//! The [`BackgroundNotifyComponent`] shows them as toasts in the top right corner, or the corner
+//! set with [`BackgroundNotifyComponent::with_anchor`], while the terminal has focus. While it does not, the component escalates without sound:
+//! * If the terminal supports desktop notifications (OSC 9 or OSC 777, see
+//!   [`DesktopNotifications`]), it sends one.
+//! * Otherwise, it prefixes the window title with `● ` until the terminal has focus again.
+//! * If the `notifications.bell` setting is enabled, it also rings the bell.
+//!
+//! Notifications that arrive in the background are shown as toasts once the terminal has focus
+//! again, collapsed into a single toast if there are many. The `notifications.do_not_disturb`
+//! setting turns off all escalation, the toasts are still shown.
+//!
+//! Focus changes are only reported if the terminal was set up with
+//! [`TerminalOptions::focus_change`](crate::TerminalOptions::focus_change). Without them, the
+//! terminal is assumed to have focus.
+
+use crate::components::Component;
+use crate::components::overlay_layout::{Corner, OverlayAnchor, Placement};
+use crate::components::settings::{Setting, Settings};
+use crate::rendering::palette;
+use crate::rendering::render::Render;
+use crate::rendering::renderer::Renderer;
+use crate::{BreakingAction, SetupInfo, SharedState, UpdateInfo};
+use crossterm::event::Event;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// A notification pushed with [`SharedState::notify`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Notification {
+    pub title: String,
+    pub body: String,
+}
+
+/// The notifications of the game, kept as an extension of the [`SharedState`] that
+/// [`SharedState::notify`] pushes to.
+#[derive(Debug, Default)]
+pub struct Notifications {
+    /// Notifications that no component has handled yet.
+    queued: VecDeque<Notification>,
+    /// Escape sequences that are written with the next frame.
+    escapes: String,
+}
+
+impl Notifications {
+    /// The maximum number of queued notifications. Older ones are dropped, e.g. if no
+    /// [`BackgroundNotifyComponent`] is installed.
+    pub const MAX_QUEUED: usize = 64;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues a notification, see [`SharedState::notify`].
+    pub fn push(&mut self, title: impl Into<String>, body: impl Into<String>) {
+        if self.queued.len() == Self::MAX_QUEUED {
+            self.queued.pop_front();
+        }
+        self.queued.push_back(Notification {
+            title: title.into(),
+            body: body.into(),
+        });
+    }
+
+    /// Returns the number of queued notifications.
+    pub fn len(&self) -> usize {
+        self.queued.len()
+    }
+
+    /// Returns true if no notifications are queued.
+    pub fn is_empty(&self) -> bool {
+        self.queued.is_empty()
+    }
+
+    /// Removes and returns the queued notifications, oldest first.
+    pub fn drain(&mut self) -> impl Iterator<Item = Notification> + '_ {
+        self.queued.drain(..)
+    }
+
+    /// Queues an escape sequence, e.g. a title change, that is written with the next frame.
+    pub fn queue_escape(&mut self, sequence: &str) {
+        self.escapes.push_str(sequence);
+    }
+
+    /// Returns the queued escape sequences.
+    pub(crate) fn take_escapes(&mut self) -> Option<String> {
+        (!self.escapes.is_empty()).then(|| std::mem::take(&mut self.escapes))
+    }
+}
+
+/// The desktop notification sequence a terminal supports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DesktopNotifications {
+    /// `OSC 9 ; text`, supported by iTerm2, Ghostty and kitty.
+    Osc9,
+    /// `OSC 777 ; notify ; title ; body`, supported by WezTerm, foot and urxvt.
+    Osc777,
+    /// No known support.
+    Unsupported,
+}
+
+impl DesktopNotifications {
+    /// Detects the support of the terminal from the `TERM_PROGRAM` and `TERM` environment
+    /// variables, see [`DesktopNotifications::from_env`].
+    pub fn detect() -> Self {
+        let term_program = std::env::var("TERM_PROGRAM").ok();
+        let term = std::env::var("TERM").ok();
+        Self::from_env(term_program.as_deref(), term.as_deref())
+    }
+
+    /// Returns the support for the values of the `TERM_PROGRAM` and `TERM` environment variables.
+    pub fn from_env(term_program: Option<&str>, term: Option<&str>) -> Self {
+        match term_program {
+            Some("iTerm.app" | "ghostty") => return DesktopNotifications::Osc9,
+            Some("WezTerm") => return DesktopNotifications::Osc777,
+            _ => {}
+        }
+        match term {
+            Some("xterm-kitty" | "xterm-ghostty") => DesktopNotifications::Osc9,
+            Some(term) if term.starts_with("foot") || term.starts_with("rxvt-unicode") => {
+                DesktopNotifications::Osc777
+            }
+            _ => DesktopNotifications::Unsupported,
+        }
+    }
+
+    /// Returns the sequence that shows `notification` on the desktop, if supported.
+    pub fn sequence(self, notification: &Notification) -> Option<String> {
+        let title = sanitize(&notification.title);
+        let body = sanitize(&notification.body);
+        match self {
+            DesktopNotifications::Osc9 if body.is_empty() => Some(format!("\x1b]9;{title}\x07")),
+            DesktopNotifications::Osc9 => Some(format!("\x1b]9;{title}: {body}\x07")),
+            DesktopNotifications::Osc777 => {
+                // the title must not end the parameter early
+                let title = title.replace(';', ",");
+                Some(format!("\x1b]777;notify;{title};{body}\x07"))
+            }
+            DesktopNotifications::Unsupported => None,
+        }
+    }
+}
+
+/// Removes control characters, which would end an escape sequence early.
+fn sanitize(text: &str) -> String {
+    text.chars().filter(|c| !c.is_control()).collect()
+}
+
+/// Returns the sequence that sets the window title.
+fn osc_title(title: &str) -> String {
+    format!("\x1b]2;{}\x07", sanitize(title))
+}
+
+/// Saves the window title on the terminal's title stack.
+const PUSH_TITLE: &str = "\x1b[22;2t";
+/// Restores the window title from the terminal's title stack.
+const POP_TITLE: &str = "\x1b[23;2t";
+const BELL: &str = "\x07";
+
+/// A toast in the stack of toasts, by default in the top right corner.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Toast {
+    title: String,
+    body: String,
+    expires_at: Instant,
+}
+
+impl Toast {
+    /// Returns the title and body as they are shown.
+    fn texts(&self) -> (String, String) {
+        let title = format!(" {} ", self.title);
+        let body = if self.body.is_empty() {
+            String::new()
+        } else {
+            format!("{} ", self.body)
+        };
+        (title, body)
+    }
+
+    fn width(&self) -> usize {
+        let (title, body) = self.texts();
+        title.chars().count() + body.chars().count()
+    }
+}
+
+/// Shows notifications as toasts, and escalates them while the terminal does not have focus.
+pub struct BackgroundNotifyComponent {
+    desktop_notifications: DesktopNotifications,
+    /// The game's window title, restored after the attention prefix.
+    title: Option<String>,
+    /// Whether the window title has the attention prefix.
+    title_marked: bool,
+    focused: bool,
+    /// Whether the terminal had focus in the last update.
+    was_focused: bool,
+    /// Notifications that arrived without focus, shown as toasts when the focus returns.
+    missed: Vec<Notification>,
+    toasts: Vec<Toast>,
+    toast_duration: Duration,
+    anchor: OverlayAnchor,
+    placement: Option<Placement>,
+}
+
+impl Default for BackgroundNotifyComponent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BackgroundNotifyComponent {
+    /// The setting that rings the bell for notifications without focus.
+    pub const BELL_SETTING: &'static str = "notifications.bell";
+    /// The setting that turns off all escalation.
+    pub const DO_NOT_DISTURB_SETTING: &'static str = "notifications.do_not_disturb";
+    /// At most this many missed notifications are shown as separate toasts.
+    pub const MAX_REPLAYED: usize = 3;
+    const ATTENTION_PREFIX: &'static str = "● ";
+    /// The name of the toast stack in the [`OverlayLayoutManager`](crate::components::overlay_layout::OverlayLayoutManager).
+    pub const OVERLAY: &'static str = "toasts";
+
+    /// Creates the component with the desktop notification support detected by
+    /// [`DesktopNotifications::detect`].
+    pub fn new() -> Self {
+        Self::with_desktop_notifications(DesktopNotifications::detect())
+    }
+
+    /// Creates the component with the given desktop notification support.
+    pub fn with_desktop_notifications(desktop_notifications: DesktopNotifications) -> Self {
+        Self {
+            desktop_notifications,
+            title: None,
+            title_marked: false,
+            focused: true,
+            was_focused: true,
+            missed: vec![],
+            toasts: vec![],
+            toast_duration: Duration::from_secs(4),
+            anchor: OverlayAnchor::new(Corner::TopRight),
+            placement: None,
+        }
+    }
+
+    /// Sets the game's window title, which the attention prefix is added to.
+    ///
+    /// Without it, the title is saved on the terminal's title stack and replaced by the
+    /// notification's title, which not all terminals restore.
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Sets how long a toast is shown. The default is four seconds.
+    pub fn with_toast_duration(mut self, duration: Duration) -> Self {
+        self.toast_duration = duration;
+        self
+    }
+
+    /// Sets the preferred corner of the toasts. The default is the top right corner.
+    pub fn with_anchor(mut self, anchor: OverlayAnchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    /// Returns true if the terminal has focus, as far as reported.
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    /// Returns true if the window title has the attention prefix.
+    pub fn is_title_marked(&self) -> bool {
+        self.title_marked
+    }
+
+    /// Returns the number of notifications that arrived without focus and were not shown yet.
+    pub fn missed(&self) -> usize {
+        self.missed.len()
+    }
+
+    /// Returns the title and body of the visible toasts, top to bottom.
+    pub fn toasts(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.toasts
+            .iter()
+            .map(|toast| (toast.title.as_str(), toast.body.as_str()))
+    }
+
+    fn show(&mut self, title: String, body: String, now: Instant) {
+        self.toasts.push(Toast {
+            title,
+            body,
+            expires_at: now + self.toast_duration,
+        });
+    }
+
+    /// Shows the missed notifications as toasts, collapsed into one if there are many.
+    fn replay_missed(&mut self, now: Instant) {
+        let missed = std::mem::take(&mut self.missed);
+        if missed.len() > Self::MAX_REPLAYED {
+            let latest = &missed[missed.len() - 1];
+            let title = format!("{} notifications while you were away", missed.len());
+            self.show(title, format!("Latest: {}", latest.title), now);
+        } else {
+            for notification in missed {
+                self.show(notification.title, notification.body, now);
+            }
+        }
+    }
+
+    fn escalate(&mut self, notification: &Notification, notifications: &mut Notifications) {
+        if let Some(sequence) = self.desktop_notifications.sequence(notification) {
+            notifications.queue_escape(&sequence);
+        } else if !self.title_marked {
+            let title = match &self.title {
+                Some(title) => title.clone(),
+                None => {
+                    notifications.queue_escape(PUSH_TITLE);
+                    notification.title.clone()
+                }
+            };
+            notifications.queue_escape(&osc_title(&format!("{}{title}", Self::ATTENTION_PREFIX)));
+            self.title_marked = true;
+        }
+    }
+
+    fn restore_title(&mut self, notifications: &mut Notifications) {
+        if !std::mem::take(&mut self.title_marked) {
+            return;
+        }
+        match &self.title {
+            Some(title) => notifications.queue_escape(&osc_title(title)),
+            None => notifications.queue_escape(POP_TITLE),
+        }
+    }
+}
+
+impl<S: 'static> Component<S> for BackgroundNotifyComponent {
+    fn runs_while_paused(&self) -> bool {
+        true
+    }
+
+    fn setup(&mut self, _setup_info: &SetupInfo, shared_state: &mut SharedState<S>) {
+        shared_state.ext_or_default::<Notifications>();
+        let settings = shared_state.ext_or_default::<Settings<S>>();
+        settings.register(
+            Setting::bool(Self::BELL_SETTING, false)
+                .with_label("Bell for background notifications")
+                .with_category("Notifications"),
+        );
+        settings.register(
+            Setting::bool(Self::DO_NOT_DISTURB_SETTING, false)
+                .with_label("Do not disturb")
+                .with_category("Notifications"),
+        );
+    }
+
+    fn on_event(
+        &mut self,
+        event: Event,
+        _shared_state: &mut SharedState<S>,
+    ) -> Option<BreakingAction> {
+        match event {
+            Event::FocusGained => self.focused = true,
+            Event::FocusLost => self.focused = false,
+            _ => {}
+        }
+        None
+    }
+
+    fn update(&mut self, update_info: UpdateInfo, shared_state: &mut SharedState<S>) {
+        let now = update_info.current_time;
+        if self.focused && !self.was_focused {
+            self.restore_title(shared_state.ext_or_default::<Notifications>());
+            self.replay_missed(now);
+        }
+        self.was_focused = self.focused;
+
+        let settings = shared_state.ext_or_default::<Settings<S>>();
+        let do_not_disturb = settings
+            .get_bool(Self::DO_NOT_DISTURB_SETTING)
+            .unwrap_or(false);
+        let bell = settings.get_bool(Self::BELL_SETTING).unwrap_or(false);
+        let notifications = shared_state.ext_or_default::<Notifications>();
+        let incoming: Vec<_> = notifications.drain().collect();
+        for notification in incoming {
+            if self.focused {
+                self.show(notification.title, notification.body, now);
+                continue;
+            }
+            if !do_not_disturb {
+                self.escalate(&notification, notifications);
+                if bell {
+                    notifications.queue_escape(BELL);
+                }
+            }
+            self.missed.push(notification);
+        }
+        self.toasts.retain(|toast| toast.expires_at > now);
+
+        if self.toasts.is_empty() {
+            shared_state.overlay_layout.release(Self::OVERLAY);
+            self.placement = None;
+        } else {
+            let display_width = shared_state.display_info.width();
+            let width = self.toasts.iter().map(Toast::width).max().unwrap_or(0);
+            self.placement = Some(shared_state.place_overlay(
+                Self::OVERLAY,
+                self.anchor,
+                width.min(display_width),
+                self.toasts.len(),
+            ));
+        }
+    }
+
+    fn render(
+        &self,
+        renderer: &mut dyn Renderer,
+        _shared_state: &SharedState<S>,
+        _depth_base: i32,
+    ) {
+        let Some(placement) = self.placement else {
+            return;
+        };
+        let rect = placement.rect;
+        let depth = i32::MAX - 85;
+        let bg = [30, 30, 30];
+        for (i, toast) in self.toasts.iter().take(rect.height).enumerate() {
+            let (title, body) = toast.texts();
+            let title_width = title.chars().count();
+            let total = toast.width().min(rect.width);
+            // toasts are aligned to the edge of the screen they are closest to
+            let x = if placement.corner.is_left() {
+                rect.x
+            } else {
+                rect.x + rect.width - total
+            };
+            let y = rect.y + i;
+            let title: String = title.chars().take(total).collect();
+            let body: String = body.chars().take(total - title.chars().count()).collect();
+            title
+                .with_color(palette::INFO)
+                .with_bg_color(bg)
+                .render(renderer, x, y, depth);
+            body.with_color([255, 255, 255]).with_bg_color(bg).render(
+                renderer,
+                x + title_width.min(total),
+                y,
+                depth,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Game;
+    use crate::components::fncomponent::FnComponent;
+    use crate::components::settings::SettingValue;
+
+    #[derive(Default)]
+    struct Escapes(String);
+
+    fn game(component: BackgroundNotifyComponent) -> Game<Vec<u8>, ()> {
+        let mut game = Game::<Vec<u8>, ()>::new_headless(80, 10);
+        game.shared_state_mut()
+            .extensions
+            .insert(Escapes::default());
+        game.add_component(Box::new(component));
+        // collects the escapes before the frame flushes them
+        game.add_component(Box::new(FnComponent::new().with_update(
+            |_, shared_state| {
+                let escapes = shared_state
+                    .ext_or_default::<Notifications>()
+                    .take_escapes()
+                    .unwrap_or_default();
+                shared_state
+                    .ext_or_default::<Escapes>()
+                    .0
+                    .push_str(&escapes);
+            },
+        )));
+        game
+    }
+
+    /// Runs a frame with the events, and returns the escapes it queued.
+    fn frame(game: &mut Game<Vec<u8>, ()>, events: &[Event]) -> String {
+        for event in events {
+            game.push_event(event.clone());
+        }
+        game.run_frames(1).unwrap();
+        std::mem::take(&mut game.shared_state_mut().ext_or_default::<Escapes>().0)
+    }
+
+    fn text(game: &Game<Vec<u8>, ()>) -> String {
+        let frame = game.frame();
+        let mut out = String::new();
+        for y in 0..frame.height() {
+            out.extend((0..frame.width()).map(|x| frame.pixel_at(x, y).c));
+            out.push('\n');
+        }
+        out
+    }
+
+    #[test]
+    fn test_capability_matrix() {
+        use DesktopNotifications::*;
+        let cases = [
+            (Some("iTerm.app"), Some("xterm-256color"), Osc9),
+            (Some("ghostty"), Some("xterm-ghostty"), Osc9),
+            (None, Some("xterm-kitty"), Osc9),
+            (Some("WezTerm"), Some("xterm-256color"), Osc777),
+            (None, Some("foot-extra"), Osc777),
+            (None, Some("rxvt-unicode-256color"), Osc777),
+            (Some("Apple_Terminal"), Some("xterm-256color"), Unsupported),
+            (None, Some("xterm"), Unsupported),
+            (None, None, Unsupported),
+        ];
+        for (term_program, term, expected) in cases {
+            assert_eq!(
+                DesktopNotifications::from_env(term_program, term),
+                expected,
+                "{term_program:?} {term:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_escalation_per_capability() {
+        let cases = [
+            (
+                DesktopNotifications::Osc9,
+                "\x1b]9;Round complete: Earned 1.2M blocks\x07",
+            ),
+            (
+                DesktopNotifications::Osc777,
+                "\x1b]777;notify;Round complete;Earned 1.2M blocks\x07",
+            ),
+            (
+                DesktopNotifications::Unsupported,
+                "\x1b[22;2t\x1b]2;● Round complete\x07",
+            ),
+        ];
+        for (desktop_notifications, expected) in cases {
+            let mut game = game(BackgroundNotifyComponent::with_desktop_notifications(
+                desktop_notifications,
+            ));
+            frame(&mut game, &[Event::FocusLost]);
+            game.shared_state_mut()
+                .notify("Round complete", "Earned 1.2M blocks");
+            let escapes = frame(&mut game, &[]);
+            assert_eq!(escapes, expected, "{desktop_notifications:?}");
+            assert!(!text(&game).contains("Round complete"));
+
+            // the missed notification is shown on return, and the title restored
+            let escapes = frame(&mut game, &[Event::FocusGained]);
+            let restored = if desktop_notifications == DesktopNotifications::Unsupported {
+                POP_TITLE
+            } else {
+                ""
+            };
+            assert_eq!(escapes, restored);
+            assert!(text(&game).contains(" Round complete Earned 1.2M blocks "));
+        }
+    }
+
+    #[test]
+    fn test_title_prefix_and_collapse() {
+        let mut game = game(
+            BackgroundNotifyComponent::with_desktop_notifications(
+                DesktopNotifications::Unsupported,
+            )
+            .with_title("Blocks"),
+        );
+        frame(&mut game, &[Event::FocusLost]);
+        game.shared_state_mut()
+            .ext_or_default::<Settings<()>>()
+            .set(
+                BackgroundNotifyComponent::BELL_SETTING,
+                SettingValue::Bool(true),
+            );
+
+        for i in 0..5 {
+            game.shared_state_mut().notify(format!("Milestone {i}"), "");
+        }
+        let escapes = frame(&mut game, &[]);
+        // the title is marked once, the bell rings for every notification
+        assert_eq!(escapes, format!("\x1b]2;● Blocks\x07{}", BELL.repeat(5)));
+
+        let escapes = frame(&mut game, &[Event::FocusGained]);
+        assert_eq!(escapes, "\x1b]2;Blocks\x07");
+        assert!(text(&game).contains(" 5 notifications while you were away Latest: Milestone 4 "));
+        // toasts expire
+        game.run_frames(300).unwrap();
+        assert!(!text(&game).contains("notifications while you were away"));
+    }
+
+    #[test]
+    fn test_do_not_disturb() {
+        let mut game = game(BackgroundNotifyComponent::with_desktop_notifications(
+            DesktopNotifications::Osc9,
+        ));
+        // settings are registered during setup
+        frame(&mut game, &[Event::FocusLost]);
+        let settings = game.shared_state_mut().ext_or_default::<Settings<()>>();
+        settings.set(
+            BackgroundNotifyComponent::BELL_SETTING,
+            SettingValue::Bool(true),
+        );
+        settings.set(
+            BackgroundNotifyComponent::DO_NOT_DISTURB_SETTING,
+            SettingValue::Bool(true),
+        );
+        game.shared_state_mut().notify("Round complete", "");
+        assert_eq!(frame(&mut game, &[]), "");
+        // still shown on return
+        frame(&mut game, &[Event::FocusGained]);
+        assert!(text(&game).contains(" Round complete "));
+
+        // with focus, notifications are toasts only
+        game.shared_state_mut().notify("Again", "");
+        assert_eq!(frame(&mut game, &[]), "");
+        assert!(text(&game).contains(" Round complete "));
+        assert!(text(&game).contains(" Again "));
+    }
+}
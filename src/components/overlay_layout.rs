@@ -0,0 +1,388 @@
+//! Placement of overlays in the corners of the screen.
+//!
+//! The built-in overlays, e.g. the [`DebugInfoComponent`](crate::components::debuginfo::DebugInfoComponent),
+//! the toasts of the [`BackgroundNotifyComponent`](crate::components::notify::BackgroundNotifyComponent)
+//! and the badge of the [`ProblemsPanelComponent`](crate::components::problems::ProblemsPanelComponent),
+//! are placed in a corner given by their [`OverlayAnchor`]. A game that draws important content in
+//! a corner reserves it, and the overlays move out of the way:
+//! ```
+//! use teng::components::Component;
+//! use teng::rendering::renderer::ClipRect;
+//! use teng::{SetupInfo, SharedState};
+//!
+//! struct MinimapComponent;
+//!
+//! impl Component for MinimapComponent {
+//!     fn setup(&mut self, _setup_info: &SetupInfo, shared_state: &mut SharedState) {
+//!         shared_state
+//!             .overlay_layout
+//!             .reserve("minimap", ClipRect::new(0, 0, 20, 10));
+//!     }
+//! }
+//! ```
+//!
+//! Every frame, each overlay asks the [`OverlayLayoutManager`] for a place with
+//! [`SharedState::place_overlay`](crate::SharedState::place_overlay). Its preferred corner is
+//! used if the overlay fits there without covering a reservation or another overlay, otherwise
+//! the other corners are tried in the order of [`Corner::preference_order`]. Since this happens
+//! every frame, overlays move as soon as the terminal is resized or a reservation changes. If no
+//! corner is free, the overlay stays in its preferred corner and a debug message is shown.
+
+use crate::rendering::renderer::ClipRect;
+use std::collections::BTreeMap;
+
+/// A corner of the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Corner {
+    pub const ALL: [Corner; 4] = [
+        Corner::TopLeft,
+        Corner::TopRight,
+        Corner::BottomLeft,
+        Corner::BottomRight,
+    ];
+
+    /// Returns the corners in the order they are tried for an overlay that prefers `self`: `self`,
+    /// the other corner on the same edge, the corner below or above, and the opposite corner.
+    pub fn preference_order(self) -> [Corner; 4] {
+        [
+            self,
+            self.mirrored_horizontally(),
+            self.mirrored_vertically(),
+            self.mirrored_horizontally().mirrored_vertically(),
+        ]
+    }
+
+    /// Returns true for the two corners at the top of the screen.
+    pub fn is_top(self) -> bool {
+        matches!(self, Corner::TopLeft | Corner::TopRight)
+    }
+
+    /// Returns true for the two corners on the left of the screen.
+    pub fn is_left(self) -> bool {
+        matches!(self, Corner::TopLeft | Corner::BottomLeft)
+    }
+
+    fn mirrored_horizontally(self) -> Corner {
+        match self {
+            Corner::TopLeft => Corner::TopRight,
+            Corner::TopRight => Corner::TopLeft,
+            Corner::BottomLeft => Corner::BottomRight,
+            Corner::BottomRight => Corner::BottomLeft,
+        }
+    }
+
+    fn mirrored_vertically(self) -> Corner {
+        match self {
+            Corner::TopLeft => Corner::BottomLeft,
+            Corner::TopRight => Corner::BottomRight,
+            Corner::BottomLeft => Corner::TopLeft,
+            Corner::BottomRight => Corner::TopRight,
+        }
+    }
+}
+
+/// Where an overlay would like to be: a corner, and the distance to the edges of the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OverlayAnchor {
+    pub corner: Corner,
+    pub margin_x: usize,
+    pub margin_y: usize,
+}
+
+impl OverlayAnchor {
+    /// Creates an anchor in `corner` without a margin.
+    pub const fn new(corner: Corner) -> Self {
+        Self {
+            corner,
+            margin_x: 0,
+            margin_y: 0,
+        }
+    }
+
+    /// Sets the number of columns and rows between the overlay and the edges of the screen.
+    pub const fn with_margin(mut self, margin_x: usize, margin_y: usize) -> Self {
+        self.margin_x = margin_x;
+        self.margin_y = margin_y;
+        self
+    }
+
+    /// Returns the rectangle of a `width` by `height` overlay in `corner` with this anchor's
+    /// margins, or `None` if it does not fit on a `display_width` by `display_height` screen.
+    pub fn rect_in(
+        &self,
+        corner: Corner,
+        width: usize,
+        height: usize,
+        display_width: usize,
+        display_height: usize,
+    ) -> Option<ClipRect> {
+        let free_x = display_width.checked_sub(width + self.margin_x)?;
+        let free_y = display_height.checked_sub(height + self.margin_y)?;
+        let x = if corner.is_left() {
+            self.margin_x
+        } else {
+            free_x
+        };
+        let y = if corner.is_top() {
+            self.margin_y
+        } else {
+            free_y
+        };
+        Some(ClipRect::new(x, y, width, height))
+    }
+}
+
+/// The place of an overlay, see [`OverlayLayoutManager::place`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Placement {
+    pub corner: Corner,
+    pub rect: ClipRect,
+    /// False if no corner was free, and the overlay was put in its preferred corner anyway.
+    pub fits: bool,
+}
+
+/// Reserved regions of the screen and the places of the overlays.
+#[derive(Debug, Default)]
+pub struct OverlayLayoutManager {
+    reservations: BTreeMap<String, ClipRect>,
+    /// Placements by overlay, in the order the overlays were first placed.
+    placements: Vec<(&'static str, Placement)>,
+}
+
+impl OverlayLayoutManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reserves a region of the screen for the game, so that overlays avoid it. Reserving the same
+    /// name again replaces the region.
+    pub fn reserve(&mut self, name: impl Into<String>, rect: ClipRect) {
+        self.reservations.insert(name.into(), rect);
+    }
+
+    /// Removes the reservation with the given name.
+    pub fn unreserve(&mut self, name: &str) {
+        self.reservations.remove(name);
+    }
+
+    /// Returns the reserved regions by name.
+    pub fn reservations(&self) -> impl Iterator<Item = (&str, ClipRect)> {
+        self.reservations
+            .iter()
+            .map(|(name, rect)| (name.as_str(), *rect))
+    }
+
+    /// Returns the last placement of an overlay.
+    pub fn placement(&self, owner: &str) -> Option<Placement> {
+        self.placements
+            .iter()
+            .find(|(name, _)| *name == owner)
+            .map(|(_, placement)| *placement)
+    }
+
+    /// Places a `width` by `height` overlay, preferably in the anchor's corner.
+    ///
+    /// A corner is free if the overlay fits on the screen there and does not intersect a
+    /// reservation or another overlay, and no other overlay is in that corner. The placements of
+    /// other overlays are kept, so an overlay that was placed first keeps its corner.
+    pub fn place(
+        &mut self,
+        owner: &'static str,
+        anchor: OverlayAnchor,
+        width: usize,
+        height: usize,
+        display_width: usize,
+        display_height: usize,
+    ) -> Placement {
+        let others: Vec<Placement> = self
+            .placements
+            .iter()
+            .filter(|(name, _)| *name != owner)
+            .map(|(_, placement)| *placement)
+            .collect();
+        let is_free = |corner: Corner, rect: ClipRect| {
+            let overlaps = |other: ClipRect| !other.intersect(rect).is_empty();
+            !self.reservations.values().any(|&r| overlaps(r))
+                && !others
+                    .iter()
+                    .any(|other| other.corner == corner || overlaps(other.rect))
+        };
+        let placement = anchor
+            .corner
+            .preference_order()
+            .into_iter()
+            .find_map(|corner| {
+                let rect = anchor.rect_in(corner, width, height, display_width, display_height)?;
+                is_free(corner, rect).then_some(Placement {
+                    corner,
+                    rect,
+                    fits: true,
+                })
+            })
+            .unwrap_or_else(|| {
+                // as close to the preferred corner as the screen allows
+                let corner = anchor.corner;
+                let width = width.min(display_width);
+                let height = height.min(display_height);
+                let anchor = OverlayAnchor::new(corner).with_margin(
+                    anchor.margin_x.min(display_width - width),
+                    anchor.margin_y.min(display_height - height),
+                );
+                Placement {
+                    corner,
+                    rect: anchor
+                        .rect_in(corner, width, height, display_width, display_height)
+                        .unwrap(),
+                    fits: false,
+                }
+            });
+        match self.placements.iter_mut().find(|(name, _)| *name == owner) {
+            Some((_, old)) => *old = placement,
+            None => self.placements.push((owner, placement)),
+        }
+        placement
+    }
+
+    /// Removes the placement of an overlay that is hidden, which frees its corner.
+    pub fn release(&mut self, owner: &str) {
+        self.placements.retain(|(name, _)| *name != owner);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZES: [(usize, usize); 4] = [(80, 24), (40, 12), (200, 60), (25, 10)];
+
+    fn assert_disjoint(manager: &OverlayLayoutManager) {
+        let placements: Vec<_> = manager.placements.iter().map(|(_, p)| *p).collect();
+        for (i, placement) in placements.iter().enumerate() {
+            assert!(placement.fits);
+            for (_, reserved) in manager.reservations() {
+                assert!(placement.rect.intersect(reserved).is_empty());
+            }
+            for other in &placements[i + 1..] {
+                assert_ne!(placement.corner, other.corner);
+                assert!(placement.rect.intersect(other.rect).is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_preference_order() {
+        assert_eq!(
+            Corner::TopLeft.preference_order(),
+            [
+                Corner::TopLeft,
+                Corner::TopRight,
+                Corner::BottomLeft,
+                Corner::BottomRight
+            ]
+        );
+        assert_eq!(
+            Corner::BottomRight.preference_order(),
+            [
+                Corner::BottomRight,
+                Corner::BottomLeft,
+                Corner::TopRight,
+                Corner::TopLeft
+            ]
+        );
+        let anchor = OverlayAnchor::new(Corner::BottomRight).with_margin(2, 1);
+        assert_eq!(
+            anchor.rect_in(Corner::BottomRight, 10, 3, 80, 24),
+            Some(ClipRect::new(68, 20, 10, 3))
+        );
+        assert_eq!(
+            anchor.rect_in(Corner::TopLeft, 10, 3, 80, 24),
+            Some(ClipRect::new(2, 1, 10, 3))
+        );
+        assert_eq!(anchor.rect_in(Corner::TopLeft, 79, 3, 80, 24), None);
+    }
+
+    #[test]
+    fn test_avoids_reservations() {
+        let debug = OverlayAnchor::new(Corner::TopLeft);
+        let toasts = OverlayAnchor::new(Corner::TopRight);
+        let badge = OverlayAnchor::new(Corner::BottomRight);
+        for (width, height) in SIZES {
+            let mut manager = OverlayLayoutManager::new();
+            manager.reserve("minimap", ClipRect::new(0, 0, 10, 5));
+            let place = |manager: &mut OverlayLayoutManager| {
+                [
+                    manager.place("toasts", toasts, 10, 2, width, height).corner,
+                    manager.place("badge", badge, 9, 1, width, height).corner,
+                    manager.place("debug", debug, 12, 4, width, height).corner,
+                ]
+            };
+            // the debug info cannot go to the top right, where the toasts are
+            assert_eq!(
+                place(&mut manager),
+                [Corner::TopRight, Corner::BottomRight, Corner::BottomLeft]
+            );
+            assert_disjoint(&manager);
+
+            // the placements are stable
+            assert_eq!(
+                place(&mut manager),
+                [Corner::TopRight, Corner::BottomRight, Corner::BottomLeft]
+            );
+
+            // everything moves back once the reservation is gone
+            manager.unreserve("minimap");
+            assert_eq!(
+                place(&mut manager),
+                [Corner::TopRight, Corner::BottomRight, Corner::TopLeft]
+            );
+            assert_disjoint(&manager);
+        }
+    }
+
+    #[test]
+    fn test_reflow_on_resize() {
+        let mut manager = OverlayLayoutManager::new();
+        let debug = OverlayAnchor::new(Corner::TopLeft).with_margin(1, 0);
+        // a sidebar along the left edge
+        manager.reserve("sidebar", ClipRect::new(0, 0, 40, 30));
+        let place = |manager: &mut OverlayLayoutManager, width, height| {
+            let placement = manager.place("debug", debug, 30, 10, width, height);
+            assert_disjoint_if_fits(manager, placement);
+            placement
+        };
+        let placement = place(&mut manager, 80, 24);
+        assert_eq!(placement.corner, Corner::TopRight);
+        assert_eq!(placement.rect, ClipRect::new(49, 0, 30, 10));
+
+        // too narrow for the right side, so nothing is free
+        let placement = place(&mut manager, 60, 24);
+        assert!(!placement.fits);
+        assert_eq!(placement.rect, ClipRect::new(1, 0, 30, 10));
+
+        // taller than the sidebar
+        let placement = place(&mut manager, 60, 50);
+        assert_eq!(placement.corner, Corner::BottomLeft);
+        assert_eq!(placement.rect, ClipRect::new(1, 40, 30, 10));
+
+        // too small for the overlay: the preferred corner, clamped to the screen
+        let placement = place(&mut manager, 20, 5);
+        assert!(!placement.fits);
+        assert_eq!(placement.rect, ClipRect::new(0, 0, 20, 5));
+
+        manager.unreserve("sidebar");
+        assert_eq!(place(&mut manager, 60, 24).corner, Corner::TopLeft);
+    }
+
+    fn assert_disjoint_if_fits(manager: &OverlayLayoutManager, placement: Placement) {
+        if placement.fits {
+            assert_disjoint(manager);
+        }
+    }
+}
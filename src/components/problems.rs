@@ -0,0 +1,485 @@
+//! Reporting recoverable problems to the player and developer.
+//!
+//! Components that run into a problem they can recover from, e.g. a save that failed or a
+//! missing asset, report it to [`SharedState::problems`] instead of panicking or pushing a debug
+//! message that scrolls away:
+//! ```rust
+//! use teng::SharedState;
+//! use teng::components::problems::{Problem, Severity};
+//!
+//! struct MusicComponent;
+//!
+//! fn play(shared_state: &mut SharedState) {
+//!     let source = std::any::type_name::<MusicComponent>();
+//!     if std::fs::metadata("music.ogg").is_err() {
+//!         shared_state.problems.report(
+//!             Problem::new(Severity::Warning, source, "music.ogg is missing")
+//!                 .with_hint("reinstall the game to restore its assets"),
+//!         );
+//!     } else {
+//!         // resolved
+//!         shared_state.problems.clear(source, "music.ogg is missing");
+//!     }
+//! }
+//! ```
+//! Reporting a problem with the same source and dedup key again updates the existing problem
+//! and counts the occurrence. Problems stay until they are dismissed or cleared.
+//!
+//! The [`ProblemsPanelComponent`] shows a badge in the bottom right corner, or the corner set with
+//! [`ProblemsPanelComponent::with_anchor`], while there are unacknowledged warnings or errors. Press F4 to open the list of problems.
+//! * Up/Down: select a problem
+//! * Enter: acknowledge the selected problem
+//! * Delete: dismiss the selected problem
+//!
+//! The panel is not part of [`Game::install_recommended_components`](crate::Game::install_recommended_components),
+//! games that want it add it themselves:
+//! ```rust
+//! # use teng::Game;
+//! # use teng::components::problems::ProblemsPanelComponent;
+//! # fn install<W: std::io::Write>(game: &mut Game<W>) {
+//! game.add_component(Box::new(ProblemsPanelComponent::new()));
+//! # }
+//! ```
+
+use crate::components::Component;
+use crate::components::overlay_layout::{Corner, OverlayAnchor, Placement};
+use crate::rendering::palette;
+use crate::rendering::render::Render;
+use crate::rendering::renderer::Renderer;
+use crate::{SharedState, UpdateInfo};
+use crossterm::event::KeyCode;
+use std::fmt;
+use std::time::Instant;
+
+/// How severe a [`Problem`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub(crate) fn color(self) -> [u8; 3] {
+        match self {
+            Severity::Info => palette::INFO,
+            Severity::Warning => palette::WARN,
+            Severity::Error => palette::DANGER,
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A recoverable problem, see [`Problems::report`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Problem {
+    pub severity: Severity,
+    /// The reporter of the problem, usually its `std::any::type_name`.
+    pub source: &'static str,
+    pub message: String,
+    /// How to resolve the problem.
+    pub hint: Option<String>,
+    /// Reports with the same source and dedup key are the same problem.
+    pub dedup_key: String,
+}
+
+impl Problem {
+    /// Creates a problem without a hint, deduplicated by its message.
+    pub fn new(severity: Severity, source: &'static str, message: impl Into<String>) -> Self {
+        let message = message.into();
+        Self {
+            severity,
+            source,
+            dedup_key: message.clone(),
+            message,
+            hint: None,
+        }
+    }
+
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    pub fn with_dedup_key(mut self, dedup_key: impl Into<String>) -> Self {
+        self.dedup_key = dedup_key.into();
+        self
+    }
+}
+
+/// A reported problem with its occurrences.
+#[derive(Clone, Debug)]
+pub struct ProblemEntry {
+    /// The last report of the problem.
+    pub problem: Problem,
+    /// How often the problem was reported.
+    pub count: usize,
+    pub first_seen: Instant,
+    pub last_seen: Instant,
+    /// Whether the problem was acknowledged since it was first reported.
+    pub acknowledged: bool,
+}
+
+/// The problems of the session, in the order they were first reported.
+#[derive(Default)]
+pub struct Problems {
+    entries: Vec<ProblemEntry>,
+}
+
+impl Problems {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reports a problem. If the same problem was reported before, it is updated instead.
+    ///
+    /// A problem that was acknowledged stays acknowledged, unless its severity increases.
+    pub fn report(&mut self, problem: Problem) {
+        let now = Instant::now();
+        let existing = self.position(problem.source, &problem.dedup_key);
+        if let Some(entry) = existing.map(|idx| &mut self.entries[idx]) {
+            if problem.severity > entry.problem.severity {
+                entry.acknowledged = false;
+            }
+            entry.problem = problem;
+            entry.count += 1;
+            entry.last_seen = now;
+        } else {
+            self.entries.push(ProblemEntry {
+                problem,
+                count: 1,
+                first_seen: now,
+                last_seen: now,
+                acknowledged: false,
+            });
+        }
+    }
+
+    fn position(&self, source: &str, dedup_key: &str) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|e| e.problem.source == source && e.problem.dedup_key == dedup_key)
+    }
+
+    /// Returns the problem with the given source and dedup key.
+    pub fn get(&self, source: &str, dedup_key: &str) -> Option<&ProblemEntry> {
+        self.position(source, dedup_key)
+            .map(|idx| &self.entries[idx])
+    }
+
+    /// Marks a problem as seen, which hides it from the badge.
+    pub fn acknowledge(&mut self, source: &str, dedup_key: &str) {
+        if let Some(idx) = self.position(source, dedup_key) {
+            self.entries[idx].acknowledged = true;
+        }
+    }
+
+    /// Removes a problem, e.g. because the player dismissed it.
+    pub fn dismiss(&mut self, source: &str, dedup_key: &str) {
+        if let Some(idx) = self.position(source, dedup_key) {
+            self.entries.remove(idx);
+        }
+    }
+
+    /// Removes a problem because it was resolved. Same as [`Problems::dismiss`].
+    pub fn clear(&mut self, source: &str, dedup_key: &str) {
+        self.dismiss(source, dedup_key);
+    }
+
+    /// Removes all problems reported by `source`.
+    pub fn clear_source(&mut self, source: &str) {
+        self.entries.retain(|e| e.problem.source != source);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ProblemEntry> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the number and the highest severity of the unacknowledged warnings and errors,
+    /// or `None` if there are none and no badge should be shown.
+    pub fn badge(&self) -> Option<(usize, Severity)> {
+        let unacknowledged = self
+            .entries
+            .iter()
+            .filter(|e| !e.acknowledged && e.problem.severity >= Severity::Warning);
+        let (count, severity) = unacknowledged.fold((0, Severity::Warning), |(n, s), e| {
+            (n + 1, s.max(e.problem.severity))
+        });
+        (count > 0).then_some((count, severity))
+    }
+}
+
+/// A component that shows the problems in [`SharedState::problems`].
+pub struct ProblemsPanelComponent {
+    open: bool,
+    selected: usize,
+    anchor: OverlayAnchor,
+    placement: Option<Placement>,
+}
+
+impl Default for ProblemsPanelComponent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProblemsPanelComponent {
+    /// The key that opens and closes the list of problems.
+    pub const TOGGLE_KEY: KeyCode = KeyCode::F(4);
+    /// The name of the badge and list in the [`OverlayLayoutManager`](crate::components::overlay_layout::OverlayLayoutManager).
+    pub const OVERLAY: &'static str = "problems";
+
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            selected: 0,
+            anchor: OverlayAnchor::new(Corner::BottomRight),
+            placement: None,
+        }
+    }
+
+    /// Sets the preferred corner of the badge and the list. The default is the bottom right corner.
+    pub fn with_anchor(mut self, anchor: OverlayAnchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    /// Returns whether the list of problems is open.
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+}
+
+impl<S> Component<S> for ProblemsPanelComponent {
+    fn runs_while_paused(&self) -> bool {
+        true
+    }
+
+    fn update(&mut self, _update_info: UpdateInfo, shared_state: &mut SharedState<S>) {
+        self.handle_keys(shared_state);
+
+        let lines = self.lines(&shared_state.problems);
+        if lines.is_empty() {
+            shared_state.overlay_layout.release(Self::OVERLAY);
+            self.placement = None;
+        } else {
+            let width = lines
+                .iter()
+                .map(|(l, _)| l.chars().count())
+                .max()
+                .unwrap_or(0);
+            self.placement =
+                Some(shared_state.place_overlay(Self::OVERLAY, self.anchor, width, lines.len()));
+        }
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, shared_state: &SharedState<S>, _depth_base: i32) {
+        let Some(placement) = self.placement else {
+            return;
+        };
+        let rect = placement.rect;
+        let depth = i32::MAX - 90;
+        let bg = [30, 30, 30];
+        let lines = self.lines(&shared_state.problems);
+        for (i, (line, color)) in lines.iter().take(rect.height).enumerate() {
+            let line: String = line.chars().take(rect.width).collect();
+            // the badge is aligned to the edge of the screen it is closest to
+            let x = if placement.corner.is_left() || self.open {
+                rect.x
+            } else {
+                rect.x + rect.width - line.chars().count()
+            };
+            let width = if self.open { rect.width } else { 0 };
+            format!("{line:width$}")
+                .with_color(*color)
+                .with_bg_color(bg)
+                .render(renderer, x, rect.y + i, depth);
+        }
+    }
+}
+
+impl ProblemsPanelComponent {
+    fn handle_keys<S>(&mut self, shared_state: &mut SharedState<S>) {
+        let keys = &shared_state.pressed_keys;
+        if keys.did_press(Self::TOGGLE_KEY) {
+            self.open = !self.open;
+        }
+        if !self.open {
+            return;
+        }
+        let problems = &mut shared_state.problems;
+        if keys.did_press(KeyCode::Up) {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        if keys.did_press(KeyCode::Down) {
+            self.selected += 1;
+        }
+        self.selected = self.selected.min(problems.len().saturating_sub(1));
+        let Some(entry) = problems.entries.get_mut(self.selected) else {
+            return;
+        };
+        if keys.did_press(KeyCode::Enter) {
+            entry.acknowledged = true;
+        } else if keys.did_press(KeyCode::Delete) {
+            problems.entries.remove(self.selected);
+        }
+    }
+
+    /// Returns the lines of the badge or the open list, which are empty if nothing is shown.
+    fn lines(&self, problems: &Problems) -> Vec<(String, [u8; 3])> {
+        if !self.open {
+            let Some((count, severity)) = problems.badge() else {
+                return vec![];
+            };
+            let text = format!(" {count} {severity}{} ", if count > 1 { "s" } else { "" });
+            return vec![(text, severity.color())];
+        }
+
+        let mut lines = vec![(
+            format!(
+                "Problems ({}) - Enter: acknowledge, Del: dismiss",
+                problems.len()
+            ),
+            [255, 255, 255],
+        )];
+        if problems.is_empty() {
+            lines.push(("No problems".to_string(), [160, 160, 160]));
+        }
+        for (idx, entry) in problems.iter().enumerate() {
+            let problem = &entry.problem;
+            let marker = if idx == self.selected { '>' } else { ' ' };
+            let count = if entry.count > 1 {
+                format!(" (x{})", entry.count)
+            } else {
+                String::new()
+            };
+            let color = if entry.acknowledged {
+                [160, 160, 160]
+            } else {
+                problem.severity.color()
+            };
+            lines.push((
+                format!("{marker} [{}] {}{count}", problem.severity, problem.message),
+                color,
+            ));
+            if idx == self.selected {
+                let source = problem.source.rsplit("::").next().unwrap_or(problem.source);
+                lines.push((format!("    from {source}"), [160, 160, 160]));
+                if let Some(hint) = &problem.hint {
+                    lines.push((format!("    hint: {hint}"), [160, 160, 160]));
+                }
+            }
+        }
+        // a trailing space, like the badge
+        for (line, _) in &mut lines {
+            line.push(' ');
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::keyboard::PressedKeys;
+
+    fn warning(key: &str) -> Problem {
+        Problem::new(Severity::Warning, "test", format!("{key} failed")).with_dedup_key(key)
+    }
+
+    #[test]
+    fn test_dedup_counting() {
+        let mut problems = Problems::new();
+        problems.report(warning("save"));
+        problems.report(warning("load"));
+        problems.report(warning("save").with_hint("check the disk space"));
+        assert_eq!(problems.len(), 2);
+        let save = problems.get("test", "save").unwrap();
+        assert_eq!(save.count, 2);
+        assert!(save.last_seen >= save.first_seen);
+        assert_eq!(save.problem.hint.as_deref(), Some("check the disk space"));
+
+        // the same key from another source is a different problem
+        problems.report(Problem::new(Severity::Info, "other", "x").with_dedup_key("save"));
+        assert_eq!(problems.len(), 3);
+    }
+
+    #[test]
+    fn test_acknowledge_and_badge() {
+        let mut problems = Problems::new();
+        problems.report(Problem::new(Severity::Info, "test", "no sound device"));
+        // infos do not show a badge
+        assert_eq!(problems.badge(), None);
+
+        problems.report(warning("save"));
+        problems.report(warning("load"));
+        assert_eq!(problems.badge(), Some((2, Severity::Warning)));
+
+        problems.acknowledge("test", "save");
+        assert_eq!(problems.badge(), Some((1, Severity::Warning)));
+        // a repeated report stays acknowledged
+        problems.report(warning("save"));
+        assert_eq!(problems.badge(), Some((1, Severity::Warning)));
+        // unless it got worse
+        problems.report(
+            Problem::new(Severity::Error, "test", "save failed again").with_dedup_key("save"),
+        );
+        assert_eq!(problems.badge(), Some((2, Severity::Error)));
+
+        problems.dismiss("test", "save");
+        problems.clear_source("test");
+        assert_eq!(problems.badge(), None);
+        assert!(problems.is_empty());
+    }
+
+    #[test]
+    fn test_panel_acknowledges_selected() {
+        let mut shared_state = SharedState::<()>::new(80, 24);
+        shared_state.problems.report(warning("save"));
+        shared_state.problems.report(warning("load"));
+        let mut panel = ProblemsPanelComponent::new();
+        let update_info = UpdateInfo::at(Instant::now());
+        for key in [
+            ProblemsPanelComponent::TOGGLE_KEY,
+            KeyCode::Down,
+            KeyCode::Enter,
+        ] {
+            shared_state.pressed_keys.insert(key);
+            panel.update(update_info, &mut shared_state);
+            shared_state.pressed_keys = PressedKeys::new();
+        }
+        assert!(panel.is_open());
+        assert!(
+            !shared_state
+                .problems
+                .get("test", "save")
+                .unwrap()
+                .acknowledged
+        );
+        assert!(
+            shared_state
+                .problems
+                .get("test", "load")
+                .unwrap()
+                .acknowledged
+        );
+        assert_eq!(shared_state.problems.badge(), Some((1, Severity::Warning)));
+    }
+}
@@ -1,26 +1,286 @@
-use crate::{BreakingAction, Component, SharedState};
-use crossterm::event::{Event, KeyCode, KeyEvent};
+use crate::components::debuginfo::DebugMessage;
+use crate::components::keyboard::normalize_key_event;
+use crate::components::settings::{Setting, Settings, format_key};
+use crate::{BreakingAction, Component, SetupInfo, SharedState};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use std::time::{Duration, Instant};
 
 /// A component that quits the game when the user presses 'q'.
-pub struct QuitterComponent;
+///
+/// The key can be changed with the `input.quit` setting, and its default with
+/// [`QuitterComponent::new_with_key`]. Games that use every plain key can quit on a key
+/// combination like Ctrl-Q instead, see [`QuitterComponent::new_with_combination`].
+///
+/// To swap the default quitter of [`Game::install_recommended_components`](crate::Game::install_recommended_components),
+/// remove it and add the configured one:
+///
+/// ```
+/// # use teng::{Game, components::quitter::QuitterComponent};
+/// # use crossterm::event::{KeyCode, KeyModifiers};
+/// # use std::time::Duration;
+/// # let mut game = Game::<Vec<u8>, ()>::new_headless(10, 10);
+/// game.shared_state_mut()
+///     .remove_components
+///     .insert(std::any::TypeId::of::<QuitterComponent>());
+/// game.add_component(Box::new(
+///     QuitterComponent::new_with_combination(KeyModifiers::CONTROL, KeyCode::Char('q'))
+///         .with_double_press(Duration::from_secs(2)),
+/// ));
+/// ```
+pub struct QuitterComponent {
+    trigger: Trigger,
+    double_press_timeout: Option<Duration>,
+    /// The time of the first press of a double press.
+    first_press: Option<Instant>,
+}
+
+/// What the quitter reacts to.
+enum Trigger {
+    /// The key of the `input.quit` setting, with the given default.
+    Setting(KeyCode),
+    /// Exactly this normalized key event.
+    Combination(KeyEvent),
+}
+
+impl QuitterComponent {
+    /// The key of the keybind setting for quitting.
+    pub const QUIT_SETTING: &'static str = "input.quit";
+
+    /// Creates a quitter that quits on 'q', or the key of the `input.quit` setting.
+    pub fn new() -> Self {
+        Self::new_with_key(KeyCode::Char('q'))
+    }
+
+    /// Creates a quitter whose `input.quit` setting defaults to `key`.
+    pub fn new_with_key(key: KeyCode) -> Self {
+        Self {
+            trigger: Trigger::Setting(key),
+            double_press_timeout: None,
+            first_press: None,
+        }
+    }
+
+    /// Creates a quitter that quits on `key` with exactly the `modifiers`, e.g. Ctrl-Q.
+    ///
+    /// The combination is compared after [normalization](crate::components::keyboard), so
+    /// `KeyCode::Char('Q')` means Shift-Q. Combinations cannot be rebound in the settings menu, so
+    /// the `input.quit` setting is not registered.
+    pub fn new_with_combination(modifiers: KeyModifiers, key: KeyCode) -> Self {
+        Self {
+            trigger: Trigger::Combination(normalize_key_event(KeyEvent::new(key, modifiers))),
+            double_press_timeout: None,
+            first_press: None,
+        }
+    }
+
+    /// Requires pressing the quit key twice within `timeout`. The first press shows a debug
+    /// message that asks for the second one.
+    pub fn with_double_press(mut self, timeout: Duration) -> Self {
+        self.double_press_timeout = Some(timeout);
+        self
+    }
+
+    fn is_quit_key<S: 'static>(&self, key: KeyEvent, shared_state: &SharedState<S>) -> bool {
+        match self.trigger {
+            Trigger::Setting(default) => match shared_state.ext::<Settings<S>>() {
+                Some(settings) if settings.setting(Self::QUIT_SETTING).is_some() => {
+                    settings.is_key(Self::QUIT_SETTING, key.code)
+                }
+                _ => match (default, key.code) {
+                    (KeyCode::Char(a), KeyCode::Char(b)) => a.eq_ignore_ascii_case(&b),
+                    (default, code) => default == code,
+                },
+            },
+            Trigger::Combination(combination) => {
+                key.code == combination.code && key.modifiers == combination.modifiers
+            }
+        }
+    }
+
+    /// Returns the quit key as shown to the user.
+    fn key_label<S: 'static>(&self, shared_state: &SharedState<S>) -> String {
+        match self.trigger {
+            Trigger::Setting(default) => format_key(
+                shared_state
+                    .ext::<Settings<S>>()
+                    .and_then(|settings| settings.get_key(Self::QUIT_SETTING))
+                    .unwrap_or(default),
+            ),
+            Trigger::Combination(KeyEvent {
+                code, modifiers, ..
+            }) => {
+                let mut label = String::new();
+                for (modifier, name) in [
+                    (KeyModifiers::CONTROL, "Ctrl-"),
+                    (KeyModifiers::ALT, "Alt-"),
+                    (KeyModifiers::SHIFT, "Shift-"),
+                ] {
+                    if modifiers.contains(modifier) {
+                        label.push_str(name);
+                    }
+                }
+                label.push_str(&format_key(code));
+                label
+            }
+        }
+    }
+}
+
+impl Default for QuitterComponent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: 'static> Component<S> for QuitterComponent {
+    fn ignores_filters(&self) -> bool {
+        true
+    }
+
+    fn setup(&mut self, _setup_info: &SetupInfo, shared_state: &mut SharedState<S>) {
+        if let Trigger::Setting(default) = self.trigger {
+            shared_state.ext_or_default::<Settings<S>>().register(
+                Setting::keybind(Self::QUIT_SETTING, default)
+                    .with_label("Quit")
+                    .with_category("Controls"),
+            );
+        }
+    }
 
-impl<S> Component<S> for QuitterComponent {
     fn on_event(
         &mut self,
         event: Event,
         shared_state: &mut SharedState<S>,
     ) -> Option<BreakingAction> {
-        if matches!(
-            event,
-            Event::Key(KeyEvent {
-                // TODO: Add breakingaction to update() and move this there and used shared_state?
-                code: KeyCode::Char('q' | 'Q'),
-                ..
-            })
-        ) {
-            Some(BreakingAction::Quit)
-        } else {
-            None
+        // TODO: Add breakingaction to update() and move this there and used shared_state?
+        let Event::Key(key) = event else {
+            return None;
+        };
+        if key.kind == KeyEventKind::Release || !self.is_quit_key(key, shared_state) {
+            return None;
         }
+        let Some(timeout) = self.double_press_timeout else {
+            return Some(BreakingAction::Quit);
+        };
+        let now = Instant::now();
+        match self.first_press.take() {
+            Some(first_press) if now.duration_since(first_press) <= timeout => {
+                Some(BreakingAction::Quit)
+            }
+            _ => {
+                self.first_press = Some(now);
+                let message = format!("Press {} again to quit", self.key_label(shared_state));
+                shared_state
+                    .debug_messages
+                    .push(DebugMessage::new(message, now + timeout));
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(
+        quitter: &mut QuitterComponent,
+        modifiers: KeyModifiers,
+        code: KeyCode,
+        shared_state: &mut SharedState,
+    ) -> bool {
+        let event = normalize_key_event(KeyEvent::new(code, modifiers));
+        matches!(
+            quitter.on_event(Event::Key(event), shared_state),
+            Some(BreakingAction::Quit)
+        )
+    }
+
+    #[test]
+    fn test_key_combination() {
+        let mut shared_state = SharedState::<()>::new(10, 5);
+        let mut quitter =
+            QuitterComponent::new_with_combination(KeyModifiers::CONTROL, KeyCode::Char('q'));
+        let none = KeyModifiers::NONE;
+        let ctrl = KeyModifiers::CONTROL;
+        assert!(!press(
+            &mut quitter,
+            none,
+            KeyCode::Char('q'),
+            &mut shared_state
+        ));
+        assert!(!press(
+            &mut quitter,
+            ctrl,
+            KeyCode::Char('c'),
+            &mut shared_state
+        ));
+        assert!(!press(
+            &mut quitter,
+            ctrl | KeyModifiers::ALT,
+            KeyCode::Char('q'),
+            &mut shared_state
+        ));
+        // Ctrl-Q as the control character of legacy terminals
+        assert!(press(
+            &mut quitter,
+            none,
+            KeyCode::Char('\u{11}'),
+            &mut shared_state
+        ));
+        assert!(press(
+            &mut quitter,
+            ctrl,
+            KeyCode::Char('q'),
+            &mut shared_state
+        ));
+
+        // releases do not quit
+        let release = KeyEvent::new_with_kind(KeyCode::Char('q'), ctrl, KeyEventKind::Release);
+        assert!(
+            quitter
+                .on_event(Event::Key(release), &mut shared_state)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_double_press() {
+        let mut shared_state = SharedState::<()>::new(10, 5);
+        let mut quitter =
+            QuitterComponent::new_with_combination(KeyModifiers::CONTROL, KeyCode::Char('q'))
+                .with_double_press(Duration::from_secs(60));
+        let ctrl = KeyModifiers::CONTROL;
+        assert!(!press(
+            &mut quitter,
+            ctrl,
+            KeyCode::Char('q'),
+            &mut shared_state
+        ));
+        assert_eq!(shared_state.debug_messages.len(), 1);
+        assert_eq!(quitter.key_label(&shared_state), "Ctrl-q");
+        assert!(press(
+            &mut quitter,
+            ctrl,
+            KeyCode::Char('q'),
+            &mut shared_state
+        ));
+
+        // the first press expires
+        let mut quitter =
+            QuitterComponent::new_with_key(KeyCode::Char('x')).with_double_press(Duration::ZERO);
+        assert!(!press(
+            &mut quitter,
+            KeyModifiers::NONE,
+            KeyCode::Char('x'),
+            &mut shared_state
+        ));
+        std::thread::sleep(Duration::from_millis(1));
+        assert!(!press(
+            &mut quitter,
+            KeyModifiers::NONE,
+            KeyCode::Char('x'),
+            &mut shared_state
+        ));
     }
 }
@@ -0,0 +1,496 @@
+//! A save slot selection screen with previews.
+//!
+//! Any component can open the screen through [`SharedState::save_slots`], and takes the chosen
+//! action once the player picked a slot:
+//! ```rust
+//! use teng::SharedState;
+//! use teng::components::saveslots::{SaveSlotsMode, SlotAction};
+//! use crossterm::event::KeyCode;
+//!
+//! fn update(shared_state: &mut SharedState) {
+//!     if shared_state.pressed_keys.did_press(KeyCode::F(5)) {
+//!         shared_state.save_slots.open(SaveSlotsMode::Save);
+//!     }
+//!     match shared_state.save_slots.take_result() {
+//!         Some(SlotAction::Save(slot)) => { /* SaveSlots::save(slot, ...) */ }
+//!         Some(SlotAction::Load(slot)) => { /* SaveSlots::load(slot) */ }
+//!         None => {}
+//!     }
+//! }
+//! ```
+//! The [`SaveSlotsComponent`] lists the slots of its [`SaveSlots`] directory with a preview of
+//! the selected slot's [`SlotMetadata`]. Saving and loading are left to the game, which knows
+//! its data, and which writes the metadata with every [`SaveSlots::save`]. Deleting is done by
+//! the screen itself.
+//!
+//! While the screen is open, all other components except the input recorder are paused. Keys:
+//! * Up/Down: select a slot
+//! * Enter: load the slot, or save to it. Overwriting a slot asks for confirmation.
+//! * Delete or d: delete the slot, after confirmation
+//! * y/Enter and n/Esc: confirm or cancel
+//! * Esc: close without a result
+//!
+//! Corrupted slots are listed as such, and can be deleted or overwritten, but not loaded.
+
+use crate::components::dim::DimBehindComponent;
+use crate::components::keyboard::KeyPressRecorderComponent;
+use crate::rendering::render::Render;
+use crate::rendering::renderer::Renderer;
+use crate::util::saveslots::{SaveSlots, SlotEntry, SlotMetadata};
+use crate::{Component, ComponentFilter, SetupInfo, SharedState, UpdateInfo};
+use crossterm::event::KeyCode;
+use std::any::TypeId;
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// What the screen was opened for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveSlotsMode {
+    Load,
+    /// Also offers a new slot.
+    Save,
+}
+
+/// The action chosen on the screen, see [`SaveSlotsMenu::take_result`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotAction {
+    /// Load the slot.
+    Load(u32),
+    /// Save to the slot. Overwriting was confirmed already.
+    Save(u32),
+}
+
+/// Opens the save slot screen and returns its result, accessible via
+/// [`SharedState::save_slots`].
+#[derive(Debug, Default)]
+pub struct SaveSlotsMenu {
+    mode: Option<SaveSlotsMode>,
+    result: Option<SlotAction>,
+}
+
+impl SaveSlotsMenu {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens the screen in the next frame. Clears a result that has not been taken yet.
+    pub fn open(&mut self, mode: SaveSlotsMode) {
+        self.mode = Some(mode);
+        self.result = None;
+    }
+
+    /// Closes the screen without a result.
+    pub fn close(&mut self) {
+        self.mode = None;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.mode.is_some()
+    }
+
+    /// Returns the mode the screen is open in, if it is.
+    pub fn mode(&self) -> Option<SaveSlotsMode> {
+        self.mode
+    }
+
+    /// Returns and clears the chosen action, if the screen was closed by choosing one.
+    pub fn take_result(&mut self) -> Option<SlotAction> {
+        self.result.take()
+    }
+}
+
+/// A pending yes/no question.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Confirm {
+    Overwrite(u32),
+    Delete(u32),
+}
+
+struct Screen {
+    mode: SaveSlotsMode,
+    entries: Vec<SlotEntry>,
+    /// Index into the rows, where the row after the entries is the new slot in save mode.
+    selected: usize,
+    confirm: Option<Confirm>,
+    error: Option<String>,
+}
+
+impl Screen {
+    fn rows(&self) -> usize {
+        self.entries.len() + usize::from(self.mode == SaveSlotsMode::Save)
+    }
+
+    fn selected_entry(&self) -> Option<&SlotEntry> {
+        self.entries.get(self.selected)
+    }
+}
+
+/// A component that shows the save slot screen.
+pub struct SaveSlotsComponent {
+    slots: SaveSlots,
+    screen: Option<Screen>,
+}
+
+impl SaveSlotsComponent {
+    /// The size of the thumbnail preview in cells.
+    const PREVIEW_SIZE: (usize, usize) = (32, 8);
+
+    /// Creates a screen for the slots in the directory `dir`, creating it if necessary.
+    pub fn new(dir: impl AsRef<Path>) -> std::io::Result<Self> {
+        Ok(Self {
+            slots: SaveSlots::open(dir)?,
+            screen: None,
+        })
+    }
+
+    pub fn slots(&self) -> &SaveSlots {
+        &self.slots
+    }
+
+    fn open<S: 'static>(&mut self, mode: SaveSlotsMode, shared_state: &mut SharedState<S>) {
+        let whitelist = HashSet::from([
+            TypeId::of::<Self>(),
+            TypeId::of::<KeyPressRecorderComponent>(),
+            TypeId::of::<DimBehindComponent>(),
+        ]);
+        shared_state.push_component_filter(ComponentFilter::Whitelist(whitelist));
+        let mut screen = Screen {
+            mode,
+            entries: vec![],
+            selected: 0,
+            confirm: None,
+            error: None,
+        };
+        self.refresh(&mut screen);
+        self.screen = Some(screen);
+    }
+
+    fn close<S>(&mut self, shared_state: &mut SharedState<S>) {
+        if self.screen.take().is_some() {
+            shared_state.pop_component_filter();
+        }
+        shared_state.save_slots.mode = None;
+    }
+
+    fn refresh(&self, screen: &mut Screen) {
+        match self.slots.list() {
+            Ok(entries) => screen.entries = entries,
+            Err(e) => screen.error = Some(format!("Failed to list the saves: {e}")),
+        }
+        screen.selected = screen.selected.min(screen.rows().saturating_sub(1));
+    }
+
+    fn choose<S>(&mut self, action: SlotAction, shared_state: &mut SharedState<S>) {
+        self.close(shared_state);
+        shared_state.save_slots.result = Some(action);
+    }
+
+    fn update_screen<S>(&mut self, shared_state: &mut SharedState<S>) {
+        let keys = &shared_state.pressed_keys;
+        let (up, down, enter, delete, yes, no) = (
+            keys.did_press(KeyCode::Up),
+            keys.did_press(KeyCode::Down),
+            keys.did_press(KeyCode::Enter),
+            keys.did_press(KeyCode::Delete) || keys.did_press_char_ignore_case('d'),
+            keys.did_press_char_ignore_case('y') || keys.did_press(KeyCode::Enter),
+            keys.did_press_char_ignore_case('n') || keys.did_press(KeyCode::Esc),
+        );
+        let close = keys.did_press(KeyCode::Esc);
+        let screen = self.screen.as_mut().unwrap();
+
+        if let Some(confirm) = screen.confirm {
+            if yes {
+                screen.confirm = None;
+                match confirm {
+                    Confirm::Overwrite(slot) => self.choose(SlotAction::Save(slot), shared_state),
+                    Confirm::Delete(slot) => self.delete(slot),
+                }
+            } else if no {
+                screen.confirm = None;
+            }
+            return;
+        }
+        if close {
+            self.close(shared_state);
+            return;
+        }
+
+        let rows = screen.rows();
+        if rows > 0 {
+            if up {
+                screen.selected = (screen.selected + rows - 1) % rows;
+            }
+            if down {
+                screen.selected = (screen.selected + 1) % rows;
+            }
+        }
+        let selected = screen.selected_entry().cloned();
+        if delete && let Some(entry) = &selected {
+            screen.confirm = Some(Confirm::Delete(entry.slot));
+        } else if enter {
+            match (screen.mode, selected) {
+                (SaveSlotsMode::Load, Some(entry)) if !entry.is_corrupted() => {
+                    self.choose(SlotAction::Load(entry.slot), shared_state);
+                }
+                (SaveSlotsMode::Load, _) => {}
+                (SaveSlotsMode::Save, Some(entry)) => {
+                    screen.confirm = Some(Confirm::Overwrite(entry.slot));
+                }
+                (SaveSlotsMode::Save, None) => match self.slots.next_free_slot() {
+                    Ok(slot) => self.choose(SlotAction::Save(slot), shared_state),
+                    Err(e) => screen.error = Some(format!("Failed to find a free slot: {e}")),
+                },
+            }
+        }
+    }
+
+    fn delete(&mut self, slot: u32) {
+        let Some(mut screen) = self.screen.take() else {
+            return;
+        };
+        if let Err(e) = self.slots.delete(slot) {
+            screen.error = Some(format!("Failed to delete slot {slot}: {e}"));
+        }
+        self.refresh(&mut screen);
+        self.screen = Some(screen);
+    }
+
+    fn render_preview(
+        renderer: &mut dyn Renderer,
+        metadata: &SlotMetadata,
+        x: usize,
+        y: usize,
+        depth: i32,
+    ) {
+        let (width, height) = Self::PREVIEW_SIZE;
+        if let Some(thumbnail) = &metadata.thumbnail {
+            // two pixels per cell
+            for cy in 0..height.min(thumbnail.height.div_ceil(2)) {
+                for cx in 0..width.min(thumbnail.width) {
+                    let top = thumbnail.get(cx, 2 * cy);
+                    let bottom = if 2 * cy + 1 < thumbnail.height {
+                        thumbnail.get(cx, 2 * cy + 1)
+                    } else {
+                        [30, 30, 30]
+                    };
+                    "▀".with_color(top).with_bg_color(bottom).render(
+                        renderer,
+                        x + cx,
+                        y + cy,
+                        depth,
+                    );
+                }
+            }
+        }
+        let lines = [
+            metadata.summary.clone(),
+            format!("Play time: {}", format_play_time(metadata.play_time)),
+            format!("Saved {}", format_age(metadata.saved_at)),
+        ];
+        for (dy, line) in lines.iter().enumerate() {
+            format!("{line:<width$}")
+                .with_bg_color([30, 30, 30])
+                .render(renderer, x, y + height + 1 + dy, depth);
+        }
+    }
+}
+
+/// Formats a play time as `h:mm:ss`.
+fn format_play_time(play_time: Duration) -> String {
+    let secs = play_time.as_secs();
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// Formats how long ago `time` was, e.g. "5 min ago".
+fn format_age(time: SystemTime) -> String {
+    let Ok(age) = SystemTime::now().duration_since(time) else {
+        return "just now".to_string();
+    };
+    match age.as_secs() {
+        0..60 => "just now".to_string(),
+        secs @ 60..3600 => format!("{} min ago", secs / 60),
+        secs @ 3600..86400 => format!("{} h ago", secs / 3600),
+        secs => format!("{} days ago", secs / 86400),
+    }
+}
+
+impl<S: 'static> Component<S> for SaveSlotsComponent {
+    fn runs_while_paused(&self) -> bool {
+        true
+    }
+
+    fn setup(&mut self, _setup_info: &SetupInfo, shared_state: &mut SharedState<S>) {
+        // opened before the game started
+        if let Some(mode) = shared_state.save_slots.mode {
+            self.open(mode, shared_state);
+        }
+    }
+
+    fn update(&mut self, _update_info: UpdateInfo, shared_state: &mut SharedState<S>) {
+        match (shared_state.save_slots.mode, &self.screen) {
+            (Some(mode), None) => self.open(mode, shared_state),
+            (Some(mode), Some(screen)) if mode != screen.mode => {
+                self.close(shared_state);
+                self.open(mode, shared_state);
+                shared_state.save_slots.mode = Some(mode);
+            }
+            (Some(_), Some(_)) => self.update_screen(shared_state),
+            (None, Some(_)) => self.close(shared_state),
+            (None, None) => {}
+        }
+    }
+
+    fn render(
+        &self,
+        renderer: &mut dyn Renderer,
+        _shared_state: &SharedState<S>,
+        _depth_base: i32,
+    ) {
+        let Some(screen) = &self.screen else {
+            return;
+        };
+        let depth = i32::MAX - 90;
+        let title = match screen.mode {
+            SaveSlotsMode::Load => "Load game",
+            SaveSlotsMode::Save => "Save game",
+        };
+        let mut lines = vec![(title.to_string(), false), (String::new(), false)];
+        if screen.rows() == 0 {
+            lines.push(("No saves".to_string(), false));
+        }
+        for (idx, entry) in screen.entries.iter().enumerate() {
+            let label = match &entry.metadata {
+                Some(metadata) if metadata.cheated => format!("{} (cheated)", metadata.summary),
+                Some(metadata) => metadata.summary.clone(),
+                None => "corrupted".to_string(),
+            };
+            lines.push((
+                format!("Slot {}: {label}", entry.slot),
+                idx == screen.selected,
+            ));
+        }
+        if screen.mode == SaveSlotsMode::Save {
+            lines.push((
+                "New slot".to_string(),
+                screen.selected == screen.entries.len(),
+            ));
+        }
+        lines.push((String::new(), false));
+        match screen.confirm {
+            Some(Confirm::Overwrite(slot)) => {
+                lines.push((format!("Overwrite slot {slot}? (y/n)"), false));
+            }
+            Some(Confirm::Delete(slot)) => {
+                lines.push((format!("Delete slot {slot}? (y/n)"), false));
+            }
+            None => {
+                lines.push((
+                    "Up/Down: select, Enter: choose, d: delete, Esc: close".to_string(),
+                    false,
+                ));
+            }
+        }
+        if let Some(error) = &screen.error {
+            lines.push((error.clone(), false));
+        }
+
+        let width = lines
+            .iter()
+            .map(|(l, _)| l.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max(30)
+            + 2;
+        for (y, (line, selected)) in lines.iter().enumerate() {
+            let cursor = if *selected { '>' } else { ' ' };
+            let bg_color = if *selected {
+                [60, 60, 90]
+            } else {
+                [30, 30, 30]
+            };
+            format!("{cursor}{line:<width$}", width = width - 1)
+                .with_bg_color(bg_color)
+                .render(renderer, 1, y + 1, depth);
+        }
+        if let Some(metadata) = screen.selected_entry().and_then(|e| e.metadata.as_ref()) {
+            Self::render_preview(renderer, metadata, width + 3, 1, depth);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Game;
+    use crossterm::event::{Event, KeyEvent, KeyModifiers};
+
+    /// Runs a frame, in which `key` was pressed if any.
+    fn frame_with_key(game: &mut Game<Vec<u8>, ()>, key: Option<KeyCode>) {
+        if let Some(key) = key {
+            game.push_event(Event::Key(KeyEvent::new(key, KeyModifiers::NONE)));
+        }
+        game.run_frames(1).unwrap();
+    }
+
+    #[test]
+    fn test_overwrite_and_delete_confirmation() {
+        let dir = std::env::temp_dir().join(format!(
+            "teng-saveslots-component-test-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        let component = SaveSlotsComponent::new(&dir).unwrap();
+        let slots = component.slots().clone();
+        let metadata = SlotMetadata::new(Duration::from_secs(5), "first");
+        slots.save(0, &metadata, &1u8).unwrap();
+        std::fs::write(slots.slot_path(1), b"garbage").unwrap();
+        let mut game = Game::<Vec<u8>, ()>::new_headless(80, 24);
+        game.add_component(Box::new(KeyPressRecorderComponent::new()));
+        game.add_component(Box::new(component));
+
+        game.shared_state_mut().save_slots.open(SaveSlotsMode::Save);
+        frame_with_key(&mut game, None);
+        assert!(game.shared_state().component_filter().is_some());
+
+        // overwriting asks first, and 'n' cancels
+        frame_with_key(&mut game, Some(KeyCode::Enter));
+        frame_with_key(&mut game, Some(KeyCode::Char('n')));
+        assert!(game.shared_state().save_slots.is_open());
+        assert_eq!(game.shared_state_mut().save_slots.take_result(), None);
+        frame_with_key(&mut game, Some(KeyCode::Enter));
+        frame_with_key(&mut game, Some(KeyCode::Char('y')));
+        assert!(!game.shared_state().save_slots.is_open());
+        assert!(game.shared_state().component_filter().is_none());
+        assert_eq!(
+            game.shared_state_mut().save_slots.take_result(),
+            Some(SlotAction::Save(0))
+        );
+
+        // the corrupted slot cannot be loaded, but deleted
+        game.shared_state_mut().save_slots.open(SaveSlotsMode::Load);
+        frame_with_key(&mut game, None);
+        frame_with_key(&mut game, Some(KeyCode::Down));
+        frame_with_key(&mut game, Some(KeyCode::Enter));
+        assert!(game.shared_state().save_slots.is_open());
+        frame_with_key(&mut game, Some(KeyCode::Char('d')));
+        frame_with_key(&mut game, Some(KeyCode::Char('y')));
+        assert_eq!(slots.list().unwrap().len(), 1);
+        frame_with_key(&mut game, Some(KeyCode::Enter));
+        assert_eq!(
+            game.shared_state_mut().save_slots.take_result(),
+            Some(SlotAction::Load(0))
+        );
+
+        // a new slot is saved without asking
+        game.shared_state_mut().save_slots.open(SaveSlotsMode::Save);
+        frame_with_key(&mut game, None);
+        frame_with_key(&mut game, Some(KeyCode::Down));
+        frame_with_key(&mut game, Some(KeyCode::Enter));
+        assert_eq!(
+            game.shared_state_mut().save_slots.take_result(),
+            Some(SlotAction::Save(1))
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,356 @@
+//! Scenes: named bundles of components that are switched as a whole, e.g. a title screen, the
+//! game itself, and a pause menu on top of it.
+//!
+//! Scenes are registered with [`SharedState::scenes`] and switched from any component:
+//! ```rust
+//! use teng::SharedState;
+//! use teng::components::scene::Scene;
+//! # use teng::components::Component;
+//! # struct TitleScreen;
+//! # impl Component for TitleScreen {}
+//! # struct World;
+//! # impl Component for World {}
+//!
+//! fn setup(shared_state: &mut SharedState) {
+//!     shared_state.scenes.register(Scene::new("title").with(Box::new(TitleScreen)));
+//!     shared_state.scenes.register(Scene::new("game").with(Box::new(World)));
+//!     shared_state.scenes.switch_to("title");
+//! }
+//!
+//! fn start_game(shared_state: &mut SharedState) {
+//!     shared_state.scenes.switch_to("game");
+//! }
+//! ```
+//! The scenes form a stack. The scene on top runs normally. Scenes below it are frozen: they are
+//! still rendered, e.g. behind a pause menu that was [pushed](SceneManager::push), but neither
+//! receive events nor update. Scenes that are not on the stack are neither updated nor rendered.
+//! Components that do not belong to any scene are not affected.
+//!
+//! The components of a scene are added to the game when the scene is entered for the first
+//! time, so that their `setup` runs then, and stay in the game afterwards. Components that opt
+//! in are notified with [`Component::on_scene_enter`] and [`Component::on_scene_exit`] when their
+//! scene enters or leaves the stack.
+//!
+//! Transitions are applied at the end of the frame, after all components were updated, so that
+//! no component disappears halfway through a frame. Several transitions in the same frame are
+//! applied in order. See `examples/scenes.rs` for a full example.
+
+use crate::components::Component;
+use crate::components::debuginfo::DebugMessage;
+use crate::{ComponentId, SharedState};
+use std::collections::HashSet;
+
+/// A named bundle of components.
+pub struct Scene<S> {
+    name: String,
+    /// The components until the scene is entered for the first time.
+    components: Vec<Box<dyn Component<S>>>,
+    /// The ids of the components once they were added to the game.
+    ids: Vec<ComponentId>,
+}
+
+impl<S> Scene<S> {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            components: vec![],
+            ids: vec![],
+        }
+    }
+
+    /// Adds a component to the scene. Components are added to the game in this order.
+    pub fn with(mut self, component: Box<dyn Component<S>>) -> Self {
+        self.components.push(component);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+enum Transition {
+    SwitchTo(String),
+    Push(String),
+    Pop,
+}
+
+/// The components whose scene left or entered the stack in a frame.
+pub(crate) struct SceneChanges {
+    /// Top scene first.
+    pub(crate) exited: Vec<ComponentId>,
+    /// Bottom scene first, in the order the components were added.
+    pub(crate) entered: Vec<ComponentId>,
+}
+
+/// The registered scenes and the scene stack, accessible via [`SharedState::scenes`].
+pub struct SceneManager<S> {
+    scenes: Vec<Scene<S>>,
+    /// Indices into `scenes`, bottom first.
+    stack: Vec<usize>,
+    pending: Vec<Transition>,
+    hidden: HashSet<ComponentId>,
+    frozen: HashSet<ComponentId>,
+}
+
+impl<S> SceneManager<S> {
+    pub fn new() -> Self {
+        Self {
+            scenes: vec![],
+            stack: vec![],
+            pending: vec![],
+            hidden: HashSet::new(),
+            frozen: HashSet::new(),
+        }
+    }
+
+    /// Registers a scene. Its components are added to the game once it is entered.
+    ///
+    /// Panics if a scene with the same name is already registered.
+    pub fn register(&mut self, scene: Scene<S>) {
+        assert!(
+            self.index_of(&scene.name).is_none(),
+            "scene {} is already registered",
+            scene.name
+        );
+        self.scenes.push(scene);
+    }
+
+    /// Replaces the whole stack with the given scene at the end of the frame.
+    pub fn switch_to(&mut self, name: impl Into<String>) {
+        self.pending.push(Transition::SwitchTo(name.into()));
+    }
+
+    /// Puts the given scene on top of the stack at the end of the frame, freezing the scene
+    /// below it.
+    pub fn push(&mut self, name: impl Into<String>) {
+        self.pending.push(Transition::Push(name.into()));
+    }
+
+    /// Removes the scene on top of the stack at the end of the frame, resuming the scene below
+    /// it.
+    pub fn pop(&mut self) {
+        self.pending.push(Transition::Pop);
+    }
+
+    /// Returns the name of the scene on top of the stack.
+    pub fn current(&self) -> Option<&str> {
+        self.stack.last().map(|&idx| self.scenes[idx].name())
+    }
+
+    /// Returns the names of the scenes on the stack, bottom first.
+    pub fn stack(&self) -> impl Iterator<Item = &str> {
+        self.stack.iter().map(|&idx| self.scenes[idx].name())
+    }
+
+    /// Returns true if transitions are waiting for the end of the frame.
+    pub fn has_pending_transitions(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Returns true if the component receives events and updates, i.e., it is not part of a
+    /// scene or its scene is on top of the stack.
+    pub(crate) fn runs(&self, id: ComponentId) -> bool {
+        !self.hidden.contains(&id) && !self.frozen.contains(&id)
+    }
+
+    /// Returns true if the component is rendered, i.e., it is not part of a scene or its scene
+    /// is on the stack.
+    pub(crate) fn renders(&self, id: ComponentId) -> bool {
+        !self.hidden.contains(&id)
+    }
+
+    fn index_of(&self, name: &str) -> Option<usize> {
+        self.scenes.iter().position(|scene| scene.name == name)
+    }
+
+    /// Applies the pending transitions, adding the components of scenes that are entered for
+    /// the first time with [`SharedState::add_component`].
+    ///
+    /// Must be called while the manager is taken out of `shared_state`.
+    pub(crate) fn apply_transitions(&mut self, shared_state: &mut SharedState<S>) -> SceneChanges {
+        let old_stack = self.stack.clone();
+        for transition in std::mem::take(&mut self.pending) {
+            let name = match &transition {
+                Transition::Pop => {
+                    self.stack.pop();
+                    continue;
+                }
+                Transition::SwitchTo(name) | Transition::Push(name) => name,
+            };
+            let Some(idx) = self.index_of(name) else {
+                shared_state
+                    .debug_messages
+                    .push(DebugMessage::warn_5s(format!("Unknown scene {name}")));
+                continue;
+            };
+            if matches!(transition, Transition::SwitchTo(_)) {
+                self.stack.clear();
+            } else if self.stack.contains(&idx) {
+                shared_state
+                    .debug_messages
+                    .push(DebugMessage::warn_5s(format!(
+                        "Scene {name} is already on the stack"
+                    )));
+                continue;
+            }
+            let scene = &mut self.scenes[idx];
+            for component in scene.components.drain(..) {
+                scene.ids.push(shared_state.add_component(component));
+            }
+            self.stack.push(idx);
+        }
+
+        let ids_of = |stack: &mut dyn Iterator<Item = &usize>, other: &[usize]| {
+            stack
+                .filter(|idx| !other.contains(idx))
+                .flat_map(|&idx| self.scenes[idx].ids.iter().copied())
+                .collect()
+        };
+        let changes = SceneChanges {
+            exited: ids_of(&mut old_stack.iter().rev(), &self.stack),
+            entered: ids_of(&mut self.stack.iter(), &old_stack),
+        };
+
+        self.hidden.clear();
+        self.frozen.clear();
+        for (idx, scene) in self.scenes.iter().enumerate() {
+            let ids = scene.ids.iter().copied();
+            match self.stack.iter().position(|&i| i == idx) {
+                None => self.hidden.extend(ids),
+                Some(pos) if pos + 1 < self.stack.len() => self.frozen.extend(ids),
+                Some(_) => {}
+            }
+        }
+        changes
+    }
+}
+
+impl<S> Default for SceneManager<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rendering::pixel::Pixel;
+    use crate::rendering::renderer::Renderer;
+    use crate::{Game, SetupInfo, UpdateInfo};
+
+    #[derive(Default)]
+    struct Log(Vec<String>);
+
+    fn log(shared_state: &mut SharedState, entry: String) {
+        shared_state
+            .extensions
+            .get_mut::<Log>()
+            .unwrap()
+            .0
+            .push(entry);
+    }
+
+    /// Logs its hooks, and renders its glyph at its column.
+    struct Tester {
+        glyph: char,
+        x: usize,
+        updates: usize,
+    }
+
+    impl Tester {
+        fn boxed(glyph: char, x: usize) -> Box<dyn Component> {
+            Box::new(Self {
+                glyph,
+                x,
+                updates: 0,
+            })
+        }
+    }
+
+    impl Component for Tester {
+        fn setup(&mut self, _setup_info: &SetupInfo, shared_state: &mut SharedState) {
+            log(shared_state, format!("{} setup", self.glyph));
+        }
+
+        fn on_scene_enter(&mut self, shared_state: &mut SharedState) {
+            log(shared_state, format!("{} enter", self.glyph));
+        }
+
+        fn on_scene_exit(&mut self, shared_state: &mut SharedState) {
+            log(shared_state, format!("{} exit", self.glyph));
+        }
+
+        fn update(&mut self, _update_info: UpdateInfo, _shared_state: &mut SharedState) {
+            self.updates += 1;
+        }
+
+        fn render(
+            &self,
+            renderer: &mut dyn Renderer,
+            _shared_state: &SharedState,
+            depth_base: i32,
+        ) {
+            let c = char::from_digit(self.updates as u32 % 10, 10).unwrap();
+            renderer.render_pixel(self.x, 0, Pixel::new(self.glyph), depth_base);
+            renderer.render_pixel(self.x, 1, Pixel::new(c), depth_base);
+        }
+    }
+
+    fn take_log(game: &mut Game<Vec<u8>, ()>) -> Vec<String> {
+        std::mem::take(&mut game.shared_state_mut().ext_mut::<Log>().unwrap().0)
+    }
+
+    fn text(game: &Game<Vec<u8>, ()>) -> String {
+        let frame = game.frame();
+        let mut out = String::new();
+        for y in 0..frame.height() {
+            out.extend((0..frame.width()).map(|x| frame.pixel_at(x, y).c));
+            out.push('\n');
+        }
+        out
+    }
+
+    #[test]
+    fn test_scene_transitions() {
+        let mut game = Game::<Vec<u8>, ()>::new_headless(3, 2);
+        let shared_state = game.shared_state_mut();
+        shared_state.extensions.insert(Log::default());
+        shared_state
+            .scenes
+            .register(Scene::new("title").with(Tester::boxed('t', 0)));
+        shared_state
+            .scenes
+            .register(Scene::new("game").with(Tester::boxed('g', 1)));
+        shared_state
+            .scenes
+            .register(Scene::new("pause").with(Tester::boxed('p', 2)));
+        shared_state.scenes.switch_to("title");
+        // not applied before the end of the frame
+        assert_eq!(shared_state.scenes.current(), None);
+        game.run_frames(2).unwrap();
+        assert_eq!(take_log(&mut game), vec!["t setup", "t enter"]);
+        assert_eq!(text(&game), "t  \n1  \n");
+
+        game.shared_state_mut().scenes.switch_to("game");
+        game.run_frames(2).unwrap();
+        assert_eq!(take_log(&mut game), vec!["t exit", "g setup", "g enter"]);
+        assert_eq!(text(&game), " g \n 1 \n");
+
+        // the game is frozen, but still rendered below the pause scene
+        game.shared_state_mut().scenes.push("pause");
+        game.run_frames(3).unwrap();
+        assert_eq!(take_log(&mut game), vec!["p setup", "p enter"]);
+        assert_eq!(text(&game), " gp\n 22\n");
+        let scenes = &game.shared_state_mut().scenes;
+        assert_eq!(scenes.stack().collect::<Vec<_>>(), vec!["game", "pause"]);
+
+        // a scene is set up only once
+        game.shared_state_mut().scenes.pop();
+        game.shared_state_mut().scenes.switch_to("title");
+        game.run_frames(1).unwrap();
+        assert_eq!(take_log(&mut game), vec!["p exit", "g exit", "t enter"]);
+        assert_eq!(text(&game), "t  \n2  \n");
+        assert_eq!(game.shared_state_mut().scenes.current(), Some("title"));
+    }
+}
@@ -0,0 +1,145 @@
+//! Saves the screen as text files.
+//!
+//! The [`ScreenshotComponent`] requests a [`FrameSnapshot`] when its key is pressed, F10 by
+//! default, and writes it to two timestamped files in its directory: a `.txt` file with the
+//! characters only, and an `.ans` file with the colors as ANSI escape sequences, which
+//! `cat screenshot-....ans` shows as it was on the screen. A debug message shows the path.
+//!
+//! The snapshot is taken after the frame in which the key was pressed is rendered, see
+//! [`FrameCapture`](crate::rendering::capture::FrameCapture), and written in the next update. If
+//! a capture is already pending, e.g. requested by another component, the same snapshot is used.
+
+use crate::components::Dependency;
+use crate::components::debuginfo::DebugMessage;
+use crate::components::keyboard::KeyPressRecorderComponent;
+use crate::rendering::capture::FrameSnapshot;
+use crate::{Component, SharedState, UpdateInfo};
+use crossterm::event::KeyCode;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// A component that saves screenshots as text.
+pub struct ScreenshotComponent {
+    key: KeyCode,
+    directory: PathBuf,
+    /// Whether this component requested the pending capture.
+    requested: bool,
+}
+
+impl ScreenshotComponent {
+    /// Creates a component that saves screenshots to `screenshots/` when F10 is pressed.
+    pub fn new() -> Self {
+        Self {
+            key: KeyCode::F(10),
+            directory: PathBuf::from("screenshots"),
+            requested: false,
+        }
+    }
+
+    /// Sets the key that takes a screenshot.
+    pub fn with_key(mut self, key: KeyCode) -> Self {
+        self.key = key;
+        self
+    }
+
+    /// Sets the directory the screenshots are saved to. It is created if it does not exist.
+    pub fn with_directory(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.directory = directory.into();
+        self
+    }
+
+    /// Writes `snapshot` to `<directory>/<name>.txt` and `<directory>/<name>.ans`, and returns the
+    /// path without an extension.
+    pub fn save(directory: &Path, name: &str, snapshot: &FrameSnapshot) -> io::Result<PathBuf> {
+        std::fs::create_dir_all(directory)?;
+        let path = directory.join(name);
+        std::fs::write(path.with_extension("txt"), snapshot.to_plain_text())?;
+        std::fs::write(path.with_extension("ans"), snapshot.to_ansi_text())?;
+        Ok(path)
+    }
+
+    fn file_name() -> String {
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+        format!("screenshot-{timestamp}")
+    }
+}
+
+impl Default for ScreenshotComponent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Component<S> for ScreenshotComponent {
+    fn runs_while_paused(&self) -> bool {
+        true
+    }
+
+    fn required_components(&self) -> Vec<Dependency> {
+        vec![Dependency::of::<KeyPressRecorderComponent>()]
+    }
+
+    fn update(&mut self, _update_info: UpdateInfo, shared_state: &mut SharedState<S>) {
+        if self.requested
+            && let Some(snapshot) = shared_state.frame_capture.take()
+        {
+            self.requested = false;
+            let message = match Self::save(&self.directory, &Self::file_name(), &snapshot) {
+                Ok(path) => {
+                    DebugMessage::new_3s(format!("Saved screenshot to {}.txt/.ans", path.display()))
+                }
+                Err(e) => DebugMessage::error_5s(format!("Failed to save screenshot: {e}")),
+            };
+            shared_state.debug_messages.push(message);
+        }
+        if shared_state.pressed_keys.did_press(self.key) {
+            shared_state.frame_capture.request();
+            self.requested = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Game;
+    use crate::components::fncomponent::FnComponent;
+    use crate::rendering::pixel::Pixel;
+    use crossterm::event::{Event, KeyEvent};
+
+    #[test]
+    fn test_screenshot_files() {
+        let dir = std::env::temp_dir().join(format!(
+            "teng-screenshot-component-test-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut game = Game::<Vec<u8>, ()>::new_headless(3, 1);
+        game.add_component(Box::new(KeyPressRecorderComponent::new()));
+        game.add_component(Box::new(ScreenshotComponent::new().with_directory(&dir)));
+        game.add_component(Box::new(FnComponent::new().with_render(
+            |renderer, _, _| {
+                renderer.render_pixel(0, 0, Pixel::new('a'), 0);
+            },
+        )));
+        game.push_event(Event::Key(KeyEvent::from(KeyCode::F(10))));
+        game.run_frames(2).unwrap();
+
+        let files = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        assert_eq!(files.len(), 2);
+        let txt = files
+            .iter()
+            .find(|p| p.extension().unwrap() == "txt")
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(txt).unwrap(), "a  \n");
+        assert_eq!(game.shared_state_mut().debug_messages.len(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
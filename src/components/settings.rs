@@ -0,0 +1,1068 @@
+//! Persistent user settings with a generated settings menu.
+//!
+//! Features declare their settings in the [`Settings`] extension, usually in their component's
+//! `setup`:
+//! ```rust
+//! use teng::SharedState;
+//! use teng::components::settings::{Setting, Settings};
+//!
+//! fn setup(shared_state: &mut SharedState) {
+//!     shared_state.ext_or_default::<Settings>().register(
+//!         Setting::range("audio.volume", 0.8, 0.0, 1.0, 0.1)
+//!             .with_label("Volume")
+//!             .with_category("Audio")
+//!             .with_apply(|value, _shared_state| {
+//!                 let volume = value.as_f64().unwrap();
+//!                 // update the mixer...
+//!             }),
+//!     );
+//! }
+//! ```
+//! The [`SettingsComponent`] loads the settings file when it is created, i.e., before any
+//! component's `setup`. Loaded values take effect as soon as their setting is registered, and
+//! the apply closures run once all components are set up, before the first frame's updates.
+//! Apply closures also run once with the initial value of a setting.
+//!
+//! Press F2 to open the settings menu. While it is open, the game is paused.
+//! * Up/Down: select a setting
+//! * Left/Right: change the selected setting
+//! * Enter: toggle, or press a key to assign to a keybind
+//! * Backspace: reset the selected setting to its default
+//! * r: revert all changes since the menu was opened
+//! * Esc or F2: close the menu and save
+//!
+//! Changes are applied immediately. The file is written atomically when the menu is closed and
+//! when the game quits. Unknown keys in the file, e.g. from a newer version of the game, are
+//! preserved.
+
+use crate::components::dim::DimBehindComponent;
+use crate::components::keyboard::KeyPressRecorderComponent;
+use crate::components::problems::{Problem, Severity};
+use crate::rendering::render::Render;
+use crate::rendering::renderer::Renderer;
+use crate::{BreakingAction, Component, ComponentFilter, SetupInfo, SharedState, UpdateInfo};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind};
+use std::any::TypeId;
+use std::collections::{BTreeMap, HashSet};
+use std::io;
+use std::path::PathBuf;
+
+/// The value of a [`Setting`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SettingValue {
+    Bool(bool),
+    F64(f64),
+    /// The index into the [`SettingKind::Choice`] options.
+    Choice(usize),
+    Key(KeyCode),
+}
+
+impl SettingValue {
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            SettingValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            SettingValue::F64(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_choice(&self) -> Option<usize> {
+        match self {
+            SettingValue::Choice(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn as_key(&self) -> Option<KeyCode> {
+        match self {
+            SettingValue::Key(k) => Some(*k),
+            _ => None,
+        }
+    }
+}
+
+/// The type of a [`Setting`], determining its valid values and how it is edited in the menu.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SettingKind {
+    /// A checkbox.
+    Bool,
+    /// A slider from `min` to `max` in increments of `step`.
+    Range { min: f64, max: f64, step: f64 },
+    /// A cycler through the given options.
+    Choice(Vec<String>),
+    /// A single key.
+    Keybind,
+}
+
+type ApplyFn<S> = Box<dyn FnMut(&SettingValue, &mut SharedState<S>)>;
+
+/// A single setting. Create one with [`Setting::bool`], [`Setting::range`], [`Setting::choice`]
+/// or [`Setting::keybind`], and register it with [`Settings::register`].
+pub struct Setting<S> {
+    key: String,
+    label: String,
+    category: String,
+    kind: SettingKind,
+    default: SettingValue,
+    value: SettingValue,
+    apply: Option<ApplyFn<S>>,
+}
+
+impl<S> Setting<S> {
+    fn new(key: impl Into<String>, kind: SettingKind, default: SettingValue) -> Self {
+        let key = key.into();
+        Self {
+            label: key.clone(),
+            key,
+            category: "General".to_string(),
+            kind,
+            default,
+            value: default,
+            apply: None,
+        }
+    }
+
+    pub fn bool(key: impl Into<String>, default: bool) -> Self {
+        Self::new(key, SettingKind::Bool, SettingValue::Bool(default))
+    }
+
+    pub fn range(key: impl Into<String>, default: f64, min: f64, max: f64, step: f64) -> Self {
+        Self::new(
+            key,
+            SettingKind::Range { min, max, step },
+            SettingValue::F64(default.clamp(min, max)),
+        )
+    }
+
+    /// # Panics
+    /// Panics if `default` is not a valid index into `options`.
+    pub fn choice(
+        key: impl Into<String>,
+        default: usize,
+        options: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        let options = options.into_iter().map(Into::into).collect::<Vec<_>>();
+        assert!(default < options.len(), "default choice out of range");
+        Self::new(
+            key,
+            SettingKind::Choice(options),
+            SettingValue::Choice(default),
+        )
+    }
+
+    pub fn keybind(key: impl Into<String>, default: KeyCode) -> Self {
+        Self::new(key, SettingKind::Keybind, SettingValue::Key(default))
+    }
+
+    /// Sets the label shown in the menu. Defaults to the key.
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = label.into();
+        self
+    }
+
+    /// Sets the category the setting is grouped under in the menu. Defaults to "General".
+    pub fn with_category(mut self, category: impl Into<String>) -> Self {
+        self.category = category.into();
+        self
+    }
+
+    /// Sets the closure that is called with the new value whenever the value changes.
+    pub fn with_apply(
+        mut self,
+        apply: impl FnMut(&SettingValue, &mut SharedState<S>) + 'static,
+    ) -> Self {
+        self.apply = Some(Box::new(apply));
+        self
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn category(&self) -> &str {
+        &self.category
+    }
+
+    pub fn kind(&self) -> &SettingKind {
+        &self.kind
+    }
+
+    pub fn default_value(&self) -> SettingValue {
+        self.default
+    }
+
+    pub fn value(&self) -> SettingValue {
+        self.value
+    }
+
+    /// Returns the value in a valid form for this setting, or `None` if it has the wrong type.
+    fn validate(&self, value: SettingValue) -> Option<SettingValue> {
+        match (&self.kind, value) {
+            (SettingKind::Bool, SettingValue::Bool(_)) => Some(value),
+            (SettingKind::Range { min, max, .. }, SettingValue::F64(v)) if !v.is_nan() => {
+                Some(SettingValue::F64(v.clamp(*min, *max)))
+            }
+            (SettingKind::Choice(options), SettingValue::Choice(i)) if i < options.len() => {
+                Some(value)
+            }
+            (SettingKind::Keybind, SettingValue::Key(_)) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn parse(&self, raw: &str) -> Option<SettingValue> {
+        let value = match &self.kind {
+            SettingKind::Bool => SettingValue::Bool(raw.parse().ok()?),
+            SettingKind::Range { .. } => SettingValue::F64(raw.parse().ok()?),
+            SettingKind::Choice(options) => {
+                SettingValue::Choice(options.iter().position(|o| o == raw)?)
+            }
+            SettingKind::Keybind => SettingValue::Key(parse_key(raw)?),
+        };
+        self.validate(value)
+    }
+
+    /// Formats the value for the settings file.
+    fn format(&self) -> String {
+        match (&self.kind, self.value) {
+            (SettingKind::Choice(options), SettingValue::Choice(i)) => options[i].clone(),
+            (_, SettingValue::Bool(b)) => b.to_string(),
+            (_, SettingValue::F64(v)) => v.to_string(),
+            (_, SettingValue::Choice(i)) => i.to_string(),
+            (_, SettingValue::Key(k)) => format_key(k),
+        }
+    }
+
+    /// Formats the value for the menu.
+    fn display_value(&self) -> String {
+        match (&self.kind, self.value) {
+            (_, SettingValue::Bool(b)) => if b { "[x]" } else { "[ ]" }.to_string(),
+            (SettingKind::Range { min, max, .. }, SettingValue::F64(v)) => {
+                const SLIDER_WIDTH: usize = 10;
+                let t = if max > min {
+                    (v - min) / (max - min)
+                } else {
+                    1.0
+                };
+                let filled = (t * SLIDER_WIDTH as f64).round() as usize;
+                format!(
+                    "[{}{}] {:.2}",
+                    "#".repeat(filled),
+                    "-".repeat(SLIDER_WIDTH - filled),
+                    v
+                )
+            }
+            (SettingKind::Choice(options), SettingValue::Choice(i)) => {
+                format!("< {} >", options[i])
+            }
+            (_, SettingValue::Key(k)) => format!("[{}]", format_key(k)),
+            _ => self.format(),
+        }
+    }
+
+    /// Returns the value after one step in `direction` (-1 or 1) in the menu.
+    fn stepped(&self, direction: i32) -> SettingValue {
+        match (&self.kind, self.value) {
+            (_, SettingValue::Bool(b)) => SettingValue::Bool(!b),
+            (SettingKind::Range { min, max, step }, SettingValue::F64(v)) => {
+                SettingValue::F64((v + step * direction as f64).clamp(*min, *max))
+            }
+            (SettingKind::Choice(options), SettingValue::Choice(i)) => {
+                let len = options.len() as i32;
+                SettingValue::Choice((i as i32 + direction).rem_euclid(len) as usize)
+            }
+            (_, value) => value,
+        }
+    }
+}
+
+/// Formats a key for the settings file and the menu.
+pub(crate) fn format_key(key: KeyCode) -> String {
+    match key {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::F(n) => format!("F{n}"),
+        KeyCode::Esc => "Esc".to_string(),
+        KeyCode::PageUp => "PageUp".to_string(),
+        KeyCode::PageDown => "PageDown".to_string(),
+        key => format!("{key:?}"),
+    }
+}
+
+/// Parses a key formatted by [`format_key`].
+fn parse_key(raw: &str) -> Option<KeyCode> {
+    let mut chars = raw.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+    let key = match raw {
+        "Space" => KeyCode::Char(' '),
+        "Esc" => KeyCode::Esc,
+        "Enter" => KeyCode::Enter,
+        "Tab" => KeyCode::Tab,
+        "Backspace" => KeyCode::Backspace,
+        "Delete" => KeyCode::Delete,
+        "Insert" => KeyCode::Insert,
+        "Home" => KeyCode::Home,
+        "End" => KeyCode::End,
+        "PageUp" => KeyCode::PageUp,
+        "PageDown" => KeyCode::PageDown,
+        "Up" => KeyCode::Up,
+        "Down" => KeyCode::Down,
+        "Left" => KeyCode::Left,
+        "Right" => KeyCode::Right,
+        _ => KeyCode::F(raw.strip_prefix('F')?.parse().ok()?),
+    };
+    Some(key)
+}
+
+/// The registry of all settings, kept as an extension of the [`SharedState`]. It is inserted by
+/// the first component that registers a setting, usually with
+/// `shared_state.ext_or_default::<Settings<S>>()`.
+pub struct Settings<S = ()> {
+    /// In registration order.
+    settings: Vec<Setting<S>>,
+    /// The raw values of the settings file, including keys that are not registered.
+    loaded: BTreeMap<String, String>,
+    /// Indices of settings whose apply closure must run.
+    pending_apply: Vec<usize>,
+    /// Whether there are changes that were not saved to the file yet.
+    dirty: bool,
+}
+
+impl<S> Default for Settings<S> {
+    fn default() -> Self {
+        Self {
+            settings: Vec::new(),
+            loaded: BTreeMap::new(),
+            pending_apply: Vec::new(),
+            dirty: false,
+        }
+    }
+}
+
+impl<S> Settings<S> {
+    /// The version written to the settings file.
+    pub const FILE_VERSION: u32 = 1;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a setting. If a value for its key was loaded, that value is used instead of
+    /// the default. Its apply closure runs with the initial value.
+    ///
+    /// Registering a key again replaces the previous setting, but keeps its current value.
+    pub fn register(&mut self, mut setting: Setting<S>) {
+        if let Some(value) = self
+            .loaded
+            .get(&setting.key)
+            .and_then(|raw| setting.parse(raw))
+        {
+            setting.value = value;
+        }
+        let idx = match self.index_of(&setting.key) {
+            Some(idx) => {
+                let previous = &self.settings[idx];
+                if let Some(value) = setting.validate(previous.value) {
+                    setting.value = value;
+                }
+                self.settings[idx] = setting;
+                idx
+            }
+            None => {
+                self.settings.push(setting);
+                self.settings.len() - 1
+            }
+        };
+        self.queue_apply(idx);
+    }
+
+    fn index_of(&self, key: &str) -> Option<usize> {
+        self.settings.iter().position(|s| s.key == key)
+    }
+
+    fn queue_apply(&mut self, idx: usize) {
+        if !self.pending_apply.contains(&idx) {
+            self.pending_apply.push(idx);
+        }
+    }
+
+    /// Returns the setting with the given key.
+    pub fn setting(&self, key: &str) -> Option<&Setting<S>> {
+        self.settings.iter().find(|s| s.key == key)
+    }
+
+    /// Returns all registered settings in registration order.
+    pub fn iter(&self) -> impl Iterator<Item = &Setting<S>> {
+        self.settings.iter()
+    }
+
+    pub fn get(&self, key: &str) -> Option<SettingValue> {
+        self.setting(key).map(|s| s.value)
+    }
+
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        self.get(key)?.as_bool()
+    }
+
+    pub fn get_f64(&self, key: &str) -> Option<f64> {
+        self.get(key)?.as_f64()
+    }
+
+    pub fn get_choice(&self, key: &str) -> Option<usize> {
+        self.get(key)?.as_choice()
+    }
+
+    pub fn get_key(&self, key: &str) -> Option<KeyCode> {
+        self.get(key)?.as_key()
+    }
+
+    /// Returns true if `code` is the key bound to the keybind setting `key`.
+    /// Characters are compared case-insensitively.
+    pub fn is_key(&self, key: &str, code: KeyCode) -> bool {
+        match (self.get_key(key), code) {
+            (Some(KeyCode::Char(a)), KeyCode::Char(b)) => a.eq_ignore_ascii_case(&b),
+            (Some(bound), code) => bound == code,
+            (None, _) => false,
+        }
+    }
+
+    /// Sets the value of a setting. Range values are clamped.
+    ///
+    /// Returns false if there is no such setting or the value has the wrong type.
+    pub fn set(&mut self, key: &str, value: SettingValue) -> bool {
+        let Some(idx) = self.index_of(key) else {
+            return false;
+        };
+        let Some(value) = self.settings[idx].validate(value) else {
+            return false;
+        };
+        if self.settings[idx].value != value {
+            self.settings[idx].value = value;
+            self.dirty = true;
+            self.queue_apply(idx);
+        }
+        true
+    }
+
+    /// Resets a setting to its default value.
+    pub fn reset(&mut self, key: &str) -> bool {
+        match self.setting(key) {
+            Some(setting) => {
+                let default = setting.default;
+                self.set(key, default)
+            }
+            None => false,
+        }
+    }
+
+    /// Returns true if there are changes that were not saved to the file yet.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Loads the contents of a settings file. Registered settings are updated immediately,
+    /// settings registered later use the loaded values.
+    ///
+    /// Unknown keys and values that do not parse are kept, but otherwise ignored.
+    pub fn load_str(&mut self, contents: &str) {
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, raw)) = line.split_once('=') else {
+                continue;
+            };
+            let (key, raw) = (key.trim(), raw.trim());
+            if key == "version" {
+                // newer versions only add keys, which we preserve anyway
+                continue;
+            }
+            self.loaded.insert(key.to_string(), raw.to_string());
+            if let Some(idx) = self.index_of(key)
+                && let Some(value) = self.settings[idx].parse(raw)
+                && self.settings[idx].value != value
+            {
+                self.settings[idx].value = value;
+                self.queue_apply(idx);
+            }
+        }
+    }
+
+    /// Returns the contents of the settings file for the current values.
+    pub fn to_file_string(&self) -> String {
+        let mut entries = self.loaded.clone();
+        for setting in &self.settings {
+            entries.insert(setting.key.clone(), setting.format());
+        }
+        let mut contents = format!("version = {}\n", Self::FILE_VERSION);
+        for (key, raw) in entries {
+            contents.push_str(&format!("{key} = {raw}\n"));
+        }
+        contents
+    }
+
+    /// Writes the settings file atomically, by writing to a temporary file first and then
+    /// renaming it.
+    pub fn save_to(&mut self, path: &std::path::Path) -> io::Result<()> {
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, self.to_file_string())?;
+        std::fs::rename(&tmp_path, path)?;
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+/// Runs all pending apply closures.
+pub(crate) fn apply_pending<S: 'static>(shared_state: &mut SharedState<S>) {
+    // the settings are moved out during the call, so that the closures can access the shared state
+    shared_state.with_ext::<Settings<S>, _>(|settings, shared_state| {
+        for idx in std::mem::take(&mut settings.pending_apply) {
+            let setting = &mut settings.settings[idx];
+            if let Some(apply) = &mut setting.apply {
+                apply(&setting.value, shared_state);
+            }
+        }
+    });
+}
+
+struct Menu {
+    /// Index into the menu order of settings.
+    selected: usize,
+    capturing: bool,
+    /// The values when the menu was opened, for reverting.
+    snapshot: Vec<(String, SettingValue)>,
+}
+
+/// A component that loads and saves the [`Settings`], applies changed values, and
+/// provides the settings menu.
+pub struct SettingsComponent {
+    path: Option<PathBuf>,
+    loaded: Option<String>,
+    menu: Option<Menu>,
+    // whether a key was captured for a keybind this frame, so that it is not also handled as
+    // a menu key
+    captured_this_frame: bool,
+}
+
+impl SettingsComponent {
+    /// The key that opens and closes the settings menu.
+    pub const MENU_KEY: KeyCode = KeyCode::F(2);
+
+    /// Creates a new settings component that persists to the file at `path`.
+    ///
+    /// The file is read immediately, so that the loaded values are available to all components
+    /// during setup. A missing file is not an error.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let loaded = std::fs::read_to_string(&path).ok();
+        Self {
+            path: Some(path),
+            loaded,
+            menu: None,
+            captured_this_frame: false,
+        }
+    }
+
+    /// Creates a new settings component that does not persist settings.
+    pub fn without_persistence() -> Self {
+        Self {
+            path: None,
+            loaded: None,
+            menu: None,
+            captured_this_frame: false,
+        }
+    }
+
+    pub fn is_menu_open(&self) -> bool {
+        self.menu.is_some()
+    }
+
+    /// Returns true if the menu waits for a key to assign to the selected keybind.
+    pub fn is_capturing_key(&self) -> bool {
+        self.menu.as_ref().is_some_and(|m| m.capturing)
+    }
+
+    /// Returns the keys of all settings in menu order, i.e., grouped by category.
+    fn menu_order<S>(settings: &Settings<S>) -> Vec<String> {
+        let mut categories: Vec<&str> = vec![];
+        for setting in settings.iter() {
+            if !categories.contains(&setting.category()) {
+                categories.push(setting.category());
+            }
+        }
+        categories
+            .into_iter()
+            .flat_map(|category| {
+                settings
+                    .iter()
+                    .filter(move |s| s.category() == category)
+                    .map(|s| s.key().to_string())
+            })
+            .collect()
+    }
+
+    /// Opens the menu and pauses all other components except the input recorder.
+    pub fn open_menu<S: 'static>(&mut self, shared_state: &mut SharedState<S>) {
+        if self.menu.is_some() {
+            return;
+        }
+        let whitelist = HashSet::from([
+            TypeId::of::<Self>(),
+            TypeId::of::<KeyPressRecorderComponent>(),
+            TypeId::of::<DimBehindComponent>(),
+        ]);
+        shared_state.push_component_filter(ComponentFilter::Whitelist(whitelist));
+        self.menu = Some(Menu {
+            selected: 0,
+            capturing: false,
+            snapshot: shared_state
+                .ext_or_default::<Settings<S>>()
+                .iter()
+                .map(|s| (s.key().to_string(), s.value()))
+                .collect(),
+        });
+    }
+
+    /// Closes the menu, resumes the game, and saves the settings.
+    pub fn close_menu<S: 'static>(&mut self, shared_state: &mut SharedState<S>) {
+        if self.menu.take().is_some() {
+            shared_state.pop_component_filter();
+            self.save(shared_state);
+        }
+    }
+
+    /// Restores all values from when the menu was opened.
+    fn revert<S: 'static>(&mut self, shared_state: &mut SharedState<S>) {
+        if let Some(menu) = &self.menu {
+            let settings = shared_state.ext_or_default::<Settings<S>>();
+            for (key, value) in &menu.snapshot {
+                settings.set(key, *value);
+            }
+        }
+    }
+
+    fn has_unsaved_changes<S: 'static>(&self, shared_state: &SharedState<S>) -> bool {
+        let settings = shared_state.ext::<Settings<S>>();
+        self.menu.as_ref().is_some_and(|menu| {
+            menu.snapshot
+                .iter()
+                .any(|(key, value)| settings.and_then(|s| s.get(key)) != Some(*value))
+        })
+    }
+
+    fn save<S: 'static>(&mut self, shared_state: &mut SharedState<S>) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let settings = shared_state.ext_or_default::<Settings<S>>();
+        if !settings.is_dirty() {
+            return;
+        }
+        let source = std::any::type_name::<Self>();
+        match settings.save_to(path) {
+            Ok(()) => shared_state.problems.clear(source, "save"),
+            Err(e) => shared_state.problems.report(
+                Problem::new(
+                    Severity::Error,
+                    source,
+                    format!("Failed to save settings: {e}"),
+                )
+                .with_hint(format!("check that {} is writable", path.display()))
+                .with_dedup_key("save"),
+            ),
+        }
+    }
+
+    fn update_menu<S: 'static>(&mut self, shared_state: &mut SharedState<S>) {
+        let order = Self::menu_order(shared_state.ext_or_default::<Settings<S>>());
+        let keys = &shared_state.pressed_keys;
+        let (up, down, left, right, enter, reset, revert, close) = (
+            keys.did_press(KeyCode::Up),
+            keys.did_press(KeyCode::Down),
+            keys.did_press(KeyCode::Left),
+            keys.did_press(KeyCode::Right),
+            keys.did_press(KeyCode::Enter),
+            keys.did_press(KeyCode::Backspace),
+            keys.did_press_char_ignore_case('r'),
+            keys.did_press(KeyCode::Esc) || keys.did_press(Self::MENU_KEY),
+        );
+        if close {
+            self.close_menu(shared_state);
+            return;
+        }
+        if revert {
+            self.revert(shared_state);
+            return;
+        }
+        let menu = self.menu.as_mut().unwrap();
+        if order.is_empty() {
+            return;
+        }
+        if up {
+            menu.selected = (menu.selected + order.len() - 1) % order.len();
+        }
+        if down {
+            menu.selected = (menu.selected + 1) % order.len();
+        }
+        menu.selected = menu.selected.min(order.len() - 1);
+        let key = &order[menu.selected];
+        let settings = shared_state.ext_or_default::<Settings<S>>();
+        let setting = settings.setting(key).unwrap();
+        if reset {
+            settings.reset(key);
+        } else if enter && *setting.kind() == SettingKind::Keybind {
+            menu.capturing = true;
+        } else if enter || right {
+            let value = setting.stepped(1);
+            settings.set(key, value);
+        } else if left {
+            let value = setting.stepped(-1);
+            settings.set(key, value);
+        }
+    }
+}
+
+impl<S: 'static> Component<S> for SettingsComponent {
+    fn runs_while_paused(&self) -> bool {
+        true
+    }
+
+    fn setup(&mut self, _setup_info: &SetupInfo, shared_state: &mut SharedState<S>) {
+        let settings = shared_state.ext_or_default::<Settings<S>>();
+        if let Some(contents) = self.loaded.take() {
+            settings.load_str(&contents);
+        }
+    }
+
+    fn on_quit(&mut self, shared_state: &mut SharedState<S>) {
+        self.save(shared_state);
+    }
+
+    fn on_event(
+        &mut self,
+        event: Event,
+        shared_state: &mut SharedState<S>,
+    ) -> Option<BreakingAction> {
+        let Some(menu) = &mut self.menu else {
+            return None;
+        };
+        if !menu.capturing {
+            return None;
+        }
+        if let Event::Key(KeyEvent {
+            code,
+            kind: KeyEventKind::Press,
+            ..
+        }) = event
+        {
+            menu.capturing = false;
+            self.captured_this_frame = true;
+            if code != KeyCode::Esc {
+                let settings = shared_state.ext_or_default::<Settings<S>>();
+                let order = Self::menu_order(settings);
+                if let Some(key) = order.get(menu.selected) {
+                    settings.set(key, SettingValue::Key(code));
+                }
+            }
+        }
+        None
+    }
+
+    fn update(&mut self, _update_info: UpdateInfo, shared_state: &mut SharedState<S>) {
+        if std::mem::take(&mut self.captured_this_frame) {
+            // the captured key must not also act as a menu key
+        } else if self.is_capturing_key() {
+            // wait for on_event
+        } else if self.menu.is_some() {
+            self.update_menu(shared_state);
+        } else if shared_state.pressed_keys.did_press(Self::MENU_KEY) {
+            self.open_menu(shared_state);
+        }
+        apply_pending(shared_state);
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, shared_state: &SharedState<S>, _depth_base: i32) {
+        let Some(menu) = &self.menu else {
+            return;
+        };
+        let Some(settings) = shared_state.ext::<Settings<S>>() else {
+            return;
+        };
+        let order = Self::menu_order(settings);
+        let mut lines = vec![("Settings".to_string(), false)];
+        let mut category = None;
+        for (idx, key) in order.iter().enumerate() {
+            let setting = settings.setting(key).unwrap();
+            if category != Some(setting.category()) {
+                category = Some(setting.category());
+                lines.push((String::new(), false));
+                lines.push((format!("{}:", setting.category()), false));
+            }
+            let selected = idx == menu.selected;
+            let value = if selected && menu.capturing {
+                "press a key...".to_string()
+            } else {
+                setting.display_value()
+            };
+            let cursor = if selected { '>' } else { ' ' };
+            lines.push((
+                format!("{cursor} {:<24} {value}", setting.label()),
+                selected,
+            ));
+        }
+        lines.push((String::new(), false));
+        if self.has_unsaved_changes(shared_state) {
+            lines.push(("Changed. Press r to revert.".to_string(), false));
+        }
+        lines.push((
+            "Up/Down: select, Left/Right/Enter: change, Backspace: default, Esc: close".to_string(),
+            false,
+        ));
+
+        let width = lines
+            .iter()
+            .map(|(l, _)| l.chars().count())
+            .max()
+            .unwrap_or(0)
+            + 2;
+        for (y, (line, selected)) in lines.iter().enumerate() {
+            let bg_color = if *selected {
+                [60, 60, 90]
+            } else {
+                [30, 30, 30]
+            };
+            format!(" {line:<width$}", width = width - 1)
+                .with_bg_color(bg_color)
+                .render(renderer, 1, y + 1, i32::MAX - 90);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Game;
+    use crate::components::fncomponent::FnComponent;
+    use crate::components::keyboard::KeyPressRecorderComponent;
+    use crossterm::event::KeyModifiers;
+
+    #[derive(Default)]
+    struct Log(Vec<String>);
+
+    fn logging_setting(setting: Setting<()>) -> Setting<()> {
+        let key = setting.key().to_string();
+        setting.with_apply(move |value, shared_state: &mut SharedState| {
+            shared_state
+                .extensions
+                .get_mut::<Log>()
+                .unwrap()
+                .0
+                .push(format!("{key}={value:?}"));
+        })
+    }
+
+    fn new_shared_state() -> SharedState<()> {
+        let mut shared_state = SharedState::<()>::new(80, 24);
+        shared_state.extensions.insert(Log::default());
+        shared_state
+    }
+
+    fn settings(shared_state: &mut SharedState) -> &mut Settings {
+        shared_state.ext_or_default()
+    }
+
+    fn log(shared_state: &SharedState) -> Vec<String> {
+        shared_state.extensions.get::<Log>().unwrap().0.clone()
+    }
+
+    #[test]
+    fn test_apply_on_load_ordering() {
+        let mut shared_state = new_shared_state();
+        // registered before loading
+        settings(&mut shared_state).register(logging_setting(Setting::bool("a", false)));
+        settings(&mut shared_state).load_str("version = 1\na = true\nb = 0.25\n");
+        // registered after loading
+        settings(&mut shared_state)
+            .register(logging_setting(Setting::range("b", 1.0, 0.0, 1.0, 0.1)));
+        assert_eq!(settings(&mut shared_state).get_bool("a"), Some(true));
+        assert_eq!(settings(&mut shared_state).get_f64("b"), Some(0.25));
+        // nothing is applied until the component runs, then each setting exactly once
+        assert!(log(&shared_state).is_empty());
+        apply_pending(&mut shared_state);
+        assert_eq!(log(&shared_state), vec!["a=Bool(true)", "b=F64(0.25)"]);
+        apply_pending(&mut shared_state);
+        assert_eq!(log(&shared_state).len(), 2);
+        // loading is not a user change
+        assert!(!settings(&mut shared_state).is_dirty());
+
+        assert!(settings(&mut shared_state).set("b", SettingValue::F64(5.0)));
+        assert!(!settings(&mut shared_state).set("b", SettingValue::Bool(true)));
+        apply_pending(&mut shared_state);
+        assert_eq!(log(&shared_state)[2], "b=F64(1.0)");
+        assert!(settings(&mut shared_state).is_dirty());
+    }
+
+    #[test]
+    fn test_apply_before_first_update() {
+        let mut game = Game::<Vec<u8>, ()>::new_headless(80, 24);
+        game.shared_state_mut().extensions.insert(Log::default());
+        game.shared_state_mut()
+            .ext_or_default::<Settings>()
+            .load_str("version = 1\na = true\n");
+        // updates before the settings component, like the recommended components
+        game.add_component(Box::new(
+            FnComponent::new()
+                .with_setup(|_, shared_state| {
+                    settings(shared_state).register(logging_setting(Setting::bool("a", false)));
+                })
+                .with_update(|_, shared_state| {
+                    let a = settings(shared_state).get_bool("a");
+                    shared_state
+                        .extensions
+                        .get_mut::<Log>()
+                        .unwrap()
+                        .0
+                        .push(format!("update a={a:?}"));
+                }),
+        ));
+        game.add_component(Box::new(SettingsComponent::without_persistence()));
+        game.run_frames(1).unwrap();
+        assert_eq!(
+            log(game.shared_state()),
+            ["a=Bool(true)", "update a=Some(true)"]
+        );
+    }
+
+    fn menu_game() -> Game<Vec<u8>, ()> {
+        let mut game = Game::<Vec<u8>, ()>::new_headless(80, 24);
+        game.add_component(Box::new(KeyPressRecorderComponent::new()));
+        game.add_component(Box::new(SettingsComponent::without_persistence()));
+        game
+    }
+
+    /// Runs a frame in which `key` was pressed.
+    fn press(game: &mut Game<Vec<u8>, ()>, key: KeyCode) {
+        let modifiers = if matches!(key, KeyCode::Char(c) if c.is_uppercase()) {
+            KeyModifiers::SHIFT
+        } else {
+            KeyModifiers::NONE
+        };
+        game.push_event(Event::Key(KeyEvent::new(key, modifiers)));
+        game.run_frames(1).unwrap();
+    }
+
+    fn text(game: &Game<Vec<u8>, ()>) -> String {
+        let frame = game.frame();
+        let mut out = String::new();
+        for y in 0..frame.height() {
+            out.extend((0..frame.width()).map(|x| frame.pixel_at(x, y).c));
+            out.push('\n');
+        }
+        out
+    }
+
+    fn jump_key(game: &Game<Vec<u8>, ()>) -> Option<KeyCode> {
+        game.shared_state().ext::<Settings>()?.get_key("jump")
+    }
+
+    #[test]
+    fn test_keybind_capture() {
+        let mut game = menu_game();
+        settings(game.shared_state_mut()).register(Setting::keybind("jump", KeyCode::Char(' ')));
+        let is_menu_open =
+            |game: &Game<Vec<u8>, ()>| game.shared_state().component_filter().is_some();
+        let is_capturing_key = |game: &Game<Vec<u8>, ()>| text(game).contains("press a key...");
+
+        press(&mut game, SettingsComponent::MENU_KEY);
+        assert!(is_menu_open(&game));
+        press(&mut game, KeyCode::Enter);
+        assert!(is_capturing_key(&game));
+
+        // Esc cancels the capture, but does not close the menu
+        press(&mut game, KeyCode::Esc);
+        assert!(!is_capturing_key(&game));
+        assert!(is_menu_open(&game));
+        assert_eq!(jump_key(&game), Some(KeyCode::Char(' ')));
+
+        // the captured key is assigned and not treated as a menu key, even if it is one
+        press(&mut game, KeyCode::Enter);
+        press(&mut game, KeyCode::Char('R'));
+        assert!(is_menu_open(&game));
+        assert!(!is_capturing_key(&game));
+        assert_eq!(jump_key(&game), Some(KeyCode::Char('R')));
+        assert!(settings(game.shared_state_mut()).is_key("jump", KeyCode::Char('r')));
+
+        // revert restores the value from when the menu was opened
+        press(&mut game, KeyCode::Char('r'));
+        assert_eq!(jump_key(&game), Some(KeyCode::Char(' ')));
+
+        press(&mut game, KeyCode::Esc);
+        assert!(!is_menu_open(&game));
+    }
+
+    #[test]
+    fn test_file_round_trip() {
+        let path =
+            std::env::temp_dir().join(format!("teng-settings-test-{}.txt", std::process::id()));
+        std::fs::write(
+            &path,
+            "version = 2\nfrom_the_future = 42\nvolume = 0.5\nquality = Low\nquit = F10\n",
+        )
+        .unwrap();
+
+        let register = |shared_state: &mut SharedState| {
+            let settings = settings(shared_state);
+            settings.register(Setting::range("volume", 1.0, 0.0, 1.0, 0.1));
+            settings.register(Setting::choice("quality", 1, ["Low", "High"]));
+            settings.register(Setting::keybind("quit", KeyCode::Char('q')));
+            settings.register(Setting::bool("vsync", true));
+        };
+
+        let mut game = Game::<Vec<u8>, ()>::new_headless(80, 24);
+        game.add_component(Box::new(SettingsComponent::new(&path)));
+        game.add_component(Box::new(
+            FnComponent::new()
+                .with_setup(move |_, shared_state| register(shared_state))
+                .with_on_event(|_, _| Some(BreakingAction::Quit)),
+        ));
+        game.run_frames(1).unwrap();
+        let settings = settings(game.shared_state_mut());
+        assert_eq!(settings.get_f64("volume"), Some(0.5));
+        assert_eq!(settings.get_choice("quality"), Some(0));
+        assert_eq!(settings.get_key("quit"), Some(KeyCode::F(10)));
+        assert_eq!(settings.get_bool("vsync"), Some(true));
+        settings.set("vsync", SettingValue::Bool(false));
+        // quitting saves the settings
+        game.push_event(Event::FocusGained);
+        assert!(game.run_frames(1).unwrap());
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            contents,
+            "version = 1\nfrom_the_future = 42\nquality = Low\nquit = F10\nvolume = 0.5\nvsync = false\n"
+        );
+
+        let mut shared_state = new_shared_state();
+        shared_state
+            .ext_or_default::<Settings>()
+            .load_str(&contents);
+        register(&mut shared_state);
+        let settings = shared_state.ext::<Settings>().unwrap();
+        assert_eq!(settings.get_bool("vsync"), Some(false));
+        assert_eq!(settings.to_file_string(), contents);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,428 @@
+//! Short-lived on-screen messages for the player, e.g. "Saved!".
+//!
+//! Unlike [debug messages](crate::components::debuginfo::DebugMessage), toasts are meant for the
+//! player. Games push them with [`SharedState::toast`]:
+//! ```rust
+//! use teng::SharedState;
+//! use teng::components::toast::{Severity, Toast};
+//!
+//! fn save(shared_state: &mut SharedState) {
+//!     shared_state.toast(Toast::new("Saved!").with_duration(2.0));
+//!     shared_state.toast(Toast::new("Disk almost full").with_severity(Severity::Warning));
+//! }
+//! ```
+//! The [`ToastComponent`] slides them in at the top right corner, or the corner set with
+//! [`ToastComponent::with_anchor`], stacks up to [`ToastComponent::with_max_visible`] of them,
+//! and fades them out before they are removed. The stack is placed with the
+//! [`OverlayLayoutManager`](crate::components::overlay_layout::OverlayLayoutManager), so it moves
+//! to another corner instead of covering e.g. the debug info in the same corner.
+//!
+//! If more toasts arrive than fit, the newer ones wait, and the oldest visible toast fades out
+//! early to make room. See `examples/toasts.rs` for an example.
+
+use crate::components::Component;
+use crate::components::overlay_layout::{Corner, OverlayAnchor, Placement};
+use crate::rendering::palette;
+use crate::rendering::render::Render;
+use crate::rendering::renderer::Renderer;
+use crate::{SharedState, UpdateInfo};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How important a toast is, which decides its color and icon.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum Severity {
+    #[default]
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn color(self) -> [u8; 3] {
+        match self {
+            Severity::Info => palette::INFO,
+            Severity::Success => palette::OK,
+            Severity::Warning => palette::WARN,
+            Severity::Error => palette::DANGER,
+        }
+    }
+
+    /// A second cue next to the color, for players that cannot tell the colors apart.
+    pub fn icon(self) -> char {
+        match self {
+            Severity::Info => 'i',
+            Severity::Success => '✓',
+            Severity::Warning => '!',
+            Severity::Error => '✗',
+        }
+    }
+}
+
+/// A message pushed with [`SharedState::toast`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Toast {
+    pub text: String,
+    /// In seconds, including the fade out.
+    pub duration: f64,
+    pub severity: Severity,
+}
+
+impl Toast {
+    /// Creates an info toast that is shown for three seconds.
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            duration: 3.0,
+            severity: Severity::Info,
+        }
+    }
+
+    /// Sets how long the toast is shown, in seconds.
+    pub fn with_duration(mut self, duration: f64) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    /// Returns the text as it is shown, with the icon.
+    fn line(&self) -> String {
+        format!(" {} {} ", self.severity.icon(), self.text)
+    }
+}
+
+/// The toasts that no [`ToastComponent`] has picked up yet, kept as an extension of the
+/// [`SharedState`]. Use [`SharedState::toast`] to push toasts.
+#[derive(Debug, Default)]
+pub struct ToastQueue {
+    queued: VecDeque<Toast>,
+}
+
+impl ToastQueue {
+    /// The maximum number of queued toasts. Older ones are dropped, e.g. if no
+    /// [`ToastComponent`] is installed.
+    pub const MAX_QUEUED: usize = 64;
+
+    pub fn push(&mut self, toast: Toast) {
+        if self.queued.len() == Self::MAX_QUEUED {
+            self.queued.pop_front();
+        }
+        self.queued.push_back(toast);
+    }
+
+    /// Returns the number of queued toasts.
+    pub fn len(&self) -> usize {
+        self.queued.len()
+    }
+
+    /// Returns true if no toasts are queued.
+    pub fn is_empty(&self) -> bool {
+        self.queued.is_empty()
+    }
+
+    /// Removes and returns the queued toasts, oldest first.
+    pub fn drain(&mut self) -> impl Iterator<Item = Toast> + '_ {
+        self.queued.drain(..)
+    }
+}
+
+/// A toast on the screen.
+struct ShownToast {
+    toast: Toast,
+    shown_at: Instant,
+    expires_at: Instant,
+}
+
+/// Shows the toasts pushed with [`SharedState::toast`].
+pub struct ToastComponent {
+    anchor: OverlayAnchor,
+    max_visible: usize,
+    slide_duration: Duration,
+    fade_duration: Duration,
+    shown: Vec<ShownToast>,
+    /// Toasts that wait for a free place in the stack.
+    waiting: VecDeque<Toast>,
+    placement: Option<Placement>,
+    /// The time of the last update, for the animations.
+    now: Option<Instant>,
+}
+
+impl Default for ToastComponent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ToastComponent {
+    /// The name of the toast stack in the [`OverlayLayoutManager`](crate::components::overlay_layout::OverlayLayoutManager).
+    pub const OVERLAY: &'static str = "game toasts";
+    /// The depth of the toasts, above other UI.
+    pub const DEPTH: i32 = i32::MAX - 60;
+    const BG: [u8; 3] = [30, 30, 30];
+    const TEXT: [u8; 3] = [255, 255, 255];
+
+    pub fn new() -> Self {
+        Self {
+            anchor: OverlayAnchor::new(Corner::TopRight).with_margin(1, 0),
+            max_visible: 5,
+            slide_duration: Duration::from_millis(200),
+            fade_duration: Duration::from_millis(500),
+            shown: vec![],
+            waiting: VecDeque::new(),
+            placement: None,
+            now: None,
+        }
+    }
+
+    /// Sets the preferred corner of the toasts. The default is the top right corner.
+    pub fn with_anchor(mut self, anchor: OverlayAnchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    /// Sets how many toasts are shown at once. The default is five.
+    pub fn with_max_visible(mut self, max_visible: usize) -> Self {
+        self.max_visible = max_visible.max(1);
+        self
+    }
+
+    /// Sets how long a toast takes to slide in and to fade out. The defaults are 200 and 500
+    /// milliseconds.
+    pub fn with_animation(mut self, slide: Duration, fade: Duration) -> Self {
+        self.slide_duration = slide;
+        self.fade_duration = fade;
+        self
+    }
+
+    /// Returns the texts of the visible toasts, oldest first.
+    pub fn toasts(&self) -> impl Iterator<Item = &str> {
+        self.shown.iter().map(|shown| shown.toast.text.as_str())
+    }
+
+    /// Returns the number of toasts that wait for a free place.
+    pub fn waiting(&self) -> usize {
+        self.waiting.len()
+    }
+
+    /// Returns how far a toast has slid in, from 0 to 1.
+    fn slide_progress(&self, shown: &ShownToast, now: Instant) -> f64 {
+        if self.slide_duration.is_zero() {
+            return 1.0;
+        }
+        let elapsed = now.saturating_duration_since(shown.shown_at);
+        (elapsed.as_secs_f64() / self.slide_duration.as_secs_f64()).min(1.0)
+    }
+
+    /// Returns the brightness of a toast, from 1 before it fades to 0 when it expires.
+    fn brightness(&self, shown: &ShownToast, now: Instant) -> f64 {
+        if self.fade_duration.is_zero() {
+            return 1.0;
+        }
+        let remaining = shown.expires_at.saturating_duration_since(now);
+        (remaining.as_secs_f64() / self.fade_duration.as_secs_f64()).min(1.0)
+    }
+}
+
+/// Blends `color` into `bg`, fully `color` at a brightness of 1.
+fn dim(color: [u8; 3], bg: [u8; 3], brightness: f64) -> [u8; 3] {
+    std::array::from_fn(|i| {
+        let (c, b) = (color[i] as f64, bg[i] as f64);
+        (b + (c - b) * brightness).round() as u8
+    })
+}
+
+impl<S> Component<S> for ToastComponent {
+    fn runs_while_paused(&self) -> bool {
+        true
+    }
+
+    fn update(&mut self, update_info: UpdateInfo, shared_state: &mut SharedState<S>) {
+        let now = update_info.current_time;
+        self.now = Some(now);
+        if let Some(queue) = shared_state.ext_mut::<ToastQueue>() {
+            self.waiting.extend(queue.drain());
+        }
+        while self.waiting.len() > ToastQueue::MAX_QUEUED {
+            self.waiting.pop_front();
+        }
+
+        self.shown.retain(|shown| shown.expires_at > now);
+        while self.shown.len() < self.max_visible
+            && let Some(toast) = self.waiting.pop_front()
+        {
+            let duration = Duration::from_secs_f64(toast.duration.max(0.0));
+            self.shown.push(ShownToast {
+                toast,
+                shown_at: now,
+                expires_at: now + duration,
+            });
+        }
+        // make room for the waiting toasts
+        if !self.waiting.is_empty()
+            && let Some(oldest) = self.shown.first_mut()
+        {
+            oldest.expires_at = oldest.expires_at.min(now + self.fade_duration);
+        }
+
+        if self.shown.is_empty() {
+            shared_state.overlay_layout.release(Self::OVERLAY);
+            self.placement = None;
+        } else {
+            let display_width = shared_state.display_info.width();
+            let width = self
+                .shown
+                .iter()
+                .map(|shown| shown.toast.line().chars().count())
+                .max()
+                .unwrap_or(0);
+            self.placement = Some(shared_state.place_overlay(
+                Self::OVERLAY,
+                self.anchor,
+                width.min(display_width),
+                self.shown.len(),
+            ));
+        }
+    }
+
+    fn render(
+        &self,
+        renderer: &mut dyn Renderer,
+        _shared_state: &SharedState<S>,
+        _depth_base: i32,
+    ) {
+        let (Some(placement), Some(now)) = (self.placement, self.now) else {
+            return;
+        };
+        let rect = placement.rect;
+        for (i, shown) in self.shown.iter().take(rect.height).enumerate() {
+            // the oldest toast is closest to the edge of the screen
+            let y = if placement.corner.is_top() {
+                rect.y + i
+            } else {
+                rect.y + rect.height - 1 - i
+            };
+            let line: Vec<char> = shown.toast.line().chars().take(rect.width).collect();
+            let width = line.len();
+            // slides in from the closest side of the screen
+            let hidden = ((1.0 - self.slide_progress(shown, now)) * width as f64).round() as usize;
+            let (x, visible) = if placement.corner.is_left() {
+                (rect.x, &line[hidden..])
+            } else {
+                (
+                    rect.x + rect.width - width + hidden,
+                    &line[..width - hidden],
+                )
+            };
+
+            let brightness = self.brightness(shown, now);
+            let bg = Self::BG;
+            let (icon, text) = visible.split_at(visible.len().min(2));
+            let icon: String = icon.iter().collect();
+            let text: String = text.iter().collect();
+            icon.with_color(dim(shown.toast.severity.color(), bg, brightness))
+                .with_bg_color(bg)
+                .render(renderer, x, y, Self::DEPTH);
+            text.with_color(dim(Self::TEXT, bg, brightness))
+                .with_bg_color(bg)
+                .render(renderer, x + icon.chars().count(), y, Self::DEPTH);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rendering::color::Color;
+    use crate::rendering::renderer::{ClipRect, DisplayRenderer};
+
+    fn render(component: &ToastComponent, shared_state: &SharedState) -> Vec<String> {
+        let (width, height) = (
+            shared_state.display_info.width(),
+            shared_state.display_info.height(),
+        );
+        let mut renderer = DisplayRenderer::new_with_sink(width, height, vec![]);
+        component.render(&mut renderer, shared_state, 0);
+        renderer.flush().unwrap();
+        let frame = renderer.previous_frame();
+        (0..height)
+            .map(|y| (0..width).map(|x| frame.pixel_at(x, y).c).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_overflow_expires_oldest_early() {
+        let now = Instant::now();
+        let mut shared_state = SharedState::<()>::new(30, 5);
+        let mut component = ToastComponent::new().with_max_visible(2);
+        for text in ["one", "two", "three"] {
+            shared_state.toast(Toast::new(text).with_duration(10.0));
+        }
+        component.update(UpdateInfo::at(now), &mut shared_state);
+        assert_eq!(component.toasts().collect::<Vec<_>>(), ["one", "two"]);
+        assert_eq!(component.waiting(), 1);
+
+        // the oldest fades out right away, instead of after ten seconds
+        component.update(
+            UpdateInfo::at(now + Duration::from_millis(600)),
+            &mut shared_state,
+        );
+        assert_eq!(component.toasts().collect::<Vec<_>>(), ["two", "three"]);
+        assert_eq!(component.waiting(), 0);
+        component.update(
+            UpdateInfo::at(now + Duration::from_secs(11)),
+            &mut shared_state,
+        );
+        assert_eq!(component.toasts().count(), 0);
+        assert_eq!(
+            shared_state
+                .overlay_layout
+                .placement(ToastComponent::OVERLAY),
+            None
+        );
+    }
+
+    #[test]
+    fn test_slide_fade_and_placement() {
+        let now = Instant::now();
+        let mut shared_state = SharedState::<()>::new(16, 4);
+        let mut component = ToastComponent::new();
+        shared_state.toast(Toast::new("Saved!").with_duration(2.0));
+        component.update(UpdateInfo::at(now), &mut shared_state);
+        // not slid in yet
+        assert_eq!(render(&component, &shared_state)[0], " ".repeat(16));
+
+        let later = now + Duration::from_millis(100);
+        component.update(UpdateInfo::at(later), &mut shared_state);
+        assert_eq!(render(&component, &shared_state)[0], "           i Sa ");
+        let later = now + Duration::from_secs(1);
+        component.update(UpdateInfo::at(later), &mut shared_state);
+        assert_eq!(render(&component, &shared_state)[0], "      i Saved!  ");
+
+        // fades into the background
+        assert_eq!(component.brightness(&component.shown[0], later), 1.0);
+        let fading = now + Duration::from_millis(1750);
+        assert_eq!(component.brightness(&component.shown[0], fading), 0.5);
+        assert_eq!(
+            dim(ToastComponent::TEXT, ToastComponent::BG, 0.5),
+            [143, 143, 143]
+        );
+
+        // a reservation in the corner moves the toasts out of the way
+        shared_state
+            .overlay_layout
+            .reserve("minimap", ClipRect::new(8, 0, 8, 2));
+        component.update(UpdateInfo::at(later), &mut shared_state);
+        let lines = render(&component, &shared_state);
+        assert_eq!(lines[3], "      i Saved!  ");
+        let mut renderer = DisplayRenderer::new_with_sink(16, 4, vec![]);
+        component.render(&mut renderer, &shared_state, 0);
+        renderer.flush().unwrap();
+        let icon = renderer.previous_frame().pixel_at(6, 3);
+        assert_eq!(icon.color, Color::Rgb(palette::INFO));
+    }
+}
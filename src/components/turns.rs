@@ -0,0 +1,106 @@
+//! Integration of turn-based gameplay with the real-time game loop.
+//!
+//! # The pattern
+//! In a turn-based game, components fall into two groups:
+//! * **Simulation** components (AI, combat, world updates) should only advance the world when
+//!   the player has taken their turn. They check [`TurnState::is_world_turn`] in their `update`
+//!   and return early otherwise.
+//! * **Presentation** components (rendering, animations, UI) run every frame as usual.
+//!
+//! The player's input component checks [`TurnState::is_accepting_input`], and once the
+//! player's input resolved into an action, calls [`TurnState::end_player_turn`].
+//! The [`TurnSystemComponent`] then starts a world turn in the next frame, during which
+//! [`TurnState::is_world_turn`] returns true for exactly one frame.
+//!
+//! Simulation components may block player input for a while, e.g. while an attack animation
+//! plays, using [`TurnState::block_until`]. The blocking time is measured in the game time
+//! tracked by the [`TurnSystemComponent`], see [`TurnState::time`].
+//!
+//! For scheduling multiple actors with different speeds and resolving their actions in order,
+//! see [`TurnScheduler`] and [`ActionQueue`].
+//!
+//! The [`TurnSystemComponent`] inserts the [`TurnState`] extension in its setup, and must appear
+//! in the update order before any component that reads it. See `examples/turns.rs` for a full
+//! example.
+//!
+//! [`TurnScheduler`]: crate::util::turns::TurnScheduler
+//! [`ActionQueue`]: crate::util::turns::ActionQueue
+
+use crate::{Component, SetupInfo, SharedState, UpdateInfo};
+
+/// The state of the turn system, kept as an extension of the [`SharedState`], e.g.
+/// `shared_state.ext_expect::<TurnState>("AiComponent")`.
+///
+/// Managed by the [`TurnSystemComponent`].
+#[derive(Debug, Clone, Default)]
+pub struct TurnState {
+    world_turn: bool,
+    player_turn_ended: bool,
+    blocked_until: f64,
+    time: f64,
+    turn: u64,
+}
+
+impl TurnState {
+    /// Creates a new turn state, waiting for the player's first turn.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns true during the single frame in which the world should advance by one turn.
+    pub fn is_world_turn(&self) -> bool {
+        self.world_turn
+    }
+
+    /// Returns true if the player may act, i.e., no world turn is pending or running and
+    /// nothing blocks input.
+    pub fn is_accepting_input(&self) -> bool {
+        !self.world_turn && !self.player_turn_ended && self.time >= self.blocked_until
+    }
+
+    /// Ends the player's turn. The world advances in the next frame.
+    pub fn end_player_turn(&mut self) {
+        self.player_turn_ended = true;
+    }
+
+    /// Blocks player input and further world turns until the given game time, e.g. while an
+    /// animation plays. Earlier times than an existing block have no effect.
+    pub fn block_until(&mut self, time: f64) {
+        self.blocked_until = self.blocked_until.max(time);
+    }
+
+    /// Blocks player input and further world turns for `duration` seconds from now.
+    pub fn block_for(&mut self, duration: f64) {
+        self.block_until(self.time + duration);
+    }
+
+    /// Returns the game time in seconds, i.e. the sum of all frames' `dt`.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// Returns the number of world turns that have started so far.
+    pub fn turn(&self) -> u64 {
+        self.turn
+    }
+}
+
+/// A component that drives the [`TurnState`].
+pub struct TurnSystemComponent;
+
+impl<S> Component<S> for TurnSystemComponent {
+    fn setup(&mut self, _setup_info: &SetupInfo, shared_state: &mut SharedState<S>) {
+        shared_state.extensions.insert(TurnState::new());
+    }
+
+    fn update(&mut self, update_info: UpdateInfo, shared_state: &mut SharedState<S>) {
+        let turns = shared_state.ext_or_default::<TurnState>();
+        turns.time += update_info.dt;
+        turns.world_turn = false;
+        if turns.player_turn_ended && turns.time >= turns.blocked_until {
+            turns.player_turn_ended = false;
+            turns.world_turn = true;
+            turns.turn += 1;
+        }
+    }
+}
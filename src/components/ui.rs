@@ -1,10 +1,13 @@
 use crate::components::Component;
-use crate::rendering::pixel::Pixel;
+use crate::rendering::render::{HalfBlockDisplayRender, Render};
 use crate::rendering::renderer::Renderer;
 use crate::{BreakingAction, SharedState, UpdateInfo};
 use crossterm::event::{Event, MouseButton, MouseEventKind};
 use std::collections::HashMap;
 
+pub mod layout;
+pub mod widgets;
+
 // TODO: it's also problematic that the SharedState contains mouse positions which are in the global frame and not the local one
 
 pub trait UiElement<S = ()> {
@@ -22,6 +25,17 @@ pub trait UiElement<S = ()> {
 
     fn on_resize(&mut self, width: usize, height: usize, shared_state: &mut SharedState<S>) {}
 
+    /// The size the element asks a [layout container](layout) for. Elements that fill the space
+    /// they are given, like [`layout::Align`], return the size of their content instead.
+    fn layout_size(&self) -> (usize, usize) {
+        self.get_size()
+    }
+
+    /// Called with the space available to the element: by a [layout container](layout) with the
+    /// slot it assigns to the element, and for a window with the terminal space right of and
+    /// below its anchor, when it is added and whenever the terminal resizes.
+    fn on_layout(&mut self, _width: usize, _height: usize, _shared_state: &mut SharedState<S>) {}
+
     fn on_event(
         &mut self,
         event: Event,
@@ -32,7 +46,75 @@ pub trait UiElement<S = ()> {
 
     fn update(&mut self, shared_state: &mut SharedState<S>) {}
 
-    fn render(&self, renderer: &mut dyn Renderer, shared_state: &SharedState<S>, depth_base: i32);
+    /// Called when the window of the element gains or loses the focus, which changes when a
+    /// window is clicked. Only the focused window receives key events.
+    fn on_focus_change(&mut self, _focused: bool, _shared_state: &mut SharedState<S>) {}
+
+    /// Called every frame with the mouse position in element coordinates while the mouse is over
+    /// the window and no other window is on top of it, `None` otherwise.
+    fn on_hover(&mut self, _position: Option<(usize, usize)>, _shared_state: &mut SharedState<S>) {}
+
+    /// Called with the scale of a window added with [`UiProxy::add_scaled_window`], when it is
+    /// added and whenever the scale changes with the terminal size.
+    fn on_scale(&mut self, _scale: usize, _shared_state: &mut SharedState<S>) {}
+
+    /// Draws the art of a window added with [`UiProxy::add_scaled_window`] at its design
+    /// resolution. `hbd` has the design size, and is scaled up to the window's scale afterwards.
+    ///
+    /// Mouse coordinates passed to scaled windows are in this design space, with the row being
+    /// the top half-block pixel of the cell.
+    fn render_hbd(&self, _hbd: &mut HalfBlockDisplayRender, _shared_state: &SharedState<S>) {}
+
+    /// Draws the window in terminal cells. Scaled windows are drawn after their
+    /// [`render_hbd`](UiElement::render_hbd) art, e.g. for text on top of it.
+    fn render(
+        &self,
+        _renderer: &mut dyn Renderer,
+        _shared_state: &SharedState<S>,
+        _depth_base: i32,
+    ) {
+    }
+}
+
+/// How the scale of a window with a design size follows the terminal size, see
+/// [`UiProxy::add_scaled_window`]. Scales are integers, so art stays crisp.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScalePolicy {
+    /// Always the given scale.
+    FixedScale(usize),
+    /// The largest scale at which the window is at most the given percentage of the terminal's
+    /// width.
+    FitWidthPercent(f64),
+    /// The largest scale at which the window fits into the terminal, clamped to `min..=max`.
+    AutoInteger { min: usize, max: usize },
+}
+
+impl ScalePolicy {
+    /// Returns the scale for a window of `design_size` half-block pixels on a terminal of
+    /// `screen_size` cells. The scale is at least 1.
+    pub fn scale_for(&self, design_size: (usize, usize), screen_size: (usize, usize)) -> usize {
+        let (design_width, design_height) = (design_size.0.max(1), design_size.1.max(1));
+        // in half-block pixels
+        let (screen_width, screen_height) = (screen_size.0, 2 * screen_size.1);
+        let scale = match *self {
+            ScalePolicy::FixedScale(scale) => scale,
+            ScalePolicy::FitWidthPercent(percent) => {
+                (screen_width as f64 * percent / 100.0 / design_width as f64).floor() as usize
+            }
+            ScalePolicy::AutoInteger { min, max } => {
+                let fitting = (screen_width / design_width).min(screen_height / design_height);
+                fitting.min(max).max(min)
+            }
+        };
+        scale.max(1)
+    }
+}
+
+struct WindowScaling {
+    design_width: usize,
+    design_height: usize,
+    policy: ScalePolicy,
+    scale: usize,
 }
 
 struct Window<S> {
@@ -41,62 +123,91 @@ struct Window<S> {
     anchor_x: i64,
     anchor_y: i64,
     element: Box<dyn UiElement<S>>,
+    // only for windows with a design size
+    scaling: Option<WindowScaling>,
 }
 
 impl<S> Window<S> {
-    fn render(&self, renderer: &mut dyn Renderer, shared_state: &SharedState<S>, depth_base: i32) {
-        // Make any render calls offset by the anchor and capped to the size
-        let (width, height) = self.element.get_size();
-
-        struct OffsetRenderer<'a> {
-            renderer: &'a mut dyn Renderer,
-            anchor_x: i64,
-            anchor_y: i64,
-            width: usize,
-            height: usize,
+    /// The size in terminal cells.
+    fn size(&self) -> (usize, usize) {
+        match &self.scaling {
+            Some(scaling) => (
+                scaling.design_width * scaling.scale,
+                (scaling.design_height * scaling.scale).div_ceil(2),
+            ),
+            None => self.element.get_size(),
         }
+    }
 
-        impl<'a> Renderer for OffsetRenderer<'a> {
-            fn render_pixel(&mut self, x: usize, y: usize, pixel: Pixel, depth: i32) {
-                if x >= self.width || y >= self.height {
-                    return;
-                }
-                let xi = x as i64;
-                let yi = y as i64;
-                self.renderer.render_pixel(
-                    (xi + self.anchor_x) as usize, // TODO: fix clamping if this is below zero
-                    (yi + self.anchor_y) as usize,
-                    pixel,
-                    depth,
-                );
-            }
+    /// Maps window coordinates in cells to the coordinates the element expects.
+    fn to_element(&self, x: usize, y: usize) -> (usize, usize) {
+        match &self.scaling {
+            Some(scaling) => (x / scaling.scale, 2 * y / scaling.scale),
+            None => (x, y),
         }
+    }
 
-        let mut offset_renderer = OffsetRenderer {
-            renderer,
-            anchor_x: self.anchor_x,
-            anchor_y: self.anchor_y,
-            width,
-            height,
+    fn update_scale(&mut self, screen_size: (usize, usize), shared_state: &mut SharedState<S>) {
+        let Some(scaling) = &mut self.scaling else {
+            return;
         };
+        let scale = scaling
+            .policy
+            .scale_for((scaling.design_width, scaling.design_height), screen_size);
+        if scale != scaling.scale {
+            scaling.scale = scale;
+            self.element.on_scale(scale, shared_state);
+        }
+    }
 
+    /// Tells the element the terminal space right of and below the anchor.
+    fn layout(&mut self, screen_size: (usize, usize), shared_state: &mut SharedState<S>) {
+        // scaled windows have a size of their own
+        if self.scaling.is_some() {
+            return;
+        }
+        let width = (screen_size.0 as i64 - self.anchor_x).max(0) as usize;
+        let height = (screen_size.1 as i64 - self.anchor_y).max(0) as usize;
+        self.element.on_layout(width, height, shared_state);
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, shared_state: &SharedState<S>, depth_base: i32) {
+        // Make any render calls offset by the anchor and capped to the size
+        let (width, height) = self.size();
+        let mut offset_renderer = renderer.with_offset(self.anchor_x, self.anchor_y);
+        offset_renderer.push_clip(0, 0, width, height);
+        if let Some(scaling) = &self.scaling {
+            // compose at design resolution, then scale up with nearest neighbor
+            let mut design =
+                HalfBlockDisplayRender::new(scaling.design_width, scaling.design_height);
+            self.element.render_hbd(&mut design, shared_state);
+            let mut scaled = HalfBlockDisplayRender::new(width, 2 * height);
+            scaled.blit_scaled(&design, 0, 0, scaling.scale);
+            scaled.render(&mut offset_renderer, 0, 0, depth_base);
+        }
         self.element.render(&mut offset_renderer, shared_state, depth_base);
+        offset_renderer.pop_clip();
     }
 
     fn is_hover_drag(&self, x: usize, y: usize) -> bool {
-        let (width, height) = self.element.get_size();
+        let (width, height) = self.size();
         let max_x = self.anchor_x + width as i64;
         let max_y = self.anchor_y + height as i64;
         if x as i64 >= max_x || y as i64 >= max_y {
             return false;
         }
-        self.element.is_hover_drag(
+        let (x, y) = self.to_element(
             (x as i64 - self.anchor_x) as usize,
             (y as i64 - self.anchor_y) as usize,
-        )
+        );
+        self.element.is_hover_drag(x, y)
     }
 
     fn is_resizing_drag(&self, x: usize, y: usize) -> bool {
+        // the size of scaled windows follows their scale
+        if self.scaling.is_some() {
+            return false;
+        }
         let (width, height) = self.element.get_size();
         let max_x = self.anchor_x + width as i64;
         let max_y = self.anchor_y + height as i64;
@@ -110,7 +221,7 @@ impl<S> Window<S> {
     }
 
     fn is_hover(&self, x: usize, y: usize) -> bool {
-        let (width, height) = self.element.get_size();
+        let (width, height) = self.size();
         let max_x = self.anchor_x + width as i64;
         let max_y = self.anchor_y + height as i64;
         let xi = x as i64;
@@ -148,12 +259,13 @@ impl<S> Window<S> {
                 if window_x < 0 || window_y < 0 {
                     return None;
                 }
-                let (width, height) = self.element.get_size();
+                let (width, height) = self.size();
                 if window_x >= width as i64 || window_y >= height as i64 {
                     return None;
                 }
-                me.column = window_x as u16;
-                me.row = window_y as u16;
+                let (element_x, element_y) = self.to_element(window_x as usize, window_y as usize);
+                me.column = element_x as u16;
+                me.row = element_y as u16;
 
                 Event::Mouse(me)
             },
@@ -169,7 +281,7 @@ impl<S> Window<S> {
 }
 
 pub struct UiProxy<S> {
-    new_elements: Vec<(String, usize, usize, Box<dyn UiElement<S>>)>,
+    new_elements: Vec<NewWindow<S>>,
     anchor_sets: Vec<(String, usize, usize)>,
 }
 
@@ -183,7 +295,27 @@ impl<S> UiProxy<S> {
 
     pub fn add_window(&mut self, key: impl Into<String>, anchor_x: usize, anchor_y: usize, element: Box<dyn UiElement<S>>) {
         let key = key.into();
-        self.new_elements.push((key, anchor_x, anchor_y, element));
+        self.new_elements.push((key, anchor_x, anchor_y, element, None));
+    }
+
+    /// Adds a window whose art is drawn at `design_size` half-block pixels by
+    /// [`UiElement::render_hbd`] and scaled up by an integer scale chosen by `policy`.
+    ///
+    /// The element is told its scale with [`UiElement::on_scale`], and receives mouse events in
+    /// design coordinates. Its [`get_size`](UiElement::get_size) is ignored.
+    pub fn add_scaled_window(
+        &mut self,
+        key: impl Into<String>,
+        anchor_x: usize,
+        anchor_y: usize,
+        design_size: (usize, usize),
+        policy: ScalePolicy,
+        element: Box<dyn UiElement<S>>,
+    ) {
+        let key = key.into();
+        let scaling = (design_size, policy);
+        self.new_elements
+            .push((key, anchor_x, anchor_y, element, Some(scaling)));
     }
     
     pub fn set_anchor(&mut self, key: impl Into<String>, anchor_x: usize, anchor_y: usize) {
@@ -192,6 +324,14 @@ impl<S> UiProxy<S> {
     }
 }
 
+type NewWindow<S> = (
+    String,
+    usize,
+    usize,
+    Box<dyn UiElement<S>>,
+    Option<((usize, usize), ScalePolicy)>,
+);
+
 struct Dragging {
     // key of the window being dragged
     index: usize,
@@ -225,20 +365,64 @@ impl<S> Ui<S> {
         }
     }
 
-    fn add_window(&mut self, key: String, anchor_x: usize, anchor_y: usize, element: Box<dyn UiElement<S>>) {
+    fn add_window(
+        &mut self,
+        key: String,
+        anchor_x: usize,
+        anchor_y: usize,
+        element: Box<dyn UiElement<S>>,
+        scaling: Option<((usize, usize), ScalePolicy)>,
+    ) -> &mut Window<S> {
         self.highest_index += 1;
         let index = self.highest_index;
-        self.elements.insert(
-            index,
-            Window {
-                index,
-                anchor_x: anchor_x as i64,
-                anchor_y: anchor_y as i64,
-                element,
-            },
-        );
+        let scaling = scaling.map(|((design_width, design_height), policy)| WindowScaling {
+            design_width,
+            design_height,
+            policy,
+            // not a valid scale, so that the element is told its first scale
+            scale: 0,
+        });
         self.keys_to_indices.insert(key, index);
         self.render_order.push(index);
+        self.elements.entry(index).or_insert(Window {
+            index,
+            anchor_x: anchor_x as i64,
+            anchor_y: anchor_y as i64,
+            element,
+            scaling,
+        })
+    }
+
+    fn set_focused(&mut self, focused: Option<usize>, shared_state: &mut SharedState<S>) {
+        if focused == self.focused {
+            return;
+        }
+        if let Some(window) = self.get_mut_focused() {
+            window.element.on_focus_change(false, shared_state);
+        }
+        self.focused = focused;
+        if let Some(window) = self.get_mut_focused() {
+            window.element.on_focus_change(true, shared_state);
+        }
+    }
+
+    /// Tells every window where the mouse is, if it is over the window and not covered.
+    fn hover_all(&mut self, shared_state: &mut SharedState<S>) {
+        let (x, y) = shared_state.mouse_info.last_mouse_pos;
+        let mut covered = false;
+        for index in self.render_order.iter().rev() {
+            let Some(window) = self.elements.get_mut(index) else {
+                continue;
+            };
+            let position = (!covered && window.is_hover(x, y)).then(|| {
+                window.to_element(
+                    (x as i64 - window.anchor_x) as usize,
+                    (y as i64 - window.anchor_y) as usize,
+                )
+            });
+            covered |= position.is_some();
+            window.element.on_hover(position, shared_state);
+        }
     }
 
     fn get_mut_focused(&mut self) -> Option<&mut Window<S>> {
@@ -309,6 +493,10 @@ impl<S> UiComponent<S> {
 }
 
 impl<S: 'static> Component<S> for UiComponent<S> {
+    fn runs_while_paused(&self) -> bool {
+        true
+    }
+
     fn on_event(
         &mut self,
         event: Event,
@@ -345,7 +533,7 @@ impl<S: 'static> Component<S> for UiComponent<S> {
                         // change render order
                         self.ui.render_order_move_to_front(focused_index);
                     };
-                    self.ui.focused = focused;
+                    self.ui.set_focused(focused, shared_state);
                 }
                 if me.kind == MouseEventKind::Up(MouseButton::Left) {
                     self.ui.move_dragging = None;
@@ -390,17 +578,34 @@ impl<S: 'static> Component<S> for UiComponent<S> {
         None
     }
 
+    fn on_resize(&mut self, width: usize, height: usize, shared_state: &mut SharedState<S>) {
+        for window in self.ui.elements.values_mut() {
+            window.update_scale((width, height), shared_state);
+            window.layout((width, height), shared_state);
+        }
+    }
+
     fn update(&mut self, update_info: UpdateInfo, shared_state: &mut SharedState<S>) {
-        for (key, x, y, element) in shared_state.ui.new_elements.drain(..) {
-            self.ui.add_window(key, x, y, element);
+        let screen_size = (
+            shared_state.display_info.width(),
+            shared_state.display_info.height(),
+        );
+        let new_elements = std::mem::take(&mut shared_state.ui.new_elements);
+        for (key, x, y, element, scaling) in new_elements {
+            let window = self.ui.add_window(key, x, y, element, scaling);
+            window.update_scale(screen_size, shared_state);
+            window.layout(screen_size, shared_state);
         }
-        for (key, x, y) in shared_state.ui.anchor_sets.drain(..) {
+        let anchor_sets = std::mem::take(&mut shared_state.ui.anchor_sets);
+        for (key, x, y) in anchor_sets {
             let index = self.ui.keys_to_indices.get(&key).unwrap();
             let window = self.ui.elements.get_mut(index).unwrap();
             window.anchor_x = x as i64;
             window.anchor_y = y as i64;
+            window.layout(screen_size, shared_state);
         }
 
+        self.ui.hover_all(shared_state);
         self.ui.update_all(shared_state);
     }
 
@@ -408,3 +613,120 @@ impl<S: 'static> Component<S> for UiComponent<S> {
         self.ui.render(renderer, shared_state, depth_base);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Game;
+    use crate::rendering::color::Color;
+    use crossterm::event::{KeyModifiers, MouseEvent};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_scale_policies() {
+        let design = (40, 20);
+        let scales = |policy: ScalePolicy| {
+            [(30, 10), (80, 24), (200, 30), (300, 100)].map(|size| policy.scale_for(design, size))
+        };
+        assert_eq!(scales(ScalePolicy::FixedScale(3)), [3, 3, 3, 3]);
+        assert_eq!(scales(ScalePolicy::FixedScale(0)), [1, 1, 1, 1]);
+        assert_eq!(scales(ScalePolicy::FitWidthPercent(50.0)), [1, 1, 2, 3]);
+        assert_eq!(
+            scales(ScalePolicy::AutoInteger { min: 1, max: 4 }),
+            [1, 2, 3, 4]
+        );
+        assert_eq!(
+            scales(ScalePolicy::AutoInteger { min: 2, max: 8 }),
+            [2, 2, 3, 7]
+        );
+    }
+
+    #[derive(Default)]
+    struct Log {
+        scales: Vec<usize>,
+        clicks: Vec<(u16, u16)>,
+    }
+
+    struct Recorder {
+        log: Rc<RefCell<Log>>,
+        color: Color,
+    }
+
+    impl UiElement for Recorder {
+        fn on_scale(&mut self, scale: usize, _shared_state: &mut SharedState) {
+            self.log.borrow_mut().scales.push(scale);
+        }
+
+        fn on_event(
+            &mut self,
+            event: Event,
+            _shared_state: &mut SharedState,
+        ) -> Option<BreakingAction> {
+            if let Event::Mouse(me) = event {
+                self.log.borrow_mut().clicks.push((me.column, me.row));
+            }
+            None
+        }
+
+        fn render_hbd(&self, hbd: &mut HalfBlockDisplayRender, _shared_state: &SharedState) {
+            hbd.set_color(0, 0, self.color);
+        }
+    }
+
+    #[test]
+    fn test_mixed_scale_windows() {
+        let mut game = Game::<Vec<u8>, ()>::new_headless(80, 24);
+        game.add_component(Box::new(UiComponent::new()));
+        let red = Color::Rgb([255, 0, 0]);
+        let blue = Color::Rgb([0, 0, 255]);
+        let fixed = Rc::new(RefCell::new(Log::default()));
+        let auto = Rc::new(RefCell::new(Log::default()));
+        let ui = &mut game.shared_state_mut().ui;
+        let element = Recorder {
+            log: fixed.clone(),
+            color: red,
+        };
+        let policy = ScalePolicy::FixedScale(3);
+        ui.add_scaled_window("fixed", 0, 0, (10, 4), policy, Box::new(element));
+        let element = Recorder {
+            log: auto.clone(),
+            color: blue,
+        };
+        let policy = ScalePolicy::AutoInteger { min: 1, max: 10 };
+        ui.add_scaled_window("auto", 40, 0, (10, 4), policy, Box::new(element));
+        game.run_frames(1).unwrap();
+        assert_eq!(fixed.borrow().scales, [3]);
+        assert_eq!(auto.borrow().scales, [8]);
+
+        // the top left design pixel covers 3x3 and 8x8 half-block pixels
+        let frame = game.frame();
+        let cell = |x, y| (frame.pixel_at(x, y).c, frame.pixel_at(x, y).color);
+        assert_eq!(cell(2, 0), ('█', red));
+        assert_eq!(cell(2, 1), ('▀', red));
+        assert_ne!(frame.pixel_at(3, 0).color, red);
+        assert_eq!(cell(47, 3), ('█', blue));
+        assert_ne!(frame.pixel_at(48, 0).color, blue);
+
+        // mouse positions arrive in design coordinates
+        let click = |column, row| {
+            Event::Mouse(MouseEvent {
+                kind: MouseEventKind::Down(MouseButton::Left),
+                column,
+                row,
+                modifiers: KeyModifiers::NONE,
+            })
+        };
+        game.push_event(click(7, 2));
+        game.push_event(click(57, 3));
+        game.run_frames(1).unwrap();
+        assert_eq!(fixed.borrow().clicks, [(2, 1)]);
+        assert_eq!(auto.borrow().clicks, [(2, 0)]);
+
+        // only the automatic scale follows the terminal size
+        game.push_event(Event::Resize(40, 10));
+        game.run_frames(1).unwrap();
+        assert_eq!(fixed.borrow().scales, [3]);
+        assert_eq!(auto.borrow().scales, [8, 4]);
+    }
+}
@@ -0,0 +1,564 @@
+//! Layout containers for the [`UiComponent`](super::UiComponent): [`VStack`] and [`HStack`]
+//! place their children below or next to each other, [`Align`] positions its child in the
+//! space it is given, and [`Fixed`] gives its child a fixed size.
+//!
+//! Containers are [`UiElement`]s themselves, so a whole panel is added as a single window and
+//! containers can be nested:
+//! ```rust
+//! use teng::SharedState;
+//! use teng::components::ui::layout::{Align, Alignment, Fixed, HStack, VStack};
+//! use teng::components::ui::widgets::{Button, Checkbox};
+//!
+//! fn setup(shared_state: &mut SharedState) {
+//!     let buttons = HStack::new()
+//!         .with_spacing(2)
+//!         .with(Box::new(Button::new("Apply")))
+//!         .with(Box::new(Button::new("Cancel")));
+//!     let panel = VStack::new()
+//!         .with_padding(1)
+//!         .with(Box::new(Checkbox::new("Fullscreen")))
+//!         .with(Box::new(Fixed::new(30, 1, Box::new(buttons))));
+//!     let centered = Align::new(Box::new(panel))
+//!         .with_horizontal(Alignment::Center)
+//!         .with_vertical(Alignment::Center);
+//!     shared_state.ui.add_window("settings", 0, 0, Box::new(centered));
+//! }
+//! ```
+//! Containers lay out their children every frame after updating them, so children may change
+//! their size at any time. A child that is clicked gets the focus and receives the key events
+//! of the container. Mouse events and hover positions are passed to the child under the mouse,
+//! in the child's coordinates.
+//!
+//! Elements learn the space they are given with [`UiElement::on_layout`]: a container passes
+//! the slot it assigns to a child, and the [`UiComponent`](super::UiComponent) passes the
+//! terminal space right of and below a window's anchor when the window is added and whenever
+//! the terminal resizes.
+
+use crate::components::ui::UiElement;
+use crate::rendering::renderer::Renderer;
+use crate::{BreakingAction, SharedState};
+use crossterm::event::{Event, MouseButton, MouseEventKind};
+
+/// Where content is placed in a larger space, along one axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Alignment {
+    #[default]
+    Start,
+    Center,
+    End,
+}
+
+impl Alignment {
+    /// Returns the offset of `content` cells in `space` cells. Content that does not fit starts
+    /// at 0.
+    pub fn offset(self, content: usize, space: usize) -> usize {
+        let free = space.saturating_sub(content);
+        match self {
+            Alignment::Start => 0,
+            Alignment::Center => free / 2,
+            Alignment::End => free,
+        }
+    }
+}
+
+/// A child element and the rectangle it was laid out in, relative to its container.
+struct Slot<S> {
+    element: Box<dyn UiElement<S>>,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+impl<S> Slot<S> {
+    fn new(element: Box<dyn UiElement<S>>) -> Self {
+        Self {
+            element,
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+        }
+    }
+
+    fn contains(&self, x: usize, y: usize) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+
+    /// Moves the slot, and tells the element if its size changed.
+    fn place(
+        &mut self,
+        (x, y): (usize, usize),
+        (width, height): (usize, usize),
+        shared_state: &mut SharedState<S>,
+    ) {
+        (self.x, self.y) = (x, y);
+        if (width, height) != (self.width, self.height) {
+            (self.width, self.height) = (width, height);
+            self.element.on_layout(width, height, shared_state);
+        }
+    }
+}
+
+/// The children of a container, and which of them has the focus.
+struct Children<S> {
+    slots: Vec<Slot<S>>,
+    focused: Option<usize>,
+}
+
+impl<S> Children<S> {
+    fn new() -> Self {
+        Self {
+            slots: vec![],
+            focused: None,
+        }
+    }
+
+    fn at(&self, x: usize, y: usize) -> Option<usize> {
+        self.slots.iter().rposition(|slot| slot.contains(x, y))
+    }
+
+    fn set_focused(&mut self, focused: Option<usize>, shared_state: &mut SharedState<S>) {
+        if focused == self.focused {
+            return;
+        }
+        if let Some(old) = self.focused {
+            self.slots[old].element.on_focus_change(false, shared_state);
+        }
+        self.focused = focused;
+        if let Some(new) = focused {
+            self.slots[new].element.on_focus_change(true, shared_state);
+        }
+    }
+
+    fn is_hover_drag(&self, x: usize, y: usize) -> bool {
+        self.at(x, y).is_some_and(|idx| {
+            let slot = &self.slots[idx];
+            slot.element.is_hover_drag(x - slot.x, y - slot.y)
+        })
+    }
+
+    fn is_resizing_drag(&self, x: usize, y: usize) -> bool {
+        self.at(x, y).is_some_and(|idx| {
+            let slot = &self.slots[idx];
+            slot.element.is_resizing_drag(x - slot.x, y - slot.y)
+        })
+    }
+
+    fn on_event(
+        &mut self,
+        event: Event,
+        shared_state: &mut SharedState<S>,
+    ) -> Option<BreakingAction> {
+        match event {
+            Event::Mouse(mut me) => {
+                let idx = self.at(me.column as usize, me.row as usize);
+                if me.kind == MouseEventKind::Down(MouseButton::Left) {
+                    self.set_focused(idx, shared_state);
+                }
+                let slot = &mut self.slots[idx?];
+                me.column -= slot.x as u16;
+                me.row -= slot.y as u16;
+                slot.element.on_event(Event::Mouse(me), shared_state)
+            }
+            _ => {
+                let slot = &mut self.slots[self.focused?];
+                slot.element.on_event(event, shared_state)
+            }
+        }
+    }
+
+    fn on_focus_change(&mut self, focused: bool, shared_state: &mut SharedState<S>) {
+        // the child that is clicked next gets the focus
+        if !focused {
+            self.set_focused(None, shared_state);
+        }
+    }
+
+    fn on_hover(&mut self, position: Option<(usize, usize)>, shared_state: &mut SharedState<S>) {
+        let hovered = position.and_then(|(x, y)| self.at(x, y));
+        for (idx, slot) in self.slots.iter_mut().enumerate() {
+            let position = position
+                .filter(|_| hovered == Some(idx))
+                .map(|(x, y)| (x - slot.x, y - slot.y));
+            slot.element.on_hover(position, shared_state);
+        }
+    }
+
+    fn update(&mut self, shared_state: &mut SharedState<S>) {
+        for slot in &mut self.slots {
+            slot.element.update(shared_state);
+        }
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, shared_state: &SharedState<S>, depth_base: i32) {
+        for slot in &self.slots {
+            let mut offset_renderer = renderer.with_offset(slot.x as i64, slot.y as i64);
+            offset_renderer.push_clip(0, 0, slot.width, slot.height);
+            slot.element
+                .render(&mut offset_renderer, shared_state, depth_base);
+            offset_renderer.pop_clip();
+        }
+    }
+}
+
+/// Forwards the [`UiElement`] methods that every container handles the same way to its
+/// `children` field.
+macro_rules! forward_to_children {
+    () => {
+        fn is_hover_drag(&self, x: usize, y: usize) -> bool {
+            self.children.is_hover_drag(x, y)
+        }
+
+        fn is_resizing_drag(&self, x: usize, y: usize) -> bool {
+            self.children.is_resizing_drag(x, y)
+        }
+
+        fn on_event(
+            &mut self,
+            event: Event,
+            shared_state: &mut SharedState<S>,
+        ) -> Option<BreakingAction> {
+            self.children.on_event(event, shared_state)
+        }
+
+        fn on_focus_change(&mut self, focused: bool, shared_state: &mut SharedState<S>) {
+            self.children.on_focus_change(focused, shared_state);
+        }
+
+        fn on_hover(
+            &mut self,
+            position: Option<(usize, usize)>,
+            shared_state: &mut SharedState<S>,
+        ) {
+            self.children.on_hover(position, shared_state);
+        }
+
+        fn render(
+            &self,
+            renderer: &mut dyn Renderer,
+            shared_state: &SharedState<S>,
+            depth_base: i32,
+        ) {
+            self.children.render(renderer, shared_state, depth_base);
+        }
+    };
+}
+
+/// Places its children below each other if `VERTICAL`, next to each other otherwise. Use the
+/// [`VStack`] and [`HStack`] aliases.
+///
+/// Every child gets its own size along the stack, and the size of the largest child across
+/// it. Wrap a child in an [`Align`] to place it in that space.
+pub struct Stack<S, const VERTICAL: bool> {
+    children: Children<S>,
+    spacing: usize,
+    padding: usize,
+    size: (usize, usize),
+}
+
+/// A [`Stack`] whose children are placed from top to bottom.
+pub type VStack<S = ()> = Stack<S, true>;
+
+/// A [`Stack`] whose children are placed from left to right.
+pub type HStack<S = ()> = Stack<S, false>;
+
+impl<S, const VERTICAL: bool> Stack<S, VERTICAL> {
+    pub fn new() -> Self {
+        Self {
+            children: Children::new(),
+            spacing: 0,
+            padding: 0,
+            size: (0, 0),
+        }
+    }
+
+    /// Adds a child after the existing ones.
+    pub fn with(mut self, element: Box<dyn UiElement<S>>) -> Self {
+        self.children.slots.push(Slot::new(element));
+        self
+    }
+
+    /// Sets the number of empty cells between two children.
+    pub fn with_spacing(mut self, spacing: usize) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    /// Sets the number of empty cells around the children, on every side.
+    pub fn with_padding(mut self, padding: usize) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Splits a size into the size along the stack and across it.
+    fn main_cross((width, height): (usize, usize)) -> (usize, usize) {
+        if VERTICAL {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    fn layout(&mut self, shared_state: &mut SharedState<S>) {
+        let slots = &mut self.children.slots;
+        let cross = slots
+            .iter()
+            .map(|slot| Self::main_cross(slot.element.layout_size()).1)
+            .max()
+            .unwrap_or(0);
+        let mut main = self.padding;
+        for (idx, slot) in slots.iter_mut().enumerate() {
+            if idx > 0 {
+                main += self.spacing;
+            }
+            let (len, _) = Self::main_cross(slot.element.layout_size());
+            // main_cross is its own inverse
+            let position = Self::main_cross((main, self.padding));
+            let size = Self::main_cross((len, cross));
+            slot.place(position, size, shared_state);
+            main += len;
+        }
+        self.size = Self::main_cross((main + self.padding, cross + 2 * self.padding));
+    }
+}
+
+impl<S, const VERTICAL: bool> Default for Stack<S, VERTICAL> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S, const VERTICAL: bool> UiElement<S> for Stack<S, VERTICAL> {
+    fn get_size(&self) -> (usize, usize) {
+        self.size
+    }
+
+    fn update(&mut self, shared_state: &mut SharedState<S>) {
+        self.children.update(shared_state);
+        self.layout(shared_state);
+    }
+
+    forward_to_children!();
+}
+
+/// Places its child in the space it is given, e.g. centered in a [`Stack`] or, as a window, in
+/// the terminal.
+///
+/// Takes up all the space it is given, but asks containers only for the size of its child.
+pub struct Align<S = ()> {
+    children: Children<S>,
+    horizontal: Alignment,
+    vertical: Alignment,
+    available: Option<(usize, usize)>,
+}
+
+impl<S> Align<S> {
+    /// Aligns the child at the top left, see [`with_horizontal`](Self::with_horizontal) and
+    /// [`with_vertical`](Self::with_vertical).
+    pub fn new(element: Box<dyn UiElement<S>>) -> Self {
+        let mut children = Children::new();
+        children.slots.push(Slot::new(element));
+        Self {
+            children,
+            horizontal: Alignment::Start,
+            vertical: Alignment::Start,
+            available: None,
+        }
+    }
+
+    pub fn with_horizontal(mut self, alignment: Alignment) -> Self {
+        self.horizontal = alignment;
+        self
+    }
+
+    pub fn with_vertical(mut self, alignment: Alignment) -> Self {
+        self.vertical = alignment;
+        self
+    }
+
+    fn layout(&mut self, shared_state: &mut SharedState<S>) {
+        let (width, height) = self.get_size();
+        let slot = &mut self.children.slots[0];
+        let (child_width, child_height) = slot.element.layout_size();
+        let x = self.horizontal.offset(child_width, width);
+        let y = self.vertical.offset(child_height, height);
+        let size = (child_width.min(width), child_height.min(height));
+        slot.place((x, y), size, shared_state);
+    }
+}
+
+impl<S> UiElement<S> for Align<S> {
+    fn get_size(&self) -> (usize, usize) {
+        self.available
+            .unwrap_or_else(|| self.children.slots[0].element.layout_size())
+    }
+
+    fn layout_size(&self) -> (usize, usize) {
+        self.children.slots[0].element.layout_size()
+    }
+
+    fn on_layout(&mut self, width: usize, height: usize, shared_state: &mut SharedState<S>) {
+        self.available = Some((width, height));
+        self.layout(shared_state);
+    }
+
+    fn update(&mut self, shared_state: &mut SharedState<S>) {
+        self.children.update(shared_state);
+        self.layout(shared_state);
+    }
+
+    forward_to_children!();
+}
+
+/// Gives its child a fixed size, regardless of the size of the child or the space it is given.
+pub struct Fixed<S = ()> {
+    children: Children<S>,
+    size: (usize, usize),
+}
+
+impl<S> Fixed<S> {
+    pub fn new(width: usize, height: usize, element: Box<dyn UiElement<S>>) -> Self {
+        let mut children = Children::new();
+        children.slots.push(Slot::new(element));
+        Self {
+            children,
+            size: (width, height),
+        }
+    }
+}
+
+impl<S> UiElement<S> for Fixed<S> {
+    fn get_size(&self) -> (usize, usize) {
+        self.size
+    }
+
+    fn update(&mut self, shared_state: &mut SharedState<S>) {
+        self.children.update(shared_state);
+        self.children.slots[0].place((0, 0), self.size, shared_state);
+    }
+
+    forward_to_children!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Game;
+    use crate::components::ui::UiComponent;
+    use crate::components::ui::widgets::Button;
+    use crate::rendering::pixel::Pixel;
+    use crossterm::event::{KeyModifiers, MouseEvent};
+
+    /// A block of its glyph.
+    struct Block(char, usize, usize);
+
+    impl UiElement for Block {
+        fn get_size(&self) -> (usize, usize) {
+            (self.1, self.2)
+        }
+
+        fn render(
+            &self,
+            renderer: &mut dyn Renderer,
+            _shared_state: &SharedState,
+            depth_base: i32,
+        ) {
+            for y in 0..self.2 {
+                for x in 0..self.1 {
+                    renderer.render_pixel(x, y, Pixel::new(self.0), depth_base);
+                }
+            }
+        }
+    }
+
+    fn text(game: &Game<Vec<u8>, ()>) -> Vec<String> {
+        let frame = game.frame();
+        (0..frame.height())
+            .map(|y| (0..frame.width()).map(|x| frame.pixel_at(x, y).c).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_nested_stacks() {
+        let mut game = Game::<Vec<u8>, ()>::new_headless(12, 8);
+        game.add_component(Box::new(UiComponent::new()));
+        let row = HStack::new()
+            .with_spacing(1)
+            .with(Box::new(Block('a', 2, 1)))
+            .with(Box::new(Block('b', 1, 2)));
+        let column = VStack::new()
+            .with_padding(1)
+            .with(Box::new(row))
+            .with(Box::new(
+                Align::new(Box::new(Block('c', 1, 1))).with_horizontal(Alignment::End),
+            ))
+            .with(Box::new(Fixed::new(3, 1, Box::new(Block('d', 5, 5)))));
+        let centered = Align::new(Box::new(column))
+            .with_horizontal(Alignment::Center)
+            .with_vertical(Alignment::End);
+        let ui = &mut game.shared_state_mut().ui;
+        ui.add_window("panel", 1, 0, Box::new(centered));
+        game.run_frames(2).unwrap();
+        #[rustfmt::skip]
+        assert_eq!(text(&game), [
+            "            ",
+            "            ",
+            "            ",
+            "    aa b    ",
+            "       b    ",
+            "       c    ",
+            "    ddd     ",
+            "            ",
+        ]);
+
+        // the layout follows the terminal size
+        game.push_event(Event::Resize(8, 8));
+        game.run_frames(1).unwrap();
+        assert_eq!(text(&game)[3], "  aa b  ");
+    }
+
+    #[test]
+    fn test_events_reach_the_child_under_the_mouse() {
+        let mut game = Game::<Vec<u8>, ()>::new_headless(20, 3);
+        game.add_component(Box::new(UiComponent::new()));
+        let ok = Button::new("OK");
+        let cancel = Button::new("Cancel");
+        let buttons = HStack::new()
+            .with_spacing(1)
+            .with(Box::new(ok.clone()))
+            .with(Box::new(cancel.clone()));
+        game.shared_state_mut()
+            .ui
+            .add_window("buttons", 2, 1, Box::new(buttons));
+        game.run_frames(1).unwrap();
+        assert_eq!(text(&game)[1], "  [ OK ] [ Cancel ] ");
+
+        let click = |column| {
+            Event::Mouse(MouseEvent {
+                kind: MouseEventKind::Down(MouseButton::Left),
+                column,
+                row: 1,
+                modifiers: KeyModifiers::NONE,
+            })
+        };
+        game.push_event(click(12));
+        game.run_frames(1).unwrap();
+        assert!(cancel.was_clicked());
+        assert!(!ok.was_clicked());
+
+        // key events go to the focused child
+        let enter = Event::Key(crossterm::event::KeyEvent::new(
+            crossterm::event::KeyCode::Enter,
+            KeyModifiers::NONE,
+        ));
+        game.push_event(enter.clone());
+        game.run_frames(1).unwrap();
+        assert!(cancel.was_clicked());
+        game.push_event(click(3));
+        game.push_event(enter);
+        game.run_frames(1).unwrap();
+        assert!(ok.was_clicked());
+        assert!(!cancel.was_clicked());
+    }
+}
@@ -0,0 +1,801 @@
+//! Standard controls for the [`UiComponent`](super::UiComponent): [`Button`], [`Checkbox`],
+//! [`Slider`] and [`TextInput`].
+//!
+//! Every widget is a [`UiElement`] that is added as its own window. Widgets are cheap handles to
+//! shared state, so a clone can be kept to poll the widget in `update`:
+//! ```rust
+//! use teng::SharedState;
+//! use teng::components::ui::widgets::{Button, Slider};
+//!
+//! struct Menu {
+//!     apply: Button,
+//!     volume: Slider,
+//! }
+//!
+//! impl Menu {
+//!     fn new(shared_state: &mut SharedState) -> Self {
+//!         let apply = Button::new("Apply");
+//!         let volume = Slider::new(0.0, 100.0, 5.0).with_value(50.0);
+//!         shared_state.ui.add_window("apply", 2, 4, Box::new(apply.clone()));
+//!         shared_state.ui.add_window("volume", 2, 2, Box::new(volume.clone()));
+//!         Self { apply, volume }
+//!     }
+//!
+//!     fn update(&mut self) {
+//!         if self.apply.was_clicked() {
+//!             println!("volume: {}", self.volume.value());
+//!         }
+//!     }
+//! }
+//! ```
+//! Clicking a widget focuses it, and only the focused widget receives key events. Widgets
+//! consume the key events they handle, so that e.g. typing a `q` into a [`TextInput`] does not
+//! quit, as long as the [`UiComponent`](super::UiComponent) runs before the components that
+//! would handle the key, see [`Game::add_component_with_priority`](crate::Game::add_component_with_priority).
+//! [`SharedState::pressed_keys`] still records all keys.
+
+use crate::components::ui::UiElement;
+use crate::rendering::pixel::Pixel;
+use crate::rendering::renderer::Renderer;
+use crate::{BreakingAction, SharedState};
+use crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEventKind,
+};
+use std::cell::RefCell;
+use std::rc::Rc;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+const HOVERED_BG: [u8; 3] = [50, 50, 60];
+const FOCUSED_BG: [u8; 3] = [40, 60, 120];
+const ACCENT: [u8; 3] = [120, 180, 255];
+const MUTED: [u8; 3] = [110, 110, 110];
+const FIELD_BG: [u8; 3] = [30, 30, 30];
+
+/// Whether a widget has the focus or the mouse is over it.
+#[derive(Clone, Copy, Debug, Default)]
+struct Highlight {
+    focused: bool,
+    hovered: bool,
+}
+
+impl Highlight {
+    fn bg(&self) -> Option<[u8; 3]> {
+        if self.focused {
+            Some(FOCUSED_BG)
+        } else if self.hovered {
+            Some(HOVERED_BG)
+        } else {
+            None
+        }
+    }
+
+    /// Renders `text` starting at `x` in the highlight's background.
+    fn render_text(
+        &self,
+        text: &str,
+        x: usize,
+        color: Option<[u8; 3]>,
+        renderer: &mut dyn Renderer,
+        depth: i32,
+    ) -> usize {
+        let mut x = x;
+        for c in text.chars() {
+            let mut pixel = Pixel::new(c);
+            if let Some(color) = color {
+                pixel = pixel.with_color(color);
+            }
+            if let Some(bg) = self.bg() {
+                pixel = pixel.with_bg_color(bg);
+            }
+            renderer.render_pixel(x, 0, pixel, depth);
+            x += c.width().unwrap_or(0);
+        }
+        x
+    }
+}
+
+fn is_press(key: &KeyEvent) -> bool {
+    key.kind != KeyEventKind::Release
+}
+
+fn is_left_down(event: &Event) -> bool {
+    matches!(event, Event::Mouse(me) if me.kind == MouseEventKind::Down(MouseButton::Left))
+}
+
+/// Returns true for a press of Enter or Space, which activate buttons and checkboxes.
+fn is_activation(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::Key(key) if is_press(key) && matches!(key.code, KeyCode::Enter | KeyCode::Char(' '))
+    )
+}
+
+type ClickFn<S> = Box<dyn FnMut(&mut SharedState<S>)>;
+
+struct ButtonState<S> {
+    label: String,
+    clicked: bool,
+    on_click: Option<ClickFn<S>>,
+    highlight: Highlight,
+}
+
+/// A button that is clicked with the mouse, or with Enter or Space while focused.
+///
+/// Clicks are either polled with [`Self::was_clicked`] or handled with a callback, see
+/// [`Self::with_on_click`].
+pub struct Button<S = ()> {
+    state: Rc<RefCell<ButtonState<S>>>,
+}
+
+impl<S> Clone for Button<S> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<S> Button<S> {
+    pub fn new(label: impl Into<String>) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(ButtonState {
+                label: label.into(),
+                clicked: false,
+                on_click: None,
+                highlight: Highlight::default(),
+            })),
+        }
+    }
+
+    /// Calls `on_click` on every click, while the event is handled.
+    pub fn with_on_click(self, on_click: impl FnMut(&mut SharedState<S>) + 'static) -> Self {
+        self.state.borrow_mut().on_click = Some(Box::new(on_click));
+        self
+    }
+
+    /// Returns true if the button was clicked since the last call.
+    pub fn was_clicked(&self) -> bool {
+        std::mem::take(&mut self.state.borrow_mut().clicked)
+    }
+
+    pub fn label(&self) -> String {
+        self.state.borrow().label.clone()
+    }
+
+    pub fn set_label(&self, label: impl Into<String>) {
+        self.state.borrow_mut().label = label.into();
+    }
+
+    fn click(&self, shared_state: &mut SharedState<S>) {
+        // taken out while it runs, so that it may use this button
+        let on_click = {
+            let mut state = self.state.borrow_mut();
+            state.clicked = true;
+            state.on_click.take()
+        };
+        if let Some(mut on_click) = on_click {
+            on_click(shared_state);
+            self.state.borrow_mut().on_click.get_or_insert(on_click);
+        }
+    }
+}
+
+impl<S> UiElement<S> for Button<S> {
+    fn get_size(&self) -> (usize, usize) {
+        (self.state.borrow().label.width() + 4, 1)
+    }
+
+    fn on_event(
+        &mut self,
+        event: Event,
+        shared_state: &mut SharedState<S>,
+    ) -> Option<BreakingAction> {
+        if is_left_down(&event) {
+            self.click(shared_state);
+        } else if is_activation(&event) {
+            self.click(shared_state);
+            return Some(BreakingAction::ConsumeEvent);
+        }
+        None
+    }
+
+    fn on_focus_change(&mut self, focused: bool, _shared_state: &mut SharedState<S>) {
+        self.state.borrow_mut().highlight.focused = focused;
+    }
+
+    fn on_hover(&mut self, position: Option<(usize, usize)>, _shared_state: &mut SharedState<S>) {
+        self.state.borrow_mut().highlight.hovered = position.is_some();
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, _shared_state: &SharedState<S>, depth_base: i32) {
+        let state = self.state.borrow();
+        let highlight = state.highlight;
+        let x = highlight.render_text("[ ", 0, Some(ACCENT), renderer, depth_base);
+        let x = highlight.render_text(&state.label, x, None, renderer, depth_base);
+        highlight.render_text(" ]", x, Some(ACCENT), renderer, depth_base);
+    }
+}
+
+struct CheckboxState {
+    label: String,
+    checked: bool,
+    toggled: bool,
+    highlight: Highlight,
+}
+
+/// A checkbox that is toggled with the mouse, or with Enter or Space while focused.
+#[derive(Clone)]
+pub struct Checkbox {
+    state: Rc<RefCell<CheckboxState>>,
+}
+
+impl Checkbox {
+    pub fn new(label: impl Into<String>) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(CheckboxState {
+                label: label.into(),
+                checked: false,
+                toggled: false,
+                highlight: Highlight::default(),
+            })),
+        }
+    }
+
+    pub fn with_checked(self, checked: bool) -> Self {
+        self.state.borrow_mut().checked = checked;
+        self
+    }
+
+    pub fn is_checked(&self) -> bool {
+        self.state.borrow().checked
+    }
+
+    /// Sets the state without counting as a toggle for [`Self::was_toggled`].
+    pub fn set_checked(&self, checked: bool) {
+        self.state.borrow_mut().checked = checked;
+    }
+
+    /// Returns true if the user toggled the checkbox since the last call.
+    pub fn was_toggled(&self) -> bool {
+        std::mem::take(&mut self.state.borrow_mut().toggled)
+    }
+
+    fn toggle(&self) {
+        let mut state = self.state.borrow_mut();
+        state.checked = !state.checked;
+        state.toggled = true;
+    }
+}
+
+impl<S> UiElement<S> for Checkbox {
+    fn get_size(&self) -> (usize, usize) {
+        (self.state.borrow().label.width() + 4, 1)
+    }
+
+    fn on_event(
+        &mut self,
+        event: Event,
+        _shared_state: &mut SharedState<S>,
+    ) -> Option<BreakingAction> {
+        if is_left_down(&event) {
+            self.toggle();
+        } else if is_activation(&event) {
+            self.toggle();
+            return Some(BreakingAction::ConsumeEvent);
+        }
+        None
+    }
+
+    fn on_focus_change(&mut self, focused: bool, _shared_state: &mut SharedState<S>) {
+        self.state.borrow_mut().highlight.focused = focused;
+    }
+
+    fn on_hover(&mut self, position: Option<(usize, usize)>, _shared_state: &mut SharedState<S>) {
+        self.state.borrow_mut().highlight.hovered = position.is_some();
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, _shared_state: &SharedState<S>, depth_base: i32) {
+        let state = self.state.borrow();
+        let highlight = state.highlight;
+        let mark = if state.checked { "[x] " } else { "[ ] " };
+        let x = highlight.render_text(mark, 0, Some(ACCENT), renderer, depth_base);
+        highlight.render_text(&state.label, x, None, renderer, depth_base);
+    }
+}
+
+struct SliderState {
+    min: f64,
+    max: f64,
+    step: f64,
+    value: f64,
+    /// The width of the track in cells.
+    width: usize,
+    changed: bool,
+    highlight: Highlight,
+}
+
+impl SliderState {
+    fn snap(&self, value: f64) -> f64 {
+        let steps = ((value - self.min) / self.step).round();
+        (self.min + steps * self.step).clamp(self.min, self.max)
+    }
+
+    fn set(&mut self, value: f64) {
+        let value = self.snap(value);
+        if value != self.value {
+            self.value = value;
+            self.changed = true;
+        }
+    }
+
+    /// The track cell of the knob.
+    fn knob(&self) -> usize {
+        let fraction = (self.value - self.min) / (self.max - self.min);
+        (fraction * (self.width - 1) as f64).round() as usize
+    }
+
+    fn format(&self, value: f64) -> String {
+        // as many decimals as the step has
+        let decimals = (0..6)
+            .find(|&d| {
+                let scaled = self.step * 10f64.powi(d);
+                (scaled - scaled.round()).abs() < 1e-9
+            })
+            .unwrap_or(6) as usize;
+        format!("{value:.decimals$}")
+    }
+
+    fn label_width(&self) -> usize {
+        self.format(self.min).len().max(self.format(self.max).len())
+    }
+}
+
+/// A slider for a number between `min` and `max` in multiples of `step`.
+///
+/// The value is set by clicking or dragging along the track, or with the arrow keys, Home and
+/// End while focused.
+#[derive(Clone)]
+pub struct Slider {
+    state: Rc<RefCell<SliderState>>,
+}
+
+impl Slider {
+    /// Creates a slider at `min`.
+    ///
+    /// Panics if `min` is not less than `max` or `step` is not positive.
+    pub fn new(min: f64, max: f64, step: f64) -> Self {
+        assert!(
+            min < max,
+            "the minimum {min} must be less than the maximum {max}"
+        );
+        assert!(step > 0.0, "the step {step} must be positive");
+        Self {
+            state: Rc::new(RefCell::new(SliderState {
+                min,
+                max,
+                step,
+                value: min,
+                width: 20,
+                changed: false,
+                highlight: Highlight::default(),
+            })),
+        }
+    }
+
+    /// Sets the value, snapped to the nearest step.
+    pub fn with_value(self, value: f64) -> Self {
+        self.set_value(value);
+        self
+    }
+
+    /// Sets the width of the track in cells, 20 by default. The value is shown after it.
+    pub fn with_width(self, width: usize) -> Self {
+        self.state.borrow_mut().width = width.max(2);
+        self
+    }
+
+    pub fn value(&self) -> f64 {
+        self.state.borrow().value
+    }
+
+    /// Sets the value, snapped to the nearest step, without counting as a change for
+    /// [`Self::was_changed`].
+    pub fn set_value(&self, value: f64) {
+        let mut state = self.state.borrow_mut();
+        state.value = state.snap(value);
+    }
+
+    /// Returns true if the user changed the value since the last call.
+    pub fn was_changed(&self) -> bool {
+        std::mem::take(&mut self.state.borrow_mut().changed)
+    }
+}
+
+impl<S> UiElement<S> for Slider {
+    fn get_size(&self) -> (usize, usize) {
+        let state = self.state.borrow();
+        (state.width + 1 + state.label_width(), 1)
+    }
+
+    fn on_event(
+        &mut self,
+        event: Event,
+        _shared_state: &mut SharedState<S>,
+    ) -> Option<BreakingAction> {
+        let mut state = self.state.borrow_mut();
+        match event {
+            Event::Mouse(me)
+                if matches!(
+                    me.kind,
+                    MouseEventKind::Down(MouseButton::Left)
+                        | MouseEventKind::Drag(MouseButton::Left)
+                ) =>
+            {
+                let x = (me.column as usize).min(state.width - 1);
+                let fraction = x as f64 / (state.width - 1) as f64;
+                let value = state.min + fraction * (state.max - state.min);
+                state.set(value);
+                None
+            }
+            Event::Key(key) if is_press(&key) => {
+                let value = match key.code {
+                    KeyCode::Left | KeyCode::Down => state.value - state.step,
+                    KeyCode::Right | KeyCode::Up => state.value + state.step,
+                    KeyCode::Home => state.min,
+                    KeyCode::End => state.max,
+                    _ => return None,
+                };
+                state.set(value);
+                Some(BreakingAction::ConsumeEvent)
+            }
+            _ => None,
+        }
+    }
+
+    fn on_focus_change(&mut self, focused: bool, _shared_state: &mut SharedState<S>) {
+        self.state.borrow_mut().highlight.focused = focused;
+    }
+
+    fn on_hover(&mut self, position: Option<(usize, usize)>, _shared_state: &mut SharedState<S>) {
+        self.state.borrow_mut().highlight.hovered = position.is_some();
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, _shared_state: &SharedState<S>, depth_base: i32) {
+        let state = self.state.borrow();
+        let highlight = state.highlight;
+        let knob = state.knob();
+        for x in 0..state.width {
+            let (c, color) = match x.cmp(&knob) {
+                std::cmp::Ordering::Less => ('━', ACCENT),
+                std::cmp::Ordering::Equal => ('●', ACCENT),
+                std::cmp::Ordering::Greater => ('─', MUTED),
+            };
+            highlight.render_text(&c.to_string(), x, Some(color), renderer, depth_base);
+        }
+        let label = format!(
+            " {:>width$}",
+            state.format(state.value),
+            width = state.label_width()
+        );
+        highlight.render_text(&label, state.width, None, renderer, depth_base);
+    }
+}
+
+struct TextInputState {
+    text: Vec<char>,
+    /// The index into `text` before which characters are inserted.
+    cursor: usize,
+    /// The first visible character.
+    scroll: usize,
+    /// The width of the field in cells.
+    width: usize,
+    max_len: Option<usize>,
+    changed: bool,
+    submitted: bool,
+    highlight: Highlight,
+}
+
+impl TextInputState {
+    /// Scrolls so that the cursor is visible, with a cell for the cursor after the last
+    /// character.
+    fn scroll_to_cursor(&mut self) {
+        if self.cursor < self.scroll {
+            self.scroll = self.cursor;
+        } else if self.cursor >= self.scroll + self.width {
+            self.scroll = self.cursor + 1 - self.width;
+        }
+    }
+
+    /// Handles a key, returns false if the key is not used by the input.
+    fn on_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Char(c)
+                if !key
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                // one cell per character, and the shifted character is already reported
+                if c.is_control() || c.width() != Some(1) {
+                    return false;
+                }
+                if self.max_len.is_none_or(|max_len| self.text.len() < max_len) {
+                    self.text.insert(self.cursor, c);
+                    self.cursor += 1;
+                    self.changed = true;
+                }
+            }
+            KeyCode::Backspace => {
+                if self.cursor > 0 {
+                    self.cursor -= 1;
+                    self.text.remove(self.cursor);
+                    self.changed = true;
+                }
+            }
+            KeyCode::Delete => {
+                if self.cursor < self.text.len() {
+                    self.text.remove(self.cursor);
+                    self.changed = true;
+                }
+            }
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.text.len()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.text.len(),
+            KeyCode::Enter => self.submitted = true,
+            _ => return false,
+        }
+        self.scroll_to_cursor();
+        true
+    }
+}
+
+/// A single-line text field.
+///
+/// While focused, printable characters are inserted at the cursor, which is moved with the arrow
+/// keys, Home and End. Backspace and Delete remove characters, and Enter submits the text, see
+/// [`Self::was_submitted`]. Text longer than the field scrolls.
+///
+/// Only characters that are one cell wide are accepted.
+#[derive(Clone)]
+pub struct TextInput {
+    state: Rc<RefCell<TextInputState>>,
+}
+
+impl TextInput {
+    /// Creates an empty field that is `width` cells wide.
+    pub fn new(width: usize) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(TextInputState {
+                text: vec![],
+                cursor: 0,
+                scroll: 0,
+                width: width.max(1),
+                max_len: None,
+                changed: false,
+                submitted: false,
+                highlight: Highlight::default(),
+            })),
+        }
+    }
+
+    /// Limits the text to `max_len` characters, further characters are ignored.
+    pub fn with_max_len(self, max_len: usize) -> Self {
+        self.state.borrow_mut().max_len = Some(max_len);
+        self
+    }
+
+    pub fn with_text(self, text: &str) -> Self {
+        self.set_text(text);
+        self
+    }
+
+    pub fn text(&self) -> String {
+        self.state.borrow().text.iter().collect()
+    }
+
+    /// Returns the cursor position in characters.
+    pub fn cursor(&self) -> usize {
+        self.state.borrow().cursor
+    }
+
+    /// Replaces the text and moves the cursor to its end, without counting as a change for
+    /// [`Self::was_changed`]. The text is cut to the maximum length.
+    pub fn set_text(&self, text: &str) {
+        let mut state = self.state.borrow_mut();
+        let max_len = state.max_len.unwrap_or(usize::MAX);
+        state.text = text.chars().take(max_len).collect();
+        state.cursor = state.text.len();
+        state.scroll = 0;
+        state.scroll_to_cursor();
+    }
+
+    /// Returns true if the user edited the text since the last call.
+    pub fn was_changed(&self) -> bool {
+        std::mem::take(&mut self.state.borrow_mut().changed)
+    }
+
+    /// Returns true if Enter was pressed since the last call.
+    pub fn was_submitted(&self) -> bool {
+        std::mem::take(&mut self.state.borrow_mut().submitted)
+    }
+}
+
+impl<S> UiElement<S> for TextInput {
+    fn get_size(&self) -> (usize, usize) {
+        (self.state.borrow().width, 1)
+    }
+
+    fn on_event(
+        &mut self,
+        event: Event,
+        _shared_state: &mut SharedState<S>,
+    ) -> Option<BreakingAction> {
+        let mut state = self.state.borrow_mut();
+        match event {
+            Event::Mouse(me) if me.kind == MouseEventKind::Down(MouseButton::Left) => {
+                state.cursor = (state.scroll + me.column as usize).min(state.text.len());
+                None
+            }
+            Event::Key(key) if is_press(&key) && state.on_key(key) => {
+                Some(BreakingAction::ConsumeEvent)
+            }
+            _ => None,
+        }
+    }
+
+    fn on_focus_change(&mut self, focused: bool, _shared_state: &mut SharedState<S>) {
+        self.state.borrow_mut().highlight.focused = focused;
+    }
+
+    fn on_hover(&mut self, position: Option<(usize, usize)>, _shared_state: &mut SharedState<S>) {
+        self.state.borrow_mut().highlight.hovered = position.is_some();
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, _shared_state: &SharedState<S>, depth_base: i32) {
+        let state = self.state.borrow();
+        let bg = state.highlight.bg().unwrap_or(FIELD_BG);
+        for x in 0..state.width {
+            let idx = state.scroll + x;
+            let c = state.text.get(idx).copied().unwrap_or(' ');
+            let pixel = if state.highlight.focused && idx == state.cursor {
+                Pixel::new(c)
+                    .with_color([0, 0, 0])
+                    .with_bg_color([220, 220, 220])
+            } else {
+                Pixel::new(c).with_bg_color(bg)
+            };
+            renderer.render_pixel(x, 0, pixel, depth_base);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Game;
+    use crate::components::ui::UiComponent;
+    use crate::rendering::color::Color;
+    use crossterm::event::MouseEvent;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> Event {
+        Event::Key(KeyEvent::new(code, modifiers))
+    }
+
+    fn mouse(kind: MouseEventKind, column: u16) -> Event {
+        Event::Mouse(MouseEvent {
+            kind,
+            column,
+            row: 0,
+            modifiers: KeyModifiers::NONE,
+        })
+    }
+
+    #[test]
+    fn test_text_input_editing() {
+        let mut shared_state = SharedState::<()>::new(10, 5);
+        let input = TextInput::new(4).with_max_len(6);
+        let mut element = input.clone();
+        let mut type_key = |code, modifiers| {
+            UiElement::<()>::on_event(&mut element, key(code, modifiers), &mut shared_state)
+        };
+        for c in "ab".chars() {
+            type_key(KeyCode::Char(c), KeyModifiers::NONE);
+        }
+        // shift arrives as the shifted character
+        let consumed = type_key(KeyCode::Char('C'), KeyModifiers::SHIFT);
+        assert!(matches!(consumed, Some(BreakingAction::ConsumeEvent)));
+        assert_eq!(input.text(), "abC");
+        assert!(input.was_changed());
+        assert!(!input.was_changed());
+
+        // shortcuts are left to other components
+        assert!(type_key(KeyCode::Char('q'), KeyModifiers::CONTROL).is_none());
+
+        type_key(KeyCode::Left, KeyModifiers::NONE);
+        type_key(KeyCode::Backspace, KeyModifiers::NONE);
+        assert_eq!((input.text().as_str(), input.cursor()), ("aC", 1));
+        type_key(KeyCode::Home, KeyModifiers::NONE);
+        type_key(KeyCode::Delete, KeyModifiers::NONE);
+        assert_eq!((input.text().as_str(), input.cursor()), ("C", 0));
+
+        for c in "123456".chars() {
+            type_key(KeyCode::Char(c), KeyModifiers::NONE);
+        }
+        assert_eq!(input.text(), "12345C");
+        assert_eq!(input.state.borrow().scroll, 2);
+        type_key(KeyCode::Enter, KeyModifiers::NONE);
+        assert!(input.was_submitted());
+    }
+
+    #[test]
+    fn test_slider_steps() {
+        let mut shared_state = SharedState::<()>::new(10, 5);
+        let slider = Slider::new(0.0, 1.0, 0.25).with_value(0.3).with_width(5);
+        assert_eq!(slider.value(), 0.25);
+        assert!(!slider.was_changed());
+        let mut element = slider.clone();
+        let mut send = |event| UiElement::<()>::on_event(&mut element, event, &mut shared_state);
+
+        send(key(KeyCode::Right, KeyModifiers::NONE));
+        assert_eq!(slider.value(), 0.5);
+        assert!(slider.was_changed());
+        send(key(KeyCode::End, KeyModifiers::NONE));
+        send(key(KeyCode::Right, KeyModifiers::NONE));
+        assert_eq!(slider.value(), 1.0);
+
+        send(mouse(MouseEventKind::Down(MouseButton::Left), 1));
+        assert_eq!(slider.value(), 0.25);
+        // past the end of the track, onto the value
+        send(mouse(MouseEventKind::Drag(MouseButton::Left), 7));
+        assert_eq!(slider.value(), 1.0);
+        assert_eq!(UiElement::<()>::get_size(&slider), (10, 1));
+    }
+
+    #[test]
+    fn test_button_and_checkbox_in_ui() {
+        let mut game = Game::<Vec<u8>, ()>::new_headless(20, 3);
+        game.add_component(Box::new(UiComponent::new()));
+        let clicks = Rc::new(RefCell::new(0));
+        let counter = clicks.clone();
+        let button = Button::new("OK").with_on_click(move |_| *counter.borrow_mut() += 1);
+        let checkbox = Checkbox::new("sound");
+        let ui = &mut game.shared_state_mut().ui;
+        ui.add_window("ok", 0, 0, Box::new(button.clone()));
+        ui.add_window("sound", 0, 1, Box::new(checkbox.clone()));
+        game.run_frames(1).unwrap();
+        let text = |game: &Game<Vec<u8>, ()>, y| {
+            (0..9)
+                .map(|x| game.frame().pixel_at(x, y).c)
+                .collect::<String>()
+        };
+        assert_eq!(text(&game, 0), "[ OK ]   ");
+        assert_eq!(text(&game, 1), "[ ] sound");
+
+        let click = |column, row| {
+            Event::Mouse(MouseEvent {
+                kind: MouseEventKind::Down(MouseButton::Left),
+                column,
+                row,
+                modifiers: KeyModifiers::NONE,
+            })
+        };
+        game.push_event(click(2, 0));
+        game.run_frames(1).unwrap();
+        assert!(button.was_clicked());
+        assert!(!button.was_clicked());
+        assert_eq!(*clicks.borrow(), 1);
+        assert_eq!(game.frame().pixel_at(0, 0).bg_color, Color::Rgb(FOCUSED_BG));
+
+        // clicking the checkbox moves the focus, and space toggles it again
+        game.push_event(click(1, 1));
+        game.push_event(key(KeyCode::Char(' '), KeyModifiers::NONE));
+        game.run_frames(1).unwrap();
+        assert!(!checkbox.is_checked());
+        assert!(checkbox.was_toggled());
+        assert_ne!(game.frame().pixel_at(0, 0).bg_color, Color::Rgb(FOCUSED_BG));
+        game.push_event(key(KeyCode::Enter, KeyModifiers::NONE));
+        game.run_frames(1).unwrap();
+        assert_eq!(text(&game, 1), "[x] sound");
+        assert_eq!(*clicks.borrow(), 1);
+    }
+}
@@ -0,0 +1,413 @@
+//! Watching values of the shared state for changes.
+//!
+//! Debugging questions like "why did my block count change" usually means sprinkling prints.
+//! Instead, register a watcher that turns some part of the state into a string:
+//! ```rust
+//! use teng::SharedState;
+//! use teng::components::watch::Watches;
+//!
+//! struct GameState {
+//!     blocks: u32,
+//! }
+//!
+//! fn setup(shared_state: &mut SharedState) {
+//!     shared_state.watch("blocks", |s: &SharedState| {
+//!         s.extensions.get::<GameState>().map(|g| g.blocks.to_string())
+//!     });
+//!     // optionally, halt the game when the value changes
+//!     shared_state.ext_or_default::<Watches>().break_on_change("blocks");
+//! }
+//! ```
+//! The [`WatchComponent`] evaluates all watchers once per frame, records every change into a
+//! bounded history, and displays the current values in a small panel in the top right corner.
+//! Press F7 to expand the panel with the recent changes.
+//!
+//! If a watcher is marked with [`Watches::break_on_change`], a change of its value halts the game
+//! like a data breakpoint: only the [`WatchComponent`] and the input components stay active until
+//! F8 is pressed.
+//!
+//! The cost of watching is one closure call per watcher per frame.
+
+use crate::components::dim::DimBehindComponent;
+use crate::components::keyboard::KeyPressRecorderComponent;
+use crate::rendering::render::Render;
+use crate::rendering::renderer::Renderer;
+use crate::{Component, ComponentFilter, SharedState, UpdateInfo};
+use crossterm::event::KeyCode;
+use std::any::TypeId;
+use std::collections::{HashSet, VecDeque};
+
+type WatchFn<S> = Box<dyn Fn(&SharedState<S>) -> Option<String>>;
+
+struct Watcher<S> {
+    name: String,
+    f: WatchFn<S>,
+    /// The last observed value. `None` inside means the closure returned `None`.
+    last: Option<Option<String>>,
+}
+
+/// A recorded change of a watched value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchChange {
+    /// The name of the watcher.
+    pub name: String,
+    /// The value before the change.
+    pub old: Option<String>,
+    /// The value after the change.
+    pub new: Option<String>,
+    /// The frame in which the change was observed.
+    pub frame: u64,
+}
+
+/// The registered watchers and their change history, kept as an extension of the
+/// [`SharedState`] that [`SharedState::watch`] inserts.
+pub struct Watches<S = ()> {
+    watchers: Vec<Watcher<S>>,
+    history: VecDeque<WatchChange>,
+    max_history: usize,
+    break_on: HashSet<String>,
+}
+
+impl<S> Watches<S> {
+    /// The default number of changes kept in the history.
+    pub const DEFAULT_MAX_HISTORY: usize = 64;
+
+    pub fn new() -> Self {
+        Self {
+            watchers: Vec::new(),
+            history: VecDeque::new(),
+            max_history: Self::DEFAULT_MAX_HISTORY,
+            break_on: HashSet::new(),
+        }
+    }
+
+    /// Registers a watcher. A watcher with the same name is replaced.
+    ///
+    /// Prefer [`SharedState::watch`], which allows the closure's type to be inferred.
+    pub fn add(
+        &mut self,
+        name: impl Into<String>,
+        f: impl Fn(&SharedState<S>) -> Option<String> + 'static,
+    ) {
+        let name = name.into();
+        self.remove(&name);
+        self.watchers.push(Watcher {
+            name,
+            f: Box::new(f),
+            last: None,
+        });
+    }
+
+    /// Removes the watcher with the given name.
+    pub fn remove(&mut self, name: &str) {
+        self.watchers.retain(|w| w.name != name);
+        self.break_on.remove(name);
+    }
+
+    /// Halts the game when the value of the watcher with the given name changes.
+    pub fn break_on_change(&mut self, name: impl Into<String>) {
+        self.break_on.insert(name.into());
+    }
+
+    /// Stops halting the game when the value of the watcher with the given name changes.
+    pub fn clear_break(&mut self, name: &str) {
+        self.break_on.remove(name);
+    }
+
+    /// Sets the maximum number of changes kept in the history.
+    pub fn set_max_history(&mut self, max_history: usize) {
+        self.max_history = max_history;
+        self.truncate_history();
+    }
+
+    /// Returns the recorded changes, oldest first.
+    pub fn history(&self) -> impl DoubleEndedIterator<Item = &WatchChange> {
+        self.history.iter()
+    }
+
+    /// Returns the name and last observed value of every watcher, in registration order.
+    pub fn current_values(&self) -> impl Iterator<Item = (&str, Option<&str>)> {
+        self.watchers
+            .iter()
+            .map(|w| (w.name.as_str(), w.last.as_ref().and_then(|v| v.as_deref())))
+    }
+
+    fn truncate_history(&mut self) {
+        while self.history.len() > self.max_history {
+            self.history.pop_front();
+        }
+    }
+}
+
+impl<S> Default for Watches<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Evaluates all watchers of `shared_state`, records changes, and returns the first change of a
+/// watcher marked with [`Watches::break_on_change`], if any.
+///
+/// The first evaluation of a watcher is not considered a change.
+fn evaluate_watches<S: 'static>(
+    shared_state: &mut SharedState<S>,
+    frame: u64,
+) -> Option<WatchChange> {
+    // take the watches out so the closures can borrow the shared state
+    shared_state
+        .with_ext::<Watches<S>, _>(|watches, shared_state| {
+            let mut hit_break = None;
+            for watcher in watches.watchers.iter_mut() {
+                let value = (watcher.f)(shared_state);
+                match watcher.last.take() {
+                    Some(old) if old != value => {
+                        let change = WatchChange {
+                            name: watcher.name.clone(),
+                            old,
+                            new: value.clone(),
+                            frame,
+                        };
+                        if hit_break.is_none() && watches.break_on.contains(&watcher.name) {
+                            hit_break = Some(change.clone());
+                        }
+                        watches.history.push_back(change);
+                    }
+                    _ => {}
+                }
+                watcher.last = Some(value);
+            }
+            watches.truncate_history();
+            hit_break
+        })
+        .flatten()
+}
+
+struct Break {
+    change: WatchChange,
+}
+
+/// A component that evaluates the [`Watches`] and displays them.
+///
+/// Should be the last component in the update order, so that it observes the state at the end of
+/// every frame.
+pub struct WatchComponent {
+    frame: u64,
+    expanded: bool,
+    active_break: Option<Break>,
+}
+
+impl WatchComponent {
+    /// The key that toggles the expanded view with the recent changes.
+    pub const EXPAND_KEY: KeyCode = KeyCode::F(7);
+    /// The key that continues the game after a break.
+    pub const CONTINUE_KEY: KeyCode = KeyCode::F(8);
+
+    pub fn new() -> Self {
+        Self {
+            frame: 0,
+            expanded: false,
+            active_break: None,
+        }
+    }
+
+    /// Returns the change that halted the game, if the game is currently halted.
+    pub fn active_break(&self) -> Option<&WatchChange> {
+        self.active_break.as_ref().map(|b| &b.change)
+    }
+
+    fn enter_break<S: 'static>(&mut self, change: WatchChange, shared_state: &mut SharedState<S>) {
+        let whitelist = HashSet::from([
+            TypeId::of::<Self>(),
+            TypeId::of::<KeyPressRecorderComponent>(),
+            TypeId::of::<DimBehindComponent>(),
+        ]);
+        shared_state.push_component_filter(ComponentFilter::Whitelist(whitelist));
+        self.active_break = Some(Break { change });
+    }
+
+    fn leave_break<S>(&mut self, shared_state: &mut SharedState<S>) {
+        if self.active_break.take().is_some() {
+            shared_state.pop_component_filter();
+        }
+    }
+}
+
+impl Default for WatchComponent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: 'static> Component<S> for WatchComponent {
+    fn runs_while_paused(&self) -> bool {
+        true
+    }
+
+    fn update(&mut self, _update_info: UpdateInfo, shared_state: &mut SharedState<S>) {
+        if shared_state.pressed_keys.did_press(Self::EXPAND_KEY) {
+            self.expanded = !self.expanded;
+        }
+        if self.active_break.is_some() {
+            if shared_state.pressed_keys.did_press(Self::CONTINUE_KEY) {
+                self.leave_break(shared_state);
+            }
+            return;
+        }
+        self.frame += 1;
+        if let Some(change) = evaluate_watches(shared_state, self.frame) {
+            self.enter_break(change, shared_state);
+        }
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer, shared_state: &SharedState<S>, _depth_base: i32) {
+        let Some(watches) = shared_state.ext::<Watches<S>>() else {
+            return;
+        };
+        if watches.watchers.is_empty() {
+            return;
+        }
+        let mut lines = vec![];
+        if let Some(active_break) = &self.active_break {
+            let change = &active_break.change;
+            lines.push(format!(
+                "BREAK: {} changed: {} -> {} (F8 to continue)",
+                change.name,
+                change.old.as_deref().unwrap_or("None"),
+                change.new.as_deref().unwrap_or("None"),
+            ));
+        }
+        for (name, value) in watches.current_values() {
+            lines.push(format!("{}: {}", name, value.unwrap_or("None")));
+        }
+        if self.expanded {
+            lines.push("Recent changes:".to_string());
+            for change in watches.history().rev().take(10) {
+                lines.push(format!(
+                    "[{}] {}: {} -> {}",
+                    change.frame,
+                    change.name,
+                    change.old.as_deref().unwrap_or("None"),
+                    change.new.as_deref().unwrap_or("None"),
+                ));
+            }
+        }
+
+        let width = shared_state.display_info.width();
+        let panel_width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+        let x = width.saturating_sub(panel_width);
+        for (y, line) in lines.iter().enumerate() {
+            line.with_bg_color([30, 30, 30])
+                .render(renderer, x, y, i32::MAX - 100);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Counter(u32);
+
+    fn shared_state() -> SharedState<()> {
+        let mut shared_state = SharedState::<()>::new(10, 10);
+        shared_state.extensions.insert(Counter(0));
+        shared_state.watch("counter", |s: &SharedState| {
+            s.extensions.get::<Counter>().map(|c| c.0.to_string())
+        });
+        shared_state
+    }
+
+    fn watches(shared_state: &mut SharedState) -> &mut Watches {
+        shared_state.ext_or_default()
+    }
+
+    fn set_counter(shared_state: &mut SharedState, value: u32) {
+        shared_state.extensions.get_mut::<Counter>().unwrap().0 = value;
+    }
+
+    #[test]
+    fn test_change_detection() {
+        let mut shared_state = shared_state();
+        evaluate_watches(&mut shared_state, 1);
+        // the first observation is not a change
+        assert_eq!(watches(&mut shared_state).history().count(), 0);
+        evaluate_watches(&mut shared_state, 2);
+        assert_eq!(watches(&mut shared_state).history().count(), 0);
+
+        set_counter(&mut shared_state, 5);
+        evaluate_watches(&mut shared_state, 3);
+        shared_state.extensions.remove::<Counter>();
+        evaluate_watches(&mut shared_state, 4);
+        let history = watches(&mut shared_state)
+            .history()
+            .cloned()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            history,
+            vec![
+                WatchChange {
+                    name: "counter".to_string(),
+                    old: Some("0".to_string()),
+                    new: Some("5".to_string()),
+                    frame: 3,
+                },
+                WatchChange {
+                    name: "counter".to_string(),
+                    old: Some("5".to_string()),
+                    new: None,
+                    frame: 4,
+                },
+            ]
+        );
+        assert_eq!(
+            watches(&mut shared_state)
+                .current_values()
+                .collect::<Vec<_>>(),
+            vec![("counter", None)]
+        );
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let mut shared_state = shared_state();
+        watches(&mut shared_state).set_max_history(3);
+        for frame in 0..10 {
+            set_counter(&mut shared_state, frame as u32);
+            evaluate_watches(&mut shared_state, frame);
+        }
+        let frames = watches(&mut shared_state)
+            .history()
+            .map(|c| c.frame)
+            .collect::<Vec<_>>();
+        assert_eq!(frames, vec![7, 8, 9]);
+    }
+
+    #[test]
+    fn test_break_on_change() {
+        let mut shared_state = shared_state();
+        watches(&mut shared_state).break_on_change("counter");
+        let mut component = WatchComponent::new();
+        let update_info = UpdateInfo::at(std::time::Instant::now());
+
+        component.update(update_info, &mut shared_state);
+        assert!(component.active_break().is_none());
+
+        set_counter(&mut shared_state, 1);
+        component.update(update_info, &mut shared_state);
+        assert_eq!(component.active_break().unwrap().new.as_deref(), Some("1"));
+        let filter = shared_state.component_filter().unwrap();
+        assert!(filter.allows(TypeId::of::<WatchComponent>()));
+
+        // halted: further changes are not evaluated until continuing
+        set_counter(&mut shared_state, 2);
+        component.update(update_info, &mut shared_state);
+        assert_eq!(watches(&mut shared_state).history().count(), 1);
+
+        shared_state
+            .pressed_keys
+            .insert(WatchComponent::CONTINUE_KEY);
+        component.update(update_info, &mut shared_state);
+        assert!(component.active_break().is_none());
+        assert!(shared_state.component_filter().is_none());
+    }
+}
@@ -1,16 +1,18 @@
 #![doc = include_str!("../README.md")]
 
-use anymap::AnyMap;
-use crossterm::event::{DisableMouseCapture, EnableMouseCapture, Event, KeyCode};
+use crossterm::event::{
+    DisableBracketedPaste, DisableFocusChange, DisableMouseCapture, EnableBracketedPaste,
+    EnableFocusChange, EnableMouseCapture, Event, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
-use crossterm::{cursor, execute};
-use smallvec::SmallVec;
-use std::any::Any;
+use crossterm::{Command, cursor, execute, queue};
 use std::cell::RefCell;
 use std::collections::HashSet;
 use std::io;
 use std::io::{Stdout, Write, stdout};
-use std::sync::mpsc::Receiver;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
 use std::time::{Duration, Instant};
 
 pub mod components;
@@ -19,13 +21,29 @@ pub mod seeds;
 pub mod util;
 
 use crate::components::Component;
-use crate::components::debuginfo::{DebugInfo, DebugInfoComponent, DebugMessage};
-use crate::components::fpslocker::FpsLockerComponent;
-use crate::components::keyboard::{KeyPressRecorderComponent, PressedKeys};
-use crate::components::mouse::{MouseEvents, MouseInfo, MousePressedInfo, MouseReleasedInfo, MouseTrackerComponent};
+use crate::components::debuginfo::{DebugInfo, DebugInfoComponent, DebugMessage, DebugMessages};
+use crate::components::flicker::FlickerDetector;
+use crate::components::fpslocker::{FpsLockerComponent, FpsMode, FpsSettings};
+use crate::components::keyboard::{
+    DebouncedKeys, HeldKeys, KeyPressRecorderComponent, PressedKeys, normalize_event,
+};
+use crate::components::notify::Notifications;
+use crate::components::overlay_layout::{OverlayAnchor, OverlayLayoutManager, Placement};
+use crate::components::mouse::{MouseCapture, MouseEvents, MouseGestures, MouseInfo, MousePressedInfo, MouseReleasedInfo, MouseTrackerComponent};
+use crate::components::problems::Problems;
 use crate::components::quitter::QuitterComponent;
+#[cfg(feature = "persistence")]
+use crate::components::saveslots::SaveSlotsMenu;
+use crate::components::scene::SceneManager;
+use crate::components::toast::{Toast, ToastQueue};
 use crate::components::ui::UiProxy;
-use crate::rendering::renderer::DisplayRenderer;
+use crate::components::watch::Watches;
+use crate::rendering::capture::FrameCapture;
+use crate::rendering::color::ColorMode;
+use crate::rendering::deferred::DrawQueue;
+use crate::util::anymap::AnyMap;
+use crate::util::clipboard::Clipboard;
+use crate::rendering::renderer::{DisplayRenderer, FrameView, Overlay, PostProcess, Renderer};
 
 /// Information about the time since the last frame.
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -38,12 +56,523 @@ pub struct UpdateInfo {
     pub dt: f64,
     /// The time in seconds that the last frame took without the sleeping to reach target fps.
     pub actual_dt: f64,
+    /// The number of the current frame, counting from 0 for the first frame of the game.
+    pub frame_number: u64,
+}
+
+#[cfg(test)]
+impl UpdateInfo {
+    /// The first frame of a test, starting at `time` with no time passed.
+    pub(crate) fn at(time: Instant) -> Self {
+        Self {
+            last_time: time,
+            current_time: time,
+            dt: 0.0,
+            actual_dt: 0.0,
+            frame_number: 0,
+        }
+    }
 }
 
 /// Actions that can be taken by components.
 pub enum BreakingAction {
     /// Quit the loop.
     Quit,
+    /// Finish the current frame, i.e., process the remaining events of the frame and run
+    /// `update` and `render` of all components once more, then quit the loop.
+    ///
+    /// Useful if a component computes state in `update` that must be saved in `on_quit`.
+    QuitAfterFrame,
+    /// Stop the current event from reaching the remaining components.
+    ConsumeEvent,
+}
+
+/// A component's answer to a quit request, see [`Component::on_quit_requested`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum QuitResponse {
+    /// The game may quit.
+    Allow,
+    /// Cancel the quit request, e.g. because the user chose "cancel" in a "save before
+    /// quitting?" dialog.
+    Veto,
+    /// Keep the quit request pending and ask again next frame, e.g. while a dialog waits for
+    /// the user. `reason` is shown as a debug message.
+    Defer { reason: String },
+}
+
+/// The update priority of a component, see [`Component::update_priority`].
+///
+/// Components with a higher priority receive events and are updated first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Priority(pub i32);
+
+impl Priority {
+    /// The priority of components that do not specify one.
+    pub const DEFAULT: Priority = Priority(0);
+    /// The priority of components that track input state for other components, e.g. the
+    /// [`MouseTrackerComponent`].
+    pub const INPUT: Priority = Priority(1000);
+}
+
+/// What happens when components are missing the dependencies they declare, see
+/// [`Game::set_dependency_check`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DependencyCheck {
+    /// The setup fails with an error that lists the problems.
+    #[default]
+    Error,
+    /// Every problem is shown as an error debug message.
+    Warn,
+    /// Dependencies are not checked.
+    Off,
+}
+
+/// Keeps track of a pending quit request.
+struct PendingQuit {
+    requests: usize,
+    since: Instant,
+    last_reason: Option<String>,
+}
+
+/// Decides when a quit request is granted, see [`Component::on_quit_requested`].
+struct QuitGate {
+    pending: Option<PendingQuit>,
+    force_quit_requests: usize,
+    force_quit_timeout: Option<Duration>,
+    /// How long the game waits for [`Component::poll_quit_finished`] after `on_quit`.
+    finish_timeout: Duration,
+}
+
+impl QuitGate {
+    fn new() -> Self {
+        Self {
+            pending: None,
+            force_quit_requests: 3,
+            force_quit_timeout: None,
+            finish_timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// Handles a [`BreakingAction::Quit`]. Returns true if the game should quit now.
+    fn request<S: Default + 'static>(
+        &mut self,
+        components: &mut [Box<dyn Component<S>>],
+        shared_state: &mut SharedState<S>,
+    ) -> bool {
+        let pending = self.pending.get_or_insert_with(|| PendingQuit {
+            requests: 0,
+            since: Instant::now(),
+            last_reason: None,
+        });
+        pending.requests += 1;
+        if pending.requests >= self.force_quit_requests {
+            return true;
+        }
+        self.poll(components, shared_state)
+    }
+
+    /// Asks all active components whether the pending quit request may proceed.
+    /// Returns true if the game should quit now.
+    fn poll<S: Default + 'static>(
+        &mut self,
+        components: &mut [Box<dyn Component<S>>],
+        shared_state: &mut SharedState<S>,
+    ) -> bool {
+        let Some(pending) = &mut self.pending else {
+            return false;
+        };
+        if self
+            .force_quit_timeout
+            .is_some_and(|timeout| pending.since.elapsed() >= timeout)
+        {
+            return true;
+        }
+        let mut deferred = None;
+        for component in components.iter_mut() {
+            if !shared_state.is_component_active(component.as_ref()) {
+                continue;
+            }
+            match component.on_quit_requested(shared_state) {
+                QuitResponse::Allow => {}
+                QuitResponse::Veto => {
+                    self.pending = None;
+                    return false;
+                }
+                QuitResponse::Defer { reason } => {
+                    deferred.get_or_insert(reason);
+                }
+            }
+        }
+        let Some(reason) = deferred else {
+            self.pending = None;
+            return true;
+        };
+        if pending.last_reason.as_ref() != Some(&reason) {
+            let remaining = self.force_quit_requests - pending.requests;
+            shared_state.debug_messages.push(DebugMessage::new_3s(format!(
+                "Quit deferred: {reason} (quit {remaining} more times to force)"
+            )));
+            pending.last_reason = Some(reason);
+        }
+        false
+    }
+}
+
+/// Calls `setup` on all components starting at `already_setup_components`.
+///
+/// Components that are added during `setup` are appended and set up as well, each exactly once.
+fn setup_components<S: 'static>(
+    components: &mut Vec<Box<dyn Component<S>>>,
+    added: &mut Vec<Added>,
+    mut already_setup_components: usize,
+    shared_state: &mut SharedState<S>,
+) {
+    let setup_info = SetupInfo {
+        display_info: shared_state.display_info.clone(),
+    };
+    while already_setup_components < components.len() {
+        let component = &mut components[already_setup_components];
+        component.setup(&setup_info, shared_state);
+        append_components_to_add(components, added, shared_state);
+        already_setup_components += 1;
+    }
+    // settings registered during setup take effect before any component updates
+    components::settings::apply_pending(shared_state);
+}
+
+/// Appends [`SharedState::components_to_add`], assigning their ids in the order they were pushed.
+fn append_components_to_add<S: 'static>(
+    components: &mut Vec<Box<dyn Component<S>>>,
+    added: &mut Vec<Added>,
+    shared_state: &mut SharedState<S>,
+) {
+    for component in shared_state.components_to_add.drain(..) {
+        added.push(Added::new(
+            ComponentId::next(&mut shared_state.next_component_id),
+            None,
+        ));
+        components.push(component);
+    }
+}
+
+/// A handle to a component added to a game, e.g. to remove that instance later when there are
+/// several components of the same type. See [`Game::add_component`] and
+/// [`SharedState::add_component`].
+///
+/// Ids are unique within a game and increase in the order the components were added in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ComponentId(u64);
+
+impl ComponentId {
+    fn next(next_id: &mut u64) -> Self {
+        let id = Self(*next_id);
+        *next_id += 1;
+        id
+    }
+}
+
+/// A component to remove, either all components of a type or a single instance, see
+/// [`RemoveComponents`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ComponentRef {
+    Type(std::any::TypeId),
+    Id(ComponentId),
+}
+
+impl From<std::any::TypeId> for ComponentRef {
+    fn from(type_id: std::any::TypeId) -> Self {
+        Self::Type(type_id)
+    }
+}
+
+impl From<ComponentId> for ComponentRef {
+    fn from(id: ComponentId) -> Self {
+        Self::Id(id)
+    }
+}
+
+/// The components to remove at the end of the frame, see [`SharedState::remove_components`].
+///
+/// Removed components are notified with [`Component::on_removed`].
+#[derive(Debug, Default)]
+pub struct RemoveComponents {
+    refs: HashSet<ComponentRef>,
+}
+
+impl RemoveComponents {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Marks all components of a type, given a [`TypeId`](std::any::TypeId), or a single component, given a
+    /// [`ComponentId`], for removal. Returns false if it was already marked.
+    pub fn insert(&mut self, component: impl Into<ComponentRef>) -> bool {
+        self.refs.insert(component.into())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.refs.is_empty()
+    }
+
+    fn matches<S: 'static>(&self, component: &dyn Component<S>, id: ComponentId) -> bool {
+        self.refs.contains(&ComponentRef::Id(id))
+            || self.refs.contains(&ComponentRef::Type(component.type_id()))
+    }
+}
+
+/// Which components are active while a modal, e.g. a menu, is open, see
+/// [`SharedState::push_component_filter`].
+///
+/// Components that return true from [`Component::ignores_filters`] are active regardless.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ComponentFilter {
+    /// Only components of these types are active.
+    Whitelist(HashSet<std::any::TypeId>),
+    /// All components except the ones of these types are active.
+    Blacklist(HashSet<std::any::TypeId>),
+    /// All components are active, e.g. to lift the filters below for a while.
+    All,
+}
+
+impl ComponentFilter {
+    /// Returns true if components of the given type are active under this filter.
+    pub fn allows(&self, type_id: std::any::TypeId) -> bool {
+        match self {
+            ComponentFilter::Whitelist(types) => types.contains(&type_id),
+            ComponentFilter::Blacklist(types) => !types.contains(&type_id),
+            ComponentFilter::All => true,
+        }
+    }
+}
+
+/// How a component was added to the game, and when it was last updated.
+#[derive(Clone, Copy, Debug)]
+struct Added {
+    /// Also the position in the order the components were added in.
+    id: ComponentId,
+    priority_override: Option<Priority>,
+    interval_override: Option<Duration>,
+    /// The end of the span of the last update, `None` if the component was not updated since it
+    /// was added or became active.
+    last_update: Option<Instant>,
+}
+
+impl Added {
+    fn new(id: ComponentId, priority_override: Option<Priority>) -> Added {
+        Added {
+            id,
+            priority_override,
+            interval_override: None,
+            last_update: None,
+        }
+    }
+
+    /// Returns the update info for an update of the component this frame, or `None` if its
+    /// update interval has not elapsed yet.
+    ///
+    /// The returned `dt` covers the span since the last update. The first update after the
+    /// component was added or became active happens right away, with the `dt` of the frame.
+    fn due(&mut self, interval: Option<Duration>, update_info: UpdateInfo) -> Option<UpdateInfo> {
+        let Some(last_update) = self.last_update else {
+            self.last_update = Some(update_info.current_time);
+            return Some(update_info);
+        };
+        let elapsed = update_info.current_time.duration_since(last_update);
+        if interval.is_some_and(|interval| elapsed < interval) {
+            return None;
+        }
+        self.last_update = Some(update_info.current_time);
+        Some(UpdateInfo {
+            last_time: last_update,
+            dt: elapsed.as_secs_f64(),
+            ..update_info
+        })
+    }
+}
+
+/// Paces the frames of [`Game::run`] in [`FpsMode::Target`] to absolute deadlines.
+///
+/// Every frame starts a fixed duration after the deadline of the previous one, so overshooting
+/// sleeps and slow frames do not accumulate drift. After a slow frame, the following frames run
+/// right away until the schedule is caught up, but after a stall of more than
+/// [`FramePacer::MAX_MISSED_FRAMES`] frames, the schedule restarts from now instead.
+struct FramePacer {
+    /// The deadline of the last frame, or `None` if the schedule starts over.
+    deadline: Option<Instant>,
+}
+
+impl FramePacer {
+    const MAX_MISSED_FRAMES: u32 = 3;
+    /// How long before a deadline sleeping stops and spinning starts, since sleeps overshoot.
+    const SPIN_DURATION: Duration = Duration::from_millis(1);
+
+    fn new() -> Self {
+        Self { deadline: None }
+    }
+
+    /// Restarts the schedule from the next frame on.
+    fn reset(&mut self) {
+        self.deadline = None;
+    }
+
+    /// Returns the deadline of the next frame, given that the last frame started at
+    /// `frame_start` and it is `now`.
+    fn next_deadline(
+        &mut self,
+        frame_start: Instant,
+        now: Instant,
+        frame_duration: Duration,
+    ) -> Instant {
+        let mut deadline = self.deadline.unwrap_or(frame_start) + frame_duration;
+        if now > deadline + frame_duration * Self::MAX_MISSED_FRAMES {
+            // resync instead of running many frames back to back
+            deadline = now;
+        }
+        self.deadline = Some(deadline);
+        deadline
+    }
+
+    /// Sleeps until shortly before `deadline`, then spins until it has passed.
+    fn sleep_until(deadline: Instant) {
+        let now = Instant::now();
+        if let Some(sleep) = deadline.checked_duration_since(now + Self::SPIN_DURATION) {
+            std::thread::sleep(sleep);
+        }
+        while Instant::now() < deadline {
+            std::hint::spin_loop();
+        }
+    }
+}
+
+/// Stably sorts the components by update priority, highest first, and returns the render order.
+///
+/// `added` holds how the components were added, one entry per component.
+fn sort_components<S: 'static>(
+    components: &mut Vec<Box<dyn Component<S>>>,
+    added: &mut Vec<Added>,
+) -> Vec<usize> {
+    debug_assert_eq!(components.len(), added.len());
+    let mut entries = components.drain(..).zip(added.drain(..)).collect::<Vec<_>>();
+    entries.sort_by_key(|(component, added)| {
+        let priority = added
+            .priority_override
+            .map_or(component.update_priority(), |p| p.0);
+        (std::cmp::Reverse(priority), added.id)
+    });
+    (*components, *added) = entries.into_iter().unzip();
+
+    let mut render_order = (0..components.len()).collect::<Vec<_>>();
+    render_order.sort_by_key(|&idx| (components[idx].render_priority(), added[idx].id));
+    render_order
+}
+
+/// Returns the short name of a type, without its module path.
+fn short_type_name(name: &str) -> &str {
+    let base = name.split('<').next().unwrap_or(name);
+    let start = base.rfind("::").map_or(0, |idx| idx + 2);
+    &name[start..]
+}
+
+/// Returns the dependencies the components declare, but that are missing or not updated before
+/// the dependent component, as messages.
+///
+/// `components` must be in update order.
+fn dependency_problems<S: 'static>(
+    components: &[Box<dyn Component<S>>],
+    extensions: &AnyMap,
+) -> Vec<String> {
+    let mut problems = vec![];
+    for (idx, component) in components.iter().enumerate() {
+        let name = short_type_name(component.name());
+        for dependency in component.required_components() {
+            let dependency_name = short_type_name(dependency.name);
+            match components
+                .iter()
+                .position(|c| (**c).type_id() == dependency.type_id)
+            {
+                None => problems.push(format!(
+                    "{name} requires {dependency_name}, which was not added"
+                )),
+                Some(dependency_idx) if dependency_idx > idx => problems.push(format!(
+                    "{name} requires {dependency_name} to be added earlier or with a higher update priority"
+                )),
+                Some(_) => {}
+            }
+        }
+        for dependency in component.required_extensions() {
+            if !extensions.contains_type_id(dependency.type_id) {
+                problems.push(format!(
+                    "{name} requires the extension {}, which was not inserted during setup",
+                    short_type_name(dependency.name)
+                ));
+            }
+        }
+    }
+    problems
+}
+
+/// Renders all active components in `render_order`, then runs the queued draws of the frame.
+fn render_components<S: Default + 'static>(
+    components: &[Box<dyn Component<S>>],
+    added: &[Added],
+    render_order: &[usize],
+    shared_state: &mut SharedState<S>,
+    renderer: &mut dyn Renderer,
+) {
+    for (order, &idx) in render_order.iter().enumerate() {
+        let component = &components[idx];
+        if !shared_state.scenes.renders(added[idx].id)
+            || !shared_state.is_component_active(component.as_ref())
+        {
+            continue;
+        }
+        component.render(renderer, shared_state, order as i32 * 100);
+    }
+    let dropped = shared_state.draw_queue.run(renderer);
+    if dropped > 0 {
+        shared_state.debug_messages.push(
+            DebugMessage::warn_5s(format!("Draw queue full, dropped {dropped} draws"))
+                .with_dedup_key("draw queue full"),
+        );
+    }
+}
+
+/// Calls `on_quit` on all components in reverse order of addition, then waits until they have
+/// finished, for at most `timeout`. Returns the names of the components that did not finish.
+///
+/// `added` holds how the components were added, one entry per component.
+fn shut_down<S: 'static>(
+    components: &mut [Box<dyn Component<S>>],
+    added: &[Added],
+    shared_state: &mut SharedState<S>,
+    timeout: Duration,
+) -> Vec<&'static str> {
+    let deadline = Instant::now() + timeout;
+    // the components are sorted by priority, not by when they were added
+    let mut order = (0..components.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&idx| std::cmp::Reverse(added[idx].id));
+    for &idx in &order {
+        components[idx].on_quit(shared_state);
+    }
+    let mut finished = vec![false; components.len()];
+    loop {
+        for &idx in &order {
+            if !finished[idx] {
+                finished[idx] = components[idx].poll_quit_finished(shared_state);
+            }
+        }
+        if finished.iter().all(|f| *f) {
+            return vec![];
+        }
+        if Instant::now() >= deadline {
+            return order
+                .into_iter()
+                .filter(|&idx| !finished[idx])
+                .map(|idx| components[idx].name())
+                .collect();
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
 }
 
 /// Information about the screen size.
@@ -89,18 +618,60 @@ pub struct SharedState<S = ()> {
     pub mouse_pressed: MousePressedInfo,
     pub mouse_released: MouseReleasedInfo,
     pub mouse_events: MouseEvents,
-    pub target_fps: Option<f64>,
+    /// Double clicks and drags, see [`MouseGestures`].
+    pub mouse_gestures: MouseGestures,
+    /// Whether the terminal sends mouse events, see [`MouseCapture`].
+    pub mouse_capture: MouseCapture,
+    /// The frame pacing of the game loop, see [`FpsSettings`].
+    pub fps: FpsSettings,
     pub display_info: DisplayInfo,
+    /// Transformations of the final colors, applied in order. See [`PostProcess`].
+    pub post_processes: Vec<PostProcess>,
+    /// Draws that run after all components have rendered, see [`SharedState::draw_later`].
+    pub draw_queue: DrawQueue,
     pub pressed_keys: PressedKeys,
-    pub debounced_down_keys: HashSet<KeyCode>,
+    /// The keys that are held down, if the terminal reports releases. See [`HeldKeys`].
+    pub held_keys: HeldKeys,
+    /// The debounced down keys and their repeats, see
+    /// [`KeypressDebouncerComponent`](crate::components::keyboard::KeypressDebouncerComponent).
+    pub debounced_keys: DebouncedKeys,
     pub debug_info: DebugInfo,
-    pub debug_messages: SmallVec<[DebugMessage; 16]>,
+    pub debug_messages: DebugMessages,
+    /// Recoverable problems of the session, see [`Problems`].
+    pub problems: Problems,
+    /// Finds flicker in the flushed frames while enabled, see [`FlickerDetector`].
+    pub flicker_detector: FlickerDetector,
+    /// The terminal's clipboard, see [`Clipboard`].
+    pub clipboard: Clipboard,
+    /// Reserved regions and the corners of the built-in overlays, see [`OverlayLayoutManager`].
+    pub overlay_layout: OverlayLayoutManager,
+    /// The full-screen effect applied by the renderer, e.g. to dim the game behind a menu.
+    /// See [`Overlay`].
+    pub overlay: Option<Overlay>,
+    /// Requested snapshots of the screen, see [`FrameCapture`].
+    pub frame_capture: FrameCapture,
+    /// Set to write every cell in the next flush instead of only the changed ones, e.g. when the
+    /// output starts being recorded. Reset after the flush.
+    pub redraw_requested: bool,
     pub extensions: AnyMap,
+    /// Components to add and set up at the end of the frame. Their ids are assigned in the order
+    /// they were pushed, see [`Self::add_component`].
     pub components_to_add: Vec<Box<dyn Component<S>>>,
+    next_component_id: u64,
     pub fake_events_for_next_frame: Vec<Event>,
-    pub remove_components: HashSet<std::any::TypeId>,
-    pub whitelisted_components: Option<HashSet<std::any::TypeId>>,
+    /// Components to remove at the end of the frame, by type or by [`ComponentId`].
+    pub remove_components: RemoveComponents,
+    /// The stack of filters pushed by modals, only the top one applies.
+    component_filters: Vec<ComponentFilter>,
+    /// While true, only components that opt in with [`Component::runs_while_paused`] are
+    /// updated. Events and rendering continue, e.g. for a pause menu.
+    pub paused: bool,
     pub ui: UiProxy<S>,
+    /// The save slot screen, see [`SaveSlotsMenu`].
+    #[cfg(feature = "persistence")]
+    pub save_slots: SaveSlotsMenu,
+    /// Named bundles of components and the scene stack, see [`SceneManager`].
+    pub scenes: SceneManager<S>,
     pub custom: S,
 }
 
@@ -111,36 +682,198 @@ impl<S: Default + 'static> SharedState<S> {
             mouse_pressed: MousePressedInfo::default(),
             mouse_released: MouseReleasedInfo::default(),
             mouse_events: MouseEvents::new(),
-            target_fps: None,
+            mouse_gestures: MouseGestures::default(),
+            mouse_capture: MouseCapture::new(terminal_options().is_none_or(|o| o.mouse_capture)),
+            fps: FpsSettings::new(),
             display_info: DisplayInfo::new(width, height),
+            post_processes: Vec::new(),
+            draw_queue: DrawQueue::new(),
             pressed_keys: PressedKeys::new(),
-            debounced_down_keys: HashSet::new(),
+            held_keys: HeldKeys::new(),
+            debounced_keys: DebouncedKeys::new(),
             debug_info: DebugInfo::new(),
-            debug_messages: SmallVec::new(),
+            debug_messages: DebugMessages::new(),
+            problems: Problems::new(),
+            flicker_detector: FlickerDetector::new(),
+            clipboard: Clipboard::new(),
+            overlay_layout: OverlayLayoutManager::new(),
+            overlay: None,
+            frame_capture: FrameCapture::new(),
+            redraw_requested: false,
             extensions: AnyMap::new(),
             components_to_add: Vec::new(),
+            next_component_id: 0,
             fake_events_for_next_frame: Vec::new(),
-            remove_components: HashSet::new(),
-            whitelisted_components: None,
+            remove_components: RemoveComponents::new(),
+            component_filters: Vec::new(),
+            paused: false,
             ui: UiProxy::new(),
+            #[cfg(feature = "persistence")]
+            save_slots: SaveSlotsMenu::new(),
+            scenes: SceneManager::new(),
             custom: S::default(),
         }
     }
 
+    /// Registers a watcher that is evaluated every frame by the
+    /// [`WatchComponent`](crate::components::watch::WatchComponent).
+    ///
+    /// Changes of the returned value are recorded, see [`Watches`] for more information.
+    pub fn watch(
+        &mut self,
+        name: impl Into<String>,
+        f: impl Fn(&SharedState<S>) -> Option<String> + 'static,
+    ) {
+        self.ext_or_default::<Watches<S>>().add(name, f);
+    }
+
+    /// Queues a draw that runs this frame, after all components have rendered.
+    ///
+    /// This allows drawing from `on_event` and `update`, e.g. a ripple where a click happened.
+    /// Pixels are placed in a dedicated range on top of regular rendering, with later draws on
+    /// top. See [`DrawQueue`] for more information.
+    pub fn draw_later(&mut self, draw: impl FnOnce(&mut dyn Renderer) + 'static) {
+        self.draw_queue.push(draw);
+    }
+
+    /// Notifies the player, e.g. that a long-running round completed.
+    ///
+    /// Shown by the [`BackgroundNotifyComponent`](crate::components::notify::BackgroundNotifyComponent),
+    /// which escalates to a desktop notification if the terminal does not have focus.
+    pub fn notify(&mut self, title: impl Into<String>, body: impl Into<String>) {
+        self.ext_or_default::<Notifications>().push(title, body);
+    }
+
+    /// Shows a short message to the player, e.g. "Saved!".
+    ///
+    /// Shown by the [`ToastComponent`](crate::components::toast::ToastComponent), which slides it
+    /// in and fades it out again.
+    pub fn toast(&mut self, toast: Toast) {
+        self.ext_or_default::<ToastQueue>().push(toast);
+    }
+
+    /// Like [`SharedState::draw_later`], but places all pixels of the draw at `depth`.
+    pub fn draw_later_at(&mut self, depth: i32, draw: impl FnOnce(&mut dyn Renderer) + 'static) {
+        self.draw_queue.push_at(depth, draw);
+    }
+
     fn resize(&mut self, width: usize, height: usize) {
         self.display_info = DisplayInfo::new(width, height);
     }
 
     fn is_component_active(&self, component: &dyn Component<S>) -> bool {
-        if let Some(whitelist) = &self.whitelisted_components {
-            if !whitelist.contains(&component.type_id()) {
-                return false;
-            }
+        if !component.ignores_filters()
+            && let Some(filter) = self.component_filters.last()
+            && !filter.allows(component.type_id())
+        {
+            return false;
         }
         component.is_active(self)
     }
 }
 
+impl<S> SharedState<S> {
+    /// Adds a component at the end of the frame, like pushing it to [`Self::components_to_add`],
+    /// and returns the id it will have.
+    pub fn add_component(&mut self, component: Box<dyn Component<S>>) -> ComponentId {
+        let id = ComponentId(self.next_component_id + self.components_to_add.len() as u64);
+        self.components_to_add.push(component);
+        id
+    }
+
+    /// Restricts which components are active until the filter is popped again with
+    /// [`Self::pop_component_filter`], e.g. to freeze the game while a menu is open.
+    ///
+    /// Filters nest: only the filter on top applies, and popping it restores the previous one,
+    /// e.g. when a dialog opened from a menu closes.
+    pub fn push_component_filter(&mut self, filter: ComponentFilter) {
+        self.component_filters.push(filter);
+    }
+
+    /// Removes the filter on top, see [`Self::push_component_filter`].
+    pub fn pop_component_filter(&mut self) -> Option<ComponentFilter> {
+        self.component_filters.pop()
+    }
+
+    /// Returns the filter that currently applies, if any.
+    pub fn component_filter(&self) -> Option<&ComponentFilter> {
+        self.component_filters.last()
+    }
+
+    /// Places an overlay on the screen, see [`OverlayLayoutManager::place`].
+    ///
+    /// Shows a debug message when the overlay stops fitting anywhere.
+    pub fn place_overlay(
+        &mut self,
+        owner: &'static str,
+        anchor: OverlayAnchor,
+        width: usize,
+        height: usize,
+    ) -> Placement {
+        let fitted = self.overlay_layout.placement(owner).is_none_or(|p| p.fits);
+        let placement = self.overlay_layout.place(
+            owner,
+            anchor,
+            width,
+            height,
+            self.display_info.width(),
+            self.display_info.height(),
+        );
+        if fitted && !placement.fits {
+            self.debug_messages.push(DebugMessage::warn_5s(format!(
+                "No free corner for {owner}, it may cover reserved regions"
+            )));
+        }
+        placement
+    }
+
+    /// Returns the extension of type `T`, if any. See [`SharedState::extensions`].
+    pub fn ext<T: 'static>(&self) -> Option<&T> {
+        self.extensions.get()
+    }
+
+    /// Returns the extension of type `T` mutably, if any.
+    pub fn ext_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.extensions.get_mut()
+    }
+
+    /// Returns the extension of type `T`, inserting its default value on first access.
+    pub fn ext_or_default<T: Default + 'static>(&mut self) -> &mut T {
+        if !self.extensions.contains::<T>() {
+            self.extensions.insert(T::default());
+        }
+        self.extensions.get_mut().unwrap()
+    }
+
+    /// Returns the extension of type `T`.
+    ///
+    /// Panics with the name of the type and `context`, e.g. the calling component, if there is
+    /// none, which usually means that the component that inserts it was set up later.
+    pub fn ext_expect<T: 'static>(&self, context: &str) -> &T {
+        self.extensions.get().unwrap_or_else(|| {
+            panic!(
+                "{context}: missing extension {}, was it inserted in an earlier setup?",
+                std::any::type_name::<T>()
+            )
+        })
+    }
+
+    /// Calls `f` with the extension of type `T` and the rest of the shared state, and returns its
+    /// result, or `None` if there is no extension of type `T`.
+    ///
+    /// The extension is removed from [`SharedState::extensions`] during the call, so that `f` can
+    /// access both without borrowing the shared state twice.
+    pub fn with_ext<T: 'static, R>(
+        &mut self,
+        f: impl FnOnce(&mut T, &mut SharedState<S>) -> R,
+    ) -> Option<R> {
+        let mut ext = self.extensions.remove::<T>()?;
+        let result = f(&mut ext, self);
+        self.extensions.insert(ext);
+        Some(result)
+    }
+}
+
 /// Information used during the setup phase of the game.
 pub struct SetupInfo {
     /// The current screen size.
@@ -166,11 +899,27 @@ pub struct SetupInfo {
 /// ```
 pub struct Game<W: Write, S = ()> {
     display_renderer: DisplayRenderer<W>,
+    /// The components in update order.
     components: Vec<Box<dyn Component<S>>>,
+    added: Vec<Added>,
+    render_order: Vec<usize>,
     shared_state: SharedState<S>,
     event_read_thread_handle: Option<std::thread::JoinHandle<()>>,
     event_reader: Receiver<Event>,
+    event_writer: std::sync::mpsc::Sender<Event>,
     event_read_stop_signal: std::sync::mpsc::Sender<()>,
+    quit_gate: QuitGate,
+    dependency_check: DependencyCheck,
+    quit_after_frame: bool,
+    is_set_up: bool,
+    /// The simulated time of the last frame of [`Game::run_frames`].
+    simulated_time: Option<Instant>,
+    /// The requested height of the strip of an inline game, see [`Game::new_inline`].
+    inline_rows: Option<usize>,
+    /// The event that woke up a [`FpsMode::PowerSaver`] wait, handled in the next frame.
+    woken_by: Option<Event>,
+    /// The number of frames run so far, see [`UpdateInfo::frame_number`].
+    frame_number: u64,
 }
 
 impl<S: Default + 'static> Game<CustomBufWriter, S> {
@@ -182,6 +931,31 @@ impl<S: Default + 'static> Game<CustomBufWriter, S> {
     }
 }
 
+impl<S: Default + 'static> Game<Vec<u8>, S> {
+    /// Creates a game of `width` x `height` cells that does not need a terminal, e.g. for tests.
+    ///
+    /// The game renders into a `Vec<u8>` and reads no events from the terminal. Inject events
+    /// with [`Game::push_event`], run it with [`Game::run_frames`] and inspect the last frame
+    /// with [`Game::frame`].
+    ///
+    /// # Example
+    /// ```
+    /// use crossterm::event::{Event, KeyCode, KeyEvent};
+    /// use teng::Game;
+    /// use teng::components::keyboard::KeyPressRecorderComponent;
+    ///
+    /// let mut game: Game<_, ()> = Game::new_headless(20, 10);
+    /// game.add_component(Box::new(KeyPressRecorderComponent::new()));
+    /// game.push_event(Event::Key(KeyEvent::from(KeyCode::Char('a'))));
+    /// game.run_frames(1).unwrap();
+    /// assert!(game.shared_state().pressed_keys.did_press_char('a'));
+    /// assert_eq!(game.frame().width(), 20);
+    /// ```
+    pub fn new_headless(width: usize, height: usize) -> Self {
+        Self::new_headless_with_sink(width, height, vec![])
+    }
+}
+
 impl<S: Default + 'static> Game<Stdout, S> {
     pub fn new_with_stdout() -> Self {
         let stdout = stdout();
@@ -195,12 +969,15 @@ impl<W: Write, S: Default + 'static> Game<W, S> {
         let (width, height) = crossterm::terminal::size().unwrap();
         let width = width as usize;
         let height = height as usize;
-        let display_renderer = DisplayRenderer::new_with_sink(width, height, sink);
+        let mut display_renderer = DisplayRenderer::new_with_sink(width, height, sink);
+        display_renderer.set_color_mode(ColorMode::detect());
 
         let (event_writer, event_reader) = std::sync::mpsc::channel();
         let (event_read_stop_signal, event_read_stop_receiver) = std::sync::mpsc::channel();
 
+        let thread_event_writer = event_writer.clone();
         let event_read_thread_handle = std::thread::spawn(move || {
+            let event_writer = thread_event_writer;
             loop {
                 if crossterm::event::poll(Duration::from_millis(10)).unwrap() {
                     if let Ok(event) = crossterm::event::read() {
@@ -216,13 +993,110 @@ impl<W: Write, S: Default + 'static> Game<W, S> {
         Self {
             display_renderer,
             components: Vec::new(),
+            added: Vec::new(),
+            render_order: Vec::new(),
             shared_state: SharedState::<S>::new(width, height),
             event_read_thread_handle: Some(event_read_thread_handle),
             event_reader,
+            event_writer,
+            event_read_stop_signal,
+            quit_gate: QuitGate::new(),
+            dependency_check: DependencyCheck::default(),
+            quit_after_frame: false,
+            is_set_up: false,
+            simulated_time: None,
+            inline_rows: None,
+            woken_by: None,
+            frame_number: 0,
+        }
+    }
+
+    /// Creates a game that renders inline into the normal screen, in a strip of `rows` rows at the
+    /// cursor, like a progress display. The rest of the screen and the scrollback are kept.
+    ///
+    /// The strip is as wide as the terminal and at most as high. [`DisplayInfo`] reports the
+    /// strip's size, and when the terminal is resized, the strip is cleared and reserved again.
+    /// When the game ends, the strip is cleared and the cursor is left at its start.
+    ///
+    /// Set up the terminal with [`terminal_setup_with`] without the alternate screen. Mouse
+    /// positions are relative to the screen, not the strip, so mouse capture is best left out:
+    ///
+    /// ```no_run
+    /// # use teng::{Game, TerminalOptions, terminal_setup_with};
+    /// terminal_setup_with(TerminalOptions {
+    ///     alternate_screen: false,
+    ///     mouse_capture: false,
+    ///     ..TerminalOptions::default()
+    /// })?;
+    /// let mut game: Game<_, ()> = Game::new_inline(5, std::io::stdout())?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn new_inline(rows: u16, sink: W) -> io::Result<Self> {
+        let mut game = Self::new(sink);
+        game.make_inline(rows as usize)?;
+        Ok(game)
+    }
+
+    /// Shrinks the game to the inline strip and reserves it, see [`Game::new_inline`].
+    fn make_inline(&mut self, rows: usize) -> io::Result<()> {
+        self.inline_rows = Some(rows);
+        let (width, height) = (
+            self.display_renderer.width(),
+            self.display_renderer.height(),
+        );
+        let rows = rows.clamp(1, height.max(1));
+        self.display_renderer.resize_discard(width, rows);
+        self.shared_state.resize(width, rows);
+        self.display_renderer.begin_inline()
+    }
+
+    /// Like [`Game::new_headless`], but renders into `sink`.
+    pub fn new_headless_with_sink(width: usize, height: usize, sink: W) -> Self {
+        let (event_writer, event_reader) = std::sync::mpsc::channel();
+        let (event_read_stop_signal, _) = std::sync::mpsc::channel();
+        Self {
+            display_renderer: DisplayRenderer::new_with_sink(width, height, sink),
+            components: Vec::new(),
+            added: Vec::new(),
+            render_order: Vec::new(),
+            shared_state: SharedState::<S>::new(width, height),
+            event_read_thread_handle: None,
+            event_reader,
+            event_writer,
             event_read_stop_signal,
+            quit_gate: QuitGate::new(),
+            dependency_check: DependencyCheck::default(),
+            quit_after_frame: false,
+            is_set_up: false,
+            simulated_time: None,
+            inline_rows: None,
+            woken_by: None,
+            frame_number: 0,
         }
     }
 
+    /// Queues an event as if the terminal had sent it. It is handled in the next frame, in the
+    /// order of queueing.
+    pub fn push_event(&mut self, event: Event) {
+        // the receiver lives as long as the game
+        self.event_writer.send(event).unwrap();
+    }
+
+    /// Returns the last frame that was rendered.
+    pub fn frame(&self) -> FrameView<'_> {
+        self.display_renderer.previous_frame()
+    }
+
+    /// Returns the shared state, e.g. to inspect it in tests.
+    pub fn shared_state(&self) -> &SharedState<S> {
+        &self.shared_state
+    }
+
+    /// Returns the shared state, e.g. to prepare it in tests.
+    pub fn shared_state_mut(&mut self) -> &mut SharedState<S> {
+        &mut self.shared_state
+    }
+
     fn width(&self) -> usize {
         self.display_renderer.width()
     }
@@ -233,10 +1107,49 @@ impl<W: Write, S: Default + 'static> Game<W, S> {
 
     /// Adds a component to the game.
     ///
-    /// The component will be added to the end of the list of components, giving it a later
-    /// update order, but a higher render priority.
-    pub fn add_component(&mut self, component: Box<dyn Component<S>>) {
+    /// Components are updated in order of their [`Component::update_priority`] and rendered in
+    /// order of their [`Component::render_priority`]. Among components with the same priority,
+    /// a component added later is updated later, but rendered on top.
+    ///
+    /// Returns the id of the component, e.g. for [`Self::remove_component`].
+    pub fn add_component(&mut self, component: Box<dyn Component<S>>) -> ComponentId {
+        self.push_component(component, None)
+    }
+
+    /// Adds a component to the game with an update priority that overrides its
+    /// [`Component::update_priority`].
+    pub fn add_component_with_priority(
+        &mut self,
+        component: Box<dyn Component<S>>,
+        priority: Priority,
+    ) -> ComponentId {
+        self.push_component(component, Some(priority))
+    }
+
+    fn push_component(
+        &mut self,
+        component: Box<dyn Component<S>>,
+        priority_override: Option<Priority>,
+    ) -> ComponentId {
+        let id = ComponentId::next(&mut self.shared_state.next_component_id);
         self.components.push(component);
+        self.added.push(Added::new(id, priority_override));
+        self.sort_components();
+        id
+    }
+
+    /// Removes the component with the given id, and returns it after calling its
+    /// [`Component::on_removed`], e.g. to read its final state. Returns `None` if there is no
+    /// such component.
+    ///
+    /// Components can also be removed during the game with [`SharedState::remove_components`].
+    pub fn remove_component(&mut self, id: ComponentId) -> Option<Box<dyn Component<S>>> {
+        let idx = self.added.iter().position(|added| added.id == id)?;
+        let mut component = self.components.remove(idx);
+        self.added.remove(idx);
+        self.sort_components();
+        component.on_removed(&mut self.shared_state);
+        Some(component)
     }
 
     // TODO: remove this? or rework once we have a new() function on the Component trait
@@ -244,69 +1157,161 @@ impl<W: Write, S: Default + 'static> Game<W, S> {
     pub fn add_component_with(
         &mut self,
         init_fn: impl FnOnce(usize, usize) -> Box<dyn Component<S>>,
-    ) {
-        self.components.push(init_fn(self.width(), self.height()));
+    ) -> ComponentId {
+        self.add_component(init_fn(self.width(), self.height()))
     }
 
-    /// Runs the game loop.
-    ///
-    /// This function will block until the game loop is finished, which happens when a component
-    /// returns [`BreakingAction::Quit`].
-    pub fn run(&mut self) -> io::Result<()> {
-        // TODO: think about taking ownership of self and making `event_read_thread_handle` non-optional
-        // Right now it feels like you can just run `run` multiple times, but this will not spawn new event reader threads.
-        // Better fix perhaps: Move the event read setup into self.setup().
+    /// Sets the update interval of the components of type `C`, overriding their
+    /// [`Component::update_interval`].
+    pub fn set_component_interval<C: Component<S>>(&mut self, interval: Duration) {
+        for (component, added) in self.components.iter().zip(self.added.iter_mut()) {
+            if (**component).type_id() == std::any::TypeId::of::<C>() {
+                added.interval_override = Some(interval);
+            }
+        }
+    }
 
-        // Setup phase
-        self.setup()?;
+    fn sort_components(&mut self) {
+        self.render_order = sort_components(&mut self.components, &mut self.added);
+    }
 
-        // Game loop
-        let mut last_frame = Instant::now();
-        let mut now = Instant::now();
-        // how much longer the last sleep() slept than expected.
-        let mut last_overhead = Duration::from_nanos(0);
+    /// Sets what happens when components are missing the components and extensions they declare
+    /// with [`Component::required_components`] and [`Component::required_extensions`], which is
+    /// checked after the setup of all components. By default, the setup fails.
+    pub fn set_dependency_check(&mut self, check: DependencyCheck) {
+        self.dependency_check = check;
+    }
 
-        // how long the last frame's computations took
-        let mut last_actual_dt = 1.0;
+    /// Sets when a quit request that components defer is forced: after `requests` quit requests
+    /// in total, or once it has been pending for `timeout`.
+    ///
+    /// The default is three requests and no timeout.
+    /// See [`Component::on_quit_requested`] for more information.
+    pub fn set_force_quit(&mut self, requests: usize, timeout: Option<Duration>) {
+        self.quit_gate.force_quit_requests = requests.max(1);
+        self.quit_gate.force_quit_timeout = timeout;
+    }
 
-        loop {
-            let nanos_per_frame = if let Some(target_fps) = self.shared_state.target_fps {
-                (1.0 / target_fps * 1_000_000_000.0) as u64
-            } else {
-                0
-            };
+    /// Sets how long the game waits for components to finish their quit work, see
+    /// [`Component::poll_quit_finished`]. Afterwards, the game exits anyway, and
+    /// [`terminal_cleanup`] prints the names of the components that did not finish.
+    ///
+    /// The default is five seconds.
+    pub fn set_quit_finish_timeout(&mut self, timeout: Duration) {
+        self.quit_gate.finish_timeout = timeout;
+    }
 
-            let update_info = UpdateInfo {
-                last_time: last_frame,
-                current_time: now,
-                dt: (now - last_frame).as_secs_f64(),
+    /// Sets the colors the terminal supports, overriding the mode detected by [`ColorMode::detect`].
+    ///
+    /// Headless games default to [`ColorMode::TrueColor`].
+    pub fn set_color_mode(&mut self, color_mode: ColorMode) {
+        self.display_renderer.set_color_mode(color_mode);
+    }
+
+    /// Sets whether images are shown with the kitty graphics protocol, e.g. from
+    /// [`detect_kitty_graphics`](rendering::kitty::detect_kitty_graphics).
+    ///
+    /// The default is false, which draws images with half blocks. See
+    /// [`KittyImageRender`](rendering::render::KittyImageRender).
+    pub fn set_kitty_graphics(&mut self, enabled: bool) {
+        self.display_renderer.set_kitty_graphics(enabled);
+    }
+
+    /// Sets the size of a terminal cell in pixels, e.g. from
+    /// [`sixel::cell_size`](rendering::sixel::cell_size), which enables sixel graphics.
+    ///
+    /// The default is `None`, which draws images with half blocks. See
+    /// [`SixelRender`](rendering::render::SixelRender).
+    pub fn set_sixel_cell_size(&mut self, cell_size: Option<(usize, usize)>) {
+        self.display_renderer.set_sixel_cell_size(cell_size);
+    }
+
+    /// Runs `frames` frames without sleeping between them, then returns. Returns true if the game
+    /// quit, in which case fewer frames may have run.
+    ///
+    /// Every frame advances the time by `1 / target_fps` seconds of [`FpsMode::Target`], or 1/60
+    /// seconds in the other modes, so the result does not depend on how fast the frames run. The
+    /// first call runs the setup of all components. Meant for headless games, see
+    /// [`Game::new_headless`].
+    pub fn run_frames(&mut self, frames: usize) -> io::Result<bool> {
+        self.setup()?;
+        for _ in 0..frames {
+            let dt = 1.0 / self.shared_state.fps.target_fps().unwrap_or(60.0);
+            let last_time = *self.simulated_time.get_or_insert_with(Instant::now);
+            let current_time = last_time + Duration::from_secs_f64(dt);
+            self.simulated_time = Some(current_time);
+            let update_info = UpdateInfo {
+                last_time,
+                current_time,
+                dt,
+                actual_dt: 0.0,
+                frame_number: self.frame_number,
+            };
+            if self.run_frame(update_info)? {
+                self.cleanup();
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Runs the game loop.
+    ///
+    /// This function will block until the game loop is finished, which happens when a component
+    /// returns [`BreakingAction::Quit`] or [`BreakingAction::QuitAfterFrame`] and no component
+    /// vetoes or defers it, see [`Component::on_quit_requested`].
+    ///
+    /// Before returning, `on_quit` is called on all components in reverse order, and `run` waits
+    /// until all components report that their quit work is finished, see
+    /// [`Component::poll_quit_finished`] and [`Game::set_quit_finish_timeout`].
+    pub fn run(&mut self) -> io::Result<()> {
+        // TODO: think about taking ownership of self and making `event_read_thread_handle` non-optional
+        // Right now it feels like you can just run `run` multiple times, but this will not spawn new event reader threads.
+        // Better fix perhaps: Move the event read setup into self.setup().
+
+        // Setup phase
+        self.setup()?;
+
+        // Game loop
+        let mut last_frame = Instant::now();
+        let mut now = Instant::now();
+        let mut pacer = FramePacer::new();
+
+        // how long the last frame's computations took
+        let mut last_actual_dt = 1.0;
+
+        loop {
+            let update_info = UpdateInfo {
+                last_time: last_frame,
+                current_time: now,
+                dt: (now - last_frame).as_secs_f64(),
                 actual_dt: last_actual_dt,
+                frame_number: self.frame_number,
             };
 
-            if let Some(action) = self.consume_events()? {
-                match action {
-                    BreakingAction::Quit => break,
-                }
+            if self.run_frame(update_info)? {
+                break;
             }
 
-            self.update(update_info);
-            self.render()?;
-            self.display_renderer.reset_screen();
-
-            // Sleep until the next frame
             let current = Instant::now();
             last_actual_dt = current.duration_since(now).as_secs_f64();
-            let this_frame_so_far = current.duration_since(now);
-            let remaining_time =
-                Duration::from_nanos(nanos_per_frame).saturating_sub(this_frame_so_far);
-            // sleep less by last frame's overhead
-            let remaining_time = remaining_time.saturating_sub(last_overhead);
-            std::thread::sleep(remaining_time);
-            let new_now = Instant::now();
-
-            let time_slept = new_now.duration_since(current);
-            let overhead = time_slept.saturating_sub(remaining_time);
-            last_overhead = overhead;
+            let new_now = match self.shared_state.fps.mode {
+                FpsMode::Unlimited => {
+                    pacer.reset();
+                    current
+                }
+                FpsMode::Target(target_fps) => {
+                    let frame_duration = Duration::from_secs_f64(1.0 / target_fps);
+                    let deadline = pacer.next_deadline(now, current, frame_duration);
+                    FramePacer::sleep_until(deadline);
+                    Instant::now()
+                }
+                FpsMode::PowerSaver => {
+                    pacer.reset();
+                    self.wait_for_event(self.shared_state.fps.power_saver_timeout);
+                    Instant::now()
+                }
+            };
 
             // // note: 'last' from perspective of next iteration
             last_frame = now;
@@ -318,9 +1323,57 @@ impl<W: Write, S: Default + 'static> Game<W, S> {
         Ok(())
     }
 
+    /// Blocks until an event arrives or `timeout` passes, for [`FpsMode::PowerSaver`]. Returns
+    /// right away if fake events or a quit are pending.
+    fn wait_for_event(&mut self, timeout: Duration) {
+        if self.woken_by.is_some()
+            || !self.shared_state.fake_events_for_next_frame.is_empty()
+            || self.quit_gate.pending.is_some()
+        {
+            return;
+        }
+        match self.event_reader.recv_timeout(timeout) {
+            Ok(event) => self.woken_by = Some(event),
+            Err(RecvTimeoutError::Timeout) => {}
+            // cannot happen while the game holds a sender, but do not spin if it does
+            Err(RecvTimeoutError::Disconnected) => std::thread::sleep(timeout),
+        }
+    }
+
+    /// Handles the events, updates and renders one frame. Returns true if the game quits.
+    fn run_frame(&mut self, update_info: UpdateInfo) -> io::Result<bool> {
+        let quit = match self.consume_events()? {
+            Some(BreakingAction::Quit) => self
+                .quit_gate
+                .request(&mut self.components, &mut self.shared_state),
+            _ => self
+                .quit_gate
+                .poll(&mut self.components, &mut self.shared_state),
+        };
+        if quit {
+            return Ok(true);
+        }
+
+        self.update(update_info);
+        self.render()?;
+        self.display_renderer.reset_screen();
+        self.frame_number += 1;
+
+        Ok(std::mem::take(&mut self.quit_after_frame)
+            && self
+                .quit_gate
+                .request(&mut self.components, &mut self.shared_state))
+    }
+
     fn consume_events(&mut self) -> io::Result<Option<BreakingAction>> {
+        // the event that ended the wait of the last frame comes first
+        if let Some(event) = self.woken_by.take()
+            && let Some(action) = self.on_event_breaking(event)
+        {
+            return Ok(Some(action));
+        }
         while let Ok(event) = self.event_reader.try_recv() {
-            if let Some(action) = self.on_event(event) {
+            if let Some(action) = self.on_event_breaking(event) {
                 return Ok(Some(action));
             }
         }
@@ -328,7 +1381,7 @@ impl<W: Write, S: Default + 'static> Game<W, S> {
         // fake events for next frame
         let events = std::mem::replace(&mut self.shared_state.fake_events_for_next_frame, vec![]);
         for event in events {
-            if let Some(action) = self.on_event(event) {
+            if let Some(action) = self.on_event_breaking(event) {
                 return Ok(Some(action));
             }
         }
@@ -336,13 +1389,41 @@ impl<W: Write, S: Default + 'static> Game<W, S> {
         Ok(None)
     }
 
+    /// Handles an event. Returns the actions that break out of the event loop.
+    fn on_event_breaking(&mut self, event: Event) -> Option<BreakingAction> {
+        match self.on_event(event) {
+            Some(BreakingAction::QuitAfterFrame) => {
+                self.quit_after_frame = true;
+                None
+            }
+            action => action,
+        }
+    }
+
     fn on_event(&mut self, event: Event) -> Option<BreakingAction> {
-        for component in self.components.iter_mut() {
-            if !self.shared_state.is_component_active(component.as_ref()) {
+        let event = match event {
+            Event::Paste(text) => Event::Paste(util::clipboard::limit_paste(
+                text,
+                &mut self.shared_state.problems,
+            )),
+            event => event,
+        };
+        let normalized = normalize_event(event.clone());
+        for (component, added) in self.components.iter_mut().zip(&self.added) {
+            if !self.shared_state.scenes.runs(added.id)
+                || !self.shared_state.is_component_active(component.as_ref())
+            {
                 continue;
             }
-            if let Some(action) = component.on_event(event.clone(), &mut self.shared_state) {
-                return Some(action);
+            let event = if component.wants_raw_events() {
+                event.clone()
+            } else {
+                normalized.clone()
+            };
+            match component.on_event(event, &mut self.shared_state) {
+                Some(BreakingAction::ConsumeEvent) => return None,
+                Some(action) => return Some(action),
+                None => {}
             }
         }
 
@@ -363,7 +1444,15 @@ impl<W: Write, S: Default + 'static> Game<W, S> {
     }
 
     fn on_resize(&mut self, width: usize, height: usize) {
+        let height = match self.inline_rows {
+            Some(rows) => rows.clamp(1, height.max(1)),
+            None => height,
+        };
         self.display_renderer.resize_discard(width, height);
+        if self.display_renderer.is_inline() {
+            // the terminal may have reflowed the strip, errors show up with the next flush
+            let _ = self.display_renderer.begin_inline();
+        }
         self.shared_state.resize(width, height);
         for component in self.components.iter_mut() {
             if !self.shared_state.is_component_active(component.as_ref()) {
@@ -374,46 +1463,25 @@ impl<W: Write, S: Default + 'static> Game<W, S> {
     }
 
     fn update(&mut self, update_info: UpdateInfo) {
-        for component in self.components.iter_mut() {
-            if !self.shared_state.is_component_active(component.as_ref()) {
+        for (component, added) in self.components.iter_mut().zip(self.added.iter_mut()) {
+            if !self.shared_state.scenes.runs(added.id)
+                || !self.shared_state.is_component_active(component.as_ref())
+                || (self.shared_state.paused && !component.runs_while_paused())
+            {
+                // the time while inactive, e.g. paused, is not passed on
+                added.last_update = None;
                 continue;
             }
-            component.update(update_info, &mut self.shared_state);
-        }
-        self.update_game(update_info);
-    }
-
-    fn swap_component<C: Component<S>>(
-        &mut self,
-        new: impl FnOnce(usize, usize) -> Box<dyn Component<S>>,
-    ) {
-        let mut found = false;
-        for idx in 0..self.components.len() {
-            if (&*self.components[idx]).type_id() == std::any::TypeId::of::<C>() {
-                self.components.remove(idx);
-                found = true;
-                break;
+            let interval = added
+                .interval_override
+                .or_else(|| component.update_interval());
+            if let Some(update_info) = added.due(interval, update_info) {
+                component.update(update_info, &mut self.shared_state);
             }
         }
-        if !found {
-            self.add_component(new(self.width(), self.height()));
-        }
+        self.update_game(update_info);
     }
 
-    // fn swap_component_dynamic(&mut self, new: Box<dyn Component>) {
-    //     let mut found = false;
-    //     for idx in 0..self.components.len() {
-    //         if (&*self.components[idx]).type_id() == (&*new).type_id() {
-    //             self.components.remove(idx);
-    //             found = true;
-    //             break;
-    //         }
-    //     }
-    //     if !found {
-    //         self.add_component(new);
-    //     }
-    // }
-
     fn update_game(&mut self, update_info: UpdateInfo) {
         // TODO: Only swap component if it existed in the first place, or add some config option
         if self
@@ -421,109 +1489,404 @@ impl<W: Write, S: Default + 'static> Game<W, S> {
             .pressed_keys
             .did_press_char_ignore_case('i')
         {
-            self.swap_component::<DebugInfoComponent>(|width, height| {
-                Box::new(DebugInfoComponent::new())
+            let debug_info = self.components.iter().position(|component| {
+                (**component).type_id() == std::any::TypeId::of::<DebugInfoComponent>()
             });
+            match debug_info {
+                Some(idx) => {
+                    self.shared_state.remove_components.insert(self.added[idx].id);
+                }
+                None => {
+                    self.shared_state
+                        .add_component(Box::new(DebugInfoComponent::new()));
+                }
+            }
+            // frees its corner if it was removed, otherwise it is placed again in its update
+            self.shared_state
+                .overlay_layout
+                .release(DebugInfoComponent::OVERLAY);
+        }
+        let mut changed = false;
+        // taken first, so that components can mark others for removal in `on_removed`
+        let remove_components = std::mem::take(&mut self.shared_state.remove_components);
+        if !remove_components.is_empty() {
+            let mut idx = 0;
+            while idx < self.components.len() {
+                if remove_components.matches(self.components[idx].as_ref(), self.added[idx].id) {
+                    let mut component = self.components.remove(idx);
+                    self.added.remove(idx);
+                    component.on_removed(&mut self.shared_state);
+                    changed = true;
+                } else {
+                    idx += 1;
+                }
+            }
         }
-        for remove_component in self.shared_state.remove_components.drain() {
-            self.components.retain(|c| c.type_id() != remove_component);
+        // scene components are added when their scene is entered for the first time
+        let mut scenes = std::mem::take(&mut self.shared_state.scenes);
+        let scene_changes = scenes.apply_transitions(&mut self.shared_state);
+        self.shared_state.scenes = scenes;
+        self.notify_components(&scene_changes.exited, |component, shared_state| {
+            component.on_scene_exit(shared_state)
+        });
+        let already_setup_components = self.components.len();
+        append_components_to_add(
+            &mut self.components,
+            &mut self.added,
+            &mut self.shared_state,
+        );
+        setup_components(
+            &mut self.components,
+            &mut self.added,
+            already_setup_components,
+            &mut self.shared_state,
+        );
+        self.notify_components(&scene_changes.entered, |component, shared_state| {
+            component.on_scene_enter(shared_state)
+        });
+        if changed || self.components.len() != already_setup_components {
+            self.sort_components();
         }
-        for new_component in self.shared_state.components_to_add.drain(..) {
-            // TODO: these components need to be setup() as well
-            self.components.push(new_component);
+    }
+
+    /// Calls `notify` for the components with the given ids that are still in the game.
+    fn notify_components(
+        &mut self,
+        ids: &[ComponentId],
+        notify: impl Fn(&mut dyn Component<S>, &mut SharedState<S>),
+    ) {
+        for id in ids {
+            if let Some(idx) = self.added.iter().position(|added| added.id == *id) {
+                notify(self.components[idx].as_mut(), &mut self.shared_state);
+            }
         }
     }
 
     fn render(&mut self) -> io::Result<()> {
-        for (idx, component) in self.components.iter().enumerate() {
-            if !self.shared_state.is_component_active(component.as_ref()) {
-                continue;
-            }
-            component.render(
-                &mut self.display_renderer,
-                &self.shared_state,
-                idx as i32 * 100,
+        if self.display_renderer.post_processes() != self.shared_state.post_processes {
+            self.display_renderer
+                .set_post_processes(self.shared_state.post_processes.clone());
+        }
+        self.display_renderer.set_overlay(self.shared_state.overlay);
+        render_components(
+            &self.components,
+            &self.added,
+            &self.render_order,
+            &mut self.shared_state,
+            &mut self.display_renderer,
+        );
+        if let Some(sequence) = self
+            .shared_state
+            .clipboard
+            .take_pending(&mut self.shared_state.problems)
+        {
+            self.display_renderer.queue_escape(&sequence);
+        }
+        if let Some(escapes) = self
+            .shared_state
+            .ext_mut::<Notifications>()
+            .and_then(Notifications::take_escapes)
+        {
+            self.display_renderer.queue_escape(&escapes);
+        }
+        if let Some(enabled) = self.shared_state.mouse_capture.take_request() {
+            self.set_mouse_capture(enabled);
+        }
+        if std::mem::take(&mut self.shared_state.redraw_requested) {
+            self.display_renderer.force_redraw();
+        }
+        self.display_renderer.flush()?;
+        let renderer = &self.display_renderer;
+        self.shared_state
+            .frame_capture
+            .fulfill(|| renderer.capture_frame());
+        if self.shared_state.flicker_detector.is_enabled() {
+            let names: Vec<_> = self
+                .render_order
+                .iter()
+                .map(|&idx| self.components[idx].name())
+                .collect();
+            self.shared_state.flicker_detector.observe(
+                &renderer.previous_frame(),
+                |x, y| Some(renderer.depth_at(x, y)).filter(|&depth| depth != i32::MIN),
+                &names,
             );
         }
-        self.display_renderer.flush()
+        Ok(())
+    }
+
+    /// Writes the escape sequence that toggles mouse capture with the next frame.
+    fn set_mouse_capture(&mut self, enabled: bool) {
+        let mut sequence = String::new();
+        let written = if enabled {
+            EnableMouseCapture.write_ansi(&mut sequence)
+        } else {
+            DisableMouseCapture.write_ansi(&mut sequence)
+        };
+        if written.is_ok() {
+            self.display_renderer.queue_escape(&sequence);
+        }
+        if let Some(options) = TERMINAL_OPTIONS.lock().unwrap().as_mut() {
+            options.mouse_capture = enabled;
+        }
+        if enabled {
+            // the terminal may have changed the screen, e.g. with its own selection
+            self.display_renderer.force_redraw();
+        }
     }
 
     fn setup(&mut self) -> io::Result<()> {
-        let setup_info = SetupInfo {
-            display_info: self.shared_state.display_info.clone(),
-        };
-        let mut already_setup_components = 0;
-        while already_setup_components < self.components.len() {
-            let component = &mut self.components[already_setup_components];
-            component.setup(&setup_info, &mut self.shared_state);
-            for new_component in self.shared_state.components_to_add.drain(..) {
-                self.components.push(new_component);
-            }
-            already_setup_components += 1;
+        if std::mem::replace(&mut self.is_set_up, true) {
+            return Ok(());
+        }
+        setup_components(
+            &mut self.components,
+            &mut self.added,
+            0,
+            &mut self.shared_state,
+        );
+        self.sort_components();
+        self.check_dependencies()
+    }
+
+    fn check_dependencies(&mut self) -> io::Result<()> {
+        if self.dependency_check == DependencyCheck::Off {
+            return Ok(());
+        }
+        let problems = dependency_problems(&self.components, &self.shared_state.extensions);
+        if problems.is_empty() {
+            return Ok(());
+        }
+        if self.dependency_check == DependencyCheck::Error {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("missing component dependencies:\n{}", problems.join("\n")),
+            ));
+        }
+        for problem in problems {
+            self.shared_state
+                .debug_messages
+                .push(DebugMessage::error_5s(problem));
         }
         Ok(())
     }
 
     fn cleanup(&mut self) {
-        for component in self.components.iter_mut() {
-            component.on_quit(&mut self.shared_state);
+        let unfinished = shut_down(
+            &mut self.components,
+            &self.added,
+            &mut self.shared_state,
+            self.quit_gate.finish_timeout,
+        );
+        // reported once the terminal is restored, where the message is visible
+        UNFINISHED_QUITS.lock().unwrap().extend(unfinished);
+        // the game is over, there is nowhere to report errors
+        let _ = self.display_renderer.end_inline();
+
+        // headless games have no event reader thread
+        if let Some(handle) = self.event_read_thread_handle.take() {
+            self.event_read_stop_signal.send(()).unwrap();
+            handle.join().unwrap();
         }
-
-        self.event_read_stop_signal.send(()).unwrap();
-        self.event_read_thread_handle
-            .take()
-            .unwrap()
-            .join()
-            .unwrap();
     }
 
     pub fn install_recommended_components(&mut self) {
         self.add_component(Box::new(KeyPressRecorderComponent::new()));
         self.add_component(Box::new(FpsLockerComponent::new(144.0)));
         self.add_component(Box::new(MouseTrackerComponent::new()));
-        self.add_component(Box::new(QuitterComponent));
+        self.add_component(Box::new(QuitterComponent::new()));
+    }
+}
+
+/// Which terminal features [`terminal_setup_with`] enables.
+///
+/// The default enables everything, like [`terminal_setup`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TerminalOptions {
+    /// Tells the terminal to send mouse events. While enabled, the terminal's own text selection
+    /// usually needs a modifier, e.g. Shift. Can be toggled at runtime with
+    /// [`SharedState::mouse_capture`].
+    pub mouse_capture: bool,
+    /// Draws on the alternate screen, which restores the previous terminal contents on cleanup.
+    pub alternate_screen: bool,
+    /// Hides the cursor.
+    pub hide_cursor: bool,
+    /// Delivers pastes as a single [`Event::Paste`], see [`clipboard`](crate::util::clipboard).
+    pub bracketed_paste: bool,
+    /// Reports [`Event::FocusGained`] and [`Event::FocusLost`] when the terminal window gains or
+    /// loses focus, e.g. for [`notify`](crate::components::notify).
+    pub focus_change: bool,
+}
+
+impl Default for TerminalOptions {
+    fn default() -> Self {
+        Self {
+            mouse_capture: true,
+            alternate_screen: true,
+            hide_cursor: true,
+            bracketed_paste: true,
+            focus_change: true,
+        }
     }
 }
 
+/// The options of the last [`terminal_setup_with`], updated when mouse capture is toggled.
+static TERMINAL_OPTIONS: Mutex<Option<TerminalOptions>> = Mutex::new(None);
+
+/// Returns the options the terminal was set up with, if it was.
+///
+/// [`TerminalOptions::mouse_capture`] reflects runtime changes by [`SharedState::mouse_capture`].
+pub fn terminal_options() -> Option<TerminalOptions> {
+    *TERMINAL_OPTIONS.lock().unwrap()
+}
+
 /// Sets up the terminal for the game.
 ///
 /// This function should be called before any other terminal functions.
 /// It sets up the terminal for raw mode, hides the cursor, tells the terminal to send mouse events,
-/// and enters the alternate screen.
+/// pastes and focus changes, and enters the alternate screen. Use [`terminal_setup_with`] to leave some of
+/// these out.
+///
+/// Pastes arrive as a single [`Event::Paste`] if the terminal supports bracketed paste. Enabling
+/// it is skipped where crossterm cannot, e.g. on legacy Windows consoles.
 ///
 /// It is recommended to call `install_panic_handler` after this function, and `terminal_cleanup` after the game loop.
 ///
 /// Note: If you are stuck in a bad terminal state, you can try running `reset` in the terminal.
 pub fn terminal_setup() -> io::Result<()> {
-    let mut stdout = stdout();
+    terminal_setup_with(TerminalOptions::default())
+}
 
-    execute!(stdout, crossterm::terminal::EnterAlternateScreen)?;
+/// Like [`terminal_setup`], but only enables the features selected by `options`. Raw mode is
+/// always enabled.
+///
+/// For example, a log viewer that does not need the mouse can leave out mouse capture, so that
+/// text can be selected and copied as usual.
+pub fn terminal_setup_with(options: TerminalOptions) -> io::Result<()> {
+    let mut stdout = stdout();
+    write_setup(&mut stdout, options)?;
     enable_raw_mode()?;
-    execute!(stdout, EnableMouseCapture)?;
-    // don't print cursor
-    execute!(stdout, cursor::Hide)?;
-
+    *TERMINAL_OPTIONS.lock().unwrap() = Some(options);
     Ok(())
 }
 
+fn write_setup(w: &mut impl Write, options: TerminalOptions) -> io::Result<()> {
+    if options.alternate_screen {
+        queue!(w, crossterm::terminal::EnterAlternateScreen)?;
+    }
+    if options.mouse_capture {
+        queue!(w, EnableMouseCapture)?;
+    }
+    if options.bracketed_paste {
+        // not every terminal supports bracketed paste, but without it pastes are still typed keys
+        let _ = queue!(w, EnableBracketedPaste);
+    }
+    if options.focus_change {
+        queue!(w, EnableFocusChange)?;
+    }
+    if options.hide_cursor {
+        // don't print cursor
+        queue!(w, cursor::Hide)?;
+    }
+    w.flush()
+}
+
+/// The names of the components that did not finish quitting in time, printed by
+/// [`terminal_cleanup`].
+static UNFINISHED_QUITS: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+/// Whether [`enable_key_release_events`] enabled release events.
+static KEY_RELEASE_EVENTS: AtomicBool = AtomicBool::new(false);
+
+/// Asks the terminal to report key releases and repeats, so that
+/// [`SharedState::held_keys`] knows which keys are held down.
+///
+/// Call this after [`terminal_setup`]. It returns false and changes nothing if the terminal does
+/// not support the kitty keyboard protocol, in which case key presses are reported as before.
+/// [`terminal_cleanup`] restores the previous reporting.
+///
+/// Once enabled, [`Event::Key`] events also have the kinds [`KeyEventKind::Release`] and
+/// [`KeyEventKind::Repeat`](crossterm::event::KeyEventKind::Repeat), so components that react to
+/// key events should check the kind.
+///
+/// [`KeyEventKind::Release`]: crossterm::event::KeyEventKind::Release
+pub fn enable_key_release_events() -> io::Result<bool> {
+    // the Windows console always reports releases
+    if cfg!(windows) {
+        return Ok(true);
+    }
+    if !crossterm::terminal::supports_keyboard_enhancement().unwrap_or(false) {
+        return Ok(false);
+    }
+    execute!(
+        stdout(),
+        PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+    )?;
+    KEY_RELEASE_EVENTS.store(true, Ordering::Relaxed);
+    Ok(true)
+}
+
+/// Returns true if the terminal reports key releases, see [`enable_key_release_events`].
+pub fn key_release_events_enabled() -> bool {
+    cfg!(windows) || KEY_RELEASE_EVENTS.load(Ordering::Relaxed)
+}
+
 /// Cleans up the terminal after the game.
 ///
 /// This function should be called after the game loop has finished. It resets everything done
-/// by `terminal_setup`.
+/// by `terminal_setup` or `terminal_setup_with`. Without a previous setup, it resets everything
+/// `terminal_setup` does.
 pub fn terminal_cleanup() -> io::Result<()> {
     let mut stdout = stdout();
-    execute!(stdout, DisableMouseCapture)?;
-    execute!(stdout, cursor::Show)?;
+    let options = TERMINAL_OPTIONS.lock().unwrap().take().unwrap_or_default();
+    if KEY_RELEASE_EVENTS.swap(false, Ordering::Relaxed) {
+        let _ = execute!(stdout, PopKeyboardEnhancementFlags);
+    }
+    let result = write_cleanup(&mut stdout, options, disable_raw_mode);
+    let unfinished = std::mem::take(&mut *UNFINISHED_QUITS.lock().unwrap());
+    if !unfinished.is_empty() {
+        eprintln!(
+            "components did not finish quitting in time: {}",
+            unfinished.join(", ")
+        );
+    }
+    result
+}
 
-    // show cursor
-    execute!(
-        stdout,
-        crossterm::terminal::Clear(crossterm::terminal::ClearType::All)
-    )?;
+fn write_cleanup(
+    w: &mut impl Write,
+    options: TerminalOptions,
+    disable_raw_mode: impl FnOnce() -> io::Result<()>,
+) -> io::Result<()> {
+    if rendering::kitty::IMAGES_SHOWN.swap(false, Ordering::Relaxed) {
+        w.write_all(rendering::kitty::DELETE_ALL.as_bytes())?;
+    }
+    if options.mouse_capture {
+        queue!(w, DisableMouseCapture)?;
+    }
+    if options.bracketed_paste {
+        let _ = queue!(w, DisableBracketedPaste);
+    }
+    if options.focus_change {
+        queue!(w, DisableFocusChange)?;
+    }
+    if options.hide_cursor {
+        // show cursor
+        queue!(w, cursor::Show)?;
+    }
+    if options.alternate_screen {
+        queue!(
+            w,
+            crossterm::terminal::Clear(crossterm::terminal::ClearType::All)
+        )?;
+    }
+    w.flush()?;
 
     disable_raw_mode()?;
 
-    execute!(stdout, crossterm::terminal::LeaveAlternateScreen)?;
+    if options.alternate_screen {
+        execute!(w, crossterm::terminal::LeaveAlternateScreen)?;
+    }
 
     Ok(())
 }
@@ -552,7 +1915,8 @@ pub struct CustomBufWriter {
 }
 
 impl CustomBufWriter {
-    fn new() -> Self {
+    /// Creates a writer that buffers everything until it is flushed to stdout.
+    pub fn new() -> Self {
         Self {
             buf: vec![],
             stdout: stdout(),
@@ -560,6 +1924,12 @@ impl CustomBufWriter {
     }
 }
 
+impl Default for CustomBufWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Write for CustomBufWriter {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         self.buf.extend_from_slice(buf);
@@ -574,3 +1944,989 @@ impl Write for CustomBufWriter {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::Dependency;
+    use crate::rendering::pixel::Pixel;
+    use crate::rendering::render::Render;
+    use crossterm::event::{KeyCode, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+    use std::any::Any;
+    use std::collections::VecDeque;
+
+    #[derive(Default)]
+    struct Log(Vec<String>);
+
+    fn log(shared_state: &mut SharedState, entry: String) {
+        shared_state.extensions.get_mut::<Log>().unwrap().0.push(entry);
+    }
+
+    struct QuitTester {
+        name: &'static str,
+        responses: VecDeque<QuitResponse>,
+        polls_until_finished: usize,
+    }
+
+    impl QuitTester {
+        fn boxed(name: &'static str, responses: Vec<QuitResponse>) -> Box<dyn Component> {
+            Box::new(Self {
+                name,
+                responses: responses.into(),
+                polls_until_finished: 0,
+            })
+        }
+    }
+
+    impl Component for QuitTester {
+        fn on_quit_requested(&mut self, _shared_state: &mut SharedState) -> QuitResponse {
+            self.responses.pop_front().unwrap_or(QuitResponse::Allow)
+        }
+
+        fn on_quit(&mut self, shared_state: &mut SharedState) {
+            log(shared_state, format!("{} quit", self.name));
+            self.polls_until_finished = 2;
+        }
+
+        fn poll_quit_finished(&mut self, shared_state: &mut SharedState) -> bool {
+            if self.polls_until_finished == 0 {
+                return true;
+            }
+            self.polls_until_finished -= 1;
+            if self.polls_until_finished == 0 {
+                log(shared_state, format!("{} finished", self.name));
+            }
+            self.polls_until_finished == 0
+        }
+    }
+
+    fn new_shared_state() -> SharedState {
+        let mut shared_state = SharedState::<()>::new(80, 24);
+        shared_state.extensions.insert(Log::default());
+        shared_state
+    }
+
+    fn defer() -> QuitResponse {
+        QuitResponse::Defer {
+            reason: "unsaved changes".to_string(),
+        }
+    }
+
+    /// Requires a component, see [`Component::required_components`].
+    struct Dependent(Dependency);
+
+    impl Component for Dependent {
+        fn required_components(&self) -> Vec<Dependency> {
+            vec![self.0]
+        }
+    }
+
+    #[test]
+    fn test_dependency_validation() {
+        let mut game = Game::<Vec<u8>, ()>::new_headless(4, 4);
+        game.add_component(Box::new(Dependent(
+            Dependency::of::<MouseTrackerComponent>(),
+        )));
+        let err = game.run_frames(1).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "missing component dependencies:\nDependent requires MouseTrackerComponent, which was not added"
+        );
+
+        // added later, but updated first because of its input priority
+        let mut game = Game::<Vec<u8>, ()>::new_headless(4, 4);
+        game.add_component(Box::new(Dependent(
+            Dependency::of::<MouseTrackerComponent>(),
+        )));
+        game.add_component(Box::new(MouseTrackerComponent::new()));
+        game.run_frames(1).unwrap();
+
+        let mut game = Game::<Vec<u8>, ()>::new_headless(4, 4);
+        game.add_component(Box::new(Dependent(Dependency::of::<ThrottledTester>())));
+        game.add_component(Box::new(ThrottledTester::default()));
+        game.set_dependency_check(DependencyCheck::Warn);
+        game.run_frames(1).unwrap();
+        assert_eq!(game.shared_state().debug_messages.len(), 1);
+        assert_eq!(
+            game.shared_state().debug_messages.iter().next().unwrap().message(),
+            "Dependent requires ThrottledTester to be added earlier or with a higher update priority"
+        );
+    }
+
+    #[test]
+    fn test_extension_accessors() {
+        #[derive(Default)]
+        struct Score(u32);
+
+        let mut shared_state = SharedState::<u32>::new(4, 4);
+        assert!(shared_state.ext::<Score>().is_none());
+        shared_state.ext_or_default::<Score>().0 += 2;
+        shared_state.ext_or_default::<Score>().0 += 3;
+        assert_eq!(shared_state.ext_expect::<Score>("test").0, 5);
+
+        let result = shared_state.with_ext(|score: &mut Score, shared_state| {
+            // the extension is not in the map while it is borrowed
+            assert!(shared_state.ext::<Score>().is_none());
+            shared_state.custom = score.0;
+            score.0 += 1;
+            "done"
+        });
+        assert_eq!(result, Some("done"));
+        assert_eq!(
+            (shared_state.custom, shared_state.ext_mut::<Score>().unwrap().0),
+            (5, 6)
+        );
+        assert_eq!(shared_state.with_ext(|_: &mut Log, _| ()), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "my component: missing extension")]
+    fn test_ext_expect_names_context() {
+        let shared_state = SharedState::<()>::new(4, 4);
+        shared_state.ext_expect::<Log>("my component");
+    }
+
+    #[test]
+    fn test_veto_then_allow() {
+        let mut shared_state = new_shared_state();
+        let mut components = vec![
+            QuitTester::boxed("a", vec![]),
+            QuitTester::boxed("dialog", vec![defer(), defer(), QuitResponse::Veto, defer()]),
+        ];
+        let mut gate = QuitGate::new();
+
+        // the dialog is shown and the user cancels
+        assert!(!gate.request(&mut components, &mut shared_state));
+        assert!(!gate.poll(&mut components, &mut shared_state));
+        assert!(!gate.poll(&mut components, &mut shared_state));
+        assert!(gate.pending.is_none());
+        // a cancelled request does not keep polling
+        assert!(!gate.poll(&mut components, &mut shared_state));
+
+        // the next request is deferred again, and then allowed
+        assert!(!gate.request(&mut components, &mut shared_state));
+        assert!(gate.poll(&mut components, &mut shared_state));
+        assert_eq!(shared_state.debug_messages.len(), 2);
+    }
+
+    #[test]
+    fn test_force_quit() {
+        let mut shared_state = new_shared_state();
+        let mut components = vec![QuitTester::boxed("dialog", vec![defer(); 10])];
+        let mut gate = QuitGate::new();
+        assert!(!gate.request(&mut components, &mut shared_state));
+        assert!(!gate.poll(&mut components, &mut shared_state));
+        assert!(!gate.request(&mut components, &mut shared_state));
+        assert!(gate.request(&mut components, &mut shared_state));
+
+        let mut gate = QuitGate::new();
+        gate.force_quit_timeout = Some(Duration::from_millis(20));
+        assert!(!gate.request(&mut components, &mut shared_state));
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(gate.poll(&mut components, &mut shared_state));
+    }
+
+    #[test]
+    fn test_shut_down_order() {
+        let mut shared_state = new_shared_state();
+        // sorted by priority, so the world that was added last comes first
+        let mut components = vec![
+            QuitTester::boxed("world", vec![]),
+            QuitTester::boxed("save", vec![]),
+        ];
+        let added = [
+            Added::new(ComponentId(1), None),
+            Added::new(ComponentId(0), None),
+        ];
+        let unfinished = shut_down(
+            &mut components,
+            &added,
+            &mut shared_state,
+            Duration::from_secs(5),
+        );
+        assert!(unfinished.is_empty());
+        assert_eq!(
+            shared_state.extensions.get::<Log>().unwrap().0,
+            vec!["world quit", "save quit", "world finished", "save finished"]
+        );
+    }
+
+    struct NeverFinishes;
+
+    impl Component for NeverFinishes {
+        fn poll_quit_finished(&mut self, _shared_state: &mut SharedState) -> bool {
+            false
+        }
+
+        fn name(&self) -> &'static str {
+            "NeverFinishes"
+        }
+    }
+
+    #[test]
+    fn test_shut_down_timeout() {
+        let mut shared_state = new_shared_state();
+        let mut components: Vec<Box<dyn Component>> = vec![
+            Box::new(NeverFinishes),
+            QuitTester::boxed("save", vec![]),
+        ];
+        let added = [
+            Added::new(ComponentId(0), None),
+            Added::new(ComponentId(1), None),
+        ];
+        let unfinished = shut_down(
+            &mut components,
+            &added,
+            &mut shared_state,
+            Duration::from_millis(20),
+        );
+        assert_eq!(unfinished, ["NeverFinishes"]);
+        assert_eq!(
+            shared_state.extensions.get::<Log>().unwrap().0,
+            vec!["save quit", "save finished"]
+        );
+    }
+
+    /// Adds `children` more nested components in `setup`.
+    struct NestedAdder {
+        name: String,
+        children: usize,
+    }
+
+    impl Component for NestedAdder {
+        fn setup(&mut self, _setup_info: &SetupInfo, shared_state: &mut SharedState) {
+            log(shared_state, format!("{} setup", self.name));
+            if self.children > 0 {
+                shared_state.components_to_add.push(Box::new(NestedAdder {
+                    name: format!("{}'", self.name),
+                    children: self.children - 1,
+                }));
+            }
+        }
+    }
+
+    #[test]
+    fn test_components_added_at_runtime_are_set_up() {
+        let mut shared_state = new_shared_state();
+        let mut components: Vec<Box<dyn Component>> = vec![Box::new(NestedAdder {
+            name: "a".to_string(),
+            children: 0,
+        })];
+        let mut added = vec![Added::new(ComponentId(0), None)];
+        shared_state.next_component_id = 1;
+        setup_components(&mut components, &mut added, 0, &mut shared_state);
+
+        // added later, e.g. from update()
+        let id = shared_state.add_component(Box::new(NestedAdder {
+            name: "b".to_string(),
+            children: 2,
+        }));
+        let already_setup_components = components.len();
+        append_components_to_add(&mut components, &mut added, &mut shared_state);
+        setup_components(
+            &mut components,
+            &mut added,
+            already_setup_components,
+            &mut shared_state,
+        );
+
+        assert_eq!(components.len(), 4);
+        let ids = added.iter().map(|added| added.id).collect::<Vec<_>>();
+        assert_eq!(ids, (0..4).map(ComponentId).collect::<Vec<_>>());
+        assert_eq!(id, ComponentId(1));
+        assert_eq!(
+            shared_state.extensions.get::<Log>().unwrap().0,
+            vec!["a setup", "b setup", "b' setup", "b'' setup"]
+        );
+    }
+
+    #[test]
+    fn test_remove_components_by_type_and_id() {
+        let mut game = Game::<Vec<u8>, ()>::new_headless(10, 5);
+        game.shared_state.extensions.insert(Log::default());
+        let first = game.add_component(QuitTester::boxed("first", vec![]));
+        let second = game.add_component(QuitTester::boxed("second", vec![]));
+        let third = game.add_component(Box::new(NestedAdder {
+            name: "third".to_string(),
+            children: 0,
+        }));
+        game.run_frames(1).unwrap();
+
+        // only the instance, not the other component of the same type
+        game.shared_state.remove_components.insert(first);
+        game.run_frames(1).unwrap();
+        assert_eq!(game.components.len(), 2);
+        let log = &game.shared_state.extensions.get::<Log>().unwrap().0;
+        assert_eq!(log.last().unwrap(), "first quit");
+
+        let removed = game.remove_component(second).unwrap();
+        assert!((removed as Box<dyn Any>).is::<QuitTester>());
+        assert!(game.remove_component(second).is_none());
+
+        game.shared_state
+            .remove_components
+            .insert(std::any::TypeId::of::<NestedAdder>());
+        game.run_frames(1).unwrap();
+        assert!(game.components.is_empty());
+        assert!(game.remove_component(third).is_none());
+        assert_eq!(
+            game.shared_state.extensions.get::<Log>().unwrap().0,
+            vec!["third setup", "first quit", "second quit"]
+        );
+    }
+
+    struct PriorityTester {
+        name: &'static str,
+        update_priority: i32,
+        render_priority: i32,
+    }
+
+    impl Component for PriorityTester {
+        fn update_priority(&self) -> i32 {
+            self.update_priority
+        }
+
+        fn render_priority(&self) -> i32 {
+            self.render_priority
+        }
+    }
+
+    fn tester(
+        name: &'static str,
+        update_priority: i32,
+        render_priority: i32,
+    ) -> Box<dyn Component> {
+        Box::new(PriorityTester {
+            name,
+            update_priority,
+            render_priority,
+        })
+    }
+
+    fn name_of(component: &dyn Component) -> &'static str {
+        (component as &dyn Any)
+            .downcast_ref::<PriorityTester>()
+            .unwrap()
+            .name
+    }
+
+    /// Sorts the components added in the given order, returns the update and render orders.
+    fn sorted_names(
+        added: Vec<(Box<dyn Component>, Option<Priority>)>,
+    ) -> (Vec<&'static str>, Vec<&'static str>) {
+        let mut components = vec![];
+        let mut added_entries = vec![];
+        for (id, (component, priority)) in added.into_iter().enumerate() {
+            components.push(component);
+            added_entries.push(Added::new(ComponentId(id as u64), priority));
+            sort_components(&mut components, &mut added_entries);
+        }
+        let render_order = sort_components(&mut components, &mut added_entries);
+        let update_names = components.iter().map(|c| name_of(c.as_ref())).collect();
+        let render_names = render_order
+            .iter()
+            .map(|&idx| name_of(components[idx].as_ref()))
+            .collect();
+        (update_names, render_names)
+    }
+
+    #[test]
+    fn test_priorities_are_independent_of_insertion_order() {
+        // the HUD updates first, but renders last
+        let hud = || (tester("hud", 10, 10), None);
+        let world = || (tester("world", 0, 0), None);
+        let input = || (tester("input", 0, -10), Some(Priority::INPUT));
+        let expected = (vec!["input", "hud", "world"], vec!["input", "world", "hud"]);
+        assert_eq!(sorted_names(vec![hud(), world(), input()]), expected);
+        assert_eq!(sorted_names(vec![world(), input(), hud()]), expected);
+    }
+
+    #[test]
+    fn test_priority_ties_keep_insertion_order() {
+        let (update, render) = sorted_names(vec![
+            (tester("a", 0, 0), None),
+            (tester("b", 5, 0), None),
+            (tester("c", 0, 0), None),
+            (tester("d", 0, 0), Some(Priority(5))),
+            (tester("e", 9, 0), Some(Priority::DEFAULT)),
+        ]);
+        assert_eq!(update, vec!["b", "d", "a", "c", "e"]);
+        assert_eq!(render, vec!["a", "b", "c", "d", "e"]);
+    }
+
+    /// Quits after the frame when the focus is lost, and logs its calls.
+    struct QuitAfterFrameTester;
+
+    impl Component for QuitAfterFrameTester {
+        fn on_event(
+            &mut self,
+            event: Event,
+            shared_state: &mut SharedState,
+        ) -> Option<BreakingAction> {
+            log(shared_state, format!("event {event:?}"));
+            if event == Event::FocusLost {
+                return Some(BreakingAction::QuitAfterFrame);
+            }
+            None
+        }
+
+        fn update(&mut self, _update_info: UpdateInfo, shared_state: &mut SharedState) {
+            log(shared_state, "update".to_string());
+        }
+
+        fn on_quit(&mut self, shared_state: &mut SharedState) {
+            log(shared_state, "quit".to_string());
+        }
+    }
+
+    #[test]
+    fn test_quit_after_frame() {
+        let mut game = Game::<Vec<u8>, ()>::new_headless(10, 5);
+        game.shared_state.extensions.insert(Log::default());
+        game.add_component(Box::new(QuitAfterFrameTester));
+        // the remaining events of the frame are still delivered
+        game.shared_state.fake_events_for_next_frame = vec![Event::FocusLost, Event::FocusGained];
+        game.run().unwrap();
+        assert_eq!(
+            game.shared_state.extensions.get::<Log>().unwrap().0,
+            vec!["event FocusLost", "event FocusGained", "update", "quit"]
+        );
+    }
+
+    /// Draws a ripple where a mouse button went down, from `on_event`.
+    struct RippleComponent;
+
+    impl Component for RippleComponent {
+        fn on_event(
+            &mut self,
+            event: Event,
+            shared_state: &mut SharedState,
+        ) -> Option<BreakingAction> {
+            if let Event::Mouse(MouseEvent {
+                kind: MouseEventKind::Down(_),
+                column,
+                row,
+                ..
+            }) = event
+            {
+                shared_state.draw_later(move |renderer| {
+                    renderer.render_pixel(column as usize, row as usize, Pixel::new('o'), 0);
+                });
+            }
+            None
+        }
+    }
+
+    fn click(column: u16, row: u16) -> Event {
+        Event::Mouse(MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        })
+    }
+
+    #[test]
+    fn test_draw_later_from_events() {
+        let mut shared_state = new_shared_state();
+        let mut components: Vec<Box<dyn Component>> = vec![Box::new(RippleComponent)];
+        for event in [click(1, 0), click(3, 1), click(0, 2)] {
+            components[0].on_event(event, &mut shared_state);
+        }
+        let mut renderer = DisplayRenderer::new_with_sink(4, 3, vec![]);
+        render_components(
+            &components,
+            &[Added::new(ComponentId(0), None)],
+            &[0],
+            &mut shared_state,
+            &mut renderer,
+        );
+        renderer.flush().unwrap();
+        let frame = renderer.previous_frame();
+        for (x, y) in [(1, 0), (3, 1), (0, 2)] {
+            assert_eq!(frame.pixel_at(x, y).c, 'o');
+        }
+        assert_eq!(frame.pixel_at(0, 0).c, ' ');
+        // the queue is cleared every frame
+        assert!(shared_state.draw_queue.is_empty());
+    }
+
+    /// Renders an `@` at the mouse and counts its frames.
+    struct CursorComponent {
+        frames: usize,
+    }
+
+    impl Component for CursorComponent {
+        fn update(&mut self, _update_info: UpdateInfo, _shared_state: &mut SharedState) {
+            self.frames += 1;
+        }
+
+        fn render(
+            &self,
+            renderer: &mut dyn Renderer,
+            shared_state: &SharedState,
+            depth_base: i32,
+        ) {
+            let (x, y) = shared_state.mouse_info.last_mouse_pos;
+            renderer.render_pixel(x, y, Pixel::new('@'), depth_base);
+            let frames = self.frames.to_string();
+            frames.render(renderer, 0, 0, depth_base);
+        }
+    }
+
+    #[test]
+    fn test_headless_game_runs_frames() {
+        let mut game = Game::<Vec<u8>, ()>::new_headless(6, 4);
+        game.add_component(Box::new(MouseTrackerComponent::new()));
+        game.add_component(Box::new(CursorComponent { frames: 0 }));
+        game.push_event(click(4, 3));
+        assert!(!game.run_frames(10).unwrap());
+        let frame = game.frame();
+        assert_eq!(frame.pixel_at(4, 3).c, '@');
+        assert_eq!((frame.pixel_at(0, 0).c, frame.pixel_at(1, 0).c), ('1', '0'));
+
+        game.push_event(click(1, 2));
+        game.shared_state_mut().frame_capture.request();
+        game.run_frames(1).unwrap();
+        assert_eq!(game.frame().pixel_at(1, 2).c, '@');
+        assert_eq!(game.frame().pixel_at(4, 3).c, ' ');
+        let snapshot = game.shared_state_mut().frame_capture.take().unwrap();
+        assert_eq!(snapshot.to_plain_text(), "11    \n      \n @    \n      \n");
+    }
+
+    /// Updates every 100ms and records the events and the dt of its updates.
+    #[derive(Default)]
+    struct ThrottledTester {
+        events: usize,
+        dts: Vec<f64>,
+    }
+
+    impl Component for ThrottledTester {
+        fn update_interval(&self) -> Option<Duration> {
+            Some(Duration::from_millis(100))
+        }
+
+        fn on_event(
+            &mut self,
+            _event: Event,
+            _shared_state: &mut SharedState,
+        ) -> Option<BreakingAction> {
+            self.events += 1;
+            None
+        }
+
+        fn update(&mut self, update_info: UpdateInfo, _shared_state: &mut SharedState) {
+            self.dts.push(update_info.dt);
+        }
+
+        fn on_quit(&mut self, shared_state: &mut SharedState) {
+            log(shared_state, format!("{} {:?}", self.events, self.dts));
+        }
+    }
+
+    #[test]
+    fn test_update_intervals() {
+        let mut game = Game::<Vec<u8>, ()>::new_headless(4, 3);
+        game.shared_state_mut().extensions.insert(Log::default());
+        game.shared_state_mut().fps.mode = FpsMode::Target(50.0);
+        game.add_component(Box::new(ThrottledTester::default()));
+        for _ in 0..12 {
+            game.push_event(Event::FocusGained);
+            game.run_frames(1).unwrap();
+        }
+        game.push_event(Event::Key(KeyCode::Char('q').into()));
+        game.add_component(Box::new(QuitterComponent::new()));
+        game.run_frames(1).unwrap();
+
+        let log = &game.shared_state().extensions.get::<Log>().unwrap().0;
+        let (events, dts) = log[0].split_once(' ').unwrap();
+        // every event arrived, including the one of the quitting frame
+        assert_eq!(events, "13");
+        // frames are 20ms apart: updates in frame 1, 6 and 11, the first one right away
+        let dts: Vec<f64> = dts
+            .trim_matches(['[', ']'])
+            .split(", ")
+            .map(|dt| dt.parse().unwrap())
+            .collect();
+        assert_eq!(dts.len(), 3);
+        assert!((dts[0] - 0.02).abs() < 1e-6);
+        assert!((dts[1] - 0.1).abs() < 1e-6 && (dts[2] - 0.1).abs() < 1e-6);
+    }
+
+    struct Plain;
+
+    impl Component for Plain {}
+
+    struct Exempt;
+
+    impl Component for Exempt {
+        fn ignores_filters(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_nested_component_filters() {
+        let mut shared_state = SharedState::<()>::new(10, 5);
+        let activity = |shared_state: &SharedState| {
+            [
+                shared_state.is_component_active(&Plain),
+                shared_state.is_component_active(&CursorComponent { frames: 0 }),
+                shared_state.is_component_active(&Exempt),
+            ]
+        };
+        assert_eq!(activity(&shared_state), [true, true, true]);
+
+        // e.g. a settings dialog
+        shared_state.push_component_filter(ComponentFilter::Whitelist(HashSet::from([
+            std::any::TypeId::of::<CursorComponent>(),
+        ])));
+        assert_eq!(activity(&shared_state), [false, true, true]);
+
+        // e.g. a pause menu opened from it
+        shared_state.push_component_filter(ComponentFilter::Blacklist(HashSet::from([
+            std::any::TypeId::of::<CursorComponent>(),
+        ])));
+        assert_eq!(activity(&shared_state), [true, false, true]);
+
+        assert!(matches!(
+            shared_state.pop_component_filter(),
+            Some(ComponentFilter::Blacklist(_))
+        ));
+        assert_eq!(activity(&shared_state), [false, true, true]);
+
+        shared_state.push_component_filter(ComponentFilter::All);
+        assert_eq!(activity(&shared_state), [true, true, true]);
+        shared_state.pop_component_filter();
+
+        shared_state.pop_component_filter();
+        assert_eq!(activity(&shared_state), [true, true, true]);
+        assert!(shared_state.pop_component_filter().is_none());
+    }
+
+    #[test]
+    fn test_component_interval_override_and_pause() {
+        let mut game = Game::<Vec<u8>, ()>::new_headless(4, 3);
+        game.add_component(Box::new(CursorComponent { frames: 0 }));
+        game.set_component_interval::<CursorComponent>(Duration::from_millis(50));
+        game.shared_state_mut().mouse_info.last_mouse_pos = (3, 2);
+        game.shared_state_mut().fps.mode = FpsMode::Target(100.0);
+        game.run_frames(10).unwrap();
+        // frames 1, 6
+        assert_eq!(game.frame().pixel_at(0, 0).c, '2');
+
+        // while paused, no time accumulates, and the first update after it happens right away
+        game.shared_state_mut()
+            .push_component_filter(ComponentFilter::Whitelist(HashSet::new()));
+        game.run_frames(20).unwrap();
+        game.shared_state_mut().pop_component_filter();
+        game.run_frames(1).unwrap();
+        assert_eq!(game.frame().pixel_at(0, 0).c, '3');
+    }
+
+    #[test]
+    fn test_paused_game_skips_updates() {
+        let mut game = Game::<Vec<u8>, ()>::new_headless(4, 3);
+        game.shared_state_mut().extensions.insert(Log::default());
+        game.shared_state_mut().fps.mode = FpsMode::Target(10.0);
+        game.add_component(Box::new(KeyPressRecorderComponent::new()));
+        game.add_component(Box::new(ThrottledTester::default()));
+        game.add_component(Box::new(CursorComponent { frames: 0 }));
+        game.shared_state_mut().mouse_info.last_mouse_pos = (3, 2);
+        game.run_frames(2).unwrap();
+
+        game.shared_state_mut().paused = true;
+        for _ in 0..20 {
+            game.push_event(Event::FocusGained);
+            game.run_frames(1).unwrap();
+        }
+        // rendering continues, updates do not
+        assert_eq!(game.frame().pixel_at(0, 0).c, '2');
+        // components that run while paused are updated
+        game.push_event(Event::Key(KeyCode::Char('a').into()));
+        game.run_frames(1).unwrap();
+        assert!(game.shared_state().pressed_keys.did_press_char('a'));
+
+        game.shared_state_mut().paused = false;
+        game.run_frames(1).unwrap();
+        assert_eq!(game.frame().pixel_at(0, 0).c, '3');
+
+        game.push_event(Event::Key(KeyCode::Char('q').into()));
+        game.add_component(Box::new(QuitterComponent::new()));
+        game.run_frames(1).unwrap();
+        let log = &game.shared_state().extensions.get::<Log>().unwrap().0;
+        // events arrived while paused, and the update after the pause has the dt of one frame
+        assert_eq!(log[0], "22 [0.1, 0.1, 0.1]");
+    }
+
+    #[test]
+    fn test_power_saver_wakes_on_event() {
+        let mut game = Game::<Vec<u8>, ()>::new_headless(4, 3);
+        game.add_component(Box::new(KeyPressRecorderComponent::new()));
+        game.shared_state_mut().fps.mode = FpsMode::PowerSaver;
+        game.run_frames(1).unwrap();
+
+        // without events, the wait times out
+        let start = Instant::now();
+        game.wait_for_event(Duration::from_millis(20));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        assert!(game.woken_by.is_none());
+
+        // an event ends the wait right away, and is handled first in the next frame
+        let writer = game.event_writer.clone();
+        let sender = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(10));
+            writer.send(Event::Key(KeyCode::Char('a').into())).unwrap();
+        });
+        let start = Instant::now();
+        game.wait_for_event(Duration::from_secs(10));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        sender.join().unwrap();
+        game.push_event(Event::Key(KeyCode::Char('b').into()));
+        game.run_frames(1).unwrap();
+        let pressed_keys = &game.shared_state().pressed_keys;
+        assert!(pressed_keys.did_press_char('a') && pressed_keys.did_press_char('b'));
+    }
+
+    #[test]
+    fn test_target_fps_does_not_drift() {
+        let frame_duration = Duration::from_secs_f64(1.0 / 60.0);
+        let start = Instant::now();
+        let mut pacer = FramePacer::new();
+        let mut now = start;
+        for frame in 0..600 {
+            // frames take varying time, and waking up always overshoots the deadline
+            let current = now + Duration::from_micros(2000 + 1000 * (frame % 7));
+            now = pacer.next_deadline(now, current, frame_duration) + Duration::from_micros(300);
+        }
+        let elapsed = now - start;
+        assert!(
+            elapsed.abs_diff(Duration::from_secs(10)) < Duration::from_millis(1),
+            "ran 600 frames in {elapsed:?}"
+        );
+    }
+
+    // Compile shims for the feature matrix: each names what its feature gates, so building the
+    // tests with `--no-default-features` and with each single feature checks the gating.
+    #[cfg(any(feature = "recording", feature = "persistence", feature = "serde"))]
+    fn serializable<T: serde::Serialize + serde::de::DeserializeOwned>() {}
+
+    // the core, available in every build
+    const _: fn() = || {
+        let _ = Game::<Vec<u8>, ()>::new_headless;
+        let _ = SharedState::<()>::new;
+        let _ = rendering::display::Display::<u8>::new;
+        let _ = util::planarvec::PlanarVec::<u8>::new;
+        let _ = components::audio::AudioHandle::play_tone;
+    };
+
+    #[cfg(feature = "recording")]
+    const _: fn() = || {
+        serializable::<Event>();
+        serializable::<components::eventrecorder::Recording>();
+    };
+
+    #[cfg(feature = "persistence")]
+    const _: fn() = || {
+        let _ = |shared_state: &SharedState| shared_state.save_slots.is_open();
+        let _ = util::persistence::ChunkPersistence::<u8>::stats;
+        serializable::<util::saveslots::SlotMetadata>();
+    };
+
+    #[cfg(feature = "serde")]
+    const _: fn() = || {
+        serializable::<util::planarvec::Bounds>();
+        serializable::<util::planarvec::PlanarVec<u8>>();
+    };
+
+    #[cfg(feature = "mapgen")]
+    const _: fn() = || {
+        let _ = util::mapgen::drunkards_walk;
+    };
+
+    #[cfg(feature = "random_table")]
+    const _: fn() = || {
+        let _ = util::random_table::WeightedTable::<u8>::new;
+    };
+
+    #[cfg(feature = "rng")]
+    const _: fn() = || {
+        let _ = seeds::seeded_rng;
+    };
+
+    #[cfg(feature = "image")]
+    const _: fn() = || {
+        let _ = rendering::sprite::ImageSprite::from_png_bytes;
+        let _ = rendering::spritefont::SpriteFont::glyph;
+    };
+
+    #[cfg(feature = "parallel")]
+    const _: fn() = || {
+        let _ = |display: &mut rendering::display::Display<u8>| {
+            display.par_fill_with(|x, _| x as u8);
+        };
+    };
+
+    #[cfg(feature = "audio")]
+    const _: fn() = || {
+        let _ = rodio::OutputStream::try_default;
+    };
+
+    fn ansi(command: impl Command) -> String {
+        let mut sequence = String::new();
+        command.write_ansi(&mut sequence).unwrap();
+        sequence
+    }
+
+    #[test]
+    fn test_terminal_cleanup_undoes_setup() {
+        let features = [
+            (
+                ansi(crossterm::terminal::EnterAlternateScreen),
+                ansi(crossterm::terminal::LeaveAlternateScreen),
+            ),
+            (ansi(EnableMouseCapture), ansi(DisableMouseCapture)),
+            (ansi(cursor::Hide), ansi(cursor::Show)),
+            (ansi(EnableBracketedPaste), ansi(DisableBracketedPaste)),
+            (ansi(EnableFocusChange), ansi(DisableFocusChange)),
+        ];
+        for bits in 0..32 {
+            let enabled = [0, 1, 2, 3, 4].map(|i| bits & (1 << i) != 0);
+            let options = TerminalOptions {
+                alternate_screen: enabled[0],
+                mouse_capture: enabled[1],
+                hide_cursor: enabled[2],
+                bracketed_paste: enabled[3],
+                focus_change: enabled[4],
+            };
+            let mut setup = vec![];
+            write_setup(&mut setup, options).unwrap();
+            let mut cleanup = vec![];
+            let mut raw_mode_disabled = false;
+            write_cleanup(&mut cleanup, options, || {
+                raw_mode_disabled = true;
+                Ok(())
+            })
+            .unwrap();
+            let (setup, cleanup) = (
+                String::from_utf8(setup).unwrap(),
+                String::from_utf8(cleanup).unwrap(),
+            );
+            assert!(raw_mode_disabled);
+            for ((enable, disable), enabled) in features.iter().zip(enabled) {
+                assert_eq!(setup.contains(enable), enabled, "{options:?}");
+                assert_eq!(cleanup.contains(disable), enabled, "{options:?}");
+            }
+            if !options.alternate_screen {
+                // the main screen is not cleared
+                assert!(!cleanup.contains(&ansi(crossterm::terminal::Clear(
+                    crossterm::terminal::ClearType::All
+                ))));
+            }
+        }
+    }
+
+    /// A sink that can be read while a game writes to it.
+    #[derive(Clone, Default)]
+    struct SharedSink(std::rc::Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedSink {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedSink {
+        fn take_string(&self) -> String {
+            String::from_utf8(std::mem::take(&mut *self.0.borrow_mut())).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_mouse_capture_toggle() {
+        let sink = SharedSink::default();
+        let mut game = Game::<SharedSink, ()>::new_headless_with_sink(4, 3, sink.clone());
+        game.add_component(Box::new(MouseTrackerComponent::new()));
+        let down = MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column: 1,
+            row: 1,
+            modifiers: KeyModifiers::NONE,
+        };
+        game.push_event(Event::Mouse(down));
+        game.run_frames(2).unwrap();
+        assert!(game.shared_state().mouse_info.left_mouse_down);
+        assert!(game.shared_state().mouse_capture.is_enabled());
+
+        sink.take_string();
+        game.shared_state_mut().mouse_capture.toggle();
+        assert!(!game.shared_state().mouse_capture.is_enabled());
+        game.run_frames(1).unwrap();
+        assert!(sink.take_string().ends_with(&ansi(DisableMouseCapture)));
+        // the release of the held button is never reported
+        assert!(!game.shared_state().mouse_info.left_mouse_down);
+
+        game.shared_state_mut().mouse_capture.set_enabled(true);
+        game.run_frames(1).unwrap();
+        assert!(sink.take_string().ends_with(&ansi(EnableMouseCapture)));
+        assert_eq!(game.frame().changed_cells_last_flush().count(), 4 * 3);
+
+        // toggling back and forth within a frame changes nothing
+        game.shared_state_mut().mouse_capture.toggle();
+        game.shared_state_mut().mouse_capture.toggle();
+        game.run_frames(1).unwrap();
+        assert!(!sink.take_string().contains(&ansi(EnableMouseCapture)));
+        assert_eq!(game.frame().changed_cells_last_flush().count(), 0);
+    }
+
+    #[test]
+    fn test_inline_game() {
+        let sink = SharedSink::default();
+        let mut game = Game::<SharedSink, ()>::new_headless_with_sink(10, 24, sink.clone());
+        game.add_component(Box::new(QuitterComponent::new()));
+        game.make_inline(4).unwrap();
+        assert!(sink.take_string().ends_with(&ansi(cursor::SavePosition)));
+        assert_eq!(game.shared_state().display_info.height(), 4);
+
+        game.run_frames(1).unwrap();
+        assert!(sink.take_string().starts_with(&ansi(cursor::RestorePosition)));
+        assert_eq!(game.frame().height(), 4);
+
+        // a resize keeps the strip height and reserves the strip again
+        game.push_event(Event::Resize(8, 30));
+        game.run_frames(1).unwrap();
+        assert_eq!(game.frame().width(), 8);
+        assert_eq!(game.frame().height(), 4);
+        assert!(sink.take_string().contains("\n\n\n"));
+        // a smaller terminal clamps it
+        game.push_event(Event::Resize(8, 2));
+        game.run_frames(1).unwrap();
+        assert_eq!(game.shared_state().display_info.height(), 2);
+        sink.take_string();
+
+        // quitting clears the strip
+        game.push_event(Event::Key(KeyCode::Char('q').into()));
+        assert!(game.run_frames(1).unwrap());
+        let clear = ansi(crossterm::terminal::Clear(
+            crossterm::terminal::ClearType::FromCursorDown,
+        ));
+        assert!(sink.take_string().ends_with(&clear));
+    }
+
+    #[test]
+    fn test_draw_queue_is_bounded() {
+        let mut shared_state = new_shared_state();
+        shared_state.draw_queue.set_max_len(2);
+        for _ in 0..5 {
+            shared_state.draw_later(|_| {});
+        }
+        assert_eq!(shared_state.draw_queue.len(), 2);
+        let mut renderer = DisplayRenderer::new_with_sink(1, 1, vec![]);
+        render_components(&[], &[], &[], &mut shared_state, &mut renderer);
+        assert_eq!(shared_state.debug_messages.len(), 1);
+    }
+}
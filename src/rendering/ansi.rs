@@ -0,0 +1,303 @@
+//! Half-block images as ANSI art text.
+//!
+//! An [`AnsiArt`] is a grid of colors, like a
+//! [`HalfBlockDisplayRender`](crate::rendering::render::HalfBlockDisplayRender). As text, every
+//! character holds two vertically stacked pixels: `▀` with the top pixel as foreground and the
+//! bottom pixel as background color, `▄` if only the bottom pixel is set, and a space if neither
+//! is. Colors are 24-bit SGR sequences, or 256-color sequences for [`Color::Ansi`], so the text
+//! can be printed with `cat` or pasted into other tools.
+//!
+//! Parsing accepts the same subset: 24-bit and 256 colors, the default colors (`39`, `49`) and
+//! resets (`0`), and additionally the 16 standard colors (`30`-`37`, `90`-`97` and their
+//! backgrounds). Other sequences are ignored.
+//!
+//! [`parse_styled_text`] parses colored text with the same subset, e.g. the output of a program
+//! that colors its log lines.
+
+use crate::rendering::color::{Color, ansi_to_rgb};
+use std::fmt::Write;
+
+/// A grid of colors that converts to and from ANSI art text.
+///
+/// Pixels are either [`Color::Rgb`], [`Color::Ansi`] or [`Color::Transparent`].
+/// [`Color::Default`] is written as transparent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AnsiArt {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl AnsiArt {
+    /// Creates a transparent image of `width` x `height` pixels.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Color::Transparent; width * height],
+        }
+    }
+
+    /// Returns the width in pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height in pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the color of the pixel, with y pointing down.
+    pub fn get(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Sets the color of the pixel, with y pointing down.
+    pub fn set(&mut self, x: usize, y: usize, color: Color) {
+        let color = match color {
+            Color::Default => Color::Transparent,
+            color => color,
+        };
+        self.pixels[y * self.width + x] = color;
+    }
+
+    /// Returns the image as ANSI art text, one line per two rows of pixels.
+    ///
+    /// An odd height is padded with a transparent row. Every line ends with a reset.
+    pub fn to_ansi_string(&self) -> String {
+        let mut out = String::new();
+        for y in (0..self.height).step_by(2) {
+            let mut fg = Color::Transparent;
+            let mut bg = Color::Transparent;
+            for x in 0..self.width {
+                let top = self.get(x, y);
+                let bottom = if y + 1 < self.height {
+                    self.get(x, y + 1)
+                } else {
+                    Color::Transparent
+                };
+                let (c, new_fg, new_bg) = match (top, bottom) {
+                    (top, _) if top.is_solid() => ('▀', top, bottom),
+                    (_, bottom) if bottom.is_solid() => ('▄', bottom, Color::Transparent),
+                    _ => (' ', fg, Color::Transparent),
+                };
+                if new_fg != fg {
+                    write_sgr(&mut out, new_fg, 38);
+                    fg = new_fg;
+                }
+                if new_bg != bg {
+                    write_sgr(&mut out, new_bg, 48);
+                    bg = new_bg;
+                }
+                out.push(c);
+            }
+            out.push_str("\x1b[0m\n");
+        }
+        out
+    }
+
+    /// Parses ANSI art text. The width is that of the longest line, shorter lines are padded with
+    /// transparent pixels.
+    pub fn parse(text: &str) -> Self {
+        let mut rows: Vec<Vec<(Color, Color)>> = vec![];
+        let mut row = vec![];
+        let mut fg = Color::Transparent;
+        let mut bg = Color::Transparent;
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\x1b' => {
+                    if chars.next_if_eq(&'[').is_none() {
+                        continue;
+                    }
+                    let mut params = String::new();
+                    // the final byte of a CSI sequence is in '@'..='~'
+                    for c in chars.by_ref() {
+                        if ('@'..='~').contains(&c) {
+                            if c == 'm' {
+                                apply_sgr(&params, &mut fg, &mut bg);
+                            }
+                            break;
+                        }
+                        params.push(c);
+                    }
+                }
+                '\n' => rows.push(std::mem::take(&mut row)),
+                '\r' => {}
+                '▀' => row.push((fg, bg)),
+                '▄' => row.push((bg, fg)),
+                '█' => row.push((fg, fg)),
+                _ => row.push((bg, bg)),
+            }
+        }
+        if !row.is_empty() {
+            rows.push(row);
+        }
+
+        let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+        let mut art = Self::new(width, rows.len() * 2);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, &(top, bottom)) in row.iter().enumerate() {
+                art.set(x, 2 * y, top);
+                art.set(x, 2 * y + 1, bottom);
+            }
+        }
+        art
+    }
+}
+
+/// A run of text with the same colors, see [`parse_styled_text`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StyledSpan {
+    pub text: String,
+    /// The foreground color, or [`Color::Transparent`] for the default color.
+    pub color: Color,
+    /// The background color, or [`Color::Transparent`] for no background.
+    pub bg_color: Color,
+}
+
+/// Parses text with embedded SGR color sequences into runs of equally colored text.
+///
+/// Accepts the same sequences as [`AnsiArt::parse`], other escape sequences are dropped.
+/// Newlines are kept as text.
+pub fn parse_styled_text(text: &str) -> Vec<StyledSpan> {
+    let mut spans: Vec<StyledSpan> = vec![];
+    let mut fg = Color::Transparent;
+    let mut bg = Color::Transparent;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            if chars.next_if_eq(&'[').is_none() {
+                continue;
+            }
+            let mut params = String::new();
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    if c == 'm' {
+                        apply_sgr(&params, &mut fg, &mut bg);
+                    }
+                    break;
+                }
+                params.push(c);
+            }
+            continue;
+        }
+        match spans.last_mut() {
+            Some(span) if span.color == fg && span.bg_color == bg => span.text.push(c),
+            _ => spans.push(StyledSpan {
+                text: c.to_string(),
+                color: fg,
+                bg_color: bg,
+            }),
+        }
+    }
+    spans
+}
+
+/// Writes the SGR sequence that sets the foreground (`base` 38) or background (`base` 48) color.
+pub(crate) fn write_sgr(out: &mut String, color: Color, base: u8) {
+    match color {
+        Color::Rgb([r, g, b]) => write!(out, "\x1b[{base};2;{r};{g};{b}m").unwrap(),
+        Color::Ansi(index) => write!(out, "\x1b[{base};5;{index}m").unwrap(),
+        _ => write!(out, "\x1b[{}m", base + 1).unwrap(),
+    }
+}
+
+/// Applies the parameters of an SGR sequence to the current colors.
+fn apply_sgr(params: &str, fg: &mut Color, bg: &mut Color) {
+    let params: Vec<u16> = params.split(';').map(|p| p.parse().unwrap_or(0)).collect();
+    let mut i = 0;
+    while i < params.len() {
+        match params[i] {
+            0 => {
+                *fg = Color::Transparent;
+                *bg = Color::Transparent;
+            }
+            39 => *fg = Color::Transparent,
+            49 => *bg = Color::Transparent,
+            code @ 30..=37 => *fg = Color::Rgb(ansi_to_rgb(code as u8 - 30)),
+            code @ 90..=97 => *fg = Color::Rgb(ansi_to_rgb(code as u8 - 90 + 8)),
+            code @ 40..=47 => *bg = Color::Rgb(ansi_to_rgb(code as u8 - 40)),
+            code @ 100..=107 => *bg = Color::Rgb(ansi_to_rgb(code as u8 - 100 + 8)),
+            base @ (38 | 48) if params.get(i + 1) == Some(&2) && i + 4 < params.len() => {
+                let rgb = [params[i + 2], params[i + 3], params[i + 4]].map(|c| c.min(255) as u8);
+                if base == 38 {
+                    *fg = Color::Rgb(rgb);
+                } else {
+                    *bg = Color::Rgb(rgb);
+                }
+                i += 4;
+            }
+            base @ (38 | 48) if params.get(i + 1) == Some(&5) && i + 2 < params.len() => {
+                let color = Color::Ansi(params[i + 2].min(255) as u8);
+                if base == 38 {
+                    *fg = color;
+                } else {
+                    *bg = color;
+                }
+                i += 2;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let red = Color::Rgb([255, 0, 0]);
+        let blue = Color::Rgb([0, 0, 255]);
+        let mut art = AnsiArt::new(3, 4);
+        art.set(0, 0, red);
+        art.set(0, 1, blue);
+        art.set(1, 1, red);
+        art.set(2, 2, Color::Ansi(208));
+        art.set(2, 3, blue);
+        let text = art.to_ansi_string();
+        assert_eq!(text.lines().count(), 2);
+        assert_eq!(AnsiArt::parse(&text), art);
+
+        // an odd height is padded
+        let mut art = AnsiArt::new(1, 1);
+        art.set(0, 0, red);
+        let parsed = AnsiArt::parse(&art.to_ansi_string());
+        assert_eq!((parsed.width(), parsed.height()), (1, 2));
+        assert_eq!(parsed.get(0, 0), red);
+        assert_eq!(parsed.get(0, 1), Color::Transparent);
+    }
+
+    #[test]
+    fn test_parse_other_tools() {
+        let art = AnsiArt::parse("\x1b[1;38;2;1;2;3m█\x1b[39;48;2;4;5;6m▄x\r\n");
+        assert_eq!((art.width(), art.height()), (3, 2));
+        assert_eq!(art.get(0, 0), Color::Rgb([1, 2, 3]));
+        assert_eq!(art.get(0, 1), Color::Rgb([1, 2, 3]));
+        assert_eq!(art.get(1, 0), Color::Rgb([4, 5, 6]));
+        assert_eq!(art.get(1, 1), Color::Transparent);
+        assert_eq!(art.get(2, 1), Color::Rgb([4, 5, 6]));
+    }
+
+    #[test]
+    fn test_parse_styled_text() {
+        let spans = parse_styled_text("a\x1b[31mbc\x1b[0m d\x1b[1;92;44m!\x1b[K");
+        let span = |text: &str, color, bg_color| StyledSpan {
+            text: text.to_string(),
+            color,
+            bg_color,
+        };
+        assert_eq!(
+            spans,
+            vec![
+                span("a", Color::Transparent, Color::Transparent),
+                span("bc", Color::Rgb([205, 0, 0]), Color::Transparent),
+                span(" d", Color::Transparent, Color::Transparent),
+                span("!", Color::Rgb([0, 255, 0]), Color::Rgb([0, 0, 238])),
+            ]
+        );
+    }
+}
@@ -0,0 +1,246 @@
+//! Snapshots of the screen, e.g. for debugging and screenshots.
+//!
+//! [`DisplayRenderer::capture_frame`](crate::rendering::renderer::DisplayRenderer::capture_frame)
+//! returns a [`FrameSnapshot`] of the last flushed frame. Components have no access to the
+//! renderer outside of `render()`, so they request a capture with
+//! [`SharedState::frame_capture`](crate::SharedState::frame_capture) instead, and take the
+//! snapshot in a later frame:
+//!
+//! ```
+//! # use teng::{SharedState, UpdateInfo};
+//! # fn update(shared_state: &mut SharedState) {
+//! use crossterm::event::KeyCode;
+//!
+//! if shared_state.pressed_keys.did_press(KeyCode::F(12)) {
+//!     shared_state.frame_capture.request();
+//! }
+//! if let Some(snapshot) = shared_state.frame_capture.take() {
+//!     let image = snapshot.to_rgb_image();
+//!     // e.g. save `image.data` as a PNG of `image.width` x `image.height` pixels
+//! }
+//! # }
+//! ```
+
+use crate::rendering::ansi::write_sgr;
+use crate::rendering::color::Color;
+use crate::rendering::display::Display;
+use crate::rendering::pixel::Pixel;
+
+/// An owned copy of a frame as it was shown.
+///
+/// Colors are post-processed, like in
+/// [`FrameView`](crate::rendering::renderer::FrameView).
+#[derive(Clone, Debug)]
+pub struct FrameSnapshot {
+    pixels: Display<Pixel>,
+    default_fg_color: [u8; 3],
+    default_bg_color: [u8; 3],
+}
+
+/// A buffer of 24-bit pixels, row by row, three bytes per pixel.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RgbBuffer {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<u8>,
+}
+
+impl RgbBuffer {
+    /// Returns the color of the pixel at `(x, y)`.
+    ///
+    /// Panics if `(x, y)` is out of bounds.
+    pub fn get(&self, x: usize, y: usize) -> [u8; 3] {
+        assert!(x < self.width && y < self.height);
+        let idx = 3 * (y * self.width + x);
+        [self.data[idx], self.data[idx + 1], self.data[idx + 2]]
+    }
+}
+
+impl FrameSnapshot {
+    pub(crate) fn new(
+        pixels: Display<Pixel>,
+        default_fg_color: [u8; 3],
+        default_bg_color: [u8; 3],
+    ) -> Self {
+        Self {
+            pixels,
+            default_fg_color,
+            default_bg_color,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.pixels.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.pixels.height()
+    }
+
+    /// Returns the pixels of the frame.
+    pub fn pixels(&self) -> &Display<Pixel> {
+        &self.pixels
+    }
+
+    /// Returns the characters of the frame without colors, one line per row.
+    ///
+    /// Wide characters are followed by the next character, without the continuation of their
+    /// second column.
+    pub fn to_plain_text(&self) -> String {
+        let mut out = String::with_capacity((self.width() + 1) * self.height());
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let c = self.pixels[(x, y)].c;
+                if c != Pixel::WIDE_CONTINUATION {
+                    out.push(c);
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Returns the frame as text with 24-bit and 256-color SGR sequences, one line per row, so
+    /// that printing it, e.g. with `cat`, shows the frame in a terminal.
+    ///
+    /// Default and transparent colors are written as the terminal's default colors, and every
+    /// line ends with its colors reset. Text attributes are not written.
+    pub fn to_ansi_text(&self) -> String {
+        let mut out = String::with_capacity((self.width() + 1) * self.height());
+        for y in 0..self.height() {
+            let (mut fg, mut bg) = (Color::Default, Color::Default);
+            for x in 0..self.width() {
+                let pixel = self.pixels[(x, y)];
+                if pixel.c == Pixel::WIDE_CONTINUATION {
+                    continue;
+                }
+                let solid = |color: Color| {
+                    if color.is_solid() {
+                        color
+                    } else {
+                        Color::Default
+                    }
+                };
+                let (pixel_fg, pixel_bg) = (solid(pixel.color), solid(pixel.bg_color));
+                if pixel_fg != fg {
+                    write_sgr(&mut out, pixel_fg, 38);
+                    fg = pixel_fg;
+                }
+                if pixel_bg != bg {
+                    write_sgr(&mut out, pixel_bg, 48);
+                    bg = pixel_bg;
+                }
+                out.push(pixel.c);
+            }
+            if (fg, bg) != (Color::Default, Color::Default) {
+                out.push_str("\x1b[0m");
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Returns the frame as an image with a 1x2 block of pixels per cell.
+    ///
+    /// Half blocks are drawn as they look: `▀` has the foreground color on top and the background
+    /// color at the bottom, `▄` the reverse. `█` and other characters are drawn in the foreground
+    /// color, spaces in the background color.
+    ///
+    /// Transparent colors and the default background color are drawn in the renderer's default
+    /// background color, the default foreground color in the renderer's default foreground color.
+    pub fn to_rgb_image(&self) -> RgbBuffer {
+        let (width, height) = (self.width(), 2 * self.height());
+        let mut data = vec![0; 3 * width * height];
+        for (x, y, pixel) in self.pixels.iter() {
+            let fg = match pixel.color {
+                Color::Default => self.default_fg_color,
+                color => color.unwrap_or(self.default_bg_color),
+            };
+            let bg = pixel.bg_color.unwrap_or(self.default_bg_color);
+            let (top, bottom) = match pixel.c {
+                '▀' => (fg, bg),
+                '▄' => (bg, fg),
+                ' ' => (bg, bg),
+                _ => (fg, fg),
+            };
+            for (dy, rgb) in [(0, top), (1, bottom)] {
+                let idx = 3 * ((2 * y + dy) * width + x);
+                data[idx..idx + 3].copy_from_slice(&rgb);
+            }
+        }
+        RgbBuffer {
+            width,
+            height,
+            data,
+        }
+    }
+}
+
+/// Frame captures requested by components.
+#[derive(Debug, Default)]
+pub struct FrameCapture {
+    requested: bool,
+    snapshot: Option<FrameSnapshot>,
+}
+
+impl FrameCapture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests a snapshot of the current frame, which is available from [`Self::take`] after
+    /// the frame was rendered.
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+
+    /// Returns true if a capture is requested and not yet taken.
+    pub fn is_pending(&self) -> bool {
+        self.requested
+    }
+
+    /// Takes the captured snapshot, if any.
+    pub fn take(&mut self) -> Option<FrameSnapshot> {
+        self.snapshot.take()
+    }
+
+    pub(crate) fn fulfill(&mut self, capture: impl FnOnce() -> FrameSnapshot) {
+        if std::mem::take(&mut self.requested) {
+            self.snapshot = Some(capture());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_conversions() {
+        let mut pixels = Display::new(3, 1, Pixel::default());
+        pixels.set(
+            0,
+            0,
+            Pixel::new('▀')
+                .with_color([255, 0, 0])
+                .with_bg_color([0, 0, 255]),
+        );
+        pixels.set(1, 0, Pixel::new('▄').with_color([0, 255, 0]));
+        pixels.set(2, 0, Pixel::new('x'));
+        let snapshot = FrameSnapshot::new(pixels, [200, 200, 200], [10, 10, 10]);
+        assert_eq!(snapshot.to_plain_text(), "▀▄x\n");
+        assert_eq!(
+            snapshot.to_ansi_text(),
+            "\x1b[38;2;255;0;0m\x1b[48;2;0;0;255m▀\x1b[38;2;0;255;0m\x1b[49m▄\x1b[39mx\n"
+        );
+
+        let image = snapshot.to_rgb_image();
+        assert_eq!((image.width, image.height), (3, 2));
+        assert_eq!(image.get(0, 0), [255, 0, 0]);
+        assert_eq!(image.get(0, 1), [0, 0, 255]);
+        // the default background color at the top
+        assert_eq!(image.get(1, 0), [10, 10, 10]);
+        assert_eq!(image.get(1, 1), [0, 255, 0]);
+        assert_eq!(image.get(2, 0), [200, 200, 200]);
+    }
+}
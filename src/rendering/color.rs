@@ -1,4 +1,9 @@
 //! Color representation for terminal rendering.
+//!
+//! Terminals without 24-bit color support get colors quantized to the xterm 256-color palette or
+//! the 16 basic colors, see [`ColorMode`].
+
+use std::sync::OnceLock;
 
 /// Represents colors for terminal rendering.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
@@ -11,6 +16,10 @@ pub enum Color {
     Transparent,
     /// An RGB color.
     Rgb([u8; 3]),
+    /// A color of the terminal's 256-color palette, see [`ansi_to_rgb`].
+    ///
+    /// Written as the palette index, so the basic colors (0-15) follow the terminal's theme.
+    Ansi(u8),
 }
 
 impl Color {
@@ -20,6 +29,7 @@ impl Color {
             Color::Default => other,
             Color::Transparent => other,
             Color::Rgb(c) => c,
+            Color::Ansi(index) => ansi_to_rgb(index),
         }
     }
 
@@ -30,6 +40,534 @@ impl Color {
             Color::Default => true,
             Color::Transparent => false,
             Color::Rgb(_) => true,
+            Color::Ansi(_) => true,
+        }
+    }
+
+    /// Returns how the color appears with the color vision deficiency `kind`.
+    ///
+    /// See [`simulate_cvd`] for more information.
+    pub fn simulate_cvd(self, kind: ColorVisionDeficiency) -> Self {
+        match self {
+            Color::Rgb(rgb) => Color::Rgb(simulate_cvd(rgb, kind)),
+            Color::Ansi(index) => Color::Rgb(simulate_cvd(ansi_to_rgb(index), kind)),
+            other => other,
+        }
+    }
+
+    /// Mixes the color with white, by `amount` from 0 (unchanged) to 1 (white).
+    ///
+    /// Palette colors become RGB colors. Default and transparent colors are unchanged.
+    pub fn lighten(self, amount: f32) -> Self {
+        self.map_rgb(|rgb| lerp_color_linear(rgb, [255; 3], amount))
+    }
+
+    /// Mixes the color with black, by `amount` from 0 (unchanged) to 1 (black).
+    ///
+    /// Palette colors become RGB colors. Default and transparent colors are unchanged.
+    pub fn darken(self, amount: f32) -> Self {
+        self.map_rgb(|rgb| lerp_color_linear(rgb, [0; 3], amount))
+    }
+
+    fn map_rgb(self, f: impl FnOnce([u8; 3]) -> [u8; 3]) -> Self {
+        match self {
+            Color::Rgb(rgb) => Color::Rgb(f(rgb)),
+            Color::Ansi(index) => Color::Rgb(f(ansi_to_rgb(index))),
+            other => other,
+        }
+    }
+}
+
+/// The colors a terminal can display.
+///
+/// [`DisplayRenderer`](crate::rendering::renderer::DisplayRenderer) quantizes RGB colors to the
+/// palette of the mode. [`Game::new`](crate::Game::new) uses [`ColorMode::detect`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum ColorMode {
+    /// 24-bit colors.
+    #[default]
+    TrueColor,
+    /// The xterm 256-color palette, see [`rgb_to_ansi256`].
+    Ansi256,
+    /// The 16 basic colors, see [`rgb_to_ansi16`].
+    Ansi16,
+}
+
+impl ColorMode {
+    /// Detects the color mode of the terminal from the `COLORTERM` and `TERM` environment
+    /// variables, see [`ColorMode::from_env`].
+    pub fn detect() -> Self {
+        let colorterm = std::env::var("COLORTERM").ok();
+        let term = std::env::var("TERM").ok();
+        Self::from_env(colorterm.as_deref(), term.as_deref())
+    }
+
+    /// Returns the color mode for the values of the `COLORTERM` and `TERM` environment variables.
+    ///
+    /// `COLORTERM=truecolor` (or `24bit`) means true color, as does a missing `TERM`, which is
+    /// usual on Windows. Otherwise, a `TERM` like `xterm-256color` means 256 colors, and any other
+    /// `TERM` only the 16 basic colors.
+    pub fn from_env(colorterm: Option<&str>, term: Option<&str>) -> Self {
+        if matches!(colorterm, Some("truecolor" | "24bit")) {
+            return ColorMode::TrueColor;
+        }
+        match term {
+            None => ColorMode::TrueColor,
+            Some(term) if term.contains("direct") => ColorMode::TrueColor,
+            Some(term) if term.contains("256color") => ColorMode::Ansi256,
+            Some(_) => ColorMode::Ansi16,
+        }
+    }
+
+    /// Returns the palette index of the color closest to `rgb`, or `None` for true color.
+    pub fn quantize(self, rgb: [u8; 3]) -> Option<u8> {
+        match self {
+            ColorMode::TrueColor => None,
+            ColorMode::Ansi256 => Some(rgb_to_ansi256(rgb)),
+            ColorMode::Ansi16 => Some(rgb_to_ansi16(rgb)),
+        }
+    }
+}
+
+/// The default xterm colors of the 16 basic colors.
+const BASIC_COLORS: [[u8; 3]; 16] = [
+    [0, 0, 0],
+    [205, 0, 0],
+    [0, 205, 0],
+    [205, 205, 0],
+    [0, 0, 238],
+    [205, 0, 205],
+    [0, 205, 205],
+    [229, 229, 229],
+    [127, 127, 127],
+    [255, 0, 0],
+    [0, 255, 0],
+    [255, 255, 0],
+    [92, 92, 255],
+    [255, 0, 255],
+    [0, 255, 255],
+    [255, 255, 255],
+];
+
+/// The channel values of the 6x6x6 color cube of the 256-color palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Returns the RGB value of the palette color `index`.
+///
+/// 0-15 are the basic colors with their xterm defaults, 16-231 a 6x6x6 color cube, and 232-255 a
+/// gray ramp.
+pub fn ansi_to_rgb(index: u8) -> [u8; 3] {
+    match index {
+        0..=15 => BASIC_COLORS[index as usize],
+        16..=231 => {
+            let i = (index - 16) as usize;
+            [
+                CUBE_LEVELS[i / 36],
+                CUBE_LEVELS[i / 6 % 6],
+                CUBE_LEVELS[i % 6],
+            ]
+        }
+        _ => [8 + 10 * (index - 232); 3],
+    }
+}
+
+fn distance(a: [u8; 3], b: [u8; 3]) -> u32 {
+    a.into_iter()
+        .zip(b)
+        .map(|(a, b)| (a.abs_diff(b) as u32).pow(2))
+        .sum()
+}
+
+/// Returns the palette index of the cube or gray ramp color closest to `rgb`.
+fn nearest_ansi256(rgb: [u8; 3]) -> u8 {
+    let level = |c: u8| (0..6).min_by_key(|&i| CUBE_LEVELS[i].abs_diff(c)).unwrap();
+    let [r, g, b] = rgb.map(level);
+    let cube = (16 + 36 * r + 6 * g + b) as u8;
+    let mean = rgb.iter().map(|&c| c as u32).sum::<u32>() / 3;
+    let gray = 232 + ((mean.saturating_sub(3)) / 10).min(23) as u8;
+    if distance(rgb, ansi_to_rgb(gray)) < distance(rgb, ansi_to_rgb(cube)) {
+        gray
+    } else {
+        cube
+    }
+}
+
+/// Returns the basic color closest to `rgb`.
+fn nearest_ansi16(rgb: [u8; 3]) -> u8 {
+    (0..16)
+        .min_by_key(|&i| distance(rgb, BASIC_COLORS[i as usize]))
+        .unwrap()
+}
+
+/// A lookup table with 5 bits per channel, built on first use.
+struct QuantizeTable(OnceLock<Box<[u8]>>);
+
+impl QuantizeTable {
+    const fn new() -> Self {
+        Self(OnceLock::new())
+    }
+
+    fn get(&self, rgb: [u8; 3], nearest: fn([u8; 3]) -> u8) -> u8 {
+        let index =
+            |[r, g, b]: [u8; 3]| (r as usize >> 3) << 10 | (g as usize >> 3) << 5 | b as usize >> 3;
+        let table = self.0.get_or_init(|| {
+            (0..1 << 15)
+                .map(|i: usize| {
+                    // the center of the bucket
+                    let channel = |shift: usize| ((i >> shift & 31) << 3 | 4) as u8;
+                    nearest([channel(10), channel(5), channel(0)])
+                })
+                .collect()
+        });
+        table[index(rgb)]
+    }
+}
+
+static ANSI256_TABLE: QuantizeTable = QuantizeTable::new();
+static ANSI16_TABLE: QuantizeTable = QuantizeTable::new();
+
+/// Returns the index of the 256-color palette color closest to `rgb`.
+///
+/// Only the color cube and the gray ramp are considered, since terminal themes change the basic
+/// colors. Uses a lookup table with 5 bits per channel.
+pub fn rgb_to_ansi256(rgb: [u8; 3]) -> u8 {
+    ANSI256_TABLE.get(rgb, nearest_ansi256)
+}
+
+/// Returns the index of the basic color closest to `rgb`, assuming the xterm defaults.
+///
+/// Uses a lookup table with 5 bits per channel.
+pub fn rgb_to_ansi16(rgb: [u8; 3]) -> u8 {
+    ANSI16_TABLE.get(rgb, nearest_ansi16)
+}
+
+/// A kind of color vision deficiency (color blindness) that can be simulated with
+/// [`simulate_cvd`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ColorVisionDeficiency {
+    /// Missing red-sensitive cones.
+    Protanopia,
+    /// Missing green-sensitive cones.
+    Deuteranopia,
+    /// Missing blue-sensitive cones.
+    Tritanopia,
+}
+
+impl ColorVisionDeficiency {
+    /// All kinds, in the order of their declaration.
+    pub const ALL: [ColorVisionDeficiency; 3] = [
+        ColorVisionDeficiency::Protanopia,
+        ColorVisionDeficiency::Deuteranopia,
+        ColorVisionDeficiency::Tritanopia,
+    ];
+
+    /// The simulation matrix for linear RGB, from Machado, Oliveira and Fernandes (2009)
+    /// with severity 1.0.
+    fn matrix(self) -> [[f64; 3]; 3] {
+        match self {
+            ColorVisionDeficiency::Protanopia => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ],
+            ColorVisionDeficiency::Deuteranopia => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ],
+            ColorVisionDeficiency::Tritanopia => [
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900],
+            ],
+        }
+    }
+}
+
+fn srgb_to_linear(c: u8) -> f64 {
+    let c = c as f64 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(l: f64) -> u8 {
+    let l = l.clamp(0.0, 1.0);
+    let c = if l <= 0.0031308 {
+        12.92 * l
+    } else {
+        1.055 * l.powf(1.0 / 2.4) - 0.055
+    };
+    (c * 255.0).round() as u8
+}
+
+/// Returns how the sRGB color `rgb` appears with the color vision deficiency `kind`.
+///
+/// The color is converted to linear RGB, transformed with the simulation matrix of Machado et al.,
+/// and converted back. Grays are unaffected.
+pub fn simulate_cvd(rgb: [u8; 3], kind: ColorVisionDeficiency) -> [u8; 3] {
+    let linear = rgb.map(srgb_to_linear);
+    let matrix = kind.matrix();
+    let mut result = [0; 3];
+    for (out, row) in result.iter_mut().zip(matrix) {
+        let l = row[0] * linear[0] + row[1] * linear[1] + row[2] * linear[2];
+        *out = linear_to_srgb(l);
+    }
+    result
+}
+
+/// Converts an RGB color to `[hue, saturation, value]`, with the hue in degrees from 0 to 360 and
+/// the saturation and value from 0 to 1.
+///
+/// Grays have a hue of 0.
+pub fn rgb_to_hsv(rgb: [u8; 3]) -> [f32; 3] {
+    let [r, g, b] = rgb.map(|c| c as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    let saturation = if max == 0.0 { 0.0 } else { delta / max };
+    [hue, saturation, max]
+}
+
+/// Converts `[hue, saturation, value]` to an RGB color, see [`rgb_to_hsv`].
+///
+/// The hue wraps around, the saturation and value are clamped to 0 to 1.
+pub fn hsv_to_rgb(hsv: [f32; 3]) -> [u8; 3] {
+    let hue = hsv[0].rem_euclid(360.0) / 60.0;
+    let saturation = hsv[1].clamp(0.0, 1.0);
+    let value = hsv[2].clamp(0.0, 1.0);
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let [r, g, b] = match hue as u32 {
+        0 => [chroma, x, 0.0],
+        1 => [x, chroma, 0.0],
+        2 => [0.0, chroma, x],
+        3 => [0.0, x, chroma],
+        4 => [x, 0.0, chroma],
+        _ => [chroma, 0.0, x],
+    };
+    let min = value - chroma;
+    [r, g, b].map(|c| ((c + min) * 255.0).round() as u8)
+}
+
+/// Linearly interpolates between two colors in linear RGB, by `t` from 0 to 1.
+///
+/// Unlike [`lerp_color`](crate::util::lerp_color), which interpolates the sRGB values, this keeps
+/// the brightness of the midpoints, e.g. red to green passes through a bright yellow instead of a
+/// muddy brown.
+pub fn lerp_color_linear(a: [u8; 3], b: [u8; 3], t: f32) -> [u8; 3] {
+    let t = t.clamp(0.0, 1.0) as f64;
+    let mut result = [0; 3];
+    for i in 0..3 {
+        let a = srgb_to_linear(a[i]);
+        let b = srgb_to_linear(b[i]);
+        result[i] = linear_to_srgb(a + (b - a) * t);
+    }
+    result
+}
+
+/// A color gradient along color stops, e.g. for heatmaps.
+///
+/// ```
+/// use teng::rendering::color::Gradient;
+///
+/// let heat = Gradient::new(&[(0.0, [255, 0, 0]), (0.5, [255, 255, 0]), (1.0, [0, 255, 0])]);
+/// assert_eq!(heat.sample(0.5), [255, 255, 0]);
+/// assert_eq!(heat.sample(0.25), [255, 127, 0]);
+/// // clamped to the first and last stop
+/// assert_eq!(heat.sample(-1.0), [255, 0, 0]);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    stops: Vec<(f32, [u8; 3])>,
+    gamma_correct: bool,
+}
+
+impl Gradient {
+    /// Creates a gradient from `(position, color)` stops. The stops are sorted by position.
+    ///
+    /// # Panics
+    ///
+    /// If there are no stops.
+    pub fn new(stops: &[(f32, [u8; 3])]) -> Self {
+        assert!(!stops.is_empty(), "a gradient needs at least one stop");
+        let mut stops = stops.to_vec();
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self {
+            stops,
+            gamma_correct: false,
+        }
+    }
+
+    /// Interpolates between stops in linear RGB, see [`lerp_color_linear`].
+    pub fn with_gamma_correction(mut self, gamma_correct: bool) -> Self {
+        self.gamma_correct = gamma_correct;
+        self
+    }
+
+    /// Returns the color at position `t`, interpolated between the two stops around it.
+    ///
+    /// Positions before the first or after the last stop have the color of that stop.
+    pub fn sample(&self, t: f32) -> [u8; 3] {
+        let next = self.stops.partition_point(|&(position, _)| position <= t);
+        if next == 0 {
+            return self.stops[0].1;
+        }
+        let (from_position, from) = self.stops[next - 1];
+        let Some(&(to_position, to)) = self.stops.get(next) else {
+            return from;
+        };
+        let t = (t - from_position) / (to_position - from_position);
+        if self.gamma_correct {
+            lerp_color_linear(from, to, t)
+        } else {
+            crate::util::lerp_color(from, to, t)
+        }
+    }
+
+    pub fn stops(&self) -> &[(f32, [u8; 3])] {
+        &self.stops
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: [u8; 3], expected: [u8; 3]) {
+        for (a, e) in actual.into_iter().zip(expected) {
+            assert!(a.abs_diff(e) <= 1, "{actual:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn test_cvd_reference_colors() {
+        use ColorVisionDeficiency::*;
+        let cases = [
+            (Protanopia, [255, 0, 0], [109, 95, 0]),
+            (Protanopia, [0, 255, 0], [255, 229, 0]),
+            (Protanopia, [0, 0, 255], [0, 89, 255]),
+            (Protanopia, [230, 159, 0], [185, 162, 0]),
+            (Deuteranopia, [255, 0, 0], [163, 144, 0]),
+            (Deuteranopia, [0, 255, 0], [239, 214, 58]),
+            (Deuteranopia, [0, 0, 255], [0, 61, 251]),
+            (Deuteranopia, [230, 159, 0], [202, 180, 17]),
+            (Tritanopia, [255, 0, 0], [255, 0, 15]),
+            (Tritanopia, [0, 255, 0], [0, 247, 217]),
+            (Tritanopia, [0, 0, 255], [0, 107, 150]),
+            (Tritanopia, [230, 159, 0], [251, 140, 135]),
+        ];
+        for (kind, rgb, expected) in cases {
+            assert_close(simulate_cvd(rgb, kind), expected);
+        }
+    }
+
+    #[test]
+    fn test_quantize_palette_colors() {
+        // palette colors quantize to themselves, up to the precision of the lookup table
+        for index in 16..=231 {
+            assert_eq!(rgb_to_ansi256(ansi_to_rgb(index)), index);
+        }
+        for index in 232..=255 {
+            let gray = ansi_to_rgb(index);
+            assert!(distance(ansi_to_rgb(rgb_to_ansi256(gray)), gray) <= 3 * 8 * 8);
+        }
+        for index in 0..16 {
+            assert_eq!(rgb_to_ansi16(ansi_to_rgb(index)), index);
+        }
+        assert_eq!(rgb_to_ansi256([255, 0, 0]), 196);
+        assert_eq!(rgb_to_ansi256([250, 10, 5]), 196);
+        assert_eq!(rgb_to_ansi256([118, 118, 118]), 243);
+        assert_eq!(rgb_to_ansi16([255, 0, 0]), 9);
+        assert_eq!(rgb_to_ansi16([180, 20, 10]), 1);
+    }
+
+    #[test]
+    fn test_color_mode_from_env() {
+        use ColorMode::*;
+        let cases = [
+            (Some("truecolor"), Some("xterm-256color"), TrueColor),
+            (Some("24bit"), Some("xterm"), TrueColor),
+            (None, Some("xterm-256color"), Ansi256),
+            (None, Some("screen-256color"), Ansi256),
+            (None, Some("xterm-direct"), TrueColor),
+            (None, Some("xterm"), Ansi16),
+            (Some(""), Some("linux"), Ansi16),
+            (None, None, TrueColor),
+        ];
+        for (colorterm, term, expected) in cases {
+            assert_eq!(ColorMode::from_env(colorterm, term), expected, "{term:?}");
+        }
+    }
+
+    #[test]
+    fn test_hsv_round_trip() {
+        for r in (0..=255).step_by(15) {
+            for g in (0..=255).step_by(15) {
+                for b in (0..=255).step_by(15) {
+                    let rgb = [r as u8, g as u8, b as u8];
+                    assert_close(hsv_to_rgb(rgb_to_hsv(rgb)), rgb);
+                }
+            }
+        }
+        assert_eq!(rgb_to_hsv([255, 0, 0]), [0.0, 1.0, 1.0]);
+        assert_eq!(rgb_to_hsv([0, 0, 255]), [240.0, 1.0, 1.0]);
+        assert_eq!(hsv_to_rgb([120.0, 1.0, 1.0]), [0, 255, 0]);
+        assert_eq!(hsv_to_rgb([480.0, 1.0, 0.5]), [0, 128, 0]);
+    }
+
+    #[test]
+    fn test_gradient_stops_and_interpolation() {
+        let stops = [(1.0, [0, 255, 0]), (0.0, [255, 0, 0]), (0.5, [255, 255, 0])];
+        let gradient = Gradient::new(&stops);
+        for (position, color) in stops {
+            assert_eq!(gradient.sample(position), color);
+        }
+        assert_eq!(gradient.sample(0.75), [127, 255, 0]);
+        assert_eq!(gradient.sample(2.0), [0, 255, 0]);
+        assert_eq!(Gradient::new(&[(0.3, [1, 2, 3])]).sample(0.0), [1, 2, 3]);
+
+        // linear RGB keeps the midpoint of red and green bright
+        let naive = Gradient::new(&[(0.0, [255, 0, 0]), (1.0, [0, 255, 0])]);
+        assert_eq!(naive.sample(0.5), [127, 127, 0]);
+        let linear = naive.with_gamma_correction(true);
+        assert_close(linear.sample(0.5), [188, 188, 0]);
+        assert_eq!(linear.sample(1.0), [0, 255, 0]);
+    }
+
+    #[test]
+    fn test_lighten_and_darken() {
+        let color = Color::Rgb([200, 100, 0]);
+        assert_eq!(color.lighten(0.0), color);
+        assert_eq!(color.lighten(1.0), Color::Rgb([255; 3]));
+        assert_eq!(color.darken(1.0), Color::Rgb([0; 3]));
+        let Color::Rgb(lighter) = color.lighten(0.5) else {
+            panic!()
+        };
+        assert!(rgb_to_hsv(lighter)[2] > rgb_to_hsv([200, 100, 0])[2]);
+        assert_eq!(Color::Ansi(9).darken(0.0), Color::Rgb([255, 0, 0]));
+        assert_eq!(Color::Default.lighten(0.5), Color::Default);
+    }
+
+    #[test]
+    fn test_cvd_preserves_grays() {
+        for kind in ColorVisionDeficiency::ALL {
+            for gray in [0, 128, 255] {
+                assert_close(simulate_cvd([gray; 3], kind), [gray; 3]);
+            }
+            assert_eq!(Color::Default.simulate_cvd(kind), Color::Default);
+            assert_eq!(Color::Transparent.simulate_cvd(kind), Color::Transparent);
         }
     }
 }
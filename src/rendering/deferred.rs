@@ -0,0 +1,135 @@
+//! Drawing from outside of `render()`.
+//!
+//! [`Component::on_event`](crate::components::Component::on_event) and
+//! [`Component::update`](crate::components::Component::update) have no access to a renderer.
+//! To react to an event with a visual, e.g. a ripple exactly where a click happened, queue a
+//! closure with [`SharedState::draw_later`](crate::SharedState::draw_later). The closure captures
+//! its own data, so every event of a frame gets its own visual.
+//!
+//! Queued closures run once, after all components have rendered, and the queue is cleared every
+//! frame.
+
+use crate::rendering::pixel::Pixel;
+use crate::rendering::render::{KittyImageRender, SixelRender};
+use crate::rendering::renderer::{ClipRect, FrameView, Renderer};
+
+/// A queued draw, see [`DrawQueue`].
+pub type DrawFn = Box<dyn FnOnce(&mut dyn Renderer)>;
+
+/// The queue of draws that run after all components have rendered.
+pub struct DrawQueue {
+    draws: Vec<(Option<i32>, DrawFn)>,
+    max_len: usize,
+    dropped: usize,
+}
+
+impl DrawQueue {
+    /// The default maximum number of draws per frame.
+    pub const DEFAULT_MAX_LEN: usize = 1024;
+    /// The lowest depth of the range that draws without an explicit depth are placed in.
+    ///
+    /// Draws are placed in queue order, so later draws are on top. The range is below the
+    /// overlays of the built-in components, e.g. context menus.
+    pub const TOP_DEPTH: i32 = i32::MAX - 10_000;
+
+    pub(crate) fn new() -> Self {
+        Self {
+            draws: Vec::new(),
+            max_len: Self::DEFAULT_MAX_LEN,
+            dropped: 0,
+        }
+    }
+
+    /// Queues a draw. Pixels it renders are placed in the top range, see [`Self::TOP_DEPTH`].
+    pub fn push(&mut self, draw: impl FnOnce(&mut dyn Renderer) + 'static) {
+        self.push_with_depth(None, Box::new(draw));
+    }
+
+    /// Queues a draw. Pixels it renders are placed at `depth`, regardless of the depth they are
+    /// rendered with.
+    pub fn push_at(&mut self, depth: i32, draw: impl FnOnce(&mut dyn Renderer) + 'static) {
+        self.push_with_depth(Some(depth), Box::new(draw));
+    }
+
+    fn push_with_depth(&mut self, depth: Option<i32>, draw: DrawFn) {
+        if self.draws.len() >= self.max_len {
+            self.dropped += 1;
+            return;
+        }
+        self.draws.push((depth, draw));
+    }
+
+    /// Sets the maximum number of draws per frame. Further draws are dropped with a debug warning.
+    pub fn set_max_len(&mut self, max_len: usize) {
+        self.max_len = max_len;
+    }
+
+    /// Returns the number of queued draws.
+    pub fn len(&self) -> usize {
+        self.draws.len()
+    }
+
+    /// Returns true if no draws are queued.
+    pub fn is_empty(&self) -> bool {
+        self.draws.is_empty()
+    }
+
+    /// Runs and removes all queued draws.
+    ///
+    /// Returns the number of draws that were dropped since the last call, because the queue was
+    /// full.
+    pub(crate) fn run(&mut self, renderer: &mut dyn Renderer) -> usize {
+        for (idx, (depth, draw)) in self.draws.drain(..).enumerate() {
+            let depth = depth.unwrap_or(Self::TOP_DEPTH.saturating_add(idx as i32));
+            draw(&mut FixedDepthRenderer {
+                inner: renderer,
+                depth,
+            });
+        }
+        std::mem::take(&mut self.dropped)
+    }
+}
+
+/// Renders everything at a fixed depth.
+struct FixedDepthRenderer<'a> {
+    inner: &'a mut dyn Renderer,
+    depth: i32,
+}
+
+impl Renderer for FixedDepthRenderer<'_> {
+    fn render_pixel(&mut self, x: usize, y: usize, pixel: Pixel, _depth: i32) {
+        self.inner.render_pixel(x, y, pixel, self.depth);
+    }
+
+    fn set_default_bg_color(&mut self, color: [u8; 3]) {
+        self.inner.set_default_bg_color(color);
+    }
+
+    fn previous_frame(&self) -> Option<FrameView<'_>> {
+        self.inner.previous_frame()
+    }
+
+    fn depth_at(&self, x: usize, y: usize) -> i32 {
+        self.inner.depth_at(x, y)
+    }
+
+    fn push_clip(&mut self, x: usize, y: usize, width: usize, height: usize) {
+        self.inner.push_clip(x, y, width, height);
+    }
+
+    fn pop_clip(&mut self) {
+        self.inner.pop_clip();
+    }
+
+    fn clip(&self) -> Option<ClipRect> {
+        self.inner.clip()
+    }
+
+    fn render_image(&mut self, x: usize, y: usize, image: &KittyImageRender, _depth: i32) -> bool {
+        self.inner.render_image(x, y, image, self.depth)
+    }
+
+    fn render_sixel(&mut self, x: usize, y: usize, image: &SixelRender, _depth: i32) -> bool {
+        self.inner.render_sixel(x, y, image, self.depth)
+    }
+}
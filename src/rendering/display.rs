@@ -228,6 +228,52 @@ impl<T> Display<T> {
             (x, y, pixel)
         })
     }
+
+    /// Returns the rows of the display with their y coordinate, top first.
+    ///
+    /// The rows are disjoint slices, so they can be filled on several threads, e.g. with rayon's
+    /// `par_bridge`. See also [`par_fill_with`](Self::par_fill_with).
+    ///
+    /// ```rust
+    /// use teng::rendering::display::Display;
+    ///
+    /// let mut display = Display::new(3, 2, 0);
+    /// for (y, row) in display.rows_mut() {
+    ///     for (x, value) in row.iter_mut().enumerate() {
+    ///         *value = 10 * y + x;
+    ///     }
+    /// }
+    /// assert_eq!(display[(2, 1)], 12);
+    /// ```
+    pub fn rows_mut(&mut self) -> impl Iterator<Item = (usize, &mut [T])> {
+        // an empty display has no pixels, and chunks must not be empty
+        self.pixels.chunks_mut(self.width.max(1)).enumerate()
+    }
+
+    /// Sets every pixel to `f(x, y)`, filling the rows in parallel on rayon's global thread pool.
+    ///
+    /// ```rust
+    /// use teng::rendering::display::Display;
+    ///
+    /// let mut display = Display::new(300, 180, 0);
+    /// display.par_fill_with(|x, y| x * y);
+    /// assert_eq!(display[(299, 179)], 299 * 179);
+    /// ```
+    #[cfg(feature = "parallel")]
+    pub fn par_fill_with(&mut self, f: impl Fn(usize, usize) -> T + Sync)
+    where
+        T: Send,
+    {
+        use rayon::prelude::*;
+        self.pixels
+            .par_chunks_mut(self.width.max(1))
+            .enumerate()
+            .for_each(|(y, row)| {
+                for (x, pixel) in row.iter_mut().enumerate() {
+                    *pixel = f(x, y);
+                }
+            });
+    }
 }
 
 impl<T> Index<(usize, usize)> for Display<T> {
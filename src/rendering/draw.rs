@@ -0,0 +1,319 @@
+//! Drawing primitives for color buffers: lines, rectangles, circles and polygons.
+//!
+//! The primitives are provided methods of the [`Canvas`] trait, which is implemented for
+//! [`HalfBlockDisplayRender`] and [`Display<Color>`]. Coordinates are signed and everything is
+//! clipped to the buffer, so off-screen geometry is fine:
+//! ```
+//! use teng::rendering::color::Color;
+//! use teng::rendering::draw::Canvas;
+//! use teng::rendering::render::HalfBlockDisplayRender;
+//!
+//! let mut hbd = HalfBlockDisplayRender::new(20, 20);
+//! let red = Color::Rgb([255, 0, 0]);
+//! hbd.draw_line(-5, -5, 30, 10, red);
+//! hbd.fill_circle(10, 10, 4, red);
+//! hbd.fill_polygon(&[(0, 19), (5, 12), (10, 19)], red);
+//! assert_eq!(hbd.get_color(10, 10), Some(red));
+//! ```
+//!
+//! Shapes are inclusive of their corners: `fill_rect(0, 0, 2, 1, ..)` fills 3x2 pixels, and a
+//! polygon covers the pixels whose centers are inside it or on its outline.
+
+use crate::rendering::color::Color;
+use crate::rendering::display::Display;
+use crate::rendering::render::HalfBlockDisplayRender;
+use crate::util::for_coord_in_line;
+
+/// A buffer of colors that can be drawn on.
+pub trait Canvas {
+    /// Returns the width and height of the buffer.
+    fn canvas_size(&self) -> (usize, usize);
+
+    /// Sets the color of a pixel that is within the buffer.
+    fn put(&mut self, x: usize, y: usize, color: Color);
+
+    /// Sets the color of a pixel, if it is within the buffer.
+    fn plot(&mut self, x: i64, y: i64, color: Color) {
+        let (width, height) = self.canvas_size();
+        if x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height {
+            self.put(x as usize, y as usize, color);
+        }
+    }
+
+    /// Draws a line from `(x0, y0)` to `(x1, y1)` with Bresenham's algorithm.
+    ///
+    /// Lines that are entirely on one side outside of the buffer are skipped, otherwise the cost is
+    /// proportional to the length of the line.
+    fn draw_line(&mut self, x0: i64, y0: i64, x1: i64, y1: i64, color: Color) {
+        let (width, height) = self.canvas_size();
+        let (width, height) = (width as i64, height as i64);
+        if (x0 < 0 && x1 < 0)
+            || (y0 < 0 && y1 < 0)
+            || (x0 >= width && x1 >= width)
+            || (y0 >= height && y1 >= height)
+        {
+            return;
+        }
+        for_coord_in_line(false, (x0, y0), (x1, y1), |x, y| self.plot(x, y, color));
+    }
+
+    /// Draws the outline of the rectangle with the corners `(x0, y0)` and `(x1, y1)`.
+    fn draw_rect(&mut self, x0: i64, y0: i64, x1: i64, y1: i64, color: Color) {
+        let (min_x, max_x) = (x0.min(x1), x0.max(x1));
+        let (min_y, max_y) = (y0.min(y1), y0.max(y1));
+        self.fill_rect(min_x, min_y, max_x, min_y, color);
+        self.fill_rect(min_x, max_y, max_x, max_y, color);
+        self.fill_rect(min_x, min_y, min_x, max_y, color);
+        self.fill_rect(max_x, min_y, max_x, max_y, color);
+    }
+
+    /// Fills the rectangle with the corners `(x0, y0)` and `(x1, y1)`.
+    fn fill_rect(&mut self, x0: i64, y0: i64, x1: i64, y1: i64, color: Color) {
+        let (width, height) = self.canvas_size();
+        let Some((min_x, max_x)) = clip_span(x0.min(x1), x0.max(x1), width) else {
+            return;
+        };
+        let Some((min_y, max_y)) = clip_span(y0.min(y1), y0.max(y1), height) else {
+            return;
+        };
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                self.put(x, y, color);
+            }
+        }
+    }
+
+    /// Draws the outline of a circle with the midpoint algorithm.
+    fn draw_circle(&mut self, cx: i64, cy: i64, radius: i64, color: Color) {
+        for_circle_octant(radius, |x, y| {
+            for (dx, dy) in [(x, y), (y, x), (-y, x), (-x, y)] {
+                self.plot(cx + dx, cy + dy, color);
+                self.plot(cx - dx, cy - dy, color);
+            }
+        });
+    }
+
+    /// Fills a circle, covering the same pixels as [`draw_circle`](Self::draw_circle) and its
+    /// inside.
+    fn fill_circle(&mut self, cx: i64, cy: i64, radius: i64, color: Color) {
+        for_circle_octant(radius, |x, y| {
+            self.fill_rect(cx - x, cy + y, cx + x, cy + y, color);
+            self.fill_rect(cx - x, cy - y, cx + x, cy - y, color);
+            self.fill_rect(cx - y, cy + x, cx + y, cy + x, color);
+            self.fill_rect(cx - y, cy - x, cx + y, cy - x, color);
+        });
+    }
+
+    /// Fills a polygon with the scanline algorithm. The vertices are connected in order, and the
+    /// last one to the first. Self-intersecting polygons are filled with the even-odd rule.
+    fn fill_polygon(&mut self, vertices: &[(i64, i64)], color: Color) {
+        let (width, height) = self.canvas_size();
+        let Some(min_y) = vertices.iter().map(|&(_, y)| y).min() else {
+            return;
+        };
+        let max_y = vertices.iter().map(|&(_, y)| y).max().unwrap();
+        let edges = || {
+            vertices
+                .iter()
+                .zip(vertices.iter().cycle().skip(1))
+                .map(|(&a, &b)| (a, b))
+        };
+        if let Some((min_y, max_y)) = clip_span(min_y, max_y, height) {
+            let mut crossings = vec![];
+            for y in min_y..=max_y {
+                let y = y as i64;
+                crossings.clear();
+                // half-open, so that vertices between two edges count once
+                for ((x0, y0), (x1, y1)) in edges() {
+                    if (y0 <= y && y < y1) || (y1 <= y && y < y0) {
+                        let t = (y - y0) as f64 / (y1 - y0) as f64;
+                        crossings.push(x0 as f64 + t * (x1 - x0) as f64);
+                    }
+                }
+                crossings.sort_by(f64::total_cmp);
+                for span in crossings.chunks_exact(2) {
+                    let (start, end) = (span[0].ceil() as i64, span[1].floor() as i64);
+                    if start <= end && clip_span(start, end, width).is_some() {
+                        self.fill_rect(start, y, end, y, color);
+                    }
+                }
+            }
+        }
+        // the outline covers the rows and columns the half-open rule leaves out
+        for ((x0, y0), (x1, y1)) in edges() {
+            self.draw_line(x0, y0, x1, y1, color);
+        }
+    }
+}
+
+/// Clips the inclusive span `min..=max` to `0..len`.
+fn clip_span(min: i64, max: i64, len: usize) -> Option<(usize, usize)> {
+    if max < 0 || len == 0 || min >= len as i64 {
+        return None;
+    }
+    Some((min.max(0) as usize, max.min(len as i64 - 1) as usize))
+}
+
+/// Calls `f` with the points of the first octant of a circle around the origin, from `(radius, 0)`
+/// until `x < y`, with the midpoint algorithm.
+fn for_circle_octant(radius: i64, mut f: impl FnMut(i64, i64)) {
+    if radius < 0 {
+        return;
+    }
+    let (mut x, mut y) = (radius, 0);
+    let mut err = 1 - radius;
+    while x >= y {
+        f(x, y);
+        y += 1;
+        if err < 0 {
+            err += 2 * y + 1;
+        } else {
+            x -= 1;
+            err += 2 * (y - x) + 1;
+        }
+    }
+}
+
+impl Canvas for HalfBlockDisplayRender {
+    fn canvas_size(&self) -> (usize, usize) {
+        (self.width(), self.height())
+    }
+
+    fn put(&mut self, x: usize, y: usize, color: Color) {
+        self.set_color(x, y, color);
+    }
+}
+
+impl Canvas for Display<Color> {
+    fn canvas_size(&self) -> (usize, usize) {
+        (self.width(), self.height())
+    }
+
+    fn put(&mut self, x: usize, y: usize, color: Color) {
+        self.set(x, y, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Color = Color::Rgb([255, 0, 0]);
+
+    /// Returns the drawn pixels in row-major order.
+    fn drawn(
+        width: usize,
+        height: usize,
+        draw: impl FnOnce(&mut Display<Color>),
+    ) -> Vec<(usize, usize)> {
+        let mut display = Display::new(width, height, Color::Transparent);
+        draw(&mut display);
+        let mut pixels: Vec<_> = display
+            .iter()
+            .filter(|&(_, _, &color)| color == RED)
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        pixels.sort_by_key(|&(x, y)| (y, x));
+        pixels
+    }
+
+    #[test]
+    fn test_lines_and_rects() {
+        assert_eq!(
+            drawn(5, 5, |d| d.draw_line(0, 0, 4, 2, RED)),
+            vec![(0, 0), (1, 0), (2, 1), (3, 1), (4, 2)]
+        );
+        // clipped at the buffer's bounds
+        assert_eq!(
+            drawn(3, 3, |d| d.draw_line(-2, 1, 10, 1, RED)),
+            vec![(0, 1), (1, 1), (2, 1)]
+        );
+        assert!(drawn(3, 3, |d| d.draw_line(-10, -1, 10, -5, RED)).is_empty());
+        assert_eq!(
+            drawn(4, 4, |d| d.draw_rect(3, 2, 1, 0, RED)),
+            vec![
+                (1, 0),
+                (2, 0),
+                (3, 0),
+                (1, 1),
+                (3, 1),
+                (1, 2),
+                (2, 2),
+                (3, 2)
+            ]
+        );
+        assert_eq!(
+            drawn(3, 3, |d| d.fill_rect(-5, 1, 1, i64::MAX, RED)),
+            vec![(0, 1), (1, 1), (0, 2), (1, 2)]
+        );
+    }
+
+    #[test]
+    fn test_circles() {
+        assert_eq!(
+            drawn(5, 5, |d| d.draw_circle(2, 2, 2, RED)),
+            vec![
+                (1, 0),
+                (2, 0),
+                (3, 0),
+                (0, 1),
+                (4, 1),
+                (0, 2),
+                (4, 2),
+                (0, 3),
+                (4, 3),
+                (1, 4),
+                (2, 4),
+                (3, 4)
+            ]
+        );
+        let filled = drawn(5, 5, |d| d.fill_circle(2, 2, 2, RED));
+        assert_eq!(filled.len(), 21);
+        // the fill covers the outline
+        let outline = drawn(5, 5, |d| d.draw_circle(2, 2, 2, RED));
+        assert!(outline.iter().all(|pixel| filled.contains(pixel)));
+        // off-center circles are clipped
+        assert_eq!(
+            drawn(3, 3, |d| d.fill_circle(-1, -1, 2, RED)),
+            vec![(0, 0), (1, 0), (0, 1)]
+        );
+        assert_eq!(drawn(3, 3, |d| d.draw_circle(1, 1, 0, RED)), vec![(1, 1)]);
+    }
+
+    #[test]
+    fn test_fill_polygon() {
+        // a rectangle covers its corners
+        assert_eq!(
+            drawn(5, 5, |d| d
+                .fill_polygon(&[(1, 1), (3, 1), (3, 2), (1, 2)], RED)),
+            vec![(1, 1), (2, 1), (3, 1), (1, 2), (2, 2), (3, 2)]
+        );
+        assert_eq!(
+            drawn(5, 5, |d| d.fill_polygon(&[(0, 0), (4, 4), (0, 4)], RED)),
+            vec![
+                (0, 0),
+                (0, 1),
+                (1, 1),
+                (0, 2),
+                (1, 2),
+                (2, 2),
+                (0, 3),
+                (1, 3),
+                (2, 3),
+                (3, 3),
+                (0, 4),
+                (1, 4),
+                (2, 4),
+                (3, 4),
+                (4, 4)
+            ]
+        );
+        // mostly off-screen
+        assert_eq!(
+            drawn(3, 3, |d| d
+                .fill_polygon(&[(-10, -10), (1, -10), (1, 1), (-10, 1)], RED)),
+            vec![(0, 0), (1, 0), (0, 1), (1, 1)]
+        );
+        assert!(drawn(3, 3, |d| d.fill_polygon(&[], RED)).is_empty());
+    }
+}
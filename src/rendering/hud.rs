@@ -0,0 +1,415 @@
+//! Layout helpers for HUD readouts that keep a constant footprint.
+//!
+//! A readout like `format!("Score: {score}")` grows by a cell whenever the score gains a digit,
+//! which shifts all text after it and makes the renderer repaint the whole row. The helpers
+//! here allocate fixed columns instead:
+//!
+//! *   [`FixedWidthNumber`]: a number padded to a fixed width, optionally with thousands
+//!     separators.
+//! *   [`AlignText::right_align_at`]: text whose last character sits at a fixed column.
+//! *   [`HudRow`]: a row of labeled numbers and texts with fixed columns, rendered in one call.
+//!
+//! ```rust
+//! use teng::rendering::hud::{FixedWidthNumber, HudRow};
+//! use teng::rendering::render::Render;
+//! # use teng::rendering::renderer::Renderer;
+//! # fn render(renderer: &mut dyn Renderer, score: u64, fps: f64) {
+//!
+//! HudRow::new()
+//!     .field("Score:", FixedWidthNumber::for_max(score as f64, 9_999_999.0).with_separator(','))
+//!     .field("FPS:", FixedWidthNumber::for_max(fps, 999.0).with_decimals(1))
+//!     .render(renderer, 0, 0, 0);
+//! // "Score:         0  FPS:  60.0" at every score and fps up to the declared maximums
+//! # }
+//! ```
+
+use crate::rendering::render::Render;
+use crate::rendering::renderer::Renderer;
+
+/// How a [`FixedWidthNumber`] determines its width.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Width {
+    Fixed(usize),
+    /// Wide enough for every value from 0 to the maximum.
+    ForMax(f64),
+}
+
+/// A number rendered into a fixed number of cells, right-aligned.
+///
+/// Values that do not fit are rendered as `#` in every cell, so the footprint never changes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FixedWidthNumber {
+    value: f64,
+    width: Width,
+    decimals: usize,
+    separator: Option<char>,
+    zeros: bool,
+    signed: bool,
+}
+
+impl FixedWidthNumber {
+    /// Creates a number rendered into exactly `width` cells.
+    pub fn new(value: f64, width: usize) -> Self {
+        Self::with_width(value, Width::Fixed(width))
+    }
+
+    /// Creates a number that is as wide as the largest of the values from 0 to `max` with the
+    /// same options, e.g. 9 cells for a `max` of `1_000_000` with separators.
+    pub fn for_max(value: f64, max: f64) -> Self {
+        Self::with_width(value, Width::ForMax(max))
+    }
+
+    fn with_width(value: f64, width: Width) -> Self {
+        Self {
+            value,
+            width,
+            decimals: 0,
+            separator: None,
+            zeros: false,
+            signed: false,
+        }
+    }
+
+    /// Shows `decimals` digits after the decimal point.
+    pub fn with_decimals(mut self, decimals: usize) -> Self {
+        self.decimals = decimals;
+        self
+    }
+
+    /// Separates groups of thousands with `separator`, e.g. `1,000,000`.
+    pub fn with_separator(mut self, separator: char) -> Self {
+        self.separator = Some(separator);
+        self
+    }
+
+    /// Pads with leading zeros instead of spaces.
+    pub fn with_zeros(mut self) -> Self {
+        self.zeros = true;
+        self
+    }
+
+    /// Reserves a cell for the sign of negative values, when the width is computed with
+    /// [`FixedWidthNumber::for_max`].
+    pub fn signed(mut self) -> Self {
+        self.signed = true;
+        self
+    }
+
+    /// Returns the number of cells the number occupies.
+    pub fn width(&self) -> usize {
+        match self.width {
+            Width::Fixed(width) => width,
+            Width::ForMax(max) => {
+                let (int_digits, frac) = self.digits(max.abs());
+                usize::from(self.signed) + self.grouped_len(int_digits.len()) + frac.len()
+            }
+        }
+    }
+
+    /// Returns the integer digits and the fractional part including the point of `value`.
+    fn digits(&self, value: f64) -> (String, String) {
+        let formatted = format!("{:.*}", self.decimals, value.abs());
+        match formatted.split_once('.') {
+            Some((int, frac)) => (int.to_string(), format!(".{frac}")),
+            None => (formatted, String::new()),
+        }
+    }
+
+    /// Returns the length of `digits` integer digits with separators.
+    fn grouped_len(&self, digits: usize) -> usize {
+        match self.separator {
+            Some(_) => digits + digits.saturating_sub(1) / 3,
+            None => digits,
+        }
+    }
+
+    fn group(&self, digits: &str) -> String {
+        let Some(separator) = self.separator else {
+            return digits.to_string();
+        };
+        let mut grouped = String::with_capacity(self.grouped_len(digits.len()));
+        for (i, c) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                grouped.push(separator);
+            }
+            grouped.push(c);
+        }
+        grouped
+    }
+
+    /// Returns the number formatted to exactly [`FixedWidthNumber::width`] characters.
+    pub fn format(&self) -> String {
+        let width = self.width();
+        let (mut int_digits, frac) = self.digits(self.value);
+        let is_zero = int_digits
+            .chars()
+            .chain(frac.chars())
+            .all(|c| c == '0' || c == '.');
+        let negative = self.value < 0.0 && !is_zero;
+        let sign = if negative { "-" } else { "" };
+        if self.zeros {
+            // as many zeros as fit, the rest is filled with spaces
+            while sign.len() + self.grouped_len(int_digits.len() + 1) + frac.len() <= width {
+                int_digits.insert(0, '0');
+            }
+        }
+        let text = format!("{sign}{}{frac}", self.group(&int_digits));
+        if text.chars().count() > width {
+            "#".repeat(width)
+        } else {
+            format!("{text:>width$}")
+        }
+    }
+}
+
+impl Render for FixedWidthNumber {
+    fn render(&self, renderer: &mut dyn Renderer, x: usize, y: usize, depth: i32) {
+        self.format().render(renderer, x, y, depth);
+    }
+}
+
+/// Text aligned at its right end, see [`AlignText::right_align_at`].
+#[derive(Debug, Clone, Copy)]
+pub struct RightAligned<'a> {
+    text: &'a str,
+    x_right: usize,
+}
+
+impl Render for RightAligned<'_> {
+    /// Renders every line so that its last character is at the column `x_right`. The `x` of the
+    /// render call is the leftmost column: characters that would be left of it are cut off.
+    fn render(&self, renderer: &mut dyn Renderer, x: usize, y: usize, depth: i32) {
+        for (dy, line) in self.text.lines().enumerate() {
+            let len = line.chars().count();
+            let start = (self.x_right + 1) as isize - len as isize;
+            let skip = (x as isize - start).max(0) as usize;
+            let start = start.max(x as isize) as usize;
+            for (i, c) in line.chars().skip(skip).enumerate() {
+                c.render(renderer, start + i, y + dy, depth);
+            }
+        }
+    }
+}
+
+/// Alignment adapters for text.
+pub trait AlignText {
+    /// Aligns the text so that its last character sits at the column `x_right`, regardless of
+    /// its length. Render it with the leftmost column it may use as `x`, usually 0.
+    fn right_align_at(&self, x_right: usize) -> RightAligned<'_>;
+}
+
+impl AlignText for str {
+    fn right_align_at(&self, x_right: usize) -> RightAligned<'_> {
+        RightAligned {
+            text: self,
+            x_right,
+        }
+    }
+}
+
+impl AlignText for String {
+    fn right_align_at(&self, x_right: usize) -> RightAligned<'_> {
+        self.as_str().right_align_at(x_right)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum HudItem {
+    Field {
+        label: String,
+        number: FixedWidthNumber,
+    },
+    Text {
+        text: String,
+        width: usize,
+    },
+}
+
+impl HudItem {
+    fn width(&self) -> usize {
+        match self {
+            HudItem::Field { label, number } => {
+                let label_len = label.chars().count();
+                label_len + usize::from(label_len > 0) + number.width()
+            }
+            HudItem::Text { width, .. } => *width,
+        }
+    }
+}
+
+/// A row of readouts with fixed columns.
+///
+/// Every item occupies the same cells no matter its value, and items are separated by a gap of
+/// two spaces by default.
+#[derive(Debug, Clone)]
+pub struct HudRow {
+    gap: usize,
+    items: Vec<HudItem>,
+}
+
+impl Default for HudRow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HudRow {
+    pub fn new() -> Self {
+        Self {
+            gap: 2,
+            items: vec![],
+        }
+    }
+
+    /// Sets the number of spaces between items.
+    pub fn with_gap(mut self, gap: usize) -> Self {
+        self.gap = gap;
+        self
+    }
+
+    /// Adds a number with a label in front of it, separated by a space.
+    pub fn field(mut self, label: impl Into<String>, number: FixedWidthNumber) -> Self {
+        self.items.push(HudItem::Field {
+            label: label.into(),
+            number,
+        });
+        self
+    }
+
+    /// Adds a text padded with spaces or cut off to `width` cells.
+    pub fn text(mut self, text: impl Into<String>, width: usize) -> Self {
+        self.items.push(HudItem::Text {
+            text: text.into(),
+            width,
+        });
+        self
+    }
+
+    /// Returns the start column relative to the row and the width of every item.
+    pub fn columns(&self) -> Vec<(usize, usize)> {
+        let mut x = 0;
+        self.items
+            .iter()
+            .map(|item| {
+                let column = (x, item.width());
+                x += item.width() + self.gap;
+                column
+            })
+            .collect()
+    }
+
+    /// Returns the number of cells of the whole row.
+    pub fn width(&self) -> usize {
+        self.columns().last().map_or(0, |(x, width)| x + width)
+    }
+
+    /// Returns the row as text, exactly [`HudRow::width`] characters long.
+    pub fn format(&self) -> String {
+        let gap = " ".repeat(self.gap);
+        let items = self.items.iter().map(|item| match item {
+            HudItem::Field { label, number } if label.is_empty() => number.format(),
+            HudItem::Field { label, number } => format!("{label} {}", number.format()),
+            HudItem::Text { text, width } => {
+                let text: String = text.chars().take(*width).collect();
+                format!("{text:<width$}")
+            }
+        });
+        items.collect::<Vec<_>>().join(&gap)
+    }
+}
+
+impl Render for HudRow {
+    fn render(&self, renderer: &mut dyn Renderer, x: usize, y: usize, depth: i32) {
+        self.format().render(renderer, x, y, depth);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rendering::pixel::Pixel;
+
+    /// Records the characters written per column of a single row.
+    #[derive(Default)]
+    struct RowRecorder(Vec<(usize, char)>);
+
+    impl Renderer for RowRecorder {
+        fn render_pixel(&mut self, x: usize, _y: usize, pixel: Pixel, _depth: i32) {
+            self.0.push((x, pixel.c));
+        }
+    }
+
+    fn written(render: impl Render, x: usize) -> Vec<(usize, char)> {
+        let mut recorder = RowRecorder::default();
+        render.render(&mut recorder, x, 0, 0);
+        recorder.0
+    }
+
+    #[test]
+    fn test_fixed_width_number() {
+        let number = |value| FixedWidthNumber::for_max(value, 1_000_000.0).with_separator(',');
+        assert_eq!(number(0.0).width(), 9);
+        assert_eq!(number(7.0).format(), "        7");
+        assert_eq!(number(12_345.0).format(), "   12,345");
+        assert_eq!(number(1_000_000.0).format(), "1,000,000");
+        assert_eq!(number(10_000_000.0).format(), "#########");
+
+        assert_eq!(
+            FixedWidthNumber::new(42.0, 5).with_zeros().format(),
+            "00042"
+        );
+        assert_eq!(
+            FixedWidthNumber::new(-42.0, 5).with_zeros().format(),
+            "-0042"
+        );
+        // zeros never exceed the width, even if a separator does not fit
+        assert_eq!(
+            FixedWidthNumber::new(1234.0, 8)
+                .with_zeros()
+                .with_separator(',')
+                .format(),
+            " 001,234"
+        );
+        let fps = |value| FixedWidthNumber::for_max(value, 999.0).with_decimals(2);
+        assert_eq!(fps(59.996).format(), " 60.00");
+        assert_eq!(fps(999.0).format(), "999.00");
+        assert_eq!(
+            FixedWidthNumber::for_max(-5.0, 99.0).signed().format(),
+            " -5"
+        );
+        // rounds to zero without a sign
+        assert_eq!(FixedWidthNumber::new(-0.001, 4).format(), "   0");
+    }
+
+    #[test]
+    fn test_footprint_is_constant() {
+        for value in [0.0, 9.0, 10.0, 999.0, 1000.0, 54_321.0, 99_999.0] {
+            let row = HudRow::new()
+                .field(
+                    "Score:",
+                    FixedWidthNumber::for_max(value, 99_999.0).with_separator(','),
+                )
+                .field("Lives:", FixedWidthNumber::for_max(3.0, 9.0))
+                .text("ok", 4);
+            let columns: Vec<usize> = written(&row, 2).iter().map(|(x, _)| *x).collect();
+            assert_eq!(columns, (2..2 + 29).collect::<Vec<_>>(), "{value}");
+            assert_eq!(row.columns(), vec![(0, 13), (15, 8), (25, 4)]);
+            // the lives are always at the same columns
+            assert_eq!(&row.format()[15..], "Lives: 3  ok  ");
+        }
+    }
+
+    #[test]
+    fn test_right_align_at() {
+        assert_eq!(written("42".right_align_at(9), 0), vec![(8, '4'), (9, '2')]);
+        assert_eq!(
+            written("1337".right_align_at(9), 0),
+            vec![(6, '1'), (7, '3'), (8, '3'), (9, '7')]
+        );
+        // cut off at the leftmost column
+        assert_eq!(
+            written(String::from("abcdef").right_align_at(3), 1),
+            vec![(1, 'd'), (2, 'e'), (3, 'f')]
+        );
+    }
+}
@@ -0,0 +1,157 @@
+//! Raster images with the kitty graphics protocol, see
+//! [`KittyImageRender`](crate::rendering::render::KittyImageRender).
+//!
+//! The protocol is supported by kitty, WezTerm, Ghostty and Konsole, among others. Other
+//! terminals ignore it, so the [`DisplayRenderer`] only uses it once it is enabled with
+//! [`Game::set_kitty_graphics`](crate::Game::set_kitty_graphics), e.g. with the result of
+//! [`detect_kitty_graphics`]. Until then, images are drawn with half blocks.
+//!
+//! The terminal stores every image under the id of its `KittyImageRender`. An image is only sent
+//! again when its pixels changed, and only placed again when it moved. Images that are not
+//! rendered in a frame are deleted with that frame, and [`terminal_cleanup`](crate::terminal_cleanup)
+//! and the [panic handler](crate::install_panic_handler) delete all images that are still shown.
+//!
+//! [`DisplayRenderer`]: crate::rendering::renderer::DisplayRenderer
+
+use crate::util::clipboard::base64;
+use crossterm::event::{Event, KeyCode, KeyEvent};
+use std::io;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::time::Duration;
+
+/// The maximum length of the base64 data in one escape sequence.
+const CHUNK_LEN: usize = 4096;
+
+/// Asks for the support of a 1x1 RGB image, without storing it. The terminal replies with the id
+/// and `OK` if it supports the protocol.
+const QUERY: &str = "\x1b_Gi=31,s=1,v=1,a=q,t=d,f=24;AAAA\x1b\\";
+
+/// Deletes all images, including their data.
+pub(crate) const DELETE_ALL: &str = "\x1b_Ga=d,d=A,q=2\x1b\\";
+
+/// Whether an image was sent to the terminal since the last cleanup.
+pub(crate) static IMAGES_SHOWN: AtomicBool = AtomicBool::new(false);
+
+/// Asks the terminal whether it supports the kitty graphics protocol.
+///
+/// The terminal must be in raw mode, e.g. after [`terminal_setup`](crate::terminal_setup), and
+/// the game must not be running yet. Waits until the terminal answered a cursor position request
+/// sent after the query, which terminals answer in order, for at most about two seconds. Key
+/// presses during the query are discarded.
+///
+/// Returns false if the terminal does not answer.
+pub fn detect_kitty_graphics() -> io::Result<bool> {
+    let mut stdout = io::stdout();
+    stdout.write_all(QUERY.as_bytes())?;
+    stdout.flush()?;
+    // the reply to the query, if any, is queued before the cursor position
+    if crossterm::cursor::position().is_err() {
+        return Ok(false);
+    }
+    // the reply is not a known escape sequence, so it arrives as key presses
+    let mut reply = String::new();
+    while crossterm::event::poll(Duration::ZERO)? {
+        if let Event::Key(KeyEvent {
+            code: KeyCode::Char(c),
+            ..
+        }) = crossterm::event::read()?
+        {
+            reply.push(c);
+        }
+    }
+    Ok(is_supported_reply(&reply))
+}
+
+fn is_supported_reply(reply: &str) -> bool {
+    reply.contains("Gi=31;OK")
+}
+
+/// Returns a new image id. Ids start at 1, since 0 means no id.
+pub(crate) fn next_image_id() -> u32 {
+    static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Returns the sequences that store the `width` by `height` image with the RGBA pixels `rgba`
+/// under `id`, without showing it.
+pub(crate) fn transmit(id: u32, width: usize, height: usize, rgba: &[u8]) -> String {
+    let data = base64(rgba);
+    let mut sequences = String::with_capacity(data.len() + data.len() / CHUNK_LEN * 16 + 64);
+    let mut chunks = data.as_bytes().chunks(CHUNK_LEN).peekable();
+    let mut first = true;
+    while let Some(chunk) = chunks.next() {
+        sequences.push_str("\x1b_G");
+        if std::mem::take(&mut first) {
+            sequences.push_str(&format!("a=t,f=32,s={width},v={height},i={id},q=2,"));
+        }
+        let more = chunks.peek().is_some() as u8;
+        sequences.push_str(&format!("m={more};"));
+        // base64 is ASCII
+        sequences.push_str(std::str::from_utf8(chunk).unwrap());
+        sequences.push_str("\x1b\\");
+    }
+    sequences
+}
+
+/// Returns the sequence that shows the image `id` at the cursor, scaled to `columns` by `rows`
+/// cells, replacing where it was shown before.
+///
+/// The image is drawn below text, and does not move the cursor.
+pub(crate) fn place(id: u32, columns: usize, rows: usize) -> String {
+    format!("\x1b_Ga=p,i={id},p=1,c={columns},r={rows},C=1,z=-1,q=2\x1b\\")
+}
+
+/// Returns the sequence that deletes the image `id`, including its data.
+pub(crate) fn delete(id: u32) -> String {
+    format!("\x1b_Ga=d,d=I,i={id},q=2\x1b\\")
+}
+
+/// Marks that the terminal shows images, which are deleted on cleanup.
+pub(crate) fn mark_images_shown() {
+    IMAGES_SHOWN.store(true, Ordering::Relaxed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transmit_is_chunked() {
+        // 4 pixels of 4 bytes are 24 characters of base64
+        let small = transmit(7, 2, 2, &[255; 16]);
+        assert_eq!(
+            small,
+            "\x1b_Ga=t,f=32,s=2,v=2,i=7,q=2,m=0;/////////////////////w==\x1b\\"
+        );
+
+        // 4096 characters of base64 per chunk, and the keys only in the first one
+        let rgba = vec![0; 64 * 48 * 4];
+        let large = transmit(8, 64, 48, &rgba);
+        let chunks = large.split("\x1b\\").filter(|chunk| !chunk.is_empty());
+        let headers = chunks
+            .map(|chunk| {
+                let (header, data) = chunk.split_once(';').unwrap();
+                assert!(data.len() <= CHUNK_LEN);
+                header.to_string()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            headers,
+            [
+                "\x1b_Ga=t,f=32,s=64,v=48,i=8,q=2,m=1",
+                "\x1b_Gm=1",
+                "\x1b_Gm=1",
+                "\x1b_Gm=0"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_supported_reply() {
+        // the reply arrives as Alt+'_', the keys and Alt+'\'
+        assert!(is_supported_reply("_Gi=31;OK\\"));
+        assert!(!is_supported_reply("_Gi=31;ENOTSUPPORTED:no such format\\"));
+        assert!(!is_supported_reply(""));
+    }
+}
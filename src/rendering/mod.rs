@@ -5,10 +5,20 @@
 //!
 //! **Sub-modules:**
 //!
+//! *   [`ansi`]: Converts half-block images to and from ANSI art text.
+//! *   [`capture`]: Snapshots of the screen as text or images.
 //! *   [`color`]: Defines the [`Color`] enum for specifying colors.
+//! *   [`palette`]: Color palettes that are safe for color vision deficiencies.
+//! *   [`deferred`]: Queues draws from outside of `render()`, e.g. from event handlers.
+//! *   [`draw`]: Line, rectangle, circle and polygon drawing primitives for color buffers.
 //! *   [`display`]: Defines the [`Display`] struct, a 2D pixel buffer.
+//! *   [`hud`]: Fixed-width numbers and rows for HUD readouts that do not jitter.
+//! *   [`kitty`]: Raster images with the kitty graphics protocol.
 //! *   [`pixel`]: Defines the [`Pixel`] struct, the basic unit of rendering.
 //! *   [`render`]: Provides the [`Render`] trait for objects that can be rendered.
+//! *   [`sixel`]: Images with sixel graphics.
+//! *   [`sprite`]: PNG images and sprite sheets, drawn into half-block buffers (feature `image`).
+//! *   [`spritefont`]: Text drawn with glyphs from an image, e.g. for titles and scores (feature `image`).
 //! *   [`renderer`]: Defines the [`Renderer`] trait and implementations for rendering to the terminal.
 //!
 //! **Key Concepts:**
@@ -33,8 +43,20 @@
 //! [`Renderer`]: crate::rendering::renderer::Renderer
 //! [`DisplayRenderer`]: crate::rendering::renderer::DisplayRenderer
 
+pub mod ansi;
+pub mod capture;
 pub mod color;
+pub mod deferred;
 pub mod display;
+pub mod draw;
+pub mod hud;
+pub mod kitty;
+pub mod palette;
 pub mod pixel;
 pub mod render;
 pub mod renderer;
+pub mod sixel;
+#[cfg(feature = "image")]
+pub mod sprite;
+#[cfg(feature = "image")]
+pub mod spritefont;
@@ -0,0 +1,80 @@
+//! Color palettes that stay distinguishable with color vision deficiencies.
+//!
+//! About one in twelve men has some form of color blindness, most commonly red-green.
+//! The [`OKABE_ITO`] palette is the recommended default for categorical colors, e.g. teams or
+//! item rarities, and the semantic colors [`DANGER`], [`WARN`], [`OK`] and [`INFO`] are the
+//! recommended defaults for status colors.
+//!
+//! To preview a game under a color vision deficiency, see
+//! [`PostProcess::simulate_cvd`](crate::rendering::renderer::PostProcess::simulate_cvd).
+//! Even with a safe palette, consider pairing colors with a second cue, e.g. a symbol.
+
+/// The eight colors of the palette by Okabe and Ito (2008).
+pub const OKABE_ITO: [[u8; 3]; 8] = [
+    OKABE_ITO_BLACK,
+    OKABE_ITO_ORANGE,
+    OKABE_ITO_SKY_BLUE,
+    OKABE_ITO_BLUISH_GREEN,
+    OKABE_ITO_YELLOW,
+    OKABE_ITO_BLUE,
+    OKABE_ITO_VERMILLION,
+    OKABE_ITO_REDDISH_PURPLE,
+];
+
+pub const OKABE_ITO_BLACK: [u8; 3] = [0, 0, 0];
+pub const OKABE_ITO_ORANGE: [u8; 3] = [230, 159, 0];
+pub const OKABE_ITO_SKY_BLUE: [u8; 3] = [86, 180, 233];
+pub const OKABE_ITO_BLUISH_GREEN: [u8; 3] = [0, 158, 115];
+pub const OKABE_ITO_YELLOW: [u8; 3] = [240, 228, 66];
+pub const OKABE_ITO_BLUE: [u8; 3] = [0, 114, 178];
+pub const OKABE_ITO_VERMILLION: [u8; 3] = [213, 94, 0];
+pub const OKABE_ITO_REDDISH_PURPLE: [u8; 3] = [204, 121, 167];
+
+/// The seven colors of Paul Tol's "bright" palette: blue, cyan, green, yellow, red, purple
+/// and grey.
+pub const TOL_BRIGHT: [[u8; 3]; 7] = [
+    [68, 119, 170],
+    [102, 204, 238],
+    [34, 136, 51],
+    [204, 187, 68],
+    [238, 102, 119],
+    [170, 51, 119],
+    [187, 187, 187],
+];
+
+/// For errors, damage and destructive actions.
+pub const DANGER: [u8; 3] = OKABE_ITO_VERMILLION;
+/// For warnings, e.g. low health.
+pub const WARN: [u8; 3] = OKABE_ITO_YELLOW;
+/// For success and healthy states.
+pub const OK: [u8; 3] = OKABE_ITO_BLUISH_GREEN;
+/// For neutral information.
+pub const INFO: [u8; 3] = OKABE_ITO_SKY_BLUE;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rendering::color::{ColorVisionDeficiency, simulate_cvd};
+
+    fn distance(a: [u8; 3], b: [u8; 3]) -> f64 {
+        a.into_iter()
+            .zip(b)
+            .map(|(a, b)| (a as f64 - b as f64).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    }
+
+    #[test]
+    fn test_semantic_colors_stay_distinguishable() {
+        let semantic = [DANGER, WARN, OK, INFO];
+        for kind in ColorVisionDeficiency::ALL {
+            let simulated = semantic.map(|c| simulate_cvd(c, kind));
+            for i in 0..simulated.len() {
+                for j in i + 1..simulated.len() {
+                    let d = distance(simulated[i], simulated[j]);
+                    assert!(d > 60.0, "{kind:?}: {i} and {j} are too similar ({d})");
+                }
+            }
+        }
+    }
+}
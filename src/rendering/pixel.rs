@@ -6,8 +6,36 @@
 //! The `Pixel` struct is the fundamental unit for building up the display in `teng`.
 //! It allows you to control the character displayed at each position on the terminal,
 //! as well as its foreground and background colors.
+//!
+//! # Wide characters
+//!
+//! Characters such as CJK ideographs and most emoji take up two columns in a terminal, see
+//! [`char_width`]. Renderers store a wide character in its cell and a
+//! [`Pixel::WIDE_CONTINUATION`] in the cell to its right, which is never written to the
+//! terminal. Rendering over either half clears the whole character.
+//!
+//! Zero-width characters, e.g. combining marks and the zero-width joiner of emoji sequences,
+//! cannot be stored in a pixel of their own. Text rendering drops them, so an emoji sequence
+//! like "👩‍🔬" shows its parts side by side. A zero-width character rendered as a pixel is shown
+//! as [`char::REPLACEMENT_CHARACTER`].
 
 use crate::rendering::color::Color;
+use unicode_width::UnicodeWidthChar;
+
+/// Returns the number of columns `c` takes up in a terminal: 2 for wide characters, 0 for
+/// zero-width characters and [`Pixel::WIDE_CONTINUATION`], and 1 otherwise, including control
+/// characters.
+pub fn char_width(c: char) -> usize {
+    if c == Pixel::WIDE_CONTINUATION {
+        return 0;
+    }
+    c.width().unwrap_or(1)
+}
+
+/// Returns the number of columns `s` takes up when rendered on a single line, see [`char_width`].
+pub fn str_width(s: &str) -> usize {
+    s.chars().map(char_width).sum()
+}
 
 /// Represents a single pixel (character) for terminal rendering.
 ///
@@ -16,6 +44,7 @@ use crate::rendering::color::Color;
 /// *   `c`: The character to be displayed.
 /// *   `color`: The foreground color of the character (using [`Color`]).
 /// *   `bg_color`: The background color of the character (using [`Color`]).
+/// *   `attributes`: The text attributes of the character, e.g. bold (using [`Attributes`]).
 ///
 /// # Defaults
 ///
@@ -24,6 +53,7 @@ use crate::rendering::color::Color;
 /// *   Character: ' ' (space)
 /// *   Foreground Color: `Color::Default` (renderer's default foreground)
 /// *   Background Color: `Color::Transparent` (no background color, lets below color show through)
+/// *   Attributes: none
 ///
 /// # Example
 ///
@@ -47,9 +77,74 @@ pub struct Pixel {
     pub color: Color,
     /// The background color of the pixel.
     pub bg_color: Color,
+    /// The text attributes of the character, e.g. bold.
+    pub attributes: Attributes,
+}
+
+/// A set of text attributes of a [`Pixel`], combined with `|`.
+///
+/// Attributes are written as SGR sequences, and terminals may not support all of them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Attributes(u8);
+
+impl Attributes {
+    pub const NONE: Self = Self(0);
+    pub const BOLD: Self = Self(1 << 0);
+    pub const DIM: Self = Self(1 << 1);
+    pub const ITALIC: Self = Self(1 << 2);
+    pub const UNDERLINE: Self = Self(1 << 3);
+    /// Swaps the foreground and background colors.
+    pub const REVERSE: Self = Self(1 << 4);
+    pub const STRIKETHROUGH: Self = Self(1 << 5);
+
+    /// Returns true if no attribute is set.
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns true if all attributes of `other` are set.
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns true if any attribute of `other` is set.
+    pub fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    /// Returns the attributes of `self` that are not set in `other`.
+    pub fn difference(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+}
+
+impl std::ops::BitOr for Attributes {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitAnd for Attributes {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+
+impl std::ops::BitOrAssign for Attributes {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
 }
 
 impl Pixel {
+    /// The character of the cell to the right of a wide character, which the wide character
+    /// covers.
+    pub const WIDE_CONTINUATION: char = '\0';
+
     /// Creates a new `Pixel` with the given character and default colors.
     ///
     /// The foreground color will be `Color::Default`, and the background color
@@ -59,6 +154,7 @@ impl Pixel {
             c,
             color: Color::Default,
             bg_color: Color::Transparent,
+            attributes: Attributes::NONE,
         }
     }
 
@@ -70,6 +166,7 @@ impl Pixel {
             c: ' ',
             color: Color::Transparent,
             bg_color: Color::Transparent,
+            attributes: Attributes::NONE,
         }
     }
 
@@ -86,8 +183,7 @@ impl Pixel {
     pub fn with_color(self, color: [u8; 3]) -> Self {
         Self {
             color: Color::Rgb(color),
-            c: self.c,
-            bg_color: self.bg_color,
+            ..self
         }
     }
 
@@ -104,11 +200,70 @@ impl Pixel {
     pub fn with_bg_color(self, bg_color: [u8; 3]) -> Self {
         Self {
             bg_color: Color::Rgb(bg_color),
-            c: self.c,
-            color: self.color,
+            ..self
         }
     }
 
+    /// Creates a new `Pixel` like `self`, with `attributes` added.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use teng::rendering::pixel::{Attributes, Pixel};
+    ///
+    /// let pixel = Pixel::new('!').with_attributes(Attributes::BOLD | Attributes::UNDERLINE);
+    /// assert_eq!(pixel, Pixel::new('!').with_bold().with_underline());
+    /// ```
+    pub fn with_attributes(self, attributes: Attributes) -> Self {
+        Self {
+            attributes: self.attributes | attributes,
+            ..self
+        }
+    }
+
+    /// Creates a new bold `Pixel` like `self`.
+    pub fn with_bold(self) -> Self {
+        self.with_attributes(Attributes::BOLD)
+    }
+
+    /// Creates a new dim `Pixel` like `self`.
+    pub fn with_dim(self) -> Self {
+        self.with_attributes(Attributes::DIM)
+    }
+
+    /// Creates a new italic `Pixel` like `self`.
+    pub fn with_italic(self) -> Self {
+        self.with_attributes(Attributes::ITALIC)
+    }
+
+    /// Creates a new underlined `Pixel` like `self`.
+    pub fn with_underline(self) -> Self {
+        self.with_attributes(Attributes::UNDERLINE)
+    }
+
+    /// Creates a new `Pixel` like `self`, with swapped foreground and background colors.
+    pub fn with_reverse(self) -> Self {
+        self.with_attributes(Attributes::REVERSE)
+    }
+
+    /// Creates a new struck through `Pixel` like `self`.
+    pub fn with_strikethrough(self) -> Self {
+        self.with_attributes(Attributes::STRIKETHROUGH)
+    }
+
+    /// Returns the number of columns the pixel's character takes up, see [`char_width`].
+    pub fn width(&self) -> usize {
+        char_width(self.c)
+    }
+
+    /// Returns whether the pixel does not change what it is drawn over, i.e., it is a space with a
+    /// transparent background and no attributes, or has only transparent colors.
+    pub fn is_invisible(&self) -> bool {
+        let no_fg =
+            self.color == Color::Transparent || (self.c == ' ' && self.attributes.is_empty());
+        no_fg && self.bg_color == Color::Transparent
+    }
+
     /// Overlays `self` over `other`, taking into account transparencies, and returns the result.
     ///
     /// # Example
@@ -131,6 +286,7 @@ impl Pixel {
         if new_pixel.color == Color::Transparent {
             new_pixel.color = other.color;
             new_pixel.c = other.c;
+            new_pixel.attributes = other.attributes;
         }
         if new_pixel.bg_color == Color::Transparent {
             new_pixel.bg_color = other.bg_color;
@@ -145,6 +301,7 @@ impl Default for Pixel {
             c: ' ',
             color: Color::Default,
             bg_color: Color::Default,
+            attributes: Attributes::NONE,
         }
     }
 }
@@ -16,7 +16,7 @@
 //!     order, allowing you to layer objects on top of each other. Higher depth values are
 //!     rendered on top.
 //! *   **Trait Extensions for Styling:**  The `Render` trait provides extension methods like
-//!     `with_color()`, `transparent()`, and `with_bg_color()` to easily create styled
+//!     `with_color()`, `with_bg_color()` and `with_bold()` to easily create styled
 //!     renderable objects without modifying the original object.
 //!
 //! **Implementations of `Render`:**
@@ -25,19 +25,30 @@
 //!
 //! *   `&str` and `String`:  Renders text strings.
 //! *   `char`: Renders a single character.
+//! *   [`Paragraph`]: Renders text wrapped and aligned to a width.
+//! *   [`BlinkingText`], [`MarqueeText`] and [`TypewriterText`]: Animated text, advanced with an
+//!     explicit `update(dt)`.
 //! *   [`Pixel`]: Renders a single pixel.
+//! *   [`KittyImageRender`]: Renders a raster image, with half blocks on terminals without the
+//!     kitty graphics protocol.
+//! *   [`SixelRender`]: Renders a raster image with sixel graphics, or with half blocks.
 //! *   [`Sprite`]: Renders a sprite (predefined grid of pixels).
 //! *   `&T` where `T: Render`: Allows rendering of references to renderable objects.
 //!
 //! **Styling and Adapters:**
 //!
-//! The `with_color()`, `transparent()`, and `with_bg_color()` methods don't directly
-//! modify the original object. Instead, they return *adapter* structs (`WithColor`,
-//! `WithTransparency`, `WithBgColor`) that wrap the original object and apply the
+//! The `with_color()`, `transparent()`, `with_bg_color()` and `with_attributes()` methods don't
+//! directly modify the original object. Instead, they return *adapter* structs (`WithColor`,
+//! `WithTransparency`, `WithBgColor`, `WithAttributes`) that wrap the original object and apply the
 //! styling during rendering.  This allows for flexible and composable styling without
 //! changing the underlying data.
 
-use crate::rendering::{color::Color, display::Display, pixel::Pixel, renderer::Renderer};
+use crate::rendering::{kitty, sixel};
+use crate::rendering::pixel::{Attributes, char_width, str_width};
+use crate::rendering::renderer::{ClipRect, Renderer};
+use crate::rendering::{color::Color, display::Display, pixel::Pixel};
+use crate::util::lerp_color;
+use crate::util::planarvec::Bounds;
 use std::fmt::Debug;
 
 /// Trait for objects that can be rendered to a [`Renderer`].
@@ -109,21 +120,63 @@ pub trait Render {
     {
         WithBgColor(bg_color, self)
     }
+
+    /// Creates a new `Render` object with the specified text attributes added, e.g.
+    /// `"hello".with_attributes(Attributes::BOLD | Attributes::ITALIC)`.
+    ///
+    /// This returns a `WithAttributes` adapter. For an example, see [`Render::with_color`].
+    fn with_attributes(&self, attributes: Attributes) -> impl Render
+    where
+        Self: Sized,
+    {
+        WithAttributes(attributes, self)
+    }
+
+    /// Creates a new bold `Render` object, see [`Render::with_attributes`].
+    fn with_bold(&self) -> impl Render
+    where
+        Self: Sized,
+    {
+        WithAttributes(Attributes::BOLD, self)
+    }
+
+    /// Creates a new italic `Render` object, see [`Render::with_attributes`].
+    fn with_italic(&self) -> impl Render
+    where
+        Self: Sized,
+    {
+        WithAttributes(Attributes::ITALIC, self)
+    }
+
+    /// Creates a new underlined `Render` object, see [`Render::with_attributes`].
+    fn with_underline(&self) -> impl Render
+    where
+        Self: Sized,
+    {
+        WithAttributes(Attributes::UNDERLINE, self)
+    }
 }
 
+/// Renders the text line by line, advancing by the width of every character. Wide characters
+/// take up two columns, and zero-width characters are dropped, see
+/// [the `pixel` module](crate::rendering::pixel#wide-characters).
 impl Render for &str {
     fn render(&self, renderer: &mut dyn Renderer, x: usize, y: usize, depth: i32) {
         let mut y = y;
         let mut draw_x = x;
-        for (i, c) in self.chars().enumerate() {
+        for c in self.chars() {
             if c == '\n' {
                 y += 1;
                 draw_x = x;
                 continue;
             }
+            let width = char_width(c);
+            if width == 0 {
+                continue;
+            }
             let pixel = Pixel::new(c);
             renderer.render_pixel(draw_x, y, pixel, depth);
-            draw_x += 1;
+            draw_x += width;
         }
     }
 }
@@ -147,6 +200,186 @@ impl Render for Pixel {
     }
 }
 
+/// How a [`Paragraph`] breaks lines that are wider than its width.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Wrap {
+    /// Breaks lines between words. Words wider than the paragraph are broken as in
+    /// [`Wrap::Hard`].
+    #[default]
+    Word,
+    /// Breaks lines at the last character that fits.
+    Hard,
+}
+
+/// How a [`Paragraph`] aligns its lines within its width.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Alignment {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// Text that is wrapped to a width and aligned, rendered with its top left corner at `(x, y)`.
+///
+/// Explicit newlines start new lines. Whitespace at the end of a line is dropped, and so is the
+/// whitespace at a line break. If the text has more lines than the optional height, the last
+/// shown line ends with an ellipsis. Widths are measured in columns, see
+/// [`str_width`].
+///
+/// Colors can be applied to the whole paragraph with [`Render::with_color`] and
+/// [`Render::with_bg_color`].
+///
+/// # Example
+///
+/// ```rust
+/// use teng::rendering::render::{Alignment, Paragraph};
+///
+/// let paragraph = Paragraph::new("the quick brown fox jumps", 10)
+///     .with_height(2)
+///     .with_alignment(Alignment::Right);
+/// assert_eq!(paragraph.lines(), vec!["the quick", "brown fox…"]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Paragraph {
+    text: String,
+    width: usize,
+    height: Option<usize>,
+    wrap: Wrap,
+    alignment: Alignment,
+}
+
+impl Paragraph {
+    /// Creates a left-aligned, word-wrapped paragraph of unlimited height.
+    pub fn new(text: impl Into<String>, width: usize) -> Self {
+        Self {
+            text: text.into(),
+            width,
+            height: None,
+            wrap: Wrap::default(),
+            alignment: Alignment::default(),
+        }
+    }
+
+    /// Limits the paragraph to `height` lines.
+    pub fn with_height(mut self, height: usize) -> Self {
+        self.height = Some(height);
+        self
+    }
+
+    pub fn with_wrap(mut self, wrap: Wrap) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    pub fn with_alignment(mut self, alignment: Alignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+
+    /// Returns the lines that are rendered, without the alignment.
+    pub fn lines(&self) -> Vec<String> {
+        // a single column still makes progress
+        let width = self.width.max(1);
+        let mut lines = vec![];
+        for line in self.text.lines() {
+            match self.wrap {
+                Wrap::Word => wrap_words(line.trim_end(), width, &mut lines),
+                Wrap::Hard => {
+                    let mut rest = hard_break(line.trim_end(), width, &mut lines);
+                    lines.push(std::mem::take(&mut rest));
+                }
+            }
+        }
+        if let Some(height) = self.height
+            && lines.len() > height
+        {
+            lines.truncate(height);
+            if let Some(last) = lines.last_mut() {
+                let mut kept = String::new();
+                for c in last.chars() {
+                    if str_width(&kept) + char_width(c) >= width {
+                        break;
+                    }
+                    kept.push(c);
+                }
+                *last = kept.trim_end().to_string() + "…";
+            }
+        }
+        lines
+    }
+
+    /// Returns the number of rendered lines.
+    pub fn line_count(&self) -> usize {
+        self.lines().len()
+    }
+}
+
+/// Breaks `text` into lines of at most `width` columns, pushes the full lines and returns the
+/// last, partial one. A character wider than `width` gets a line of its own.
+fn hard_break(text: &str, width: usize, lines: &mut Vec<String>) -> String {
+    let mut line = String::new();
+    let mut line_width = 0;
+    for c in text.chars() {
+        let c_width = char_width(c);
+        if line_width > 0 && line_width + c_width > width {
+            lines.push(std::mem::take(&mut line));
+            line_width = 0;
+        }
+        line.push(c);
+        line_width += c_width;
+    }
+    line
+}
+
+/// Breaks `text` between words into lines of at most `width` columns. Leading whitespace is
+/// kept, the whitespace at line breaks is dropped.
+fn wrap_words(text: &str, width: usize, lines: &mut Vec<String>) {
+    let mut line = String::new();
+    let mut line_width = 0;
+    // whether `line` continues a line that was broken
+    let mut broken = false;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let word_start = rest.len() - rest.trim_start().len();
+        let gap = &rest[..word_start];
+        rest = &rest[word_start..];
+        let word_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let word = &rest[..word_end];
+        rest = &rest[word_end..];
+
+        let gap = if broken && line.is_empty() { "" } else { gap };
+        let (gap_width, word_width) = (str_width(gap), str_width(word));
+        if line_width + gap_width + word_width <= width {
+            line.push_str(gap);
+            line.push_str(word);
+            line_width += gap_width + word_width;
+            continue;
+        }
+        if line_width > 0 {
+            lines.push(std::mem::take(&mut line));
+        }
+        broken = true;
+        line = hard_break(word, width, lines);
+        line_width = str_width(&line);
+    }
+    lines.push(line);
+}
+
+impl Render for Paragraph {
+    fn render(&self, renderer: &mut dyn Renderer, x: usize, y: usize, depth: i32) {
+        for (i, line) in self.lines().iter().enumerate() {
+            let free = self.width.saturating_sub(str_width(line));
+            let offset = match self.alignment {
+                Alignment::Left => 0,
+                Alignment::Center => free / 2,
+                Alignment::Right => free,
+            };
+            line.as_str().render(renderer, x + offset, y + i, depth);
+        }
+    }
+}
+
 // TODO: refactor Sprite into separate module, remove generics? smallvec could help if we flatten the array
 /// Represents a sprite (a fixed-size grid of pixels).
 ///
@@ -268,6 +501,18 @@ impl<T: Render> Render for WithBgColor<T> {
     }
 }
 
+struct WithAttributes<T>(pub Attributes, pub T);
+
+impl<T: Render> Render for WithAttributes<T> {
+    fn render(&self, renderer: &mut dyn Renderer, x: usize, y: usize, depth: i32) {
+        let mut adapter = AttributesRendererAdapter {
+            renderer,
+            attributes: self.0,
+        };
+        self.1.render(&mut adapter, x, y, depth);
+    }
+}
+
 struct WithTransparency<T>(pub T);
 
 impl<T: Render> Render for WithTransparency<T> {
@@ -291,6 +536,22 @@ impl<'a> Renderer for ColorRendererAdapter<'a> {
     fn flush(&mut self) -> std::io::Result<()> {
         self.renderer.flush()
     }
+
+    fn push_clip(&mut self, x: usize, y: usize, width: usize, height: usize) {
+        self.renderer.push_clip(x, y, width, height);
+    }
+
+    fn pop_clip(&mut self) {
+        self.renderer.pop_clip();
+    }
+
+    fn clip(&self) -> Option<ClipRect> {
+        self.renderer.clip()
+    }
+
+    fn depth_at(&self, x: usize, y: usize) -> i32 {
+        self.renderer.depth_at(x, y)
+    }
 }
 
 struct BgColorRendererAdapter<'a> {
@@ -307,6 +568,54 @@ impl<'a> Renderer for BgColorRendererAdapter<'a> {
     fn flush(&mut self) -> std::io::Result<()> {
         self.renderer.flush()
     }
+
+    fn push_clip(&mut self, x: usize, y: usize, width: usize, height: usize) {
+        self.renderer.push_clip(x, y, width, height);
+    }
+
+    fn pop_clip(&mut self) {
+        self.renderer.pop_clip();
+    }
+
+    fn clip(&self) -> Option<ClipRect> {
+        self.renderer.clip()
+    }
+
+    fn depth_at(&self, x: usize, y: usize) -> i32 {
+        self.renderer.depth_at(x, y)
+    }
+}
+
+struct AttributesRendererAdapter<'a> {
+    renderer: &'a mut dyn Renderer,
+    attributes: Attributes,
+}
+
+impl<'a> Renderer for AttributesRendererAdapter<'a> {
+    fn render_pixel(&mut self, x: usize, y: usize, pixel: Pixel, depth: i32) {
+        self.renderer
+            .render_pixel(x, y, pixel.with_attributes(self.attributes), depth);
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.renderer.flush()
+    }
+
+    fn push_clip(&mut self, x: usize, y: usize, width: usize, height: usize) {
+        self.renderer.push_clip(x, y, width, height);
+    }
+
+    fn pop_clip(&mut self) {
+        self.renderer.pop_clip();
+    }
+
+    fn clip(&self) -> Option<ClipRect> {
+        self.renderer.clip()
+    }
+
+    fn depth_at(&self, x: usize, y: usize) -> i32 {
+        self.renderer.depth_at(x, y)
+    }
 }
 
 struct TransparentRendererAdapter<'a> {
@@ -322,6 +631,22 @@ impl<'a> Renderer for TransparentRendererAdapter<'a> {
     fn flush(&mut self) -> std::io::Result<()> {
         self.renderer.flush()
     }
+
+    fn push_clip(&mut self, x: usize, y: usize, width: usize, height: usize) {
+        self.renderer.push_clip(x, y, width, height);
+    }
+
+    fn pop_clip(&mut self) {
+        self.renderer.pop_clip();
+    }
+
+    fn clip(&self) -> Option<ClipRect> {
+        self.renderer.clip()
+    }
+
+    fn depth_at(&self, x: usize, y: usize) -> i32 {
+        self.renderer.depth_at(x, y)
+    }
 }
 
 /// A struct representing a display with "double the resolution" of the terminal.
@@ -376,6 +701,120 @@ impl HalfBlockDisplayRender {
         dirty_rect.3 = dirty_rect.3.max(y);
     }
 
+    /// Blends `color` over the pixel with the given opacity, from 0.0 (unchanged) to 1.0
+    /// (like [`set_color`](Self::set_color)). Uses the half-block coordinate space.
+    ///
+    /// A transparent `color` leaves the pixel unchanged, and a transparent pixel is treated as
+    /// fully transparent, i.e., it is set to `color`. [`Color::Default`] has no known RGB value,
+    /// so blending with it on either side leaves the pixel unchanged.
+    pub fn set_color_blended(&mut self, x: usize, y: usize, color: Color, alpha: f32) {
+        let Some(dest) = self.get_color(x, y) else {
+            return;
+        };
+        if alpha <= 0.0 || color == Color::Transparent {
+            return;
+        }
+        if alpha >= 1.0 || dest == Color::Transparent {
+            self.set_color(x, y, color);
+            return;
+        }
+        if color == Color::Default || dest == Color::Default {
+            return;
+        }
+        let blended = lerp_color(dest.unwrap_or([0; 3]), color.unwrap_or([0; 3]), alpha);
+        self.set_color(x, y, Color::Rgb(blended));
+    }
+
+    /// Blends every drawn pixel of `other` over this display with the given opacity, see
+    /// [`set_color_blended`](Self::set_color_blended). The top left pixel of `other` lands at
+    /// `(x_offset, y_offset)`, pixels outside this display are skipped.
+    ///
+    /// E.g. a damage flash blends a red silhouette of a sprite over it at `alpha` 0.5.
+    pub fn blend_from(
+        &mut self,
+        other: &HalfBlockDisplayRender,
+        x_offset: i64,
+        y_offset: i64,
+        alpha: f32,
+    ) {
+        let Some((min_x, min_y, max_x, max_y)) = other.dirty_rect else {
+            return;
+        };
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                let Some(color) = other.get_color(x, y) else {
+                    continue;
+                };
+                let target_x = x as i64 + x_offset;
+                let target_y = y as i64 + y_offset;
+                if target_x < 0 || target_y < 0 {
+                    continue;
+                }
+                self.set_color_blended(target_x as usize, target_y as usize, color, alpha);
+            }
+        }
+    }
+
+    /// Draws every drawn pixel of `other` onto this display as a `scale` x `scale` square, so
+    /// pixel art stays crisp. The top left pixel of `other` lands at `(x_offset, y_offset)`,
+    /// transparent pixels and pixels outside this display are skipped.
+    pub fn blit_scaled(
+        &mut self,
+        other: &HalfBlockDisplayRender,
+        x_offset: i64,
+        y_offset: i64,
+        scale: usize,
+    ) {
+        let Some((min_x, min_y, max_x, max_y)) = other.dirty_rect else {
+            return;
+        };
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                let Some(color) = other.get_color(x, y) else {
+                    continue;
+                };
+                if color == Color::Transparent {
+                    continue;
+                }
+                let target_x = (x * scale) as i64 + x_offset;
+                let target_y = (y * scale) as i64 + y_offset;
+                for dy in 0..scale as i64 {
+                    for dx in 0..scale as i64 {
+                        let (px, py) = (target_x + dx, target_y + dy);
+                        if px >= 0 && py >= 0 {
+                            self.set_color(px as usize, py as usize, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the rows of the display with their y coordinate, top first, see
+    /// [`Display::rows_mut`]. Uses the half-block coordinate space.
+    ///
+    /// The whole display counts as drawn afterwards.
+    pub fn rows_mut(&mut self) -> impl Iterator<Item = (usize, &mut [Color])> {
+        self.mark_all_drawn();
+        self.display.rows_mut()
+    }
+
+    /// Sets every pixel to `f(x, y)`, filling the rows in parallel, see
+    /// [`Display::par_fill_with`]. Uses the half-block coordinate space.
+    ///
+    /// Much faster than [`set_color`](Self::set_color) for every pixel of a large display, e.g.
+    /// to draw a simulation where every pixel shows a tile of the world.
+    #[cfg(feature = "parallel")]
+    pub fn par_fill_with(&mut self, f: impl Fn(usize, usize) -> Color + Sync) {
+        self.mark_all_drawn();
+        self.display.par_fill_with(f);
+    }
+
+    fn mark_all_drawn(&mut self) {
+        self.dirty_rect = (self.width > 0 && self.height > 0)
+            .then(|| (0, 0, self.width - 1, self.height - 1));
+    }
+
     /// Returns the color of a specific pixel in the display. Uses the half-block coordinate space.
     /// Returns `None` if the coordinates are out of bounds.
     pub fn get_color(&self, x: usize, y: usize) -> Option<Color> {
@@ -394,6 +833,109 @@ impl HalfBlockDisplayRender {
         self.display.clear();
         self.dirty_rect = None;
     }
+
+    /// Outlines everything drawn within `bounds` (in half-block coordinates, inclusive).
+    ///
+    /// Every transparent pixel within `bounds` that is adjacent to a non-transparent pixel
+    /// within `bounds` is set to `outline_color`. Pixels outside `bounds` are ignored, so content
+    /// touching the region's border is not outlined against it.
+    /// The cost is proportional to the size of the region.
+    pub fn draw_outline_of_region(
+        &mut self,
+        bounds: Bounds,
+        outline_color: Color,
+        connectivity: Connectivity,
+    ) {
+        self.draw_outline_of_region_matching(bounds, outline_color, connectivity, |color| {
+            color != Color::Transparent
+        });
+    }
+
+    /// Like [`draw_outline_of_region`](Self::draw_outline_of_region), but only outlines pixels
+    /// for which `is_content` returns true, e.g. to outline a single entity by its color.
+    ///
+    /// Pixels that are not content may be overwritten by the outline, even if they are not
+    /// transparent.
+    pub fn draw_outline_of_region_matching(
+        &mut self,
+        bounds: Bounds,
+        outline_color: Color,
+        connectivity: Connectivity,
+        mut is_content: impl FnMut(Color) -> bool,
+    ) {
+        // clip the region to the display
+        if bounds.is_empty() || bounds.max_x < 0 || bounds.max_y < 0 {
+            return;
+        }
+        let min_x = bounds.min_x.max(0) as usize;
+        let min_y = bounds.min_y.max(0) as usize;
+        let max_x = (bounds.max_x as usize).min(self.width.saturating_sub(1));
+        let max_y = (bounds.max_y as usize).min(self.height.saturating_sub(1));
+        if self.width == 0 || self.height == 0 || min_x > max_x || min_y > max_y {
+            return;
+        }
+
+        // snapshot the content first, so that outline pixels do not count as content
+        let region_width = max_x - min_x + 1;
+        let region_height = max_y - min_y + 1;
+        let mut content = Display::new(region_width, region_height, false);
+        for y in 0..region_height {
+            for x in 0..region_width {
+                content[(x, y)] = is_content(self.display[(min_x + x, min_y + y)]);
+            }
+        }
+
+        for y in 0..region_height {
+            for x in 0..region_width {
+                if content[(x, y)] {
+                    continue;
+                }
+                let touches_content = connectivity.offsets().iter().any(|&(dx, dy)| {
+                    let nx = x as isize + dx;
+                    let ny = y as isize + dy;
+                    nx >= 0 && ny >= 0 && content.get(nx as usize, ny as usize) == Some(&true)
+                });
+                if touches_content {
+                    self.set_color(min_x + x, min_y + y, outline_color);
+                }
+            }
+        }
+    }
+}
+
+/// Which neighbors of a pixel count as adjacent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connectivity {
+    /// Only the horizontal and vertical neighbors.
+    Four,
+    /// The horizontal, vertical and diagonal neighbors.
+    Eight,
+}
+
+impl Connectivity {
+    fn offsets(self) -> &'static [(isize, isize)] {
+        match self {
+            Connectivity::Four => &[(0, -1), (-1, 0), (1, 0), (0, 1)],
+            Connectivity::Eight => &[
+                (-1, -1),
+                (0, -1),
+                (1, -1),
+                (-1, 0),
+                (1, 0),
+                (-1, 1),
+                (0, 1),
+                (1, 1),
+            ],
+        }
+    }
+}
+
+/// Returns an outline color that pulses between `from` and `to` with the given period,
+/// starting at `from` at `time == 0`.
+pub fn pulsing_outline_color(from: [u8; 3], to: [u8; 3], time: f64, period: f64) -> Color {
+    let phase = (time / period) * std::f64::consts::TAU;
+    let t = (1.0 - phase.cos()) / 2.0;
+    Color::Rgb(lerp_color(from, to, t as f32))
 }
 
 impl Render for HalfBlockDisplayRender {
@@ -403,8 +945,24 @@ impl Render for HalfBlockDisplayRender {
         };
 
         // adjust y's to terminal space
-        let min_y = min_y / 2;
-        let max_y = max_y / 2;
+        let mut min_y = min_y / 2;
+        let mut max_y = max_y / 2;
+        let (mut min_x, mut max_x) = (min_x, max_x);
+
+        // skip the cells outside of the active clip
+        if let Some(clip) = renderer.clip() {
+            let (clip_end_x, clip_end_y) = (clip.x + clip.width, clip.y + clip.height);
+            if clip_end_x <= base_x || clip_end_y <= base_y {
+                return;
+            }
+            min_x = min_x.max(clip.x.saturating_sub(base_x));
+            max_x = max_x.min(clip_end_x - base_x - 1);
+            min_y = min_y.max(clip.y.saturating_sub(base_y));
+            max_y = max_y.min(clip_end_y - base_y - 1);
+            if min_x > max_x || min_y > max_y {
+                return;
+            }
+        }
 
         // for y_offset in 0..(self.height / 2) {
         // for x_offset in 0..self.width {
@@ -454,3 +1012,1315 @@ impl Render for HalfBlockDisplayRender {
         }
     }
 }
+
+/// An RGBA image that is shown with the kitty graphics protocol if the renderer supports it, and
+/// with half blocks otherwise.
+///
+/// By default, every cell covers one by two pixels, like [`HalfBlockDisplayRender`], but
+/// [`with_cells`](Self::with_cells) shows the image in any number of cells, e.g. a high resolution
+/// image in few cells. The half blocks then sample the image. See the
+/// [`kitty`] module for how images are sent to the terminal.
+///
+/// Every image has an id that the terminal stores it under, and is only sent again when its
+/// pixels changed. Keep the image around instead of creating it every frame. Clones get a new id.
+#[derive(Debug)]
+pub struct KittyImageRender {
+    id: u32,
+    /// Changes whenever the pixels change.
+    generation: u64,
+    width: usize,
+    height: usize,
+    /// The columns and rows the image is shown in, if set.
+    cells: Option<(usize, usize)>,
+    rgba: Vec<u8>,
+}
+
+impl KittyImageRender {
+    /// Creates a transparent image of `width` by `height` pixels.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            id: kitty::next_image_id(),
+            generation: 0,
+            width,
+            height,
+            cells: None,
+            rgba: vec![0; width * height * 4],
+        }
+    }
+
+    /// Shows the image in `columns` by `rows` cells, scaling it.
+    pub fn with_cells(mut self, columns: usize, rows: usize) -> Self {
+        self.cells = Some((columns, rows));
+        self
+    }
+
+    /// Returns the id the terminal stores the image under.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns a number that changes whenever the pixels change.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Returns the width of the image in pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of the image in pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the number of columns the image is shown in.
+    pub fn columns(&self) -> usize {
+        self.cells.map_or(self.width, |(columns, _)| columns)
+    }
+
+    /// Returns the number of rows the image is shown in.
+    pub fn rows(&self) -> usize {
+        self.cells.map_or(self.height.div_ceil(2), |(_, rows)| rows)
+    }
+
+    /// Returns the pixels, four bytes per pixel, row by row.
+    pub fn rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    /// Returns the RGBA color of a pixel, or `None` if the coordinates are out of bounds.
+    pub fn get_pixel(&self, x: usize, y: usize) -> Option<[u8; 4]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let idx = (y * self.width + x) * 4;
+        Some(self.rgba[idx..idx + 4].try_into().unwrap())
+    }
+
+    /// Sets the RGBA color of a pixel. Pixels out of bounds are ignored.
+    pub fn set_pixel(&mut self, x: usize, y: usize, rgba: [u8; 4]) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        let idx = (y * self.width + x) * 4;
+        if self.rgba[idx..idx + 4] != rgba {
+            self.rgba[idx..idx + 4].copy_from_slice(&rgba);
+            self.generation += 1;
+        }
+    }
+
+    /// Sets a pixel to an opaque color, or clears it for transparent and default colors.
+    pub fn set_color(&mut self, x: usize, y: usize, color: Color) {
+        let rgba = match color {
+            Color::Default | Color::Transparent => [0; 4],
+            color => {
+                let [r, g, b] = color.unwrap_or([0; 3]);
+                [r, g, b, 255]
+            }
+        };
+        self.set_pixel(x, y, rgba);
+    }
+
+    /// Copies the pixels of `hbd`, resizing the image to its size, see
+    /// [`set_color`](Self::set_color).
+    pub fn copy_from_hbd(&mut self, hbd: &HalfBlockDisplayRender) {
+        if (self.width, self.height) != (hbd.width(), hbd.height()) {
+            self.resize_discard(hbd.width(), hbd.height());
+        }
+        for y in 0..self.height {
+            for x in 0..self.width {
+                self.set_color(x, y, hbd.get_color(x, y).unwrap());
+            }
+        }
+    }
+
+    /// Resizes the image to the specified width and height, clearing it.
+    pub fn resize_discard(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        self.rgba.clear();
+        self.rgba.resize(width * height * 4, 0);
+        self.generation += 1;
+    }
+
+    /// Makes every pixel transparent.
+    pub fn clear(&mut self) {
+        if self.rgba.iter().any(|&byte| byte != 0) {
+            self.rgba.fill(0);
+            self.generation += 1;
+        }
+    }
+
+    /// Samples the image into half blocks of its columns and rows. Pixels that are less than half
+    /// opaque are transparent.
+    fn to_half_blocks(&self) -> HalfBlockDisplayRender {
+        sample_half_blocks(self.columns(), self.rows(), self.width, self.height, |x, y| {
+            let [r, g, b, a] = self.get_pixel(x, y).unwrap();
+            (a >= 128).then_some(Color::Rgb([r, g, b]))
+        })
+    }
+}
+
+/// Samples a `width` by `height` image into half blocks of `columns` by `rows` cells.
+fn sample_half_blocks(
+    columns: usize,
+    rows: usize,
+    width: usize,
+    height: usize,
+    pixel: impl Fn(usize, usize) -> Option<Color>,
+) -> HalfBlockDisplayRender {
+    let (hbd_width, hbd_height) = (columns, rows * 2);
+    let mut hbd = HalfBlockDisplayRender::new(hbd_width, hbd_height);
+    for y in 0..hbd_height {
+        for x in 0..hbd_width {
+            if let Some(color) = pixel(x * width / hbd_width, y * height / hbd_height) {
+                hbd.set_color(x, y, color);
+            }
+        }
+    }
+    hbd
+}
+
+impl Clone for KittyImageRender {
+    fn clone(&self) -> Self {
+        Self {
+            id: kitty::next_image_id(),
+            generation: self.generation,
+            width: self.width,
+            height: self.height,
+            cells: self.cells,
+            rgba: self.rgba.clone(),
+        }
+    }
+}
+
+impl Render for KittyImageRender {
+    fn render(&self, renderer: &mut dyn Renderer, x: usize, y: usize, depth: i32) {
+        if self.width == 0 || self.height == 0 || renderer.render_image(x, y, self, depth) {
+            return;
+        }
+        self.to_half_blocks().render(renderer, x, y, depth);
+    }
+}
+
+/// An RGB image that is drawn with sixel graphics if the renderer supports it, and with half
+/// blocks otherwise.
+///
+/// Like [`KittyImageRender`], every cell covers one by two pixels by default, and
+/// [`with_cells`](Self::with_cells) shows the image in any number of cells. The image is scaled to
+/// the pixels of its cells. See the [`sixel`] module for when sixels are
+/// used and how text around the image is kept intact.
+///
+/// The encoded image is cached by the renderer under the image's id until the pixels change. Keep
+/// the image around instead of creating it every frame. Clones get a new id.
+#[derive(Debug)]
+pub struct SixelRender {
+    id: u32,
+    /// Changes whenever the pixels change.
+    generation: u64,
+    width: usize,
+    height: usize,
+    /// The columns and rows the image is shown in, if set.
+    cells: Option<(usize, usize)>,
+    rgb: Vec<u8>,
+}
+
+impl SixelRender {
+    /// Creates a black image of `width` by `height` pixels.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            id: kitty::next_image_id(),
+            generation: 0,
+            width,
+            height,
+            cells: None,
+            rgb: vec![0; width * height * 3],
+        }
+    }
+
+    /// Shows the image in `columns` by `rows` cells, scaling it.
+    pub fn with_cells(mut self, columns: usize, rows: usize) -> Self {
+        self.cells = Some((columns, rows));
+        self
+    }
+
+    /// Returns the id the renderer caches the image under.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns a number that changes whenever the pixels change.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Returns the width of the image in pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of the image in pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the number of columns the image is shown in.
+    pub fn columns(&self) -> usize {
+        self.cells.map_or(self.width, |(columns, _)| columns)
+    }
+
+    /// Returns the number of rows the image is shown in.
+    pub fn rows(&self) -> usize {
+        self.cells.map_or(self.height.div_ceil(2), |(_, rows)| rows)
+    }
+
+    /// Returns the pixels, three bytes per pixel, row by row.
+    pub fn rgb(&self) -> &[u8] {
+        &self.rgb
+    }
+
+    /// Returns the color of a pixel, or `None` if the coordinates are out of bounds.
+    pub fn get_pixel(&self, x: usize, y: usize) -> Option<[u8; 3]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let idx = (y * self.width + x) * 3;
+        Some(self.rgb[idx..idx + 3].try_into().unwrap())
+    }
+
+    /// Sets the color of a pixel. Pixels out of bounds are ignored.
+    pub fn set_pixel(&mut self, x: usize, y: usize, rgb: [u8; 3]) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        let idx = (y * self.width + x) * 3;
+        if self.rgb[idx..idx + 3] != rgb {
+            self.rgb[idx..idx + 3].copy_from_slice(&rgb);
+            self.generation += 1;
+        }
+    }
+
+    /// Copies the pixels of `hbd`, resizing the image to its size. Transparent and default colors
+    /// become `background`.
+    pub fn copy_from_hbd(&mut self, hbd: &HalfBlockDisplayRender, background: [u8; 3]) {
+        if (self.width, self.height) != (hbd.width(), hbd.height()) {
+            self.resize_discard(hbd.width(), hbd.height());
+        }
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let color = hbd.get_color(x, y).unwrap();
+                self.set_pixel(x, y, color.unwrap_or(background));
+            }
+        }
+    }
+
+    /// Resizes the image to the specified width and height, making it black.
+    pub fn resize_discard(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        self.rgb.clear();
+        self.rgb.resize(width * height * 3, 0);
+        self.generation += 1;
+    }
+
+    /// Returns the sixel sequence that draws the image scaled to `width` by `height` pixels, or an
+    /// empty string if either the image or the target is empty.
+    ///
+    /// The renderer calls this with the pixel size of the image's cells.
+    pub fn to_sixel(&self, width: usize, height: usize) -> String {
+        if self.width == 0 || self.height == 0 || width == 0 || height == 0 {
+            return String::new();
+        }
+        sixel::encode(self.width, self.height, &self.rgb, width, height)
+    }
+
+    fn to_half_blocks(&self) -> HalfBlockDisplayRender {
+        sample_half_blocks(self.columns(), self.rows(), self.width, self.height, |x, y| {
+            self.get_pixel(x, y).map(Color::Rgb)
+        })
+    }
+}
+
+impl Clone for SixelRender {
+    fn clone(&self) -> Self {
+        Self {
+            id: kitty::next_image_id(),
+            generation: self.generation,
+            width: self.width,
+            height: self.height,
+            cells: self.cells,
+            rgb: self.rgb.clone(),
+        }
+    }
+}
+
+impl Render for SixelRender {
+    fn render(&self, renderer: &mut dyn Renderer, x: usize, y: usize, depth: i32) {
+        if self.width == 0 || self.height == 0 || renderer.render_sixel(x, y, self, depth) {
+            return;
+        }
+        self.to_half_blocks().render(renderer, x, y, depth);
+    }
+}
+
+/// A monochrome display with 2x4 dots per terminal cell, drawn with the Unicode braille
+/// characters (U+2800 to U+28FF).
+///
+/// Useful for plots and particle effects that need more resolution than
+/// [`HalfBlockDisplayRender`]. All dots of a cell share one foreground color, set with
+/// [`set_color_for_cell`](Self::set_color_for_cell). Cells without any set dots are not drawn.
+#[derive(Debug)]
+pub struct BrailleDisplayRender {
+    width: usize,
+    height: usize,
+    // one bit per dot, in the order of the braille codepoint
+    cells: Display<u8>,
+    colors: Display<Color>,
+}
+
+impl BrailleDisplayRender {
+    /// Creates a new `BrailleDisplayRender` with the specified width and height in dots.
+    ///
+    /// The display covers `width / 2` by `height / 4` terminal cells, rounded up.
+    pub fn new(width: usize, height: usize) -> Self {
+        let (cells_x, cells_y) = Self::cell_size(width, height);
+        Self {
+            width,
+            height,
+            cells: Display::new(cells_x, cells_y, 0),
+            colors: Display::new(cells_x, cells_y, Color::Default),
+        }
+    }
+
+    fn cell_size(width: usize, height: usize) -> (usize, usize) {
+        (width.div_ceil(2), height.div_ceil(4))
+    }
+
+    /// Returns the bit of the dot at `(x, y)` within its cell.
+    ///
+    /// The first three rows are numbered column by column, the fourth row comes last, because it
+    /// was added to the original six-dot braille.
+    fn dot_bit(x: usize, y: usize) -> u8 {
+        match (x % 2, y % 4) {
+            (0, 3) => 0x40,
+            (1, 3) => 0x80,
+            (dx, dy) => 1 << (dx * 3 + dy),
+        }
+    }
+
+    /// Returns the height of the display in dots.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the width of the display in dots.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Sets or unsets the dot at `(x, y)`. Dots outside of the display are ignored.
+    pub fn set(&mut self, x: usize, y: usize, value: bool) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        let bit = Self::dot_bit(x, y);
+        let cell = &mut self.cells[(x / 2, y / 4)];
+        if value {
+            *cell |= bit;
+        } else {
+            *cell &= !bit;
+        }
+    }
+
+    /// Returns whether the dot at `(x, y)` is set, or `None` if it is out of bounds.
+    pub fn get(&self, x: usize, y: usize) -> Option<bool> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        Some(self.cells[(x / 2, y / 4)] & Self::dot_bit(x, y) != 0)
+    }
+
+    /// Sets the color of all dots in the terminal cell `(cell_x, cell_y)`.
+    pub fn set_color_for_cell(&mut self, cell_x: usize, cell_y: usize, color: Color) {
+        self.colors.set(cell_x, cell_y, color);
+    }
+
+    /// Resizes the display to the specified width and height in dots, discarding the current
+    /// content.
+    pub fn resize_discard(&mut self, width: usize, height: usize) {
+        let (cells_x, cells_y) = Self::cell_size(width, height);
+        self.width = width;
+        self.height = height;
+        self.cells.resize_discard(cells_x, cells_y);
+        self.colors.resize_discard(cells_x, cells_y);
+    }
+
+    /// Clears the display, unsetting all dots and resetting all colors to the default color.
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.colors.clear();
+    }
+}
+
+impl Render for BrailleDisplayRender {
+    fn render(&self, renderer: &mut dyn Renderer, base_x: usize, base_y: usize, depth: i32) {
+        for (x, y, &bits) in self.cells.iter() {
+            if bits == 0 {
+                continue;
+            }
+            let mut pixel = Pixel::new(char::from_u32(0x2800 + bits as u32).unwrap());
+            pixel.color = self.colors[(x, y)];
+            pixel.bg_color = Color::Transparent;
+            renderer.render_pixel(base_x + x, base_y + y, pixel, depth);
+        }
+    }
+}
+
+/// Text that blinks with a fixed period, e.g. a "PRESS START" prompt.
+///
+/// Driven by [`BlinkingText::update`], so the same elapsed time always shows the same state. The
+/// text is shown for the first `duty_cycle` of every period, starting at time zero. With
+/// [`BlinkingText::with_fade`], it instead fades to a second color and back. With reduce motion,
+/// it is always shown, in bold.
+///
+/// # Example
+///
+/// ```rust
+/// use teng::rendering::render::BlinkingText;
+///
+/// let mut text = BlinkingText::new("PRESS START", 1.0).with_duty_cycle(0.75);
+/// assert!(text.is_visible());
+/// text.update(0.8);
+/// assert!(!text.is_visible());
+/// ```
+#[derive(Clone, Debug)]
+pub struct BlinkingText {
+    text: String,
+    period: f64,
+    duty_cycle: f64,
+    color: [u8; 3],
+    fade_to: Option<[u8; 3]>,
+    reduce_motion: bool,
+    elapsed: f64,
+}
+
+impl BlinkingText {
+    /// Creates white text that blinks every `period` seconds, shown for half of each period.
+    pub fn new(text: impl Into<String>, period: f64) -> Self {
+        Self {
+            text: text.into(),
+            period,
+            duty_cycle: 0.5,
+            color: [255, 255, 255],
+            fade_to: None,
+            reduce_motion: false,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Sets the fraction of each period in which the text is shown, clamped to `0.0..=1.0`.
+    pub fn with_duty_cycle(mut self, duty_cycle: f64) -> Self {
+        self.duty_cycle = duty_cycle.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_color(mut self, color: [u8; 3]) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Fades between the text color and `color` instead of blinking. The duty cycle is ignored.
+    pub fn with_fade(mut self, color: [u8; 3]) -> Self {
+        self.fade_to = Some(color);
+        self
+    }
+
+    /// Replaces the animation by static bold text.
+    pub fn with_reduce_motion(mut self, reduce_motion: bool) -> Self {
+        self.reduce_motion = reduce_motion;
+        self
+    }
+
+    /// Advances the animation by `dt` seconds.
+    pub fn update(&mut self, dt: f64) {
+        self.elapsed += dt;
+    }
+
+    /// Restarts the animation.
+    pub fn reset(&mut self) {
+        self.elapsed = 0.0;
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns whether the text is currently shown.
+    pub fn is_visible(&self) -> bool {
+        if self.reduce_motion || self.fade_to.is_some() || self.period <= 0.0 {
+            return true;
+        }
+        self.elapsed.rem_euclid(self.period) < self.duty_cycle * self.period
+    }
+
+    /// Returns the current color of the text, or `None` if it is hidden.
+    pub fn current_color(&self) -> Option<[u8; 3]> {
+        if !self.is_visible() {
+            return None;
+        }
+        match self.fade_to {
+            Some(to) if !self.reduce_motion && self.period > 0.0 => {
+                // the same curve as `pulsing_outline_color`
+                let phase = (self.elapsed / self.period) * std::f64::consts::TAU;
+                let t = (1.0 - phase.cos()) / 2.0;
+                Some(lerp_color(self.color, to, t as f32))
+            }
+            _ => Some(self.color),
+        }
+    }
+}
+
+impl Render for BlinkingText {
+    fn render(&self, renderer: &mut dyn Renderer, x: usize, y: usize, depth: i32) {
+        let Some(color) = self.current_color() else {
+            return;
+        };
+        if self.reduce_motion {
+            self.text
+                .as_str()
+                .with_color(color)
+                .with_bold()
+                .render(renderer, x, y, depth);
+        } else {
+            self.text
+                .as_str()
+                .with_color(color)
+                .render(renderer, x, y, depth);
+        }
+    }
+}
+
+/// A single line of text that scrolls through a window of fixed width, e.g. a long message in a
+/// narrow status area.
+///
+/// The text scrolls to the left by `speed` columns per second, followed by `gap` blank columns
+/// before it repeats. Every cycle starts with a pause in which the start of the text is shown.
+/// Text that fits into the window does not scroll. A wide character that is cut off by either
+/// edge of the window is replaced by spaces, so it is never split.
+///
+/// # Example
+///
+/// ```rust
+/// use teng::rendering::render::MarqueeText;
+///
+/// let mut marquee = MarqueeText::new("hello world", 5).with_speed(2.0).with_gap(1);
+/// assert_eq!(marquee.visible_text(), "hello");
+/// marquee.update(1.0);
+/// assert_eq!(marquee.visible_text(), "llo w");
+/// ```
+#[derive(Clone, Debug)]
+pub struct MarqueeText {
+    text: String,
+    width: usize,
+    speed: f64,
+    gap: usize,
+    pause: f64,
+    elapsed: f64,
+}
+
+impl MarqueeText {
+    /// Creates a marquee that scrolls by 8 columns per second with a gap of 3 columns and no
+    /// pause.
+    pub fn new(text: impl Into<String>, width: usize) -> Self {
+        Self {
+            text: text.into(),
+            width,
+            speed: 8.0,
+            gap: 3,
+            pause: 0.0,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Sets the scrolling speed in columns per second.
+    pub fn with_speed(mut self, speed: f64) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Sets the number of blank columns between the end of the text and its next repetition.
+    pub fn with_gap(mut self, gap: usize) -> Self {
+        self.gap = gap;
+        self
+    }
+
+    /// Pauses for `seconds` at the start of every cycle.
+    pub fn with_pause(mut self, seconds: f64) -> Self {
+        self.pause = seconds;
+        self
+    }
+
+    /// Advances the animation by `dt` seconds.
+    pub fn update(&mut self, dt: f64) {
+        self.elapsed += dt;
+    }
+
+    /// Restarts the animation.
+    pub fn reset(&mut self) {
+        self.elapsed = 0.0;
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns whether the text is wider than the window and therefore scrolls.
+    pub fn scrolls(&self) -> bool {
+        str_width(&self.text) > self.width
+    }
+
+    /// Returns the column of the repeated text that is at the left edge of the window.
+    pub fn offset(&self) -> usize {
+        if !self.scrolls() || self.speed <= 0.0 {
+            return 0;
+        }
+        let cycle_width = str_width(&self.text) + self.gap;
+        let cycle = self.pause + cycle_width as f64 / self.speed;
+        let time = self.elapsed.rem_euclid(cycle) - self.pause;
+        if time <= 0.0 {
+            return 0;
+        }
+        ((time * self.speed) as usize).min(cycle_width - 1)
+    }
+
+    /// Returns the text in the window, padded with spaces to exactly the window's width.
+    pub fn visible_text(&self) -> String {
+        if !self.scrolls() {
+            let padding = self.width - str_width(&self.text);
+            return self.text.clone() + &" ".repeat(padding);
+        }
+        let offset = self.offset();
+        let gap = std::iter::repeat_n(' ', self.gap);
+        let repeated = self.text.chars().chain(gap).cycle();
+        let mut visible = String::new();
+        let mut visible_width = 0;
+        // the column of the repeated text after the current character
+        let mut end = 0;
+        for c in repeated {
+            let width = char_width(c);
+            if width == 0 {
+                continue;
+            }
+            end += width;
+            if end <= offset {
+                continue;
+            }
+            if end - width < offset || visible_width + width > self.width {
+                // cut off by the left or right edge
+                let blank = (end - offset.max(end - width)).min(self.width - visible_width);
+                visible.extend(std::iter::repeat_n(' ', blank));
+                visible_width += blank;
+            } else {
+                visible.push(c);
+                visible_width += width;
+            }
+            if visible_width == self.width {
+                break;
+            }
+        }
+        visible
+    }
+}
+
+impl Render for MarqueeText {
+    fn render(&self, renderer: &mut dyn Renderer, x: usize, y: usize, depth: i32) {
+        self.visible_text().render(renderer, x, y, depth);
+    }
+}
+
+/// Text that is revealed character by character, like on a typewriter.
+///
+/// Driven by [`TypewriterText::update`]. While the text is being revealed, an optional cursor is
+/// shown after the last revealed character.
+///
+/// # Example
+///
+/// ```rust
+/// use teng::rendering::render::TypewriterText;
+///
+/// let mut text = TypewriterText::new("Hello!", 4.0).with_cursor('_');
+/// text.update(0.5);
+/// assert_eq!(text.visible_text(), "He");
+/// text.update(1.0);
+/// assert!(text.is_complete());
+/// ```
+#[derive(Clone, Debug)]
+pub struct TypewriterText {
+    text: String,
+    chars_per_second: f64,
+    cursor: Option<char>,
+    elapsed: f64,
+}
+
+impl TypewriterText {
+    pub fn new(text: impl Into<String>, chars_per_second: f64) -> Self {
+        Self {
+            text: text.into(),
+            chars_per_second,
+            cursor: None,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Shows `cursor` after the revealed text until the text is complete.
+    pub fn with_cursor(mut self, cursor: char) -> Self {
+        self.cursor = Some(cursor);
+        self
+    }
+
+    /// Advances the animation by `dt` seconds.
+    pub fn update(&mut self, dt: f64) {
+        self.elapsed += dt;
+    }
+
+    /// Restarts the animation.
+    pub fn reset(&mut self) {
+        self.elapsed = 0.0;
+    }
+
+    /// Reveals the whole text.
+    pub fn skip(&mut self) {
+        self.elapsed = f64::INFINITY;
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the number of revealed characters.
+    pub fn revealed(&self) -> usize {
+        let total = self.text.chars().count();
+        if self.chars_per_second <= 0.0 {
+            return if self.elapsed.is_infinite() { total } else { 0 };
+        }
+        let revealed = (self.elapsed * self.chars_per_second).max(0.0);
+        if revealed >= total as f64 {
+            total
+        } else {
+            revealed as usize
+        }
+    }
+
+    /// Returns whether the whole text is revealed.
+    pub fn is_complete(&self) -> bool {
+        self.revealed() == self.text.chars().count()
+    }
+
+    /// Returns the revealed text, without the cursor.
+    pub fn visible_text(&self) -> &str {
+        match self.text.char_indices().nth(self.revealed()) {
+            Some((end, _)) => &self.text[..end],
+            None => &self.text,
+        }
+    }
+}
+
+impl Render for TypewriterText {
+    fn render(&self, renderer: &mut dyn Renderer, x: usize, y: usize, depth: i32) {
+        let visible = self.visible_text();
+        visible.render(renderer, x, y, depth);
+        if let Some(cursor) = self.cursor
+            && !self.is_complete()
+        {
+            // after the last character of the last line
+            let cursor_y = y + visible.matches('\n').count();
+            let last_line = visible.rsplit('\n').next().unwrap_or("");
+            let cursor_x = x + str_width(last_line);
+            cursor.render(renderer, cursor_x, cursor_y, depth);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTLINE: Color = Color::Rgb([255, 255, 0]);
+
+    // a plus-shaped sprite in the middle of a 7x7 display
+    fn plus() -> HalfBlockDisplayRender {
+        let mut hbd = HalfBlockDisplayRender::new(7, 7);
+        for (x, y) in [(3, 2), (2, 3), (3, 3), (4, 3), (3, 4)] {
+            hbd.set_color(x, y, Color::Rgb([255, 0, 0]));
+        }
+        hbd
+    }
+
+    fn full_bounds() -> Bounds {
+        Bounds {
+            min_x: 0,
+            max_x: 6,
+            min_y: 0,
+            max_y: 6,
+        }
+    }
+
+    fn outline_pixels(hbd: &HalfBlockDisplayRender) -> Vec<(usize, usize)> {
+        let mut pixels = vec![];
+        for y in 0..hbd.height() {
+            for x in 0..hbd.width() {
+                if hbd.get_color(x, y) == Some(OUTLINE) {
+                    pixels.push((x, y));
+                }
+            }
+        }
+        pixels
+    }
+
+    #[test]
+    fn test_outline_four_connected() {
+        let mut hbd = plus();
+        hbd.draw_outline_of_region(full_bounds(), OUTLINE, Connectivity::Four);
+        #[rustfmt::skip]
+        let expected = vec![
+                            (3, 1),
+                    (2, 2),         (4, 2),
+            (1, 3),                         (5, 3),
+                    (2, 4),         (4, 4),
+                            (3, 5),
+        ];
+        assert_eq!(outline_pixels(&hbd), expected);
+    }
+
+    #[test]
+    fn test_outline_eight_connected() {
+        let mut hbd = plus();
+        hbd.draw_outline_of_region(full_bounds(), OUTLINE, Connectivity::Eight);
+        #[rustfmt::skip]
+        let expected = vec![
+                    (2, 1), (3, 1), (4, 1),
+            (1, 2), (2, 2),         (4, 2), (5, 2),
+            (1, 3),                         (5, 3),
+            (1, 4), (2, 4),         (4, 4), (5, 4),
+                    (2, 5), (3, 5), (4, 5),
+        ];
+        assert_eq!(outline_pixels(&hbd), expected);
+    }
+
+    #[test]
+    fn test_outline_respects_region() {
+        let mut hbd = plus();
+        // only the top half of the plus, touching the region's bottom border
+        let bounds = Bounds {
+            min_x: 0,
+            max_x: 6,
+            min_y: 0,
+            max_y: 3,
+        };
+        hbd.draw_outline_of_region(bounds, OUTLINE, Connectivity::Four);
+        assert_eq!(
+            outline_pixels(&hbd),
+            vec![(3, 1), (2, 2), (4, 2), (1, 3), (5, 3)]
+        );
+    }
+
+    #[test]
+    fn test_outline_matching() {
+        let mut hbd = plus();
+        let other = Color::Rgb([0, 0, 255]);
+        hbd.set_color(5, 5, other);
+        hbd.draw_outline_of_region_matching(full_bounds(), OUTLINE, Connectivity::Four, |c| {
+            c == other
+        });
+        // only the other entity is outlined, clipped to the display
+        assert_eq!(outline_pixels(&hbd), vec![(5, 4), (4, 5), (6, 5), (5, 6)]);
+    }
+
+    #[test]
+    fn test_pulsing_outline_color() {
+        let (from, to) = ([0, 0, 0], [200, 100, 50]);
+        assert_eq!(pulsing_outline_color(from, to, 0.0, 2.0), Color::Rgb(from));
+        assert_eq!(pulsing_outline_color(from, to, 1.0, 2.0), Color::Rgb(to));
+        assert_eq!(pulsing_outline_color(from, to, 2.0, 2.0), Color::Rgb(from));
+    }
+
+    #[test]
+    fn test_half_block_respects_clip() {
+        struct ClipRecorder {
+            clip: ClipRect,
+            drawn: Vec<(usize, usize)>,
+        }
+
+        impl Renderer for ClipRecorder {
+            fn render_pixel(&mut self, x: usize, y: usize, _pixel: Pixel, _depth: i32) {
+                self.drawn.push((x, y));
+            }
+
+            fn clip(&self) -> Option<ClipRect> {
+                Some(self.clip)
+            }
+        }
+
+        let mut hbd = HalfBlockDisplayRender::new(4, 4);
+        for y in 0..4 {
+            for x in 0..4 {
+                hbd.set_color(x, y, Color::Rgb([255, 0, 0]));
+            }
+        }
+        let mut recorder = ClipRecorder {
+            clip: ClipRect::new(0, 0, 12, 11),
+            drawn: vec![],
+        };
+        hbd.render(&mut recorder, 10, 10, 0);
+        // only the cells inside the clip are drawn
+        assert_eq!(recorder.drawn, vec![(10, 10), (11, 10)]);
+
+        recorder.clip = ClipRect::new(20, 0, 5, 20);
+        recorder.drawn.clear();
+        hbd.render(&mut recorder, 10, 10, 0);
+        assert!(recorder.drawn.is_empty());
+    }
+
+    #[test]
+    fn test_blending() {
+        let red = Color::Rgb([255, 0, 0]);
+        let mut hbd = HalfBlockDisplayRender::new(3, 2);
+        hbd.set_color(0, 0, Color::Rgb([0, 0, 255]));
+        hbd.set_color(1, 0, Color::Default);
+        hbd.set_color_blended(0, 0, red, 0.5);
+        assert_eq!(hbd.get_color(0, 0), Some(Color::Rgb([127, 0, 127])));
+        // default colors are skipped, transparent ones are replaced
+        hbd.set_color_blended(1, 0, red, 0.5);
+        assert_eq!(hbd.get_color(1, 0), Some(Color::Default));
+        hbd.set_color_blended(2, 0, red, 0.5);
+        assert_eq!(hbd.get_color(2, 0), Some(red));
+        hbd.set_color_blended(2, 0, Color::Transparent, 0.5);
+        assert_eq!(hbd.get_color(2, 0), Some(red));
+
+        // a red silhouette tints the pixels below it
+        let mut silhouette = HalfBlockDisplayRender::new(2, 2);
+        silhouette.set_color(0, 0, red);
+        silhouette.set_color(1, 1, red);
+        let mut sprite = HalfBlockDisplayRender::new(3, 3);
+        sprite.set_color(1, 1, Color::Rgb([0, 255, 0]));
+        sprite.blend_from(&silhouette, 1, 1, 0.5);
+        assert_eq!(sprite.get_color(1, 1), Some(Color::Rgb([127, 127, 0])));
+        assert_eq!(sprite.get_color(2, 2), Some(red));
+        assert_eq!(sprite.get_color(2, 1), Some(Color::Transparent));
+        // pixels that land outside are skipped
+        sprite.blend_from(&silhouette, -1, 2, 1.0);
+        assert_eq!(sprite.get_color(0, 2), Some(Color::Transparent));
+    }
+
+    #[test]
+    fn test_blit_scaled() {
+        let red = Color::Rgb([255, 0, 0]);
+        let blue = Color::Rgb([0, 0, 255]);
+        let mut art = HalfBlockDisplayRender::new(2, 2);
+        art.set_color(0, 0, red);
+        art.set_color(1, 1, blue);
+        let mut hbd = HalfBlockDisplayRender::new(5, 5);
+        hbd.set_color(2, 0, blue);
+        hbd.blit_scaled(&art, -1, 0, 3);
+        let row = |y| {
+            (0..5)
+                .map(|x| hbd.get_color(x, y).unwrap())
+                .collect::<Vec<_>>()
+        };
+        let t = Color::Transparent;
+        // transparent pixels of the art keep what was below
+        assert_eq!(row(0), [red, red, blue, t, t]);
+        assert_eq!(row(2), [red, red, t, t, t]);
+        assert_eq!(row(3), [t, t, blue, blue, blue]);
+        assert_eq!(row(4), [t, t, blue, blue, blue]);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_par_fill_matches_set_color() {
+        let color = |x: usize, y: usize| Color::Rgb([x as u8, y as u8, (x ^ y) as u8]);
+        let mut sequential = HalfBlockDisplayRender::new(37, 20);
+        for y in 0..20 {
+            for x in 0..37 {
+                sequential.set_color(x, y, color(x, y));
+            }
+        }
+        let mut parallel = HalfBlockDisplayRender::new(37, 20);
+        parallel.par_fill_with(color);
+        for y in 0..20 {
+            for x in 0..37 {
+                assert_eq!(parallel.get_color(x, y), sequential.get_color(x, y));
+            }
+        }
+        assert_eq!(parallel.dirty_rect, sequential.dirty_rect);
+
+        // an empty display has nothing to fill
+        let mut empty = HalfBlockDisplayRender::new(0, 4);
+        empty.par_fill_with(color);
+        assert_eq!(empty.rows_mut().count(), 0);
+        assert_eq!(empty.dirty_rect, None);
+    }
+
+    #[test]
+    fn test_paragraph_breaks_long_words() {
+        let lines = |text: &str, wrap| Paragraph::new(text, 4).with_wrap(wrap).lines();
+        assert_eq!(
+            lines("abcdefghij k", Wrap::Word),
+            vec!["abcd", "efgh", "ij k"]
+        );
+        assert_eq!(lines("ab cdefgh", Wrap::Word), vec!["ab", "cdef", "gh"]);
+        assert_eq!(lines("ab cdefgh", Wrap::Hard), vec!["ab c", "defg", "h"]);
+        // wide characters are never split
+        assert_eq!(lines("日本語", Wrap::Word), vec!["日本", "語"]);
+    }
+
+    #[test]
+    fn test_paragraph_trailing_whitespace() {
+        let lines = |text: &str| Paragraph::new(text, 5).lines();
+        assert_eq!(lines("hi   \nthere   you  "), vec!["hi", "there", "you"]);
+        // leading whitespace is kept, but not at a line break
+        assert_eq!(lines("  a b\n\nc    d"), vec!["  a b", "", "c", "d"]);
+        assert_eq!(
+            Paragraph::new("one two three", 5).with_height(2).lines(),
+            vec!["one", "two…"]
+        );
+
+        // alignment ignores trailing whitespace
+        struct RowRecorder(Vec<(usize, char)>);
+        impl Renderer for RowRecorder {
+            fn render_pixel(&mut self, x: usize, _y: usize, pixel: Pixel, _depth: i32) {
+                self.0.push((x, pixel.c));
+            }
+        }
+        let mut recorder = RowRecorder(vec![]);
+        Paragraph::new("ab   ", 5)
+            .with_alignment(Alignment::Right)
+            .render(&mut recorder, 0, 0, 0);
+        assert_eq!(recorder.0, vec![(3, 'a'), (4, 'b')]);
+    }
+
+    #[test]
+    fn test_braille_dot_positions() {
+        // the codepoint offset of every dot of a cell, in the order of the braille dot numbers
+        let dots = [
+            ((0, 0), 0x01),
+            ((0, 1), 0x02),
+            ((0, 2), 0x04),
+            ((1, 0), 0x08),
+            ((1, 1), 0x10),
+            ((1, 2), 0x20),
+            ((0, 3), 0x40),
+            ((1, 3), 0x80),
+        ];
+        for ((x, y), bit) in dots {
+            let mut braille = BrailleDisplayRender::new(4, 8);
+            // the same position in the second cell of each axis
+            braille.set(2 + x, 4 + y, true);
+            assert_eq!(braille.get(2 + x, 4 + y), Some(true));
+            let mut recorder = CharRecorder(vec![]);
+            braille.render(&mut recorder, 0, 0, 0);
+            let c = char::from_u32(0x2800 + bit).unwrap();
+            assert_eq!(recorder.0, vec![(1, 1, c)], "dot ({x}, {y})");
+        }
+    }
+
+    struct CharRecorder(Vec<(usize, usize, char)>);
+
+    impl Renderer for CharRecorder {
+        fn render_pixel(&mut self, x: usize, y: usize, pixel: Pixel, _depth: i32) {
+            self.0.push((x, y, pixel.c));
+        }
+    }
+
+    #[test]
+    fn test_braille_cells() {
+        let mut braille = BrailleDisplayRender::new(3, 5);
+        // rounded up to whole cells, but dots outside of the display are ignored
+        braille.set(3, 0, true);
+        braille.set(0, 5, true);
+        assert_eq!(braille.get(3, 0), None);
+        for y in 0..4 {
+            for x in 0..2 {
+                braille.set(x, y, true);
+            }
+        }
+        braille.set(1, 1, false);
+        braille.set(2, 4, true);
+        let color = Color::Rgb([0, 255, 0]);
+        braille.set_color_for_cell(1, 1, color);
+
+        struct ColorRecorder(Vec<(usize, usize, char, Color)>);
+        impl Renderer for ColorRecorder {
+            fn render_pixel(&mut self, x: usize, y: usize, pixel: Pixel, _depth: i32) {
+                self.0.push((x, y, pixel.c, pixel.color));
+            }
+        }
+        let mut recorder = ColorRecorder(vec![]);
+        braille.render(&mut recorder, 10, 20, 0);
+        // empty cells are skipped
+        assert_eq!(
+            recorder.0,
+            vec![(10, 20, '⣯', Color::Default), (11, 21, '⠁', color)]
+        );
+
+        braille.clear();
+        assert_eq!(braille.get(0, 0), Some(false));
+        braille.resize_discard(4, 4);
+        assert_eq!((braille.width(), braille.height()), (4, 4));
+        let mut recorder = ColorRecorder(vec![]);
+        braille.render(&mut recorder, 0, 0, 0);
+        assert!(recorder.0.is_empty());
+    }
+
+    #[test]
+    fn test_blinking_text() {
+        let mut text = BlinkingText::new("START", 2.0).with_duty_cycle(0.25);
+        let mut states = vec![];
+        for _ in 0..5 {
+            states.push(text.is_visible());
+            text.update(0.5);
+        }
+        assert_eq!(states, [true, false, false, false, true]);
+        assert_eq!(text.current_color(), None);
+
+        let mut fading = BlinkingText::new("START", 2.0)
+            .with_color([0, 0, 0])
+            .with_fade([200, 100, 0]);
+        fading.update(1.0);
+        assert_eq!(fading.current_color(), Some([200, 100, 0]));
+        fading.update(0.5);
+        assert_eq!(fading.current_color(), Some([100, 50, 0]));
+
+        let mut reduced = text.clone().with_reduce_motion(true);
+        reduced.update(0.5);
+        assert!(reduced.is_visible());
+        struct BoldRecorder(Vec<bool>);
+        impl Renderer for BoldRecorder {
+            fn render_pixel(&mut self, _x: usize, _y: usize, pixel: Pixel, _depth: i32) {
+                self.0.push(pixel.attributes.contains(Attributes::BOLD));
+            }
+        }
+        let mut recorder = BoldRecorder(vec![]);
+        reduced.render(&mut recorder, 0, 0, 0);
+        assert_eq!(recorder.0, [true; 5]);
+    }
+
+    #[test]
+    fn test_marquee_text() {
+        let mut marquee = MarqueeText::new("abcdef", 4)
+            .with_speed(2.0)
+            .with_gap(2)
+            .with_pause(1.0);
+        let mut visible = vec![];
+        for _ in 0..7 {
+            visible.push(marquee.visible_text());
+            marquee.update(1.0);
+        }
+        assert_eq!(
+            visible,
+            ["abcd", "abcd", "cdef", "ef  ", "  ab", "abcd", "abcd"]
+        );
+
+        let short = MarqueeText::new("ab", 4);
+        assert!(!short.scrolls());
+        assert_eq!(short.visible_text(), "ab  ");
+    }
+
+    #[test]
+    fn test_marquee_wide_chars() {
+        // "a" is one column, every other character two
+        let mut marquee = MarqueeText::new("a日本語", 4).with_speed(1.0).with_gap(1);
+        let mut visible = vec![];
+        for _ in 0..8 {
+            let text = marquee.visible_text();
+            assert_eq!(str_width(&text), 4);
+            visible.push(text);
+            marquee.update(1.0);
+        }
+        assert_eq!(
+            visible,
+            [
+                "a日 ", "日本", " 本 ", "本語", " 語 ", "語 a", "  a ", " a日",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_typewriter_text() {
+        let mut text = TypewriterText::new("ab\ncd", 2.0).with_cursor('_');
+        assert_eq!(text.visible_text(), "");
+        text.update(1.6);
+        assert_eq!(text.revealed(), 3);
+        assert_eq!(text.visible_text(), "ab\n");
+        assert!(!text.is_complete());
+
+        let mut recorder = CharRecorder(vec![]);
+        text.render(&mut recorder, 5, 5, 0);
+        assert_eq!(recorder.0, [(5, 5, 'a'), (6, 5, 'b'), (5, 6, '_')]);
+
+        text.update(1.0);
+        assert!(text.is_complete());
+        assert_eq!(text.visible_text(), "ab\ncd");
+        let mut recorder = CharRecorder(vec![]);
+        text.render(&mut recorder, 0, 0, 0);
+        // no cursor once complete
+        assert_eq!(recorder.0.len(), 4);
+
+        text.reset();
+        assert_eq!(text.revealed(), 0);
+        text.skip();
+        assert!(text.is_complete());
+    }
+
+    #[test]
+    fn test_kitty_image_half_blocks() {
+        let mut image = KittyImageRender::new(4, 4).with_cells(2, 1);
+        // the half blocks sample the top-left pixel of every 2x2 block
+        image.set_pixel(0, 0, [255, 0, 0, 255]);
+        image.set_pixel(2, 2, [0, 255, 0, 255]);
+        // less than half opaque
+        image.set_pixel(0, 2, [0, 0, 255, 100]);
+        let mut recorder = CharRecorder(vec![]);
+        image.render(&mut recorder, 1, 1, 0);
+        assert_eq!(recorder.0, [(1, 1, '▀'), (2, 1, '▄')]);
+
+        let generation = image.generation();
+        image.set_color(0, 0, Color::Rgb([255, 0, 0]));
+        assert_eq!(image.generation(), generation);
+        image.set_color(0, 0, Color::Transparent);
+        assert_ne!(image.generation(), generation);
+        assert_eq!(image.get_pixel(0, 0), Some([0; 4]));
+        assert_ne!(image.clone().id(), image.id());
+
+        let mut hbd = HalfBlockDisplayRender::new(3, 2);
+        hbd.set_color(2, 1, Color::Rgb([1, 2, 3]));
+        image.copy_from_hbd(&hbd);
+        assert_eq!((image.width(), image.height()), (3, 2));
+        assert_eq!(image.get_pixel(2, 1), Some([1, 2, 3, 255]));
+    }
+
+    #[test]
+    fn test_sixel_render() {
+        let mut hbd = HalfBlockDisplayRender::new(2, 2);
+        hbd.set_color(0, 0, Color::Rgb([255, 0, 0]));
+        let mut image = SixelRender::new(0, 0);
+        image.copy_from_hbd(&hbd, [0, 0, 255]);
+        assert_eq!((image.columns(), image.rows()), (2, 1));
+        assert_eq!(image.get_pixel(0, 0), Some([255, 0, 0]));
+        // transparent pixels become the background
+        assert_eq!(image.get_pixel(1, 1), Some([0, 0, 255]));
+
+        let generation = image.generation();
+        image.copy_from_hbd(&hbd, [0, 0, 255]);
+        assert_eq!(image.generation(), generation);
+
+        // without sixel support, every pixel is drawn
+        let mut recorder = CharRecorder(vec![]);
+        image.render(&mut recorder, 0, 0, 0);
+        assert_eq!(recorder.0, [(0, 0, '▀'), (1, 0, '█')]);
+
+        assert!(image.to_sixel(4, 4).starts_with("\x1bP0;1;0q\"1;1;4;4"));
+        assert_eq!(image.to_sixel(0, 4), "");
+    }
+}
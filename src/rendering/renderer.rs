@@ -19,16 +19,35 @@
 //!     to handle overlapping pixels and ensure correct rendering order. Pixels with higher depth
 //!     values are rendered on top of pixels with lower depth values.
 //! *   **Color Management:**  `DisplayRenderer` manages default foreground and background colors
-//!     and efficiently sets terminal colors only when they change.
+//!     and efficiently sets terminal colors only when they change. On terminals without 24-bit
+//!     color support, colors are quantized to a palette, see [`ColorMode`].
 //! *   **Flushing to Terminal:** `flush()` function writes the contents of the `display` buffer
 //!     to the terminal, optimizing updates by only sending changes since the last frame.
+//! *   **Post-Processing:** [`PostProcess`]es transform the final colors during `flush()`,
+//!     e.g. to preview the game with a color vision deficiency.
+//! *   **Previous Frame:** [`Renderer::previous_frame()`] gives read access to what was shown last
+//!     frame, e.g. for trails or a magnifier.
+//! *   **Clipping:** [`Renderer::push_clip()`] and [`Renderer::pop_clip()`] restrict rendering to
+//!     a rectangle, e.g. a UI panel, and [`OffsetRenderer`] translates rendering into local
+//!     coordinates.
 //! *   **Resizing:**  `resize_discard()` and `resize_keep()` functions allow you to resize the
 //!     rendering area, either discarding or preserving existing content.
+//! *   **Images:** [`Renderer::render_image()`] shows a [`KittyImageRender`] as a raster image on
+//!     terminals with the kitty graphics protocol, see [`DisplayRenderer::set_kitty_graphics()`],
+//!     and [`Renderer::render_sixel()`] draws a [`SixelRender`] with sixel graphics, see
+//!     [`DisplayRenderer::set_sixel_cell_size()`].
 
+use crate::rendering::capture::FrameSnapshot;
+use crate::rendering::color::{Color, ColorMode, ColorVisionDeficiency, simulate_cvd};
+use crate::rendering::kitty;
+use crate::rendering::pixel::{Attributes, char_width};
+use crate::rendering::render::{KittyImageRender, SixelRender};
 use crate::rendering::{display::Display, pixel::Pixel};
 use crossterm::queue;
+use std::collections::HashMap;
 use std::io;
 use std::io::Write;
+use std::ops::Range;
 
 /// Trait for rendering operations.
 ///
@@ -59,6 +78,211 @@ pub trait Renderer {
     fn set_default_bg_color(&mut self, color: [u8; 3]) {
         // default implementation does nothing
     }
+
+    /// Returns a view of the frame that was flushed last, if the renderer keeps it.
+    ///
+    /// During `render()`, this is the previous frame. Copy what you need out of the view before
+    /// rendering, since the view borrows the renderer.
+    fn previous_frame(&self) -> Option<FrameView<'_>> {
+        None
+    }
+
+    /// Returns the depth of the topmost pixel rendered at `(x, y)` in the current frame, or
+    /// `i32::MIN` if nothing was rendered there or the renderer does not track depths.
+    ///
+    /// Useful for occlusion queries, e.g. to only show a tooltip if nothing above a certain depth
+    /// covers it. Renderers that wrap another renderer must forward this.
+    fn depth_at(&self, _x: usize, _y: usize) -> i32 {
+        i32::MIN
+    }
+
+    /// Restricts rendering to the intersection of the active clip and the given rectangle, until
+    /// the matching [`Renderer::pop_clip`]. Pixels outside of it are silently dropped.
+    ///
+    /// Clips that do not intersect result in an empty clip, in which nothing is drawn. Renderers
+    /// that wrap another renderer must forward this.
+    fn push_clip(&mut self, _x: usize, _y: usize, _width: usize, _height: usize) {
+        // default implementation does nothing
+    }
+
+    /// Restores the clip that was active before the last [`Renderer::push_clip`].
+    fn pop_clip(&mut self) {
+        // default implementation does nothing
+    }
+
+    /// Returns the active clip, or `None` if rendering is not clipped.
+    ///
+    /// Pixels outside of the clip are dropped anyway, but large renders can skip them early.
+    fn clip(&self) -> Option<ClipRect> {
+        None
+    }
+
+    /// Shows `image` as a raster image with its top-left cell at `(x, y)`, and returns true, or
+    /// returns false if the renderer cannot, in which case the caller draws it otherwise.
+    ///
+    /// Used by [`KittyImageRender`], see [`DisplayRenderer::render_image`]. Renderers that wrap
+    /// another renderer without changing the pixels must forward this.
+    fn render_image(
+        &mut self,
+        _x: usize,
+        _y: usize,
+        _image: &KittyImageRender,
+        _depth: i32,
+    ) -> bool {
+        false
+    }
+
+    /// Draws `image` with sixel graphics with its top-left cell at `(x, y)`, and returns true, or
+    /// returns false if the renderer cannot, in which case the caller draws it otherwise.
+    ///
+    /// Used by [`SixelRender`], see [`DisplayRenderer::render_sixel`]. Renderers that wrap
+    /// another renderer without changing the pixels must forward this.
+    fn render_sixel(&mut self, _x: usize, _y: usize, _image: &SixelRender, _depth: i32) -> bool {
+        false
+    }
+}
+
+impl<'r> dyn Renderer + 'r {
+    /// Returns a renderer that translates all coordinates by `(dx, dy)`, so that e.g. a UI
+    /// element can render in its local coordinates. See [`OffsetRenderer`].
+    pub fn with_offset(&mut self, dx: i64, dy: i64) -> OffsetRenderer<'_> {
+        OffsetRenderer::new(self, dx, dy)
+    }
+}
+
+/// A rectangle that rendering is restricted to, see [`Renderer::push_clip`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClipRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl ClipRect {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Returns true if `(x, y)` is inside the rectangle.
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x && y >= self.y && x - self.x < self.width && y - self.y < self.height
+    }
+
+    /// Returns true if the rectangle contains no cells.
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// Returns the cells that are in both rectangles, which may be empty.
+    pub fn intersect(&self, other: ClipRect) -> ClipRect {
+        let min_x = self.x.max(other.x);
+        let min_y = self.y.max(other.y);
+        let max_x = (self.x + self.width).min(other.x + other.width);
+        let max_y = (self.y + self.height).min(other.y + other.height);
+        ClipRect::new(
+            min_x,
+            min_y,
+            max_x.saturating_sub(min_x),
+            max_y.saturating_sub(min_y),
+        )
+    }
+}
+
+/// A renderer that translates all coordinates by an offset before forwarding them, see
+/// [`Renderer::with_offset`](trait.Renderer.html#method.with_offset).
+///
+/// Pixels that end up at negative coordinates are dropped. Clips are translated as well, so a
+/// clip at `(0, 0)` starts at the offset.
+pub struct OffsetRenderer<'a> {
+    inner: &'a mut dyn Renderer,
+    dx: i64,
+    dy: i64,
+}
+
+impl<'a> OffsetRenderer<'a> {
+    pub fn new(inner: &'a mut dyn Renderer, dx: i64, dy: i64) -> Self {
+        Self { inner, dx, dy }
+    }
+}
+
+impl Renderer for OffsetRenderer<'_> {
+    fn render_pixel(&mut self, x: usize, y: usize, pixel: Pixel, depth: i32) {
+        let x = x as i64 + self.dx;
+        let y = y as i64 + self.dy;
+        if x < 0 || y < 0 {
+            return;
+        }
+        self.inner
+            .render_pixel(x as usize, y as usize, pixel, depth);
+    }
+
+    fn set_default_bg_color(&mut self, color: [u8; 3]) {
+        self.inner.set_default_bg_color(color);
+    }
+
+    fn depth_at(&self, x: usize, y: usize) -> i32 {
+        let x = x as i64 + self.dx;
+        let y = y as i64 + self.dy;
+        if x < 0 || y < 0 {
+            return i32::MIN;
+        }
+        self.inner.depth_at(x as usize, y as usize)
+    }
+
+    fn push_clip(&mut self, x: usize, y: usize, width: usize, height: usize) {
+        // the part at negative coordinates is cut off
+        let translate = |start: usize, len: usize, offset: i64| {
+            let start = start as i64 + offset;
+            let len = (len as i64 + start.min(0)).max(0);
+            (start.max(0) as usize, len as usize)
+        };
+        let (x, width) = translate(x, width, self.dx);
+        let (y, height) = translate(y, height, self.dy);
+        self.inner.push_clip(x, y, width, height);
+    }
+
+    fn pop_clip(&mut self) {
+        self.inner.pop_clip();
+    }
+
+    fn clip(&self) -> Option<ClipRect> {
+        // in local coordinates, cut off at 0
+        self.inner.clip().map(|clip| {
+            let translate = |start: usize, len: usize, offset: i64| {
+                let start = start as i64 - offset;
+                let len = (len as i64 + start.min(0)).max(0);
+                (start.max(0) as usize, len as usize)
+            };
+            let (x, width) = translate(clip.x, clip.width, self.dx);
+            let (y, height) = translate(clip.y, clip.height, self.dy);
+            ClipRect::new(x, y, width, height)
+        })
+    }
+
+    fn render_image(&mut self, x: usize, y: usize, image: &KittyImageRender, depth: i32) -> bool {
+        let x = x as i64 + self.dx;
+        let y = y as i64 + self.dy;
+        // images cannot be cut off, the half blocks can
+        if x < 0 || y < 0 {
+            return false;
+        }
+        self.inner.render_image(x as usize, y as usize, image, depth)
+    }
+
+    fn render_sixel(&mut self, x: usize, y: usize, image: &SixelRender, depth: i32) -> bool {
+        let x = x as i64 + self.dx;
+        let y = y as i64 + self.dy;
+        if x < 0 || y < 0 {
+            return false;
+        }
+        self.inner.render_sixel(x as usize, y as usize, image, depth)
+    }
 }
 
 impl<W: Write> Renderer for DisplayRenderer<W> {
@@ -73,6 +297,266 @@ impl<W: Write> Renderer for DisplayRenderer<W> {
     fn set_default_bg_color(&mut self, color: [u8; 3]) {
         DisplayRenderer::set_default_bg_color(self, color);
     }
+
+    fn previous_frame(&self) -> Option<FrameView<'_>> {
+        Some(DisplayRenderer::previous_frame(self))
+    }
+
+    fn depth_at(&self, x: usize, y: usize) -> i32 {
+        DisplayRenderer::depth_at(self, x, y)
+    }
+
+    fn push_clip(&mut self, x: usize, y: usize, width: usize, height: usize) {
+        DisplayRenderer::push_clip(self, x, y, width, height);
+    }
+
+    fn pop_clip(&mut self) {
+        DisplayRenderer::pop_clip(self);
+    }
+
+    fn clip(&self) -> Option<ClipRect> {
+        DisplayRenderer::clip(self)
+    }
+
+    fn render_image(&mut self, x: usize, y: usize, image: &KittyImageRender, depth: i32) -> bool {
+        DisplayRenderer::render_image(self, x, y, image, depth)
+    }
+
+    fn render_sixel(&mut self, x: usize, y: usize, image: &SixelRender, depth: i32) -> bool {
+        DisplayRenderer::render_sixel(self, x, y, image, depth)
+    }
+}
+
+/// A transformation of the final colors of every frame, see [`DisplayRenderer::set_post_processes`].
+///
+/// Post-processes run on the final 24-bit colors in order, right before they are written to the
+/// terminal. Any color quantization, e.g. for terminals with fewer colors, must come after them,
+/// so that simulations see the colors the game intended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PostProcess {
+    /// Shows the colors as seen with a color vision deficiency.
+    SimulateCvd(ColorVisionDeficiency),
+    /// Multiplies every channel with the given factor out of 255, e.g. for ambient lighting.
+    /// `[255, 255, 255]` leaves the colors unchanged.
+    Multiply([u8; 3]),
+}
+
+impl PostProcess {
+    /// Shows the colors as seen with the color vision deficiency `kind`, see [`simulate_cvd`].
+    pub fn simulate_cvd(kind: ColorVisionDeficiency) -> Self {
+        Self::SimulateCvd(kind)
+    }
+
+    /// Applies the post-process to a single color.
+    pub fn apply(&self, rgb: [u8; 3]) -> [u8; 3] {
+        match *self {
+            PostProcess::SimulateCvd(kind) => simulate_cvd(rgb, kind),
+            PostProcess::Multiply(factor) => {
+                std::array::from_fn(|i| (rgb[i] as u16 * factor[i] as u16 / 255) as u8)
+            }
+        }
+    }
+}
+
+fn apply_post_processes(post_processes: &[PostProcess], rgb: [u8; 3]) -> [u8; 3] {
+    post_processes.iter().fold(rgb, |rgb, p| p.apply(rgb))
+}
+
+/// An effect of an [`Overlay`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OverlayEffect {
+    /// Darkens colors by a factor from 0 (unchanged) to 1 (black).
+    Dim(f32),
+    /// Blends colors towards a color, with a strength from 0 (unchanged) to 1 (the color).
+    Tint([u8; 3], f32),
+    /// Darkens colors towards the edges of the screen, with a strength from 0 (unchanged) to 1
+    /// (black corners).
+    Vignette(f32),
+}
+
+/// A full-screen effect on the cells below a depth, e.g. to dim the game behind a dialog, see
+/// [`DisplayRenderer::set_overlay`].
+///
+/// The overlay is applied during flush to the colors of every cell whose topmost pixel has a
+/// depth below [`Overlay::below_depth`], before post-processes. Default colors are resolved to
+/// the renderer's default colors. The previous frame stores the overlaid pixels, so a constant
+/// overlay over unchanged content writes nothing to the terminal.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Overlay {
+    pub effect: OverlayEffect,
+    /// Cells with pixels at this depth or above are not affected.
+    pub below_depth: i32,
+}
+
+impl Overlay {
+    /// Dims every cell, see [`OverlayEffect::Dim`].
+    pub fn dim(factor: f32) -> Self {
+        Self::new(OverlayEffect::Dim(factor))
+    }
+
+    /// Tints every cell, see [`OverlayEffect::Tint`].
+    pub fn tint(color: [u8; 3], strength: f32) -> Self {
+        Self::new(OverlayEffect::Tint(color, strength))
+    }
+
+    /// Adds a vignette to the screen, see [`OverlayEffect::Vignette`].
+    pub fn vignette(strength: f32) -> Self {
+        Self::new(OverlayEffect::Vignette(strength))
+    }
+
+    fn new(effect: OverlayEffect) -> Self {
+        Self {
+            effect,
+            below_depth: i32::MAX,
+        }
+    }
+
+    /// Restricts the overlay to cells whose pixels are below `depth`.
+    pub fn below(self, depth: i32) -> Self {
+        Self {
+            below_depth: depth,
+            ..self
+        }
+    }
+
+    /// Applies the overlay to the color of the cell `(x, y)` of a `width` x `height` screen.
+    pub fn apply(&self, rgb: [u8; 3], x: usize, y: usize, width: usize, height: usize) -> [u8; 3] {
+        let scale =
+            |rgb: [u8; 3], factor: f32| rgb.map(|c| (c as f32 * factor.clamp(0.0, 1.0)) as u8);
+        match self.effect {
+            OverlayEffect::Dim(factor) => scale(rgb, 1.0 - factor),
+            OverlayEffect::Tint(color, strength) => {
+                crate::util::lerp_color(rgb, color, strength.clamp(0.0, 1.0))
+            }
+            OverlayEffect::Vignette(strength) => {
+                // from -1 to 1 at the screen edges
+                let nx = (x as f32 + 0.5) / width as f32 * 2.0 - 1.0;
+                let ny = (y as f32 + 0.5) / height as f32 * 2.0 - 1.0;
+                scale(rgb, 1.0 - strength * (nx * nx + ny * ny) / 2.0)
+            }
+        }
+    }
+}
+
+/// A read-only view of a frame flushed by a [`DisplayRenderer`], see [`Renderer::previous_frame`].
+///
+/// Pixels are returned as they were shown, i.e., with the post-processes of their flush applied.
+/// Default colors stay `Color::Default`, except in cells affected by an [`Overlay`].
+pub struct FrameView<'a> {
+    display: &'a Display<Pixel>,
+    post_processes: &'a [PostProcess],
+    changed_cells: &'a [(usize, usize)],
+}
+
+impl<'a> FrameView<'a> {
+    pub fn width(&self) -> usize {
+        self.display.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.display.height()
+    }
+
+    /// Returns the pixel that was shown at `(x, y)`.
+    ///
+    /// Panics if `(x, y)` is out of bounds.
+    pub fn pixel_at(&self, x: usize, y: usize) -> Pixel {
+        self.post_process(self.display[(x, y)])
+    }
+
+    /// Returns an iterator over all cells and their pixels, row by row.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize, Pixel)> + '_ {
+        self.display
+            .iter()
+            .map(|(x, y, pixel)| (x, y, self.post_process(*pixel)))
+    }
+
+    /// Returns the cells that were written to the terminal in the flush of this frame, in row-major
+    /// order. After a resize or a change of default colors or post-processes, these are all cells.
+    pub fn changed_cells_last_flush(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.changed_cells.iter().copied()
+    }
+
+    fn post_process(&self, mut pixel: Pixel) -> Pixel {
+        if self.post_processes.is_empty() {
+            return pixel;
+        }
+        for color in [&mut pixel.color, &mut pixel.bg_color] {
+            if let Color::Rgb(rgb) = color {
+                *rgb = apply_post_processes(self.post_processes, *rgb);
+            }
+        }
+        pixel
+    }
+}
+
+/// The tiles of a [`DisplayRenderer`]'s screen that may differ from the last flushed frame.
+///
+/// A tile is dirty if a pixel was rendered to it since the last reset, or before the last reset,
+/// since the reset cleared those pixels. Only dirty tiles need to be compared in `flush()`.
+struct DirtyTiles {
+    columns: usize,
+    rows: usize,
+    drawn: Vec<bool>,
+    drawn_before_reset: Vec<bool>,
+}
+
+impl DirtyTiles {
+    const TILE_WIDTH: usize = 16;
+    const TILE_HEIGHT: usize = 8;
+
+    /// Creates the tiles of a `width` x `height` screen, all dirty.
+    fn new(width: usize, height: usize) -> Self {
+        let mut tiles = Self {
+            columns: 0,
+            rows: 0,
+            drawn: vec![],
+            drawn_before_reset: vec![],
+        };
+        tiles.resize(width, height);
+        tiles
+    }
+
+    /// Resizes to a `width` x `height` screen and marks every tile dirty.
+    fn resize(&mut self, width: usize, height: usize) {
+        self.columns = width.div_ceil(Self::TILE_WIDTH);
+        self.rows = height.div_ceil(Self::TILE_HEIGHT);
+        self.drawn = vec![true; self.columns * self.rows];
+        self.drawn_before_reset = self.drawn.clone();
+    }
+
+    fn mark(&mut self, x: usize, y: usize) {
+        self.drawn[y / Self::TILE_HEIGHT * self.columns + x / Self::TILE_WIDTH] = true;
+    }
+
+    fn is_dirty(&self, column: usize, row: usize) -> bool {
+        let idx = row * self.columns + column;
+        self.drawn[idx] || self.drawn_before_reset[idx]
+    }
+
+    /// Keeps the drawn tiles dirty until after the next reset.
+    fn reset(&mut self) {
+        std::mem::swap(&mut self.drawn, &mut self.drawn_before_reset);
+        self.drawn.fill(false);
+    }
+
+    /// Returns the runs of x coordinates of the cells in row `y` of a `width` wide screen that
+    /// are in dirty tiles, or the whole row if `all` is true.
+    fn dirty_spans(&self, y: usize, width: usize, all: bool) -> impl Iterator<Item = Range<usize>> {
+        let row = y / Self::TILE_HEIGHT;
+        let mut column = 0;
+        std::iter::from_fn(move || {
+            while column < self.columns && !(all || self.is_dirty(column, row)) {
+                column += 1;
+            }
+            let start = column;
+            while column < self.columns && (all || self.is_dirty(column, row)) {
+                column += 1;
+            }
+            (start < column)
+                .then(|| start * Self::TILE_WIDTH..(column * Self::TILE_WIDTH).min(width))
+        })
+    }
 }
 
 /// Concrete `Renderer` implementation that renders to a terminal using `crossterm`.
@@ -94,9 +578,70 @@ pub struct DisplayRenderer<W: Write> {
     last_fg_color: [u8; 3],
     default_bg_color: [u8; 3],
     last_bg_color: [u8; 3],
+    post_processes: Vec<PostProcess>,
+    /// Whether the post-processes changed since the last flush, which invalidates `prev_display`.
+    post_processes_changed: bool,
+    /// The post-processes `prev_display` was flushed with.
+    flushed_post_processes: Vec<PostProcess>,
+    /// The cells written to the terminal in the last flush.
+    changed_cells: Vec<(usize, usize)>,
+    overlay: Option<Overlay>,
+    /// Whether the next flush writes every cell, see [`Self::force_redraw`].
+    force_redraw: bool,
+    /// Whether positions are relative to the saved cursor position, see [`Self::begin_inline`].
+    inline: bool,
+    /// Escape sequences that are written after the next frame, see [`Self::queue_escape`].
+    escapes: String,
+    /// The active clips, each already intersected with the ones below, see [`Self::push_clip`].
+    clip_stack: Vec<ClipRect>,
+    /// The tiles that `flush()` compares against the previous frame.
+    dirty_tiles: DirtyTiles,
+    /// Whether the last flush applied an overlay, which touches every cell.
+    overlay_flushed: bool,
+    /// The colors the terminal supports, see [`Self::set_color_mode`].
+    color_mode: ColorMode,
+    /// Whether images are shown with the kitty graphics protocol, see [`Self::set_kitty_graphics`].
+    kitty_graphics: bool,
+    /// The images rendered this frame, by id, and the cells they cover.
+    frame_images: Vec<(u32, ClipRect)>,
+    /// The images stored in the terminal, by id.
+    kitty_images: HashMap<u32, KittyImageState>,
+    /// The size of a cell in pixels, which enables sixels, see [`Self::set_sixel_cell_size`].
+    sixel_cell_size: Option<(usize, usize)>,
+    /// The sixel images rendered this frame, by id, with their cells and depth.
+    frame_sixels: Vec<(u32, ClipRect, i32)>,
+    /// The sixel images of the last and this frame, by id.
+    sixel_images: HashMap<u32, SixelImageState>,
     sink: W,
 }
 
+/// An image that is stored in the terminal, see [`DisplayRenderer::render_image`].
+struct KittyImageState {
+    /// The generation of the image that `transmit` sends.
+    generation: u64,
+    /// The sequences that send the image data to the terminal.
+    transmit: String,
+    /// Whether `transmit` changed since it was written.
+    changed: bool,
+    /// The cells the image is shown in, if it is shown.
+    placement: Option<ClipRect>,
+}
+
+/// A sixel image, see [`DisplayRenderer::render_sixel`].
+struct SixelImageState {
+    /// The generation and size in pixels of the image that `sixel` draws.
+    generation: u64,
+    size: (usize, usize),
+    /// The sequence that draws the image.
+    sixel: String,
+    /// Whether `sixel` changed since it was written.
+    changed: bool,
+    /// The cells the image was last drawn in, if it is shown.
+    placement: Option<ClipRect>,
+    /// Whether the image is drawn with this flush.
+    redraw: bool,
+}
+
 impl<W: Write> DisplayRenderer<W> {
     /// Creates a new `DisplayRenderer` with a custom output sink.
     ///
@@ -121,6 +666,24 @@ impl<W: Write> DisplayRenderer<W> {
             last_fg_color: [255, 255, 255],
             default_bg_color: [0, 0, 0],
             last_bg_color: [0, 0, 0],
+            post_processes: vec![],
+            post_processes_changed: false,
+            flushed_post_processes: vec![],
+            changed_cells: vec![],
+            overlay: None,
+            force_redraw: false,
+            inline: false,
+            escapes: String::new(),
+            clip_stack: vec![],
+            dirty_tiles: DirtyTiles::new(width, height),
+            overlay_flushed: false,
+            color_mode: ColorMode::TrueColor,
+            kitty_graphics: false,
+            frame_images: vec![],
+            kitty_images: HashMap::new(),
+            sixel_cell_size: None,
+            frame_sixels: vec![],
+            sixel_images: HashMap::new(),
         }
     }
 
@@ -144,6 +707,269 @@ impl<W: Write> DisplayRenderer<W> {
         self.default_bg_color = color;
     }
 
+    /// Returns the post-processes applied on flush.
+    pub fn post_processes(&self) -> &[PostProcess] {
+        &self.post_processes
+    }
+
+    /// Sets the post-processes applied on flush, in order. Works on next flush.
+    pub fn set_post_processes(&mut self, post_processes: Vec<PostProcess>) {
+        if post_processes != self.post_processes {
+            self.post_processes = post_processes;
+            self.post_processes_changed = true;
+        }
+    }
+
+    /// Returns the overlay applied on flush.
+    pub fn overlay(&self) -> Option<Overlay> {
+        self.overlay
+    }
+
+    /// Sets the overlay applied on flush, see [`Overlay`]. Works on next flush.
+    ///
+    /// Without an overlay, flushing does no extra work.
+    pub fn set_overlay(&mut self, overlay: Option<Overlay>) {
+        self.overlay = overlay;
+    }
+
+    /// Returns the colors the terminal supports.
+    pub fn color_mode(&self) -> ColorMode {
+        self.color_mode
+    }
+
+    /// Sets the colors the terminal supports, e.g. from [`ColorMode::detect`]. Works on next flush.
+    ///
+    /// The default is [`ColorMode::TrueColor`]. In the other modes, RGB colors are quantized to
+    /// the closest palette color.
+    pub fn set_color_mode(&mut self, color_mode: ColorMode) {
+        if color_mode != self.color_mode {
+            self.color_mode = color_mode;
+            self.force_redraw = true;
+        }
+    }
+
+    /// Returns whether images are shown with the kitty graphics protocol.
+    pub fn kitty_graphics(&self) -> bool {
+        self.kitty_graphics
+    }
+
+    /// Sets whether the terminal supports the kitty graphics protocol, e.g. from
+    /// [`detect_kitty_graphics`](kitty::detect_kitty_graphics). Works on next render.
+    ///
+    /// The default is false, which draws images with half blocks.
+    pub fn set_kitty_graphics(&mut self, enabled: bool) {
+        self.kitty_graphics = enabled;
+    }
+
+    /// Shows `image` with its top-left cell at `(x, y)` with the kitty graphics protocol, and
+    /// returns true. Returns false if the protocol is disabled, the image is not fully inside the
+    /// screen and the active clip, or the image was already rendered this frame.
+    ///
+    /// The cells of the image are cleared at `depth`, so that nothing below it is drawn over the
+    /// image, and so that the cells do not change while the image is shown. The image is drawn
+    /// below the text of the pixels above it. Overlays and post-processes do not change the image.
+    ///
+    /// The image is sent to the terminal with the next flush if it changed, see the
+    /// [`kitty`] module.
+    pub fn render_image(
+        &mut self,
+        x: usize,
+        y: usize,
+        image: &KittyImageRender,
+        depth: i32,
+    ) -> bool {
+        let (columns, rows) = (image.columns(), image.rows());
+        if !self.kitty_graphics
+            || image.width() == 0
+            || image.height() == 0
+            || columns == 0
+            || rows == 0
+            || !self.is_drawable(x, y)
+            || !self.is_drawable(x + columns - 1, y + rows - 1)
+            || self.frame_images.iter().any(|&(id, _)| id == image.id())
+        {
+            return false;
+        }
+
+        for cell_y in y..y + rows {
+            for cell_x in x..x + columns {
+                self.render_pixel(cell_x, cell_y, Self::image_cell(), depth);
+            }
+        }
+
+        let unchanged = self
+            .kitty_images
+            .get(&image.id())
+            .is_some_and(|state| state.generation == image.generation());
+        if !unchanged {
+            let transmit = kitty::transmit(image.id(), image.width(), image.height(), image.rgba());
+            self.kitty_images.insert(
+                image.id(),
+                KittyImageState {
+                    generation: image.generation(),
+                    transmit,
+                    changed: true,
+                    placement: None,
+                },
+            );
+        }
+        self.frame_images
+            .push((image.id(), ClipRect::new(x, y, columns, rows)));
+        true
+    }
+
+    /// The pixel that images clear their cells with.
+    fn image_cell() -> Pixel {
+        let mut pixel = Pixel::new(' ');
+        pixel.bg_color = Color::Default;
+        pixel
+    }
+
+    /// Returns the size of a cell in pixels that sixel images are drawn with, if enabled.
+    pub fn sixel_cell_size(&self) -> Option<(usize, usize)> {
+        self.sixel_cell_size
+    }
+
+    /// Sets the size of a cell in pixels, e.g. from
+    /// [`sixel::cell_size`](crate::rendering::sixel::cell_size), which enables sixel
+    /// graphics. Works on next render.
+    ///
+    /// The default is `None`, which draws sixel images with half blocks. Set it again when the
+    /// terminal's font size changes.
+    pub fn set_sixel_cell_size(&mut self, cell_size: Option<(usize, usize)>) {
+        self.sixel_cell_size = cell_size.filter(|&(width, height)| width > 0 && height > 0);
+    }
+
+    /// Draws `image` with its top-left cell at `(x, y)` with sixel graphics, and returns true.
+    /// Returns false if sixels are disabled, the image is not fully inside the screen and the
+    /// active clip, or the image was already rendered this frame.
+    ///
+    /// The image is scaled to the pixels of its cells. Like for [`Self::render_image`], the cells
+    /// are cleared at `depth`, and the pixels above the image are written after it, so that they
+    /// stay visible. Overlays and post-processes do not change the image.
+    ///
+    /// The image is drawn after the other cells of the frame whenever it changed, moved, or one
+    /// of its cells was written, see the [`sixel`](crate::rendering::sixel) module.
+    pub fn render_sixel(&mut self, x: usize, y: usize, image: &SixelRender, depth: i32) -> bool {
+        let (columns, rows) = (image.columns(), image.rows());
+        let Some((cell_width, cell_height)) = self.sixel_cell_size else {
+            return false;
+        };
+        if image.width() == 0
+            || image.height() == 0
+            || columns == 0
+            || rows == 0
+            || !self.is_drawable(x, y)
+            || !self.is_drawable(x + columns - 1, y + rows - 1)
+            || self.frame_sixels.iter().any(|&(id, ..)| id == image.id())
+        {
+            return false;
+        }
+
+        for cell_y in y..y + rows {
+            for cell_x in x..x + columns {
+                self.render_pixel(cell_x, cell_y, Self::image_cell(), depth);
+            }
+        }
+
+        let size = (columns * cell_width, rows * cell_height);
+        match self.sixel_images.get_mut(&image.id()) {
+            Some(state) if (state.generation, state.size) == (image.generation(), size) => {}
+            Some(state) => {
+                state.generation = image.generation();
+                state.size = size;
+                state.sixel = image.to_sixel(size.0, size.1);
+                state.changed = true;
+            }
+            None => {
+                let state = SixelImageState {
+                    generation: image.generation(),
+                    size,
+                    sixel: image.to_sixel(size.0, size.1),
+                    changed: true,
+                    placement: None,
+                    redraw: false,
+                };
+                self.sixel_images.insert(image.id(), state);
+            }
+        }
+        self.frame_sixels
+            .push((image.id(), ClipRect::new(x, y, columns, rows), depth));
+        true
+    }
+
+    /// Returns the color that is written to the terminal for the final `color` of a cell.
+    fn terminal_color(&self, color: Color, default: [u8; 3]) -> crossterm::style::Color {
+        // palette colors are written as is, unless they are transformed or not supported
+        if let Color::Ansi(index) = color
+            && self.post_processes.is_empty()
+            && (index < 16 || self.color_mode != ColorMode::Ansi16)
+        {
+            return crossterm::style::Color::AnsiValue(index);
+        }
+        let rgb = apply_post_processes(&self.post_processes, color.unwrap_or(default));
+        match self.color_mode.quantize(rgb) {
+            Some(index) => crossterm::style::Color::AnsiValue(index),
+            None => crossterm::style::Color::Rgb {
+                r: rgb[0],
+                g: rgb[1],
+                b: rgb[2],
+            },
+        }
+    }
+
+    /// Applies the overlay to the cells of the current frame.
+    fn apply_overlay(&mut self) {
+        let Some(overlay) = self.overlay else {
+            return;
+        };
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let depth = self.depth_buffer[(x, y)].max(self.bg_depth_buffer[(x, y)]);
+                if depth >= overlay.below_depth {
+                    continue;
+                }
+                let pixel = &mut self.display[(x, y)];
+                let fg = pixel.color.unwrap_or(self.default_fg_color);
+                let bg = pixel.bg_color.unwrap_or(self.default_bg_color);
+                pixel.color = Color::Rgb(overlay.apply(fg, x, y, self.width, self.height));
+                pixel.bg_color = Color::Rgb(overlay.apply(bg, x, y, self.width, self.height));
+            }
+        }
+    }
+
+    /// Returns a view of the frame that was flushed last.
+    pub fn previous_frame(&self) -> FrameView<'_> {
+        FrameView {
+            display: &self.prev_display,
+            post_processes: &self.flushed_post_processes,
+            changed_cells: &self.changed_cells,
+        }
+    }
+
+    /// Returns the depth of the topmost pixel rendered at `(x, y)`, or `i32::MIN` if nothing was
+    /// rendered there or it is out of bounds. See [`Renderer::depth_at`].
+    ///
+    /// Between a flush and the next [`reset_screen`](Self::reset_screen), these are the depths of
+    /// the flushed frame.
+    pub fn depth_at(&self, x: usize, y: usize) -> i32 {
+        self.depth_buffer.get(x, y).copied().unwrap_or(i32::MIN)
+    }
+
+    /// Returns a snapshot of the last flushed frame, as it was shown.
+    pub fn capture_frame(&self) -> FrameSnapshot {
+        let frame = self.previous_frame();
+        let mut pixels = Display::new(self.width, self.height, Pixel::default());
+        for (x, y, pixel) in frame.iter() {
+            pixels.set(x, y, pixel);
+        }
+        FrameSnapshot::new(
+            pixels,
+            apply_post_processes(&self.flushed_post_processes, self.default_fg_color),
+            apply_post_processes(&self.flushed_post_processes, self.default_bg_color),
+        )
+    }
+
     /// Resizes the display and mangles the existing contents.
     pub fn resize_discard(&mut self, width: usize, height: usize) {
         self.width = width;
@@ -161,6 +987,15 @@ impl<W: Write> DisplayRenderer<W> {
         }
         self.depth_buffer.resize_discard(width, height);
         self.bg_depth_buffer.resize_discard(width, height);
+        self.dirty_tiles.resize(width, height);
+        self.changed_cells.clear();
+        // the terminal may have moved the images
+        for state in self.kitty_images.values_mut() {
+            state.placement = None;
+        }
+        for state in self.sixel_images.values_mut() {
+            state.changed = true;
+        }
     }
 
     /// Resizes the display and keeps the existing contents.
@@ -171,6 +1006,8 @@ impl<W: Write> DisplayRenderer<W> {
         self.prev_display.resize_keep(width, height);
         self.depth_buffer.resize_keep(width, height);
         self.bg_depth_buffer.resize_keep(width, height);
+        self.dirty_tiles.resize(width, height);
+        self.changed_cells.retain(|&(x, y)| x < width && y < height);
     }
 
     /// Renders a single pixel to the display buffer at the specified coordinates and depth.
@@ -180,10 +1017,76 @@ impl<W: Write> DisplayRenderer<W> {
     /// call `flush()` to perform the actual terminal output.
     ///
     /// Higher depths have higher priority. At same depth, the first call wins.
-    pub fn render_pixel(&mut self, x: usize, y: usize, new_pixel: Pixel, new_depth: i32) {
-        if x >= self.width || y >= self.height {
+    ///
+    /// Parts of the pixel that are transparent show what is below them: with a transparent
+    /// background, the character is drawn over the cell's background, and a space with a
+    /// transparent background and no attributes does not change the cell at all.
+    ///
+    /// A wide character also covers the cell to its right, and is replaced by a space if that
+    /// cell is off-screen or clipped. Covering either half of a wide character with another
+    /// character clears the whole wide character, see
+    /// [the `pixel` module](crate::rendering::pixel#wide-characters).
+    pub fn render_pixel(&mut self, x: usize, y: usize, mut new_pixel: Pixel, new_depth: i32) {
+        if !self.is_drawable(x, y) {
+            return;
+        }
+        match new_pixel.width() {
+            0 if new_pixel.c != Pixel::WIDE_CONTINUATION => {
+                new_pixel.c = char::REPLACEMENT_CHARACTER;
+            }
+            2 if self.is_drawable(x + 1, y) => {
+                self.render_cell(x, y, new_pixel, new_depth);
+                let continuation = Pixel {
+                    c: Pixel::WIDE_CONTINUATION,
+                    ..new_pixel
+                };
+                self.render_cell(x + 1, y, continuation, new_depth);
+                self.repair_wide_chars(x, x + 1, y);
+                return;
+            }
+            2 => new_pixel.c = ' ',
+            _ => {}
+        }
+        self.render_cell(x, y, new_pixel, new_depth);
+        self.repair_wide_chars(x, x, y);
+    }
+
+    /// Returns whether `(x, y)` is on screen and inside the clip.
+    fn is_drawable(&self, x: usize, y: usize) -> bool {
+        x < self.width
+            && y < self.height
+            && self
+                .clip_stack
+                .last()
+                .is_none_or(|clip| clip.contains(x, y))
+    }
+
+    /// Clears the halves of wide characters next to and in the cells `start_x..=end_x` of row `y`
+    /// whose other half was rendered over.
+    fn repair_wide_chars(&mut self, start_x: usize, end_x: usize, y: usize) {
+        let end_x = (end_x + 1).min(self.width - 1);
+        for x in start_x.saturating_sub(1)..=end_x {
+            let c = self.display[(x, y)].c;
+            let intact = if c == Pixel::WIDE_CONTINUATION {
+                x > 0 && self.display[(x - 1, y)].width() == 2
+            } else if char_width(c) == 2 {
+                x + 1 < self.width && self.display[(x + 1, y)].c == Pixel::WIDE_CONTINUATION
+            } else {
+                true
+            };
+            if !intact {
+                self.display[(x, y)].c = ' ';
+            }
+        }
+    }
+
+    /// Renders a pixel to a single cell, see [`Self::render_pixel`].
+    fn render_cell(&mut self, x: usize, y: usize, new_pixel: Pixel, new_depth: i32) {
+        if new_pixel.is_invisible() {
+            // e.g. the padding of a panel, which must not cover what is below it
             return;
         }
+        self.dirty_tiles.mark(x, y);
 
         // match &mut new_pixel.color {
         //     Color::Rgb(arr) => {
@@ -229,100 +1132,439 @@ impl<W: Write> DisplayRenderer<W> {
         self.depth_buffer[(x, y)] = old_depth.max(new_depth);
     }
 
+    /// Restricts rendering to the intersection of the active clip and the given rectangle, until
+    /// the matching [`Self::pop_clip`].
+    pub fn push_clip(&mut self, x: usize, y: usize, width: usize, height: usize) {
+        let mut clip = ClipRect::new(x, y, width, height);
+        if let Some(active) = self.clip_stack.last() {
+            clip = active.intersect(clip);
+        }
+        self.clip_stack.push(clip);
+    }
+
+    /// Restores the clip that was active before the last [`Self::push_clip`].
+    ///
+    /// Popping without a matching push is a bug, which panics in debug builds.
+    pub fn pop_clip(&mut self) {
+        let popped = self.clip_stack.pop();
+        debug_assert!(popped.is_some(), "pop_clip without a matching push_clip");
+    }
+
+    /// Returns the active clip, or `None` if rendering is not clipped.
+    pub fn clip(&self) -> Option<ClipRect> {
+        self.clip_stack.last().copied()
+    }
+
     /// Resets the screen to a blank state.
     ///
     /// Clears both the `display` buffer and the depth buffers, effectively preparing
-    /// for a fresh frame of rendering.
+    /// for a fresh frame of rendering. The cleared cells stay dirty for the next flush, so that
+    /// pixels that are not rendered again get erased. Clips that were not popped are a bug, which panics in
+    /// debug builds, and are removed otherwise.
     pub fn reset_screen(&mut self) {
+        debug_assert!(
+            self.clip_stack.is_empty(),
+            "{} push_clip without a matching pop_clip",
+            self.clip_stack.len()
+        );
+        self.clip_stack.clear();
         // needed because otherwise we get the 'solitaire bouncing cards' effect
         self.display.clear();
         self.depth_buffer.clear();
         self.bg_depth_buffer.clear();
+        self.dirty_tiles.reset();
+    }
+
+    /// Makes the next flush write every cell, e.g. because the terminal's contents may not
+    /// match the last frame anymore.
+    pub fn force_redraw(&mut self) {
+        self.force_redraw = true;
+    }
+
+    /// Renders inline into the normal screen, in a strip of `self.height()` rows that starts at
+    /// the cursor's line, instead of at the top of the screen.
+    ///
+    /// Writes newlines to make room for the strip, scrolling the screen if needed, and saves the
+    /// strip's top-left corner as the cursor position. Every flush positions the cursor relative
+    /// to it, so nothing outside the strip is touched.
+    ///
+    /// If already rendering inline, the old strip is cleared and the strip is reserved again at
+    /// its start, e.g. after a resize reflowed the screen.
+    pub fn begin_inline(&mut self) -> io::Result<()> {
+        if std::mem::replace(&mut self.inline, true) {
+            queue!(
+                self.sink,
+                crossterm::style::ResetColor,
+                crossterm::cursor::RestorePosition,
+                crossterm::terminal::Clear(crossterm::terminal::ClearType::FromCursorDown)
+            )?;
+        }
+        let rows = self.height.max(1) as u16;
+        for _ in 1..rows {
+            self.sink.write_all(b"\n")?;
+        }
+        if rows > 1 {
+            queue!(self.sink, crossterm::cursor::MoveUp(rows - 1))?;
+        }
+        queue!(
+            self.sink,
+            crossterm::cursor::MoveToColumn(0),
+            crossterm::cursor::SavePosition
+        )?;
+        self.force_redraw();
+        self.sink.flush()
+    }
+
+    /// Clears the strip of [`Self::begin_inline`] and leaves the cursor at its start, so that
+    /// the shell continues there.
+    pub fn end_inline(&mut self) -> io::Result<()> {
+        if !std::mem::take(&mut self.inline) {
+            return Ok(());
+        }
+        queue!(
+            self.sink,
+            crossterm::style::ResetColor,
+            crossterm::cursor::RestorePosition,
+            crossterm::terminal::Clear(crossterm::terminal::ClearType::FromCursorDown)
+        )?;
+        self.sink.flush()
+    }
+
+    /// Returns true if rendering inline, see [`Self::begin_inline`].
+    pub fn is_inline(&self) -> bool {
+        self.inline
+    }
+
+    /// Moves the cursor to the cell `(x, y)`.
+    fn queue_move_to(sink: &mut W, inline: bool, x: usize, y: usize) -> io::Result<()> {
+        if !inline {
+            return queue!(sink, crossterm::cursor::MoveTo(x as u16, y as u16));
+        }
+        queue!(sink, crossterm::cursor::RestorePosition)?;
+        if y > 0 {
+            queue!(sink, crossterm::cursor::MoveDown(y as u16))?;
+        }
+        if x > 0 {
+            queue!(sink, crossterm::cursor::MoveToColumn(x as u16))?;
+        }
+        Ok(())
+    }
+
+    /// Queues the SGR sequences that set the colors that changed.
+    ///
+    /// The 16 basic colors use their own SGR codes, which terminals without 256-color support
+    /// understand.
+    fn queue_colors(
+        sink: &mut W,
+        fg: Option<crossterm::style::Color>,
+        bg: Option<crossterm::style::Color>,
+    ) -> io::Result<()> {
+        let mut colors = crossterm::style::Colors {
+            foreground: None,
+            background: None,
+        };
+        for (color, base, extended) in [
+            (fg, 30, &mut colors.foreground),
+            (bg, 40, &mut colors.background),
+        ] {
+            match color {
+                Some(crossterm::style::Color::AnsiValue(index @ 0..8)) => {
+                    write!(sink, "\x1b[{}m", base + index)?
+                }
+                Some(crossterm::style::Color::AnsiValue(index @ 8..16)) => {
+                    write!(sink, "\x1b[{}m", base + 60 + index - 8)?
+                }
+                color => *extended = color,
+            }
+        }
+        // if both are None, this is a noop
+        queue!(sink, crossterm::style::SetColors(colors))
+    }
+
+    /// Queues the SGR sequences that change the terminal's text attributes from `from` to `to`.
+    fn queue_attributes(sink: &mut W, from: Attributes, to: Attributes) -> io::Result<()> {
+        use crossterm::style::Attribute;
+        if from == to {
+            return Ok(());
+        }
+        let mut added = to.difference(from);
+        let removed = from.difference(to);
+        let mut off = crossterm::style::Attributes::default();
+        if removed.intersects(Attributes::BOLD | Attributes::DIM) {
+            // turns off both, so the remaining one is turned on again
+            off.set(Attribute::NormalIntensity);
+            added |= to & (Attributes::BOLD | Attributes::DIM);
+        }
+        for (attribute, sgr) in [
+            (Attributes::ITALIC, Attribute::NoItalic),
+            (Attributes::UNDERLINE, Attribute::NoUnderline),
+            (Attributes::REVERSE, Attribute::NoReverse),
+            (Attributes::STRIKETHROUGH, Attribute::NotCrossedOut),
+        ] {
+            if removed.contains(attribute) {
+                off.set(sgr);
+            }
+        }
+        let mut on = crossterm::style::Attributes::default();
+        for (attribute, sgr) in [
+            (Attributes::BOLD, Attribute::Bold),
+            (Attributes::DIM, Attribute::Dim),
+            (Attributes::ITALIC, Attribute::Italic),
+            (Attributes::UNDERLINE, Attribute::Underlined),
+            (Attributes::REVERSE, Attribute::Reverse),
+            (Attributes::STRIKETHROUGH, Attribute::CrossedOut),
+        ] {
+            if added.contains(attribute) {
+                on.set(sgr);
+            }
+        }
+        // separately, since the sequences of one command are not ordered
+        if !off.is_empty() {
+            queue!(sink, crossterm::style::SetAttributes(off))?;
+        }
+        if !on.is_empty() {
+            queue!(sink, crossterm::style::SetAttributes(on))?;
+        }
+        Ok(())
+    }
+
+    /// Writes the images of this frame that changed or moved, and deletes the images that were
+    /// not rendered this frame. With `forced`, every image is sent again.
+    fn flush_images(&mut self, forced: bool) -> io::Result<()> {
+        let frame_images = std::mem::take(&mut self.frame_images);
+        let mut removed = self
+            .kitty_images
+            .keys()
+            .copied()
+            .filter(|&id| frame_images.iter().all(|&(frame_id, _)| frame_id != id))
+            .collect::<Vec<_>>();
+        removed.sort_unstable();
+        for id in removed {
+            self.kitty_images.remove(&id);
+            self.sink.write_all(kitty::delete(id).as_bytes())?;
+        }
+
+        for (id, cells) in frame_images {
+            let state = self.kitty_images.get_mut(&id).unwrap();
+            if std::mem::take(&mut state.changed) || forced {
+                // replaces the old data and removes the old placement
+                self.sink.write_all(kitty::delete(id).as_bytes())?;
+                self.sink.write_all(state.transmit.as_bytes())?;
+                state.placement = None;
+                kitty::mark_images_shown();
+            }
+            if state.placement != Some(cells) {
+                Self::queue_move_to(&mut self.sink, self.inline, cells.x, cells.y)?;
+                let place = kitty::place(id, cells.width, cells.height);
+                self.sink.write_all(place.as_bytes())?;
+                state.placement = Some(cells);
+            }
+        }
+        Ok(())
+    }
+
+    /// Decides which sixel images are drawn with this flush, and makes the cell diff write the
+    /// cells that images moved away from, so that no stale pixels are left.
+    fn prepare_sixels(&mut self, render_everything: bool) {
+        let mut stale = vec![];
+        let frame_sixels = &self.frame_sixels;
+        self.sixel_images.retain(|&id, state| {
+            let cells = frame_sixels
+                .iter()
+                .find(|&&(frame_id, ..)| frame_id == id)
+                .map(|&(_, cells, _)| cells);
+            if state.placement.is_some() && state.placement != cells {
+                stale.extend(state.placement.take());
+            }
+            cells.is_some()
+        });
+        for cells in stale {
+            for y in cells.y..(cells.y + cells.height).min(self.height) {
+                for x in cells.x..(cells.x + cells.width).min(self.width) {
+                    self.prev_display[(x, y)] = Pixel::default().with_color([1, 2, 3]);
+                    self.dirty_tiles.mark(x, y);
+                }
+            }
+        }
+
+        for &(id, cells, _) in &self.frame_sixels {
+            let state = self.sixel_images.get_mut(&id).unwrap();
+            // writing a cell of the image clears the image in it
+            let written = (cells.y..cells.y + cells.height).any(|y| {
+                (cells.x..cells.x + cells.width)
+                    .any(|x| self.display[(x, y)] != self.prev_display[(x, y)])
+            });
+            state.redraw =
+                render_everything || state.changed || state.placement.is_none() || written;
+        }
+    }
+
+    /// Draws the sixel images that need it, each followed by the pixels above it.
+    fn flush_sixels(&mut self) -> io::Result<()> {
+        for (id, cells, depth) in std::mem::take(&mut self.frame_sixels) {
+            let state = self.sixel_images.get_mut(&id).unwrap();
+            if !std::mem::take(&mut state.redraw) {
+                continue;
+            }
+            state.changed = false;
+            state.placement = Some(cells);
+            Self::queue_move_to(&mut self.sink, self.inline, cells.x, cells.y)?;
+            // leaves the cursor next to the image instead of below it, so that an image in the
+            // last row does not scroll the screen
+            self.sink.write_all(b"\x1b[?8452h")?;
+            self.sink.write_all(state.sixel.as_bytes())?;
+            self.sink.write_all(b"\x1b[?8452l")?;
+
+            for y in cells.y..cells.y + cells.height {
+                for x in cells.x..cells.x + cells.width {
+                    let pixel = self.display[(x, y)];
+                    if pixel.c == Pixel::WIDE_CONTINUATION {
+                        // the wide character starts left of the image
+                        if x == cells.x && x > 0 {
+                            self.write_cell(x - 1, y)?;
+                        }
+                        continue;
+                    }
+                    if self.depth_buffer[(x, y)] != depth || pixel != Self::image_cell() {
+                        self.write_cell(x, y)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the cell at `(x, y)` with its colors and attributes, independent of what was
+    /// written before.
+    fn write_cell(&mut self, x: usize, y: usize) -> io::Result<()> {
+        let pixel = self.display[(x, y)];
+        Self::queue_move_to(&mut self.sink, self.inline, x, y)?;
+        let color = self.terminal_color(pixel.color, self.default_fg_color);
+        let bg_color = self.terminal_color(pixel.bg_color, self.default_bg_color);
+        Self::queue_colors(&mut self.sink, Some(color), Some(bg_color))?;
+        Self::queue_attributes(&mut self.sink, Attributes::NONE, pixel.attributes)?;
+        queue!(self.sink, crossterm::style::Print(pixel.c))?;
+        Self::queue_attributes(&mut self.sink, pixel.attributes, Attributes::NONE)
+    }
+
+    /// Queues a raw escape sequence, e.g. a clipboard write, that is written with the next flush,
+    /// after the frame.
+    pub fn queue_escape(&mut self, sequence: &str) {
+        self.escapes.push_str(sequence);
     }
 
     /// Flushes the contents of the display buffer to the terminal.
     ///
     /// This function iterates through the `display` buffer and writes the changes
     /// to the terminal output using `crossterm`. It optimizes updates by only
-    /// redrawing pixels that have changed since the last `flush()`.
+    /// redrawing pixels that have changed since the last `flush()`, and by only comparing the
+    /// tiles of the screen that were rendered to in this or the previous frame.
     pub fn flush(&mut self) -> io::Result<()> {
         // queue!(self.sink, crossterm::terminal::BeginSynchronizedUpdate)?;
-        queue!(self.sink, crossterm::cursor::MoveTo(0, 0))?;
+        Self::queue_move_to(&mut self.sink, self.inline, 0, 0)?;
+        self.apply_overlay();
 
+        let forced = std::mem::take(&mut self.post_processes_changed)
+            | std::mem::take(&mut self.force_redraw);
         let render_everything = self.last_bg_color != self.default_bg_color
-            || self.last_fg_color != self.default_fg_color;
+            || self.last_fg_color != self.default_fg_color
+            || forced;
+        // overlays change cells that were not rendered to
+        let overlay_applied = self.overlay.is_some();
+        let overlay_flushed = std::mem::replace(&mut self.overlay_flushed, overlay_applied);
+        let compare_everything = render_everything || overlay_applied || overlay_flushed;
+        self.prepare_sixels(render_everything);
 
-        self.last_fg_color = self.default_fg_color;
-        self.last_bg_color = self.default_bg_color;
+        // the post-processed colors that are currently set in the terminal
+        let mut last_fg_color = self.terminal_color(Color::Default, self.default_fg_color);
+        let mut last_bg_color = self.terminal_color(Color::Default, self.default_bg_color);
+        // the reset clears attributes that are left from other output
+        let mut last_attributes = Attributes::NONE;
         queue!(
             self.sink,
-            crossterm::style::SetColors(crossterm::style::Colors {
-                foreground: Some(crossterm::style::Color::Rgb {
-                    r: self.default_fg_color[0],
-                    g: self.default_fg_color[1],
-                    b: self.default_fg_color[2],
-                }),
-                background: Some(crossterm::style::Color::Rgb {
-                    r: self.default_bg_color[0],
-                    g: self.default_bg_color[1],
-                    b: self.default_bg_color[2],
-                }),
-            }),
+            crossterm::style::SetAttribute(crossterm::style::Attribute::Reset)
         )?;
+        Self::queue_colors(&mut self.sink, Some(last_fg_color), Some(last_bg_color))?;
 
+        self.changed_cells.clear();
         let mut curr_pos = (0, 0);
         for y in 0..self.height {
-            for x in 0..self.width {
-                let pixel = self.display[(x, y)];
-                if !render_everything {
-                    if pixel == self.prev_display[(x, y)] {
+            for span in self
+                .dirty_tiles
+                .dirty_spans(y, self.width, compare_everything)
+            {
+                for x in span {
+                    let pixel = self.display[(x, y)];
+                    // written together with the wide character to its left
+                    if pixel.c == Pixel::WIDE_CONTINUATION {
                         continue;
                     }
-                    if curr_pos != (x, y) {
-                        queue!(self.sink, crossterm::cursor::MoveTo(x as u16, y as u16))?;
+                    let wide = pixel.width() == 2 && x + 1 < self.width;
+                    if !render_everything {
+                        let unchanged = |x| self.display[(x, y)] == self.prev_display[(x, y)];
+                        // a wide character also changes with its second half
+                        if unchanged(x) && (!wide || unchanged(x + 1)) {
+                            continue;
+                        }
+                        if curr_pos != (x, y) {
+                            // attributes must not bleed into the cells in between
+                            Self::queue_attributes(
+                                &mut self.sink,
+                                last_attributes,
+                                Attributes::NONE,
+                            )?;
+                            last_attributes = Attributes::NONE;
+                            Self::queue_move_to(&mut self.sink, self.inline, x, y)?;
+                        }
                     }
+                    self.changed_cells.push((x, y));
+                    if wide {
+                        self.changed_cells.push((x + 1, y));
+                    }
+                    let mut new_color_change = None;
+                    let mut new_bg_color_change = None;
+                    let new_color = self.terminal_color(pixel.color, self.default_fg_color);
+                    if new_color != last_fg_color {
+                        new_color_change = Some(new_color);
+                        last_fg_color = new_color;
+                    }
+                    let new_bg_color = self.terminal_color(pixel.bg_color, self.default_bg_color);
+                    if new_bg_color != last_bg_color {
+                        new_bg_color_change = Some(new_bg_color);
+                        last_bg_color = new_bg_color;
+                    }
+                    // optimize color changes by combining into a single SetColors
+                    Self::queue_colors(&mut self.sink, new_color_change, new_bg_color_change)?;
+                    Self::queue_attributes(&mut self.sink, last_attributes, pixel.attributes)?;
+                    last_attributes = pixel.attributes;
+                    queue!(self.sink, crossterm::style::Print(pixel.c))?;
+                    // the terminal's cursor advances by the width of the character
+                    curr_pos = (x + 1 + wide as usize, y);
                 }
-                let mut new_color_change = None;
-                let mut new_bg_color_change = None;
-                let new_color = pixel.color.unwrap_or(self.default_fg_color);
-                if new_color != self.last_fg_color {
-                    new_color_change = Some(crossterm::style::Color::Rgb {
-                        r: new_color[0],
-                        g: new_color[1],
-                        b: new_color[2],
-                    });
-                    self.last_fg_color = new_color;
-                }
-                let new_bg_color = pixel.bg_color.unwrap_or(self.default_bg_color);
-                if new_bg_color != self.last_bg_color {
-                    new_bg_color_change = Some(crossterm::style::Color::Rgb {
-                        r: new_bg_color[0],
-                        g: new_bg_color[1],
-                        b: new_bg_color[2],
-                    });
-                    self.last_bg_color = new_bg_color;
-                }
-                // optimize color changes by combining into a single SetColors. If both are None, this is a noop.
-                queue!(
-                    self.sink,
-                    crossterm::style::SetColors(crossterm::style::Colors {
-                        foreground: new_color_change,
-                        background: new_bg_color_change,
-                    })
-                )?;
-                queue!(self.sink, crossterm::style::Print(pixel.c))?;
-                curr_pos = (x, y);
             }
             if y < self.height - 1 {
+                Self::queue_attributes(&mut self.sink, last_attributes, Attributes::NONE)?;
+                last_attributes = Attributes::NONE;
                 queue!(self.sink, crossterm::cursor::MoveToNextLine(1))?;
                 curr_pos = (0, y + 1);
             }
         }
 
+        Self::queue_attributes(&mut self.sink, last_attributes, Attributes::NONE)?;
+        self.flush_images(forced)?;
+        self.flush_sixels()?;
         // queue!(self.sink, crossterm::terminal::EndSynchronizedUpdate)?;
-        
+
+        if !self.escapes.is_empty() {
+            self.sink.write_all(self.escapes.as_bytes())?;
+            self.escapes.clear();
+        }
+
         self.sink.flush()?;
         std::mem::swap(&mut self.display, &mut self.prev_display);
+        if self.flushed_post_processes != self.post_processes {
+            self.flushed_post_processes = self.post_processes.clone();
+        }
 
         // We're "abusing" these fields to compute on next flush whether the defaults have changed.
         // If the defaults did indeed change across calls, our 'prev_display' is essentially invalidated,
@@ -335,3 +1577,536 @@ impl<W: Write> DisplayRenderer<W> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rendering::render::{HalfBlockDisplayRender, Render};
+
+    fn flush_output(renderer: &mut DisplayRenderer<Vec<u8>>) -> String {
+        renderer.flush().unwrap();
+        renderer.reset_screen();
+        String::from_utf8(std::mem::take(&mut renderer.sink)).unwrap()
+    }
+
+    #[test]
+    fn test_inline_escape_sequences() {
+        let mut renderer = DisplayRenderer::new_with_sink(4, 3, vec![]);
+        renderer.begin_inline().unwrap();
+        // two newlines make room for three rows, then the strip's start is saved
+        assert_eq!(
+            String::from_utf8(std::mem::take(&mut renderer.sink)).unwrap(),
+            "\n\n\x1b[2A\x1b[1G\x1b7"
+        );
+
+        renderer.render_pixel(1, 2, Pixel::new('x'), 0);
+        let output = flush_output(&mut renderer);
+        assert!(output.starts_with("\x1b8"));
+        assert!(output.contains('x'));
+        // absolute positions would escape the strip
+        assert!(!output.contains(";1H"));
+
+        renderer.render_pixel(1, 2, Pixel::new('y'), 0);
+        let output = flush_output(&mut renderer);
+        // the only changed cell is reached relative to the strip's start
+        assert!(output.contains("\x1b8\x1b[2B\x1b[2Gy"));
+        assert!(!output.contains(";1H"));
+
+        renderer.end_inline().unwrap();
+        assert!(
+            String::from_utf8(std::mem::take(&mut renderer.sink))
+                .unwrap()
+                .ends_with("\x1b8\x1b[J")
+        );
+        assert!(!renderer.is_inline());
+        // ending twice writes nothing
+        renderer.end_inline().unwrap();
+        assert!(renderer.sink.is_empty());
+    }
+
+    #[test]
+    fn test_post_processes_apply_on_flush() {
+        let mut renderer = DisplayRenderer::new_with_sink(2, 1, vec![]);
+        let red = Pixel::new('x').with_color([255, 0, 0]);
+        renderer.render_pixel(0, 0, red, 0);
+        assert!(flush_output(&mut renderer).contains("38;2;255;0;0m"));
+
+        // the unchanged frame is redrawn with the simulated colors
+        renderer.render_pixel(0, 0, red, 0);
+        renderer.set_post_processes(vec![PostProcess::simulate_cvd(
+            ColorVisionDeficiency::Protanopia,
+        )]);
+        let output = flush_output(&mut renderer);
+        assert!(output.contains("38;2;109;95;0m"));
+        assert!(!output.contains("38;2;255;0;0m"));
+
+        renderer.render_pixel(0, 0, red, 0);
+        assert!(!flush_output(&mut renderer).contains('x'));
+    }
+
+    #[test]
+    fn test_previous_frame_matches_flush() {
+        let mut renderer = DisplayRenderer::new_with_sink(3, 2, vec![]);
+        assert!(Renderer::previous_frame(&renderer).is_some());
+        let red = Pixel::new('x').with_color([255, 0, 0]);
+        let blue = Pixel::new('y').with_bg_color([0, 0, 255]);
+        renderer.render_pixel(0, 0, red, 0);
+        renderer.render_pixel(2, 1, blue, 0);
+        flush_output(&mut renderer);
+        // the first flush draws everything
+        assert_eq!(
+            renderer.previous_frame().changed_cells_last_flush().count(),
+            6
+        );
+
+        renderer.render_pixel(0, 0, red, 0);
+        renderer.render_pixel(1, 1, blue, 0);
+        renderer.set_post_processes(vec![PostProcess::simulate_cvd(
+            ColorVisionDeficiency::Protanopia,
+        )]);
+        flush_output(&mut renderer);
+        let frame = renderer.previous_frame();
+        assert_eq!(
+            frame.pixel_at(0, 0),
+            Pixel::new('x').with_color([109, 95, 0])
+        );
+        assert_eq!(
+            frame.pixel_at(1, 1),
+            Pixel::new('y').with_bg_color([0, 89, 255])
+        );
+        assert_eq!(frame.pixel_at(2, 1), Pixel::default());
+        assert_eq!(frame.iter().filter(|(.., p)| p.c != ' ').count(), 2);
+
+        // only the changed cells are written
+        renderer.render_pixel(0, 0, red, 0);
+        renderer.render_pixel(1, 1, blue, 0);
+        renderer.render_pixel(1, 0, red, 0);
+        flush_output(&mut renderer);
+        let frame = renderer.previous_frame();
+        assert_eq!(
+            frame.changed_cells_last_flush().collect::<Vec<_>>(),
+            vec![(1, 0)]
+        );
+        assert_eq!(
+            frame.pixel_at(1, 0),
+            Pixel::new('x').with_color([109, 95, 0])
+        );
+    }
+
+    #[test]
+    fn test_escapes_are_written_after_the_frame() {
+        let mut renderer = DisplayRenderer::new_with_sink(2, 1, vec![]);
+        renderer.render_pixel(1, 0, Pixel::new('x'), 0);
+        renderer.queue_escape(&crate::util::clipboard::osc52("hello"));
+        let output = flush_output(&mut renderer);
+        assert!(output.ends_with("x\x1b]52;c;aGVsbG8=\x07"));
+        assert!(!flush_output(&mut renderer).contains("\x1b]52"));
+    }
+
+    #[test]
+    fn test_overlay_repaints_only_on_change() {
+        let mut renderer = DisplayRenderer::new_with_sink(3, 2, vec![]);
+        let red = Pixel::new('x').with_color([200, 0, 0]);
+        let flush_frame = |renderer: &mut DisplayRenderer<Vec<u8>>| {
+            for (x, y) in [(0, 0), (1, 0), (2, 1)] {
+                renderer.render_pixel(x, y, red, 0);
+            }
+            // a dialog above the overlay
+            renderer.render_pixel(1, 1, Pixel::new('d'), 10);
+            flush_output(renderer);
+            renderer.previous_frame().changed_cells_last_flush().count()
+        };
+        assert_eq!(flush_frame(&mut renderer), 6);
+        assert_eq!(flush_frame(&mut renderer), 0);
+
+        renderer.set_overlay(Some(Overlay::dim(0.5).below(10)));
+        assert_eq!(flush_frame(&mut renderer), 5);
+        let frame = renderer.previous_frame();
+        assert_eq!(
+            frame.pixel_at(0, 0),
+            Pixel::new('x')
+                .with_color([100, 0, 0])
+                .with_bg_color([0, 0, 0])
+        );
+        assert_eq!(frame.pixel_at(1, 1), Pixel::new('d'));
+        // steady state
+        assert_eq!(flush_frame(&mut renderer), 0);
+        assert_eq!(flush_frame(&mut renderer), 0);
+
+        renderer.set_overlay(None);
+        assert_eq!(flush_frame(&mut renderer), 5);
+        assert_eq!(flush_frame(&mut renderer), 0);
+    }
+
+    #[test]
+    fn test_overlay_effects() {
+        let gray = [200, 200, 200];
+        assert_eq!(
+            Overlay::tint([0, 0, 100], 0.5).apply(gray, 0, 0, 1, 1),
+            [100, 100, 150]
+        );
+        let vignette = Overlay::vignette(1.0);
+        // the center is unchanged, the corners are darkest
+        assert_eq!(vignette.apply(gray, 5, 5, 11, 11), gray);
+        let corner = vignette.apply(gray, 0, 0, 11, 11);
+        assert!(corner[0] < vignette.apply(gray, 0, 5, 11, 11)[0]);
+        assert!(corner[0] < 40);
+    }
+
+    #[test]
+    fn test_clip_stack() {
+        let mut renderer = DisplayRenderer::new_with_sink(6, 4, vec![]);
+        let fill = |renderer: &mut DisplayRenderer<Vec<u8>>| {
+            for y in 0..4 {
+                for x in 0..6 {
+                    renderer.render_pixel(x, y, Pixel::new('x'), 0);
+                }
+            }
+        };
+        let drawn = |renderer: &mut DisplayRenderer<Vec<u8>>| {
+            renderer.flush().unwrap();
+            renderer.reset_screen();
+            let frame = renderer.previous_frame();
+            frame
+                .iter()
+                .filter(|(.., p)| p.c == 'x')
+                .map(|(x, y, _)| (x, y))
+                .collect::<Vec<_>>()
+        };
+
+        // nested clips intersect
+        renderer.push_clip(1, 1, 3, 3);
+        renderer.push_clip(2, 0, 4, 2);
+        assert_eq!(renderer.clip(), Some(ClipRect::new(2, 1, 2, 1)));
+        fill(&mut renderer);
+        renderer.pop_clip();
+        assert_eq!(renderer.clip(), Some(ClipRect::new(1, 1, 3, 3)));
+        renderer.pop_clip();
+        assert_eq!(drawn(&mut renderer), vec![(2, 1), (3, 1)]);
+
+        // clips that do not intersect draw nothing
+        renderer.push_clip(0, 0, 2, 2);
+        renderer.push_clip(3, 2, 2, 2);
+        assert!(renderer.clip().unwrap().is_empty());
+        fill(&mut renderer);
+        renderer.pop_clip();
+        renderer.pop_clip();
+        assert_eq!(drawn(&mut renderer), vec![]);
+
+        // offset renderers clip and draw in local coordinates
+        let dyn_renderer: &mut dyn Renderer = &mut renderer;
+        let mut window = dyn_renderer.with_offset(-1, 2);
+        window.push_clip(0, 0, 3, 5);
+        // the part left of the screen is cut off
+        assert_eq!(window.clip(), Some(ClipRect::new(1, 0, 2, 5)));
+        window.render_pixel(0, 0, Pixel::new('x'), 0);
+        window.render_pixel(2, 1, Pixel::new('x'), 0);
+        window.render_pixel(3, 1, Pixel::new('x'), 0);
+        window.pop_clip();
+        assert_eq!(drawn(&mut renderer), vec![(1, 3)]);
+    }
+
+    #[test]
+    fn test_transparent_compositing_and_depths() {
+        let mut renderer = DisplayRenderer::new_with_sink(4, 1, vec![]);
+        let red = [255, 0, 0];
+        // a background layer with glyphs
+        for x in 0..4 {
+            renderer.render_pixel(x, 0, Pixel::new('.').with_bg_color(red), 10);
+        }
+        // a panel with transparent padding above it
+        renderer.render_pixel(0, 0, Pixel::new('a'), 50);
+        renderer.render_pixel(1, 0, Pixel::new(' '), 50);
+        renderer.render_pixel(2, 0, Pixel::transparent(), 50);
+        renderer.render_pixel(3, 0, Pixel::new(' ').with_underline(), 50);
+        // at the same depth, the first pixel wins
+        renderer.render_pixel(0, 0, Pixel::new('b'), 50);
+        // below the panel, behind everything
+        renderer.render_pixel(0, 0, Pixel::new('c').with_bg_color([0, 0, 255]), 0);
+
+        assert_eq!(renderer.depth_at(0, 0), 50);
+        // transparent padding does not cover anything
+        assert_eq!(renderer.depth_at(1, 0), 10);
+        assert_eq!(renderer.depth_at(2, 0), 10);
+        assert_eq!(renderer.depth_at(3, 0), 50);
+        assert_eq!(renderer.depth_at(4, 0), i32::MIN);
+        let dyn_renderer: &mut dyn Renderer = &mut renderer;
+        assert_eq!(dyn_renderer.with_offset(1, 0).depth_at(2, 0), 50);
+
+        renderer.flush().unwrap();
+        let frame = renderer.previous_frame();
+        // the panel's character over the background of the layer below
+        assert_eq!(frame.pixel_at(0, 0), Pixel::new('a').with_bg_color(red));
+        assert_eq!(frame.pixel_at(1, 0), Pixel::new('.').with_bg_color(red));
+        assert_eq!(frame.pixel_at(2, 0), Pixel::new('.').with_bg_color(red));
+        assert_eq!(
+            frame.pixel_at(3, 0),
+            Pixel::new(' ').with_underline().with_bg_color(red)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "push_clip without a matching pop_clip")]
+    fn test_unbalanced_clip_is_detected() {
+        let mut renderer = DisplayRenderer::new_with_sink(2, 2, vec![]);
+        renderer.push_clip(0, 0, 1, 1);
+        renderer.reset_screen();
+    }
+
+    #[test]
+    fn test_dirty_tiles_follow_rendering() {
+        let mut renderer = DisplayRenderer::new_with_sink(40, 20, vec![]);
+        // the first frames compare everything
+        flush_output(&mut renderer);
+        flush_output(&mut renderer);
+        let dirty = |renderer: &DisplayRenderer<Vec<u8>>| {
+            (0..20)
+                .flat_map(|y| {
+                    renderer
+                        .dirty_tiles
+                        .dirty_spans(y, 40, false)
+                        .flatten()
+                        .map(move |x| (x, y))
+                })
+                .count()
+        };
+        assert_eq!(dirty(&renderer), 0);
+
+        renderer.render_pixel(20, 10, Pixel::new('x'), 0);
+        assert_eq!(dirty(&renderer), 16 * 8);
+        assert!(flush_output(&mut renderer).contains('x'));
+        // the reset keeps the tile dirty, so the pixel is erased
+        assert_eq!(dirty(&renderer), 16 * 8);
+        let output = flush_output(&mut renderer);
+        assert!(!output.contains('x') && output.contains(' '));
+        assert_eq!(renderer.previous_frame().pixel_at(20, 10), Pixel::default());
+        assert_eq!(dirty(&renderer), 0);
+    }
+
+    #[test]
+    fn test_half_block_blit_with_dirty_tiles() {
+        let (width, height) = (37, 11);
+        let mut renderer = DisplayRenderer::new_with_sink(width, height, vec![]);
+        let mut hbd = HalfBlockDisplayRender::new(width, 2 * height);
+        let color =
+            |x: usize, y: usize, frame: usize| Color::Rgb([(x * 5 + frame) as u8, y as u8, 0]);
+        for frame in 0..4 {
+            for y in 0..2 * height {
+                for x in 0..width {
+                    // only the left half changes
+                    let frame = if x < width / 2 { frame } else { 0 };
+                    hbd.set_color(x, y, color(x, y, frame));
+                }
+            }
+            hbd.render(&mut renderer, 0, 0, 0);
+            flush_output(&mut renderer);
+            let shown = renderer.previous_frame();
+            for y in 0..height {
+                for x in 0..width {
+                    let frame = if x < width / 2 { frame } else { 0 };
+                    let pixel = shown.pixel_at(x, y);
+                    assert_eq!(pixel.color, color(x, 2 * y, frame), "({x}, {y})");
+                    assert_eq!(pixel.bg_color, color(x, 2 * y + 1, frame), "({x}, {y})");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_wide_chars_are_diffed_as_one_glyph() {
+        let mut renderer = DisplayRenderer::new_with_sink(10, 1, vec![]);
+        let row = |renderer: &DisplayRenderer<Vec<u8>>| {
+            let frame = renderer.previous_frame();
+            (0..10).map(|x| frame.pixel_at(x, 0).c).collect::<String>()
+        };
+
+        "日本語abc".render(&mut renderer, 0, 0, 0);
+        let output = flush_output(&mut renderer);
+        assert!(output.contains("日本語abc"));
+        assert!(!output.contains(Pixel::WIDE_CONTINUATION));
+        assert_eq!(row(&renderer), "日\0本\0語\0abc ");
+        assert_eq!(renderer.capture_frame().to_plain_text(), "日本語abc \n");
+
+        // an unchanged frame writes nothing
+        "日本語abc".render(&mut renderer, 0, 0, 0);
+        let output = flush_output(&mut renderer);
+        assert!(!output.contains(['日', '本', '語', 'a', 'b', 'c']));
+
+        // covering the second half clears the whole glyph
+        "日本語abc".render(&mut renderer, 0, 0, 0);
+        renderer.render_pixel(1, 0, Pixel::new('x'), 1);
+        let output = flush_output(&mut renderer);
+        assert!(output.contains(" x") && !output.contains('本'));
+        assert_eq!(row(&renderer), " x本\0語\0abc ");
+
+        // zero-width joiners are dropped, and wide chars do not fit into the last column
+        "👩\u{200d}🔬!".render(&mut renderer, 0, 0, 0);
+        "日".render(&mut renderer, 9, 0, 0);
+        flush_output(&mut renderer);
+        assert_eq!(row(&renderer), "👩\0🔬\0!     ");
+    }
+
+    #[test]
+    fn test_attributes_are_diffed() {
+        let mut renderer = DisplayRenderer::new_with_sink(8, 2, vec![]);
+        flush_output(&mut renderer);
+
+        "ab".with_bold().render(&mut renderer, 0, 0, 0);
+        "c".with_underline().render(&mut renderer, 5, 0, 0);
+        let output = flush_output(&mut renderer);
+        // bold is set once for both cells, and turned off before moving on
+        assert!(output.contains("\x1b[1mab\x1b[22m\x1b[1;6H\x1b[4mc\x1b[24m"));
+        assert_eq!(
+            renderer.previous_frame().pixel_at(1, 0),
+            Pixel::new('b').with_bold()
+        );
+
+        // an unchanged frame writes nothing
+        "ab".with_bold().render(&mut renderer, 0, 0, 0);
+        "c".with_underline().render(&mut renderer, 5, 0, 0);
+        assert!(!flush_output(&mut renderer).contains(['a', 'b', 'c']));
+
+        // losing an attribute is a change
+        "ab".with_bold().render(&mut renderer, 0, 0, 0);
+        "c".render(&mut renderer, 5, 0, 0);
+        let output = flush_output(&mut renderer);
+        assert!(output.contains("\x1b[1;6Hc") && !output.contains(['a', 'b']));
+        // attributes are reset at the start of every flush
+        assert!(output.starts_with("\x1b[1;1H\x1b[0m"));
+    }
+
+    #[test]
+    fn test_color_modes() {
+        let mut renderer = DisplayRenderer::new_with_sink(3, 1, vec![]);
+        renderer.set_color_mode(ColorMode::Ansi256);
+        let red = Pixel::new('a').with_color([255, 0, 0]);
+        renderer.render_pixel(0, 0, red, 0);
+        renderer.render_pixel(1, 0, red.with_bg_color([118, 118, 118]), 0);
+        let green = Pixel {
+            color: Color::Ansi(2),
+            ..Pixel::new('c')
+        };
+        renderer.render_pixel(2, 0, green, 0);
+        let output = flush_output(&mut renderer);
+        // the default colors are quantized as well, basic colors use their own codes
+        assert!(output.contains("\x1b[38;5;231;48;5;16m"));
+        assert!(output.contains("\x1b[38;5;196ma\x1b[48;5;243ma\x1b[32m\x1b[48;5;16mc"));
+
+        renderer.set_color_mode(ColorMode::Ansi16);
+        renderer.render_pixel(0, 0, red, 0);
+        let output = flush_output(&mut renderer);
+        assert!(output.contains("\x1b[97m\x1b[40m"));
+        assert!(output.contains("\x1b[91ma"));
+    }
+
+    #[test]
+    fn test_kitty_images_are_sent_moved_and_deleted() {
+        use crate::rendering::render::Render;
+
+        let mut renderer = DisplayRenderer::new_with_sink(4, 3, vec![]);
+        let mut image = KittyImageRender::new(2, 4);
+        image.set_pixel(0, 0, [255, 0, 0, 255]);
+        let id = image.id();
+        // without the protocol, the image is drawn with half blocks
+        image.render(&mut renderer, 1, 1, 0);
+        let output = flush_output(&mut renderer);
+        assert!(!output.contains("\x1b_G"));
+        assert_eq!(renderer.previous_frame().pixel_at(1, 1).c, '▀');
+
+        renderer.set_kitty_graphics(true);
+        renderer.render_pixel(2, 2, Pixel::new('b'), -1);
+        renderer.render_pixel(1, 1, Pixel::new('a'), 1);
+        image.render(&mut renderer, 1, 1, 0);
+        let output = flush_output(&mut renderer);
+        assert!(output.contains(&format!("a=t,f=32,s=2,v=4,i={id},")));
+        assert!(output.contains(&format!("a=p,i={id},p=1,c=2,r=2,")));
+        // the cells of the image are cleared, except for the pixels above it
+        let frame = renderer.previous_frame();
+        assert_eq!(frame.pixel_at(1, 1).c, 'a');
+        assert_eq!(frame.pixel_at(2, 2).c, ' ');
+
+        // unchanged images are not sent again
+        image.render(&mut renderer, 1, 1, 0);
+        assert!(!flush_output(&mut renderer).contains("\x1b_G"));
+
+        // moved images are only placed again
+        image.render(&mut renderer, 2, 1, 0);
+        let output = flush_output(&mut renderer);
+        assert!(!output.contains("a=t"));
+        assert!(output.contains(&format!("a=p,i={id},")));
+
+        // changed images replace the old data
+        image.set_pixel(1, 3, [0, 0, 255, 255]);
+        image.render(&mut renderer, 2, 1, 0);
+        let output = flush_output(&mut renderer);
+        let delete = kitty::delete(id);
+        assert!(output.find(&delete).unwrap() < output.find("a=t").unwrap());
+
+        // images that are not rendered are deleted, once
+        assert!(flush_output(&mut renderer).contains(&delete));
+        assert!(!flush_output(&mut renderer).contains("\x1b_G"));
+
+        // images outside of the screen are drawn with half blocks
+        image.render(&mut renderer, 3, 2, 0);
+        let output = flush_output(&mut renderer);
+        assert!(!output.contains("\x1b_G"));
+        assert_eq!(renderer.previous_frame().pixel_at(3, 2).c, '▀');
+    }
+
+    #[test]
+    fn test_sixel_images_keep_the_text_around_them() {
+        use crate::rendering::render::Render;
+
+        /// Renders text, a pixel above the image and the image at `x`, and returns the output and
+        /// the written cells.
+        fn frame(
+            renderer: &mut DisplayRenderer<Vec<u8>>,
+            x: usize,
+            above: char,
+            image: Option<&SixelRender>,
+        ) -> (String, Vec<(usize, usize)>) {
+            "ab".render(renderer, 0, 0, 0);
+            renderer.render_pixel(x, 1, Pixel::new(above), 5);
+            if let Some(image) = image {
+                image.render(renderer, x, 1, 0);
+            }
+            let output = flush_output(renderer);
+            let changed = renderer.previous_frame().changed_cells_last_flush().collect();
+            (output, changed)
+        }
+
+        let mut renderer = DisplayRenderer::new_with_sink(6, 3, vec![]);
+        // 2x1 cells
+        let image = SixelRender::new(2, 2);
+        // without a cell size, the image is drawn with half blocks
+        let (output, _) = frame(&mut renderer, 1, 't', Some(&image));
+        assert!(!output.contains("\x1bP"));
+        assert_eq!(renderer.previous_frame().pixel_at(2, 1).c, '█');
+
+        renderer.set_sixel_cell_size(Some((2, 4)));
+        // scaled to 2x1 cells of 2x4 pixels, and the pixel above the image is written after it
+        let (output, _) = frame(&mut renderer, 1, 't', Some(&image));
+        let sixel = output.find("\x1bP0;1;0q\"1;1;4;4").unwrap();
+        assert!(output.rfind('t').unwrap() > sixel);
+
+        // unchanged images are not drawn again
+        let (output, _) = frame(&mut renderer, 1, 't', Some(&image));
+        assert!(!output.contains("\x1bP"));
+
+        // writing a cell of the image draws it again
+        let (output, _) = frame(&mut renderer, 1, 'u', Some(&image));
+        assert!(output.rfind('u').unwrap() > output.find("\x1bP").unwrap());
+
+        // the cells the image moved away from are written again
+        let (output, changed) = frame(&mut renderer, 3, 'u', Some(&image));
+        assert!(output.contains("\x1bP"));
+        assert!(changed.contains(&(1, 1)) && changed.contains(&(2, 1)));
+
+        // as are the cells of removed images
+        let (output, changed) = frame(&mut renderer, 5, 'u', None);
+        assert!(!output.contains("\x1bP"));
+        assert!(changed.contains(&(3, 1)) && changed.contains(&(4, 1)));
+        // the text around the image is unchanged
+        assert!(!changed.contains(&(0, 0)));
+    }
+}
@@ -0,0 +1,215 @@
+//! Images with sixel graphics, see [`SixelRender`](crate::rendering::render::SixelRender).
+//!
+//! Sixels are supported by xterm (with `-ti vt340`), mlterm, foot, WezTerm and Windows Terminal,
+//! among others. Since sixel images have a size in pixels, the [`DisplayRenderer`] needs the size
+//! of a cell in pixels to fit an image into its cells, and only draws sixels once it is set with
+//! [`Game::set_sixel_cell_size`](crate::Game::set_sixel_cell_size), e.g. from [`cell_size`].
+//! Until then, images are drawn with half blocks.
+//!
+//! Unlike kitty images, sixels are drawn into the cells, and writing a cell replaces the part of
+//! the image in it. The renderer draws an image again whenever it changed, moved, or one of its
+//! cells was written, and then writes the cells of the pixels above the image, so that text around
+//! and over the image stays intact.
+//!
+//! Sixel colors come from a palette. Every pixel is quantized to a fixed palette of 252 colors,
+//! with 6 levels of red and blue and 7 levels of green, through lookup tables.
+//!
+//! [`DisplayRenderer`]: crate::rendering::renderer::DisplayRenderer
+
+use std::fmt::Write;
+use std::io;
+
+const RED_LEVELS: usize = 6;
+const GREEN_LEVELS: usize = 7;
+const BLUE_LEVELS: usize = 6;
+const PALETTE_LEN: usize = RED_LEVELS * GREEN_LEVELS * BLUE_LEVELS;
+
+/// Maps every channel value to the closest of `levels` evenly spaced levels.
+const fn level_table(levels: usize) -> [u8; 256] {
+    let mut table = [0; 256];
+    let mut value = 0;
+    while value < 256 {
+        table[value] = ((value * (levels - 1) + 127) / 255) as u8;
+        value += 1;
+    }
+    table
+}
+
+const RED_TABLE: [u8; 256] = level_table(RED_LEVELS);
+const GREEN_TABLE: [u8; 256] = level_table(GREEN_LEVELS);
+const BLUE_TABLE: [u8; 256] = level_table(BLUE_LEVELS);
+
+/// Returns the size of a cell in pixels, or `None` if the terminal does not report it.
+pub fn cell_size() -> io::Result<Option<(usize, usize)>> {
+    let size = crossterm::terminal::window_size()?;
+    if size.width == 0 || size.height == 0 || size.columns == 0 || size.rows == 0 {
+        return Ok(None);
+    }
+    Ok(Some((
+        (size.width / size.columns) as usize,
+        (size.height / size.rows) as usize,
+    )))
+}
+
+/// Returns the index of the palette color closest to `rgb`.
+fn palette_index([r, g, b]: [u8; 3]) -> u8 {
+    let red = RED_TABLE[r as usize] as usize;
+    let green = GREEN_TABLE[g as usize] as usize;
+    let blue = BLUE_TABLE[b as usize] as usize;
+    ((red * GREEN_LEVELS + green) * BLUE_LEVELS + blue) as u8
+}
+
+/// Returns the color of the palette index in percent, as sixel defines colors.
+fn palette_percent(index: usize) -> [usize; 3] {
+    let percent = |level: usize, levels: usize| (level * 100 + (levels - 1) / 2) / (levels - 1);
+    [
+        percent(index / (GREEN_LEVELS * BLUE_LEVELS), RED_LEVELS),
+        percent(index / BLUE_LEVELS % GREEN_LEVELS, GREEN_LEVELS),
+        percent(index % BLUE_LEVELS, BLUE_LEVELS),
+    ]
+}
+
+/// Returns the sixel sequence that draws the `src_width` by `src_height` RGB image `rgb`, scaled
+/// to `width` by `height` pixels by nearest sampling.
+///
+/// None of the sizes may be zero.
+pub(crate) fn encode(
+    src_width: usize,
+    src_height: usize,
+    rgb: &[u8],
+    width: usize,
+    height: usize,
+) -> String {
+    let source_x = (0..width)
+        .map(|x| x * src_width / width * 3)
+        .collect::<Vec<_>>();
+    let mut indices = vec![0u8; width * height];
+    let mut used = [false; PALETTE_LEN];
+    for (y, row) in indices.chunks_exact_mut(width).enumerate() {
+        let source_row = &rgb[y * src_height / height * src_width * 3..][..src_width * 3];
+        for (index, &x) in row.iter_mut().zip(&source_x) {
+            let pixel = &source_row[x..x + 3];
+            *index = palette_index([pixel[0], pixel[1], pixel[2]]);
+            used[*index as usize] = true;
+        }
+    }
+
+    // pixels that no color sets keep what is below them, and the raster attributes give the size
+    let mut out = format!("\x1bP0;1;0q\"1;1;{width};{height}");
+    for index in (0..PALETTE_LEN).filter(|&index| used[index]) {
+        let [r, g, b] = palette_percent(index);
+        let _ = write!(out, "#{index};2;{r};{g};{b}");
+    }
+
+    // every band of six rows is written color by color, each from the first to the last column
+    // that has the color in the band
+    let mut masks = vec![0u8; PALETTE_LEN * width];
+    let mut spans = [(usize::MAX, 0); PALETTE_LEN];
+    let mut band_colors = Vec::with_capacity(PALETTE_LEN);
+    for band_y in (0..height).step_by(6) {
+        for dy in 0..6.min(height - band_y) {
+            let row = &indices[(band_y + dy) * width..][..width];
+            for (x, &index) in row.iter().enumerate() {
+                let index = index as usize;
+                let span = &mut spans[index];
+                if span.0 == usize::MAX {
+                    *span = (x, x);
+                    band_colors.push(index);
+                } else {
+                    span.0 = span.0.min(x);
+                    span.1 = span.1.max(x);
+                }
+                masks[index * width + x] |= 1 << dy;
+            }
+        }
+
+        if band_y > 0 {
+            // next band
+            out.push('-');
+        }
+        for (n, &index) in band_colors.iter().enumerate() {
+            if n > 0 {
+                // back to the start of the band
+                out.push('$');
+            }
+            let _ = write!(out, "#{index}");
+            let (start, end) = std::mem::replace(&mut spans[index], (usize::MAX, 0));
+            push_run(&mut out, 0, start);
+            let row = &mut masks[index * width..][start..=end];
+            let mut run = (row[0], 0);
+            for &sixel in row.iter() {
+                if sixel == run.0 {
+                    run.1 += 1;
+                } else {
+                    push_run(&mut out, run.0, run.1);
+                    run = (sixel, 1);
+                }
+            }
+            push_run(&mut out, run.0, run.1);
+            row.fill(0);
+        }
+        band_colors.clear();
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+/// Writes `len` columns with the bits `sixel`, run-length encoded if shorter.
+fn push_run(out: &mut String, sixel: u8, len: usize) {
+    let c = (b'?' + sixel) as char;
+    if len > 3 {
+        let _ = write!(out, "!{len}{c}");
+    } else {
+        out.extend(std::iter::repeat_n(c, len));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palette() {
+        assert_eq!(PALETTE_LEN, 252);
+        assert_eq!(palette_index([0, 0, 0]), 0);
+        assert_eq!(palette_index([255, 255, 255]), 251);
+        for index in 0..PALETTE_LEN {
+            // every palette color maps to itself
+            let rgb = palette_percent(index).map(|percent| (percent * 255 / 100) as u8);
+            assert_eq!(palette_index(rgb) as usize, index, "{rgb:?}");
+        }
+        assert_eq!(
+            palette_percent(palette_index([255, 0, 0]) as usize),
+            [100, 0, 0]
+        );
+    }
+
+    #[test]
+    fn test_encode() {
+        let red = [255, 0, 0];
+        assert_eq!(
+            encode(1, 1, &red, 1, 1),
+            "\x1bP0;1;0q\"1;1;1;1#210;2;100;0;0#210@\x1b\\"
+        );
+        // scaled up, with a run
+        assert_eq!(
+            encode(1, 1, &red, 5, 1),
+            "\x1bP0;1;0q\"1;1;5;1#210;2;100;0;0#210!5@\x1b\\"
+        );
+
+        // a white row over a black row
+        let mut rgb = [[255; 3]; 3].concat();
+        rgb.extend([0; 9]);
+        // six black rows fill the first band, the red row starts the second
+        let mut tall = [0; 3 * 7];
+        tall[18..].copy_from_slice(&red);
+        assert_eq!(
+            encode(3, 2, &rgb, 3, 2),
+            "\x1bP0;1;0q\"1;1;3;2#0;2;0;0;0#251;2;100;100;100#251@@@$#0AAA\x1b\\"
+        );
+        assert_eq!(
+            encode(1, 7, &tall, 3, 7),
+            "\x1bP0;1;0q\"1;1;3;7#0;2;0;0;0#210;2;100;0;0#0~~~-#210@@@\x1b\\"
+        );
+    }
+}
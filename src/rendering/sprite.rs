@@ -0,0 +1,360 @@
+//! Images loaded from PNG files, for drawing into a [`HalfBlockDisplayRender`].
+//!
+//! An [`ImageSprite`] stores the RGBA pixels of an image. It is drawn with
+//! [`ImageSprite::render_to_hbd`]: fully transparent pixels are skipped, partially transparent
+//! ones are blended with what is already there, and everything outside of the buffer is clipped.
+//! ```no_run
+//! use teng::rendering::render::HalfBlockDisplayRender;
+//! use teng::rendering::sprite::{ImageSprite, SpriteSheet};
+//!
+//! let sheet = SpriteSheet::new(ImageSprite::from_file("walk.png").unwrap(), 16, 16);
+//! let mut hbd = HalfBlockDisplayRender::new(80, 48);
+//! // the third frame of the animation, partially off-screen
+//! sheet.get(2).unwrap().render_to_hbd(-4, 40, &mut hbd);
+//! ```
+//! Parts of an image are borrowed as a [`SpriteView`], e.g. the tiles of a [`SpriteSheet`] or a
+//! region from [`ImageSprite::crop`].
+
+use crate::rendering::color::Color;
+use crate::rendering::render::HalfBlockDisplayRender;
+use std::io;
+use std::path::Path;
+
+/// An RGBA image.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImageSprite {
+    width: usize,
+    height: usize,
+    /// Row by row.
+    pixels: Vec<[u8; 4]>,
+}
+
+impl ImageSprite {
+    /// Creates an image from its pixels, row by row.
+    ///
+    /// # Panics
+    /// If the number of pixels is not `width * height`.
+    pub fn from_rgba(width: usize, height: usize, pixels: Vec<[u8; 4]>) -> Self {
+        assert_eq!(pixels.len(), width * height, "wrong number of pixels");
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Decodes a PNG image.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidData`] if the bytes are not a valid PNG image.
+    pub fn from_png_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let image = image::load_from_memory_with_format(bytes, image::ImageFormat::Png)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?
+            .into_rgba8();
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let pixels = image.pixels().map(|pixel| pixel.0).collect();
+        Ok(Self::from_rgba(width, height, pixels))
+    }
+
+    /// Reads and decodes a PNG file, see [`ImageSprite::from_png_bytes`].
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_png_bytes(&std::fs::read(path)?)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the pixel at `(x, y)`, or `None` if it is outside of the image.
+    pub fn get(&self, x: usize, y: usize) -> Option<[u8; 4]> {
+        (x < self.width && y < self.height).then(|| self.pixels[y * self.width + x])
+    }
+
+    /// Mirrors the image, so that the left edge becomes the right edge.
+    pub fn flip_horizontal(&mut self) {
+        if self.width == 0 {
+            return;
+        }
+        for row in self.pixels.chunks_exact_mut(self.width) {
+            row.reverse();
+        }
+    }
+
+    /// Returns the whole image as a view.
+    pub fn view(&self) -> SpriteView<'_> {
+        SpriteView {
+            image: self,
+            x: 0,
+            y: 0,
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// Returns the `width` by `height` region at `(x, y)`, clipped to the image.
+    pub fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> SpriteView<'_> {
+        self.view().crop(x, y, width, height)
+    }
+
+    /// Draws the image with its top left corner at `(x, y)`. See [`SpriteView::render_to_hbd`].
+    pub fn render_to_hbd(&self, x: i64, y: i64, hbd: &mut HalfBlockDisplayRender) {
+        self.view().render_to_hbd(x, y, hbd);
+    }
+}
+
+/// A borrowed region of an [`ImageSprite`].
+#[derive(Clone, Copy, Debug)]
+pub struct SpriteView<'a> {
+    image: &'a ImageSprite,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+impl SpriteView<'_> {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the pixel at `(x, y)` relative to the view, or `None` if it is outside of the view.
+    pub fn get(&self, x: usize, y: usize) -> Option<[u8; 4]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.image.get(self.x + x, self.y + y)
+    }
+
+    /// Returns the `width` by `height` region at `(x, y)` relative to the view, clipped to the
+    /// view.
+    pub fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> Self {
+        let x = x.min(self.width);
+        let y = y.min(self.height);
+        Self {
+            image: self.image,
+            x: self.x + x,
+            y: self.y + y,
+            width: width.min(self.width - x),
+            height: height.min(self.height - y),
+        }
+    }
+
+    /// Copies the region into a new image.
+    pub fn to_sprite(&self) -> ImageSprite {
+        let pixels = (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .map(|(x, y)| self.get(x, y).unwrap())
+            .collect();
+        ImageSprite::from_rgba(self.width, self.height, pixels)
+    }
+
+    /// Draws the region with its top left corner at `(x, y)`, in half-block pixels.
+    ///
+    /// Pixels with an alpha of 0 are skipped, and partially transparent pixels are blended with
+    /// [`HalfBlockDisplayRender::set_color_blended`]. Pixels outside of `hbd` are clipped.
+    pub fn render_to_hbd(&self, x: i64, y: i64, hbd: &mut HalfBlockDisplayRender) {
+        self.blit(x, y, hbd, |color| color);
+    }
+
+    /// Like [`SpriteView::render_to_hbd`], but multiplies every color with `tint`. White pixels
+    /// become `tint`, black pixels stay black.
+    pub fn render_tinted_to_hbd(
+        &self,
+        x: i64,
+        y: i64,
+        tint: [u8; 3],
+        hbd: &mut HalfBlockDisplayRender,
+    ) {
+        self.blit(x, y, hbd, |color| {
+            std::array::from_fn(|i| (color[i] as u16 * tint[i] as u16 / 255) as u8)
+        });
+    }
+
+    fn blit(
+        &self,
+        x: i64,
+        y: i64,
+        hbd: &mut HalfBlockDisplayRender,
+        map: impl Fn([u8; 3]) -> [u8; 3],
+    ) {
+        // only the part of the view that overlaps the buffer
+        let start_x = (-x).clamp(0, self.width as i64) as usize;
+        let start_y = (-y).clamp(0, self.height as i64) as usize;
+        let end_x = (hbd.width() as i64 - x).clamp(0, self.width as i64) as usize;
+        let end_y = (hbd.height() as i64 - y).clamp(0, self.height as i64) as usize;
+        for dy in start_y..end_y {
+            for dx in start_x..end_x {
+                let [r, g, b, a] = self.get(dx, dy).unwrap();
+                let (hx, hy) = ((x + dx as i64) as usize, (y + dy as i64) as usize);
+                if a > 0 {
+                    hbd.set_color_blended(hx, hy, Color::Rgb(map([r, g, b])), a as f32 / 255.0);
+                }
+            }
+        }
+    }
+}
+
+/// An image of equally sized tiles, e.g. the frames of an animation.
+///
+/// Tiles are numbered row by row, starting at the top left. Partial tiles at the right and bottom
+/// edges are ignored.
+#[derive(Clone, Debug)]
+pub struct SpriteSheet {
+    image: ImageSprite,
+    tile_width: usize,
+    tile_height: usize,
+}
+
+impl SpriteSheet {
+    /// # Panics
+    /// If the tile width or height is zero.
+    pub fn new(image: ImageSprite, tile_width: usize, tile_height: usize) -> Self {
+        assert!(tile_width > 0 && tile_height > 0, "empty tiles");
+        Self {
+            image,
+            tile_width,
+            tile_height,
+        }
+    }
+
+    pub fn image(&self) -> &ImageSprite {
+        &self.image
+    }
+
+    /// Returns the number of tiles per row.
+    pub fn columns(&self) -> usize {
+        self.image.width / self.tile_width
+    }
+
+    /// Returns the number of tiles.
+    pub fn len(&self) -> usize {
+        self.columns() * (self.image.height / self.tile_height)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the tile with the given index, or `None` if there is no such tile.
+    pub fn get(&self, index: usize) -> Option<SpriteView<'_>> {
+        if index >= self.len() {
+            return None;
+        }
+        let (column, row) = (index % self.columns(), index / self.columns());
+        Some(self.image.crop(
+            column * self.tile_width,
+            row * self.tile_height,
+            self.tile_width,
+            self.tile_height,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const GREEN: [u8; 4] = [0, 255, 0, 255];
+    const CLEAR: [u8; 4] = [0, 0, 255, 0];
+    const HALF: [u8; 4] = [0, 0, 255, 128];
+
+    /// A 4x2 image: two 2x2 tiles, the second with transparent pixels.
+    fn image() -> ImageSprite {
+        ImageSprite::from_rgba(4, 2, vec![RED, GREEN, RED, CLEAR, GREEN, RED, HALF, RED])
+    }
+
+    fn encode_png(image: &ImageSprite) -> Vec<u8> {
+        let raw = image.pixels.iter().flatten().copied().collect();
+        let buffer = image::RgbaImage::from_raw(image.width as u32, image.height as u32, raw);
+        let mut bytes = io::Cursor::new(vec![]);
+        buffer
+            .unwrap()
+            .write_to(&mut bytes, image::ImageFormat::Png)
+            .unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn test_png_round_trip() {
+        let image = image();
+        let decoded = ImageSprite::from_png_bytes(&encode_png(&image)).unwrap();
+        assert_eq!(decoded, image);
+        let error = ImageSprite::from_png_bytes(b"not a png").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let error = ImageSprite::from_file("does/not/exist.png").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_views() {
+        let mut image = image();
+        let crop = image.crop(1, 1, 10, 10);
+        assert_eq!((crop.width(), crop.height()), (3, 1));
+        assert_eq!(crop.get(0, 0), Some(RED));
+        assert_eq!(crop.get(2, 0), Some(RED));
+        assert_eq!(crop.get(0, 1), None);
+        assert_eq!(crop.crop(1, 0, 1, 1).get(0, 0), Some(HALF));
+        assert_eq!(image.crop(5, 5, 1, 1).width(), 0);
+
+        let sheet = SpriteSheet::new(image.clone(), 2, 2);
+        assert_eq!(sheet.len(), 2);
+        assert_eq!(
+            sheet.get(1).unwrap().to_sprite(),
+            ImageSprite::from_rgba(2, 2, vec![RED, CLEAR, HALF, RED])
+        );
+        assert!(sheet.get(2).is_none());
+        // partial tiles are ignored
+        assert!(SpriteSheet::new(image.clone(), 3, 3).is_empty());
+
+        image.flip_horizontal();
+        assert_eq!(image.get(0, 0), Some(CLEAR));
+        assert_eq!(image.get(3, 1), Some(GREEN));
+    }
+
+    #[test]
+    fn test_render_to_hbd() {
+        let image = image();
+        let mut hbd = HalfBlockDisplayRender::new(3, 3);
+        let background = Color::Rgb([0, 0, 0]);
+        for y in 0..3 {
+            for x in 0..3 {
+                hbd.set_color(x, y, background);
+            }
+        }
+        // clipped on all sides
+        image.render_to_hbd(-1, 2, &mut hbd);
+        image.render_to_hbd(2, -1, &mut hbd);
+        let colors: Vec<_> = (0..3)
+            .flat_map(|y| (0..3).map(move |x| (x, y)))
+            .map(|(x, y)| hbd.get_color(x, y).unwrap())
+            .collect();
+        let blended = Color::Rgb([0, 0, 128]);
+        assert_eq!(
+            colors,
+            [
+                background,
+                background,
+                Color::Rgb([0, 255, 0]),
+                background,
+                background,
+                background,
+                Color::Rgb([0, 255, 0]),
+                Color::Rgb([255, 0, 0]),
+                // the transparent pixel of the second tile
+                background,
+            ]
+        );
+        let mut hbd = HalfBlockDisplayRender::new(1, 1);
+        hbd.set_color(0, 0, background);
+        image.render_to_hbd(-2, -1, &mut hbd);
+        assert_eq!(hbd.get_color(0, 0), Some(blended));
+    }
+}
@@ -0,0 +1,288 @@
+//! Large stylized text drawn from the glyphs of an image, e.g. for title screens and scores.
+//!
+//! A [`SpriteFont`] maps characters to rectangles of an [`ImageSprite`]. Fonts whose glyphs are
+//! laid out in a grid are created with [`SpriteFont::from_grid`]:
+//! ```no_run
+//! use teng::rendering::color::Color;
+//! use teng::rendering::render::HalfBlockDisplayRender;
+//! use teng::rendering::sprite::ImageSprite;
+//! use teng::rendering::spritefont::SpriteFont;
+//!
+//! // 8x12 pixels per glyph, the digits in the first row and a few symbols in the second
+//! let image = ImageSprite::from_file("digits.png").unwrap();
+//! let font = SpriteFont::from_grid(image, 8, 12, "0123456789\n+-x?").with_fallback('?');
+//! let mut hbd = HalfBlockDisplayRender::new(80, 48);
+//! let (width, _) = font.measure("+150");
+//! font.draw(&mut hbd, 40 - width as i64 / 2, 2, "+150", Some(Color::Rgb([255, 200, 0])));
+//! ```
+//! Other layouts, e.g. exported from a sprite packer, are described by a [`FontMetadata`], which
+//! can be deserialized with the `serde` feature.
+
+use crate::rendering::color::Color;
+use crate::rendering::render::HalfBlockDisplayRender;
+use crate::rendering::sprite::ImageSprite;
+use std::collections::HashMap;
+
+/// Where a glyph is in the image and how it is placed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Glyph {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    /// How far the next glyph starts to the right of this one.
+    pub advance: usize,
+    /// Moves the glyph down from the top of its line, e.g. for descenders or small punctuation.
+    pub offset_y: i64,
+}
+
+/// The glyphs, kerning and line height of a [`SpriteFont`], see [`SpriteFont::from_metadata`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FontMetadata {
+    pub line_height: usize,
+    pub glyphs: Vec<(char, Glyph)>,
+    /// Adjustments of the advance between two characters, e.g. `('A', 'V', -1)`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub kerning: Vec<(char, char, i64)>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub fallback: Option<char>,
+}
+
+/// A font made of image glyphs.
+#[derive(Clone, Debug)]
+pub struct SpriteFont {
+    image: ImageSprite,
+    glyphs: HashMap<char, Glyph>,
+    kerning: HashMap<(char, char), i64>,
+    line_height: usize,
+    fallback: Option<char>,
+}
+
+impl SpriteFont {
+    /// Creates a font without glyphs.
+    pub fn new(image: ImageSprite, line_height: usize) -> Self {
+        Self {
+            image,
+            glyphs: HashMap::new(),
+            kerning: HashMap::new(),
+            line_height,
+            fallback: None,
+        }
+    }
+
+    /// Creates a font from an image of `cell_width` x `cell_height` cells. The characters of
+    /// `charset` are assigned to the cells row by row, and a newline starts the next row.
+    pub fn from_grid(
+        image: ImageSprite,
+        cell_width: usize,
+        cell_height: usize,
+        charset: &str,
+    ) -> Self {
+        let mut font = Self::new(image, cell_height);
+        for (row, line) in charset.split('\n').enumerate() {
+            for (column, c) in line.chars().enumerate() {
+                let glyph = Glyph {
+                    x: column * cell_width,
+                    y: row * cell_height,
+                    width: cell_width,
+                    height: cell_height,
+                    advance: cell_width,
+                    offset_y: 0,
+                };
+                font.glyphs.insert(c, glyph);
+            }
+        }
+        font
+    }
+
+    pub fn from_metadata(image: ImageSprite, metadata: FontMetadata) -> Self {
+        Self {
+            image,
+            glyphs: metadata.glyphs.into_iter().collect(),
+            kerning: metadata
+                .kerning
+                .into_iter()
+                .map(|(left, right, adjust)| ((left, right), adjust))
+                .collect(),
+            line_height: metadata.line_height,
+            fallback: metadata.fallback,
+        }
+    }
+
+    /// Adds or replaces the glyph of `c`.
+    pub fn with_glyph(mut self, c: char, glyph: Glyph) -> Self {
+        self.glyphs.insert(c, glyph);
+        self
+    }
+
+    /// Adds `adjust` to the advance of `left` when it is followed by `right`.
+    pub fn with_kerning(mut self, left: char, right: char, adjust: i64) -> Self {
+        self.kerning.insert((left, right), adjust);
+        self
+    }
+
+    /// Draws characters without a glyph as `fallback`. Without a fallback, they are skipped.
+    pub fn with_fallback(mut self, fallback: char) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    pub fn with_line_height(mut self, line_height: usize) -> Self {
+        self.line_height = line_height;
+        self
+    }
+
+    pub fn line_height(&self) -> usize {
+        self.line_height
+    }
+
+    /// Returns the glyph that `c` is drawn with, which is the fallback's glyph if `c` has none.
+    pub fn glyph(&self, c: char) -> Option<&Glyph> {
+        self.glyphs
+            .get(&c)
+            .or_else(|| self.glyphs.get(&self.fallback?))
+    }
+
+    /// Returns the position of every glyph of a line relative to the start of the line, and the
+    /// width of the line.
+    fn layout_line(&self, line: &str) -> (Vec<(i64, &Glyph)>, usize) {
+        let mut glyphs = vec![];
+        let mut pen_x = 0i64;
+        let mut previous = None;
+        for c in line.chars() {
+            let Some(glyph) = self.glyph(c) else {
+                continue;
+            };
+            if let Some(previous) = previous {
+                pen_x += self.kerning.get(&(previous, c)).copied().unwrap_or(0);
+            }
+            glyphs.push((pen_x, glyph));
+            pen_x += glyph.advance as i64;
+            previous = Some(c);
+        }
+        (glyphs, pen_x.max(0) as usize)
+    }
+
+    /// Returns the width and height of `text` in pixels. The width is the sum of the advances and
+    /// kerning of the widest line, the height is the line height times the number of lines.
+    pub fn measure(&self, text: &str) -> (usize, usize) {
+        let mut width = 0;
+        let mut lines = 0;
+        for line in text.split('\n') {
+            width = width.max(self.layout_line(line).1);
+            lines += 1;
+        }
+        (width, lines * self.line_height)
+    }
+
+    /// Draws `text` with its top left corner at `(x, y)`. Transparent glyph pixels are skipped,
+    /// and with a `tint`, glyph colors are multiplied with it, where [`Color::Default`] and
+    /// [`Color::Transparent`] leave them unchanged, see
+    /// [`SpriteView::render_tinted_to_hbd`](crate::rendering::sprite::SpriteView::render_tinted_to_hbd).
+    pub fn draw(
+        &self,
+        hbd: &mut HalfBlockDisplayRender,
+        x: i64,
+        y: i64,
+        text: &str,
+        tint: Option<Color>,
+    ) {
+        let tint = tint.map(|tint| tint.unwrap_or([255, 255, 255]));
+        for (line_index, line) in text.split('\n').enumerate() {
+            let line_y = y + (line_index * self.line_height) as i64;
+            for (offset_x, glyph) in self.layout_line(line).0 {
+                let view = self.image.crop(glyph.x, glyph.y, glyph.width, glyph.height);
+                let (glyph_x, glyph_y) = (x + offset_x, line_y + glyph.offset_y);
+                match tint {
+                    Some(tint) => view.render_tinted_to_hbd(glyph_x, glyph_y, tint, hbd),
+                    None => view.render_to_hbd(glyph_x, glyph_y, hbd),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const W: [u8; 4] = [255, 255, 255, 255];
+    const G: [u8; 4] = [128, 128, 128, 255];
+    const C: [u8; 4] = [0, 0, 0, 0];
+
+    /// Two 2x2 glyphs side by side, "A" is white over gray, "B" is a transparent checkerboard.
+    fn image() -> ImageSprite {
+        ImageSprite::from_rgba(4, 2, vec![W, W, W, C, G, G, C, W])
+    }
+
+    #[test]
+    fn test_grid_charset() {
+        let image = ImageSprite::from_rgba(6, 4, vec![C; 24]);
+        let font = SpriteFont::from_grid(image, 3, 2, "ab\ncd");
+        assert_eq!(font.glyph('a').map(|g| (g.x, g.y)), Some((0, 0)));
+        assert_eq!(font.glyph('b').map(|g| (g.x, g.y)), Some((3, 0)));
+        assert_eq!(font.glyph('d').map(|g| (g.x, g.y)), Some((3, 2)));
+        assert_eq!(font.glyph('e'), None);
+        let font = font.with_fallback('c');
+        assert_eq!(font.glyph('e').map(|g| (g.x, g.y)), Some((0, 2)));
+        assert_eq!(font.line_height(), 2);
+    }
+
+    #[test]
+    fn test_measure_with_kerning() {
+        let font = SpriteFont::from_grid(image(), 2, 2, "AB")
+            .with_glyph(
+                '.',
+                Glyph {
+                    x: 0,
+                    y: 1,
+                    width: 1,
+                    height: 1,
+                    advance: 1,
+                    offset_y: 1,
+                },
+            )
+            .with_kerning('A', 'B', -1)
+            .with_line_height(3);
+        assert_eq!(font.measure(""), (0, 3));
+        assert_eq!(font.measure("AB"), (3, 3));
+        assert_eq!(font.measure("BA"), (4, 3));
+        assert_eq!(font.measure("AB.\nBAB"), (5, 6));
+        // unmapped characters are skipped without a fallback, keeping the kerning of their neighbors
+        assert_eq!(font.measure("A?B"), (3, 3));
+        assert_eq!(font.with_fallback('.').measure("A?B"), (5, 3));
+    }
+
+    #[test]
+    fn test_draw_tinted() {
+        let font = SpriteFont::from_grid(image(), 2, 2, "AB").with_kerning('A', 'B', -1);
+        let black = Color::Rgb([0, 0, 0]);
+        let mut hbd = HalfBlockDisplayRender::new(4, 2);
+        for (x, y) in [
+            (0, 0),
+            (1, 0),
+            (2, 0),
+            (3, 0),
+            (0, 1),
+            (1, 1),
+            (2, 1),
+            (3, 1),
+        ] {
+            hbd.set_color(x, y, black);
+        }
+        font.draw(&mut hbd, 0, 0, "AB", Some(Color::Rgb([200, 100, 0])));
+        let colors: Vec<_> = (0..2)
+            .flat_map(|y| (0..4).map(move |x| (x, y)))
+            .map(|(x, y)| hbd.get_color(x, y).unwrap())
+            .collect();
+        let tint = Color::Rgb([200, 100, 0]);
+        let gray = Color::Rgb([100, 50, 0]);
+        assert_eq!(
+            colors,
+            // "B" starts at x = 1 and only covers "A" where it is not transparent
+            [tint, tint, black, black, gray, gray, tint, black]
+        );
+    }
+}
@@ -1,5 +1,24 @@
 //! Responsible for deriving seeds for the game from a passed seed.
+//!
+//! With the `rng` feature, [`seeded_rng_for`] and [`rng_for_frame`] return random number
+//! generators derived from the global seed, so that a game with the same seed and inputs, e.g. a
+//! replayed recording, makes the same random choices:
+//! ```
+//! # #[cfg(feature = "rng")] {
+//! use rand::Rng;
+//! use teng::seeds::{seeded_rng_for, set_seed};
+//!
+//! set_seed(42);
+//! let mut spawns = seeded_rng_for("spawn jitter");
+//! let jitter: i64 = spawns.gen_range(-2..=2);
+//! assert_eq!(seeded_rng_for("spawn jitter").gen_range(-2..=2), jitter);
+//! # }
+//! ```
 
+#[cfg(feature = "rng")]
+use rand::SeedableRng;
+#[cfg(feature = "rng")]
+use rand::rngs::StdRng;
 use std::hash::{DefaultHasher, Hash, Hasher};
 use std::sync::OnceLock;
 
@@ -45,3 +64,74 @@ seed_impl!(get_i8_seed_for, i8);
 seed_impl!(get_u32_seed_for, u32);
 seed_impl!(get_u16_seed_for, u16);
 seed_impl!(get_u8_seed_for, u8);
+
+/// Returns a random number generator seeded with the global seed.
+///
+/// Every call returns a generator with the same stream, so different systems should use
+/// [`seeded_rng_for`] to not make the same choices.
+///
+/// # Panics
+///
+/// If the global seed has not been set.
+#[cfg(feature = "rng")]
+pub fn seeded_rng() -> StdRng {
+    StdRng::seed_from_u64(get_seed())
+}
+
+/// Returns a random number generator for `label`, derived from the global seed.
+///
+/// Per global seed and label the stream is the same, and different labels get independent
+/// streams, e.g. `"world gen"` and `"spawn jitter"`.
+///
+/// # Panics
+///
+/// If the global seed has not been set.
+#[cfg(feature = "rng")]
+pub fn seeded_rng_for(label: &str) -> StdRng {
+    StdRng::seed_from_u64(get_u64_seed_for(label))
+}
+
+/// Returns a random number generator for `label` in frame `frame_number`, derived from the global
+/// seed.
+///
+/// Unlike a generator kept across frames, the choices in a frame do not depend on how many
+/// numbers earlier frames drew, so they stay the same in a replay that skips or changes earlier
+/// frames. Use [`UpdateInfo::frame_number`](crate::UpdateInfo::frame_number).
+///
+/// # Panics
+///
+/// If the global seed has not been set.
+#[cfg(feature = "rng")]
+pub fn rng_for_frame(frame_number: u64, label: &str) -> StdRng {
+    let mut hasher = DefaultHasher::new();
+    SEED.get().unwrap().hash(&mut hasher);
+    label.hash(&mut hasher);
+    frame_number.hash(&mut hasher);
+    StdRng::seed_from_u64(hasher.finish())
+}
+
+#[cfg(all(test, feature = "rng"))]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    fn first_values(mut rng: StdRng) -> Vec<u64> {
+        (0..100).map(|_| rng.r#gen()).collect()
+    }
+
+    #[test]
+    fn test_streams_are_reproducible_and_independent() {
+        // the global seed can only be set once per process, other tests may have set it already
+        let _ = SEED.set(42);
+
+        let spawns = first_values(seeded_rng_for("spawn jitter"));
+        assert_eq!(first_values(seeded_rng_for("spawn jitter")), spawns);
+        assert_ne!(first_values(seeded_rng_for("world gen")), spawns);
+        assert_eq!(first_values(seeded_rng()), first_values(seeded_rng()));
+
+        let frame = first_values(rng_for_frame(7, "spawn jitter"));
+        assert_eq!(first_values(rng_for_frame(7, "spawn jitter")), frame);
+        assert_ne!(first_values(rng_for_frame(8, "spawn jitter")), frame);
+        assert_ne!(first_values(rng_for_frame(7, "world gen")), frame);
+    }
+}
@@ -0,0 +1,86 @@
+//! A map that stores at most one value of every type, see [`AnyMap`].
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+/// A map with types as keys, storing at most one value of every type.
+///
+/// Used for [`SharedState::extensions`](crate::SharedState::extensions), so components can share
+/// data whose types are not known to **teng**.
+///
+/// # Example
+/// ```
+/// use teng::util::anymap::AnyMap;
+///
+/// struct Score(u32);
+///
+/// let mut map = AnyMap::new();
+/// assert!(map.insert(Score(1)).is_none());
+/// map.get_mut::<Score>().unwrap().0 += 1;
+/// assert_eq!(map.get::<Score>().unwrap().0, 2);
+/// assert_eq!(map.remove::<Score>().unwrap().0, 2);
+/// assert!(!map.contains::<Score>());
+/// ```
+#[derive(Debug, Default)]
+pub struct AnyMap {
+    values: HashMap<TypeId, Box<dyn Any>>,
+}
+
+impl AnyMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts `value`, and returns the previous value of the same type, if any.
+    pub fn insert<T: 'static>(&mut self, value: T) -> Option<T> {
+        self.values
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .map(|old| *old.downcast().expect("values are stored under their type id"))
+    }
+
+    /// Returns a reference to the value of type `T`, if any.
+    pub fn get<T: 'static>(&self) -> Option<&T> {
+        self.values
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref())
+    }
+
+    /// Returns a mutable reference to the value of type `T`, if any.
+    pub fn get_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.values
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_mut())
+    }
+
+    /// Removes and returns the value of type `T`, if any.
+    pub fn remove<T: 'static>(&mut self) -> Option<T> {
+        self.values
+            .remove(&TypeId::of::<T>())
+            .map(|old| *old.downcast().expect("values are stored under their type id"))
+    }
+
+    /// Returns true if there is a value of type `T`.
+    pub fn contains<T: 'static>(&self) -> bool {
+        self.values.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Returns true if there is a value whose type has the id `type_id`.
+    pub fn contains_type_id(&self, type_id: TypeId) -> bool {
+        self.values.contains_key(&type_id)
+    }
+
+    /// Returns the number of values.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns true if there are no values.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Removes all values.
+    pub fn clear(&mut self) {
+        self.values.clear();
+    }
+}
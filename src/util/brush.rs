@@ -0,0 +1,308 @@
+//! Brush tools for editing a grid world with a limited block budget.
+//!
+//! A [`Stroke`] records a single gesture (mouse down, drags, mouse up) of a [`BrushTool`].
+//! While the gesture is in progress, [`Stroke::preview`] computes the affected cells and the
+//! [`BrushPreview::cost`] in blocks, which can be rendered as a preview (e.g. tinted red if
+//! [`BrushPreview::is_affordable`] returns false). Once the gesture ends, the preview's cells
+//! are committed as one unit, e.g. as a single undo step.
+//!
+//! Placing a block costs one block from the budget, erasing one refunds it. Cells that would
+//! not change (placing onto an existing block, erasing an empty cell) are free and not part of
+//! the preview.
+//!
+//! The world is accessed through the [`Grid`] trait, where `is_filled` decides whether a cell
+//! currently holds a block.
+
+use crate::util::for_coord_in_line;
+use crate::util::grid::{Grid, flood_fill};
+use std::collections::BTreeSet;
+
+/// The shape a [`Stroke`] paints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrushTool {
+    /// Paints along the mouse path with a square brush of `size` by `size` cells.
+    Freehand { size: u8 },
+    /// A straight line from the gesture's start to its end.
+    Line,
+    /// A rectangle spanned by the gesture's start and end, either just the outline or filled.
+    Rect { filled: bool },
+    /// All cells connected to the gesture's start that are in the same state as the start cell.
+    /// Regions larger than `max_cells` are rejected, so that fills cannot run away into
+    /// unbounded areas.
+    BucketFill { max_cells: usize },
+}
+
+/// Whether a [`Stroke`] places or removes blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrushMode {
+    Place,
+    Erase,
+}
+
+/// A single gesture of a [`BrushTool`].
+#[derive(Debug, Clone)]
+pub struct Stroke {
+    tool: BrushTool,
+    mode: BrushMode,
+    path: Vec<(i64, i64)>,
+}
+
+impl Stroke {
+    /// Starts a new gesture at `start`.
+    pub fn begin(tool: BrushTool, mode: BrushMode, start: (i64, i64)) -> Self {
+        Self {
+            tool,
+            mode,
+            path: vec![start],
+        }
+    }
+
+    pub fn tool(&self) -> BrushTool {
+        self.tool
+    }
+
+    pub fn mode(&self) -> BrushMode {
+        self.mode
+    }
+
+    /// Continues the gesture to `pos`.
+    pub fn drag_to(&mut self, pos: (i64, i64)) {
+        if self.path.last() != Some(&pos) {
+            self.path.push(pos);
+        }
+    }
+
+    /// Returns the gesture's start position.
+    pub fn start(&self) -> (i64, i64) {
+        self.path[0]
+    }
+
+    /// Returns the gesture's current end position.
+    pub fn end(&self) -> (i64, i64) {
+        *self.path.last().unwrap()
+    }
+
+    /// Returns all cells covered by the tool's shape, regardless of their current state,
+    /// in sorted order.
+    pub fn shape_cells<T>(
+        &self,
+        grid: &impl Grid<T>,
+        mut is_filled: impl FnMut(&T) -> bool,
+    ) -> Vec<(i64, i64)> {
+        let mut cells = BTreeSet::new();
+        let (start, end) = (self.start(), self.end());
+        match self.tool {
+            BrushTool::Freehand { size } => {
+                let size = size.max(1) as i64;
+                // center the brush, rounding towards the top left for even sizes
+                let offset = (size - 1) / 2;
+                let mut prev = start;
+                for &pos in &self.path {
+                    for_coord_in_line(false, prev, pos, |x, y| {
+                        for dy in 0..size {
+                            for dx in 0..size {
+                                cells.insert((y + dy - offset, x + dx - offset));
+                            }
+                        }
+                    });
+                    prev = pos;
+                }
+            }
+            BrushTool::Line => {
+                for_coord_in_line(false, start, end, |x, y| {
+                    cells.insert((y, x));
+                });
+            }
+            BrushTool::Rect { filled } => {
+                let (min_x, max_x) = (start.0.min(end.0), start.0.max(end.0));
+                let (min_y, max_y) = (start.1.min(end.1), start.1.max(end.1));
+                for y in min_y..=max_y {
+                    for x in min_x..=max_x {
+                        let on_outline = x == min_x || x == max_x || y == min_y || y == max_y;
+                        if filled || on_outline {
+                            cells.insert((y, x));
+                        }
+                    }
+                }
+            }
+            BrushTool::BucketFill { max_cells } => {
+                if let Some(start_filled) = grid.get_cell(start.0, start.1).map(&mut is_filled) {
+                    let region = flood_fill(grid, start, |t| is_filled(t) == start_filled);
+                    if region.len() <= max_cells {
+                        cells.extend(region.into_iter().map(|(x, y)| (y, x)));
+                    }
+                }
+            }
+        }
+        // the set is ordered by (y, x) for row-major output
+        cells
+            .into_iter()
+            .map(|(y, x)| (x, y))
+            .filter(|&(x, y)| grid.get_cell(x, y).is_some())
+            .collect()
+    }
+
+    /// Computes the cells this stroke would change and their cost against `budget`.
+    pub fn preview<T>(
+        &self,
+        grid: &impl Grid<T>,
+        mut is_filled: impl FnMut(&T) -> bool,
+        budget: u64,
+    ) -> BrushPreview {
+        let wants_filled = self.mode == BrushMode::Place;
+        let cells: Vec<_> = self
+            .shape_cells(grid, &mut is_filled)
+            .into_iter()
+            .filter(|&(x, y)| grid.get_cell(x, y).map(&mut is_filled) != Some(wants_filled))
+            .collect();
+        let (cost, refund) = match self.mode {
+            BrushMode::Place => (cells.len() as u64, 0),
+            BrushMode::Erase => (0, cells.len() as u64),
+        };
+        BrushPreview {
+            mode: self.mode,
+            cells,
+            cost,
+            refund,
+            budget,
+        }
+    }
+}
+
+/// The cells a [`Stroke`] would change if committed now.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrushPreview {
+    pub mode: BrushMode,
+    /// The cells that change state, in row-major order.
+    pub cells: Vec<(i64, i64)>,
+    /// Blocks taken from the budget.
+    pub cost: u64,
+    /// Blocks returned to the budget.
+    pub refund: u64,
+    /// The budget the preview was computed against.
+    pub budget: u64,
+}
+
+impl BrushPreview {
+    /// Returns true if the budget covers the cost.
+    pub fn is_affordable(&self) -> bool {
+        self.cost <= self.budget
+    }
+
+    /// Returns the budget after committing.
+    pub fn remaining_budget(&self) -> u64 {
+        self.budget.saturating_sub(self.cost) + self.refund
+    }
+
+    /// Returns the cells to change and the remaining budget, or `None` if the preview is
+    /// not affordable.
+    pub fn commit(self) -> Option<(Vec<(i64, i64)>, u64)> {
+        if !self.is_affordable() {
+            return None;
+        }
+        let remaining = self.remaining_budget();
+        Some((self.cells, remaining))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::planarvec::{Bounds, PlanarVec};
+
+    fn world() -> PlanarVec<bool> {
+        let mut world = PlanarVec::new(
+            Bounds {
+                min_x: -10,
+                max_x: 10,
+                min_y: -10,
+                max_y: 10,
+            },
+            false,
+        );
+        // a closed 3x3 room with a 1x1 interior around (5, 5)
+        for y in 4..=6 {
+            for x in 4..=6 {
+                world[(x, y)] = (x, y) != (5, 5);
+            }
+        }
+        world
+    }
+
+    fn stroke(tool: BrushTool, mode: BrushMode, path: &[(i64, i64)]) -> Stroke {
+        let mut stroke = Stroke::begin(tool, mode, path[0]);
+        for &pos in &path[1..] {
+            stroke.drag_to(pos);
+        }
+        stroke
+    }
+
+    fn cost(tool: BrushTool, path: &[(i64, i64)]) -> u64 {
+        stroke(tool, BrushMode::Place, path)
+            .preview(&world(), |b| *b, u64::MAX)
+            .cost
+    }
+
+    #[test]
+    fn test_cost_per_tool() {
+        assert_eq!(cost(BrushTool::Freehand { size: 1 }, &[(0, 0)]), 1);
+        assert_eq!(cost(BrushTool::Freehand { size: 3 }, &[(0, 0)]), 9);
+        // the drag is interpolated: 4 cells in a row, two rows high
+        assert_eq!(cost(BrushTool::Freehand { size: 2 }, &[(0, 0), (3, 0)]), 10);
+        assert_eq!(cost(BrushTool::Line, &[(-3, 0), (3, 0)]), 7);
+        assert_eq!(cost(BrushTool::Line, &[(0, 0), (0, 5), (3, 3)]), 4);
+        assert_eq!(
+            cost(BrushTool::Rect { filled: true }, &[(0, 0), (2, 3)]),
+            12
+        );
+        assert_eq!(
+            cost(BrushTool::Rect { filled: false }, &[(0, 0), (2, 3)]),
+            10
+        );
+        // only the interior of the room
+        assert_eq!(cost(BrushTool::BucketFill { max_cells: 100 }, &[(5, 5)]), 1);
+        // the open area is too large
+        assert_eq!(cost(BrushTool::BucketFill { max_cells: 100 }, &[(0, 0)]), 0);
+    }
+
+    #[test]
+    fn test_existing_blocks_are_free() {
+        // the rect outline exactly covers the room's walls
+        assert_eq!(
+            cost(BrushTool::Rect { filled: false }, &[(4, 4), (6, 6)]),
+            0
+        );
+        assert_eq!(cost(BrushTool::Rect { filled: true }, &[(4, 4), (6, 6)]), 1);
+        // clipped to the world bounds
+        assert_eq!(cost(BrushTool::Line, &[(8, 0), (12, 0)]), 3);
+
+        let erase = stroke(
+            BrushTool::Rect { filled: true },
+            BrushMode::Erase,
+            &[(3, 3), (7, 7)],
+        );
+        let preview = erase.preview(&world(), |b| *b, 0);
+        assert_eq!(preview.cost, 0);
+        assert_eq!(preview.refund, 8);
+        assert_eq!(preview.commit().map(|(_, budget)| budget), Some(8));
+    }
+
+    #[test]
+    fn test_budget_enforcement() {
+        let rect = stroke(
+            BrushTool::Rect { filled: true },
+            BrushMode::Place,
+            &[(0, 0), (2, 2)],
+        );
+        let preview = rect.preview(&world(), |b| *b, 9);
+        assert!(preview.is_affordable());
+        assert_eq!(preview.remaining_budget(), 0);
+        let (cells, remaining) = preview.commit().unwrap();
+        assert_eq!(cells.len(), 9);
+        assert_eq!(remaining, 0);
+
+        let preview = rect.preview(&world(), |b| *b, 8);
+        assert!(!preview.is_affordable());
+        assert_eq!(preview.commit(), None);
+    }
+}
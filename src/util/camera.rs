@@ -0,0 +1,358 @@
+//! Mapping between world coordinates and the screen.
+//!
+//! Games that draw with a [`HalfBlockDisplayRender`] have three coordinate systems:
+//! * terminal cells, e.g. the mouse position, with y pointing down,
+//! * half-block pixels, two per cell, with y pointing down,
+//! * world coordinates, usually with y pointing up.
+//!
+//! A [`Camera2D`] does the conversions in one place, instead of every game repeating the y-flip,
+//! the factor of two and the camera offset. Register it in [`SharedState::extensions`] to let the
+//! [`CoordinateDebugComponent`] show the world coordinates under the mouse.
+//!
+//! Games that draw one pixel per terminal cell use [`Camera2D::for_cells`] instead, where the
+//! y-axis is not doubled.
+//!
+//! [`SharedState::extensions`]: crate::SharedState::extensions
+//! [`CoordinateDebugComponent`]: crate::components::coordinates::CoordinateDebugComponent
+
+use crate::rendering::color::Color;
+use crate::rendering::pixel::Pixel;
+use crate::rendering::render::HalfBlockDisplayRender;
+use crate::rendering::renderer::Renderer;
+use crate::util::planarvec::Bounds;
+
+/// Which half of a terminal cell a half-block pixel is in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CellHalf {
+    Top,
+    Bottom,
+}
+
+/// A 2D camera that maps world coordinates to the half-block pixels of a viewport.
+///
+/// World y points up. Integer world coordinates `(x, y)` name the tile from `(x, y)` to
+/// `(x + 1, y + 1)`, so negative coordinates round down, see [`Camera2D::pixel_to_tile`]. The viewport is a rectangle of terminal cells, which is the whole screen
+/// unless part of the screen is reserved, e.g. for a HUD or letterboxing.
+///
+/// # Example
+/// ```
+/// use teng::util::camera::{Camera2D, CellHalf};
+///
+/// let camera = Camera2D::new(80, 24);
+/// // the bottom left world pixel is in the bottom half of the bottom left cell
+/// assert_eq!(camera.world_to_screen(0.0, 0.0), (0, 23, CellHalf::Bottom));
+/// assert_eq!(camera.screen_to_world(0, 23, CellHalf::Top), (0.0, 1.0));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Camera2D {
+    /// The world coordinates shown at the bottom left corner of the viewport.
+    pub position: (f64, f64),
+    /// The world units per half-block pixel, the inverse of [`Camera2D::zoom`].
+    pub scale: f64,
+    /// The top left cell of the viewport on the screen.
+    pub viewport_offset: (usize, usize),
+    /// The size of the viewport in terminal cells.
+    pub viewport_size: (usize, usize),
+    /// The extent of the world as `(min, max)`, if the world is finite.
+    pub world_bounds: Option<((f64, f64), (f64, f64))>,
+    /// Whether every terminal cell shows two pixels on top of each other. Otherwise, every cell
+    /// is one pixel and [`CellHalf::Top`] and [`CellHalf::Bottom`] are the same.
+    pub half_block: bool,
+}
+
+impl Camera2D {
+    /// Creates a camera at the world origin with one world unit per half-block pixel, for a
+    /// viewport that fills a screen of `width` x `height` cells.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            position: (0.0, 0.0),
+            scale: 1.0,
+            viewport_offset: (0, 0),
+            viewport_size: (width, height),
+            world_bounds: None,
+            half_block: true,
+        }
+    }
+
+    /// Same as [`Camera2D::new`], for a [`HalfBlockDisplayRender`] of `width` x `2 * height`
+    /// pixels.
+    pub fn for_half_block(width: usize, height: usize) -> Self {
+        Self::new(width, height)
+    }
+
+    /// Creates a camera like [`Camera2D::new`], but with one pixel per terminal cell.
+    pub fn for_cells(width: usize, height: usize) -> Self {
+        Self {
+            half_block: false,
+            ..Self::new(width, height)
+        }
+    }
+
+    /// Returns the pixels per world unit.
+    pub fn zoom(&self) -> f64 {
+        1.0 / self.scale
+    }
+
+    /// Sets the pixels per world unit, keeping the world point at the center of the viewport in
+    /// place.
+    pub fn set_zoom(&mut self, zoom: f64) {
+        let center = self.center();
+        self.scale = 1.0 / zoom;
+        self.center_on(center.0, center.1);
+    }
+
+    /// The number of pixels per terminal cell, vertically.
+    fn pixels_per_cell(&self) -> usize {
+        if self.half_block { 2 } else { 1 }
+    }
+
+    /// The height of the viewport in pixels.
+    fn pixel_height(&self) -> f64 {
+        (self.pixels_per_cell() * self.viewport_size.1) as f64
+    }
+
+    /// Returns the viewport size in pixels.
+    pub fn pixel_size(&self) -> (usize, usize) {
+        let (width, height) = self.viewport_size;
+        (width, self.pixels_per_cell() * height)
+    }
+
+    /// Returns the world point at the center of the viewport.
+    pub fn center(&self) -> (f64, f64) {
+        let (width, height) = self.pixel_size();
+        (
+            self.position.0 + width as f64 * self.scale / 2.0,
+            self.position.1 + height as f64 * self.scale / 2.0,
+        )
+    }
+
+    /// Moves the camera so that the world point is at the center of the viewport.
+    ///
+    /// With an odd viewport size, the center is in the middle of a pixel.
+    pub fn center_on(&mut self, x: f64, y: f64) {
+        let (width, height) = self.pixel_size();
+        self.position = (
+            x - width as f64 * self.scale / 2.0,
+            y - height as f64 * self.scale / 2.0,
+        );
+    }
+
+    /// Returns the half-block pixel of the viewport that shows the world point. The pixel may be
+    /// outside of the viewport.
+    pub fn world_to_pixel(&self, x: f64, y: f64) -> (i64, i64) {
+        let px = ((x - self.position.0) / self.scale).floor();
+        let py = self.pixel_height() - 1.0 - ((y - self.position.1) / self.scale).floor();
+        (px as i64, py as i64)
+    }
+
+    /// Returns the world coordinates of the bottom left corner of the half-block pixel.
+    pub fn pixel_to_world(&self, px: i64, py: i64) -> (f64, f64) {
+        let x = self.position.0 + px as f64 * self.scale;
+        let y = self.position.1 + (self.pixel_height() - 1.0 - py as f64) * self.scale;
+        (x, y)
+    }
+
+    /// Returns the world tile that the center of the pixel is in.
+    pub fn pixel_to_tile(&self, px: i64, py: i64) -> (i64, i64) {
+        let (x, y) = self.pixel_to_world(px, py);
+        let half = self.scale / 2.0;
+        ((x + half).floor() as i64, (y + half).floor() as i64)
+    }
+
+    /// Returns the world tile that the center of the half of a screen cell is in.
+    pub fn screen_to_tile(&self, cell_x: usize, cell_y: usize, half: CellHalf) -> (i64, i64) {
+        let (px, py) = self.screen_to_pixel(cell_x, cell_y, half);
+        self.pixel_to_tile(px, py)
+    }
+
+    /// Returns the screen cell and the half of it that shows the world point. The cell may be
+    /// outside of the screen, in which case negative coordinates are clamped to 0.
+    pub fn world_to_screen(&self, x: f64, y: f64) -> (usize, usize, CellHalf) {
+        let (px, py) = self.world_to_pixel(x, y);
+        let per_cell = self.pixels_per_cell() as i64;
+        let half = if py.rem_euclid(per_cell) == 0 {
+            CellHalf::Top
+        } else {
+            CellHalf::Bottom
+        };
+        let cell_x = self.viewport_offset.0 as i64 + px;
+        let cell_y = self.viewport_offset.1 as i64 + py.div_euclid(per_cell);
+        (cell_x.max(0) as usize, cell_y.max(0) as usize, half)
+    }
+
+    /// Returns the world coordinates of the bottom left corner of the half of a screen cell.
+    pub fn screen_to_world(&self, cell_x: usize, cell_y: usize, half: CellHalf) -> (f64, f64) {
+        let (px, py) = self.screen_to_pixel(cell_x, cell_y, half);
+        self.pixel_to_world(px, py)
+    }
+
+    fn screen_to_pixel(&self, cell_x: usize, cell_y: usize, half: CellHalf) -> (i64, i64) {
+        let px = cell_x as i64 - self.viewport_offset.0 as i64;
+        let py = self.pixels_per_cell() as i64 * (cell_y as i64 - self.viewport_offset.1 as i64);
+        match half {
+            CellHalf::Bottom if self.half_block => (px, py + 1),
+            _ => (px, py),
+        }
+    }
+
+    /// Returns whether the screen cell is inside the viewport.
+    pub fn contains_cell(&self, cell_x: usize, cell_y: usize) -> bool {
+        let (ox, oy) = self.viewport_offset;
+        let (w, h) = self.viewport_size;
+        (ox..ox + w).contains(&cell_x) && (oy..oy + h).contains(&cell_y)
+    }
+
+    /// Returns the world rectangle that the viewport shows, as `(min, max)`.
+    pub fn visible_bounds(&self) -> ((f64, f64), (f64, f64)) {
+        let min = self.position;
+        let max = (
+            min.0 + self.viewport_size.0 as f64 * self.scale,
+            min.1 + self.pixel_height() * self.scale,
+        );
+        (min, max)
+    }
+
+    /// Returns the world tiles that are at least partially visible, e.g. to load or expand the
+    /// visible part of a [`PlanarVec`](crate::util::planarvec::PlanarVec).
+    pub fn visible_tile_bounds(&self) -> Bounds {
+        let ((min_x, min_y), (max_x, max_y)) = self.visible_bounds();
+        Bounds {
+            min_x: min_x.floor() as i64,
+            min_y: min_y.floor() as i64,
+            // the max edge belongs to the next tile
+            max_x: max_x.ceil() as i64 - 1,
+            max_y: max_y.ceil() as i64 - 1,
+        }
+    }
+
+    /// Sets the pixel of `hbd` that shows the world point, if it is inside of `hbd`. `hbd` covers
+    /// the viewport, starting at its top left corner.
+    pub fn render_world_pixel(
+        &self,
+        hbd: &mut HalfBlockDisplayRender,
+        x: f64,
+        y: f64,
+        color: Color,
+    ) {
+        let (px, py) = self.world_to_pixel(x, y);
+        if px >= 0 && py >= 0 {
+            hbd.set_color(px as usize, py as usize, color);
+        }
+    }
+
+    /// Renders `pixel` in the screen cell that shows the world point, if the cell is inside of
+    /// the viewport.
+    pub fn render_world_cell(
+        &self,
+        renderer: &mut dyn Renderer,
+        x: f64,
+        y: f64,
+        pixel: Pixel,
+        depth: i32,
+    ) {
+        let (px, py) = self.world_to_pixel(x, y);
+        let cell_x = self.viewport_offset.0 as i64 + px;
+        let cell_y = self.viewport_offset.1 as i64 + py.div_euclid(self.pixels_per_cell() as i64);
+        if cell_x >= 0 && cell_y >= 0 && self.contains_cell(cell_x as usize, cell_y as usize) {
+            renderer.render_pixel(cell_x as usize, cell_y as usize, pixel, depth);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_with_offset_and_scale() {
+        let camera = Camera2D {
+            position: (-10.0, 5.0),
+            scale: 0.5,
+            viewport_offset: (2, 1),
+            viewport_size: (20, 10),
+            world_bounds: None,
+            half_block: true,
+        };
+        for (cell_x, cell_y) in [(2, 1), (5, 4), (21, 10)] {
+            for half in [CellHalf::Top, CellHalf::Bottom] {
+                let (x, y) = camera.screen_to_world(cell_x, cell_y, half);
+                // the center of the half-block pixel maps back to it
+                let center = (x + 0.25, y + 0.25);
+                assert_eq!(
+                    camera.world_to_screen(center.0, center.1),
+                    (cell_x, cell_y, half)
+                );
+            }
+        }
+        assert_eq!(camera.visible_bounds(), ((-10.0, 5.0), (0.0, 15.0)));
+        // the top left of the viewport shows the top of the visible bounds
+        assert_eq!(camera.screen_to_world(2, 1, CellHalf::Top), (-10.0, 14.5));
+    }
+
+    #[test]
+    fn test_odd_sizes_and_negative_tiles() {
+        // 5 x 3 cells are 5 x 6 pixels, the center is in the middle of pixel column 2
+        let mut camera = Camera2D::for_half_block(5, 3);
+        camera.center_on(0.0, 0.0);
+        assert_eq!(camera.position, (-2.5, -3.0));
+        assert_eq!(camera.center(), (0.0, 0.0));
+        // tile (-1, -1) is left of and below the origin
+        assert_eq!(camera.pixel_to_tile(2, 3), (0, -1));
+        assert_eq!(camera.pixel_to_tile(1, 3), (-1, -1));
+        assert_eq!(camera.pixel_to_tile(0, 5), (-2, -3));
+        assert_eq!(camera.world_to_pixel(-0.5, -0.5), (2, 3));
+        assert_eq!(camera.world_to_pixel(-3.0, -3.0), (-1, 5));
+        assert_eq!(
+            camera.visible_tile_bounds(),
+            Bounds {
+                min_x: -3,
+                max_x: 2,
+                min_y: -3,
+                max_y: 2,
+            }
+        );
+        assert_eq!(camera.screen_to_tile(0, 2, CellHalf::Bottom), (-2, -3));
+        assert_eq!(camera.screen_to_tile(4, 0, CellHalf::Top), (2, 2));
+
+        // zooming keeps the center, with two pixels per tile
+        camera.set_zoom(2.0);
+        assert_eq!(camera.zoom(), 2.0);
+        assert_eq!(camera.center(), (0.0, 0.0));
+        assert_eq!(camera.pixel_to_tile(2, 2), (0, 0));
+        assert_eq!(camera.pixel_to_tile(1, 3), (-1, -1));
+        assert_eq!(camera.pixel_to_tile(0, 3), (-1, -1));
+
+        let mut hbd = HalfBlockDisplayRender::new(5, 6);
+        let red = Color::Rgb([255, 0, 0]);
+        camera.render_world_pixel(&mut hbd, -0.25, -0.25, red);
+        // outside of the viewport
+        camera.render_world_pixel(&mut hbd, -5.0, 0.0, red);
+        assert_eq!(hbd.get_color(2, 3), Some(red));
+        let set = (0..6)
+            .flat_map(|y| (0..5).map(move |x| (x, y)))
+            .filter(|&(x, y)| hbd.get_color(x, y) == Some(red))
+            .count();
+        assert_eq!(set, 1);
+    }
+
+    #[test]
+    fn test_cells() {
+        struct Recorder(Vec<(usize, usize)>);
+        impl Renderer for Recorder {
+            fn render_pixel(&mut self, x: usize, y: usize, _pixel: Pixel, _depth: i32) {
+                self.0.push((x, y));
+            }
+        }
+        let mut camera = Camera2D::for_cells(3, 3);
+        camera.viewport_offset = (1, 1);
+        camera.center_on(0.0, 0.0);
+        assert_eq!(camera.world_to_screen(0.0, 0.0), (2, 2, CellHalf::Top));
+        assert_eq!(camera.world_to_screen(-1.0, 1.0), (1, 1, CellHalf::Top));
+        assert_eq!(camera.screen_to_tile(1, 3, CellHalf::Bottom), (-1, -1));
+        let mut recorder = Recorder(vec![]);
+        for (x, y) in [(-1.5, -1.5), (1.4, 1.4), (-1.6, 0.0), (0.0, 1.6)] {
+            camera.render_world_cell(&mut recorder, x, y, Pixel::new('#'), 0);
+        }
+        assert_eq!(recorder.0, [(1, 3), (3, 1)]);
+    }
+}
@@ -0,0 +1,177 @@
+//! Copying to and pasting from the system clipboard of the terminal.
+//!
+//! Copies use the OSC 52 escape sequence, which asks the terminal to set its clipboard. Queue a
+//! copy with [`SharedState::clipboard`](crate::SharedState::clipboard) and the sequence is written
+//! with the next frame, so it does not interleave with the frame's output.
+//!
+//! Pastes are delivered as [`Event::Paste`](crossterm::event::Event::Paste), since
+//! [`terminal_setup`](crate::terminal_setup) enables bracketed paste. Pasted text longer than
+//! [`MAX_PASTE_BYTES`] is truncated before any component sees it.
+//!
+//! Not all terminals support OSC 52, and some only with an opt-in. Unsupported terminals ignore
+//! the sequence.
+
+use crate::components::problems::{Problem, Problems, Severity};
+
+/// The maximum length in bytes of copied text.
+///
+/// Terminals limit the length of OSC 52 sequences, e.g. hterm to 100 000 bytes. 74 994 bytes of
+/// text encode to 99 992 bytes of base64. Longer text is truncated with a warning in
+/// [`SharedState::problems`](crate::SharedState::problems).
+pub const MAX_COPY_BYTES: usize = 74_994;
+
+/// The maximum length in bytes of pasted text. Longer pastes are truncated with a warning in
+/// [`SharedState::problems`](crate::SharedState::problems).
+pub const MAX_PASTE_BYTES: usize = 1 << 20;
+
+const SOURCE: &str = "clipboard";
+
+/// The clipboard of the terminal.
+#[derive(Debug, Default)]
+pub struct Clipboard {
+    /// The OSC 52 sequence of the last copy of this frame.
+    pending: Option<String>,
+    /// The length of the last copy of this frame, if it was truncated.
+    truncated_from: Option<usize>,
+}
+
+impl Clipboard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Copies `text` to the clipboard with the next frame.
+    ///
+    /// Only the last copy of a frame is written. Text longer than [`MAX_COPY_BYTES`] is truncated.
+    pub fn copy(&mut self, text: &str) {
+        let truncated = truncate(text, MAX_COPY_BYTES);
+        self.truncated_from = (truncated.len() < text.len()).then_some(text.len());
+        self.pending = Some(osc52(truncated));
+    }
+
+    /// Returns true if a copy is queued for the next frame.
+    pub fn has_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Returns the queued OSC 52 sequence and reports a truncated copy.
+    pub(crate) fn take_pending(&mut self, problems: &mut Problems) -> Option<String> {
+        let sequence = self.pending.take()?;
+        match self.truncated_from.take() {
+            Some(len) => problems.report(
+                Problem::new(
+                    Severity::Warning,
+                    SOURCE,
+                    format!("Copied text was truncated from {len} to {MAX_COPY_BYTES} bytes"),
+                )
+                .with_dedup_key("copy"),
+            ),
+            None => problems.clear(SOURCE, "copy"),
+        }
+        Some(sequence)
+    }
+}
+
+/// Truncates a paste to [`MAX_PASTE_BYTES`] and reports it.
+pub(crate) fn limit_paste(mut text: String, problems: &mut Problems) -> String {
+    let len = truncate(&text, MAX_PASTE_BYTES).len();
+    if len < text.len() {
+        problems.report(
+            Problem::new(
+                Severity::Warning,
+                SOURCE,
+                format!(
+                    "Pasted text was truncated from {} to {len} bytes",
+                    text.len()
+                ),
+            )
+            .with_dedup_key("paste"),
+        );
+        text.truncate(len);
+    }
+    text
+}
+
+/// Returns the longest prefix of `text` that is at most `max_len` bytes and ends at a char
+/// boundary.
+fn truncate(text: &str, max_len: usize) -> &str {
+    if text.len() <= max_len {
+        return text;
+    }
+    let mut end = max_len;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+/// Returns the OSC 52 sequence that sets the clipboard to `text`.
+pub fn osc52(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", base64(text.as_bytes()))
+}
+
+/// Encodes `bytes` as standard base64 with padding.
+pub(crate) fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osc52_bytes() {
+        assert_eq!(osc52("hello"), "\x1b]52;c;aGVsbG8=\x07");
+        assert_eq!(
+            osc52("teng ▀").as_bytes(),
+            b"\x1b]52;c;dGVuZyDiloA=\x07".as_slice()
+        );
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"ab"), "YWI=");
+        assert_eq!(base64(b"abc"), "YWJj");
+
+        let mut problems = Problems::new();
+        let mut clipboard = Clipboard::new();
+        clipboard.copy("first");
+        clipboard.copy("hello");
+        assert_eq!(
+            clipboard.take_pending(&mut problems).as_deref(),
+            Some("\x1b]52;c;aGVsbG8=\x07")
+        );
+        assert!(clipboard.take_pending(&mut problems).is_none());
+        assert!(problems.is_empty());
+    }
+
+    #[test]
+    fn test_truncation_keeps_char_boundaries() {
+        let mut problems = Problems::new();
+        let mut clipboard = Clipboard::new();
+        clipboard.copy(&"é".repeat(MAX_COPY_BYTES));
+        let sequence = clipboard.take_pending(&mut problems).unwrap();
+        // 37 497 two-byte chars fit
+        assert_eq!(sequence, osc52(&"é".repeat(MAX_COPY_BYTES / 2)));
+        assert_eq!(problems.len(), 1);
+
+        let paste = limit_paste("ab".repeat(MAX_PASTE_BYTES), &mut problems);
+        assert_eq!(paste.len(), MAX_PASTE_BYTES);
+        assert_eq!(problems.len(), 2);
+        assert_eq!(limit_paste("short".to_string(), &mut problems), "short");
+    }
+}
@@ -8,6 +8,19 @@
 
 /// A simple fixed update runner that accumulates time and runs fixed update at a fixed rate.
 ///
+/// Fuel it with the `dt` of [`Component::update`](crate::components::Component::update). That
+/// `dt` never includes time while the game was paused with
+/// [`SharedState::paused`](crate::SharedState::paused), unless the component
+/// [runs while paused](crate::components::Component::runs_while_paused). Such components should
+/// not fuel the runner while paused, or [`reset`](Self::reset) it when resuming, which also
+/// clears the [`alpha`](Self::alpha).
+///
+/// A single long frame, e.g. while the terminal is resized, would otherwise be caught up with many
+/// ticks at once. If a tick takes longer than `fixed_dt` to compute, the next frame is even longer,
+/// and the game never catches up. [`with_max_ticks_per_frame`](Self::with_max_ticks_per_frame)
+/// drops the fuel beyond a number of ticks instead, so the simulation slows down rather than
+/// freezing.
+///
 /// # Example
 /// ```
 /// use teng::util::fixedupdate::FixedUpdateRunner;
@@ -19,10 +32,17 @@
 ///    runner.consume();
 ///    // run fragment of code that should run at fixed rate
 /// }
+/// // how far the render is between the last two ticks, to interpolate positions
+/// let alpha = runner.alpha();
 /// ```
 pub struct FixedUpdateRunner {
     dt_accumulator: f64,
     fixed_dt: f64,
+    max_ticks_per_frame: Option<u64>,
+    /// The ticks dropped by the last call to `fuel`.
+    last_dropped_ticks: u64,
+    /// The ticks dropped since the runner was created.
+    dropped_ticks: u64,
 }
 
 impl FixedUpdateRunner {
@@ -31,20 +51,47 @@ impl FixedUpdateRunner {
         Self {
             dt_accumulator: 0.0,
             fixed_dt,
+            max_ticks_per_frame: None,
+            last_dropped_ticks: 0,
+            dropped_ticks: 0,
         }
     }
 
     /// Create a new fixed update runner that consumes `1.0 / rate` amount of time every fixed update.
     pub fn new_from_rate_per_second(rate: f64) -> Self {
-        Self {
-            dt_accumulator: 0.0,
-            fixed_dt: 1.0 / rate,
-        }
+        Self::new(1.0 / rate)
+    }
+
+    /// Caps the ticks available after fueling at `max_ticks`. Fuel beyond that is dropped, see
+    /// [`last_dropped_ticks`](Self::last_dropped_ticks).
+    pub fn with_max_ticks_per_frame(mut self, max_ticks: u64) -> Self {
+        self.max_ticks_per_frame = Some(max_ticks);
+        self
     }
 
     /// Add time to the accumulator. Call this with the delta time from the game loop.
+    ///
+    /// With a [cap](Self::with_max_ticks_per_frame), whole ticks beyond the cap are dropped. The
+    /// fraction of a tick is kept, so that the [`alpha`](Self::alpha) stays continuous.
     pub fn fuel(&mut self, dt: f64) {
         self.dt_accumulator += dt;
+        self.last_dropped_ticks = 0;
+        if let Some(max_ticks) = self.max_ticks_per_frame {
+            let excess = self.available_ticks().saturating_sub(max_ticks);
+            self.dt_accumulator -= excess as f64 * self.fixed_dt;
+            self.last_dropped_ticks = excess;
+            self.dropped_ticks += excess;
+        }
+    }
+
+    /// Returns the number of ticks dropped by the last [`fuel`](Self::fuel) because of the cap.
+    pub fn last_dropped_ticks(&self) -> u64 {
+        self.last_dropped_ticks
+    }
+
+    /// Returns the number of ticks dropped because of the cap since the runner was created.
+    pub fn dropped_ticks(&self) -> u64 {
+        self.dropped_ticks
     }
 
     /// Returns true if there is enough time in the accumulator to run a fixed update.
@@ -57,6 +104,11 @@ impl FixedUpdateRunner {
         self.dt_accumulator -= self.fixed_dt;
     }
 
+    /// Discards the accumulated time, e.g. when resuming after a pause.
+    pub fn reset(&mut self) {
+        self.dt_accumulator = 0.0;
+    }
+
     /// Available ticks to consume.
     pub fn available_ticks(&self) -> u64 {
         (self.dt_accumulator / self.fixed_dt).floor() as u64
@@ -67,10 +119,26 @@ impl FixedUpdateRunner {
         self.fixed_dt
     }
 
-    /// Change the fixed delta time.
+    /// Change the fixed delta time. The accumulated time is kept, so it is spent at the new rate.
     pub fn set_fixed_dt(&mut self, fixed_dt: f64) {
         self.fixed_dt = fixed_dt;
     }
+
+    /// Change the rate of fixed updates, e.g. for a slow motion effect. The accumulated time is
+    /// kept, so it is spent at the new rate.
+    pub fn set_rate_per_second(&mut self, rate: f64) {
+        self.set_fixed_dt(1.0 / rate);
+    }
+
+    /// Returns how far the accumulated time is into the next tick, from 0 up to but excluding 1.
+    ///
+    /// After consuming all available ticks, this is how far the current frame is between the
+    /// last tick and the next one, e.g. to render entities at
+    /// `previous + (current - previous) * alpha` for smooth movement at frame rates above the
+    /// tick rate.
+    pub fn alpha(&self) -> f64 {
+        (self.dt_accumulator / self.fixed_dt).fract()
+    }
 }
 
 #[cfg(test)]
@@ -105,4 +173,56 @@ mod tests {
         runner.fuel(0.5);
         assert_eq!(runner.available_ticks(), (0.5 / (1.0 / 60.0)) as u64);
     }
+
+    #[test]
+    fn test_max_ticks_per_frame() {
+        let mut runner = FixedUpdateRunner::new(0.1).with_max_ticks_per_frame(3);
+        // a stall of a second would be ten ticks
+        runner.fuel(1.05);
+        assert_eq!(runner.available_ticks(), 3);
+        assert_eq!(runner.last_dropped_ticks(), 7);
+        let mut ticks = 0;
+        while runner.has_gas() {
+            runner.consume();
+            ticks += 1;
+        }
+        assert_eq!(ticks, 3);
+        // the fraction of a tick is kept
+        assert!((runner.alpha() - 0.5).abs() < 1e-9);
+
+        runner.fuel(0.1);
+        assert_eq!(runner.available_ticks(), 1);
+        assert_eq!(runner.last_dropped_ticks(), 0);
+        assert_eq!(runner.dropped_ticks(), 7);
+    }
+
+    #[test]
+    fn test_alpha_after_partial_consumption() {
+        let mut runner = FixedUpdateRunner::new(0.25);
+        runner.fuel(0.6);
+        assert!((runner.alpha() - 0.4).abs() < 1e-9);
+        runner.consume();
+        runner.consume();
+        assert_eq!(runner.available_ticks(), 0);
+        assert!((runner.alpha() - 0.4).abs() < 1e-9);
+        runner.reset();
+        assert_eq!(runner.alpha(), 0.0);
+    }
+
+    #[test]
+    fn test_rate_change_keeps_fuel() {
+        let mut runner = FixedUpdateRunner::new_from_rate_per_second(8.0);
+        runner.fuel(0.5);
+        runner.consume();
+        // the 0.375s left are six ticks at the doubled rate
+        runner.set_rate_per_second(16.0);
+        assert_eq!(runner.fixed_dt(), 0.0625);
+        assert_eq!(runner.available_ticks(), 6);
+        runner.fuel(0.03125);
+        for _ in 0..6 {
+            runner.consume();
+        }
+        assert!(!runner.has_gas());
+        assert_eq!(runner.alpha(), 0.5);
+    }
 }
@@ -0,0 +1,265 @@
+//! A common interface for 2D containers and algorithms that work on any of them.
+//!
+//! **teng** has two 2D containers: [`Display`] (indexed by `usize`, screen-oriented) and
+//! [`PlanarVec`] (indexed by `i64` within arbitrary [`Bounds`], world-oriented).
+//! The [`Grid`] trait captures their shared surface using `i64` coordinates, so that the
+//! algorithms in this module work on either of them:
+//!
+//! * [`flood_fill`]: all cells connected to a start cell.
+//! * [`distance_field`]: breadth-first distances to a target, e.g. for pathfinding.
+//! * [`solidity`]: an `is_solid(x, y)` adapter, e.g. for [`ParticleSystem::step_with_collision`].
+//!
+//! A [`Display`] has the bounds `(0, 0)` to `(width - 1, height - 1)`.
+//! The containers' inherent methods are unaffected, so to call a `Grid` method on a container
+//! that has an inherent method of the same name, use the fully qualified syntax, e.g.
+//! `Grid::bounds(&display)`.
+//!
+//! [`Display`]: crate::rendering::display::Display
+//! [`ParticleSystem::step_with_collision`]: crate::util::verlet::ParticleSystem::step_with_collision
+
+use crate::rendering::display::Display;
+use crate::util::planarvec::{Bounds, PlanarVec};
+use std::collections::VecDeque;
+
+/// A 2D container with `i64` coordinates.
+pub trait Grid<T> {
+    /// Returns the bounds of all cells in the grid.
+    fn bounds(&self) -> Bounds;
+
+    /// Returns a reference to the cell at `(x, y)`, or `None` if it is out of bounds.
+    fn get_cell(&self, x: i64, y: i64) -> Option<&T>;
+
+    /// Returns a mutable reference to the cell at `(x, y)`, or `None` if it is out of bounds.
+    fn get_cell_mut(&mut self, x: i64, y: i64) -> Option<&mut T>;
+
+    /// Sets every cell to `value`.
+    fn fill_cells(&mut self, value: T)
+    where
+        T: Clone;
+
+    /// Returns an iterator over all cells and their coordinates, row by row.
+    fn iter_cells<'a>(&'a self) -> impl Iterator<Item = (i64, i64, &'a T)>
+    where
+        T: 'a,
+    {
+        let bounds = self.bounds();
+        (bounds.min_y..=bounds.max_y).flat_map(move |y| {
+            (bounds.min_x..=bounds.max_x)
+                .map(move |x| (x, y, self.get_cell(x, y).expect("cell in bounds")))
+        })
+    }
+}
+
+impl<T> Grid<T> for Display<T> {
+    fn bounds(&self) -> Bounds {
+        if self.width() == 0 || self.height() == 0 {
+            return Bounds::empty();
+        }
+        Bounds {
+            min_x: 0,
+            max_x: self.width() as i64 - 1,
+            min_y: 0,
+            max_y: self.height() as i64 - 1,
+        }
+    }
+
+    fn get_cell(&self, x: i64, y: i64) -> Option<&T> {
+        if x < 0 || y < 0 {
+            return None;
+        }
+        self.get(x as usize, y as usize)
+    }
+
+    fn get_cell_mut(&mut self, x: i64, y: i64) -> Option<&mut T> {
+        if x < 0 || y < 0 {
+            return None;
+        }
+        self.get_mut(x as usize, y as usize)
+    }
+
+    fn fill_cells(&mut self, value: T)
+    where
+        T: Clone,
+    {
+        self.fill(value);
+    }
+
+    fn iter_cells<'a>(&'a self) -> impl Iterator<Item = (i64, i64, &'a T)>
+    where
+        T: 'a,
+    {
+        self.iter().map(|(x, y, t)| (x as i64, y as i64, t))
+    }
+}
+
+impl<T> Grid<T> for PlanarVec<T> {
+    fn bounds(&self) -> Bounds {
+        PlanarVec::bounds(self)
+    }
+
+    fn get_cell(&self, x: i64, y: i64) -> Option<&T> {
+        self.get(x, y)
+    }
+
+    fn get_cell_mut(&mut self, x: i64, y: i64) -> Option<&mut T> {
+        self.get_mut(x, y)
+    }
+
+    fn fill_cells(&mut self, value: T)
+    where
+        T: Clone,
+    {
+        self.clear(value);
+    }
+}
+
+const NEIGHBORS_4: [(i64, i64); 4] = [(0, 1), (0, -1), (1, 0), (-1, 0)];
+
+/// Returns all cells that are 4-connected to `start` through cells for which `is_fillable`
+/// returns true, in breadth-first order.
+///
+/// Returns an empty vector if `start` is out of bounds or not fillable itself.
+pub fn flood_fill<T>(
+    grid: &impl Grid<T>,
+    start: (i64, i64),
+    mut is_fillable: impl FnMut(&T) -> bool,
+) -> Vec<(i64, i64)> {
+    let bounds = grid.bounds();
+    let mut visited = PlanarVec::new(bounds, false);
+    let mut filled = vec![];
+    let mut queue = VecDeque::from([start]);
+    while let Some((x, y)) = queue.pop_front() {
+        match visited.get_mut(x, y) {
+            Some(v) if !*v => *v = true,
+            _ => continue,
+        }
+        if !grid.get_cell(x, y).is_some_and(&mut is_fillable) {
+            continue;
+        }
+        filled.push((x, y));
+        for (dx, dy) in NEIGHBORS_4 {
+            queue.push_back((x + dx, y + dy));
+        }
+    }
+    filled
+}
+
+/// Computes the 4-connected breadth-first distance of every cell to `target`, moving only
+/// through cells for which `is_passable` returns true.
+///
+/// The returned `PlanarVec` has the same bounds as `grid`. Unreachable and impassable cells
+/// are `None`.
+pub fn distance_field<T>(
+    grid: &impl Grid<T>,
+    target: (i64, i64),
+    mut is_passable: impl FnMut(&T) -> bool,
+) -> PlanarVec<Option<u32>> {
+    let mut distances = PlanarVec::new(grid.bounds(), None);
+    let mut queue = VecDeque::from([(target, 0)]);
+    while let Some(((x, y), dist)) = queue.pop_front() {
+        if !matches!(distances.get(x, y), Some(None)) {
+            continue;
+        }
+        if !grid.get_cell(x, y).is_some_and(&mut is_passable) {
+            continue;
+        }
+        distances[(x, y)] = Some(dist);
+        for (dx, dy) in NEIGHBORS_4 {
+            queue.push_back(((x + dx, y + dy), dist + 1));
+        }
+    }
+    distances
+}
+
+/// Returns an `is_solid(x, y)` closure for `grid`. Out of bounds cells are solid.
+pub fn solidity<'a, T>(
+    grid: &'a impl Grid<T>,
+    is_solid: impl Fn(&T) -> bool + 'a,
+) -> impl Fn(i64, i64) -> bool + 'a {
+    move |x, y| grid.get_cell(x, y).is_none_or(&is_solid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAP: [&str; 5] = [
+        "..#....", //
+        "..#.##.", //
+        "..#.#.#", //
+        "....##.", //
+        "###....", //
+    ];
+
+    fn display() -> Display<bool> {
+        let mut display = Display::new(7, 5, false);
+        for (y, row) in MAP.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                display[(x, y)] = c == '#';
+            }
+        }
+        display
+    }
+
+    fn planar_vec() -> PlanarVec<bool> {
+        let display = display();
+        let mut planar_vec = PlanarVec::new(Grid::bounds(&display), true);
+        for (x, y, wall) in display.iter_cells() {
+            planar_vec[(x, y)] = *wall;
+        }
+        planar_vec
+    }
+
+    #[test]
+    fn test_grid_impls_agree() {
+        let display = display();
+        let planar_vec = planar_vec();
+        assert_eq!(Grid::bounds(&display), Grid::bounds(&planar_vec));
+        assert!(display.iter_cells().eq(planar_vec.iter_cells()));
+        assert_eq!(display.get_cell(-1, 0), None);
+        assert_eq!(planar_vec.get_cell(-1, 0), None);
+        assert_eq!(display.get_cell(7, 0), None);
+        assert_eq!(planar_vec.get_cell(7, 0), None);
+    }
+
+    #[test]
+    fn test_flood_fill() {
+        let from_display = flood_fill(&display(), (0, 0), |wall| !wall);
+        let from_planar_vec = flood_fill(&planar_vec(), (0, 0), |wall| !wall);
+        assert_eq!(from_display, from_planar_vec);
+        // the enclosed cell (5, 2) is not reachable, all other open cells are
+        let open_cells = MAP.iter().flat_map(|r| r.chars()).filter(|c| *c == '.');
+        assert_eq!(from_display.len(), open_cells.count() - 1);
+        assert!(!from_display.contains(&(5, 2)));
+        assert!(flood_fill(&display(), (2, 0), |wall| !wall).is_empty());
+    }
+
+    #[test]
+    fn test_distance_field() {
+        let from_display = distance_field(&display(), (0, 0), |wall| !wall);
+        let from_planar_vec = distance_field(&planar_vec(), (0, 0), |wall| !wall);
+        assert!(from_display.iter_cells().eq(from_planar_vec.iter_cells()));
+        assert_eq!(from_display[(0, 0)], Some(0));
+        assert_eq!(from_display[(1, 3)], Some(4));
+        // around the wall
+        assert_eq!(from_display[(3, 0)], Some(9));
+        assert_eq!(from_display[(2, 0)], None);
+        assert_eq!(from_display[(5, 2)], None);
+    }
+
+    #[test]
+    fn test_solidity() {
+        let display = display();
+        let planar_vec = planar_vec();
+        let display_solid = solidity(&display, |wall| *wall);
+        let planar_vec_solid = solidity(&planar_vec, |wall| *wall);
+        for y in -1..=5 {
+            for x in -1..=7 {
+                assert_eq!(display_solid(x, y), planar_vec_solid(x, y));
+            }
+        }
+        assert!(display_solid(-1, 0));
+        assert!(display_solid(2, 0));
+        assert!(!display_solid(0, 0));
+    }
+}
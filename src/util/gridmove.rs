@@ -0,0 +1,459 @@
+//! Grid-aligned movement that glides smoothly between tiles.
+//!
+//! Many grid games have entities that logically occupy a single tile, but visually move between
+//! tiles over a few frames. A [`GridMover`] handles the bookkeeping for such an entity:
+//!
+//! * Moves are requested one tile at a time with [`GridMover::try_move`], and advanced with
+//!   [`GridMover::update`] at a speed in tiles per second. Progress that overshoots the target tile
+//!   carries over into the next move, so consecutive moves have a constant speed regardless of
+//!   the frame rate.
+//! * While a move is in progress, further requests are rejected, or buffered in a single queue slot
+//!   if enabled with [`GridMover::with_queue`].
+//! * [`GridMover::visual_pos`] returns the interpolated position to render at, optionally eased
+//!   per step with [`GridMover::with_easing`].
+//! * For held direction keys, [`GridMover::press`] and [`GridMover::release`] keep moving as long as
+//!   a direction is held. With a [tap threshold](GridMover::with_tap_threshold), briefly tapping a
+//!   new direction only turns the mover to face it.
+//!
+//! Walkability is checked with an `is_walkable(x, y)` closure when a move starts. With
+//! [`GridMover::with_halfway_check`], it is checked again at the halfway point of the move, see
+//! there for the exact rule.
+//!
+//! # Example
+//! ```
+//! use teng::util::gridmove::{Direction, GridMover, GridMoveEvent};
+//!
+//! let walls = [(2, 0)];
+//! let is_walkable = |x, y| !walls.contains(&(x, y));
+//!
+//! let mut mover = GridMover::new((0, 0)).with_queue(true);
+//! assert!(mover.try_move(Direction::Right, is_walkable));
+//! // mid-move, the next move is buffered
+//! assert!(mover.try_move(Direction::Right, is_walkable));
+//!
+//! // at 4 tiles per second, a tile takes 0.25 seconds
+//! let events = mover.update(0.25, 4.0, is_walkable);
+//! assert_eq!(events, vec![GridMoveEvent::Arrived((1, 0))]);
+//! // the buffered move ran into the wall, so the mover only turned
+//! assert_eq!(mover.tile(), (1, 0));
+//! assert!(!mover.is_moving());
+//! assert_eq!(mover.visual_pos(), (1.0, 0.0));
+//! ```
+
+/// One of the four grid directions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    /// Returns the offset of one step in this direction, with y growing downwards.
+    pub fn delta(self) -> (i64, i64) {
+        match self {
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+        }
+    }
+
+    /// Returns the tile one step from `tile` in this direction.
+    pub fn step(self, (x, y): (i64, i64)) -> (i64, i64) {
+        let (dx, dy) = self.delta();
+        (x + dx, y + dy)
+    }
+}
+
+/// Something that happened during a [`GridMover::update`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GridMoveEvent {
+    /// The mover arrived at the given tile.
+    Arrived((i64, i64)),
+    /// The target tile became unwalkable before the halfway point, so the mover turned back
+    /// towards the given tile it came from. See [`GridMover::with_halfway_check`].
+    TurnedBack((i64, i64)),
+}
+
+/// A direction held with [`GridMover::press`].
+#[derive(Debug, Clone, Copy)]
+struct Held {
+    direction: Direction,
+    /// How long the direction has been held, in seconds.
+    time: f64,
+    /// Whether the press turned the mover, so it only moves after the tap threshold.
+    turned: bool,
+}
+
+/// An entity that occupies a tile and glides to neighboring tiles.
+#[derive(Debug, Clone)]
+pub struct GridMover {
+    tile: (i64, i64),
+    target: Option<(i64, i64)>,
+    /// The progress from `tile` to `target`, in `0.0..1.0`.
+    progress: f64,
+    facing: Direction,
+    /// Whether the current move is a return after [`GridMoveEvent::TurnedBack`].
+    returning: bool,
+    queue_enabled: bool,
+    queued: Option<Direction>,
+    easing: Option<fn(f64) -> f64>,
+    halfway_check: bool,
+    tap_threshold: f64,
+    held: Option<Held>,
+}
+
+impl GridMover {
+    /// Creates a mover standing on `tile`, facing down.
+    pub fn new(tile: (i64, i64)) -> Self {
+        Self {
+            tile,
+            target: None,
+            progress: 0.0,
+            facing: Direction::Down,
+            returning: false,
+            queue_enabled: false,
+            queued: None,
+            easing: None,
+            halfway_check: false,
+            tap_threshold: 0.0,
+            held: None,
+        }
+    }
+
+    /// Enables or disables buffering a single move that is requested while moving.
+    ///
+    /// A buffered move starts when the current one arrives, and a newer request replaces it.
+    pub fn with_queue(mut self, enabled: bool) -> Self {
+        self.queue_enabled = enabled;
+        self
+    }
+
+    /// Eases the visual position of every step with `easing`, which maps the linear progress in
+    /// `0.0..=1.0` to the eased progress, e.g. `|t| t * t * (3.0 - 2.0 * t)`.
+    ///
+    /// The logical progress and timing are unaffected.
+    pub fn with_easing(mut self, easing: fn(f64) -> f64) -> Self {
+        self.easing = Some(easing);
+        self
+    }
+
+    /// Enables checking the target tile again when the mover passes the halfway point of a move.
+    ///
+    /// If the target is no longer walkable at that point, for example because another entity
+    /// moved onto it, the move is reverted: the mover turns back and glides to the tile it came
+    /// from, which is not checked again, and [`GridMoveEvent::TurnedBack`] is reported. Once the
+    /// halfway point is passed, the move always completes.
+    pub fn with_halfway_check(mut self, enabled: bool) -> Self {
+        self.halfway_check = enabled;
+        self
+    }
+
+    /// Makes [`GridMover::press`] of a direction the mover is not facing only turn it, unless the
+    /// direction is held for at least `seconds`. `0.0` disables turning without moving.
+    pub fn with_tap_threshold(mut self, seconds: f64) -> Self {
+        self.tap_threshold = seconds.max(0.0);
+        self
+    }
+
+    /// Returns the tile the mover stands on, or the tile it is moving away from.
+    pub fn tile(&self) -> (i64, i64) {
+        self.tile
+    }
+
+    /// Returns the tile the mover is moving to, if any.
+    pub fn target(&self) -> Option<(i64, i64)> {
+        self.target
+    }
+
+    /// Returns the progress of the current move in `0.0..1.0`, or `0.0` when standing.
+    pub fn progress(&self) -> f64 {
+        self.progress
+    }
+
+    /// Returns the direction the mover faces.
+    pub fn facing(&self) -> Direction {
+        self.facing
+    }
+
+    /// Returns true if the mover is between two tiles.
+    pub fn is_moving(&self) -> bool {
+        self.target.is_some()
+    }
+
+    /// Returns the buffered move, see [`GridMover::with_queue`].
+    pub fn queued(&self) -> Option<Direction> {
+        self.queued
+    }
+
+    /// Teleports the mover to `tile`, cancelling the current and the buffered move.
+    pub fn set_tile(&mut self, tile: (i64, i64)) {
+        self.tile = tile;
+        self.target = None;
+        self.progress = 0.0;
+        self.returning = false;
+        self.queued = None;
+    }
+
+    /// Returns the position to render the mover at, between its tile and its target.
+    pub fn visual_pos(&self) -> (f64, f64) {
+        let (x, y) = (self.tile.0 as f64, self.tile.1 as f64);
+        let Some((target_x, target_y)) = self.target else {
+            return (x, y);
+        };
+        let t = self
+            .easing
+            .map_or(self.progress, |ease| ease(self.progress));
+        (x + (target_x as f64 - x) * t, y + (target_y as f64 - y) * t)
+    }
+
+    /// Requests a move of one tile in `direction`. Returns true if the move started, or was
+    /// buffered because the mover is moving and the queue is enabled.
+    ///
+    /// A standing mover turns to face `direction` even if the move is rejected because the target
+    /// is not walkable.
+    pub fn try_move(
+        &mut self,
+        direction: Direction,
+        is_walkable: impl Fn(i64, i64) -> bool,
+    ) -> bool {
+        if self.is_moving() {
+            if self.queue_enabled {
+                self.queued = Some(direction);
+            }
+            return self.queue_enabled;
+        }
+        self.start(direction, &is_walkable)
+    }
+
+    /// Marks `direction` as held, to be called when its key is pressed or every frame it is held.
+    ///
+    /// The mover keeps moving in a held direction, starting in the next [`GridMover::update`]. If
+    /// the mover stands and faces another direction, and a tap threshold is set, it turns right away
+    /// and only starts moving once the direction is held for the threshold.
+    pub fn press(&mut self, direction: Direction) {
+        if self.held.is_some_and(|held| held.direction == direction) {
+            return;
+        }
+        let turned = !self.is_moving() && self.tap_threshold > 0.0 && self.facing != direction;
+        if turned {
+            self.facing = direction;
+        }
+        self.held = Some(Held {
+            direction,
+            time: 0.0,
+            turned,
+        });
+    }
+
+    /// Releases the held direction, see [`GridMover::press`].
+    pub fn release(&mut self) {
+        self.held = None;
+    }
+
+    /// Advances the mover by `dt` seconds at `speed` tiles per second, and returns what happened.
+    ///
+    /// Buffered and held moves start as soon as the current move arrives, with the remaining
+    /// progress of this update.
+    pub fn update(
+        &mut self,
+        dt: f64,
+        speed: f64,
+        is_walkable: impl Fn(i64, i64) -> bool,
+    ) -> Vec<GridMoveEvent> {
+        let mut events = vec![];
+        if let Some(held) = &mut self.held {
+            held.time += dt;
+        }
+        let mut remaining = dt * speed;
+        loop {
+            let Some(target) = self.target else {
+                if !self.start_next(&is_walkable) {
+                    break;
+                }
+                continue;
+            };
+            let before = self.progress;
+            self.progress += remaining;
+            if self.halfway_check
+                && !self.returning
+                && before < 0.5
+                && self.progress >= 0.5
+                && !is_walkable(target.0, target.1)
+            {
+                // mirror the move at the halfway point
+                remaining = self.progress - 0.5;
+                self.progress = 0.5;
+                self.target = Some(self.tile);
+                self.tile = target;
+                self.returning = true;
+                self.queued = None;
+                events.push(GridMoveEvent::TurnedBack(self.target.unwrap()));
+                continue;
+            }
+            if self.progress < 1.0 {
+                break;
+            }
+            remaining = self.progress - 1.0;
+            self.tile = target;
+            self.target = None;
+            self.progress = 0.0;
+            self.returning = false;
+            events.push(GridMoveEvent::Arrived(target));
+        }
+        events
+    }
+
+    /// Starts the buffered move or the held move of a standing mover. Returns true if one started.
+    fn start_next(&mut self, is_walkable: &impl Fn(i64, i64) -> bool) -> bool {
+        if let Some(direction) = self.queued.take() {
+            return self.start(direction, is_walkable);
+        }
+        match self.held {
+            Some(held) if !held.turned || held.time >= self.tap_threshold => {
+                self.start(held.direction, is_walkable)
+            }
+            _ => false,
+        }
+    }
+
+    fn start(&mut self, direction: Direction, is_walkable: &impl Fn(i64, i64) -> bool) -> bool {
+        self.facing = direction;
+        let target = direction.step(self.tile);
+        if !is_walkable(target.0, target.1) {
+            return false;
+        }
+        self.target = Some(target);
+        self.progress = 0.0;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(_x: i64, _y: i64) -> bool {
+        true
+    }
+
+    #[test]
+    fn test_rejection_and_queueing() {
+        let wall = |x, y| (x, y) != (0, -1);
+
+        let mut mover = GridMover::new((0, 0));
+        // a blocked move only turns
+        assert!(!mover.try_move(Direction::Up, wall));
+        assert_eq!(mover.facing(), Direction::Up);
+        assert!(!mover.is_moving());
+
+        assert!(mover.try_move(Direction::Right, wall));
+        // rejected mid-move without a queue
+        assert!(!mover.try_move(Direction::Down, wall));
+        assert_eq!(
+            mover.update(1.0, 1.0, wall),
+            vec![GridMoveEvent::Arrived((1, 0))]
+        );
+        assert!(!mover.is_moving());
+
+        let mut mover = GridMover::new((0, 0)).with_queue(true);
+        assert!(mover.try_move(Direction::Right, open));
+        assert!(mover.try_move(Direction::Up, open));
+        // the newest request replaces the buffered one
+        assert!(mover.try_move(Direction::Down, open));
+        assert_eq!(mover.queued(), Some(Direction::Down));
+        // the buffered move continues with the remaining progress
+        let events = mover.update(1.25, 1.0, open);
+        assert_eq!(events, vec![GridMoveEvent::Arrived((1, 0))]);
+        assert_eq!(mover.target(), Some((1, 1)));
+        assert_eq!(mover.progress(), 0.25);
+        assert_eq!(mover.facing(), Direction::Down);
+    }
+
+    #[test]
+    fn test_tap_to_turn() {
+        let mut mover = GridMover::new((0, 0)).with_tap_threshold(0.1);
+        // facing down, so pressing down moves right away
+        mover.press(Direction::Down);
+        mover.update(0.01, 10.0, open);
+        assert_eq!(mover.target(), Some((0, 1)));
+        mover.release();
+        mover.update(0.1, 10.0, open);
+        assert_eq!(mover.tile(), (0, 1));
+
+        // a short tap only turns
+        mover.press(Direction::Left);
+        assert_eq!(mover.facing(), Direction::Left);
+        mover.update(0.05, 10.0, open);
+        assert!(!mover.is_moving());
+        mover.release();
+        mover.update(0.1, 10.0, open);
+        assert_eq!(mover.tile(), (0, 1));
+
+        // holding past the threshold moves, and keeps moving
+        mover.press(Direction::Right);
+        mover.update(0.09, 10.0, open);
+        assert!(!mover.is_moving());
+        mover.update(0.02, 10.0, open);
+        assert_eq!(mover.target(), Some((1, 1)));
+        let events = mover.update(0.15, 10.0, open);
+        assert_eq!(events, vec![GridMoveEvent::Arrived((1, 1))]);
+        assert_eq!(mover.target(), Some((2, 1)));
+    }
+
+    #[test]
+    fn test_interpolation_is_continuous() {
+        let mut mover = GridMover::new((0, 0)).with_queue(true);
+        let mut last = mover.visual_pos();
+        let directions = [Direction::Right, Direction::Right, Direction::Down];
+        let mut next = directions.iter();
+        mover.try_move(*next.next().unwrap(), open);
+        for _ in 0..60 {
+            if mover.queued().is_none()
+                && let Some(&direction) = next.next()
+            {
+                mover.try_move(direction, open);
+            }
+            mover.update(1.0 / 60.0, 3.7, open);
+            let pos = mover.visual_pos();
+            let step = (pos.0 - last.0).abs() + (pos.1 - last.1).abs();
+            // every frame moves the same distance, also across tiles
+            if mover.is_moving() {
+                assert!((step - 3.7 / 60.0).abs() < 1e-9, "jumped {step} to {pos:?}");
+            } else {
+                assert!(step <= 3.7 / 60.0 + 1e-9);
+            }
+            last = pos;
+        }
+        assert_eq!(mover.tile(), (2, 1));
+        assert_eq!(last, (2.0, 1.0));
+
+        // easing changes the visual position, but not the timing
+        let mut mover = GridMover::new((0, 0)).with_easing(|t| t * t);
+        mover.try_move(Direction::Right, open);
+        mover.update(0.5, 1.0, open);
+        assert_eq!(mover.visual_pos(), (0.25, 0.0));
+    }
+
+    #[test]
+    fn test_halfway_check() {
+        let mut mover = GridMover::new((0, 0)).with_halfway_check(true);
+        mover.try_move(Direction::Right, open);
+        mover.update(0.4, 1.0, open);
+        // the target became occupied
+        let blocked = |x, y| (x, y) != (1, 0);
+        let events = mover.update(0.2, 1.0, blocked);
+        assert_eq!(events, vec![GridMoveEvent::TurnedBack((0, 0))]);
+        assert!((mover.visual_pos().0 - 0.4).abs() < 1e-9);
+        let events = mover.update(0.5, 1.0, blocked);
+        assert_eq!(events, vec![GridMoveEvent::Arrived((0, 0))]);
+
+        // past the halfway point, the move completes
+        mover.try_move(Direction::Right, open);
+        mover.update(0.6, 1.0, open);
+        let events = mover.update(0.5, 1.0, blocked);
+        assert_eq!(events, vec![GridMoveEvent::Arrived((1, 0))]);
+    }
+}
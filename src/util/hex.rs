@@ -0,0 +1,619 @@
+//! Hexagonal grids, parallel to the square grids of [`grid`](crate::util::grid).
+//!
+//! Hexes are addressed with axial coordinates, see [`Hex`]. Its methods cover the usual hex
+//! math: neighbors, distances, lines, ranges and rings. Rectangular maps are easier to describe in
+//! [`OffsetCoord`]s, where every row of hexes is a row of the map and odd rows are shifted right by
+//! half a hex.
+//!
+//! Hexes are pointy-top, so that rows of hexes map to rows of terminal cells. A [`HexLayout`]
+//! converts between hexes and terminal cells, either with one cell per hex and a gap in between,
+//! or with two cells per hex for a better aspect ratio:
+//! ```text
+//! Compact:       DoubleWidth:
+//! a b c d        <><><><>
+//!  e f g h        <><><><>
+//! i j k l        <><><><>
+//! ```
+//! A [`HexMap`] stores a value per hex of a rectangular map, and the algorithms of this module
+//! work on it:
+//!
+//! * [`flood_fill`]: all hexes connected to a start hex.
+//! * [`reachable`]: all hexes within a number of steps, e.g. the movement range of a unit.
+//! * [`find_path`]: a shortest path between two hexes, with A*.
+//!
+//! # Example
+//! ```
+//! use teng::util::hex::{Hex, HexLayout, HexMap, find_path};
+//!
+//! let mut walls = HexMap::new(8, 6, false);
+//! walls[Hex::new(2, 1)] = true;
+//! let path = find_path(&walls, Hex::new(0, 1), Hex::new(4, 1), |wall| !wall).unwrap();
+//! // around the wall, one step longer than the straight line
+//! assert_eq!(path.len(), 6);
+//! assert_eq!(Hex::new(0, 1).distance(Hex::new(4, 1)), 4);
+//!
+//! // the hex under the mouse
+//! let hex = HexLayout::DoubleWidth.cell_to_hex(7, 1);
+//! assert_eq!(hex, Hex::new(3, 1));
+//! ```
+
+use crate::rendering::pixel::Pixel;
+use crate::rendering::renderer::Renderer;
+use crate::util::grid::Grid;
+use crate::util::planarvec::{Bounds, PlanarVec};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::ops::{Add, Index, IndexMut, Mul, Sub};
+
+/// A hex in axial coordinates.
+///
+/// `q` grows to the east, `r` to the south-east. The implicit third cube coordinate is
+/// [`s`](Hex::s), so that `q + r + s == 0`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Hex {
+    pub q: i64,
+    pub r: i64,
+}
+
+impl Hex {
+    /// The offsets to the six neighbors, counter-clockwise starting in the east.
+    pub const DIRECTIONS: [Hex; 6] = [
+        Hex::new(1, 0),
+        Hex::new(1, -1),
+        Hex::new(0, -1),
+        Hex::new(-1, 0),
+        Hex::new(-1, 1),
+        Hex::new(0, 1),
+    ];
+
+    pub const fn new(q: i64, r: i64) -> Self {
+        Self { q, r }
+    }
+
+    /// Returns the third cube coordinate.
+    pub fn s(self) -> i64 {
+        -self.q - self.r
+    }
+
+    /// Returns the neighbor in the direction `DIRECTIONS[direction % 6]`.
+    pub fn neighbor(self, direction: usize) -> Hex {
+        self + Self::DIRECTIONS[direction % 6]
+    }
+
+    /// Returns the six neighbors, in the order of [`Hex::DIRECTIONS`].
+    pub fn neighbors(self) -> impl Iterator<Item = Hex> {
+        Self::DIRECTIONS.into_iter().map(move |d| self + d)
+    }
+
+    /// Returns the number of steps between the two hexes.
+    pub fn distance(self, other: Hex) -> i64 {
+        let d = self - other;
+        (d.q.abs() + d.r.abs() + d.s().abs()) / 2
+    }
+
+    /// Returns the hexes on the line from `self` to `other`, both included.
+    ///
+    /// Lines are deterministic: points on the edge between two hexes are nudged in a fixed
+    /// direction, so the same endpoints always result in the same line.
+    pub fn line_to(self, other: Hex) -> Vec<Hex> {
+        let n = self.distance(other);
+        // the nudge keeps the rounding away from edges between hexes
+        let (a_q, a_r) = (self.q as f64 + 1e-6, self.r as f64 + 2e-6);
+        let (b_q, b_r) = (other.q as f64 + 1e-6, other.r as f64 + 2e-6);
+        (0..=n)
+            .map(|i| {
+                let t = if n == 0 { 0.0 } else { i as f64 / n as f64 };
+                Hex::round(a_q + (b_q - a_q) * t, a_r + (b_r - a_r) * t)
+            })
+            .collect()
+    }
+
+    /// Returns the hex that contains the fractional axial coordinates `(q, r)`.
+    pub fn round(q: f64, r: f64) -> Hex {
+        let s = -q - r;
+        let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+        let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+        // the coordinate that was rounded the most is recomputed from the other two
+        if dq > dr && dq > ds {
+            rq = -rr - rs;
+        } else if dr > ds {
+            rr = -rq - rs;
+        }
+        Hex::new(rq as i64, rr as i64)
+    }
+
+    /// Returns all hexes within `radius` steps, including `self`.
+    pub fn range(self, radius: i64) -> impl Iterator<Item = Hex> {
+        (-radius..=radius).flat_map(move |dq| {
+            let min_dr = (-radius).max(-dq - radius);
+            let max_dr = radius.min(-dq + radius);
+            (min_dr..=max_dr).map(move |dr| self + Hex::new(dq, dr))
+        })
+    }
+
+    /// Returns the hexes exactly `radius` steps away, counter-clockwise starting in the
+    /// south-west. A radius of 0 is just `self`.
+    pub fn ring(self, radius: i64) -> Vec<Hex> {
+        if radius <= 0 {
+            return vec![self];
+        }
+        let mut ring = Vec::with_capacity(6 * radius as usize);
+        let mut hex = self + Self::DIRECTIONS[4] * radius;
+        for direction in 0..6 {
+            for _ in 0..radius {
+                ring.push(hex);
+                hex = hex.neighbor(direction);
+            }
+        }
+        ring
+    }
+
+    /// Returns the offset coordinates of the hex.
+    pub fn to_offset(self) -> OffsetCoord {
+        OffsetCoord {
+            col: self.q + (self.r - (self.r & 1)) / 2,
+            row: self.r,
+        }
+    }
+}
+
+impl Add for Hex {
+    type Output = Hex;
+
+    fn add(self, other: Hex) -> Hex {
+        Hex::new(self.q + other.q, self.r + other.r)
+    }
+}
+
+impl Sub for Hex {
+    type Output = Hex;
+
+    fn sub(self, other: Hex) -> Hex {
+        Hex::new(self.q - other.q, self.r - other.r)
+    }
+}
+
+impl Mul<i64> for Hex {
+    type Output = Hex;
+
+    fn mul(self, factor: i64) -> Hex {
+        Hex::new(self.q * factor, self.r * factor)
+    }
+}
+
+/// A hex in "odd-r" offset coordinates: every row of hexes is a row of the map, and odd rows are
+/// shifted right by half a hex.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct OffsetCoord {
+    pub col: i64,
+    pub row: i64,
+}
+
+impl OffsetCoord {
+    pub fn new(col: i64, row: i64) -> Self {
+        Self { col, row }
+    }
+
+    /// Returns the axial coordinates of the hex.
+    pub fn to_hex(self) -> Hex {
+        Hex::new(self.col - (self.row - (self.row & 1)) / 2, self.row)
+    }
+}
+
+/// How hexes are laid out in terminal cells. Rows of hexes are rows of cells, and every row is
+/// shifted by one cell relative to the previous one, so that the rows interlock.
+///
+/// Cell coordinates are relative to hex `(0, 0)`, add the screen position of the map to them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HexLayout {
+    /// One cell per hex, with an empty cell between the hexes of a row.
+    Compact,
+    /// Two cells per hex, without gaps. Closer to the aspect ratio of a hex.
+    DoubleWidth,
+}
+
+impl HexLayout {
+    /// Returns how many cells wide a hex is drawn.
+    pub fn hex_width(self) -> i64 {
+        match self {
+            HexLayout::Compact => 1,
+            HexLayout::DoubleWidth => 2,
+        }
+    }
+
+    /// Returns the leftmost cell of the hex.
+    pub fn hex_to_cell(self, hex: Hex) -> (i64, i64) {
+        (2 * hex.q + hex.r, hex.r)
+    }
+
+    /// Returns the hex that covers the cell. In the [`Compact`](HexLayout::Compact) layout, the gap
+    /// right of a hex belongs to it.
+    pub fn cell_to_hex(self, x: i64, y: i64) -> Hex {
+        Hex::new((x - y).div_euclid(2), y)
+    }
+
+    /// Draws the hex filled with `color`, with the hex's top left cell at `origin` plus its
+    /// [cell](Self::hex_to_cell). Cells at negative coordinates are skipped.
+    pub fn render_fill(
+        self,
+        renderer: &mut dyn Renderer,
+        origin: (i64, i64),
+        hex: Hex,
+        color: [u8; 3],
+        depth: i32,
+    ) {
+        let pixels: &[Pixel] = match self {
+            HexLayout::Compact => &[Pixel::new('⬢').with_color(color)],
+            HexLayout::DoubleWidth => &[Pixel::new(' ').with_bg_color(color); 2],
+        };
+        self.render_pixels(renderer, origin, hex, pixels, depth);
+    }
+
+    /// Draws the outline of the hex in `color`, see [`render_fill`](Self::render_fill). In the
+    /// [`DoubleWidth`](HexLayout::DoubleWidth) layout, the outlines of neighboring hexes
+    /// interlock to a honeycomb.
+    pub fn render_outline(
+        self,
+        renderer: &mut dyn Renderer,
+        origin: (i64, i64),
+        hex: Hex,
+        color: [u8; 3],
+        depth: i32,
+    ) {
+        let pixels: &[Pixel] = match self {
+            HexLayout::Compact => &[Pixel::new('⬡').with_color(color)],
+            HexLayout::DoubleWidth => &[
+                Pixel::new('<').with_color(color),
+                Pixel::new('>').with_color(color),
+            ],
+        };
+        self.render_pixels(renderer, origin, hex, pixels, depth);
+    }
+
+    fn render_pixels(
+        self,
+        renderer: &mut dyn Renderer,
+        (origin_x, origin_y): (i64, i64),
+        hex: Hex,
+        pixels: &[Pixel],
+        depth: i32,
+    ) {
+        let (x, y) = self.hex_to_cell(hex);
+        let (x, y) = (origin_x + x, origin_y + y);
+        for (i, pixel) in pixels.iter().enumerate() {
+            let x = x + i as i64;
+            if x >= 0 && y >= 0 {
+                renderer.render_pixel(x as usize, y as usize, *pixel, depth);
+            }
+        }
+    }
+}
+
+/// A rectangular map with a value per hex.
+///
+/// The map covers the [`OffsetCoord`]s `(0, 0)` to `(columns - 1, rows - 1)`. It is stored in
+/// offset coordinates, because a rectangle in axial coordinates is a rhombus on screen and would
+/// waste about half of its cells on hexes outside of the map.
+///
+/// As a [`Grid`], the map is indexed by offset coordinates, e.g. for
+/// [`solidity`](crate::util::grid::solidity). The algorithms of the `grid` module assume square
+/// neighbors, use the ones of this module instead.
+#[derive(Debug, Clone)]
+pub struct HexMap<T> {
+    cells: PlanarVec<T>,
+}
+
+impl<T> HexMap<T> {
+    pub fn new(columns: usize, rows: usize, default: T) -> Self
+    where
+        T: Clone,
+    {
+        let bounds = if columns == 0 || rows == 0 {
+            Bounds::empty()
+        } else {
+            Bounds {
+                min_x: 0,
+                max_x: columns as i64 - 1,
+                min_y: 0,
+                max_y: rows as i64 - 1,
+            }
+        };
+        Self {
+            cells: PlanarVec::new(bounds, default),
+        }
+    }
+
+    pub fn columns(&self) -> usize {
+        self.cells.x_range().count()
+    }
+
+    pub fn rows(&self) -> usize {
+        self.cells.y_range().count()
+    }
+
+    pub fn contains(&self, hex: Hex) -> bool {
+        let offset = hex.to_offset();
+        self.cells.bounds().contains(offset.col, offset.row)
+    }
+
+    /// Returns the value of the hex, or `None` if it is outside of the map.
+    pub fn get(&self, hex: Hex) -> Option<&T> {
+        let offset = hex.to_offset();
+        self.cells.get(offset.col, offset.row)
+    }
+
+    /// Returns the value of the hex, or `None` if it is outside of the map.
+    pub fn get_mut(&mut self, hex: Hex) -> Option<&mut T> {
+        let offset = hex.to_offset();
+        self.cells.get_mut(offset.col, offset.row)
+    }
+
+    /// Returns an iterator over all hexes and their values, row by row.
+    pub fn iter(&self) -> impl Iterator<Item = (Hex, &T)> {
+        self.cells
+            .iter_cells()
+            .map(|(col, row, value)| (OffsetCoord::new(col, row).to_hex(), value))
+    }
+}
+
+impl<T> Index<Hex> for HexMap<T> {
+    type Output = T;
+
+    fn index(&self, hex: Hex) -> &T {
+        self.get(hex).expect("hex outside of the map")
+    }
+}
+
+impl<T> IndexMut<Hex> for HexMap<T> {
+    fn index_mut(&mut self, hex: Hex) -> &mut T {
+        self.get_mut(hex).expect("hex outside of the map")
+    }
+}
+
+impl<T> Grid<T> for HexMap<T> {
+    fn bounds(&self) -> Bounds {
+        self.cells.bounds()
+    }
+
+    fn get_cell(&self, x: i64, y: i64) -> Option<&T> {
+        self.cells.get(x, y)
+    }
+
+    fn get_cell_mut(&mut self, x: i64, y: i64) -> Option<&mut T> {
+        self.cells.get_mut(x, y)
+    }
+
+    fn fill_cells(&mut self, value: T)
+    where
+        T: Clone,
+    {
+        self.cells.clear(value);
+    }
+}
+
+/// Returns all hexes connected to `start` through hexes for which `is_fillable` returns true, in
+/// breadth-first order.
+///
+/// Returns an empty vector if `start` is outside of the map or not fillable itself.
+pub fn flood_fill<T>(map: &HexMap<T>, start: Hex, is_fillable: impl FnMut(&T) -> bool) -> Vec<Hex> {
+    reachable(map, start, u32::MAX, is_fillable)
+        .into_iter()
+        .map(|(hex, _)| hex)
+        .collect()
+}
+
+/// Returns all hexes that are at most `max_steps` steps away from `start`, moving only through
+/// hexes for which `is_passable` returns true, with their number of steps, in breadth-first order.
+///
+/// Returns an empty vector if `start` is outside of the map or not passable itself.
+pub fn reachable<T>(
+    map: &HexMap<T>,
+    start: Hex,
+    max_steps: u32,
+    mut is_passable: impl FnMut(&T) -> bool,
+) -> Vec<(Hex, u32)> {
+    let mut visited = HexMap::new(map.columns(), map.rows(), false);
+    let mut found = vec![];
+    let mut queue = VecDeque::from([(start, 0)]);
+    while let Some((hex, steps)) = queue.pop_front() {
+        match visited.get_mut(hex) {
+            Some(v) if !*v => *v = true,
+            _ => continue,
+        }
+        if !map.get(hex).is_some_and(&mut is_passable) {
+            continue;
+        }
+        found.push((hex, steps));
+        if steps < max_steps {
+            queue.extend(hex.neighbors().map(|neighbor| (neighbor, steps + 1)));
+        }
+    }
+    found
+}
+
+/// Returns a shortest path from `start` to `goal`, both included, moving only through hexes for
+/// which `is_passable` returns true. Uses A* with the hex distance as heuristic.
+///
+/// Returns `None` if there is no such path. Of several shortest paths, the same one is returned
+/// every time.
+pub fn find_path<T>(
+    map: &HexMap<T>,
+    start: Hex,
+    goal: Hex,
+    mut is_passable: impl FnMut(&T) -> bool,
+) -> Option<Vec<Hex>> {
+    let mut passable = |hex| map.get(hex).is_some_and(&mut is_passable);
+    if !passable(start) || !passable(goal) {
+        return None;
+    }
+    let mut came_from = HashMap::new();
+    let mut steps = HashMap::from([(start, 0)]);
+    // ordered by estimated length, then by remaining distance, then by hex for determinism
+    let mut open = BinaryHeap::from([Reverse((start.distance(goal), start.distance(goal), start))]);
+    while let Some(Reverse((_, _, hex))) = open.pop() {
+        if hex == goal {
+            let mut path = vec![goal];
+            while let Some(&previous) = came_from.get(path.last().unwrap()) {
+                path.push(previous);
+            }
+            path.reverse();
+            return Some(path);
+        }
+        let next_steps = steps[&hex] + 1;
+        for neighbor in hex.neighbors() {
+            if steps.get(&neighbor).is_some_and(|&s| s <= next_steps) || !passable(neighbor) {
+                continue;
+            }
+            steps.insert(neighbor, next_steps);
+            came_from.insert(neighbor, hex);
+            let remaining = neighbor.distance(goal);
+            open.push(Reverse((next_steps + remaining, remaining, neighbor)));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coordinate_round_trips() {
+        for hex in Hex::new(0, 0).range(6) {
+            assert_eq!(hex.to_offset().to_hex(), hex);
+            for layout in [HexLayout::Compact, HexLayout::DoubleWidth] {
+                let (x, y) = layout.hex_to_cell(hex);
+                for dx in 0..layout.hex_width() {
+                    assert_eq!(layout.cell_to_hex(x + dx, y), hex);
+                }
+            }
+        }
+        // odd rows are shifted right
+        assert_eq!(OffsetCoord::new(0, 1).to_hex(), Hex::new(0, 1));
+        assert_eq!(OffsetCoord::new(0, 2).to_hex(), Hex::new(-1, 2));
+        assert_eq!(OffsetCoord::new(0, -1).to_hex(), Hex::new(1, -1));
+        let cell = |col, row| HexLayout::Compact.hex_to_cell(OffsetCoord::new(col, row).to_hex());
+        assert_eq!(
+            [cell(0, 0), cell(1, 0), cell(0, 1), cell(0, 2)],
+            [(0, 0), (2, 0), (1, 1), (0, 2)]
+        );
+        // the gap belongs to the hex left of it
+        assert_eq!(HexLayout::Compact.cell_to_hex(1, 0), Hex::new(0, 0));
+    }
+
+    #[test]
+    fn test_distance() {
+        let hexes: Vec<_> = Hex::new(1, -2).range(4).collect();
+        assert_eq!(hexes.len(), 61);
+        for &a in &hexes {
+            for &b in &hexes {
+                assert_eq!(a.distance(b), b.distance(a));
+            }
+        }
+        for neighbor in Hex::new(3, 3).neighbors() {
+            assert_eq!(neighbor.distance(Hex::new(3, 3)), 1);
+        }
+        assert_eq!(Hex::new(0, 0).distance(Hex::new(3, -1)), 3);
+        assert_eq!(Hex::new(0, 0).distance(Hex::new(2, 2)), 4);
+    }
+
+    #[test]
+    fn test_lines() {
+        let (a, b) = (Hex::new(-2, 1), Hex::new(3, -3));
+        let line = a.line_to(b);
+        assert_eq!(line.len() as i64, a.distance(b) + 1);
+        assert_eq!((line[0], *line.last().unwrap()), (a, b));
+        for pair in line.windows(2) {
+            assert_eq!(pair[0].distance(pair[1]), 1);
+        }
+        // deterministic even along the edges between hexes
+        let edge = Hex::new(0, 0).line_to(Hex::new(1, 1));
+        assert_eq!(edge, Hex::new(0, 0).line_to(Hex::new(1, 1)));
+        assert_eq!(edge, vec![Hex::new(0, 0), Hex::new(0, 1), Hex::new(1, 1)]);
+        assert_eq!(a.line_to(a), vec![a]);
+    }
+
+    #[test]
+    fn test_rings() {
+        let center = Hex::new(2, -1);
+        assert_eq!(center.ring(0), vec![center]);
+        for radius in 1..4 {
+            let ring = center.ring(radius);
+            assert_eq!(ring.len() as i64, 6 * radius);
+            assert!(ring.iter().all(|hex| hex.distance(center) == radius));
+        }
+        assert_eq!(center.range(2).count(), 1 + 6 + 12);
+    }
+
+    /// A 6x4 map with a wall in the third column, open in the last row.
+    fn walls() -> HexMap<bool> {
+        let mut walls = HexMap::new(6, 4, false);
+        for row in 0..3 {
+            walls[OffsetCoord::new(2, row).to_hex()] = true;
+        }
+        walls
+    }
+
+    #[test]
+    fn test_map_algorithms() {
+        let walls = walls();
+        let start = OffsetCoord::new(0, 0).to_hex();
+        assert_eq!(flood_fill(&walls, start, |wall| !wall).len(), 6 * 4 - 3);
+        assert!(flood_fill(&walls, Hex::new(-1, 0), |wall| !wall).is_empty());
+        assert_eq!(walls.iter().filter(|(_, wall)| **wall).count(), 3);
+        assert_eq!(Grid::bounds(&walls), walls.cells.bounds());
+
+        let range = reachable(&walls, start, 2, |wall| !wall);
+        assert_eq!(range[0], (start, 0));
+        assert!(
+            range
+                .iter()
+                .all(|&(hex, steps)| hex.distance(start) <= steps as i64)
+        );
+        assert!(!range.iter().any(|(hex, _)| walls[*hex]));
+
+        let goal = OffsetCoord::new(4, 0).to_hex();
+        let path = find_path(&walls, start, goal, |wall| !wall).unwrap();
+        assert_eq!((path[0], *path.last().unwrap()), (start, goal));
+        for pair in path.windows(2) {
+            assert_eq!(pair[0].distance(pair[1]), 1);
+            assert!(!walls[pair[1]]);
+        }
+        // down around the wall and back up, as short as the breadth-first distance
+        let steps = reachable(&walls, start, u32::MAX, |wall| !wall);
+        let goal_steps = steps.iter().find(|(hex, _)| *hex == goal).unwrap().1;
+        assert_eq!(path.len(), goal_steps as usize + 1);
+        assert!(path.iter().any(|hex| hex.to_offset().row == 3));
+        assert_eq!(find_path(&walls, start, goal, |wall| !wall), Some(path));
+        assert_eq!(find_path(&walls, start, Hex::new(2, 0), |wall| !wall), None);
+    }
+
+    #[test]
+    fn test_render() {
+        struct Recorder(Vec<(usize, usize, char)>);
+        impl Renderer for Recorder {
+            fn render_pixel(&mut self, x: usize, y: usize, pixel: Pixel, _depth: i32) {
+                self.0.push((x, y, pixel.c));
+            }
+        }
+        let mut recorder = Recorder(vec![]);
+        let layout = HexLayout::DoubleWidth;
+        for hex in [Hex::new(0, 0), Hex::new(0, 1), Hex::new(-1, 0)] {
+            layout.render_outline(&mut recorder, (1, 0), hex, [255, 255, 255], 0);
+        }
+        // the hex left of the origin is cut off
+        assert_eq!(
+            recorder.0,
+            vec![
+                (1, 0, '<'),
+                (2, 0, '>'),
+                (2, 1, '<'),
+                (3, 1, '>'),
+                (0, 0, '>')
+            ]
+        );
+        let mut recorder = Recorder(vec![]);
+        HexLayout::Compact.render_fill(&mut recorder, (0, 0), Hex::new(1, 1), [0, 0, 0], 0);
+        assert_eq!(recorder.0, vec![(3, 1, '⬢')]);
+    }
+}
@@ -0,0 +1,421 @@
+//! Influence maps (also known as heat or aggro maps) for AI decision making.
+//!
+//! An [`InfluenceMap`] stores a scalar value for every cell of a rectangular region.
+//! Sources such as the player, noise or other enemies add influence around themselves, which
+//! AI can then follow (seek) or flee from (avoid) using [`InfluenceMap::best_neighbor`] or
+//! [`InfluenceMap::gradient_at`].
+//!
+//! There are two ways of filling a map:
+//! * Immediate: [`InfluenceMap::add_source`] stamps a source's contribution onto the map directly.
+//!   Combined with [`InfluenceMap::decay`], this results in influence that fades over time, e.g.
+//!   for noise that enemies should investigate.
+//! * Budgeted: [`InfluenceMap::push_source`] registers persistent sources, and
+//!   [`InfluenceMap::recompute_budgeted`] recomputes only a limited number of cells per call.
+//!   This spreads the cost of large maps over multiple frames.
+//!
+//! # Example
+//! ```
+//! use teng::util::influence::{Falloff, InfluenceMap};
+//! use teng::util::planarvec::Bounds;
+//!
+//! let bounds = Bounds { min_x: 0, max_x: 19, min_y: 0, max_y: 9 };
+//! let mut noise = InfluenceMap::new(bounds);
+//! noise.add_source((10, 5), 1.0, 6.0, Falloff::Linear);
+//! // an enemy at (4, 5) moves towards the noise
+//! assert_eq!(noise.best_neighbor((4, 5)), Some((5, 5)));
+//! ```
+
+use crate::rendering::color::Color;
+use crate::rendering::display::Display;
+use crate::rendering::pixel::Pixel;
+use crate::rendering::render::Render;
+use crate::rendering::renderer::Renderer;
+use crate::util::lerp_color;
+use crate::util::planarvec::Bounds;
+
+/// How a source's influence falls off with distance.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Falloff {
+    /// Full strength everywhere inside the radius.
+    Constant,
+    /// Strength decreases linearly to zero at the radius.
+    Linear,
+    /// Strength decreases quadratically to zero at the radius, staying strong near the source.
+    Quadratic,
+    /// Strength decreases with the inverse square of the distance, cut off at the radius.
+    InverseSquare,
+}
+
+impl Falloff {
+    /// Returns the factor in `0.0..=1.0` that is applied to a source's strength at `distance`
+    /// from a source with the given `radius`.
+    pub fn factor(self, distance: f32, radius: f32) -> f32 {
+        if distance > radius || radius <= 0.0 {
+            return 0.0;
+        }
+        let t = distance / radius;
+        match self {
+            Falloff::Constant => 1.0,
+            Falloff::Linear => 1.0 - t,
+            Falloff::Quadratic => 1.0 - t * t,
+            Falloff::InverseSquare => 1.0 / (1.0 + distance * distance),
+        }
+    }
+}
+
+/// A source of influence.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InfluenceSource {
+    /// The position of the source.
+    pub pos: (i64, i64),
+    /// The influence at the source's position. Negative strengths are allowed.
+    pub strength: f32,
+    /// The maximum distance at which the source has any influence.
+    pub radius: f32,
+    /// How the strength falls off with distance.
+    pub falloff: Falloff,
+}
+
+impl InfluenceSource {
+    fn contribution_at(&self, (x, y): (i64, i64)) -> f32 {
+        let dx = (x - self.pos.0) as f32;
+        let dy = (y - self.pos.1) as f32;
+        let distance = (dx * dx + dy * dy).sqrt();
+        self.strength * self.falloff.factor(distance, self.radius)
+    }
+
+    /// The bounds that this source can influence.
+    fn bounds(&self) -> Bounds {
+        let r = self.radius.ceil().max(0.0) as i64;
+        Bounds {
+            min_x: self.pos.0 - r,
+            max_x: self.pos.0 + r,
+            min_y: self.pos.1 - r,
+            max_y: self.pos.1 + r,
+        }
+    }
+}
+
+/// A scalar field over a rectangular region used to guide AI movement.
+#[derive(Debug, Clone)]
+pub struct InfluenceMap {
+    bounds: Bounds,
+    values: Display<f32>,
+    sources: Vec<InfluenceSource>,
+    /// Linear index of the next cell to recompute in budgeted mode.
+    recompute_cursor: usize,
+}
+
+impl InfluenceMap {
+    /// Creates a new influence map covering `bounds`, with all values set to zero.
+    pub fn new(bounds: Bounds) -> Self {
+        let (width, height) = if bounds.is_empty() {
+            (0, 0)
+        } else {
+            (
+                (bounds.max_x - bounds.min_x + 1) as usize,
+                (bounds.max_y - bounds.min_y + 1) as usize,
+            )
+        };
+        Self {
+            bounds,
+            values: Display::new(width, height, 0.0),
+            sources: Vec::new(),
+            recompute_cursor: 0,
+        }
+    }
+
+    /// Returns the bounds covered by this map.
+    pub fn bounds(&self) -> Bounds {
+        self.bounds
+    }
+
+    fn to_local(&self, (x, y): (i64, i64)) -> Option<(usize, usize)> {
+        if !self.bounds.contains(x, y) {
+            return None;
+        }
+        Some((
+            (x - self.bounds.min_x) as usize,
+            (y - self.bounds.min_y) as usize,
+        ))
+    }
+
+    /// Returns the value at the given position, or `None` if it is out of bounds.
+    pub fn get(&self, pos: (i64, i64)) -> Option<f32> {
+        let (x, y) = self.to_local(pos)?;
+        self.values.get(x, y).copied()
+    }
+
+    /// Sets the value at the given position, if it is in bounds.
+    pub fn set(&mut self, pos: (i64, i64), value: f32) {
+        if let Some((x, y)) = self.to_local(pos) {
+            self.values.set(x, y, value);
+        }
+    }
+
+    /// Sets every value to zero. Does not remove persistent sources.
+    pub fn clear(&mut self) {
+        self.values.fill(0.0);
+    }
+
+    /// Adds the contribution of a source to the map immediately.
+    pub fn add_source(&mut self, pos: (i64, i64), strength: f32, radius: f32, falloff: Falloff) {
+        let source = InfluenceSource {
+            pos,
+            strength,
+            radius,
+            falloff,
+        };
+        let area = source.bounds();
+        for x in area.min_x..=area.max_x {
+            for y in area.min_y..=area.max_y {
+                if let Some((lx, ly)) = self.to_local((x, y)) {
+                    self.values[(lx, ly)] += source.contribution_at((x, y));
+                }
+            }
+        }
+    }
+
+    /// Fades all values towards zero exponentially, losing `rate` of the value per second.
+    pub fn decay(&mut self, dt: f64, rate: f64) {
+        let factor = (-rate * dt).exp() as f32;
+        for (_, _, value) in self.values.iter_mut() {
+            *value *= factor;
+        }
+    }
+
+    /// Adds `other`'s values multiplied by `weight` to this map.
+    ///
+    /// Use a negative weight to subtract a map, e.g. to make AI avoid areas.
+    ///
+    /// # Panics
+    /// Panics if the bounds of the maps differ.
+    pub fn add_weighted(&mut self, other: &InfluenceMap, weight: f32) {
+        assert_eq!(self.bounds, other.bounds, "influence map bounds differ");
+        for (x, y, value) in self.values.iter_mut() {
+            *value += other.values[(x, y)] * weight;
+        }
+    }
+
+    /// Sets every value to the maximum of this map's and `other`'s value.
+    ///
+    /// # Panics
+    /// Panics if the bounds of the maps differ.
+    pub fn max_with(&mut self, other: &InfluenceMap) {
+        assert_eq!(self.bounds, other.bounds, "influence map bounds differ");
+        for (x, y, value) in self.values.iter_mut() {
+            *value = value.max(other.values[(x, y)]);
+        }
+    }
+
+    /// Returns the position with the highest value among `pos` and its eight neighbors.
+    ///
+    /// Returns `pos` itself if no neighbor is strictly better, and `None` if `pos` is out of bounds.
+    /// To move away from influence instead, negate the map with [`InfluenceMap::add_weighted`].
+    pub fn best_neighbor(&self, pos: (i64, i64)) -> Option<(i64, i64)> {
+        let mut best = (pos, self.get(pos)?);
+        // orthogonal neighbors first, so ties prefer straight movement
+        for (dx, dy) in [
+            (1, 0),
+            (-1, 0),
+            (0, 1),
+            (0, -1),
+            (1, 1),
+            (1, -1),
+            (-1, 1),
+            (-1, -1),
+        ] {
+            let neighbor = (pos.0 + dx, pos.1 + dy);
+            match self.get(neighbor) {
+                Some(value) if value > best.1 => best = (neighbor, value),
+                _ => {}
+            }
+        }
+        Some(best.0)
+    }
+
+    /// Returns the gradient of the map at `pos` using central differences, pointing towards
+    /// increasing influence.
+    ///
+    /// Out of bounds neighbors are treated as having the same value as `pos`.
+    pub fn gradient_at(&self, pos: (i64, i64)) -> (f32, f32) {
+        let Some(center) = self.get(pos) else {
+            return (0.0, 0.0);
+        };
+        let sample = |dx: i64, dy: i64| self.get((pos.0 + dx, pos.1 + dy)).unwrap_or(center);
+        (
+            (sample(1, 0) - sample(-1, 0)) / 2.0,
+            (sample(0, 1) - sample(0, -1)) / 2.0,
+        )
+    }
+
+    /// Registers a persistent source that is taken into account by
+    /// [`InfluenceMap::recompute_budgeted`].
+    pub fn push_source(&mut self, source: InfluenceSource) {
+        self.sources.push(source);
+    }
+
+    /// Returns the persistent sources mutably, e.g. to move them around.
+    pub fn sources_mut(&mut self) -> &mut Vec<InfluenceSource> {
+        &mut self.sources
+    }
+
+    /// Recomputes up to `max_cells` cells from the persistent sources, continuing where the
+    /// previous call stopped.
+    ///
+    /// Returns `true` if the call completed a full pass over the map.
+    pub fn recompute_budgeted(&mut self, max_cells: usize) -> bool {
+        let total = self.values.width() * self.values.height();
+        if total == 0 {
+            return true;
+        }
+        let mut finished_pass = false;
+        for _ in 0..max_cells.min(total) {
+            let idx = self.recompute_cursor;
+            let (lx, ly) = (idx % self.values.width(), idx / self.values.width());
+            let pos = (self.bounds.min_x + lx as i64, self.bounds.min_y + ly as i64);
+            self.values[(lx, ly)] = self.sources.iter().map(|s| s.contribution_at(pos)).sum();
+            self.recompute_cursor = (idx + 1) % total;
+            if self.recompute_cursor == 0 {
+                finished_pass = true;
+            }
+        }
+        finished_pass
+    }
+
+    /// Recomputes the entire map from the persistent sources.
+    pub fn recompute(&mut self) {
+        self.recompute_cursor = 0;
+        self.recompute_budgeted(usize::MAX);
+    }
+
+    /// Returns a renderable debug visualization of this map.
+    ///
+    /// Values are mapped linearly from `low_color` at `min` to `high_color` at `max`.
+    /// The map's `(min_x, min_y)` corner is drawn at the position passed to `render`.
+    pub fn debug_view(
+        &self,
+        (min, max): (f32, f32),
+        low_color: [u8; 3],
+        high_color: [u8; 3],
+    ) -> InfluenceMapDebugView<'_> {
+        InfluenceMapDebugView {
+            map: self,
+            min,
+            max,
+            low_color,
+            high_color,
+        }
+    }
+}
+
+/// A debug visualization of an [`InfluenceMap`]. Created by [`InfluenceMap::debug_view`].
+///
+/// Each cell is rendered as a background color. Cells whose value is below the lower end of the
+/// range are not rendered.
+pub struct InfluenceMapDebugView<'a> {
+    map: &'a InfluenceMap,
+    min: f32,
+    max: f32,
+    low_color: [u8; 3],
+    high_color: [u8; 3],
+}
+
+impl Render for InfluenceMapDebugView<'_> {
+    fn render(&self, renderer: &mut dyn Renderer, x: usize, y: usize, depth: i32) {
+        for (lx, ly, value) in self.map.values.iter() {
+            if *value <= self.min {
+                continue;
+            }
+            let t = ((value - self.min) / (self.max - self.min)).clamp(0.0, 1.0);
+            let mut pixel = Pixel::transparent();
+            pixel.bg_color = Color::Rgb(lerp_color(self.low_color, self.high_color, t));
+            renderer.render_pixel(x + lx, y + ly, pixel, depth);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds() -> Bounds {
+        Bounds {
+            min_x: -10,
+            max_x: 10,
+            min_y: -10,
+            max_y: 10,
+        }
+    }
+
+    #[test]
+    fn test_falloff() {
+        assert_eq!(Falloff::Constant.factor(4.0, 5.0), 1.0);
+        assert_eq!(Falloff::Linear.factor(0.0, 4.0), 1.0);
+        assert_eq!(Falloff::Linear.factor(2.0, 4.0), 0.5);
+        assert_eq!(Falloff::Quadratic.factor(2.0, 4.0), 0.75);
+        assert_eq!(Falloff::InverseSquare.factor(1.0, 4.0), 0.5);
+        for falloff in [Falloff::Constant, Falloff::Linear, Falloff::Quadratic] {
+            assert_eq!(falloff.factor(4.5, 4.0), 0.0);
+        }
+
+        let mut map = InfluenceMap::new(bounds());
+        map.add_source((0, 0), 2.0, 4.0, Falloff::Linear);
+        assert_eq!(map.get((0, 0)), Some(2.0));
+        assert_eq!(map.get((2, 0)), Some(1.0));
+        assert_eq!(map.get((5, 0)), Some(0.0));
+    }
+
+    #[test]
+    fn test_decay() {
+        let mut map = InfluenceMap::new(bounds());
+        map.set((0, 0), 1.0);
+        // decaying twice for half a second is the same as decaying once for a second
+        for _ in 0..2 {
+            map.decay(0.5, 2.0);
+        }
+        let expected = (-2.0f64).exp() as f32;
+        assert!((map.get((0, 0)).unwrap() - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_combination() {
+        let mut a = InfluenceMap::new(bounds());
+        let mut b = InfluenceMap::new(bounds());
+        a.set((1, 1), 1.0);
+        b.set((1, 1), 3.0);
+        b.set((2, 2), -1.0);
+
+        let mut sum = a.clone();
+        sum.add_weighted(&b, 0.5);
+        assert_eq!(sum.get((1, 1)), Some(2.5));
+        assert_eq!(sum.get((2, 2)), Some(-0.5));
+
+        a.max_with(&b);
+        assert_eq!(a.get((1, 1)), Some(3.0));
+        assert_eq!(a.get((2, 2)), Some(0.0));
+    }
+
+    #[test]
+    fn test_best_neighbor_descends_to_source() {
+        let mut map = InfluenceMap::new(bounds());
+        map.push_source(InfluenceSource {
+            pos: (3, -2),
+            strength: 1.0,
+            radius: 40.0,
+            falloff: Falloff::Linear,
+        });
+        // budgeted recomputation eventually covers the whole map
+        while !map.recompute_budgeted(50) {}
+
+        for start in [(-10, -10), (10, 10), (-10, 7), (0, 0), (3, -2)] {
+            let mut pos = start;
+            for _ in 0..30 {
+                pos = map.best_neighbor(pos).unwrap();
+            }
+            assert_eq!(pos, (3, -2), "starting from {:?}", start);
+        }
+        let (gx, gy) = map.gradient_at((0, 0));
+        assert!(gx > 0.0 && gy < 0.0);
+    }
+}
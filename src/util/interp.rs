@@ -0,0 +1,109 @@
+//! Smooth rendering of states that change at a fixed tick rate, see [`Interpolated`].
+//!
+//! With a [`FixedUpdateRunner`](crate::util::fixedupdate::FixedUpdateRunner) ticking physics at
+//! 60 Hz and a render rate of 144 fps, positions only change on tick boundaries, and movement
+//! stutters. Instead, keep the state of the last two ticks and render between them with the
+//! runner's [`alpha`](crate::util::fixedupdate::FixedUpdateRunner::alpha):
+//! ```
+//! use teng::util::fixedupdate::FixedUpdateRunner;
+//! use teng::util::interp::Interpolated;
+//!
+//! let mut runner = FixedUpdateRunner::new(0.25);
+//! let mut pos = (0.0, 0.0);
+//! let mut render_pos = Interpolated::new(pos);
+//!
+//! runner.fuel(0.375);
+//! while runner.has_gas() {
+//!     runner.consume();
+//!     pos.0 += 1.0;
+//!     render_pos.push(pos);
+//! }
+//! // halfway between the last two ticks
+//! assert_eq!(render_pos.sample(runner.alpha()), (0.5, 0.0));
+//! ```
+//! This renders the state up to one tick in the past, which is rarely noticeable.
+//!
+//! States implement [`Lerp`], like for [`SnapshotBuffer`](crate::util::snapshot_interp::SnapshotBuffer).
+
+pub use crate::util::snapshot_interp::Lerp;
+
+/// The state of the previous and the current fixed tick.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Interpolated<T> {
+    previous: T,
+    current: T,
+}
+
+impl<T: Lerp> Interpolated<T> {
+    /// Creates a state that has not moved yet.
+    pub fn new(value: T) -> Self {
+        Self {
+            previous: value.clone(),
+            current: value,
+        }
+    }
+
+    /// Records the state of a new tick. Call this once per fixed tick.
+    pub fn push(&mut self, value: T) {
+        self.previous = std::mem::replace(&mut self.current, value);
+    }
+
+    /// Sets the state without interpolating towards it, e.g. when an entity respawns somewhere
+    /// else. Otherwise, it would be rendered moving across the screen for a tick.
+    pub fn reset(&mut self, value: T) {
+        self.previous = value.clone();
+        self.current = value;
+    }
+
+    /// Returns the state a fraction `alpha` of the way from the previous to the current tick.
+    pub fn sample(&self, alpha: f64) -> T {
+        self.previous.lerp(&self.current, alpha)
+    }
+
+    /// Returns the state of the previous tick.
+    pub fn previous(&self) -> &T {
+        &self.previous
+    }
+
+    /// Returns the state of the current tick.
+    pub fn current(&self) -> &T {
+        &self.current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rendering::color::Color;
+
+    #[test]
+    fn test_push_sample_and_reset() {
+        let mut pos = Interpolated::new((0.0, 10.0));
+        assert_eq!(pos.sample(0.7), (0.0, 10.0));
+        pos.push((4.0, 10.0));
+        pos.push((8.0, 6.0));
+        assert_eq!(pos.sample(0.0), (4.0, 10.0));
+        assert_eq!(pos.sample(0.25), (5.0, 9.0));
+        assert_eq!(pos.sample(1.0), (8.0, 6.0));
+
+        // a teleport is not interpolated
+        pos.reset((100.0, 0.0));
+        assert_eq!(pos.sample(0.5), (100.0, 0.0));
+        pos.push((101.0, 0.0));
+        assert_eq!(pos.sample(0.5), (100.5, 0.0));
+    }
+
+    #[test]
+    fn test_color_lerp() {
+        let mut color = Interpolated::new(Color::Rgb([0, 100, 200]));
+        color.push(Color::Rgb([100, 100, 0]));
+        assert_eq!(color.sample(0.25), Color::Rgb([25, 100, 150]));
+        // clamped when extrapolating
+        assert_eq!(color.sample(2.0), Color::Rgb([100, 100, 0]));
+
+        // other colors switch halfway
+        color.push(Color::Default);
+        assert_eq!(color.sample(0.4), Color::Rgb([100, 100, 0]));
+        assert_eq!(color.sample(0.5), Color::Default);
+    }
+}
@@ -1,11 +1,36 @@
 //! Common utility functions.
 
+pub mod anymap;
 pub mod bidivec;
+pub mod brush;
+pub mod camera;
+pub mod clipboard;
 pub mod planarvec;
 // Experimental replacement for planarvec, uses a single vector and grows exponentially in every direction.
 // Benchmarks in prototype game resulted in ~5% increased frames, at the cost of way worse maximum frametimes (>1.5s frametimes when expanding)
 pub mod fixedupdate;
+pub mod grid;
+pub mod gridmove;
+pub mod hex;
+pub mod influence;
+pub mod interp;
+#[cfg(feature = "mapgen")]
+pub mod mapgen;
 mod planarvec2;
+pub mod noise;
+#[cfg(feature = "persistence")]
+pub mod persistence;
+#[cfg(feature = "random_table")]
+pub mod random_table;
+#[cfg(feature = "persistence")]
+pub mod saveslots;
+pub mod smallmap;
+pub mod snapshot_interp;
+pub mod spatial;
+pub mod task;
+pub mod turns;
+pub mod verlet;
+pub mod world;
 
 pub mod planarvec2_experimental {
     pub use super::planarvec2::*;
@@ -100,7 +125,8 @@ lerp_t_impl_clamped!(get_lerp_t_f32_clamped, f32);
 lerp_t_impl_clamped!(get_lerp_t_f64_clamped, f64);
 
 /// Linearly interpolate between two colors.
-/// Uses RGB color space.
+/// Uses RGB color space. See [`lerp_color_linear`](crate::rendering::color::lerp_color_linear) for
+/// brighter midpoints between saturated colors.
 pub fn lerp_color(a: [u8; 3], b: [u8; 3], t: f32) -> [u8; 3] {
     [
         (a[0] as f32 + (b[0] as f32 - a[0] as f32) * t) as u8,
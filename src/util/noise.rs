@@ -0,0 +1,166 @@
+//! Seeded gradient noise for procedural generation, see [`Perlin2D`].
+//!
+//! ```
+//! use teng::util::noise::Perlin2D;
+//!
+//! let terrain = Perlin2D::new(42);
+//! let height = terrain.fbm(12.5, 3.25, 4, 2.0, 0.5);
+//! assert!((-1.0..=1.0).contains(&height));
+//! // the same seed always produces the same noise
+//! assert_eq!(Perlin2D::new(42).fbm(12.5, 3.25, 4, 2.0, 0.5), height);
+//! ```
+//!
+//! The noise only depends on the seed: the permutation is shuffled with a fixed generator instead
+//! of `rand`, and sampling only uses basic float arithmetic, so a seed produces the same world
+//! across runs, platforms and dependency updates.
+
+/// Two-dimensional Perlin noise.
+#[derive(Clone, Debug)]
+pub struct Perlin2D {
+    /// A permutation of 0..256, repeated once so that `perm[perm[x] + y]` needs no wrapping.
+    perm: [u8; 512],
+}
+
+impl Perlin2D {
+    /// Creates the noise for `seed`.
+    pub fn new(seed: u64) -> Self {
+        let mut table: [u8; 256] = std::array::from_fn(|i| i as u8);
+        let mut state = seed;
+        // Fisher-Yates shuffle
+        for i in (1..table.len()).rev() {
+            let j = (splitmix64(&mut state) % (i as u64 + 1)) as usize;
+            table.swap(i, j);
+        }
+        Self {
+            perm: std::array::from_fn(|i| table[i % 256]),
+        }
+    }
+
+    /// Creates the noise for the [global seed](crate::seeds::set_seed).
+    ///
+    /// For several independent layers, e.g. terrain and moisture, use
+    /// `Perlin2D::new(get_u64_seed_for("terrain"))` with
+    /// [`get_u64_seed_for`](crate::seeds::get_u64_seed_for) instead.
+    ///
+    /// # Panics
+    ///
+    /// If the global seed has not been set.
+    pub fn from_global_seed() -> Self {
+        Self::new(crate::seeds::get_seed())
+    }
+
+    /// Returns the noise at `(x, y)`, from -1 to 1.
+    ///
+    /// The noise is 0 at integer coordinates and changes smoothly over about one unit, so scale
+    /// the coordinates down for larger features.
+    pub fn sample(&self, x: f64, y: f64) -> f64 {
+        let x_floor = x.floor();
+        let y_floor = y.floor();
+        let xi = (x_floor as i64 & 255) as usize;
+        let yi = (y_floor as i64 & 255) as usize;
+        let xf = x - x_floor;
+        let yf = y - y_floor;
+
+        let hash = |dx: usize, dy: usize| self.perm[self.perm[xi + dx] as usize + yi + dy];
+        let n00 = gradient(hash(0, 0), xf, yf);
+        let n10 = gradient(hash(1, 0), xf - 1.0, yf);
+        let n01 = gradient(hash(0, 1), xf, yf - 1.0);
+        let n11 = gradient(hash(1, 1), xf - 1.0, yf - 1.0);
+
+        let u = fade(xf);
+        let v = fade(yf);
+        let nx0 = n00 + u * (n10 - n00);
+        let nx1 = n01 + u * (n11 - n01);
+        (nx0 + v * (nx1 - nx0)).clamp(-1.0, 1.0)
+    }
+
+    /// Returns fractal Brownian motion at `(x, y)`, from -1 to 1: the sum of `octaves` layers of
+    /// noise, each with `lacunarity` times the frequency and `gain` times the amplitude of the
+    /// previous one.
+    ///
+    /// A lacunarity of 2 and a gain of 0.5 are common. More octaves add finer detail.
+    pub fn fbm(&self, x: f64, y: f64, octaves: u32, lacunarity: f64, gain: f64) -> f64 {
+        let mut sum = 0.0;
+        let mut total_amplitude = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+        for _ in 0..octaves {
+            sum += amplitude * self.sample(x * frequency, y * frequency);
+            total_amplitude += amplitude;
+            amplitude *= gain;
+            frequency *= lacunarity;
+        }
+        if total_amplitude == 0.0 {
+            return 0.0;
+        }
+        (sum / total_amplitude).clamp(-1.0, 1.0)
+    }
+}
+
+/// The next value of the SplitMix64 generator.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// The dot product of the offset `(x, y)` with one of eight gradients of length √2.
+fn gradient(hash: u8, x: f64, y: f64) -> f64 {
+    match hash & 7 {
+        0 => x + y,
+        1 => -x + y,
+        2 => x - y,
+        3 => -x - y,
+        4 => std::f64::consts::SQRT_2 * x,
+        5 => -std::f64::consts::SQRT_2 * x,
+        6 => std::f64::consts::SQRT_2 * y,
+        _ => -std::f64::consts::SQRT_2 * y,
+    }
+}
+
+/// Perlin's smootherstep, `6t^5 - 15t^4 + 10t^3`.
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_golden_values() {
+        let noise = Perlin2D::new(1234);
+        let cases = [
+            ((0.5, 0.5), -0.3535533905932738),
+            ((1.25, 2.75), -0.15619497078482525),
+            ((-3.4, 7.9), -0.056528214003155774),
+            ((100.1, -55.5), 0.49098158297652306),
+        ];
+        for ((x, y), expected) in cases {
+            let actual = noise.sample(x, y);
+            assert!((actual - expected).abs() < 1e-12, "({x}, {y}): {actual}");
+        }
+        let fbm = noise.fbm(10.3, 4.7, 5, 2.0, 0.5);
+        assert!((fbm - 0.2509901995100038).abs() < 1e-12, "fbm: {fbm}");
+    }
+
+    #[test]
+    fn test_range_and_smoothness() {
+        let noise = Perlin2D::new(7);
+        let other = Perlin2D::new(8);
+        let mut differs = false;
+        for i in 0..2000 {
+            let (x, y) = (i as f64 * 0.37 - 300.0, i as f64 * 0.11);
+            let value = noise.sample(x, y);
+            assert!((-1.0..=1.0).contains(&value));
+            assert!((noise.sample(x + 0.001, y) - value).abs() < 0.01);
+            assert!((-1.0..=1.0).contains(&noise.fbm(x, y, 6, 2.0, 0.5)));
+            differs |= value != other.sample(x, y);
+        }
+        assert!(differs);
+        assert_eq!(noise.sample(3.0, -4.0), 0.0);
+        assert_eq!(noise.fbm(0.3, 0.4, 0, 2.0, 0.5), 0.0);
+    }
+}
@@ -0,0 +1,470 @@
+//! Incremental saving of chunked worlds.
+//!
+//! A [`ChunkPersistence`] saves the chunks of a world that were edited, in the background, and
+//! loads them again when they are needed. It works with any chunked store: the store tells it
+//! which chunks changed with [`ChunkPersistence::mark_dirty`], and asks it for a chunk with
+//! [`ChunkPersistence::load_or_generate`] before generating the chunk from scratch.
+//!
+//! # Example
+//! ```
+//! use std::time::{Duration, Instant};
+//! use teng::util::persistence::ChunkPersistence;
+//!
+//! let dir = std::env::temp_dir().join(format!("teng-persistence-doc-{}", std::process::id()));
+//! let mut persistence = ChunkPersistence::<Vec<u8>>::open(&dir).unwrap();
+//! persistence.set_autosave_interval(Some(Duration::from_secs(60)));
+//!
+//! let mut chunk = persistence.load_or_generate((0, 0), || vec![0; 16 * 16]);
+//! chunk[0] = 1; // the player edits the chunk
+//! persistence.mark_dirty((0, 0), chunk);
+//!
+//! // every frame, e.g. in a component's update
+//! persistence.update(Instant::now());
+//! // in the component's on_quit
+//! persistence.flush_all();
+//! # drop(persistence);
+//! # std::fs::remove_dir_all(&dir).unwrap();
+//! ```
+//!
+//! # On disk
+//! Every chunk is saved to its own file, by writing a temporary file and renaming it, so a chunk
+//! file is never torn. After a chunk file is in place, its coordinate is appended to an index
+//! journal. When the directory is opened, leftover temporary files are removed and the journal is
+//! compacted to the chunks whose files exist, so a crash at any point leaves a consistent subset
+//! of the saved chunks.
+
+use crate::components::debuginfo::DebugInfo;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{Sender, channel};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// The coordinate of a chunk.
+pub type ChunkCoord = (i64, i64);
+
+const INDEX_FILE: &str = "index.log";
+
+/// Statistics of a [`ChunkPersistence`], see [`ChunkPersistence::stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChunkPersistenceStats {
+    /// The number of chunks written to disk.
+    pub chunks_saved: usize,
+    /// The number of chunks read from disk.
+    pub chunks_loaded: usize,
+    /// The number of chunks that are dirty or waiting to be written.
+    pub queue_depth: usize,
+    /// The number of chunks that could not be written.
+    pub write_errors: usize,
+}
+
+/// Saves edited chunks in the background and loads them on demand.
+///
+/// Chunks marked dirty are saved once they have been dirty for the coalescing delay, so a chunk
+/// that is edited repeatedly is only saved once.
+///
+/// Dropping a `ChunkPersistence` without [`flush_all`](Self::flush_all) stops the background
+/// writer after the chunk it is currently writing, and unsaved edits are lost.
+pub struct ChunkPersistence<T> {
+    coalesce_delay: Duration,
+    autosave_interval: Option<Duration>,
+    last_autosave: Instant,
+    dirty: HashMap<ChunkCoord, (Instant, T)>,
+    next_generation: u64,
+    chunks_loaded: usize,
+    shared: Arc<WriterShared<T>>,
+    jobs: Option<Sender<Job<T>>>,
+    writer: Option<JoinHandle<()>>,
+}
+
+/// State shared with the background writer.
+struct WriterShared<T> {
+    dir: PathBuf,
+    /// Chunks whose files are complete and listed in the index journal.
+    on_disk: Mutex<HashSet<ChunkCoord>>,
+    /// Chunks that were sent to the writer, but are not written yet. Loads must see them.
+    in_flight: Mutex<HashMap<ChunkCoord, (u64, Arc<T>)>>,
+    chunks_saved: AtomicUsize,
+    write_errors: AtomicUsize,
+    stop: AtomicBool,
+}
+
+enum Job<T> {
+    Save(ChunkCoord, u64, Arc<T>),
+    Flush(Sender<()>),
+}
+
+impl<T: Serialize + DeserializeOwned + Clone + Send + Sync + 'static> ChunkPersistence<T> {
+    /// The default time a chunk stays dirty before it is saved.
+    pub const DEFAULT_COALESCE_DELAY: Duration = Duration::from_secs(5);
+
+    /// Opens the chunk directory `dir`, creating it if necessary, and starts the background writer.
+    pub fn open(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        let on_disk = recover(&dir)?;
+
+        let shared = Arc::new(WriterShared {
+            dir,
+            on_disk: Mutex::new(on_disk),
+            in_flight: Mutex::new(HashMap::new()),
+            chunks_saved: AtomicUsize::new(0),
+            write_errors: AtomicUsize::new(0),
+            stop: AtomicBool::new(false),
+        });
+        let (jobs, job_receiver) = channel::<Job<T>>();
+        let writer_shared = shared.clone();
+        let writer = std::thread::spawn(move || {
+            for job in job_receiver {
+                if writer_shared.stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                match job {
+                    Job::Save(coord, generation, chunk) => {
+                        writer_shared.save(coord, generation, &*chunk);
+                    }
+                    Job::Flush(done) => {
+                        let _ = done.send(());
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            coalesce_delay: Self::DEFAULT_COALESCE_DELAY,
+            autosave_interval: None,
+            last_autosave: Instant::now(),
+            dirty: HashMap::new(),
+            next_generation: 0,
+            chunks_loaded: 0,
+            shared,
+            jobs: Some(jobs),
+            writer: Some(writer),
+        })
+    }
+
+    /// Sets the time a chunk stays dirty before it is saved.
+    pub fn set_coalesce_delay(&mut self, delay: Duration) {
+        self.coalesce_delay = delay;
+    }
+
+    /// Sets the interval after which all dirty chunks are saved, regardless of the coalescing
+    /// delay. `None` disables autosaving.
+    pub fn set_autosave_interval(&mut self, interval: Option<Duration>) {
+        self.autosave_interval = interval;
+    }
+
+    /// Marks a chunk as edited, with its current contents.
+    ///
+    /// Marking an already dirty chunk replaces its contents, but does not delay its save.
+    pub fn mark_dirty(&mut self, coord: ChunkCoord, chunk: T) {
+        if let Some((_, dirty_chunk)) = self.dirty.get_mut(&coord) {
+            *dirty_chunk = chunk;
+        } else {
+            self.dirty.insert(coord, (Instant::now(), chunk));
+        }
+    }
+
+    /// Sends the chunks that have been dirty for the coalescing delay to the background writer,
+    /// and all dirty chunks if the autosave interval has passed.
+    pub fn update(&mut self, now: Instant) {
+        if let Some(interval) = self.autosave_interval
+            && now.duration_since(self.last_autosave) >= interval
+        {
+            self.last_autosave = now;
+            self.queue_where(|_| true);
+            return;
+        }
+        let delay = self.coalesce_delay;
+        self.queue_where(|dirty_since| now.duration_since(dirty_since) >= delay);
+    }
+
+    /// Saves all dirty chunks and blocks until they are written.
+    pub fn flush_all(&mut self) {
+        self.queue_where(|_| true);
+        let (done, done_receiver) = channel();
+        if let Some(jobs) = &self.jobs
+            && jobs.send(Job::Flush(done)).is_ok()
+        {
+            let _ = done_receiver.recv();
+        }
+    }
+
+    fn queue_where(&mut self, mut should_queue: impl FnMut(Instant) -> bool) {
+        let coords = self
+            .dirty
+            .iter()
+            .filter(|(_, (dirty_since, _))| should_queue(*dirty_since))
+            .map(|(coord, _)| *coord)
+            .collect::<Vec<_>>();
+        for coord in coords {
+            let (_, chunk) = self.dirty.remove(&coord).unwrap();
+            let chunk = Arc::new(chunk);
+            let generation = self.next_generation;
+            self.next_generation += 1;
+            self.shared
+                .in_flight
+                .lock()
+                .unwrap()
+                .insert(coord, (generation, chunk.clone()));
+            if let Some(jobs) = &self.jobs {
+                let _ = jobs.send(Job::Save(coord, generation, chunk));
+            }
+        }
+    }
+
+    /// Returns true if the chunk has a saved or unsaved edit.
+    pub fn contains(&self, coord: ChunkCoord) -> bool {
+        self.dirty.contains_key(&coord)
+            || self.shared.in_flight.lock().unwrap().contains_key(&coord)
+            || self.shared.on_disk.lock().unwrap().contains(&coord)
+    }
+
+    /// Returns the latest edited contents of a chunk, from memory if it is not saved yet, or from
+    /// disk. Returns `None` if the chunk was never edited or cannot be read.
+    pub fn load(&mut self, coord: ChunkCoord) -> Option<T> {
+        if let Some((_, chunk)) = self.dirty.get(&coord) {
+            return Some(chunk.clone());
+        }
+        if let Some((_, chunk)) = self.shared.in_flight.lock().unwrap().get(&coord) {
+            return Some(T::clone(chunk));
+        }
+        if !self.shared.on_disk.lock().unwrap().contains(&coord) {
+            return None;
+        }
+        let bytes = fs::read(chunk_path(&self.shared.dir, coord)).ok()?;
+        let chunk = bincode::deserialize(&bytes).ok()?;
+        self.chunks_loaded += 1;
+        Some(chunk)
+    }
+
+    /// Loads a chunk, or generates it with `generate` if it was never edited.
+    pub fn load_or_generate(&mut self, coord: ChunkCoord, generate: impl FnOnce() -> T) -> T {
+        self.load(coord).unwrap_or_else(generate)
+    }
+
+    /// Returns the statistics of this `ChunkPersistence`.
+    pub fn stats(&self) -> ChunkPersistenceStats {
+        ChunkPersistenceStats {
+            chunks_saved: self.shared.chunks_saved.load(Ordering::Relaxed),
+            chunks_loaded: self.chunks_loaded,
+            queue_depth: self.dirty.len() + self.shared.in_flight.lock().unwrap().len(),
+            write_errors: self.shared.write_errors.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Reports the statistics to the debug info, under `persistence.*`.
+    pub fn report(&self, debug_info: &mut DebugInfo) {
+        let stats = self.stats();
+        debug_info.set_i64("persistence.chunks_saved", stats.chunks_saved as i64);
+        debug_info.set_i64("persistence.chunks_loaded", stats.chunks_loaded as i64);
+        debug_info.set_i64("persistence.queue_depth", stats.queue_depth as i64);
+        if stats.write_errors > 0 {
+            debug_info.set_i64("persistence.write_errors", stats.write_errors as i64);
+        }
+    }
+}
+
+impl<T> Drop for ChunkPersistence<T> {
+    fn drop(&mut self) {
+        self.shared.stop.store(true, Ordering::Relaxed);
+        self.jobs.take();
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+impl<T: Serialize> WriterShared<T> {
+    fn save(&self, coord: ChunkCoord, generation: u64, chunk: &T) {
+        if self.write_chunk(coord, chunk).is_ok() {
+            self.chunks_saved.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.write_errors.fetch_add(1, Ordering::Relaxed);
+        }
+        let mut in_flight = self.in_flight.lock().unwrap();
+        // a newer version of the chunk may be queued already
+        if in_flight.get(&coord).is_some_and(|(g, _)| *g == generation) {
+            in_flight.remove(&coord);
+        }
+    }
+
+    fn write_chunk(&self, coord: ChunkCoord, chunk: &T) -> io::Result<()> {
+        let bytes = bincode::serialize(chunk).map_err(io::Error::other)?;
+        let path = chunk_path(&self.dir, coord);
+        let tmp_path = path.with_extension("tmp");
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(&bytes)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &path)?;
+
+        let mut on_disk = self.on_disk.lock().unwrap();
+        if !on_disk.contains(&coord) {
+            let mut index = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.dir.join(INDEX_FILE))?;
+            writeln!(index, "{} {}", coord.0, coord.1)?;
+            on_disk.insert(coord);
+        }
+        Ok(())
+    }
+}
+
+fn chunk_path(dir: &Path, (x, y): ChunkCoord) -> PathBuf {
+    dir.join(format!("chunk_{x}_{y}.bin"))
+}
+
+/// Removes leftover temporary files and compacts the index journal to the chunks whose files
+/// exist. Returns those chunks.
+fn recover(dir: &Path) -> io::Result<HashSet<ChunkCoord>> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "tmp") {
+            fs::remove_file(&path)?;
+        }
+    }
+
+    let index_path = dir.join(INDEX_FILE);
+    let index = match fs::read_to_string(&index_path) {
+        Ok(index) => index,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err),
+    };
+    // a torn last line does not parse and is skipped
+    let on_disk = index
+        .lines()
+        .filter_map(|line| {
+            let (x, y) = line.split_once(' ')?;
+            Some((x.parse().ok()?, y.parse().ok()?))
+        })
+        .filter(|&coord| chunk_path(dir, coord).exists())
+        .collect::<HashSet<ChunkCoord>>();
+
+    let mut compacted = String::new();
+    for (x, y) in &on_disk {
+        compacted.push_str(&format!("{x} {y}\n"));
+    }
+    let tmp_path = index_path.with_extension("tmp");
+    fs::write(&tmp_path, compacted)?;
+    fs::rename(&tmp_path, &index_path)?;
+    Ok(on_disk)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "teng-persistence-test-{name}-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn generate(coord: ChunkCoord) -> Vec<i64> {
+        vec![coord.0 * 1000 + coord.1; 64]
+    }
+
+    fn edit(coord: ChunkCoord) -> Vec<i64> {
+        let mut chunk = generate(coord);
+        chunk[0] = -1;
+        chunk[63] = -1;
+        chunk
+    }
+
+    fn coords() -> impl Iterator<Item = ChunkCoord> {
+        (-5..5).flat_map(|x| (-5..5).map(move |y| (x, y)))
+    }
+
+    fn is_edited(coord: ChunkCoord) -> bool {
+        (coord.0 + coord.1) % 3 == 0
+    }
+
+    #[test]
+    fn test_edits_round_trip() {
+        let dir = test_dir("round-trip");
+        let mut persistence = ChunkPersistence::open(&dir).unwrap();
+        for coord in coords().filter(|&c| is_edited(c)) {
+            // repeated edits of a chunk are coalesced
+            persistence.mark_dirty(coord, generate(coord));
+            persistence.mark_dirty(coord, edit(coord));
+        }
+        persistence.flush_all();
+        let edited = coords().filter(|&c| is_edited(c)).count();
+        assert_eq!(persistence.stats().chunks_saved, edited);
+        assert_eq!(persistence.stats().queue_depth, 0);
+        drop(persistence);
+
+        let mut persistence = ChunkPersistence::open(&dir).unwrap();
+        for coord in coords() {
+            let chunk = persistence.load_or_generate(coord, || generate(coord));
+            if is_edited(coord) {
+                assert_eq!(chunk, edit(coord));
+            } else {
+                assert_eq!(chunk, generate(coord));
+            }
+        }
+        assert_eq!(persistence.stats().chunks_loaded, edited);
+        drop(persistence);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_interrupted_save_is_consistent() {
+        let dir = test_dir("interrupted");
+        let mut persistence = ChunkPersistence::open(&dir).unwrap();
+        persistence.set_coalesce_delay(Duration::ZERO);
+        for coord in coords() {
+            persistence.mark_dirty(coord, edit(coord));
+        }
+        persistence.update(Instant::now());
+        // dropping without flushing interrupts the background writer
+        drop(persistence);
+        // simulate a crash in the middle of writing a chunk
+        fs::write(chunk_path(&dir, (100, 100)).with_extension("tmp"), [1, 2]).unwrap();
+
+        let mut persistence = ChunkPersistence::open(&dir).unwrap();
+        for coord in coords() {
+            let chunk = persistence.load_or_generate(coord, || generate(coord));
+            assert!(chunk == edit(coord) || chunk == generate(coord));
+        }
+        assert!(!persistence.contains((100, 100)));
+        assert!(
+            fs::read_dir(&dir)
+                .unwrap()
+                .all(|entry| entry.unwrap().path().extension().unwrap() != "tmp")
+        );
+        drop(persistence);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_coalesce_delay() {
+        let dir = test_dir("coalesce");
+        let mut persistence = ChunkPersistence::open(&dir).unwrap();
+        persistence.set_coalesce_delay(Duration::from_secs(10));
+        persistence.mark_dirty((0, 0), edit((0, 0)));
+        let now = Instant::now();
+        persistence.update(now);
+        assert_eq!(persistence.stats().queue_depth, 1);
+        // unsaved edits are visible to loads
+        assert_eq!(persistence.load((0, 0)), Some(edit((0, 0))));
+
+        persistence.update(now + Duration::from_secs(11));
+        assert!(persistence.dirty.is_empty());
+        persistence.flush_all();
+        assert_eq!(persistence.stats().chunks_saved, 1);
+        drop(persistence);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
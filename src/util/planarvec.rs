@@ -2,6 +2,10 @@
 //!
 //! - [`Bounds`]: Represents the boundaries of a 2D plane, defined by minimum and maximum x and y coordinates.
 //! - [`PlanarVec`]: A 2D vector-like data structure capable of storing data of a growable 2D plane, efficiently growable in the x-dimension, and indexable by `(i64, i64)` tuples.
+//!
+//! With the `serde` feature, both implement `Serialize` and `Deserialize`. A `PlanarVec` is stored
+//! as its bounds and the cells inside of them, column by column. Use
+//! [`PlanarVec::shrink_to_fit_bounds`] before saving a mostly empty world.
 
 use crate::util::bidivec::BidiVec;
 use std::ops::{Index, IndexMut};
@@ -13,6 +17,7 @@ use std::ops::{Index, IndexMut};
 /// *   `x` values range from `min_x`..=`max_x` (inclusive)
 /// *   `y` values range from `min_y`..=`max_y` (inclusive)
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Bounds {
     /// Maximum x-coordinate of the bounds.
     pub max_x: i64,
@@ -123,6 +128,16 @@ impl Bounds {
         bounds
     }
 
+    /// Grows `self` just enough to contain `(x, y)`. Empty bounds become the single point.
+    pub fn expand_to_include(&mut self, x: i64, y: i64) {
+        *self = self.union(Bounds {
+            min_x: x,
+            max_x: x,
+            min_y: y,
+            max_y: y,
+        });
+    }
+
     /// Returns the bounds containing both `self` and `other` bounds.
     pub fn union(&self, other: Bounds) -> Bounds {
         if self.is_empty() {
@@ -148,11 +163,14 @@ impl Bounds {
 /// and stable i64 indexing for both x and y coordinates.
 ///
 /// Efficiently growable in the x dimension due to the ordering of the internal `BidiVec`s.
+/// Moving the content with [`PlanarVec::translate`] is free.
 #[derive(Debug, Clone)]
 pub struct PlanarVec<T> {
     // Outer index is x, inner index is y
     data: BidiVec<BidiVec<T>>,
     bounds: Bounds,
+    // The coordinates whose data is at index (0, 0) of `data`.
+    origin: (i64, i64),
 }
 
 impl<T> Default for PlanarVec<T> {
@@ -160,6 +178,7 @@ impl<T> Default for PlanarVec<T> {
         Self {
             data: BidiVec::default(),
             bounds: Bounds::default(),
+            origin: (0, 0),
         }
     }
 }
@@ -177,7 +196,11 @@ impl<T> PlanarVec<T> {
             row.grow(bounds.min_y..=bounds.max_y, default.clone());
         }
 
-        Self { data, bounds }
+        Self {
+            data,
+            bounds,
+            origin: (0, 0),
+        }
     }
 
     /// Returns the world bounds
@@ -211,7 +234,7 @@ impl<T> PlanarVec<T> {
             return None;
         }
 
-        Some(&self.data[x][y])
+        Some(&self.data[x - self.origin.0][y - self.origin.1])
     }
 
     /// Gets the value at the given position mutably, if it exists.
@@ -220,7 +243,53 @@ impl<T> PlanarVec<T> {
             return None;
         }
 
-        Some(&mut self.data[x][y])
+        Some(&mut self.data[x - self.origin.0][y - self.origin.1])
+    }
+
+    /// Returns an iterator over all cells and their coordinates, column by column.
+    pub fn iter(&self) -> impl Iterator<Item = (i64, i64, &T)> {
+        self.x_range()
+            .flat_map(move |x| self.y_range().map(move |y| (x, y, &self[(x, y)])))
+    }
+
+    /// Returns an iterator over the cells that are not equal to `default`, column by column, e.g.
+    /// to export a sparse world.
+    pub fn iter_non_default<'a>(&'a self, default: &'a T) -> impl Iterator<Item = (i64, i64, &'a T)>
+    where
+        T: PartialEq,
+    {
+        self.iter().filter(move |(_, _, value)| *value != default)
+    }
+
+    /// Shrinks the bounds to the smallest bounds that contain all cells that are not equal to
+    /// `default`. If all cells are equal to `default`, the bounds become empty.
+    ///
+    /// The storage is rebuilt, but like for [`PlanarVec::new`], it always reaches from the origin
+    /// to the bounds.
+    pub fn shrink_to_fit_bounds(&mut self, default: T)
+    where
+        T: Clone + PartialEq,
+    {
+        let fitted = self
+            .iter_non_default(&default)
+            .fold(Bounds::empty(), |bounds, (x, y, _)| {
+                bounds.union(Bounds {
+                    min_x: x,
+                    max_x: x,
+                    min_y: y,
+                    max_y: y,
+                })
+            });
+        if fitted == self.bounds {
+            return;
+        }
+        let mut shrunk = PlanarVec::new(fitted, default.clone());
+        for x in shrunk.x_range() {
+            for y in shrunk.y_range() {
+                shrunk[(x, y)] = std::mem::replace(&mut self[(x, y)], default.clone());
+            }
+        }
+        *self = shrunk;
     }
 
     /// Expands the `PlanarVec` to at least contain the given bounds.
@@ -231,24 +300,64 @@ impl<T> PlanarVec<T> {
     where
         T: Clone,
     {
-        let union_bounds = Bounds {
-            min_x: self.bounds.min_x.min(bounds.min_x),
-            max_x: self.bounds.max_x.max(bounds.max_x),
-            min_y: self.bounds.min_y.min(bounds.min_y),
-            max_y: self.bounds.max_y.max(bounds.max_y),
-        };
+        let union_bounds = self.bounds.union(bounds);
 
         if union_bounds == self.bounds {
             return;
         }
 
-        self.data
-            .grow(union_bounds.min_x..=union_bounds.max_x, BidiVec::new());
+        let (origin_x, origin_y) = self.origin;
+        self.data.grow(
+            union_bounds.min_x - origin_x..=union_bounds.max_x - origin_x,
+            BidiVec::new(),
+        );
         for row in self.data.iter_mut() {
-            row.grow(union_bounds.min_y..=union_bounds.max_y, default.clone());
+            row.grow(
+                union_bounds.min_y - origin_y..=union_bounds.max_y - origin_y,
+                default.clone(),
+            );
         }
 
-        self.bounds = union_bounds;
+        // the storage may already reach past the old bounds, e.g. towards the origin
+        let old_bounds = std::mem::replace(&mut self.bounds, union_bounds);
+        for new_region in union_bounds.subtract(old_bounds) {
+            if new_region.is_empty() {
+                continue;
+            }
+            for x in new_region.min_x..=new_region.max_x {
+                for y in new_region.min_y..=new_region.max_y {
+                    self[(x, y)] = default.clone();
+                }
+            }
+        }
+    }
+
+    /// Moves all content by `(dx, dy)`, so that the value at `(x, y)` is at `(x + dx, y + dy)`
+    /// afterwards. The bounds move along. Nothing is copied.
+    pub fn translate(&mut self, dx: i64, dy: i64) {
+        if !self.bounds.is_empty() {
+            self.bounds.min_x += dx;
+            self.bounds.max_x += dx;
+            self.bounds.min_y += dy;
+            self.bounds.max_y += dy;
+        }
+        self.origin.0 += dx;
+        self.origin.1 += dy;
+    }
+
+    /// Returns the value at `(x, y)`. If it is out of bounds, the `PlanarVec` is first expanded
+    /// just enough to contain it, and the new cells are filled with the value of `f`.
+    pub fn get_or_insert_with(&mut self, x: i64, y: i64, f: impl FnOnce() -> T) -> &mut T
+    where
+        T: Clone,
+    {
+        if !self.bounds.contains(x, y) {
+            let mut bounds = self.bounds;
+            bounds.expand_to_include(x, y);
+            self.expand(bounds, f());
+        }
+        self.get_mut(x, y)
+            .expect("expanded to contain the position")
     }
 }
 
@@ -266,6 +375,69 @@ impl<T> IndexMut<(i64, i64)> for PlanarVec<T> {
     }
 }
 
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize> serde::Serialize for PlanarVec<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        struct Cells<'a, T>(&'a PlanarVec<T>);
+        impl<T: serde::Serialize> serde::Serialize for Cells<'_, T> {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_seq(self.0.iter().map(|(_, _, value)| value))
+            }
+        }
+
+        let mut state = serializer.serialize_struct("PlanarVec", 2)?;
+        state.serialize_field("bounds", &self.bounds)?;
+        state.serialize_field("cells", &Cells(self))?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: Clone + serde::Deserialize<'de>> serde::Deserialize<'de> for PlanarVec<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(rename = "PlanarVec")]
+        struct Serialized<T> {
+            bounds: Bounds,
+            cells: Vec<T>,
+        }
+
+        let Serialized { bounds, cells } = Serialized::<T>::deserialize(deserializer)?;
+        let (width, height) = if bounds.is_empty() {
+            (0, 0)
+        } else {
+            (
+                bounds.max_x - bounds.min_x + 1,
+                bounds.max_y - bounds.min_y + 1,
+            )
+        };
+        if cells.len() as i128 != width as i128 * height as i128 {
+            return Err(serde::de::Error::invalid_length(
+                cells.len(),
+                &"one cell for every position in the bounds",
+            ));
+        }
+        let Some(first) = cells.first() else {
+            return Ok(Self {
+                data: BidiVec::new(),
+                bounds,
+                origin: (0, 0),
+            });
+        };
+        let mut planar_vec = PlanarVec::new(bounds, first.clone());
+        for (i, value) in cells.into_iter().enumerate() {
+            let (x, y) = (
+                bounds.min_x + i as i64 / height,
+                bounds.min_y + i as i64 % height,
+            );
+            planar_vec[(x, y)] = value;
+        }
+        Ok(planar_vec)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -301,4 +473,161 @@ mod tests {
         assert_eq!(planar_vec[(2, 1)], 0);
         assert_eq!(planar_vec.get(2, 2), None);
     }
+
+    fn sparse_world() -> PlanarVec<u8> {
+        let mut world = PlanarVec::new(
+            Bounds {
+                min_x: -10,
+                max_x: 10,
+                min_y: -5,
+                max_y: 8,
+            },
+            0,
+        );
+        world[(-7, -5)] = 1;
+        world[(-3, 2)] = 2;
+        world[(4, -1)] = 3;
+        world
+    }
+
+    #[test]
+    fn test_shrink_to_fit_bounds() {
+        let mut world = sparse_world();
+        let cells: Vec<_> = world
+            .iter_non_default(&0)
+            .map(|(x, y, &v)| (x, y, v))
+            .collect();
+        assert_eq!(cells, [(-7, -5, 1), (-3, 2, 2), (4, -1, 3)]);
+
+        world.shrink_to_fit_bounds(0);
+        assert_eq!(
+            world.bounds(),
+            Bounds {
+                min_x: -7,
+                max_x: 4,
+                min_y: -5,
+                max_y: 2,
+            }
+        );
+        let shrunk: Vec<_> = world
+            .iter_non_default(&0)
+            .map(|(x, y, &v)| (x, y, v))
+            .collect();
+        assert_eq!(shrunk, cells);
+        assert_eq!(world.iter().count(), 12 * 8);
+
+        // still growable afterwards
+        world.expand(
+            Bounds {
+                min_x: 0,
+                max_x: 12,
+                min_y: 0,
+                max_y: 0,
+            },
+            0,
+        );
+        assert_eq!(world[(-3, 2)], 2);
+        assert_eq!(world[(12, 0)], 0);
+
+        world.clear(0);
+        world.shrink_to_fit_bounds(0);
+        assert!(world.bounds().is_empty());
+        assert_eq!(world.iter().count(), 0);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let mut world = sparse_world();
+        let json = serde_json::to_string(&world).unwrap();
+        let loaded: PlanarVec<u8> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.bounds(), world.bounds());
+        assert!(loaded.iter().eq(world.iter()));
+        assert_eq!(loaded[(-7, -5)], 1);
+        assert_eq!(loaded.get(-11, 0), None);
+
+        world.shrink_to_fit_bounds(0);
+        let json = serde_json::to_string(&world).unwrap();
+        assert!(json.starts_with(r#"{"bounds":{"max_x":4,"min_x":-7,"max_y":2,"min_y":-5}"#));
+        let loaded: PlanarVec<u8> = serde_json::from_str(&json).unwrap();
+        assert!(loaded.iter().eq(world.iter()));
+        assert_eq!(loaded[(4, -1)], 3);
+
+        let empty: PlanarVec<u8> = serde_json::from_str(
+            r#"{"bounds":{"max_x":-1,"min_x":0,"max_y":-1,"min_y":0},"cells":[]}"#,
+        )
+        .unwrap();
+        assert!(empty.bounds().is_empty());
+        let wrong_length = serde_json::from_str::<PlanarVec<u8>>(
+            r#"{"bounds":{"max_x":1,"min_x":0,"max_y":0,"min_y":0},"cells":[1]}"#,
+        );
+        assert!(wrong_length.is_err());
+    }
+
+    #[test]
+    fn test_translate() {
+        let mut planar_vec = sparse_world();
+        planar_vec.translate(-100, 3);
+        assert_eq!(
+            planar_vec.bounds(),
+            Bounds {
+                min_x: -110,
+                max_x: -90,
+                min_y: -2,
+                max_y: 11,
+            }
+        );
+        let cells: Vec<_> = planar_vec
+            .iter_non_default(&0)
+            .map(|(x, y, &v)| (x, y, v))
+            .collect();
+        assert_eq!(cells, [(-107, -2, 1), (-103, 5, 2), (-96, 2, 3)]);
+        assert_eq!(planar_vec.get(-7, -5), None);
+
+        // expanding and shrinking keep working after a translation
+        planar_vec.expand(
+            Bounds {
+                min_x: -120,
+                max_x: 0,
+                min_y: 0,
+                max_y: 0,
+            },
+            9,
+        );
+        assert_eq!(planar_vec[(-120, 0)], 9);
+        assert_eq!(planar_vec[(0, 11)], 9);
+        assert_eq!(planar_vec[(-103, 5)], 2);
+        planar_vec.translate(103, -5);
+        assert_eq!(planar_vec[(0, 0)], 2);
+    }
+
+    #[test]
+    fn test_get_or_insert_with() {
+        let mut bounds = Bounds::empty();
+        bounds.expand_to_include(-3, 4);
+        assert_eq!(
+            bounds,
+            Bounds {
+                min_x: -3,
+                max_x: -3,
+                min_y: 4,
+                max_y: 4,
+            }
+        );
+
+        let mut planar_vec = PlanarVec::default();
+        *planar_vec.get_or_insert_with(-3, 4, || 1) += 1;
+        assert_eq!(planar_vec.bounds(), bounds);
+        assert_eq!(planar_vec[(-3, 4)], 2);
+        // in bounds, `f` is not called
+        *planar_vec.get_or_insert_with(-3, 4, || unreachable!()) += 1;
+        assert_eq!(planar_vec[(-3, 4)], 3);
+
+        *planar_vec.get_or_insert_with(2, 2, || 0) = 5;
+        assert_eq!(planar_vec.bounds().min_x, -3);
+        assert_eq!(planar_vec.bounds().max_y, 4);
+        assert_eq!(planar_vec[(2, 2)], 5);
+        assert_eq!(planar_vec[(-3, 2)], 0);
+        assert_eq!(planar_vec[(-3, 4)], 3);
+    }
 }
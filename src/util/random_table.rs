@@ -0,0 +1,554 @@
+//! Weighted random choices, e.g. drop tables, random events or the weather, see
+//! [`WeightedTable`].
+//!
+//! All sampling takes an `&mut impl Rng`, so a table rolls deterministically with an rng seeded
+//! from [`get_u64_seed_for`](crate::seeds::get_u64_seed_for), e.g. for replays.
+//!
+//! # Example
+//! ```
+//! use rand::SeedableRng;
+//! use rand::rngs::StdRng;
+//! use teng::util::random_table::{Pity, WeightedTable};
+//!
+//! let gems = WeightedTable::from_pairs([("ruby", 1.0), ("emerald", 1.0)]);
+//! let mut drops = WeightedTable::from_pairs([("nothing", 80.0), ("coin", 19.0)]);
+//! let gem = drops.push_table(gems, 1.0);
+//! // a gem drops at least every 50 rolls
+//! drops.set_pity(gem, Pity::new(0.1).with_cap(50));
+//!
+//! let mut rng = StdRng::seed_from_u64(42);
+//! let drop = drops.roll(&mut rng);
+//! assert!(["nothing", "coin", "ruby", "emerald"].contains(drop));
+//! ```
+
+use rand::Rng;
+
+/// Increases the weight of an entry each time it is not rolled, reset when it is.
+///
+/// The effective weight is `weight * (1 + growth * misses)`. With a cap of `K`, the entry is
+/// rolled at the latest on the `K`th roll after it was last rolled, unless it is excluded.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pity {
+    growth: f64,
+    cap: Option<u32>,
+}
+
+impl Pity {
+    /// Creates a pity that adds `growth` times the entry's weight per miss.
+    pub fn new(growth: f64) -> Self {
+        Self {
+            growth: growth.max(0.0),
+            cap: None,
+        }
+    }
+
+    /// Guarantees the entry within `cap` rolls.
+    pub fn with_cap(mut self, cap: u32) -> Self {
+        self.cap = Some(cap.max(1));
+        self
+    }
+}
+
+/// The pity counters of a table and its nested tables, for save games.
+///
+/// See [`WeightedTable::pity_state`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub struct PityState {
+    /// The misses of every entry, depth first.
+    pub misses: Vec<u32>,
+}
+
+/// What an entry of a [`WeightedTable`] resolves to.
+pub enum Outcome<T, C = ()> {
+    Item(T),
+    /// A nested table that is rolled in turn.
+    Table(WeightedTable<T, C>),
+}
+
+/// Returns true if an entry is excluded from a roll with the context.
+type Exclusion<C> = Box<dyn Fn(&C) -> bool>;
+
+struct Entry<T, C> {
+    outcome: Outcome<T, C>,
+    weight: f64,
+    pity: Option<Pity>,
+    misses: u32,
+    /// Excludes the entry from a roll, given the roll's context.
+    exclude: Option<Exclusion<C>>,
+}
+
+impl<T, C> Entry<T, C> {
+    fn effective_weight(&self) -> f64 {
+        match self.pity {
+            Some(pity) => self.weight * (1.0 + pity.growth * self.misses as f64),
+            None => self.weight,
+        }
+    }
+
+    /// Returns true if the pity cap forces the entry this roll.
+    fn is_forced(&self) -> bool {
+        self.pity
+            .and_then(|pity| pity.cap)
+            .is_some_and(|cap| self.misses + 1 >= cap)
+    }
+}
+
+/// Vose's alias table over the base weights, for O(1) sampling.
+#[derive(Default)]
+struct AliasTable {
+    prob: Vec<f64>,
+    alias: Vec<usize>,
+}
+
+impl AliasTable {
+    fn new(weights: &[f64]) -> Self {
+        let n = weights.len();
+        let total: f64 = weights.iter().sum();
+        if n == 0 || total <= 0.0 {
+            return Self::default();
+        }
+        let mut scaled: Vec<f64> = weights.iter().map(|w| w * n as f64 / total).collect();
+        let mut prob = vec![1.0; n];
+        let mut alias: Vec<usize> = (0..n).collect();
+        let (mut small, mut large): (Vec<usize>, Vec<usize>) =
+            (0..n).partition(|&i| scaled[i] < 1.0);
+        while let (Some(s), Some(&l)) = (small.pop(), large.last()) {
+            prob[s] = scaled[s];
+            alias[s] = l;
+            scaled[l] -= 1.0 - scaled[s];
+            if scaled[l] < 1.0 {
+                large.pop();
+                small.push(l);
+            }
+        }
+        // the rest are 1.0 up to rounding errors
+        Self { prob, alias }
+    }
+
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<usize> {
+        if self.prob.is_empty() {
+            return None;
+        }
+        let i = rng.gen_range(0..self.prob.len());
+        Some(if rng.r#gen::<f64>() < self.prob[i] {
+            i
+        } else {
+            self.alias[i]
+        })
+    }
+}
+
+/// A table of weighted entries, each an item or a nested table.
+///
+/// Rolls sample the alias table in O(1), unless pity or exclusions change the weights of the
+/// roll, which takes O(n). `C` is the context that exclusions are evaluated against, see
+/// [`WeightedTable::exclude_if`].
+pub struct WeightedTable<T, C = ()> {
+    entries: Vec<Entry<T, C>>,
+    /// Rebuilt on the next roll after the weights changed.
+    alias: Option<AliasTable>,
+}
+
+impl<T, C> Default for WeightedTable<T, C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, C> FromIterator<(T, f64)> for WeightedTable<T, C> {
+    fn from_iter<I: IntoIterator<Item = (T, f64)>>(iter: I) -> Self {
+        Self::from_pairs(iter)
+    }
+}
+
+impl<T, C> WeightedTable<T, C> {
+    pub fn new() -> Self {
+        Self {
+            entries: vec![],
+            alias: None,
+        }
+    }
+
+    /// Creates a table of items with their weights.
+    pub fn from_pairs(pairs: impl IntoIterator<Item = (T, f64)>) -> Self {
+        let mut table = Self::new();
+        for (item, weight) in pairs {
+            table.push_outcome(Outcome::Item(item), weight);
+        }
+        table
+    }
+
+    /// Returns the number of entries, not counting the entries of nested tables.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if there are no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds an item and returns the index of its entry.
+    ///
+    /// # Panics
+    /// Panics if `weight` is negative or not finite.
+    pub fn push(&mut self, item: T, weight: f64) -> usize {
+        self.push_outcome(Outcome::Item(item), weight)
+    }
+
+    /// Adds a nested table, which is rolled when its entry is, and returns the index of its entry.
+    ///
+    /// # Panics
+    /// Panics if `weight` is negative or not finite.
+    pub fn push_table(&mut self, table: WeightedTable<T, C>, weight: f64) -> usize {
+        self.push_outcome(Outcome::Table(table), weight)
+    }
+
+    fn push_outcome(&mut self, outcome: Outcome<T, C>, weight: f64) -> usize {
+        assert!(
+            weight.is_finite() && weight >= 0.0,
+            "invalid weight {weight}"
+        );
+        self.entries.push(Entry {
+            outcome,
+            weight,
+            pity: None,
+            misses: 0,
+            exclude: None,
+        });
+        self.alias = None;
+        self.entries.len() - 1
+    }
+
+    /// Sets the base weight of the entry at `index`.
+    ///
+    /// # Panics
+    /// Panics if `weight` is negative or not finite.
+    pub fn set_weight(&mut self, index: usize, weight: f64) {
+        assert!(
+            weight.is_finite() && weight >= 0.0,
+            "invalid weight {weight}"
+        );
+        self.entries[index].weight = weight;
+        self.alias = None;
+    }
+
+    /// Adds a pity to the entry at `index`, see [`Pity`].
+    pub fn set_pity(&mut self, index: usize, pity: Pity) {
+        self.entries[index].pity = Some(pity);
+    }
+
+    /// Excludes the entry at `index` from rolls whose context `exclude` returns true for, e.g.
+    /// rain in a desert biome.
+    pub fn exclude_if(&mut self, index: usize, exclude: impl Fn(&C) -> bool + 'static) {
+        self.entries[index].exclude = Some(Box::new(exclude));
+    }
+
+    /// Returns the outcome of the entry at `index`.
+    pub fn outcome(&self, index: usize) -> &Outcome<T, C> {
+        &self.entries[index].outcome
+    }
+
+    /// Returns the number of rolls since the entry at `index` was last rolled.
+    pub fn misses(&self, index: usize) -> u32 {
+        self.entries[index].misses
+    }
+
+    /// Rolls an item, or returns `None` if every entry is excluded or has no weight.
+    pub fn roll_with<R: Rng + ?Sized>(&mut self, rng: &mut R, context: &C) -> Option<&T> {
+        let path = self.roll_path(rng, context, &[])?;
+        self.update_pity(&[path[0]]);
+        Some(self.resolve(&path))
+    }
+
+    /// Rolls up to `n` items from distinct entries, fewer if there are not enough entries that
+    /// can be rolled. Pity counts this as a single roll.
+    pub fn roll_n_unique_with<R: Rng + ?Sized>(
+        &mut self,
+        rng: &mut R,
+        n: usize,
+        context: &C,
+    ) -> Vec<&T> {
+        let mut paths: Vec<Vec<usize>> = vec![];
+        while paths.len() < n {
+            let taken: Vec<usize> = paths.iter().map(|path| path[0]).collect();
+            let Some(path) = self.roll_path(rng, context, &taken) else {
+                break;
+            };
+            paths.push(path);
+        }
+        let taken: Vec<usize> = paths.iter().map(|path| path[0]).collect();
+        self.update_pity(&taken);
+        paths.iter().map(|path| self.resolve(path)).collect()
+    }
+
+    /// Returns true if an item can be rolled from the entry at `index`.
+    fn is_rollable(&self, index: usize, context: &C) -> bool {
+        let entry = &self.entries[index];
+        if entry.weight <= 0.0
+            || entry
+                .exclude
+                .as_ref()
+                .is_some_and(|exclude| exclude(context))
+        {
+            return false;
+        }
+        match &entry.outcome {
+            Outcome::Item(_) => true,
+            Outcome::Table(table) => (0..table.len()).any(|i| table.is_rollable(i, context)),
+        }
+    }
+
+    /// Picks an entry that is not in `taken`, and rolls nested tables. Returns the indices from
+    /// the top level down to the item.
+    fn roll_path<R: Rng + ?Sized>(
+        &mut self,
+        rng: &mut R,
+        context: &C,
+        taken: &[usize],
+    ) -> Option<Vec<usize>> {
+        if self.alias.is_none() {
+            let weights: Vec<f64> = self.entries.iter().map(|entry| entry.weight).collect();
+            self.alias = Some(AliasTable::new(&weights));
+        }
+        let index = self.pick(rng, context, taken)?;
+        let mut path = vec![index];
+        if let Outcome::Table(table) = &mut self.entries[index].outcome {
+            let nested = table.roll_path(rng, context, &[])?;
+            table.update_pity(&[nested[0]]);
+            path.extend(nested);
+        }
+        Some(path)
+    }
+
+    fn pick<R: Rng + ?Sized>(&self, rng: &mut R, context: &C, taken: &[usize]) -> Option<usize> {
+        let needs_weights = !taken.is_empty()
+            || self.entries.iter().any(|entry| {
+                entry.exclude.is_some()
+                    || (entry.pity.is_some() && entry.misses > 0)
+                    || entry.is_forced()
+            });
+        if !needs_weights
+            && let Some(index) = self.alias.as_ref().and_then(|alias| alias.sample(rng))
+            // e.g. a nested table whose items are all excluded
+            && self.is_rollable(index, context)
+        {
+            return Some(index);
+        }
+
+        let candidates: Vec<usize> = (0..self.entries.len())
+            .filter(|i| !taken.contains(i) && self.is_rollable(*i, context))
+            .collect();
+        // the entry that waited the longest wins among the forced ones
+        let forced = candidates
+            .iter()
+            .copied()
+            .filter(|&i| self.entries[i].is_forced())
+            .max_by_key(|&i| (self.entries[i].misses, std::cmp::Reverse(i)));
+        if forced.is_some() {
+            return forced;
+        }
+        let total: f64 = candidates
+            .iter()
+            .map(|&i| self.entries[i].effective_weight())
+            .sum();
+        let mut target = rng.r#gen::<f64>() * total;
+        for &i in &candidates {
+            target -= self.entries[i].effective_weight();
+            if target < 0.0 {
+                return Some(i);
+            }
+        }
+        // rounding errors
+        candidates.last().copied()
+    }
+
+    /// Resets the misses of the rolled entries and counts a miss for the others.
+    fn update_pity(&mut self, rolled: &[usize]) {
+        for (i, entry) in self.entries.iter_mut().enumerate() {
+            if rolled.contains(&i) {
+                entry.misses = 0;
+            } else if entry.pity.is_some() {
+                entry.misses = entry.misses.saturating_add(1);
+            }
+        }
+    }
+
+    fn resolve(&self, path: &[usize]) -> &T {
+        match &self.entries[path[0]].outcome {
+            Outcome::Item(item) => item,
+            Outcome::Table(table) => table.resolve(&path[1..]),
+        }
+    }
+
+    /// Returns the pity counters of this table and its nested tables, e.g. to save them.
+    pub fn pity_state(&self) -> PityState {
+        let mut state = PityState::default();
+        self.collect_misses(&mut state.misses);
+        state
+    }
+
+    fn collect_misses(&self, misses: &mut Vec<u32>) {
+        for entry in &self.entries {
+            misses.push(entry.misses);
+            if let Outcome::Table(table) = &entry.outcome {
+                table.collect_misses(misses);
+            }
+        }
+    }
+
+    /// Restores pity counters saved with [`WeightedTable::pity_state`]. Returns false and
+    /// changes nothing if the state does not fit the table, e.g. because entries were added.
+    pub fn restore_pity_state(&mut self, state: &PityState) -> bool {
+        if state.misses.len() != self.count_entries() {
+            return false;
+        }
+        self.restore_misses(&mut state.misses.iter().copied());
+        true
+    }
+
+    fn count_entries(&self) -> usize {
+        self.entries
+            .iter()
+            .map(|entry| match &entry.outcome {
+                Outcome::Item(_) => 1,
+                Outcome::Table(table) => 1 + table.count_entries(),
+            })
+            .sum()
+    }
+
+    fn restore_misses(&mut self, misses: &mut impl Iterator<Item = u32>) {
+        for entry in &mut self.entries {
+            entry.misses = misses.next().unwrap_or(0);
+            if let Outcome::Table(table) = &mut entry.outcome {
+                table.restore_misses(misses);
+            }
+        }
+    }
+}
+
+impl<T> WeightedTable<T> {
+    /// Rolls an item.
+    ///
+    /// # Panics
+    /// Panics if every entry is excluded or has no weight, e.g. if the table is empty.
+    pub fn roll<R: Rng + ?Sized>(&mut self, rng: &mut R) -> &T {
+        self.roll_with(rng, &())
+            .expect("the table has no entry that can be rolled")
+    }
+
+    /// Rolls up to `n` items from distinct entries, see [`WeightedTable::roll_n_unique_with`].
+    pub fn roll_n_unique<R: Rng + ?Sized>(&mut self, rng: &mut R, n: usize) -> Vec<&T> {
+        self.roll_n_unique_with(rng, n, &())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    /// Returns the chi-squared statistic of the counts against the expected weights.
+    fn chi_squared(counts: &[usize], weights: &[f64]) -> f64 {
+        let n: usize = counts.iter().sum();
+        let total: f64 = weights.iter().sum();
+        counts
+            .iter()
+            .zip(weights)
+            .map(|(&count, &weight)| {
+                let expected = n as f64 * weight / total;
+                (count as f64 - expected).powi(2) / expected
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_distribution() {
+        let weights = [1.0, 2.0, 3.0, 4.0, 0.5];
+        let mut table = WeightedTable::from_pairs(weights.iter().enumerate().map(|(i, &w)| (i, w)));
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut counts = [0; 5];
+        for _ in 0..100_000 {
+            counts[*table.roll(&mut rng)] += 1;
+        }
+        // the 99.9% quantile of chi-squared with 4 degrees of freedom
+        assert!(chi_squared(&counts, &weights) < 18.47, "{counts:?}");
+
+        // the linear path samples the same distribution
+        let mut table: WeightedTable<usize, bool> =
+            WeightedTable::from_pairs(weights.iter().enumerate().map(|(i, &w)| (i, w)));
+        table.exclude_if(0, |&no_zero| no_zero);
+        let mut counts = [0; 5];
+        for _ in 0..100_000 {
+            counts[*table.roll_with(&mut rng, &true).unwrap()] += 1;
+        }
+        assert_eq!(counts[0], 0);
+        assert!(
+            chi_squared(&counts[1..], &weights[1..]) < 16.27,
+            "{counts:?}"
+        );
+    }
+
+    #[test]
+    fn test_pity_guarantee() {
+        let mut table = WeightedTable::from_pairs([("common", 1000.0), ("rare", 0.001)]);
+        table.set_pity(1, Pity::new(0.0).with_cap(10));
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut since_rare = 0;
+        for _ in 0..1000 {
+            since_rare += 1;
+            if *table.roll(&mut rng) == "rare" {
+                assert!(since_rare <= 10);
+                since_rare = 0;
+            }
+        }
+        assert_eq!(since_rare, 0, "the rare entry is forced every 10th roll");
+
+        // growth alone makes the entry more likely
+        let mut table = WeightedTable::from_pairs([("common", 100.0), ("rare", 1.0)]);
+        table.set_pity(1, Pity::new(10.0));
+        for _ in 0..5 {
+            table.roll(&mut rng);
+        }
+        let state = table.pity_state();
+        let mut restored: WeightedTable<_> =
+            WeightedTable::from_pairs([("common", 100.0), ("rare", 1.0)]);
+        assert!(restored.restore_pity_state(&state));
+        assert_eq!(restored.misses(1), table.misses(1));
+        assert!(!WeightedTable::<_>::from_pairs([("common", 1.0)]).restore_pity_state(&state));
+    }
+
+    #[test]
+    fn test_nested_tables() {
+        let gems = WeightedTable::from_pairs([("ruby", 1.0), ("emerald", 3.0)]);
+        let mut drops = WeightedTable::from_pairs([("coin", 1.0)]);
+        drops.push_table(gems, 3.0);
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut counts = [0; 3];
+        for _ in 0..40_000 {
+            match *drops.roll(&mut rng) {
+                "coin" => counts[0] += 1,
+                "ruby" => counts[1] += 1,
+                "emerald" => counts[2] += 1,
+                other => panic!("{other}"),
+            }
+        }
+        // a gem is rolled 3/4 of the time, an emerald 3/4 of those
+        assert!(chi_squared(&counts, &[4.0, 3.0, 9.0]) < 13.82, "{counts:?}");
+
+        // a table whose items are all excluded is skipped
+        let mut gems: WeightedTable<&str, u32> = WeightedTable::from_pairs([("ruby", 1.0)]);
+        gems.exclude_if(0, |&level| level < 5);
+        let mut drops = WeightedTable::from_pairs([("coin", 0.001)]);
+        drops.push_table(gems, 1000.0);
+        assert_eq!(drops.roll_with(&mut rng, &1), Some(&"coin"));
+        assert_eq!(drops.roll_with(&mut rng, &10), Some(&"ruby"));
+
+        let mut table = WeightedTable::from_pairs([("a", 1.0), ("b", 1.0), ("c", 0.0)]);
+        let mut unique = table.roll_n_unique(&mut rng, 3);
+        unique.sort();
+        assert_eq!(unique, vec![&"a", &"b"]);
+    }
+}
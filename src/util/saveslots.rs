@@ -0,0 +1,428 @@
+//! Numbered save slots with metadata headers, for save/load screens.
+//!
+//! [`SaveSlots`] manages a directory of slot files. Every slot file starts with a small header
+//! of [`SlotMetadata`]: the play time, a summary of the progress, when it was saved, whether
+//! cheats were used, and an optional [`Thumbnail`] of the screen. Listing the slots only reads the headers, so a
+//! selection screen stays fast with large saves. See
+//! [`SaveSlotsComponent`](crate::components::saveslots::SaveSlotsComponent) for such a screen.
+//!
+//! ```
+//! use std::time::Duration;
+//! use teng::util::saveslots::{SaveSlots, SlotMetadata};
+//!
+//! let dir = std::env::temp_dir().join(format!("teng-saveslots-doc-{}", std::process::id()));
+//! let slots = SaveSlots::open(&dir).unwrap();
+//! let metadata = SlotMetadata::new(Duration::from_secs(90), "Level 2, 3 lives");
+//! slots.save(slots.next_free_slot().unwrap(), &metadata, &vec![1u8, 2, 3]).unwrap();
+//!
+//! for entry in slots.list().unwrap() {
+//!     match &entry.metadata {
+//!         Some(metadata) => println!("{}: {}", entry.slot, metadata.summary),
+//!         None => println!("{}: corrupted", entry.slot),
+//!     }
+//! }
+//! let data: Vec<u8> = slots.load(0).unwrap();
+//! assert_eq!(data, vec![1, 2, 3]);
+//! # std::fs::remove_dir_all(&dir).unwrap();
+//! ```
+//!
+//! # On disk
+//! A slot file `slot_<n>.sav` consists of the magic bytes `TENGSLOT`, the format version and
+//! the header length as little-endian `u32`s, the bincode-encoded header, and the
+//! bincode-encoded save data. Files are written to a temporary file and renamed, so a slot is
+//! never torn. Files that cannot be read are listed as corrupted instead of failing the listing.
+//! Files of version 1, from before the cheated flag, are still read.
+
+use crate::rendering::capture::FrameSnapshot;
+use bincode::Options;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+const MAGIC: &[u8; 8] = b"TENGSLOT";
+const VERSION: u32 = 2;
+/// Headers are small, anything larger is corrupted.
+const MAX_HEADER_LEN: u32 = 1 << 20;
+
+/// A small picture of the screen at save time, one color per pixel, row by row.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Thumbnail {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<[u8; 3]>,
+}
+
+impl Thumbnail {
+    /// Scales a snapshot down to at most `max_width` x `max_height` pixels, keeping its aspect
+    /// ratio. Every cell of the snapshot is two pixels high, see
+    /// [`FrameSnapshot::to_rgb_image`].
+    pub fn from_snapshot(snapshot: &FrameSnapshot, max_width: usize, max_height: usize) -> Self {
+        let image = snapshot.to_rgb_image();
+        if image.width == 0 || image.height == 0 {
+            return Self {
+                width: 0,
+                height: 0,
+                pixels: vec![],
+            };
+        }
+        let scale = (image.width as f64 / max_width as f64)
+            .max(image.height as f64 / max_height as f64)
+            .max(1.0);
+        let width = ((image.width as f64 / scale) as usize).max(1);
+        let height = ((image.height as f64 / scale) as usize).max(1);
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| image.get(x * image.width / width, y * image.height / height))
+            .collect();
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Returns the color of the pixel at `(x, y)`.
+    ///
+    /// Panics if `(x, y)` is out of bounds.
+    pub fn get(&self, x: usize, y: usize) -> [u8; 3] {
+        assert!(x < self.width && y < self.height);
+        self.pixels[y * self.width + x]
+    }
+}
+
+/// The header of a slot file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SlotMetadata {
+    /// The total play time of the save.
+    pub play_time: Duration,
+    /// A short description of the progress, e.g. "Level 3, 5 coins".
+    pub summary: String,
+    /// When the slot was saved.
+    pub saved_at: SystemTime,
+    pub thumbnail: Option<Thumbnail>,
+    /// Whether cheats were used in the saved session, see
+    /// [`CheatLog`](crate::components::cheats::CheatLog).
+    pub cheated: bool,
+}
+
+/// The header of version 1 slot files, from before [`SlotMetadata::cheated`].
+#[derive(Deserialize)]
+struct SlotMetadataV1 {
+    play_time: Duration,
+    summary: String,
+    saved_at: SystemTime,
+    thumbnail: Option<Thumbnail>,
+}
+
+impl From<SlotMetadataV1> for SlotMetadata {
+    fn from(v1: SlotMetadataV1) -> Self {
+        Self {
+            play_time: v1.play_time,
+            summary: v1.summary,
+            saved_at: v1.saved_at,
+            thumbnail: v1.thumbnail,
+            cheated: false,
+        }
+    }
+}
+
+impl SlotMetadata {
+    /// Creates metadata saved now, without a thumbnail.
+    pub fn new(play_time: Duration, summary: impl Into<String>) -> Self {
+        Self {
+            play_time,
+            summary: summary.into(),
+            saved_at: SystemTime::now(),
+            thumbnail: None,
+            cheated: false,
+        }
+    }
+
+    pub fn with_thumbnail(mut self, thumbnail: Thumbnail) -> Self {
+        self.thumbnail = Some(thumbnail);
+        self
+    }
+
+    /// Marks the save as cheated, usually with
+    /// [`CheatLog::is_cheated`](crate::components::cheats::CheatLog::is_cheated).
+    pub fn with_cheated(mut self, cheated: bool) -> Self {
+        self.cheated = cheated;
+        self
+    }
+}
+
+/// A slot file found by [`SaveSlots::list`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SlotEntry {
+    pub slot: u32,
+    /// The header of the slot, or `None` if the file is corrupted.
+    pub metadata: Option<SlotMetadata>,
+}
+
+impl SlotEntry {
+    pub fn is_corrupted(&self) -> bool {
+        self.metadata.is_none()
+    }
+}
+
+/// A directory of numbered save slots.
+#[derive(Clone, Debug)]
+pub struct SaveSlots {
+    dir: PathBuf,
+}
+
+impl SaveSlots {
+    /// Opens the save directory `dir`, creating it if necessary.
+    pub fn open(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the path of the file of `slot`.
+    pub fn slot_path(&self, slot: u32) -> PathBuf {
+        self.dir.join(format!("slot_{slot}.sav"))
+    }
+
+    /// Returns all slot files sorted by slot, with their headers.
+    pub fn list(&self) -> io::Result<Vec<SlotEntry>> {
+        let mut entries = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let name = entry?.file_name();
+            let slot = name
+                .to_str()
+                .and_then(|name| name.strip_prefix("slot_")?.strip_suffix(".sav"))
+                .and_then(|slot| slot.parse().ok());
+            if let Some(slot) = slot {
+                entries.push(SlotEntry {
+                    slot,
+                    metadata: self.read_metadata(slot).ok(),
+                });
+            }
+        }
+        entries.sort_by_key(|entry| entry.slot);
+        Ok(entries)
+    }
+
+    /// Returns the lowest slot without a file.
+    pub fn next_free_slot(&self) -> io::Result<u32> {
+        let used = self.list()?;
+        Ok((0..)
+            .find(|slot| used.iter().all(|entry| entry.slot != *slot))
+            .unwrap())
+    }
+
+    /// Reads the header of `slot` without reading the save data.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidData`] if the file is corrupted.
+    pub fn read_metadata(&self, slot: u32) -> io::Result<SlotMetadata> {
+        let mut file = io::BufReader::new(fs::File::open(self.slot_path(slot))?);
+        read_header(&mut file)
+    }
+
+    /// Reads the save data of `slot`.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidData`] if the file is corrupted.
+    pub fn load<T: DeserializeOwned>(&self, slot: u32) -> io::Result<T> {
+        let bytes = fs::read(self.slot_path(slot))?;
+        let mut reader = bytes.as_slice();
+        read_header(&mut reader)?;
+        options(bytes.len() as u64)
+            .deserialize(reader)
+            .map_err(invalid_data)
+    }
+
+    /// Saves `data` to `slot` with the header `metadata`, replacing the slot if it exists.
+    pub fn save<T: Serialize>(
+        &self,
+        slot: u32,
+        metadata: &SlotMetadata,
+        data: &T,
+    ) -> io::Result<()> {
+        let header = options(MAX_HEADER_LEN as u64)
+            .serialize(metadata)
+            .map_err(io::Error::other)?;
+        let data = options(u64::MAX)
+            .serialize(data)
+            .map_err(io::Error::other)?;
+        let path = self.slot_path(slot);
+        let tmp_path = path.with_extension("tmp");
+        let mut file = io::BufWriter::new(fs::File::create(&tmp_path)?);
+        file.write_all(MAGIC)?;
+        file.write_all(&VERSION.to_le_bytes())?;
+        file.write_all(&(header.len() as u32).to_le_bytes())?;
+        file.write_all(&header)?;
+        file.write_all(&data)?;
+        file.into_inner()
+            .map_err(io::IntoInnerError::into_error)?
+            .sync_all()?;
+        fs::rename(&tmp_path, &path)
+    }
+
+    /// Deletes the file of `slot`. Deleting a missing slot is not an error.
+    pub fn delete(&self, slot: u32) -> io::Result<()> {
+        match fs::remove_file(self.slot_path(slot)) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+}
+
+fn options(limit: u64) -> impl Options {
+    bincode::DefaultOptions::new().with_limit(limit)
+}
+
+fn invalid_data(e: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+fn read_header(reader: &mut impl Read) -> io::Result<SlotMetadata> {
+    let mut prefix = [0; 16];
+    reader
+        .read_exact(&mut prefix)
+        .map_err(|_| invalid_data("truncated slot file"))?;
+    if &prefix[..8] != MAGIC {
+        return Err(invalid_data("not a slot file"));
+    }
+    let version = u32::from_le_bytes(prefix[8..12].try_into().unwrap());
+    if version != VERSION && version != 1 {
+        return Err(invalid_data(format!("unknown slot version {version}")));
+    }
+    let header_len = u32::from_le_bytes(prefix[12..16].try_into().unwrap());
+    if header_len > MAX_HEADER_LEN {
+        return Err(invalid_data("slot header too large"));
+    }
+    let mut header = vec![0; header_len as usize];
+    reader
+        .read_exact(&mut header)
+        .map_err(|_| invalid_data("truncated slot header"))?;
+    let options = options(header_len as u64);
+    if version == 1 {
+        return options
+            .deserialize::<SlotMetadataV1>(&header)
+            .map(SlotMetadata::from)
+            .map_err(invalid_data);
+    }
+    options.deserialize(&header).map_err(invalid_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rendering::display::Display;
+    use crate::rendering::pixel::Pixel;
+
+    fn temp_slots(name: &str) -> SaveSlots {
+        let dir =
+            std::env::temp_dir().join(format!("teng-saveslots-test-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        SaveSlots::open(dir).unwrap()
+    }
+
+    #[test]
+    fn test_header_round_trip() {
+        let slots = temp_slots("round-trip");
+        let mut pixels = Display::new(4, 2, Pixel::default());
+        pixels.set(0, 0, Pixel::new('█').with_color([255, 0, 0]));
+        let snapshot = FrameSnapshot::new(pixels, [255, 255, 255], [0, 0, 0]);
+        let thumbnail = Thumbnail::from_snapshot(&snapshot, 2, 2);
+        // 4x4 pixels scaled down to 2x2
+        assert_eq!((thumbnail.width, thumbnail.height), (2, 2));
+        assert_eq!(thumbnail.get(0, 0), [255, 0, 0]);
+        assert_eq!(thumbnail.get(1, 1), [0, 0, 0]);
+
+        let metadata =
+            SlotMetadata::new(Duration::from_secs(3661), "Level 3").with_thumbnail(thumbnail);
+        slots
+            .save(2, &metadata, &(42u32, "world".to_string()))
+            .unwrap();
+        slots
+            .save(
+                0,
+                &SlotMetadata::new(Duration::ZERO, "Start"),
+                &(0u32, String::new()),
+            )
+            .unwrap();
+
+        assert_eq!(slots.read_metadata(2).unwrap(), metadata);
+        let data: (u32, String) = slots.load(2).unwrap();
+        assert_eq!(data, (42, "world".to_string()));
+        let listed: Vec<u32> = slots.list().unwrap().iter().map(|e| e.slot).collect();
+        assert_eq!(listed, vec![0, 2]);
+        assert_eq!(slots.next_free_slot().unwrap(), 1);
+
+        slots.delete(2).unwrap();
+        slots.delete(2).unwrap();
+        assert_eq!(slots.list().unwrap().len(), 1);
+        fs::remove_dir_all(slots.dir()).unwrap();
+    }
+
+    #[test]
+    fn test_corrupt_slots_are_listed() {
+        let slots = temp_slots("corrupt");
+        let metadata = SlotMetadata::new(Duration::from_secs(1), "ok");
+        slots.save(0, &metadata, &vec![7u8; 100]).unwrap();
+        let valid = fs::read(slots.slot_path(0)).unwrap();
+
+        // not a slot file, a truncated header, and a huge header length
+        fs::write(slots.slot_path(1), b"hello").unwrap();
+        fs::write(slots.slot_path(2), &valid[..20]).unwrap();
+        let mut huge = valid.clone();
+        huge[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+        fs::write(slots.slot_path(3), huge).unwrap();
+        // a valid header with truncated data
+        fs::write(slots.slot_path(4), &valid[..valid.len() - 10]).unwrap();
+        // not a slot
+        fs::write(slots.dir().join("notes.txt"), b"hi").unwrap();
+
+        let entries = slots.list().unwrap();
+        let corrupted: Vec<(u32, bool)> =
+            entries.iter().map(|e| (e.slot, e.is_corrupted())).collect();
+        assert_eq!(
+            corrupted,
+            vec![(0, false), (1, true), (2, true), (3, true), (4, false)]
+        );
+        // only the header is read when listing, loading finds the truncated data
+        let error = slots.load::<Vec<u8>>(4).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(slots.load::<Vec<u8>>(0).unwrap(), vec![7; 100]);
+        fs::remove_dir_all(slots.dir()).unwrap();
+    }
+
+    #[test]
+    fn test_cheated_flag() {
+        let slots = temp_slots("cheated");
+        let metadata = SlotMetadata::new(Duration::from_secs(10), "Phase 2").with_cheated(true);
+        slots.save(0, &metadata, &1u8).unwrap();
+        assert!(slots.read_metadata(0).unwrap().cheated);
+
+        // version 1 files have no flag, and are read as not cheated
+        let header = options(MAX_HEADER_LEN as u64)
+            .serialize(&(
+                Duration::from_secs(5),
+                "old".to_string(),
+                SystemTime::UNIX_EPOCH,
+                None::<Thumbnail>,
+            ))
+            .unwrap();
+        let mut file = MAGIC.to_vec();
+        file.extend(1u32.to_le_bytes());
+        file.extend((header.len() as u32).to_le_bytes());
+        file.extend(header);
+        file.extend(options(u64::MAX).serialize(&7u8).unwrap());
+        fs::write(slots.slot_path(1), file).unwrap();
+        let metadata = slots.read_metadata(1).unwrap();
+        assert_eq!(metadata.summary, "old");
+        assert!(!metadata.cheated);
+        assert_eq!(slots.load::<u8>(1).unwrap(), 7);
+        fs::remove_dir_all(slots.dir()).unwrap();
+    }
+}
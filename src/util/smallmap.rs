@@ -0,0 +1,120 @@
+//! A small map with a fixed capacity that does not allocate, see [`SmallMap`].
+
+/// A map with room for at most `N` entries, stored inline without allocating.
+///
+/// Lookups compare every key, which is faster than hashing for the handful of entries this is
+/// meant for, e.g. the keys pressed in a single frame in
+/// [`PressedKeys`](crate::components::keyboard::PressedKeys).
+///
+/// # Example
+/// ```
+/// use teng::util::smallmap::SmallMap;
+///
+/// let mut map = SmallMap::<char, u8, 2>::new();
+/// assert_eq!(map.insert('a', 1), Ok(None));
+/// assert_eq!(map.insert('a', 2), Ok(Some(1)));
+/// assert_eq!(map.insert('b', 1), Ok(None));
+/// // the map is full
+/// assert_eq!(map.insert('c', 1), Err(('c', 1)));
+/// assert_eq!(map.get(&'a'), Some(&2));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmallMap<K, V, const N: usize> {
+    entries: [Option<(K, V)>; N],
+    len: usize,
+}
+
+impl<K: PartialEq, V, const N: usize> Default for SmallMap<K, V, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: PartialEq, V, const N: usize> SmallMap<K, V, N> {
+    pub fn new() -> Self {
+        Self {
+            entries: std::array::from_fn(|_| None),
+            len: 0,
+        }
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if there are no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns true if no new key can be inserted.
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Inserts a value for `key`, and returns the previous value of the key, if any.
+    ///
+    /// If the key is new and the map is full, the entry is returned as an error instead.
+    pub fn insert(&mut self, key: K, value: V) -> Result<Option<V>, (K, V)> {
+        if let Some(old) = self.get_mut(&key) {
+            return Ok(Some(std::mem::replace(old, value)));
+        }
+        if self.is_full() {
+            return Err((key, value));
+        }
+        // entries are kept contiguous
+        self.entries[self.len] = Some((key, value));
+        self.len += 1;
+        Ok(None)
+    }
+
+    /// Returns a reference to the value of `key`, if any.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.iter().find(|(k, _)| *k == key).map(|(_, v)| v)
+    }
+
+    /// Returns a mutable reference to the value of `key`, if any.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.entries[..self.len]
+            .iter_mut()
+            .flatten()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v)
+    }
+
+    /// Returns true if the map contains `key`.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Removes and returns the value of `key`, if any.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let idx = self.iter().position(|(k, _)| k == key)?;
+        self.len -= 1;
+        // move the last entry into the gap to keep the entries contiguous
+        self.entries.swap(idx, self.len);
+        self.entries[self.len].take().map(|(_, v)| v)
+    }
+
+    /// Removes all entries.
+    pub fn clear(&mut self) {
+        for entry in &mut self.entries[..self.len] {
+            *entry = None;
+        }
+        self.len = 0;
+    }
+
+    /// Returns an iterator over the entries, in insertion order unless entries were removed.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.entries[..self.len]
+            .iter()
+            .flatten()
+            .map(|(k, v)| (k, v))
+    }
+
+    /// Returns an iterator over the keys, see [`SmallMap::iter`].
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.iter().map(|(k, _)| k)
+    }
+}
@@ -0,0 +1,354 @@
+//! Smooth rendering of states that arrive at a lower rate than frames, see [`SnapshotBuffer`].
+//!
+//! Positions of remote players, replayed ghosts or recorded demos arrive as timestamped
+//! snapshots, often with jitter. Rendering them as they arrive looks choppy. Instead, a
+//! [`SnapshotBuffer`] renders them slightly in the past, interpolating between the two
+//! snapshots around the sampled time:
+//! ```
+//! use teng::util::snapshot_interp::SnapshotBuffer;
+//!
+//! // render half a second in the past
+//! let mut ghost = SnapshotBuffer::new().with_delay(0.5);
+//! ghost.push(0.0, (0.0, 0.0));
+//! ghost.push(1.0, (10.0, 0.0));
+//! ghost.push(2.0, (20.0, 5.0));
+//!
+//! // the frame at 2 s shows the state at 1.5 s
+//! assert_eq!(ghost.sample(2.0), Some((15.0, 2.5)));
+//! ```
+//! If no newer snapshot arrived in time, the buffer extrapolates from the last two snapshots,
+//! but at most for [`SnapshotBuffer::with_max_extrapolation`], after which the state stops.
+//!
+//! States implement [`Lerp`]. Implement it for your own state types, e.g. by interpolating
+//! every field.
+
+use crate::components::debuginfo::DebugInfo;
+use crate::rendering::color::Color;
+use crate::util::gridmove::Direction;
+use std::collections::VecDeque;
+
+/// A state that can be interpolated, see [`Lerp::lerp`].
+///
+/// # Example
+/// ```
+/// use teng::util::snapshot_interp::Lerp;
+///
+/// #[derive(Clone)]
+/// struct Ship {
+///     pos: (f64, f64),
+///     angle: f64,
+///     alive: bool,
+/// }
+///
+/// impl Lerp for Ship {
+///     fn lerp(&self, other: &Self, t: f64) -> Self {
+///         Self {
+///             pos: self.pos.lerp(&other.pos, t),
+///             angle: self.angle.lerp(&other.angle, t),
+///             // discrete fields switch halfway
+///             alive: if t < 0.5 { self.alive } else { other.alive },
+///         }
+///     }
+/// }
+/// ```
+pub trait Lerp: Clone {
+    /// Returns the state a fraction `t` of the way from `self` to `other`.
+    ///
+    /// `t` is 0 for `self` and 1 for `other`, and greater than 1 when extrapolating.
+    fn lerp(&self, other: &Self, t: f64) -> Self;
+}
+
+impl Lerp for f64 {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for f32 {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        self + (other - self) * t as f32
+    }
+}
+
+impl<A: Lerp, B: Lerp> Lerp for (A, B) {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        (self.0.lerp(&other.0, t), self.1.lerp(&other.1, t))
+    }
+}
+
+/// Switches to the other direction halfway, e.g. for a `((f64, f64), Direction)` state.
+impl Lerp for Direction {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        if t < 0.5 { *self } else { *other }
+    }
+}
+
+/// Blends two RGB colors, clamped to the colors when extrapolating. Other colors switch halfway.
+impl Lerp for Color {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        match (self, other) {
+            (Color::Rgb(a), Color::Rgb(b)) => {
+                let t = t.clamp(0.0, 1.0);
+                Color::Rgb(std::array::from_fn(|i| {
+                    (a[i] as f64).lerp(&(b[i] as f64), t).round() as u8
+                }))
+            }
+            _ if t < 0.5 => *self,
+            _ => *other,
+        }
+    }
+}
+
+/// Health metrics of a [`SnapshotBuffer`], see [`SnapshotBuffer::health`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BufferHealth {
+    /// The number of buffered snapshots.
+    pub depth: usize,
+    /// How far the sampled time lags behind the newest snapshot, in seconds. Negative while
+    /// extrapolating.
+    pub lead: f64,
+    /// The fraction of the recent samples that were extrapolated, from 0 to 1.
+    ///
+    /// A high ratio means that snapshots arrive later than the delay allows for.
+    pub extrapolation_ratio: f64,
+}
+
+/// Timestamped snapshots of a state, sampled with interpolation and bounded extrapolation.
+///
+/// Times are in seconds, on any clock that is shared by [`SnapshotBuffer::push`] and
+/// [`SnapshotBuffer::sample`], e.g. the sender's game time.
+#[derive(Clone, Debug)]
+pub struct SnapshotBuffer<T> {
+    /// Sorted by time.
+    snapshots: VecDeque<(f64, T)>,
+    delay: f64,
+    max_extrapolation: f64,
+    window: f64,
+    /// Whether each of the recent samples was extrapolated, newest last.
+    recent_samples: VecDeque<bool>,
+    last_sample_time: Option<f64>,
+}
+
+impl<T: Lerp> Default for SnapshotBuffer<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Lerp> SnapshotBuffer<T> {
+    /// The default interpolation delay, in seconds.
+    pub const DEFAULT_DELAY: f64 = 0.1;
+    /// The default maximum extrapolation time, in seconds.
+    pub const DEFAULT_MAX_EXTRAPOLATION: f64 = 0.25;
+    /// The default age, relative to the newest snapshot, after which snapshots are dropped.
+    pub const DEFAULT_WINDOW: f64 = 1.0;
+    /// The number of recent samples the extrapolation ratio is computed over.
+    pub const METRICS_SAMPLES: usize = 120;
+
+    pub fn new() -> Self {
+        Self {
+            snapshots: VecDeque::new(),
+            delay: Self::DEFAULT_DELAY,
+            max_extrapolation: Self::DEFAULT_MAX_EXTRAPOLATION,
+            window: Self::DEFAULT_WINDOW,
+            recent_samples: VecDeque::with_capacity(Self::METRICS_SAMPLES),
+            last_sample_time: None,
+        }
+    }
+
+    /// Sets how far in the past samples are taken. Larger delays absorb more jitter.
+    pub fn with_delay(mut self, delay: f64) -> Self {
+        self.delay = delay.max(0.0);
+        self
+    }
+
+    /// Sets how long samples past the newest snapshot are extrapolated before the state stops.
+    pub fn with_max_extrapolation(mut self, max_extrapolation: f64) -> Self {
+        self.max_extrapolation = max_extrapolation.max(0.0);
+        self
+    }
+
+    /// Sets the age, relative to the newest snapshot, after which snapshots are dropped.
+    ///
+    /// The window should be larger than the delay, otherwise samples clamp to the oldest
+    /// snapshot.
+    pub fn with_window(mut self, window: f64) -> Self {
+        self.window = window.max(0.0);
+        self
+    }
+
+    pub fn delay(&self) -> f64 {
+        self.delay
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    /// Removes all snapshots and metrics.
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.recent_samples.clear();
+        self.last_sample_time = None;
+    }
+
+    /// Returns the time of the newest snapshot.
+    pub fn newest_time(&self) -> Option<f64> {
+        self.snapshots.back().map(|(t, _)| *t)
+    }
+
+    /// Adds the snapshot of `state` at time `t`, and drops the snapshots that left the window.
+    ///
+    /// Snapshots may arrive out of order. A snapshot with the time of an existing one replaces
+    /// it, and one that is already outside of the window is ignored.
+    pub fn push(&mut self, t: f64, state: T) {
+        let idx = self.snapshots.partition_point(|(st, _)| *st < t);
+        match self.snapshots.get_mut(idx) {
+            Some((st, existing)) if *st == t => *existing = state,
+            _ => self.snapshots.insert(idx, (t, state)),
+        }
+        let newest = self.newest_time().unwrap_or(t);
+        while self
+            .snapshots
+            .front()
+            .is_some_and(|(st, _)| *st < newest - self.window)
+        {
+            self.snapshots.pop_front();
+        }
+    }
+
+    /// Returns the state at `render_time` minus the delay, or `None` if there are no snapshots.
+    ///
+    /// Between snapshots, the state is interpolated. Before the oldest snapshot, it is the
+    /// oldest state. After the newest snapshot, it is extrapolated from the last two snapshots,
+    /// for at most the maximum extrapolation time.
+    pub fn sample(&mut self, render_time: f64) -> Option<T> {
+        let t = render_time - self.delay;
+        self.last_sample_time = Some(t);
+        let (newest_t, newest) = self.snapshots.back()?;
+        let extrapolated = t > *newest_t;
+        if self.recent_samples.len() == Self::METRICS_SAMPLES {
+            self.recent_samples.pop_front();
+        }
+        self.recent_samples.push_back(extrapolated);
+
+        if t == *newest_t || (extrapolated && self.snapshots.len() == 1) {
+            return Some(newest.clone());
+        }
+        if extrapolated {
+            let (prev_t, prev) = &self.snapshots[self.snapshots.len() - 2];
+            let ahead = (t - newest_t).min(self.max_extrapolation);
+            let f = 1.0 + ahead / (newest_t - prev_t);
+            return Some(prev.lerp(newest, f));
+        }
+        // the first snapshot after t
+        let idx = self.snapshots.partition_point(|(st, _)| *st <= t);
+        if idx == 0 {
+            return Some(self.snapshots[0].1.clone());
+        }
+        let (a_t, a) = &self.snapshots[idx - 1];
+        let (b_t, b) = &self.snapshots[idx];
+        Some(a.lerp(b, (t - a_t) / (b_t - a_t)))
+    }
+
+    /// Returns the health metrics of the buffer.
+    pub fn health(&self) -> BufferHealth {
+        let extrapolated = self.recent_samples.iter().filter(|e| **e).count();
+        BufferHealth {
+            depth: self.snapshots.len(),
+            lead: match (self.newest_time(), self.last_sample_time) {
+                (Some(newest), Some(t)) => newest - t,
+                _ => 0.0,
+            },
+            extrapolation_ratio: extrapolated as f64 / self.recent_samples.len().max(1) as f64,
+        }
+    }
+
+    /// Sets the health metrics as debug values under `key`, e.g. `"ghost"`.
+    pub fn report_debug_info(&self, debug_info: &mut DebugInfo, key: &str) {
+        let health = self.health();
+        debug_info.set(format!("{key}.depth"), health.depth);
+        debug_info.set(format!("{key}.lead"), health.lead);
+        debug_info.set(
+            format!("{key}.extrapolation_ratio"),
+            health.extrapolation_ratio,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer() -> SnapshotBuffer<f64> {
+        let mut buffer = SnapshotBuffer::new().with_delay(0.0).with_window(10.0);
+        for (t, x) in [(0.0, 0.0), (1.0, 10.0), (2.0, 30.0), (3.0, 60.0)] {
+            buffer.push(t, x);
+        }
+        buffer
+    }
+
+    #[test]
+    fn test_exact_at_snapshot_times() {
+        let mut buffer = buffer();
+        for (t, x) in [(0.0, 0.0), (1.0, 10.0), (2.0, 30.0), (3.0, 60.0)] {
+            assert_eq!(buffer.sample(t), Some(x));
+        }
+        assert_eq!(buffer.sample(1.5), Some(20.0));
+        // before the oldest snapshot
+        assert_eq!(buffer.sample(-1.0), Some(0.0));
+        assert_eq!(SnapshotBuffer::<f64>::new().sample(0.0), None);
+    }
+
+    #[test]
+    fn test_delay() {
+        let mut buffer = buffer().with_delay(0.5);
+        assert_eq!(buffer.sample(1.5), Some(10.0));
+        assert_eq!(buffer.sample(2.0), Some(20.0));
+        assert_eq!(buffer.health().lead, 1.5);
+
+        // out of order snapshots are sorted in
+        let mut buffer = SnapshotBuffer::new().with_delay(0.25);
+        buffer.push(1.0, ((2.0, 0.0), Direction::Right));
+        buffer.push(0.0, ((0.0, 0.0), Direction::Up));
+        assert_eq!(buffer.sample(0.5), Some(((0.5, 0.0), Direction::Up)));
+        assert_eq!(buffer.sample(1.0), Some(((1.5, 0.0), Direction::Right)));
+    }
+
+    #[test]
+    fn test_extrapolation_is_clamped() {
+        let mut buffer = buffer().with_max_extrapolation(0.5);
+        // continues with the velocity of the last two snapshots
+        assert_eq!(buffer.sample(3.25), Some(67.5));
+        assert_eq!(buffer.sample(3.5), Some(75.0));
+        // and then stops
+        assert_eq!(buffer.sample(10.0), Some(75.0));
+
+        let health = buffer.health();
+        assert_eq!(health.depth, 4);
+        assert_eq!(health.lead, -7.0);
+        assert_eq!(health.extrapolation_ratio, 1.0);
+        buffer.sample(1.0);
+        assert_eq!(buffer.health().extrapolation_ratio, 0.75);
+    }
+
+    #[test]
+    fn test_window_eviction() {
+        let mut buffer = SnapshotBuffer::new().with_delay(0.0).with_window(1.5);
+        for t in 0..5 {
+            buffer.push(t as f64, t as f64);
+        }
+        // 4.0 is the newest, so everything before 2.5 is dropped
+        assert_eq!(buffer.len(), 2);
+        assert_eq!(buffer.sample(0.0), Some(3.0));
+        // snapshots outside of the window are ignored
+        buffer.push(1.0, 1.0);
+        assert_eq!(buffer.len(), 2);
+        buffer.push(3.0, -3.0);
+        assert_eq!(buffer.sample(3.0), Some(-3.0));
+    }
+}
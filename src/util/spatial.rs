@@ -0,0 +1,300 @@
+//! Broad-phase collision detection with a [`SpatialHashGrid`].
+//!
+//! The grid splits the world into square cells and remembers which items overlap which cells, so
+//! finding the candidates for a collision only looks at the items close by, instead of every item:
+//! ```
+//! use teng::util::spatial::{Aabb, SpatialHashGrid};
+//!
+//! let mut grid = SpatialHashGrid::new(8);
+//! grid.insert_with_aabb(0, Aabb::new(0, 0, 3, 3));
+//! grid.insert_with_aabb(1, Aabb::new(2, 2, 5, 5));
+//! grid.insert_with_aabb(2, Aabb::new(100, 100, 101, 101));
+//!
+//! let mut near = grid.query_aabb(Aabb::new(3, 3, 4, 4)).copied().collect::<Vec<_>>();
+//! near.sort();
+//! assert_eq!(near, [0, 1]);
+//! assert_eq!(grid.query_point(100, 101).collect::<Vec<_>>(), [&2]);
+//! ```
+//!
+//! To rebuild the grid every frame, [`clear`](SpatialHashGrid::clear) it and insert every item
+//! again. Clearing keeps the memory of every cell, so this does not allocate once the grid has
+//! warmed up.
+
+use crate::rendering::color::Color;
+use crate::rendering::render::HalfBlockDisplayRender;
+use std::collections::HashMap;
+
+/// Axis-aligned bounding box in world coordinates. Both corners are inclusive.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub struct Aabb {
+    pub min_x: i64,
+    pub min_y: i64,
+    pub max_x: i64,
+    pub max_y: i64,
+}
+
+impl Aabb {
+    pub fn new(min_x: i64, min_y: i64, max_x: i64, max_y: i64) -> Self {
+        Self {
+            min_x,
+            min_y,
+            max_x,
+            max_y,
+        }
+    }
+
+    /// Returns the box that only contains `(x, y)`.
+    pub fn point(x: i64, y: i64) -> Self {
+        Self::new(x, y, x, y)
+    }
+
+    /// Returns true if the boxes share at least one coordinate.
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        self.min_x <= other.max_x
+            && other.min_x <= self.max_x
+            && self.min_y <= other.max_y
+            && other.min_y <= self.max_y
+    }
+
+    /// Returns true if `(x, y)` is inside the box.
+    pub fn contains(&self, x: i64, y: i64) -> bool {
+        self.overlaps(&Aabb::point(x, y))
+    }
+}
+
+/// An inclusive range of cells.
+#[derive(Clone, Copy)]
+struct CellRange {
+    min: (i64, i64),
+    max: (i64, i64),
+}
+
+impl CellRange {
+    fn iter(self) -> impl Iterator<Item = (i64, i64)> {
+        (self.min.0..=self.max.0).flat_map(move |x| (self.min.1..=self.max.1).map(move |y| (x, y)))
+    }
+}
+
+/// A grid of square cells that finds the items overlapping a box or point.
+pub struct SpatialHashGrid<T> {
+    cells: HashMap<(i64, i64), Vec<(Aabb, T)>>,
+    cell_size: i64,
+}
+
+impl<T: Copy> SpatialHashGrid<T> {
+    /// Creates an empty grid with cells of `cell_size` by `cell_size` world units.
+    ///
+    /// A cell size of about the size of a typical item works well: smaller cells store large
+    /// items in many cells, larger cells return more candidates per query.
+    ///
+    /// # Panics
+    ///
+    /// If `cell_size` is not positive.
+    pub fn new(cell_size: i64) -> Self {
+        assert!(cell_size > 0, "cell size must be positive, got {cell_size}");
+        Self {
+            cells: HashMap::new(),
+            cell_size,
+        }
+    }
+
+    pub fn cell_size(&self) -> i64 {
+        self.cell_size
+    }
+
+    /// Inserts `value` into every cell that `aabb` overlaps.
+    pub fn insert_with_aabb(&mut self, value: T, aabb: Aabb) {
+        for cell in self.cell_range(aabb).iter() {
+            self.cells.entry(cell).or_default().push((aabb, value));
+        }
+    }
+
+    /// Returns every item whose box overlaps `aabb`, each exactly once, in no particular order.
+    pub fn query_aabb(&self, aabb: Aabb) -> impl Iterator<Item = &T> {
+        let query_cells = self.cell_range(aabb);
+        query_cells.iter().flat_map(move |cell| {
+            // an item in several cells is only returned from the first cell it shares with the
+            // query, i.e. the cell in the query's first row (column), or where the item starts
+            let first_x = cell.0 == query_cells.min.0;
+            let first_y = cell.1 == query_cells.min.1;
+            let (cell_min_x, cell_min_y) = (cell.0 * self.cell_size, cell.1 * self.cell_size);
+            self.cell(cell).filter_map(move |(item_aabb, value)| {
+                let first_shared = (first_x || item_aabb.min_x >= cell_min_x)
+                    && (first_y || item_aabb.min_y >= cell_min_y);
+                (first_shared && item_aabb.overlaps(&aabb)).then_some(value)
+            })
+        })
+    }
+
+    /// Returns every item whose box contains `(x, y)`.
+    pub fn query_point(&self, x: i64, y: i64) -> impl Iterator<Item = &T> {
+        self.cell(self.cell_of(x, y))
+            .filter(move |(aabb, _)| aabb.contains(x, y))
+            .map(|(_, value)| value)
+    }
+
+    /// Removes every item, but keeps the memory of every cell for the next insertions.
+    pub fn clear(&mut self) {
+        for items in self.cells.values_mut() {
+            items.clear();
+        }
+    }
+
+    /// Frees the memory of cells without items, e.g. after the items moved to another part of a
+    /// large world.
+    pub fn shrink(&mut self) {
+        self.cells.retain(|_, items| !items.is_empty());
+    }
+
+    /// Only keeps the items for which `f` returns true, e.g. to remove the items that moved
+    /// before inserting them again at their new position.
+    ///
+    /// `f` is called once for every cell an item is in, and must return the same answer each
+    /// time.
+    pub fn retain(&mut self, mut f: impl FnMut(&T) -> bool) {
+        for items in self.cells.values_mut() {
+            items.retain(|(_, value)| f(value));
+        }
+    }
+
+    /// Returns true if the grid contains no items.
+    pub fn is_empty(&self) -> bool {
+        self.cells.values().all(|items| items.is_empty())
+    }
+
+    /// Draws the outline of every cell that contains an item, using world coordinates as pixel
+    /// coordinates. Cells outside of `hbd` are clipped.
+    pub fn debug_render(&self, hbd: &mut HalfBlockDisplayRender, color: Color) {
+        let width = hbd.width() as i64;
+        let height = hbd.height() as i64;
+        let mut set = |x: i64, y: i64| {
+            if (0..width).contains(&x) && (0..height).contains(&y) {
+                hbd.set_color(x as usize, y as usize, color);
+            }
+        };
+        for (&(cell_x, cell_y), items) in &self.cells {
+            if items.is_empty() {
+                continue;
+            }
+            let min_x = cell_x * self.cell_size;
+            let min_y = cell_y * self.cell_size;
+            let max_x = min_x + self.cell_size - 1;
+            let max_y = min_y + self.cell_size - 1;
+            for x in min_x..=max_x {
+                set(x, min_y);
+                set(x, max_y);
+            }
+            for y in min_y..=max_y {
+                set(min_x, y);
+                set(max_x, y);
+            }
+        }
+    }
+
+    fn cell(&self, cell: (i64, i64)) -> impl Iterator<Item = &(Aabb, T)> {
+        self.cells.get(&cell).into_iter().flatten()
+    }
+
+    fn cell_of(&self, x: i64, y: i64) -> (i64, i64) {
+        // floor division, so that cells have the same size across zero
+        (x.div_euclid(self.cell_size), y.div_euclid(self.cell_size))
+    }
+
+    fn cell_range(&self, aabb: Aabb) -> CellRange {
+        CellRange {
+            min: self.cell_of(aabb.min_x, aabb.min_y),
+            max: self.cell_of(aabb.max_x, aabb.max_y),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn random_aabb(rng: &mut StdRng) -> Aabb {
+        let min_x = rng.gen_range(-50..50);
+        let min_y = rng.gen_range(-50..50);
+        Aabb::new(
+            min_x,
+            min_y,
+            min_x + rng.gen_range(0..20),
+            min_y + rng.gen_range(0..20),
+        )
+    }
+
+    #[test]
+    fn test_queries_match_brute_force() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut grid = SpatialHashGrid::new(1);
+        for round in 0..200 {
+            // vary the cell size, and reuse the grid to also cover `clear`
+            if round % 20 == 0 {
+                grid = SpatialHashGrid::new(rng.gen_range(1..16));
+            }
+            grid.clear();
+            let aabbs = (0..rng.gen_range(0..60))
+                .map(|_| random_aabb(&mut rng))
+                .collect::<Vec<_>>();
+            for (idx, &aabb) in aabbs.iter().enumerate() {
+                grid.insert_with_aabb(idx, aabb);
+            }
+
+            for (idx1, aabb1) in aabbs.iter().enumerate() {
+                let mut found = grid.query_aabb(*aabb1).copied().collect::<Vec<_>>();
+                found.sort();
+                let expected = (0..aabbs.len())
+                    .filter(|&idx2| aabb1.overlaps(&aabbs[idx2]))
+                    .collect::<Vec<_>>();
+                assert_eq!(found, expected, "overlaps of {idx1} in round {round}");
+            }
+
+            let (x, y) = (rng.gen_range(-60..80), rng.gen_range(-60..80));
+            let mut found = grid.query_point(x, y).copied().collect::<Vec<_>>();
+            found.sort();
+            let expected = (0..aabbs.len())
+                .filter(|&idx| aabbs[idx].contains(x, y))
+                .collect::<Vec<_>>();
+            assert_eq!(found, expected, "point ({x}, {y}) in round {round}");
+        }
+    }
+
+    #[test]
+    fn test_clear_retain_and_shrink() {
+        let mut grid = SpatialHashGrid::new(4);
+        grid.insert_with_aabb('a', Aabb::new(-5, -5, 5, 5));
+        grid.insert_with_aabb('b', Aabb::point(1, 1));
+        let cells = grid.cells.len();
+
+        grid.retain(|&value| value != 'a');
+        assert_eq!(grid.query_point(1, 1).collect::<Vec<_>>(), [&'b']);
+        assert_eq!(grid.query_point(-5, -5).count(), 0);
+
+        grid.clear();
+        assert!(grid.is_empty());
+        // the cells are kept for the next frame
+        assert_eq!(grid.cells.len(), cells);
+        grid.shrink();
+        assert!(grid.cells.is_empty());
+    }
+
+    #[test]
+    fn test_debug_render_outlines_occupied_cells() {
+        let mut grid = SpatialHashGrid::new(3);
+        grid.insert_with_aabb((), Aabb::point(4, 1));
+        let mut hbd = HalfBlockDisplayRender::new(6, 6);
+        grid.debug_render(&mut hbd, Color::Rgb([255, 0, 0]));
+
+        let mut outline = String::new();
+        for y in 0..6 {
+            for x in 0..6 {
+                let set = hbd.get_color(x, y) == Some(Color::Rgb([255, 0, 0]));
+                outline.push(if set { '#' } else { '.' });
+            }
+            outline.push('\n');
+        }
+        assert_eq!(outline, "...###\n...#.#\n...###\n......\n......\n......\n");
+    }
+}
@@ -0,0 +1,389 @@
+//! Background jobs that can be cancelled and report their progress.
+//!
+//! A job started with [`TaskHandle::spawn`] runs on its own thread and receives a
+//! [`TaskContext`]. It reports its progress with [`TaskContext::report`], and polls
+//! [`TaskContext::checkpoint`] to stop early once the task is cancelled. The component that
+//! started the job polls the handle every frame, e.g. to draw a progress bar, and takes the result
+//! once it is done. A cancelled task never delivers its result, even if the job finished anyway.
+//!
+//! For jobs whose newer submissions replace older ones, e.g. regenerating a minimap after every
+//! edit, [`LatestOnly`] cancels the superseded job.
+//!
+//! # Example
+//! ```
+//! use teng::util::task::TaskHandle;
+//!
+//! let mut task = TaskHandle::spawn(|ctx| {
+//!     let mut sum = 0u64;
+//!     for row in 0..100 {
+//!         ctx.checkpoint()?;
+//!         sum += row;
+//!         ctx.report(row + 1, 100);
+//!     }
+//!     Ok(sum)
+//! });
+//!
+//! // every frame, e.g. in a component's update
+//! let result = loop {
+//!     let _progress = task.progress(); // from 0.0 to 1.0
+//!     if let Some(result) = task.try_take() {
+//!         break result;
+//!     }
+//!     # std::thread::yield_now();
+//! };
+//! assert_eq!(result, 4950);
+//! ```
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, TryRecvError, channel};
+use std::sync::{Arc, Mutex};
+
+/// The error of a job that stopped because its task was cancelled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the task was cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// A flag that is set when a task is cancelled. Clones share the flag.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Returns `Err(Cancelled)` if the task was cancelled, so that jobs can stop with `?`.
+    pub fn checkpoint(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The progress of a task, as a fraction from 0.0 to 1.0. Clones share the progress.
+///
+/// Progress never goes backwards: reports lower than the current progress are ignored, so that a
+/// progress bar does not jump back when, e.g., the total grows.
+#[derive(Clone, Debug, Default)]
+pub struct ProgressReporter(Arc<Mutex<f64>>);
+
+impl ProgressReporter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reports that `done` of `total` steps are done. A `total` of 0 counts as done.
+    pub fn report(&self, done: u64, total: u64) {
+        let fraction = if total == 0 {
+            1.0
+        } else {
+            done as f64 / total as f64
+        };
+        self.report_fraction(fraction);
+    }
+
+    /// Reports the progress as a fraction, which is clamped to 0.0 to 1.0.
+    pub fn report_fraction(&self, fraction: f64) {
+        if fraction.is_nan() {
+            return;
+        }
+        let mut progress = self.0.lock().unwrap();
+        *progress = progress.max(fraction.clamp(0.0, 1.0));
+    }
+
+    pub fn fraction(&self) -> f64 {
+        *self.0.lock().unwrap()
+    }
+}
+
+/// What a job receives to check for cancellation and report its progress.
+#[derive(Clone, Debug, Default)]
+pub struct TaskContext {
+    pub token: CancellationToken,
+    pub progress: ProgressReporter,
+}
+
+impl TaskContext {
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// See [`CancellationToken::checkpoint`].
+    pub fn checkpoint(&self) -> Result<(), Cancelled> {
+        self.token.checkpoint()
+    }
+
+    /// See [`ProgressReporter::report`].
+    pub fn report(&self, done: u64, total: u64) {
+        self.progress.report(done, total);
+    }
+}
+
+/// A job running on a background thread.
+///
+/// Dropping the handle cancels the task.
+#[derive(Debug)]
+pub struct TaskHandle<T> {
+    context: TaskContext,
+    result: Receiver<Result<T, Cancelled>>,
+    finished: Arc<AtomicBool>,
+    taken: bool,
+}
+
+impl<T: Send + 'static> TaskHandle<T> {
+    /// Runs `job` on a new thread.
+    pub fn spawn(job: impl FnOnce(&TaskContext) -> Result<T, Cancelled> + Send + 'static) -> Self {
+        let context = TaskContext::default();
+        let (sender, result) = channel();
+        let finished = Arc::new(AtomicBool::new(false));
+        let job_context = context.clone();
+        let job_finished = finished.clone();
+        std::thread::spawn(move || {
+            let result = job(&job_context);
+            // the handle may be gone already
+            let _ = sender.send(result);
+            job_finished.store(true, Ordering::Release);
+        });
+        Self {
+            context,
+            result,
+            finished,
+            taken: false,
+        }
+    }
+}
+
+impl<T> TaskHandle<T> {
+    /// Cancels the task. Its result is dropped, even if the job already finished.
+    pub fn cancel(&self) {
+        self.context.token.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.context.is_cancelled()
+    }
+
+    /// Returns a clone of the task's token, e.g. to cancel it from elsewhere.
+    pub fn token(&self) -> CancellationToken {
+        self.context.token.clone()
+    }
+
+    /// Returns the progress the job reported, from 0.0 to 1.0.
+    pub fn progress(&self) -> f64 {
+        self.context.progress.fraction()
+    }
+
+    /// Returns true once the job has returned, including jobs that stopped after cancellation.
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Acquire)
+    }
+
+    /// Returns the result of the job once it is done. Returns `None` while the job is running,
+    /// after the result was taken, and if the task was cancelled or the job panicked.
+    pub fn try_take(&mut self) -> Option<T> {
+        if self.taken || self.is_cancelled() {
+            return None;
+        }
+        match self.result.try_recv() {
+            Ok(Ok(result)) => {
+                self.taken = true;
+                Some(result)
+            }
+            Ok(Err(Cancelled)) | Err(TryRecvError::Disconnected) => {
+                self.taken = true;
+                None
+            }
+            Err(TryRecvError::Empty) => None,
+        }
+    }
+}
+
+impl<T> Drop for TaskHandle<T> {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// Runs at most one job at a time that delivers its result: submitting a job cancels the
+/// previous one, so a stale result never replaces a newer one.
+#[derive(Debug)]
+pub struct LatestOnly<T> {
+    current: Option<TaskHandle<T>>,
+}
+
+impl<T> Default for LatestOnly<T> {
+    fn default() -> Self {
+        Self { current: None }
+    }
+}
+
+impl<T: Send + 'static> LatestOnly<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the current job, if any, and runs `job` instead.
+    pub fn submit(
+        &mut self,
+        job: impl FnOnce(&TaskContext) -> Result<T, Cancelled> + Send + 'static,
+    ) {
+        self.cancel();
+        self.current = Some(TaskHandle::spawn(job));
+    }
+}
+
+impl<T> LatestOnly<T> {
+    /// Cancels the current job, if any.
+    pub fn cancel(&mut self) {
+        if let Some(task) = self.current.take() {
+            task.cancel();
+        }
+    }
+
+    /// Returns true while the latest job has not delivered its result.
+    pub fn is_running(&self) -> bool {
+        self.current.is_some()
+    }
+
+    /// Returns the progress of the latest job, or `None` if there is none.
+    pub fn progress(&self) -> Option<f64> {
+        self.current.as_ref().map(TaskHandle::progress)
+    }
+
+    /// Returns the result of the latest job once it is done, see [`TaskHandle::try_take`].
+    pub fn try_take(&mut self) -> Option<T> {
+        let task = self.current.as_mut()?;
+        let result = task.try_take();
+        if task.taken {
+            self.current = None;
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::Sender;
+    use std::time::{Duration, Instant};
+
+    fn wait_until(mut condition: impl FnMut() -> bool) {
+        let start = Instant::now();
+        while !condition() {
+            assert!(start.elapsed() < Duration::from_secs(10), "timed out");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// A job that reports progress in steps and stops at a checkpoint once cancelled.
+    fn stepping_job(
+        steps: Receiver<()>,
+        checkpoints: Sender<u64>,
+    ) -> impl FnOnce(&TaskContext) -> Result<u64, Cancelled> + Send + 'static {
+        move |ctx| {
+            for done in 0..10 {
+                ctx.checkpoint()?;
+                let _ = checkpoints.send(done);
+                steps.recv().map_err(|_| Cancelled)?;
+                ctx.report(done + 1, 10);
+            }
+            Ok(10)
+        }
+    }
+
+    #[test]
+    fn test_cancel_mid_run() {
+        let (step, steps) = channel();
+        let (checkpoint_sender, checkpoints) = channel();
+        let mut task = TaskHandle::spawn(stepping_job(steps, checkpoint_sender));
+        for done in 0..3 {
+            assert_eq!(checkpoints.recv().unwrap(), done);
+            step.send(()).unwrap();
+        }
+        // the job waits for the fourth step
+        assert_eq!(checkpoints.recv().unwrap(), 3);
+        assert_eq!(task.progress(), 0.3);
+        assert_eq!(task.try_take(), None);
+        assert!(!task.is_finished());
+
+        task.cancel();
+        step.send(()).unwrap();
+        wait_until(|| task.is_finished());
+        // the job stopped at the next checkpoint instead of running to the end
+        assert_eq!(checkpoints.iter().next(), None);
+        assert_eq!(task.progress(), 0.4);
+        assert_eq!(task.try_take(), None);
+    }
+
+    #[test]
+    fn test_cancelled_result_is_dropped() {
+        let mut task = TaskHandle::spawn(|_| Ok(5));
+        wait_until(|| task.is_finished());
+        task.cancel();
+        assert_eq!(task.try_take(), None);
+
+        let mut task = TaskHandle::spawn(|_| Ok(5));
+        wait_until(|| task.is_finished());
+        assert_eq!(task.try_take(), Some(5));
+        assert_eq!(task.try_take(), None);
+    }
+
+    #[test]
+    fn test_progress_is_monotonic() {
+        let progress = ProgressReporter::new();
+        progress.report(5, 10);
+        assert_eq!(progress.fraction(), 0.5);
+        // the total grew, but the bar stays
+        progress.report(6, 20);
+        assert_eq!(progress.fraction(), 0.5);
+        progress.report_fraction(f64::NAN);
+        progress.report_fraction(2.0);
+        assert_eq!(progress.fraction(), 1.0);
+        progress.report(0, 0);
+        assert_eq!(progress.fraction(), 1.0);
+    }
+
+    #[test]
+    fn test_latest_only_cancels_superseded() {
+        let mut latest = LatestOnly::new();
+        let (step, steps) = channel();
+        let (checkpoint_sender, checkpoints) = channel();
+        latest.submit(stepping_job(steps, checkpoint_sender));
+        assert_eq!(checkpoints.recv().unwrap(), 0);
+
+        latest.submit(|ctx| {
+            ctx.report(1, 1);
+            Ok(42)
+        });
+        // the first job is cancelled, not just ignored
+        step.send(()).unwrap();
+        assert_eq!(checkpoints.iter().next(), None);
+
+        let mut result = None;
+        wait_until(|| {
+            result = latest.try_take();
+            result.is_some()
+        });
+        assert_eq!(result, Some(42));
+        assert!(!latest.is_running());
+        assert_eq!(latest.progress(), None);
+    }
+}
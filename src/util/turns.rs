@@ -0,0 +1,315 @@
+//! Turn-based scheduling utilities.
+//!
+//! **teng**'s game loop is real-time, but many games (roguelikes, tactics games) are turn-based:
+//! the world only advances when the player acts, while animations still play every frame.
+//! This module provides the building blocks for such games:
+//!
+//! * [`TurnScheduler`]: an energy-based scheduler that decides which actor acts next.
+//!   Every actor gains energy according to its speed, and acts once it has enough.
+//!   Faster actors therefore act more often, and actions may cost different amounts of energy.
+//! * [`ActionQueue`]: a queue of actions that are resolved in order. An action may report that
+//!   it is busy until some point in time (e.g. while its animation plays), which blocks the
+//!   resolution of further actions until then.
+//!
+//! See [`TurnSystemComponent`] for how to integrate these with the component architecture.
+//!
+//! # Example
+//! ```
+//! use teng::util::turns::TurnScheduler;
+//!
+//! let mut scheduler = TurnScheduler::new();
+//! let player = scheduler.add_actor(10);
+//! let snail = scheduler.add_actor(5);
+//!
+//! let mut order = vec![];
+//! for _ in 0..6 {
+//!     let actor = scheduler.next_actor().unwrap();
+//!     order.push(actor);
+//!     scheduler.end_turn(actor, TurnScheduler::DEFAULT_ACTION_COST);
+//! }
+//! // the player is twice as fast as the snail
+//! assert_eq!(order, vec![player, player, snail, player, player, snail]);
+//! ```
+//!
+//! [`TurnSystemComponent`]: crate::components::turns::TurnSystemComponent
+
+use std::collections::VecDeque;
+
+/// Identifies an actor in a [`TurnScheduler`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ActorId(u32);
+
+#[derive(Debug, Clone)]
+struct Actor {
+    id: ActorId,
+    speed: u32,
+    energy: i64,
+}
+
+/// An energy-based turn scheduler.
+///
+/// Every tick, each actor gains energy equal to its speed. Once an actor's energy reaches
+/// [`TurnScheduler::DEFAULT_ACTION_COST`], it may act. Acting costs energy, which is paid in
+/// [`TurnScheduler::end_turn`].
+///
+/// Scheduling is deterministic: if multiple actors are ready at the same time, the one with
+/// the most energy acts first, with ties broken by the order in which actors were added.
+#[derive(Debug, Clone, Default)]
+pub struct TurnScheduler {
+    actors: Vec<Actor>,
+    next_id: u32,
+    current: Option<ActorId>,
+}
+
+impl TurnScheduler {
+    /// The energy an actor needs to act, and the cost of a regular action.
+    pub const DEFAULT_ACTION_COST: u32 = 100;
+
+    /// Creates an empty scheduler.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an actor with the given speed and returns its id.
+    ///
+    /// The actor starts with no energy.
+    pub fn add_actor(&mut self, speed: u32) -> ActorId {
+        let id = ActorId(self.next_id);
+        self.next_id += 1;
+        self.actors.push(Actor {
+            id,
+            speed,
+            energy: 0,
+        });
+        id
+    }
+
+    /// Removes an actor, e.g. because it died.
+    pub fn remove_actor(&mut self, actor: ActorId) {
+        self.actors.retain(|a| a.id != actor);
+        if self.current == Some(actor) {
+            self.current = None;
+        }
+    }
+
+    /// Changes the speed of an actor.
+    pub fn set_speed(&mut self, actor: ActorId, speed: u32) {
+        if let Some(a) = self.actors.iter_mut().find(|a| a.id == actor) {
+            a.speed = speed;
+        }
+    }
+
+    /// Returns the actor whose turn it currently is, if [`TurnScheduler::next_actor`] has been
+    /// called and the turn has not been ended yet.
+    pub fn current_actor(&self) -> Option<ActorId> {
+        self.current
+    }
+
+    /// Returns the actor that acts next, advancing time until some actor is ready.
+    ///
+    /// Calling this repeatedly without [`TurnScheduler::end_turn`] returns the same actor.
+    /// Returns `None` if there are no actors, or no actor can ever act (all speeds are zero).
+    pub fn next_actor(&mut self) -> Option<ActorId> {
+        if let Some(current) = self.current {
+            return Some(current);
+        }
+        if self.actors.iter().all(|a| a.speed == 0) {
+            // only actors that already have enough energy can act
+            self.current = self.ready_actor();
+            return self.current;
+        }
+        loop {
+            if let Some(actor) = self.ready_actor() {
+                self.current = Some(actor);
+                return Some(actor);
+            }
+            for actor in self.actors.iter_mut() {
+                actor.energy += actor.speed as i64;
+            }
+        }
+    }
+
+    fn ready_actor(&self) -> Option<ActorId> {
+        let threshold = Self::DEFAULT_ACTION_COST as i64;
+        // max_by_key returns the last maximum, so iterate in reverse to prefer earlier actors
+        self.actors
+            .iter()
+            .rev()
+            .filter(|a| a.energy >= threshold)
+            .max_by_key(|a| a.energy)
+            .map(|a| a.id)
+    }
+
+    /// Ends the turn of `actor`, which spends `cost` energy.
+    pub fn end_turn(&mut self, actor: ActorId, cost: u32) {
+        if let Some(a) = self.actors.iter_mut().find(|a| a.id == actor) {
+            a.energy -= cost as i64;
+        }
+        if self.current == Some(actor) {
+            self.current = None;
+        }
+    }
+}
+
+/// The result of resolving a single action in an [`ActionQueue`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ActionResult {
+    /// The action finished immediately, the next action can be resolved.
+    Done,
+    /// The action is busy until the given game time (in seconds), e.g. because an animation is
+    /// playing. No further actions are resolved until then.
+    BusyUntil(f64),
+}
+
+/// A queue of actions that are resolved in order, with support for blocking on in-progress
+/// actions.
+#[derive(Debug, Clone)]
+pub struct ActionQueue<A> {
+    queue: VecDeque<(ActorId, A)>,
+    busy_until: Option<f64>,
+}
+
+impl<A> Default for ActionQueue<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A> ActionQueue<A> {
+    /// Creates an empty action queue.
+    pub fn new() -> Self {
+        Self {
+            queue: VecDeque::new(),
+            busy_until: None,
+        }
+    }
+
+    /// Queues an action to be resolved after all previously queued actions.
+    pub fn queue_action(&mut self, actor: ActorId, action: A) {
+        self.queue.push_back((actor, action));
+    }
+
+    /// Returns the number of queued actions.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Returns true if there are no queued actions.
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Returns true if a previously resolved action is still busy at time `now`.
+    pub fn is_busy(&self, now: f64) -> bool {
+        self.busy_until.is_some_and(|t| now < t)
+    }
+
+    /// Returns true if there is nothing left to resolve and no action is busy at time `now`.
+    pub fn is_idle(&self, now: f64) -> bool {
+        self.is_empty() && !self.is_busy(now)
+    }
+
+    /// Resolves queued actions in order by calling `resolve` on each, until the queue is empty
+    /// or an action reports that it is busy past `now`.
+    ///
+    /// Returns the number of resolved actions.
+    pub fn resolve(
+        &mut self,
+        now: f64,
+        mut resolve: impl FnMut(ActorId, A) -> ActionResult,
+    ) -> usize {
+        let mut resolved = 0;
+        while !self.is_busy(now) {
+            let Some((actor, action)) = self.queue.pop_front() else {
+                break;
+            };
+            resolved += 1;
+            self.busy_until = match resolve(actor, action) {
+                ActionResult::Done => None,
+                ActionResult::BusyUntil(t) => Some(t),
+            };
+        }
+        resolved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_energy_ordering() {
+        let mut scheduler = TurnScheduler::new();
+        let fast = scheduler.add_actor(20);
+        let normal = scheduler.add_actor(10);
+        let slow = scheduler.add_actor(5);
+
+        let mut counts = [0; 3];
+        let mut order = vec![];
+        for _ in 0..35 {
+            let actor = scheduler.next_actor().unwrap();
+            // same actor until the turn is ended
+            assert_eq!(scheduler.next_actor(), Some(actor));
+            order.push(actor);
+            counts[[fast, normal, slow]
+                .iter()
+                .position(|a| *a == actor)
+                .unwrap()] += 1;
+            scheduler.end_turn(actor, TurnScheduler::DEFAULT_ACTION_COST);
+        }
+        assert_eq!(counts, [20, 10, 5]);
+        // ties between ready actors are broken by insertion order
+        assert_eq!(&order[..3], &[fast, fast, normal]);
+
+        // an expensive action delays the next turn
+        let mut scheduler = TurnScheduler::new();
+        let a = scheduler.add_actor(10);
+        let b = scheduler.add_actor(10);
+        assert_eq!(scheduler.next_actor(), Some(a));
+        scheduler.end_turn(a, 300);
+        assert_eq!(scheduler.next_actor(), Some(b));
+        scheduler.end_turn(b, 100);
+        assert_eq!(scheduler.next_actor(), Some(b));
+        scheduler.end_turn(b, 100);
+        assert_eq!(scheduler.next_actor(), Some(b));
+        scheduler.end_turn(b, 100);
+        assert_eq!(scheduler.next_actor(), Some(a));
+
+        // actors that cannot gain energy still act while they have enough
+        let mut scheduler = TurnScheduler::new();
+        let a = scheduler.add_actor(10);
+        assert_eq!(scheduler.next_actor(), Some(a));
+        scheduler.end_turn(a, 0);
+        scheduler.set_speed(a, 0);
+        assert_eq!(scheduler.next_actor(), Some(a));
+        assert_eq!(scheduler.current_actor(), Some(a));
+        scheduler.end_turn(a, TurnScheduler::DEFAULT_ACTION_COST);
+        assert_eq!(scheduler.next_actor(), None);
+    }
+
+    #[test]
+    fn test_busy_wait_resolution() {
+        let mut scheduler = TurnScheduler::new();
+        let actor = scheduler.add_actor(10);
+        let mut queue = ActionQueue::new();
+        queue.queue_action(actor, "instant");
+        queue.queue_action(actor, "animated");
+        queue.queue_action(actor, "after");
+
+        let mut resolved = vec![];
+        let mut resolve = |_actor, action| {
+            resolved.push(action);
+            if action == "animated" {
+                ActionResult::BusyUntil(1.0)
+            } else {
+                ActionResult::Done
+            }
+        };
+        assert_eq!(queue.resolve(0.0, &mut resolve), 2);
+        assert!(queue.is_busy(0.5));
+        assert_eq!(queue.resolve(0.5, &mut resolve), 0);
+        assert_eq!(queue.resolve(1.0, &mut resolve), 1);
+        assert!(queue.is_idle(1.0));
+        assert_eq!(resolved, vec!["instant", "animated", "after"]);
+    }
+}
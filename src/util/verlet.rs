@@ -0,0 +1,370 @@
+//! Verlet integration and constraint solving for ropes, chains and other soft bodies.
+//!
+//! A [`ParticleSystem`] stores point masses that are integrated with position-based Verlet
+//! integration. Particles are connected by [`DistanceConstraint`]s and can be pinned in place.
+//! Every [`ParticleSystem::step`] integrates all particles once and then relaxes the constraints
+//! a configurable number of times, which is enough to simulate ropes, chains, cloth strips or
+//! grappling hooks.
+//!
+//! For a fixed `dt`, the simulation is deterministic: particles and constraints are always
+//! processed in insertion order.
+//!
+//! # Example
+//! ```
+//! use teng::util::verlet::ParticleSystem;
+//!
+//! // A rope hanging from (0, 0) with 10 segments, the first particle is pinned.
+//! let mut rope = ParticleSystem::rope((0.0, 0.0), (10.0, 0.0), 10);
+//! for _ in 0..100 {
+//!     rope.step(1.0 / 60.0);
+//! }
+//! // the pinned particle did not move
+//! assert_eq!(rope.particles()[0].pos, (0.0, 0.0));
+//! for (start, end) in rope.segments() {
+//!     // draw a line from start to end
+//! }
+//! ```
+
+/// A single point mass.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Particle {
+    /// The current position.
+    pub pos: (f64, f64),
+    /// The position in the previous step. The difference to `pos` is the particle's velocity.
+    pub prev_pos: (f64, f64),
+    /// Pinned particles are neither integrated nor moved by constraints.
+    pub pinned: bool,
+}
+
+impl Particle {
+    /// Creates a new resting, unpinned particle at the given position.
+    pub fn new(pos: (f64, f64)) -> Self {
+        Self {
+            pos,
+            prev_pos: pos,
+            pinned: false,
+        }
+    }
+}
+
+/// A constraint that tries to keep two particles at a fixed distance from each other.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DistanceConstraint {
+    /// Index of the first particle.
+    pub a: usize,
+    /// Index of the second particle.
+    pub b: usize,
+    /// The distance the constraint tries to maintain.
+    pub rest_length: f64,
+    /// How much of the error is corrected per iteration, in `0.0..=1.0`.
+    /// A stiffness of `1.0` results in a rigid link, lower values result in a springy link.
+    pub stiffness: f64,
+}
+
+/// A system of particles connected by distance constraints, integrated with Verlet integration.
+#[derive(Debug, Clone)]
+pub struct ParticleSystem {
+    particles: Vec<Particle>,
+    constraints: Vec<DistanceConstraint>,
+    /// The acceleration applied to every unpinned particle, in units per second squared.
+    pub gravity: (f64, f64),
+    /// Velocity retained per step, in `0.0..=1.0`. `1.0` means no damping.
+    pub damping: f64,
+    iterations: usize,
+}
+
+impl Default for ParticleSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ParticleSystem {
+    /// Creates an empty particle system with downwards gravity, slight damping and 8 solver
+    /// iterations per step.
+    pub fn new() -> Self {
+        Self {
+            particles: Vec::new(),
+            constraints: Vec::new(),
+            gravity: (0.0, 40.0),
+            damping: 0.99,
+            iterations: 8,
+        }
+    }
+
+    /// Creates a rope from `from` to `to` consisting of `segments` rigid segments.
+    ///
+    /// The first particle (at `from`) is pinned.
+    pub fn rope(from: (f64, f64), to: (f64, f64), segments: usize) -> Self {
+        let segments = segments.max(1);
+        let points = (0..=segments).map(|i| {
+            let t = i as f64 / segments as f64;
+            (from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t)
+        });
+        Self::chain(points)
+    }
+
+    /// Creates a chain of particles along the given points, each connected to the next with a
+    /// rigid distance constraint of their initial distance.
+    ///
+    /// The first particle is pinned.
+    pub fn chain(points: impl IntoIterator<Item = (f64, f64)>) -> Self {
+        let mut system = Self::new();
+        for point in points {
+            system.push_to_chain(point, 1.0);
+        }
+        if !system.particles.is_empty() {
+            system.pin(0);
+        }
+        system
+    }
+
+    /// Sets the number of constraint relaxation iterations per step.
+    ///
+    /// More iterations result in stiffer, more accurate constraints at the cost of performance.
+    pub fn with_iterations(mut self, iterations: usize) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    /// Sets the gravity of the system.
+    pub fn with_gravity(mut self, gravity: (f64, f64)) -> Self {
+        self.gravity = gravity;
+        self
+    }
+
+    /// Returns the number of constraint relaxation iterations per step.
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    /// Sets the number of constraint relaxation iterations per step.
+    pub fn set_iterations(&mut self, iterations: usize) {
+        self.iterations = iterations;
+    }
+
+    /// Returns all particles in insertion order.
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    /// Returns all particles mutably in insertion order.
+    pub fn particles_mut(&mut self) -> &mut [Particle] {
+        &mut self.particles
+    }
+
+    /// Returns all constraints in insertion order.
+    pub fn constraints(&self) -> &[DistanceConstraint] {
+        &self.constraints
+    }
+
+    /// Removes all particles and constraints.
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.constraints.clear();
+    }
+
+    /// Adds a resting particle at the given position and returns its index.
+    pub fn add_particle(&mut self, pos: (f64, f64)) -> usize {
+        self.particles.push(Particle::new(pos));
+        self.particles.len() - 1
+    }
+
+    /// Adds a particle at `pos` and connects it to the last particle (if any) with a distance
+    /// constraint of their current distance. Returns the new particle's index.
+    pub fn push_to_chain(&mut self, pos: (f64, f64), stiffness: f64) -> usize {
+        let idx = self.add_particle(pos);
+        if idx > 0 {
+            self.add_distance_constraint(idx - 1, idx, stiffness);
+        }
+        idx
+    }
+
+    /// Connects particles `a` and `b` with a distance constraint, using their current distance
+    /// as the rest length.
+    pub fn add_distance_constraint(&mut self, a: usize, b: usize, stiffness: f64) {
+        let rest_length = distance(self.particles[a].pos, self.particles[b].pos);
+        self.add_distance_constraint_with_length(a, b, rest_length, stiffness);
+    }
+
+    /// Connects particles `a` and `b` with a distance constraint of the given rest length.
+    pub fn add_distance_constraint_with_length(
+        &mut self,
+        a: usize,
+        b: usize,
+        rest_length: f64,
+        stiffness: f64,
+    ) {
+        self.constraints.push(DistanceConstraint {
+            a,
+            b,
+            rest_length,
+            stiffness: stiffness.clamp(0.0, 1.0),
+        });
+    }
+
+    /// Pins the particle in place.
+    pub fn pin(&mut self, idx: usize) {
+        let particle = &mut self.particles[idx];
+        particle.pinned = true;
+        particle.prev_pos = particle.pos;
+    }
+
+    /// Pins the particle at the given position, teleporting it there.
+    pub fn pin_at(&mut self, idx: usize, pos: (f64, f64)) {
+        let particle = &mut self.particles[idx];
+        particle.pinned = true;
+        particle.pos = pos;
+        particle.prev_pos = pos;
+    }
+
+    /// Unpins the particle. It will start at rest.
+    pub fn unpin(&mut self, idx: usize) {
+        let particle = &mut self.particles[idx];
+        particle.pinned = false;
+        particle.prev_pos = particle.pos;
+    }
+
+    /// Advances the simulation by `dt` seconds without collisions.
+    pub fn step(&mut self, dt: f64) {
+        self.step_with_collision(dt, |_, _| false);
+    }
+
+    /// Advances the simulation by `dt` seconds. Particles that end up in a cell for which
+    /// `is_solid(x, y)` returns true are pushed back out of it.
+    ///
+    /// Cells are identified by flooring the particle's position.
+    pub fn step_with_collision(&mut self, dt: f64, is_solid: impl Fn(i64, i64) -> bool) {
+        self.integrate(dt);
+        for _ in 0..self.iterations {
+            self.relax_constraints();
+            self.collide(&is_solid);
+        }
+    }
+
+    fn integrate(&mut self, dt: f64) {
+        let (ax, ay) = (self.gravity.0 * dt * dt, self.gravity.1 * dt * dt);
+        for particle in self.particles.iter_mut().filter(|p| !p.pinned) {
+            let vx = (particle.pos.0 - particle.prev_pos.0) * self.damping;
+            let vy = (particle.pos.1 - particle.prev_pos.1) * self.damping;
+            particle.prev_pos = particle.pos;
+            particle.pos = (particle.pos.0 + vx + ax, particle.pos.1 + vy + ay);
+        }
+    }
+
+    fn relax_constraints(&mut self) {
+        for constraint in &self.constraints {
+            let a = self.particles[constraint.a];
+            let b = self.particles[constraint.b];
+            // split the correction between both particles according to which ones may move
+            let (weight_a, weight_b) = match (a.pinned, b.pinned) {
+                (true, true) => continue,
+                (true, false) => (0.0, 1.0),
+                (false, true) => (1.0, 0.0),
+                (false, false) => (0.5, 0.5),
+            };
+            let dx = b.pos.0 - a.pos.0;
+            let dy = b.pos.1 - a.pos.1;
+            let dist = (dx * dx + dy * dy).sqrt().max(0.0001);
+            let error = (dist - constraint.rest_length) / dist * constraint.stiffness;
+            let (cx, cy) = (dx * error, dy * error);
+
+            let a = &mut self.particles[constraint.a];
+            a.pos = (a.pos.0 + cx * weight_a, a.pos.1 + cy * weight_a);
+            let b = &mut self.particles[constraint.b];
+            b.pos = (b.pos.0 - cx * weight_b, b.pos.1 - cy * weight_b);
+        }
+    }
+
+    fn collide(&mut self, is_solid: &impl Fn(i64, i64) -> bool) {
+        let cell = |(x, y): (f64, f64)| (x.floor() as i64, y.floor() as i64);
+        for particle in self.particles.iter_mut().filter(|p| !p.pinned) {
+            let (x, y) = cell(particle.pos);
+            if !is_solid(x, y) {
+                continue;
+            }
+            // try to only revert a single axis first, so particles can slide along surfaces
+            let (prev_x, prev_y) = cell(particle.prev_pos);
+            if !is_solid(prev_x, y) {
+                particle.pos.0 = particle.prev_pos.0;
+            } else if !is_solid(x, prev_y) {
+                particle.pos.1 = particle.prev_pos.1;
+            } else {
+                particle.pos = particle.prev_pos;
+            }
+        }
+    }
+
+    /// Returns the largest absolute difference between a constraint's current length and its
+    /// rest length.
+    pub fn max_constraint_error(&self) -> f64 {
+        self.constraints
+            .iter()
+            .map(|c| {
+                let dist = distance(self.particles[c.a].pos, self.particles[c.b].pos);
+                (dist - c.rest_length).abs()
+            })
+            .fold(0.0, f64::max)
+    }
+
+    /// Returns an iterator over the `(start, end)` positions of every constraint, suitable for
+    /// drawing lines with e.g. [`for_coord_in_line`](crate::util::for_coord_in_line).
+    pub fn segments(&self) -> impl Iterator<Item = ((f64, f64), (f64, f64))> + '_ {
+        self.constraints
+            .iter()
+            .map(|c| (self.particles[c.a].pos, self.particles[c.b].pos))
+    }
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constraints_converge() {
+        // a slack rope with both ends pinned must relax to its rest lengths
+        let mut rope = ParticleSystem::rope((0.0, 0.0), (10.0, 0.0), 10)
+            .with_gravity((0.0, 0.0))
+            .with_iterations(50);
+        let last = rope.particles().len() - 1;
+        rope.pin_at(last, (8.0, 0.0));
+        for particle in rope.particles_mut()[1..last].iter_mut() {
+            particle.pos.1 += 3.0;
+            particle.prev_pos = particle.pos;
+        }
+        assert!(rope.max_constraint_error() > 1.0);
+        for _ in 0..20 {
+            rope.step(1.0 / 60.0);
+        }
+        assert!(rope.max_constraint_error() < 0.01);
+    }
+
+    #[test]
+    fn test_pins_are_immobile() {
+        let mut rope = ParticleSystem::rope((5.0, 5.0), (15.0, 5.0), 5);
+        rope.pin_at(5, (20.0, 5.0));
+        for _ in 0..200 {
+            rope.step_with_collision(1.0 / 60.0, |_, y| y >= 8);
+        }
+        assert_eq!(rope.particles()[0].pos, (5.0, 5.0));
+        assert_eq!(rope.particles()[5].pos, (20.0, 5.0));
+        // no particle ended up in the floor
+        assert!(rope.particles().iter().all(|p| p.pos.1 < 8.0));
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let run = || {
+            let mut rope = ParticleSystem::rope((0.0, 0.0), (10.0, 0.0), 10);
+            for _ in 0..100 {
+                rope.step(1.0 / 60.0);
+            }
+            rope.particles().to_vec()
+        };
+        assert_eq!(run(), run());
+    }
+}
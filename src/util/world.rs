@@ -0,0 +1,490 @@
+//! An unbounded grid world with undoable edits, a content hash and region snapshots.
+//!
+//! A [`World`] stores its cells in a [`PlanarVec`] that grows in chunks of [`World::CHUNK_SIZE`]
+//! as cells are written. All writes go through an [`EditSession`] from [`World::edit`], which
+//! records the old and new value of every changed cell. When the session ends, its changes
+//! become one undo step:
+//! ```
+//! use teng::util::world::World;
+//!
+//! let mut world = World::new(0u8);
+//! let mut edit = world.edit();
+//! edit.set(-3, 4, 1);
+//! edit.set(5, 5, 2);
+//! edit.commit();
+//! assert_eq!(*world.get(-3, 4), 1);
+//!
+//! world.undo();
+//! assert_eq!(*world.get(-3, 4), 0);
+//! assert_eq!(*world.get(5, 5), 0);
+//! ```
+//!
+//! [`World::content_hash`] is updated with every write. It only depends on the cells that are not
+//! the default value, not on the order of the edits or on how far the storage has grown, and it is
+//! stable across runs and platforms. Use it to check whether two worlds are equal, e.g. to
+//! validate a replay, or whether a world changed since it was saved.
+//!
+//! [`World::snapshot_region`] copies the cells of a region, and [`World::restore_region`] writes
+//! them back, e.g. to rewind or to reset a demo. A snapshot only stores the cells of its region.
+
+use crate::util::planarvec::{Bounds, PlanarVec};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// A change of a single cell.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CellChange<T> {
+    pub x: i64,
+    pub y: i64,
+    pub old: T,
+    pub new: T,
+}
+
+/// An unbounded grid world.
+#[derive(Clone, Debug)]
+pub struct World<T> {
+    cells: PlanarVec<T>,
+    default: T,
+    hash: u64,
+    undo: Vec<Vec<CellChange<T>>>,
+    redo: Vec<Vec<CellChange<T>>>,
+}
+
+impl<T: Clone + PartialEq + Hash> World<T> {
+    /// The storage grows to multiples of this size, so that writing cell by cell does not
+    /// reallocate every time.
+    pub const CHUNK_SIZE: i64 = 16;
+
+    /// Creates an empty world in which every cell is `default`.
+    pub fn new(default: T) -> Self {
+        Self {
+            cells: PlanarVec::default(),
+            default,
+            hash: 0,
+            undo: vec![],
+            redo: vec![],
+        }
+    }
+
+    pub fn default_value(&self) -> &T {
+        &self.default
+    }
+
+    /// Returns the cell at `(x, y)`.
+    pub fn get(&self, x: i64, y: i64) -> &T {
+        self.cells.get(x, y).unwrap_or(&self.default)
+    }
+
+    /// Returns the storage, e.g. for the algorithms of [`grid`](crate::util::grid). Cells outside
+    /// of its bounds are the default value.
+    pub fn cells(&self) -> &PlanarVec<T> {
+        &self.cells
+    }
+
+    /// Returns the hash of all cells that are not the default value.
+    pub fn content_hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Starts an edit session. Its changes become one undo step when it is committed or dropped.
+    pub fn edit(&mut self) -> EditSession<'_, T> {
+        EditSession {
+            world: self,
+            changes: vec![],
+            changed: HashMap::new(),
+        }
+    }
+
+    /// Reverts the most recent edit session. Returns false if there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let Some(changes) = self.undo.pop() else {
+            return false;
+        };
+        for change in changes.iter().rev() {
+            self.write(change.x, change.y, change.old.clone());
+        }
+        self.redo.push(changes);
+        true
+    }
+
+    /// Applies the most recently undone edit session again. Returns false if there is nothing to
+    /// redo.
+    pub fn redo(&mut self) -> bool {
+        let Some(changes) = self.redo.pop() else {
+            return false;
+        };
+        for change in &changes {
+            self.write(change.x, change.y, change.new.clone());
+        }
+        self.undo.push(changes);
+        true
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Forgets all undo and redo steps, e.g. when a building phase ends.
+    pub fn clear_history(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    /// Copies the cells in `bounds`.
+    pub fn snapshot_region(&self, bounds: Bounds) -> RegionSnapshot<T> {
+        let cells = if bounds.is_empty() {
+            vec![]
+        } else {
+            (bounds.min_x..=bounds.max_x)
+                .flat_map(|x| (bounds.min_y..=bounds.max_y).map(move |y| (x, y)))
+                .map(|(x, y)| self.get(x, y).clone())
+                .collect()
+        };
+        RegionSnapshot { bounds, cells }
+    }
+
+    /// Writes the cells of a snapshot back into the world. This is not an undo step, and the
+    /// history is left as is.
+    pub fn restore_region(&mut self, snapshot: &RegionSnapshot<T>) {
+        for (x, y, value) in snapshot.iter() {
+            self.write(x, y, value.clone());
+        }
+    }
+
+    /// Sets a cell, growing the storage if needed, and returns the old value.
+    fn write(&mut self, x: i64, y: i64, value: T) -> T {
+        if !self.cells.bounds().contains(x, y) {
+            if value == self.default {
+                return value;
+            }
+            let chunk = |v: i64| v.div_euclid(Self::CHUNK_SIZE) * Self::CHUNK_SIZE;
+            let bounds = Bounds {
+                min_x: chunk(x),
+                max_x: chunk(x) + Self::CHUNK_SIZE - 1,
+                min_y: chunk(y),
+                max_y: chunk(y) + Self::CHUNK_SIZE - 1,
+            };
+            if self.cells.bounds().is_empty() {
+                self.cells = PlanarVec::new(bounds, self.default.clone());
+            } else {
+                self.cells.expand(bounds, self.default.clone());
+            }
+        }
+        let new_hash = self.cell_hash(x, y, &value);
+        let old = std::mem::replace(&mut self.cells[(x, y)], value);
+        self.hash = self
+            .hash
+            .wrapping_sub(self.cell_hash(x, y, &old))
+            .wrapping_add(new_hash);
+        old
+    }
+
+    fn cell_hash(&self, x: i64, y: i64, value: &T) -> u64 {
+        if *value == self.default {
+            return 0;
+        }
+        let mut hasher = StableHasher::default();
+        hasher.write_i64(x);
+        hasher.write_i64(y);
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// The changes to a [`World`] that form one undo step, see [`World::edit`].
+pub struct EditSession<'a, T> {
+    world: &'a mut World<T>,
+    changes: Vec<CellChange<T>>,
+    /// The index of every changed cell in `changes`.
+    changed: HashMap<(i64, i64), usize>,
+}
+
+impl<T: Clone + PartialEq + Hash> EditSession<'_, T> {
+    /// Returns the cell at `(x, y)`, including the changes of this session.
+    pub fn get(&self, x: i64, y: i64) -> &T {
+        self.world.get(x, y)
+    }
+
+    /// Sets the cell at `(x, y)`. Setting a cell to its current value does nothing.
+    pub fn set(&mut self, x: i64, y: i64, value: T) {
+        if *self.world.get(x, y) == value {
+            return;
+        }
+        let old = self.world.write(x, y, value.clone());
+        match self.changed.get(&(x, y)) {
+            // the first old value is the one to undo to
+            Some(&index) => self.changes[index].new = value,
+            None => {
+                self.changed.insert((x, y), self.changes.len());
+                self.changes.push(CellChange {
+                    x,
+                    y,
+                    old,
+                    new: value,
+                });
+            }
+        }
+    }
+
+    /// Returns the changes so far, in the order in which the cells were first changed.
+    pub fn changes(&self) -> &[CellChange<T>] {
+        &self.changes
+    }
+
+    /// Returns the content hash of the world, including the changes of this session.
+    pub fn content_hash(&self) -> u64 {
+        self.world.content_hash()
+    }
+
+    /// Ends the session and records its changes as one undo step, like dropping it.
+    pub fn commit(self) {}
+
+    /// Reverts the changes of this session, without an undo step.
+    pub fn cancel(mut self) {
+        for change in std::mem::take(&mut self.changes).into_iter().rev() {
+            self.world.write(change.x, change.y, change.old);
+        }
+    }
+}
+
+impl<T> Drop for EditSession<'_, T> {
+    fn drop(&mut self) {
+        if self.changes.is_empty() {
+            return;
+        }
+        self.world.undo.push(std::mem::take(&mut self.changes));
+        self.world.redo.clear();
+    }
+}
+
+/// A copy of the cells of a region of a [`World`], see [`World::snapshot_region`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegionSnapshot<T> {
+    bounds: Bounds,
+    /// Column by column.
+    cells: Vec<T>,
+}
+
+impl<T> RegionSnapshot<T> {
+    pub fn bounds(&self) -> Bounds {
+        self.bounds
+    }
+
+    /// Returns the cell at `(x, y)`, or `None` if it is outside of the region.
+    pub fn get(&self, x: i64, y: i64) -> Option<&T> {
+        if !self.bounds.contains(x, y) {
+            return None;
+        }
+        let height = self.bounds.max_y - self.bounds.min_y + 1;
+        let index = (x - self.bounds.min_x) * height + (y - self.bounds.min_y);
+        self.cells.get(index as usize)
+    }
+
+    /// Returns an iterator over all cells and their coordinates, column by column.
+    pub fn iter(&self) -> impl Iterator<Item = (i64, i64, &T)> {
+        let bounds = self.bounds;
+        let height = (bounds.max_y - bounds.min_y + 1).max(1);
+        self.cells.iter().enumerate().map(move |(i, value)| {
+            let i = i as i64;
+            (bounds.min_x + i / height, bounds.min_y + i % height, value)
+        })
+    }
+}
+
+/// FNV-1a with little-endian integers, so that hashes are the same on every platform and with
+/// every Rust version, unlike `DefaultHasher`.
+struct StableHasher(u64);
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_i16(&mut self, i: i16) {
+        self.write_u16(i as u16);
+    }
+
+    fn write_i32(&mut self, i: i32) {
+        self.write_u32(i as u32);
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_i128(&mut self, i: i128) {
+        self.write_u128(i as u128);
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_u64(i as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(world: &mut World<u8>) {
+        let mut edit = world.edit();
+        for (i, (x, y)) in [(-20, -3), (0, 0), (7, 40), (-1, -1)]
+            .into_iter()
+            .enumerate()
+        {
+            edit.set(x, y, i as u8 + 1);
+        }
+    }
+
+    #[test]
+    fn test_group_undo() {
+        let mut world = World::new(0u8);
+        build(&mut world);
+        let built = world.content_hash();
+        let mut edit = world.edit();
+        edit.set(0, 0, 9);
+        edit.set(0, 0, 8);
+        edit.set(-30, 5, 7);
+        // unchanged cells are not recorded
+        edit.set(1, 1, 0);
+        assert_eq!(
+            edit.changes(),
+            [
+                CellChange {
+                    x: 0,
+                    y: 0,
+                    old: 2,
+                    new: 8
+                },
+                CellChange {
+                    x: -30,
+                    y: 5,
+                    old: 0,
+                    new: 7
+                },
+            ]
+        );
+        edit.commit();
+        assert_eq!(*world.get(-30, 5), 7);
+        assert_ne!(world.content_hash(), built);
+
+        assert!(world.undo());
+        assert_eq!(*world.get(0, 0), 2);
+        assert_eq!(*world.get(-30, 5), 0);
+        assert_eq!(world.content_hash(), built);
+        assert!(world.redo());
+        assert_eq!(*world.get(0, 0), 8);
+        assert!(world.undo());
+        assert!(world.undo());
+        assert_eq!(world.content_hash(), 0);
+        assert!(!world.undo());
+
+        // a cancelled session leaves no trace
+        let mut edit = world.edit();
+        edit.set(3, 3, 3);
+        edit.cancel();
+        assert_eq!(*world.get(3, 3), 0);
+        assert_eq!(world.content_hash(), 0);
+        assert!(!world.can_undo());
+        // a new edit clears the redo steps
+        assert!(world.can_redo());
+        world.edit().set(4, 4, 4);
+        assert!(!world.can_redo());
+    }
+
+    #[test]
+    fn test_hash_stability() {
+        let mut a = World::new(0u8);
+        let mut b = World::new(0u8);
+        build(&mut a);
+        build(&mut b);
+        assert_eq!(a.content_hash(), b.content_hash());
+        // pinned, so that saves and replays from earlier versions stay valid
+        assert_eq!(a.content_hash(), 0xec1a_b8dd_2a33_b712);
+
+        // independent of the edit order and of the storage size
+        let mut c = World::new(0u8);
+        c.edit().set(1000, 1000, 5);
+        c.edit().set(1000, 1000, 0);
+        let mut edit = c.edit();
+        for (i, (x, y)) in [(-20, -3), (0, 0), (7, 40), (-1, -1)]
+            .into_iter()
+            .enumerate()
+            .rev()
+        {
+            edit.set(x, y, i as u8 + 1);
+        }
+        edit.commit();
+        assert_eq!(c.content_hash(), a.content_hash());
+        c.edit().set(0, 0, 3);
+        assert_ne!(c.content_hash(), a.content_hash());
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let mut world = World::new(0u8);
+        build(&mut world);
+        let bounds = Bounds {
+            min_x: -21,
+            max_x: -1,
+            min_y: -4,
+            max_y: -1,
+        };
+        let snapshot = world.snapshot_region(bounds);
+        assert_eq!(snapshot.iter().count(), 21 * 4);
+        assert_eq!(snapshot.get(-20, -3), Some(&1));
+        assert_eq!(snapshot.get(-1, -1), Some(&4));
+        assert_eq!(snapshot.get(0, 0), None);
+        let hash = world.content_hash();
+
+        let mut edit = world.edit();
+        edit.set(-20, -3, 0);
+        edit.set(-10, -2, 6);
+        edit.set(0, 0, 6);
+        edit.commit();
+        world.restore_region(&snapshot);
+        assert_eq!(*world.get(-20, -3), 1);
+        assert_eq!(*world.get(-10, -2), 0);
+        // outside of the region
+        assert_eq!(*world.get(0, 0), 6);
+        world.edit().set(0, 0, 2);
+        assert_eq!(world.content_hash(), hash);
+        assert_eq!(world.snapshot_region(bounds), snapshot);
+    }
+}